@@ -0,0 +1,159 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use jupiter_core::color::compose::compose;
+use jupiter_core::consts::DEFAULT_LUMINANCE_WEIGHT;
+use jupiter_core::frame::Frame;
+use jupiter_core::io::image_io::{load_image, save_color_image};
+use jupiter_core::pipeline::config::{ChannelRole, CompositionConfig, RegistrationMode};
+
+#[derive(Clone, ValueEnum)]
+pub enum ComposeModeArg {
+    /// Red, green and blue images in their own channels
+    Rgb,
+    /// RGB with the luminance image layered on top
+    Lrgb,
+    /// RGB with the infrared image as luminance
+    IrRgb,
+    /// RGB with the methane image in place of one channel
+    FalseColor,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum ComposeChannelArg {
+    Red,
+    Green,
+    Blue,
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum RegistrationArg {
+    None,
+    Translation,
+    Similarity,
+}
+
+#[derive(Args)]
+pub struct ComposeArgs {
+    /// Stacked image taken through the luminance (clear) filter
+    #[arg(long)]
+    pub lum: Option<PathBuf>,
+
+    /// Stacked image taken through the red filter
+    #[arg(long)]
+    pub red: Option<PathBuf>,
+
+    /// Stacked image taken through the green filter
+    #[arg(long)]
+    pub green: Option<PathBuf>,
+
+    /// Stacked image taken through the blue filter
+    #[arg(long)]
+    pub blue: Option<PathBuf>,
+
+    /// Stacked image taken through an IR-pass filter
+    #[arg(long)]
+    pub ir: Option<PathBuf>,
+
+    /// Stacked image taken through a methane (CH4) filter
+    #[arg(long)]
+    pub ch4: Option<PathBuf>,
+
+    /// How the images are combined
+    #[arg(long, value_enum, default_value = "rgb")]
+    pub mode: ComposeModeArg,
+
+    /// Channel the methane image replaces in false-color mode
+    #[arg(long, value_enum, default_value = "red")]
+    pub ch4_channel: ComposeChannelArg,
+
+    /// How the images are registered to the reference (luminance, else
+    /// green): shift only, or shift, rotation and scale
+    #[arg(long, value_enum, default_value = "translation")]
+    pub register: RegistrationArg,
+
+    /// Weight of the luminance image's lightness (0.0-1.0)
+    #[arg(long, default_value_t = DEFAULT_LUMINANCE_WEIGHT)]
+    pub lum_weight: f32,
+
+    /// Keep each image's levels instead of stretching it to the full range
+    #[arg(long)]
+    pub no_normalize: bool,
+
+    /// Output file path
+    #[arg(short, long, default_value = "composite.tiff")]
+    pub output: PathBuf,
+}
+
+pub fn run(args: &ComposeArgs) -> Result<()> {
+    let mut config = match args.mode {
+        ComposeModeArg::Rgb => CompositionConfig::rgb(),
+        ComposeModeArg::Lrgb => CompositionConfig::lrgb(),
+        ComposeModeArg::IrRgb => CompositionConfig::ir_rgb(),
+        ComposeModeArg::FalseColor => CompositionConfig::false_color(match args.ch4_channel {
+            ComposeChannelArg::Red => ChannelRole::Red,
+            ComposeChannelArg::Green => ChannelRole::Green,
+            ComposeChannelArg::Blue => ChannelRole::Blue,
+        }),
+    };
+    config.registration = match args.register {
+        RegistrationArg::None => RegistrationMode::None,
+        RegistrationArg::Translation => RegistrationMode::Translation,
+        RegistrationArg::Similarity => RegistrationMode::Similarity,
+    };
+    config.luminance_weight = args.lum_weight;
+    config.normalize = !args.no_normalize;
+
+    let images = config
+        .required_roles()
+        .into_iter()
+        .map(|role| {
+            let path = input_path(args, role)
+                .with_context(|| format!("{} mode needs the {role} image", mode_name(args)))?;
+            Ok((role, load_channel(path, role)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    println!(
+        "Composing {} (registration: {})",
+        mode_name(args),
+        config.registration
+    );
+    let color = compose(&images, &config)?;
+    save_color_image(&color, &args.output)?;
+    println!("Saved to {}", args.output.display());
+    Ok(())
+}
+
+/// The input given for `role`, if any.
+fn input_path(args: &ComposeArgs, role: ChannelRole) -> Option<&Path> {
+    match role {
+        ChannelRole::Luminance => args.lum.as_deref(),
+        ChannelRole::Red => args.red.as_deref(),
+        ChannelRole::Green => args.green.as_deref(),
+        ChannelRole::Blue => args.blue.as_deref(),
+        ChannelRole::Infrared => args.ir.as_deref(),
+        ChannelRole::Methane => args.ch4.as_deref(),
+    }
+}
+
+fn load_channel(path: &Path, role: ChannelRole) -> Result<Frame> {
+    let frame = load_image(path).with_context(|| format!("Failed to load {}", path.display()))?;
+    println!(
+        "Loaded {role}: {} ({}x{})",
+        path.display(),
+        frame.width(),
+        frame.height()
+    );
+    Ok(frame)
+}
+
+fn mode_name(args: &ComposeArgs) -> &'static str {
+    match args.mode {
+        ComposeModeArg::Rgb => "RGB",
+        ComposeModeArg::Lrgb => "LRGB",
+        ComposeModeArg::IrRgb => "IR-RGB",
+        ComposeModeArg::FalseColor => "False-color",
+    }
+}
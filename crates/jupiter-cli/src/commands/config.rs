@@ -19,6 +19,7 @@ pub struct ConfigArgs {
 pub fn run(args: &ConfigArgs) -> Result<()> {
     let config = PipelineConfig {
         input: PathBuf::from("input.ser"),
+        extra_inputs: vec![],
         output: PathBuf::from("result.tiff"),
         device: DevicePreference::Auto,
         memory: Default::default(),
@@ -2,11 +2,24 @@ use std::path::PathBuf;
 
 use anyhow::{Context, Result};
 use clap::Args;
-use jupiter_core::filters::gaussian_blur::gaussian_blur;
-use jupiter_core::filters::histogram::{auto_stretch, histogram_stretch};
-use jupiter_core::filters::levels::{brightness_contrast, gamma_correct};
-use jupiter_core::filters::unsharp_mask::unsharp_mask;
-use jupiter_core::io::image_io::{load_image, save_image};
+use jupiter_core::pipeline::config::FilterStep;
+
+use crate::postprocess::{apply_filters, load_input, load_recipe, save_output};
+
+/// Options that `--recipe` replaces.
+const FILTER_OPTIONS: [&str; 11] = [
+    "stretch",
+    "gamma",
+    "brightness",
+    "contrast",
+    "unsharp_mask",
+    "blur",
+    "nlm",
+    "bilateral",
+    "guided",
+    "wavelet_denoise",
+    "chroma_only",
+];
 
 #[derive(Args)]
 pub struct FilterArgs {
@@ -37,70 +50,146 @@ pub struct FilterArgs {
     #[arg(long)]
     pub blur: Option<f32>,
 
+    /// Non-local means denoise: "strength,patch_radius,search_radius" (e.g. "1.0,2,5")
+    #[arg(long)]
+    pub nlm: Option<String>,
+
+    /// Bilateral denoise: "spatial_sigma,range_sigma" (e.g. "2.0,0.05")
+    #[arg(long)]
+    pub bilateral: Option<String>,
+
+    /// Guided filter denoise: "radius,epsilon" (e.g. "4,0.001")
+    #[arg(long)]
+    pub guided: Option<String>,
+
+    /// Wavelet (BayesShrink) denoise: "layers,strength" (e.g. "4,1.0")
+    #[arg(long)]
+    pub wavelet_denoise: Option<String>,
+
+    /// On colour images, apply the denoisers to the colour differences only
+    #[arg(long)]
+    pub chroma_only: bool,
+
+    /// TOML recipe whose [[filters]] replace the individual filter options
+    /// (a pipeline config works too)
+    #[arg(long, conflicts_with_all = FILTER_OPTIONS)]
+    pub recipe: Option<PathBuf>,
+
     /// Output file path
     #[arg(short, long, default_value = "filtered.tiff")]
     pub output: PathBuf,
 }
 
 pub fn run(args: &FilterArgs) -> Result<()> {
-    let mut frame = load_image(&args.file)
-        .with_context(|| format!("Failed to load {}", args.file.display()))?;
+    let image = load_input(&args.file)?;
+
+    let steps = match args.recipe {
+        Some(ref path) => load_recipe(path)?.filters,
+        None => filter_steps(args)?,
+    };
+    if steps.is_empty() {
+        eprintln!("No filters given, copying the image unchanged");
+    }
 
-    println!("Loaded {}x{} image", frame.width(), frame.height());
+    let image = apply_filters(image, &steps);
+    save_output(&image, &args.output)
+}
+
+/// The filter steps requested on the command line, in order.
+fn filter_steps(args: &FilterArgs) -> Result<Vec<FilterStep>> {
+    // Denoisers run first, on the unstretched data.
+    let mut steps = denoise_steps(args)?;
 
     if let Some(ref stretch_str) = args.stretch {
         if stretch_str == "auto" {
-            println!("Applying auto histogram stretch");
-            frame = auto_stretch(&frame, 0.001, 0.999);
+            steps.push(FilterStep::AutoStretch {
+                low_percentile: 0.001,
+                high_percentile: 0.999,
+            });
         } else {
-            let parts: Vec<f32> = stretch_str
-                .split(',')
-                .map(|s| s.trim().parse::<f32>())
-                .collect::<std::result::Result<_, _>>()
-                .context("Invalid stretch format (expected 'auto' or 'black,white')")?;
-            if parts.len() != 2 {
-                anyhow::bail!("Stretch requires exactly 2 values: black_point,white_point");
-            }
-            println!("Applying histogram stretch [{}, {}]", parts[0], parts[1]);
-            frame = histogram_stretch(&frame, parts[0], parts[1]);
+            let v = parse_values(stretch_str, 2, "black,white")?;
+            steps.push(FilterStep::HistogramStretch {
+                black_point: v[0],
+                white_point: v[1],
+            });
         }
     }
 
     if let Some(gamma) = args.gamma {
-        println!("Applying gamma correction: {}", gamma);
-        frame = gamma_correct(&frame, gamma);
+        steps.push(FilterStep::Gamma(gamma));
     }
 
     if args.brightness.is_some() || args.contrast.is_some() {
-        let b = args.brightness.unwrap_or(0.0);
-        let c = args.contrast.unwrap_or(1.0);
-        println!("Applying brightness={}, contrast={}", b, c);
-        frame = brightness_contrast(&frame, b, c);
+        steps.push(FilterStep::BrightnessContrast {
+            brightness: args.brightness.unwrap_or(0.0),
+            contrast: args.contrast.unwrap_or(1.0),
+        });
     }
 
     if let Some(ref usm_str) = args.unsharp_mask {
-        let parts: Vec<f32> = usm_str
-            .split(',')
-            .map(|s| s.trim().parse::<f32>())
-            .collect::<std::result::Result<_, _>>()
-            .context("Invalid unsharp mask format (expected 'radius,amount,threshold')")?;
-        if parts.len() != 3 {
-            anyhow::bail!("Unsharp mask requires exactly 3 values: radius,amount,threshold");
-        }
-        println!(
-            "Applying unsharp mask: radius={}, amount={}, threshold={}",
-            parts[0], parts[1], parts[2]
-        );
-        frame = unsharp_mask(&frame, parts[0], parts[1], parts[2]);
+        let v = parse_values(usm_str, 3, "radius,amount,threshold")?;
+        steps.push(FilterStep::UnsharpMask {
+            radius: v[0],
+            amount: v[1],
+            threshold: v[2],
+        });
     }
 
     if let Some(sigma) = args.blur {
-        println!("Applying Gaussian blur: sigma={}", sigma);
-        frame = gaussian_blur(&frame, sigma);
+        steps.push(FilterStep::GaussianBlur { sigma });
     }
 
-    save_image(&frame, &args.output)?;
-    println!("Saved to {}", args.output.display());
+    Ok(steps)
+}
 
-    Ok(())
+/// The denoise steps requested on the command line, in order.
+fn denoise_steps(args: &FilterArgs) -> Result<Vec<FilterStep>> {
+    let mut steps = Vec::new();
+    if let Some(ref nlm) = args.nlm {
+        let v = parse_values(nlm, 3, "strength,patch_radius,search_radius")?;
+        steps.push(FilterStep::NonLocalMeans {
+            strength: v[0],
+            patch_radius: v[1] as usize,
+            search_radius: v[2] as usize,
+            chroma_only: args.chroma_only,
+        });
+    }
+    if let Some(ref bilateral) = args.bilateral {
+        let v = parse_values(bilateral, 2, "spatial_sigma,range_sigma")?;
+        steps.push(FilterStep::Bilateral {
+            spatial_sigma: v[0],
+            range_sigma: v[1],
+            chroma_only: args.chroma_only,
+        });
+    }
+    if let Some(ref guided) = args.guided {
+        let v = parse_values(guided, 2, "radius,epsilon")?;
+        steps.push(FilterStep::GuidedFilter {
+            radius: v[0] as usize,
+            epsilon: v[1],
+            chroma_only: args.chroma_only,
+        });
+    }
+    if let Some(ref wavelet) = args.wavelet_denoise {
+        let v = parse_values(wavelet, 2, "layers,strength")?;
+        steps.push(FilterStep::WaveletDenoise {
+            layers: v[0] as usize,
+            strength: v[1],
+            chroma_only: args.chroma_only,
+        });
+    }
+    Ok(steps)
+}
+
+/// Parse exactly `count` comma-separated numbers described by `format`.
+fn parse_values(s: &str, count: usize, format: &str) -> Result<Vec<f32>> {
+    let values: Vec<f32> = s
+        .split(',')
+        .map(|v| v.trim().parse::<f32>())
+        .collect::<std::result::Result<_, _>>()
+        .with_context(|| format!("Invalid value '{s}' (expected '{format}')"))?;
+    if values.len() != count {
+        anyhow::bail!("Expected {count} values: {format}");
+    }
+    Ok(values)
 }
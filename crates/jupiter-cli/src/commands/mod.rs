@@ -1,4 +1,5 @@
 pub mod auto_crop;
+pub mod compose;
 pub mod config;
 pub mod filter;
 pub mod info;
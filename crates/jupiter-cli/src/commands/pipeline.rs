@@ -1,25 +1,39 @@
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Result};
 use clap::Args;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use jupiter_core::color::debayer::DebayerMethod;
 use jupiter_core::compute::{create_backend, DevicePreference};
+use jupiter_core::io::ser::FileContribution;
 use jupiter_core::pipeline::config::{
     AlignmentConfig, AlignmentMethod, CentroidConfig, DebayerConfig, DeconvolutionConfig,
-    DeconvolutionMethod, EnhancedPhaseConfig, FilterStep, FrameSelectionConfig, MemoryStrategy,
-    PipelineConfig, PsfModel, PyramidConfig, SharpeningConfig, StackMethod, StackingConfig,
+    DeconvolutionMethod, EnhancedPhaseConfig, FilterStep, FrameSelectionConfig, LimbFitConfig,
+    LimbModel, MemoryStrategy, OutlierRejection, PipelineConfig, PsfModel, PsfRegion,
+    PyramidConfig, ReferenceRefinement, SharpeningConfig, StackMethod, StackingConfig, TilePsf,
+    TiledDeconvolution,
 };
-use jupiter_core::pipeline::{run_pipeline_reported, PipelineStage, ProgressReporter};
-use jupiter_core::sharpen::wavelet::WaveletParams;
+use jupiter_core::pipeline::session::SessionCache;
+use jupiter_core::pipeline::{
+    run_pipeline_cached, run_pipeline_reported, PipelineStage, ProgressReporter,
+};
+use jupiter_core::sharpen::regularized_rl::RlOptions;
+use jupiter_core::sharpen::wavelet::{LayerDenoise, WaveletKernel, WaveletParams};
+use jupiter_core::stack::ap_placement::load_ap_list;
 use jupiter_core::stack::drizzle::DrizzleConfig;
-use jupiter_core::stack::multi_point::MultiPointConfig;
+use jupiter_core::stack::multi_point::{ApPlacement, MultiPointConfig};
+use jupiter_core::stack::optical_flow::OpticalFlowConfig;
+use jupiter_core::stack::rejection::GesdParams;
 use jupiter_core::stack::sigma_clip::SigmaClipParams;
+use jupiter_core::stack::super_resolution::SuperResolutionConfig;
 use jupiter_core::stack::surface_warp::SurfaceWarpConfig;
 
-use super::stack::StackMethodArg;
+use super::sharpen::{
+    parse_layer_denoise, parse_psf_region, parse_wavelet_kernel, wavelet_scaling,
+};
+use super::stack::{LocalMethodArg, StackMethodArg, WarpFieldArg};
 
 #[derive(Clone, clap::ValueEnum)]
 pub enum DeviceArg {
@@ -49,13 +63,29 @@ pub enum AlignMethodArg {
     Centroid,
     Gradient,
     Pyramid,
+    LimbFit,
+}
+
+#[derive(Clone, clap::ValueEnum)]
+pub enum LimbModelArg {
+    Circle,
+    Ellipse,
+}
+
+#[derive(Clone, clap::ValueEnum)]
+pub enum ApPlacementArg {
+    Grid,
+    Structure,
+    Limb,
+    MultiScale,
 }
 
 #[derive(Args)]
 pub struct RunArgs {
-    /// Input SER file
+    /// Input SER file(s); several files from one session are stacked as one
+    /// sequence, with frames selected across all of them
     #[arg(required_unless_present = "config")]
-    pub file: Option<PathBuf>,
+    pub files: Vec<PathBuf>,
 
     /// Pipeline config file (TOML)
     #[arg(long)]
@@ -93,18 +123,72 @@ pub struct RunArgs {
     #[arg(long, default_value = "3")]
     pub pyramid_levels: usize,
 
+    /// Minimum limb edge strength for limb-fit alignment (0.0-1.0, fraction of the strongest edge)
+    #[arg(long, default_value = "0.3")]
+    pub limb_edge_threshold: f32,
+
+    /// Limb shape for limb-fit alignment (ellipse for oblate disks such as Jupiter and Saturn)
+    #[arg(long, value_enum, default_value = "circle")]
+    pub limb_model: LimbModelArg,
+
+    /// Re-align against a stacked reference built from the best aligned frames
+    #[arg(long)]
+    pub refine_reference: bool,
+
+    /// Fraction of the selected frames averaged into the stacked reference (0.0-1.0)
+    #[arg(long, default_value = "0.25")]
+    pub refine_fraction: f32,
+
+    /// Maximum number of re-alignment passes against the stacked reference
+    #[arg(long, default_value = "3")]
+    pub refine_passes: usize,
+
+    /// Reject frames whose alignment is unreliable or far off the drift track
+    #[arg(long)]
+    pub reject_outliers: bool,
+
+    /// Minimum correlation peak-to-mean ratio of an aligned frame (outlier rejection)
+    #[arg(long, default_value = "8.0")]
+    pub min_confidence: f64,
+
+    /// Maximum distance in pixels from the smoothed drift track (outlier rejection)
+    #[arg(long, default_value = "3.0")]
+    pub max_drift: f64,
+
     /// Percentage of best frames to keep (1-100)
     #[arg(long, default_value = "25")]
     pub select: u32,
 
+    /// Frame report (CSV/JSON from `jupiter quality --export`) whose selected
+    /// frames replace --select (for every stacking method)
+    #[arg(long)]
+    pub frames: Option<PathBuf>,
+
     /// Stacking method
     #[arg(long, value_enum, default_value = "multi-point")]
     pub method: StackMethodArg,
 
-    /// Sigma threshold for sigma-clip stacking
+    /// Sigma threshold for sigma-clip, winsorized and linear-fit stacking
     #[arg(long, default_value = "2.5")]
     pub sigma: f32,
 
+    /// Largest fraction of a pixel's values GESD may reject
+    #[arg(long, default_value = "0.3")]
+    pub gesd_outliers: f32,
+
+    /// Significance level of each GESD test
+    #[arg(long, default_value = "0.05")]
+    pub gesd_alpha: f32,
+
+    /// Weight frames by quality score (mean, median and rejection methods)
+    #[arg(long)]
+    pub weighted: bool,
+
+    /// Per-AP stacking method (multi-point mode); median and the rejection
+    /// methods other than sigma-clip need --memory eager
+    #[arg(long, value_enum, default_value = "mean")]
+    pub local_method: LocalMethodArg,
+
     /// Comma-separated wavelet sharpening coefficients
     #[arg(long)]
     pub sharpen: Option<String>,
@@ -113,6 +197,26 @@ pub struct RunArgs {
     #[arg(long)]
     pub denoise: Option<String>,
 
+    /// Wavelet smoothing kernel (b3 or gaussian)
+    #[arg(long, value_parser = parse_wavelet_kernel, default_value = "b3")]
+    pub wavelet_kernel: WaveletKernel,
+
+    /// Space the wavelet layers linearly by this many pixels instead of doubling
+    #[arg(long)]
+    pub wavelet_step: Option<usize>,
+
+    /// How the denoise thresholds are applied (soft or bilateral)
+    #[arg(long, value_parser = parse_layer_denoise, default_value = "soft")]
+    pub denoise_mode: LayerDenoise,
+
+    /// Strength (0-1) of the protection of the limb and other strong edges from the layer boosts
+    #[arg(long, default_value = "0.0")]
+    pub edge_protection: f32,
+
+    /// Sharpen the luminance of colour images and add the same detail to every channel
+    #[arg(long)]
+    pub luminance: bool,
+
     /// Alignment point size in pixels (multi-point mode)
     #[arg(long, default_value = "64")]
     pub ap_size: usize,
@@ -125,14 +229,74 @@ pub struct RunArgs {
     #[arg(long, default_value = "0.05")]
     pub min_brightness: f32,
 
-    /// Deconvolution method (rl or wiener)
+    /// Alignment point placement (multi-point mode)
+    #[arg(long, value_enum, default_value = "grid")]
+    pub ap_placement: ApPlacementArg,
+
+    /// Minimum gradient energy of a structure-placed AP (0.0-1.0, fraction of the strongest)
+    #[arg(long, default_value = "0.15")]
+    pub ap_min_energy: f32,
+
+    /// Size in pixels of the small APs in multi-scale placement
+    #[arg(long, default_value = "32")]
+    pub ap_small_size: usize,
+
+    /// AP list (`y,x` or `y,x,size` per line) whose points replace --ap-placement
+    #[arg(long)]
+    pub ap_list: Option<PathBuf>,
+
+    /// Deformation field through the AP shifts (surface-warp mode)
+    #[arg(long, value_enum, default_value = "bilinear")]
+    pub warp_field: WarpFieldArg,
+
+    /// Thin-plate spline smoothing for --warp-field spline (0 = exact fit)
+    #[arg(long, default_value = "0.05")]
+    pub warp_smoothing: f32,
+
+    /// Pyramid levels (optical-flow mode)
+    #[arg(long, default_value = "3")]
+    pub flow_levels: usize,
+
+    /// Lucas–Kanade window radius in pixels (optical-flow mode)
+    #[arg(long, default_value = "7")]
+    pub flow_window: usize,
+
+    /// Refinement iterations per pyramid level (optical-flow mode)
+    #[arg(long, default_value = "3")]
+    pub flow_iterations: usize,
+
+    /// Output scale factor (super-resolution mode, 1.0-4.0)
+    #[arg(long, default_value = "2.0")]
+    pub sr_scale: f32,
+
+    /// Back-projection iterations (super-resolution mode)
+    #[arg(long, default_value = "20")]
+    pub sr_iterations: usize,
+
+    /// Gaussian PSF sigma in output pixels (super-resolution mode)
+    #[arg(long, default_value = "1.0")]
+    pub sr_psf_sigma: f32,
+
+    /// Smoothness prior weight (super-resolution mode, 0 = none)
+    #[arg(long, default_value = "0.01")]
+    pub sr_regularization: f32,
+
+    /// Deconvolution method (rl, wiener or blind)
     #[arg(long)]
     pub deconv: Option<String>,
 
-    /// PSF model (gaussian, kolmogorov, airy)
+    /// PSF model (gaussian, kolmogorov, airy, measured, limb)
     #[arg(long, default_value = "gaussian")]
     pub psf: String,
 
+    /// Image of a point source (star or small moon) for the measured PSF
+    #[arg(long, required_if_eq("psf", "measured"))]
+    pub psf_file: Option<PathBuf>,
+
+    /// Region X,Y,W,H of the PSF image holding the point source [default: whole image]
+    #[arg(long, value_parser = parse_psf_region)]
+    pub psf_region: Option<PsfRegion>,
+
     /// Gaussian PSF sigma in pixels
     #[arg(long, default_value = "2.0")]
     pub psf_sigma: f32,
@@ -145,14 +309,46 @@ pub struct RunArgs {
     #[arg(long, default_value = "2.5")]
     pub airy_radius: f32,
 
-    /// Richardson-Lucy iteration count
+    /// Richardson-Lucy iteration count (rounds for blind deconvolution)
     #[arg(long, default_value = "20")]
     pub rl_iterations: usize,
 
+    /// Richardson-Lucy total-variation regularization weight (0 = off)
+    #[arg(long, default_value = "0.0")]
+    pub rl_tv: f32,
+
+    /// Richardson-Lucy damping threshold in noise sigmas (0 = off)
+    #[arg(long, default_value = "0.0")]
+    pub rl_damping: f32,
+
+    /// Stop Richardson-Lucy early once the residual reaches the noise level
+    #[arg(long)]
+    pub rl_auto_stop: bool,
+
+    /// Mirror-pad the image edges for Richardson-Lucy instead of wrapping around
+    #[arg(long)]
+    pub rl_pad_edges: bool,
+
+    /// Only deconvolve the fitted planet disk with Richardson-Lucy
+    #[arg(long)]
+    pub rl_planet_mask: bool,
+
+    /// Side length in pixels of the PSF estimated by blind deconvolution
+    #[arg(long, default_value = "15")]
+    pub psf_size: usize,
+
     /// Wiener noise-to-signal ratio
     #[arg(long, default_value = "0.001")]
     pub noise_ratio: f32,
 
+    /// Deconvolve in overlapping tiles of this size in pixels
+    #[arg(long)]
+    pub deconv_tiles: Option<usize>,
+
+    /// PSF per tile for tiled deconvolution (uniform or quality)
+    #[arg(long, default_value = "quality")]
+    pub tile_psf: String,
+
     /// Drizzle output scale factor (e.g., 2.0 = 2x resolution)
     #[arg(long, default_value = "2.0")]
     pub drizzle_scale: f32,
@@ -180,6 +376,19 @@ pub struct RunArgs {
     /// Save effective config as TOML and exit without processing
     #[arg(long)]
     pub save_config: Option<PathBuf>,
+
+    /// Don't read or write the session cache next to the input file
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Delete the session cache for the input file(s) before running
+    #[arg(long)]
+    pub clear_cache: bool,
+
+    /// Write AP diagnostics (JSON, shift quiver, usage heatmap) to this
+    /// directory (multi-point and surface warp; needs the session cache)
+    #[arg(long)]
+    pub ap_diagnostics: Option<PathBuf>,
 }
 
 /// Progress reporter using indicatif MultiProgress with stage + detail bars.
@@ -188,6 +397,7 @@ struct MultiProgressReporter {
     detail_bar: ProgressBar,
     stage_count: AtomicUsize,
     current_total: AtomicUsize,
+    contributions: Mutex<Vec<FileContribution>>,
 }
 
 impl MultiProgressReporter {
@@ -212,12 +422,25 @@ impl MultiProgressReporter {
             detail_bar,
             stage_count: AtomicUsize::new(0),
             current_total: AtomicUsize::new(0),
+            contributions: Mutex::new(Vec::new()),
         })
     }
 
     fn finish(&self) {
         self.detail_bar.finish_and_clear();
         self.stage_bar.finish_with_message("Done");
+
+        let contributions = self.contributions.lock().unwrap();
+        if !contributions.is_empty() {
+            println!("\nFrames stacked per file:");
+            for c in contributions.iter() {
+                let name = c.path.display();
+                match c.selected {
+                    Some(selected) => println!("  {name}: {selected} of {} frames", c.frames),
+                    None => println!("  {name}: {} frames, selected per region", c.frames),
+                }
+            }
+        }
     }
 }
 
@@ -242,6 +465,10 @@ impl ProgressReporter for MultiProgressReporter {
         self.detail_bar.set_position(items_done as u64);
     }
 
+    fn file_contributions(&self, contributions: &[FileContribution]) {
+        *self.contributions.lock().unwrap() = contributions.to_vec();
+    }
+
     fn finish_stage(&self) {
         let count = self.stage_count.fetch_add(1, Ordering::Relaxed) + 1;
         self.stage_bar.set_position(count as u64);
@@ -264,14 +491,26 @@ pub fn run(args: &RunArgs) -> Result<()> {
     };
 
     // CLI overrides TOML
-    if let Some(ref file) = args.file {
-        config.input = file.clone();
+    if let Some((first, rest)) = args.files.split_first() {
+        config.input = first.clone();
+        config.extra_inputs = rest.to_vec();
     }
     if let Some(ref out) = args.output {
         config.output = out.clone();
     } else if config.output.as_os_str().is_empty() {
         config.output = PathBuf::from("result.tiff");
     }
+    if let Some(ref frames) = args.frames {
+        config.frame_selection.frame_list = Some(frames.clone());
+    }
+    if let Some(ref ap_list) = args.ap_list {
+        let StackMethod::MultiPoint(ref mut mp) = config.stacking.method else {
+            anyhow::bail!("--ap-list requires multi-point stacking");
+        };
+        let points = load_ap_list(ap_list)
+            .with_context(|| format!("Failed to read AP list {}", ap_list.display()))?;
+        mp.placement = ApPlacement::Custom { points };
+    }
 
     // Save config and exit if --save-config is set
     if let Some(ref save_path) = args.save_config {
@@ -283,16 +522,51 @@ pub fn run(args: &RunArgs) -> Result<()> {
         return Ok(());
     }
 
+    if args.ap_diagnostics.is_some() {
+        if args.no_cache {
+            anyhow::bail!("--ap-diagnostics needs the session cache; drop --no-cache");
+        }
+        if !matches!(
+            config.stacking.method,
+            StackMethod::MultiPoint(_) | StackMethod::SurfaceWarp(_)
+        ) {
+            anyhow::bail!("--ap-diagnostics requires multi-point or surface warp stacking");
+        }
+    }
+
     let backend = create_backend(&config.device);
     crate::summary::print_pipeline_summary(&config, backend.name());
 
     let multi = MultiProgress::new();
     let reporter = Arc::new(MultiProgressReporter::new(&multi)?);
 
-    run_pipeline_reported(&config, backend, reporter.clone())?;
+    if args.clear_cache {
+        SessionCache::clear(&config.inputs())?;
+    }
+
+    let session = if args.no_cache {
+        run_pipeline_reported(&config, backend, reporter.clone())?;
+        None
+    } else {
+        let mut session = SessionCache::open_all(&config.inputs())?;
+        let output = run_pipeline_cached(&config, backend, reporter.clone(), &mut session)?;
+        if let Some(ref dir) = args.ap_diagnostics {
+            let diagnostics = session
+                .last_ap_diagnostics()
+                .context("No AP diagnostics in the session cache")?;
+            diagnostics.save(dir, Some(&output.to_mono().data))?;
+        }
+        Some(session)
+    };
 
     reporter.finish();
     println!("\nOutput saved to {}", config.output.display());
+    if let Some(ref dir) = args.ap_diagnostics {
+        println!("AP diagnostics saved to {}", dir.display());
+    }
+    if let Some(session) = session {
+        println!("Session cached in {}", session.dir().display());
+    }
 
     Ok(())
 }
@@ -301,7 +575,7 @@ fn build_config_from_args(args: &RunArgs) -> PipelineConfig {
     let sharpening = if args.no_sharpen {
         None
     } else {
-        let wavelet = if let Some(ref coeff_str) = args.sharpen {
+        let mut wavelet = if let Some(ref coeff_str) = args.sharpen {
             let coefficients: Vec<f32> = coeff_str
                 .split(',')
                 .filter_map(|s| s.trim().parse().ok())
@@ -315,6 +589,7 @@ fn build_config_from_args(args: &RunArgs) -> PipelineConfig {
                 num_layers: coefficients.len(),
                 coefficients,
                 denoise,
+                ..Default::default()
             }
         } else {
             let mut params = WaveletParams::default();
@@ -323,6 +598,11 @@ fn build_config_from_args(args: &RunArgs) -> PipelineConfig {
             }
             params
         };
+        wavelet.denoise_mode = args.denoise_mode;
+        wavelet.kernel = args.wavelet_kernel;
+        wavelet.scaling = wavelet_scaling(args.wavelet_step);
+        wavelet.edge_protection = args.edge_protection;
+        wavelet.linked_luminance = args.luminance;
         let deconvolution = build_deconv_config(args);
         Some(SharpeningConfig {
             wavelet,
@@ -330,6 +610,10 @@ fn build_config_from_args(args: &RunArgs) -> PipelineConfig {
         })
     };
 
+    let gesd = GesdParams {
+        max_outliers: args.gesd_outliers,
+        alpha: args.gesd_alpha,
+    };
     let stacking_method = match args.method {
         StackMethodArg::Mean => StackMethod::Mean,
         StackMethodArg::Median => StackMethod::Median,
@@ -337,11 +621,32 @@ fn build_config_from_args(args: &RunArgs) -> PipelineConfig {
             sigma: args.sigma,
             ..Default::default()
         }),
+        StackMethodArg::Winsorized => StackMethod::WinsorizedSigmaClip(SigmaClipParams {
+            sigma: args.sigma,
+            ..Default::default()
+        }),
+        StackMethodArg::LinearFit => StackMethod::LinearFitClip(SigmaClipParams {
+            sigma: args.sigma,
+            ..Default::default()
+        }),
+        StackMethodArg::Gesd => StackMethod::Gesd(gesd.clone()),
         StackMethodArg::MultiPoint => StackMethod::MultiPoint(MultiPointConfig {
             ap_size: args.ap_size,
             search_radius: args.search_radius,
             select_percentage: args.select as f32 / 100.0,
             min_brightness: args.min_brightness,
+            local_stack_method: args.local_method.to_local(args.sigma, &gesd),
+            placement: match args.ap_placement {
+                ApPlacementArg::Grid => ApPlacement::Grid,
+                ApPlacementArg::Structure => ApPlacement::Structure {
+                    min_energy: args.ap_min_energy,
+                },
+                ApPlacementArg::Limb => ApPlacement::Limb,
+                ApPlacementArg::MultiScale => ApPlacement::MultiScale {
+                    small_size: args.ap_small_size,
+                    min_energy: args.ap_min_energy,
+                },
+            },
             ..Default::default()
         }),
         StackMethodArg::Drizzle => StackMethod::Drizzle(DrizzleConfig {
@@ -355,8 +660,25 @@ fn build_config_from_args(args: &RunArgs) -> PipelineConfig {
             search_radius: args.search_radius,
             select_percentage: args.select as f32 / 100.0,
             min_brightness: args.min_brightness,
+            field_model: args.warp_field.to_model(args.warp_smoothing),
             ..Default::default()
         }),
+        StackMethodArg::OpticalFlow => StackMethod::OpticalFlow(OpticalFlowConfig {
+            select_percentage: args.select as f32 / 100.0,
+            levels: args.flow_levels,
+            window_radius: args.flow_window,
+            iterations: args.flow_iterations,
+            ..Default::default()
+        }),
+        StackMethodArg::SuperResolution => StackMethod::SuperResolution(SuperResolutionConfig {
+            scale: args.sr_scale,
+            iterations: args.sr_iterations,
+            psf: PsfModel::Gaussian {
+                sigma: args.sr_psf_sigma,
+            },
+            regularization: args.sr_regularization,
+            quality_weighted: true,
+        }),
     };
 
     let mut filters = Vec::new();
@@ -377,8 +699,9 @@ fn build_config_from_args(args: &RunArgs) -> PipelineConfig {
         DeviceArg::Cuda => DevicePreference::Cuda,
     };
 
-    // file is always Some when --config is absent (required_unless_present)
-    let input = args.file.clone().unwrap_or_default();
+    // files is never empty when --config is absent (required_unless_present)
+    let input = args.files.first().cloned().unwrap_or_default();
+    let extra_inputs = args.files.iter().skip(1).cloned().collect();
     let output = args
         .output
         .clone()
@@ -397,6 +720,7 @@ fn build_config_from_args(args: &RunArgs) -> PipelineConfig {
 
     PipelineConfig {
         input,
+        extra_inputs,
         output,
         device,
         memory: match args.memory {
@@ -425,10 +749,29 @@ fn build_config_from_args(args: &RunArgs) -> PipelineConfig {
                 AlignMethodArg::Pyramid => AlignmentMethod::Pyramid(PyramidConfig {
                     levels: args.pyramid_levels,
                 }),
+                AlignMethodArg::LimbFit => AlignmentMethod::LimbFit(LimbFitConfig {
+                    model: match args.limb_model {
+                        LimbModelArg::Circle => LimbModel::Circle,
+                        LimbModelArg::Ellipse => LimbModel::Ellipse,
+                    },
+                    edge_threshold: args.limb_edge_threshold,
+                    ..Default::default()
+                }),
             },
+            refinement: args.refine_reference.then(|| ReferenceRefinement {
+                top_fraction: args.refine_fraction,
+                max_passes: args.refine_passes,
+                ..Default::default()
+            }),
+            rejection: args.reject_outliers.then(|| OutlierRejection {
+                min_confidence: args.min_confidence,
+                max_drift: args.max_drift,
+                ..Default::default()
+            }),
         },
         stacking: StackingConfig {
             method: stacking_method,
+            quality_weighted: args.weighted,
         },
         sharpening,
         filters,
@@ -441,10 +784,21 @@ fn build_deconv_config(args: &RunArgs) -> Option<DeconvolutionConfig> {
     let method = match method_str {
         "rl" => DeconvolutionMethod::RichardsonLucy {
             iterations: args.rl_iterations,
+            options: RlOptions {
+                tv_weight: args.rl_tv,
+                damping: args.rl_damping,
+                auto_stop: args.rl_auto_stop,
+                pad_edges: args.rl_pad_edges,
+                planet_mask: args.rl_planet_mask,
+            },
         },
         "wiener" => DeconvolutionMethod::Wiener {
             noise_ratio: args.noise_ratio,
         },
+        "blind" => DeconvolutionMethod::BlindRichardsonLucy {
+            iterations: args.rl_iterations,
+            psf_size: args.psf_size,
+        },
         _ => {
             eprintln!("Unknown deconv method '{}', skipping", method_str);
             return None;
@@ -461,6 +815,11 @@ fn build_deconv_config(args: &RunArgs) -> Option<DeconvolutionConfig> {
         "airy" => PsfModel::Airy {
             radius: args.airy_radius,
         },
+        "measured" => PsfModel::Measured {
+            path: args.psf_file.clone()?,
+            region: args.psf_region,
+        },
+        "limb" => PsfModel::Limb,
         _ => {
             eprintln!("Unknown PSF model '{}', using Gaussian", args.psf);
             PsfModel::Gaussian {
@@ -469,5 +828,21 @@ fn build_deconv_config(args: &RunArgs) -> Option<DeconvolutionConfig> {
         }
     };
 
-    Some(DeconvolutionConfig { method, psf })
+    let tiling = args.deconv_tiles.map(|tile_size| TiledDeconvolution {
+        tile_size,
+        psf: match args.tile_psf.as_str() {
+            "uniform" => TilePsf::Uniform,
+            "quality" => TilePsf::LocalQuality,
+            other => {
+                eprintln!("Unknown tile PSF '{}', using quality", other);
+                TilePsf::LocalQuality
+            }
+        },
+    });
+
+    Some(DeconvolutionConfig {
+        method,
+        psf,
+        tiling,
+    })
 }
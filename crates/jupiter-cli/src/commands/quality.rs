@@ -3,7 +3,11 @@ use std::path::PathBuf;
 use anyhow::Result;
 use clap::{Args, ValueEnum};
 use indicatif::{ProgressBar, ProgressStyle};
+use jupiter_core::align::phase_correlation::compute_offset_with_confidence;
+use jupiter_core::frame::AlignmentOffset;
+use jupiter_core::io::frame_report::FrameReport;
 use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::QualityMetric;
 use jupiter_core::quality::gradient::rank_frames_gradient;
 use jupiter_core::quality::laplacian::rank_frames;
 
@@ -25,6 +29,18 @@ pub struct QualityArgs {
     /// Quality metric to use
     #[arg(long, value_enum, default_value = "laplacian")]
     pub metric: MetricArg,
+
+    /// Write per-frame scores to a CSV or JSON report (by extension)
+    #[arg(long)]
+    pub export: Option<PathBuf>,
+
+    /// Percentage of best frames flagged as selected in the report (1-100)
+    #[arg(long, default_value = "25", value_parser = clap::value_parser!(u32).range(1..=100))]
+    pub select: u32,
+
+    /// Also compute alignment offsets of selected frames against the best frame
+    #[arg(long, requires = "export")]
+    pub offsets: bool,
 }
 
 pub fn run(args: &QualityArgs) -> Result<()> {
@@ -79,5 +95,50 @@ pub fn run(args: &QualityArgs) -> Result<()> {
         println!("Worst score: {:.6}", worst);
     }
 
+    if let Some(ref path) = args.export {
+        let mut report = FrameReport::new(total);
+        report.record_timestamps(&reader);
+        // Record the secondary metric first so `composite` ends up as the ranking metric.
+        match args.metric {
+            MetricArg::Laplacian => {
+                report.record_scores(&QualityMetric::Gradient, &rank_frames_gradient(&frames));
+                report.record_scores(&QualityMetric::Laplacian, &ranked);
+            }
+            MetricArg::Gradient => {
+                report.record_scores(&QualityMetric::Laplacian, &rank_frames(&frames));
+                report.record_scores(&QualityMetric::Gradient, &ranked);
+            }
+        }
+
+        let keep = ((total as f32 * args.select as f32 / 100.0).ceil() as usize)
+            .max(1)
+            .min(total);
+        let selected: Vec<usize> = ranked.iter().take(keep).map(|(i, _)| *i).collect();
+        report.mark_selected(&selected);
+
+        if args.offsets && !selected.is_empty() {
+            let reference = &frames[selected[0]].data;
+            let mut offsets = Vec::with_capacity(selected.len());
+            let mut confidences = Vec::with_capacity(selected.len());
+            for &idx in &selected {
+                let (offset, confidence) = if idx == selected[0] {
+                    (AlignmentOffset::default(), f64::INFINITY)
+                } else {
+                    compute_offset_with_confidence(reference, &frames[idx].data)?
+                };
+                offsets.push(offset);
+                confidences.push(confidence);
+            }
+            report.record_offsets(&selected, &offsets, Some(&confidences));
+        }
+
+        report.save(path)?;
+        println!(
+            "\nFrame report ({} selected) written to {}",
+            selected.len(),
+            path.display()
+        );
+    }
+
     Ok(())
 }
@@ -1,11 +1,51 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use clap::Args;
-use jupiter_core::io::image_io::{load_image, save_image};
-use jupiter_core::pipeline::config::{DeconvolutionConfig, DeconvolutionMethod, PsfModel};
-use jupiter_core::sharpen::deconvolution::deconvolve;
-use jupiter_core::sharpen::wavelet::{self, WaveletParams};
+use jupiter_core::frame::ColorFrame;
+use jupiter_core::pipeline::config::{
+    DeconvolutionConfig, DeconvolutionMethod, PsfModel, PsfRegion, SharpeningConfig, TilePsf,
+    TiledDeconvolution,
+};
+use jupiter_core::pipeline::PipelineOutput;
+use jupiter_core::sharpen::deconvolution::{deconvolve_loaded, deconvolve_with_psf, Psf};
+use jupiter_core::sharpen::psf::{kernel_sigma, save_psf};
+use jupiter_core::sharpen::regularized_rl::RlOptions;
+use jupiter_core::sharpen::wavelet::{
+    self, LayerDenoise, WaveletKernel, WaveletParams, WaveletScaling,
+};
+use ndarray::Array2;
+
+use crate::postprocess::{apply_filters, load_input, load_recipe, save_output};
+
+/// Options a recipe's `[sharpening]` replaces; `--recipe` conflicts with them.
+const SHARPEN_OPTIONS: [&str; 25] = [
+    "layers",
+    "coefficients",
+    "denoise",
+    "wavelet_kernel",
+    "wavelet_step",
+    "denoise_mode",
+    "edge_protection",
+    "luminance",
+    "deconv",
+    "psf",
+    "psf_file",
+    "psf_region",
+    "psf_sigma",
+    "seeing",
+    "airy_radius",
+    "rl_iterations",
+    "rl_tv",
+    "rl_damping",
+    "rl_auto_stop",
+    "rl_pad_edges",
+    "rl_planet_mask",
+    "psf_size",
+    "noise_ratio",
+    "deconv_tiles",
+    "tile_psf",
+];
 
 #[derive(Args)]
 pub struct SharpenArgs {
@@ -24,14 +64,42 @@ pub struct SharpenArgs {
     #[arg(long)]
     pub denoise: Option<String>,
 
-    /// Deconvolution method (rl or wiener)
+    /// Wavelet smoothing kernel (b3 or gaussian)
+    #[arg(long, value_parser = parse_wavelet_kernel, default_value = "b3")]
+    pub wavelet_kernel: WaveletKernel,
+
+    /// Space the wavelet layers linearly by this many pixels instead of doubling
+    #[arg(long)]
+    pub wavelet_step: Option<usize>,
+
+    /// How the denoise thresholds are applied (soft or bilateral)
+    #[arg(long, value_parser = parse_layer_denoise, default_value = "soft")]
+    pub denoise_mode: LayerDenoise,
+
+    /// Strength (0-1) of the protection of the limb and other strong edges from the layer boosts
+    #[arg(long, default_value = "0.0")]
+    pub edge_protection: f32,
+
+    /// On colour images, sharpen the luminance and add its detail to every channel
+    #[arg(long)]
+    pub luminance: bool,
+
+    /// Deconvolution method (rl, wiener or blind)
     #[arg(long)]
     pub deconv: Option<String>,
 
-    /// PSF model (gaussian, kolmogorov, airy)
+    /// PSF model (gaussian, kolmogorov, airy, measured, limb)
     #[arg(long, default_value = "gaussian")]
     pub psf: String,
 
+    /// Image of a point source (star or small moon) for the measured PSF
+    #[arg(long, required_if_eq("psf", "measured"))]
+    pub psf_file: Option<PathBuf>,
+
+    /// Region X,Y,W,H of the PSF image holding the point source [default: whole image]
+    #[arg(long, value_parser = parse_psf_region)]
+    pub psf_region: Option<PsfRegion>,
+
     /// Gaussian PSF sigma in pixels
     #[arg(long, default_value = "2.0")]
     pub psf_sigma: f32,
@@ -44,25 +112,117 @@ pub struct SharpenArgs {
     #[arg(long, default_value = "2.5")]
     pub airy_radius: f32,
 
-    /// Richardson-Lucy iteration count
+    /// Richardson-Lucy iteration count (rounds for blind deconvolution)
     #[arg(long, default_value = "20")]
     pub rl_iterations: usize,
 
+    /// Richardson-Lucy total-variation regularization weight (0 = off)
+    #[arg(long, default_value = "0.0")]
+    pub rl_tv: f32,
+
+    /// Richardson-Lucy damping threshold in noise sigmas (0 = off)
+    #[arg(long, default_value = "0.0")]
+    pub rl_damping: f32,
+
+    /// Stop Richardson-Lucy early once the residual reaches the noise level
+    #[arg(long)]
+    pub rl_auto_stop: bool,
+
+    /// Mirror-pad the image edges for Richardson-Lucy instead of wrapping around
+    #[arg(long)]
+    pub rl_pad_edges: bool,
+
+    /// Only deconvolve the fitted planet disk with Richardson-Lucy
+    #[arg(long)]
+    pub rl_planet_mask: bool,
+
+    /// Side length in pixels of the PSF estimated by blind deconvolution
+    #[arg(long, default_value = "15")]
+    pub psf_size: usize,
+
     /// Wiener noise-to-signal ratio
     #[arg(long, default_value = "0.001")]
     pub noise_ratio: f32,
 
+    /// Deconvolve in overlapping tiles of this size in pixels
+    #[arg(long)]
+    pub deconv_tiles: Option<usize>,
+
+    /// PSF per tile for tiled deconvolution (uniform or quality)
+    #[arg(long, default_value = "quality")]
+    pub tile_psf: String,
+
+    /// Save the PSF estimated by blind deconvolution to this image
+    #[arg(long)]
+    pub save_psf: Option<PathBuf>,
+
+    /// TOML recipe whose [sharpening] replaces the sharpening options and
+    /// whose [[filters]] run afterwards (a pipeline config works too); the
+    /// options' defaults are used when the recipe has no [sharpening]
+    #[arg(long, conflicts_with_all = SHARPEN_OPTIONS)]
+    pub recipe: Option<PathBuf>,
+
     /// Output file path
     #[arg(short, long, default_value = "sharpened.tiff")]
     pub output: PathBuf,
 }
 
 pub fn run(args: &SharpenArgs) -> Result<()> {
-    let frame = load_image(&args.file)
-        .with_context(|| format!("Failed to load {}", args.file.display()))?;
+    let image = load_input(&args.file)?;
+
+    // A recipe's [sharpening] replaces the sharpening options; its
+    // [[filters]] run afterwards.
+    let (sharpening, filters) = match args.recipe {
+        Some(ref path) => {
+            let recipe = load_recipe(path)?;
+            let sharpening = match recipe.sharpening {
+                Some(sharpening) => sharpening,
+                None => sharpening_from_args(args)?,
+            };
+            (sharpening, recipe.filters)
+        }
+        None => (sharpening_from_args(args)?, Vec::new()),
+    };
+
+    crate::summary::print_sharpen_summary(&sharpening.wavelet, sharpening.deconvolution.as_ref());
+
+    // Deconvolution (before wavelet)
+    let image = match (image, &sharpening.deconvolution) {
+        (PipelineOutput::Mono(frame), Some(deconv_config)) => {
+            let (restored, estimate) = deconvolve_with_psf(&frame, deconv_config)?;
+            save_estimate(estimate, args.save_psf.as_deref())?;
+            PipelineOutput::Mono(restored)
+        }
+        (PipelineOutput::Color(color), Some(deconv_config)) => {
+            let psf = Psf::load(&deconv_config.psf)?;
+            let deconvolve = |frame| deconvolve_loaded(frame, deconv_config, &psf);
+            let (red, (green, blue)) = rayon::join(
+                || deconvolve(&color.red),
+                || rayon::join(|| deconvolve(&color.green), || deconvolve(&color.blue)),
+            );
+            let ((red, _), (green, estimate), (blue, _)) = (red?, green?, blue?);
+            // The PSF saved for colour images is the green channel's estimate.
+            save_estimate(estimate, args.save_psf.as_deref())?;
+            PipelineOutput::Color(ColorFrame { red, green, blue })
+        }
+        (image, None) => image,
+    };
+
+    let sharpened = match image {
+        PipelineOutput::Mono(frame) => {
+            PipelineOutput::Mono(wavelet::sharpen(&frame, &sharpening.wavelet))
+        }
+        PipelineOutput::Color(color) => {
+            PipelineOutput::Color(wavelet::sharpen_color(&color, &sharpening.wavelet))
+        }
+    };
 
-    println!("Loaded {}x{} image", frame.width(), frame.height());
+    let result = apply_filters(sharpened, &filters);
+    save_output(&result, &args.output)
+}
 
+/// Wavelet and deconvolution settings from the command-line options.
+fn sharpening_from_args(args: &SharpenArgs) -> Result<SharpeningConfig> {
     let coefficients = if let Some(ref coeff_str) = args.coefficients {
         coeff_str
             .split(',')
@@ -83,28 +243,41 @@ pub fn run(args: &SharpenArgs) -> Result<()> {
         vec![]
     };
 
-    let params = WaveletParams {
+    let wavelet = WaveletParams {
         num_layers: args.layers,
         coefficients,
-        denoise: denoise.clone(),
+        denoise,
+        denoise_mode: args.denoise_mode,
+        kernel: args.wavelet_kernel,
+        scaling: wavelet_scaling(args.wavelet_step),
+        edge_protection: args.edge_protection,
+        linked_luminance: args.luminance,
     };
 
-    let deconv_config = build_deconv_config(args);
-
-    crate::summary::print_sharpen_summary(&params, deconv_config.as_ref());
-
-    // Deconvolution (before wavelet)
-    let frame = if let Some(ref deconv_config) = deconv_config {
-        deconvolve(&frame, deconv_config)
-    } else {
-        frame
-    };
-
-    let sharpened = wavelet::sharpen(&frame, &params);
-
-    save_image(&sharpened, &args.output)?;
-    println!("Saved to {}", args.output.display());
+    Ok(SharpeningConfig {
+        wavelet,
+        deconvolution: build_deconv_config(args),
+    })
+}
 
+/// Save the PSF estimated by blind deconvolution when `--save-psf` is given.
+fn save_estimate(estimate: Option<Array2<f32>>, path: Option<&Path>) -> Result<()> {
+    match (estimate, path) {
+        (Some(kernel), Some(path)) => {
+            save_psf(&kernel, path)?;
+            println!(
+                "Estimated PSF ({}x{} px, sigma {:.2} px) saved to {}",
+                kernel.ncols(),
+                kernel.nrows(),
+                kernel_sigma(&kernel),
+                path.display()
+            );
+        }
+        (None, Some(_)) => {
+            eprintln!("--save-psf only applies to --deconv blind without --deconv-tiles")
+        }
+        _ => {}
+    }
     Ok(())
 }
 
@@ -114,10 +287,21 @@ fn build_deconv_config(args: &SharpenArgs) -> Option<DeconvolutionConfig> {
     let method = match method_str {
         "rl" => DeconvolutionMethod::RichardsonLucy {
             iterations: args.rl_iterations,
+            options: RlOptions {
+                tv_weight: args.rl_tv,
+                damping: args.rl_damping,
+                auto_stop: args.rl_auto_stop,
+                pad_edges: args.rl_pad_edges,
+                planet_mask: args.rl_planet_mask,
+            },
         },
         "wiener" => DeconvolutionMethod::Wiener {
             noise_ratio: args.noise_ratio,
         },
+        "blind" => DeconvolutionMethod::BlindRichardsonLucy {
+            iterations: args.rl_iterations,
+            psf_size: args.psf_size,
+        },
         _ => {
             eprintln!("Unknown deconv method '{}', skipping", method_str);
             return None;
@@ -134,6 +318,11 @@ fn build_deconv_config(args: &SharpenArgs) -> Option<DeconvolutionConfig> {
         "airy" => PsfModel::Airy {
             radius: args.airy_radius,
         },
+        "measured" => PsfModel::Measured {
+            path: args.psf_file.clone()?,
+            region: args.psf_region,
+        },
+        "limb" => PsfModel::Limb,
         _ => {
             eprintln!("Unknown PSF model '{}', using Gaussian", args.psf);
             PsfModel::Gaussian {
@@ -142,5 +331,71 @@ fn build_deconv_config(args: &SharpenArgs) -> Option<DeconvolutionConfig> {
         }
     };
 
-    Some(DeconvolutionConfig { method, psf })
+    let tiling = args.deconv_tiles.map(|tile_size| TiledDeconvolution {
+        tile_size,
+        psf: match args.tile_psf.as_str() {
+            "uniform" => TilePsf::Uniform,
+            "quality" => TilePsf::LocalQuality,
+            other => {
+                eprintln!("Unknown tile PSF '{}', using quality", other);
+                TilePsf::LocalQuality
+            }
+        },
+    });
+
+    Some(DeconvolutionConfig {
+        method,
+        psf,
+        tiling,
+    })
+}
+
+/// Linear wavelet scales with the given step, dyadic without one.
+pub(crate) fn wavelet_scaling(step: Option<usize>) -> WaveletScaling {
+    match step {
+        Some(step) => WaveletScaling::Linear { step },
+        None => WaveletScaling::Dyadic,
+    }
+}
+
+/// Parse a wavelet kernel name.
+pub(crate) fn parse_wavelet_kernel(s: &str) -> std::result::Result<WaveletKernel, String> {
+    match s {
+        "b3" => Ok(WaveletKernel::B3Spline),
+        "gaussian" => Ok(WaveletKernel::Gaussian),
+        _ => Err(format!(
+            "unknown wavelet kernel '{s}' (expected b3 or gaussian)"
+        )),
+    }
+}
+
+/// Parse a wavelet layer denoise mode.
+pub(crate) fn parse_layer_denoise(s: &str) -> std::result::Result<LayerDenoise, String> {
+    match s {
+        "soft" => Ok(LayerDenoise::SoftThreshold),
+        "bilateral" => Ok(LayerDenoise::Bilateral),
+        _ => Err(format!(
+            "unknown denoise mode '{s}' (expected soft or bilateral)"
+        )),
+    }
+}
+
+/// Parse a PSF region given as `X,Y,W,H`.
+pub(crate) fn parse_psf_region(s: &str) -> std::result::Result<PsfRegion, String> {
+    let values = s
+        .split(',')
+        .map(|v| v.trim().parse::<usize>())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid PSF region '{s}': {e}"))?;
+    match values[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(PsfRegion {
+            x,
+            y,
+            width,
+            height,
+        }),
+        _ => Err(format!(
+            "invalid PSF region '{s}': expected X,Y,W,H with W and H > 0"
+        )),
+    }
 }
@@ -2,32 +2,93 @@ use anyhow::Result;
 use clap::{Args, ValueEnum};
 use indicatif::{ProgressBar, ProgressStyle};
 use jupiter_core::align::phase_correlation::{align_frames_with_progress, compute_offset};
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::frame::{AlignmentOffset, Frame};
 use jupiter_core::io::image_io::save_image;
 use jupiter_core::io::ser::SerReader;
-use jupiter_core::pipeline::config::QualityMetric;
+use jupiter_core::pipeline::config::{AlignmentConfig, PsfModel, QualityMetric, StackMethod};
 use jupiter_core::quality::laplacian::rank_frames;
+use jupiter_core::stack::ap_diagnostics::ApDiagnostics;
 use jupiter_core::stack::drizzle::{drizzle_stack, DrizzleConfig};
-use jupiter_core::stack::mean::mean_stack;
-use jupiter_core::stack::median::median_stack;
-use jupiter_core::stack::multi_point::{multi_point_stack, MultiPointConfig};
-use jupiter_core::stack::sigma_clip::{sigma_clip_stack, SigmaClipParams};
-use jupiter_core::stack::surface_warp::{surface_warp_stack, SurfaceWarpConfig};
-use std::path::PathBuf;
+use jupiter_core::stack::mean::{mean_stack, weighted_mean_stack_with_progress};
+use jupiter_core::stack::median::{median_stack, weighted_median_stack};
+use jupiter_core::stack::multi_point::{
+    multi_point_stack_with_diagnostics, LocalStackMethod, MultiPointConfig,
+};
+use jupiter_core::stack::optical_flow::{optical_flow_stack, OpticalFlowConfig};
+use jupiter_core::stack::rejection::{rejection_stack, GesdParams};
+use jupiter_core::stack::sigma_clip::SigmaClipParams;
+use jupiter_core::stack::super_resolution::{super_resolve_with_progress, SuperResolutionConfig};
+use jupiter_core::stack::surface_warp::{
+    surface_warp_stack_with_diagnostics, ShiftFieldModel, SurfaceWarpConfig,
+};
+use std::path::{Path, PathBuf};
 
 #[derive(Clone, ValueEnum)]
 pub enum StackMethodArg {
     Mean,
     Median,
     SigmaClip,
+    Winsorized,
+    LinearFit,
+    Gesd,
     MultiPoint,
     Drizzle,
     SurfaceWarp,
+    OpticalFlow,
+    SuperResolution,
+}
+
+/// Per-AP stacking method for multi-point mode.
+#[derive(Clone, ValueEnum)]
+pub enum LocalMethodArg {
+    Mean,
+    Median,
+    SigmaClip,
+    Winsorized,
+    LinearFit,
+    Gesd,
+}
+
+impl LocalMethodArg {
+    pub fn to_local(&self, sigma: f32, gesd: &GesdParams) -> LocalStackMethod {
+        let iterations = SigmaClipParams::default().iterations;
+        match self {
+            Self::Mean => LocalStackMethod::Mean,
+            Self::Median => LocalStackMethod::Median,
+            Self::SigmaClip => LocalStackMethod::SigmaClip { sigma, iterations },
+            Self::Winsorized => LocalStackMethod::WinsorizedSigmaClip { sigma, iterations },
+            Self::LinearFit => LocalStackMethod::LinearFitClip { sigma, iterations },
+            Self::Gesd => LocalStackMethod::Gesd {
+                max_outliers: gesd.max_outliers,
+                alpha: gesd.alpha,
+            },
+        }
+    }
+}
+
+/// Deformation field model for surface warp.
+#[derive(Clone, ValueEnum)]
+pub enum WarpFieldArg {
+    Bilinear,
+    Spline,
+}
+
+impl WarpFieldArg {
+    pub fn to_model(&self, smoothing: f32) -> ShiftFieldModel {
+        match self {
+            Self::Bilinear => ShiftFieldModel::Bilinear,
+            Self::Spline => ShiftFieldModel::ThinPlateSpline { smoothing },
+        }
+    }
 }
 
 #[derive(Args)]
 pub struct StackArgs {
-    /// Input SER file
-    pub file: PathBuf,
+    /// Input SER file(s); several files from one session are stacked as one
+    /// sequence
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
 
     /// Percentage of best frames to keep (1-100)
     #[arg(long, default_value = "25")]
@@ -37,10 +98,30 @@ pub struct StackArgs {
     #[arg(long, value_enum, default_value = "mean")]
     pub method: StackMethodArg,
 
-    /// Sigma threshold for sigma-clip stacking
+    /// Sigma threshold for sigma-clip, winsorized and linear-fit stacking
     #[arg(long, default_value = "2.5")]
     pub sigma: f32,
 
+    /// Largest fraction of a pixel's values GESD may reject
+    #[arg(long, default_value = "0.3")]
+    pub gesd_outliers: f32,
+
+    /// Significance level of each GESD test
+    #[arg(long, default_value = "0.05")]
+    pub gesd_alpha: f32,
+
+    /// Weight frames by quality score (mean, median and rejection methods)
+    #[arg(long)]
+    pub weighted: bool,
+
+    /// Write the rejected-pixel fraction map of a rejection method to this file
+    #[arg(long)]
+    pub rejection_map: Option<PathBuf>,
+
+    /// Per-AP stacking method (multi-point mode)
+    #[arg(long, value_enum, default_value = "mean")]
+    pub local_method: LocalMethodArg,
+
     /// Alignment point size in pixels (multi-point mode)
     #[arg(long, default_value = "64")]
     pub ap_size: usize,
@@ -61,19 +142,62 @@ pub struct StackArgs {
     #[arg(long, default_value = "0.7")]
     pub pixfrac: f32,
 
+    /// Deformation field through the AP shifts (surface-warp mode)
+    #[arg(long, value_enum, default_value = "bilinear")]
+    pub warp_field: WarpFieldArg,
+
+    /// Thin-plate spline smoothing for --warp-field spline (0 = exact fit)
+    #[arg(long, default_value = "0.05")]
+    pub warp_smoothing: f32,
+
+    /// Pyramid levels for optical-flow stacking
+    #[arg(long, default_value = "3")]
+    pub flow_levels: usize,
+
+    /// Lucas–Kanade window radius in pixels for optical-flow stacking
+    #[arg(long, default_value = "7")]
+    pub flow_window: usize,
+
+    /// Refinement iterations per pyramid level for optical-flow stacking
+    #[arg(long, default_value = "3")]
+    pub flow_iterations: usize,
+
+    /// Output scale factor for super-resolution (1.0-4.0)
+    #[arg(long, default_value = "2.0")]
+    pub sr_scale: f32,
+
+    /// Back-projection iterations for super-resolution
+    #[arg(long, default_value = "20")]
+    pub sr_iterations: usize,
+
+    /// Gaussian PSF sigma in output pixels for super-resolution
+    #[arg(long, default_value = "1.0")]
+    pub sr_psf_sigma: f32,
+
+    /// Smoothness prior weight for super-resolution (0 = none)
+    #[arg(long, default_value = "0.01")]
+    pub sr_regularization: f32,
+
+    /// Write AP diagnostics (JSON, shift quiver, usage heatmap) to this
+    /// directory (multi-point and surface warp)
+    #[arg(long)]
+    pub ap_diagnostics: Option<PathBuf>,
+
     /// Output file path
     #[arg(short, long, default_value = "stacked.tiff")]
     pub output: PathBuf,
 }
 
 pub fn run(args: &StackArgs) -> Result<()> {
-    let reader = SerReader::open(&args.file)?;
+    let reader = SerReader::open_all(&args.files)?;
     let percentage = (args.select as f32 / 100.0).clamp(0.01, 1.0);
 
     match args.method {
         StackMethodArg::MultiPoint => run_multi_point(&reader, args, percentage),
         StackMethodArg::Drizzle => run_drizzle(&reader, args, percentage),
         StackMethodArg::SurfaceWarp => run_surface_warp(&reader, args, percentage),
+        StackMethodArg::OpticalFlow => run_optical_flow(&reader, args, percentage),
+        StackMethodArg::SuperResolution => run_super_resolution(&reader, args, percentage),
         _ => run_standard(&reader, args, percentage),
     }
 }
@@ -91,6 +215,7 @@ fn run_multi_point(reader: &SerReader, args: &StackArgs, percentage: f32) -> Res
         select_percentage: percentage,
         min_brightness: args.min_brightness,
         quality_metric: QualityMetric::Laplacian,
+        local_stack_method: args.local_method.to_local(args.sigma, &gesd_params(args)),
         ..Default::default()
     };
 
@@ -101,14 +226,21 @@ fn run_multi_point(reader: &SerReader, args: &StackArgs, percentage: f32) -> Res
             .progress_chars("=> "),
     );
 
-    let result = multi_point_stack(reader, &mp_config, |progress| {
-        pb.set_position((progress * 100.0) as u64);
-    })?;
+    let alignment = AlignmentConfig::default();
+    let (result, diagnostics) = multi_point_stack_with_diagnostics(
+        reader,
+        &mp_config,
+        &alignment,
+        &CpuBackend,
+        |progress| {
+            pb.set_position((progress * 100.0) as u64);
+        },
+    )?;
     pb.finish();
 
     save_image(&result, &args.output)?;
     println!("Saved to {}", args.output.display());
-    Ok(())
+    save_diagnostics(&diagnostics, &result, args.ap_diagnostics.as_deref())
 }
 
 fn run_standard(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result<()> {
@@ -123,12 +255,16 @@ fn run_standard(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result
     let keep = (total as f32 * percentage).ceil() as usize;
     let keep = keep.max(1).min(total);
     println!("Selected {} best frames (top {}%)", keep, args.select);
+    let indices: Vec<usize> = ranked.iter().take(keep).map(|(i, _)| *i).collect();
+    print_contributions(reader, &indices);
 
     let selected: Vec<_> = ranked
         .iter()
         .take(keep)
         .map(|(i, _)| frames[*i].clone())
         .collect();
+    let scores: Vec<f64> = ranked.iter().take(keep).map(|(_, s)| s.composite).collect();
+    let weights = args.weighted.then_some(scores.as_slice());
 
     let pb = ProgressBar::new(keep as u64);
     pb.set_style(
@@ -142,25 +278,46 @@ fn run_standard(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result
     })?;
     pb.finish();
 
-    let method_name = match args.method {
-        StackMethodArg::Mean => "mean",
-        StackMethodArg::Median => "median",
-        StackMethodArg::SigmaClip => "sigma-clip",
+    let method = match args.method {
+        StackMethodArg::Mean => StackMethod::Mean,
+        StackMethodArg::Median => StackMethod::Median,
+        StackMethodArg::SigmaClip => StackMethod::SigmaClip(sigma_clip_params(args)),
+        StackMethodArg::Winsorized => StackMethod::WinsorizedSigmaClip(sigma_clip_params(args)),
+        StackMethodArg::LinearFit => StackMethod::LinearFitClip(sigma_clip_params(args)),
+        StackMethodArg::Gesd => StackMethod::Gesd(gesd_params(args)),
         _ => unreachable!(),
     };
-    println!("Stacking ({})...", method_name);
-
-    let result = match args.method {
-        StackMethodArg::Mean => mean_stack(&aligned)?,
-        StackMethodArg::Median => median_stack(&aligned)?,
-        StackMethodArg::SigmaClip => {
-            let params = SigmaClipParams {
-                sigma: args.sigma,
-                ..Default::default()
-            };
-            sigma_clip_stack(&aligned, &params)?
+    println!(
+        "Stacking ({}{})...",
+        method,
+        if args.weighted {
+            ", quality-weighted"
+        } else {
+            ""
+        }
+    );
+
+    let result = match (&method, weights) {
+        (StackMethod::Mean, None) => mean_stack(&aligned)?,
+        (StackMethod::Mean, Some(weights)) => {
+            weighted_mean_stack_with_progress(&aligned, weights, |_| {})?
+        }
+        (StackMethod::Median, None) => median_stack(&aligned)?,
+        (StackMethod::Median, Some(weights)) => weighted_median_stack(&aligned, weights)?,
+        _ => {
+            let rejection = method.rejection().expect("rejection method");
+            let (result, map) = rejection_stack(&aligned, &rejection, weights)?;
+            println!(
+                "Rejected {} pixel values ({:.2}%)",
+                map.total(),
+                map.fraction() * 100.0
+            );
+            if let Some(path) = &args.rejection_map {
+                save_image(&map.to_frame(), path)?;
+                println!("Rejection map saved to {}", path.display());
+            }
+            result
         }
-        _ => unreachable!(),
     };
 
     save_image(&result, &args.output)?;
@@ -168,13 +325,114 @@ fn run_standard(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result
     Ok(())
 }
 
+/// Print how many of the `selected` frames came from each input file, when
+/// several were given.
+fn print_contributions(reader: &SerReader, selected: &[usize]) {
+    if reader.file_count() < 2 {
+        return;
+    }
+    for c in reader.file_contributions(Some(selected)) {
+        let count = c.selected.unwrap_or_default();
+        println!("  {}: {} of {} frames", c.path.display(), count, c.frames);
+    }
+}
+
+fn sigma_clip_params(args: &StackArgs) -> SigmaClipParams {
+    SigmaClipParams {
+        sigma: args.sigma,
+        ..Default::default()
+    }
+}
+
+fn gesd_params(args: &StackArgs) -> GesdParams {
+    GesdParams {
+        max_outliers: args.gesd_outliers,
+        alpha: args.gesd_alpha,
+    }
+}
+
 fn run_drizzle(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result<()> {
-    let total = reader.frame_count();
     println!(
         "Drizzle stacking {} frames (scale={}, pixfrac={})",
-        total, args.drizzle_scale, args.pixfrac
+        reader.frame_count(),
+        args.drizzle_scale,
+        args.pixfrac
     );
+    let (selected, offsets, quality_scores) = select_and_align(reader, args, percentage)?;
+
+    println!("Drizzle stacking...");
+    let drizzle_config = DrizzleConfig {
+        scale: args.drizzle_scale,
+        pixfrac: args.pixfrac,
+        quality_weighted: true,
+        ..Default::default()
+    };
+
+    let result = drizzle_stack(&selected, &offsets, &drizzle_config, Some(&quality_scores))?;
 
+    save_image(&result, &args.output)?;
+    println!(
+        "Saved {}x{} drizzle result to {}",
+        result.width(),
+        result.height(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn run_super_resolution(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result<()> {
+    println!(
+        "Super-resolution stacking {} frames (scale={}, iterations={})",
+        reader.frame_count(),
+        args.sr_scale,
+        args.sr_iterations
+    );
+    let (selected, offsets, quality_scores) = select_and_align(reader, args, percentage)?;
+
+    let sr_config = SuperResolutionConfig {
+        scale: args.sr_scale,
+        iterations: args.sr_iterations,
+        psf: PsfModel::Gaussian {
+            sigma: args.sr_psf_sigma,
+        },
+        regularization: args.sr_regularization,
+        quality_weighted: true,
+    };
+
+    let pb = ProgressBar::new(args.sr_iterations as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("Reconstructing [{bar:40}] {pos}/{len}")?
+            .progress_chars("=> "),
+    );
+    let result = super_resolve_with_progress(
+        &selected,
+        &offsets,
+        &sr_config,
+        Some(&quality_scores),
+        &CpuBackend,
+        |done| pb.set_position(done as u64),
+    )?;
+    pb.finish();
+
+    save_image(&result, &args.output)?;
+    println!(
+        "Saved {}x{} super-resolution result to {}",
+        result.width(),
+        result.height(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+/// Read, score and select the best frames, then align them to the best one.
+/// Returns the selected frames, their offsets and quality scores.
+fn select_and_align(
+    reader: &SerReader,
+    args: &StackArgs,
+    percentage: f32,
+) -> Result<(Vec<Frame>, Vec<AlignmentOffset>, Vec<f64>)> {
+    let total = reader.frame_count();
     println!("Reading {} frames...", total);
     let frames: Vec<_> = reader.frames().collect::<std::result::Result<_, _>>()?;
 
@@ -186,6 +444,7 @@ fn run_drizzle(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result<
     println!("Selected {} best frames (top {}%)", keep, args.select);
 
     let selected_indices: Vec<usize> = ranked.iter().take(keep).map(|(i, _)| *i).collect();
+    print_contributions(reader, &selected_indices);
     let selected: Vec<_> = selected_indices
         .iter()
         .map(|&i| frames[i].clone())
@@ -217,7 +476,7 @@ fn run_drizzle(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result<
         .map(|(i, frame)| {
             pb.set_position(i as u64 + 1);
             if i == 0 {
-                jupiter_core::frame::AlignmentOffset::default()
+                AlignmentOffset::default()
             } else {
                 compute_offset(reference, frame).unwrap_or_default()
             }
@@ -225,24 +484,7 @@ fn run_drizzle(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result<
         .collect();
     pb.finish();
 
-    println!("Drizzle stacking...");
-    let drizzle_config = DrizzleConfig {
-        scale: args.drizzle_scale,
-        pixfrac: args.pixfrac,
-        quality_weighted: true,
-        ..Default::default()
-    };
-
-    let result = drizzle_stack(&selected, &offsets, &drizzle_config, Some(&quality_scores))?;
-
-    save_image(&result, &args.output)?;
-    println!(
-        "Saved {}x{} drizzle result to {}",
-        result.width(),
-        result.height(),
-        args.output.display()
-    );
-    Ok(())
+    Ok((selected, offsets, quality_scores))
 }
 
 fn run_surface_warp(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result<()> {
@@ -257,6 +499,7 @@ fn run_surface_warp(reader: &SerReader, args: &StackArgs, percentage: f32) -> Re
         search_radius: args.search_radius,
         select_percentage: percentage,
         min_brightness: args.min_brightness,
+        field_model: args.warp_field.to_model(args.warp_smoothing),
         ..Default::default()
     };
 
@@ -267,7 +510,47 @@ fn run_surface_warp(reader: &SerReader, args: &StackArgs, percentage: f32) -> Re
             .progress_chars("=> "),
     );
 
-    let result = surface_warp_stack(reader, &sw_config, |progress| {
+    let alignment = AlignmentConfig::default();
+    let (result, diagnostics) = surface_warp_stack_with_diagnostics(
+        reader,
+        &sw_config,
+        &alignment,
+        &CpuBackend,
+        |progress| {
+            pb.set_position((progress * 100.0) as u64);
+        },
+    )?;
+    pb.finish();
+
+    save_image(&result, &args.output)?;
+    println!("Saved to {}", args.output.display());
+    save_diagnostics(&diagnostics, &result, args.ap_diagnostics.as_deref())
+}
+
+fn run_optical_flow(reader: &SerReader, args: &StackArgs, percentage: f32) -> Result<()> {
+    let total = reader.frame_count();
+    println!(
+        "Optical flow stacking {} frames (levels={}, window={})",
+        total, args.flow_levels, args.flow_window
+    );
+
+    let flow_config = OpticalFlowConfig {
+        select_percentage: percentage,
+        levels: args.flow_levels,
+        window_radius: args.flow_window,
+        iterations: args.flow_iterations,
+        ..Default::default()
+    };
+
+    let pb = ProgressBar::new(100);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("Optical Flow [{bar:40}] {pos}%")?
+            .progress_chars("=> "),
+    );
+
+    let alignment = AlignmentConfig::default();
+    let result = optical_flow_stack(reader, &flow_config, &alignment, &CpuBackend, |progress| {
         pb.set_position((progress * 100.0) as u64);
     })?;
     pb.finish();
@@ -276,3 +559,21 @@ fn run_surface_warp(reader: &SerReader, args: &StackArgs, percentage: f32) -> Re
     println!("Saved to {}", args.output.display());
     Ok(())
 }
+
+fn save_diagnostics(
+    diagnostics: &ApDiagnostics,
+    stacked: &Frame,
+    dir: Option<&Path>,
+) -> Result<()> {
+    let Some(dir) = dir else {
+        return Ok(());
+    };
+    diagnostics.save(dir, Some(&stacked.data))?;
+    println!(
+        "AP diagnostics ({} APs, {} rejected AP/frame pairs) saved to {}",
+        diagnostics.aps.len(),
+        diagnostics.rejected.len(),
+        dir.display()
+    );
+    Ok(())
+}
@@ -1,4 +1,5 @@
 mod commands;
+mod postprocess;
 mod summary;
 
 use anyhow::Result;
@@ -29,8 +30,10 @@ enum Commands {
     Sharpen(commands::sharpen::SharpenArgs),
     /// Apply post-processing filters to an image
     Filter(commands::filter::FilterArgs),
+    /// Combine stacked mono filter images into a color image
+    Compose(commands::compose::ComposeArgs),
     /// Run the full processing pipeline
-    Run(commands::pipeline::RunArgs),
+    Run(Box<commands::pipeline::RunArgs>),
     /// Print or save a default pipeline config as TOML
     Config(commands::config::ConfigArgs),
     /// Auto-detect planet and crop SER file
@@ -53,6 +56,7 @@ fn main() -> Result<()> {
         Commands::Stack(args) => commands::stack::run(args),
         Commands::Sharpen(args) => commands::sharpen::run(args),
         Commands::Filter(args) => commands::filter::run(args),
+        Commands::Compose(args) => commands::compose::run(args),
         Commands::Run(args) => commands::pipeline::run(args),
         Commands::Config(args) => commands::config::run(args),
         Commands::AutoCrop(args) => commands::auto_crop::run(args),
@@ -0,0 +1,65 @@
+//! Shared input/output and recipe handling for the `sharpen` and `filter`
+//! commands, which work on mono and colour images alike.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use jupiter_core::io::image_io::{
+    is_color_image, load_color_image, load_image, save_color_image, save_image,
+};
+use jupiter_core::pipeline::config::{FilterStep, PostProcessConfig};
+use jupiter_core::pipeline::{apply_filter_step, apply_filter_step_color, PipelineOutput};
+
+/// Load an image, keeping its colour channels when it has any.
+pub fn load_input(path: &Path) -> Result<PipelineOutput> {
+    let color =
+        is_color_image(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let image = if color {
+        PipelineOutput::Color(
+            load_color_image(path).with_context(|| format!("Failed to load {}", path.display()))?,
+        )
+    } else {
+        PipelineOutput::Mono(
+            load_image(path).with_context(|| format!("Failed to load {}", path.display()))?,
+        )
+    };
+
+    let (width, height) = match &image {
+        PipelineOutput::Mono(frame) => (frame.width(), frame.height()),
+        PipelineOutput::Color(color) => (color.red.width(), color.red.height()),
+    };
+    let kind = if color { "colour" } else { "mono" };
+    println!("Loaded {}x{} {} image", width, height, kind);
+    Ok(image)
+}
+
+/// Save a mono or colour image.
+pub fn save_output(image: &PipelineOutput, path: &Path) -> Result<()> {
+    match image {
+        PipelineOutput::Mono(frame) => save_image(frame, path)?,
+        PipelineOutput::Color(color) => save_color_image(color, path)?,
+    }
+    println!("Saved to {}", path.display());
+    Ok(())
+}
+
+/// Read a post-processing recipe. A full pipeline config is accepted; only
+/// its `[sharpening]` and `[[filters]]` sections are used.
+pub fn load_recipe(path: &Path) -> Result<PostProcessConfig> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read recipe {}", path.display()))?;
+    toml::from_str(&contents).context("Invalid post-processing recipe")
+}
+
+/// Apply filter steps in order, per channel for colour images.
+pub fn apply_filters(image: PipelineOutput, steps: &[FilterStep]) -> PipelineOutput {
+    steps.iter().fold(image, |image, step| {
+        println!("Applying {step}");
+        match image {
+            PipelineOutput::Mono(frame) => PipelineOutput::Mono(apply_filter_step(&frame, step)),
+            PipelineOutput::Color(color) => {
+                PipelineOutput::Color(apply_filter_step_color(&color, step))
+            }
+        }
+    })
+}
@@ -2,6 +2,7 @@ use console::Style;
 use jupiter_core::pipeline::config::{DeconvolutionConfig, PipelineConfig, StackMethod};
 use jupiter_core::sharpen::wavelet::WaveletParams;
 use jupiter_core::stack::multi_point::MultiPointConfig;
+use jupiter_core::stack::rejection::GesdParams;
 use jupiter_core::stack::sigma_clip::SigmaClipParams;
 
 struct Styles {
@@ -42,6 +43,9 @@ pub fn print_pipeline_summary(config: &PipelineConfig, device_name: &str) {
         s.label.apply_to("Input"),
         s.path.apply_to(config.input.display())
     );
+    for extra in &config.extra_inputs {
+        println!("  {:<14}{}", "", s.path.apply_to(extra.display()));
+    }
     println!(
         "  {:<14}{}",
         s.label.apply_to("Output"),
@@ -76,14 +80,22 @@ pub fn print_pipeline_summary(config: &PipelineConfig, device_name: &str) {
         s.label.apply_to("Metric"),
         s.value.apply_to(&config.frame_selection.metric)
     );
-    println!(
-        "    {:<12}{}",
-        s.label.apply_to("Keep"),
-        s.value.apply_to(format!(
-            "{:.0}%",
-            config.frame_selection.select_percentage * 100.0
-        ))
-    );
+    if let Some(ref list) = config.frame_selection.frame_list {
+        println!(
+            "    {:<12}{}",
+            s.label.apply_to("Frame list"),
+            s.value.apply_to(list.display())
+        );
+    } else {
+        println!(
+            "    {:<12}{}",
+            s.label.apply_to("Keep"),
+            s.value.apply_to(format!(
+                "{:.0}%",
+                config.frame_selection.select_percentage * 100.0
+            ))
+        );
+    }
     println!();
 
     // Alignment
@@ -93,6 +105,27 @@ pub fn print_pipeline_summary(config: &PipelineConfig, device_name: &str) {
         s.label.apply_to("Method"),
         s.method.apply_to(&config.alignment.method)
     );
+    if let Some(ref refine) = config.alignment.refinement {
+        println!(
+            "    {:<12}{}",
+            s.label.apply_to("Reference"),
+            s.value.apply_to(format!(
+                "stacked top {:.0}%, up to {} passes",
+                refine.top_fraction * 100.0,
+                refine.max_passes
+            ))
+        );
+    }
+    if let Some(ref reject) = config.alignment.rejection {
+        println!(
+            "    {:<12}{}",
+            s.label.apply_to("Outliers"),
+            s.value.apply_to(format!(
+                "confidence < {:.1} or drift > {:.1} px rejected",
+                reject.min_confidence, reject.max_drift
+            ))
+        );
+    }
     println!();
 
     // Stacking
@@ -103,6 +136,13 @@ pub fn print_pipeline_summary(config: &PipelineConfig, device_name: &str) {
         s.method.apply_to(&config.stacking.method)
     );
     print_stack_sub_params(&s, &config.stacking.method);
+    if config.stacking.quality_weighted {
+        println!(
+            "    {:<12}{}",
+            s.label.apply_to("Weighted"),
+            s.value.apply_to("quality score")
+        );
+    }
     println!();
 
     // Sharpening
@@ -164,6 +204,26 @@ fn print_sharpening_section(
         s.label.apply_to("Coefficients"),
         params.coefficients
     );
+    println!(
+        "    {:<14}{}",
+        s.label.apply_to("Scales"),
+        s.value
+            .apply_to(format!("{}, {}", params.kernel, params.scaling))
+    );
+    if params.edge_protection > 0.0 {
+        println!(
+            "    {:<14}{}",
+            s.label.apply_to("Edge protect"),
+            s.value.apply_to(params.edge_protection)
+        );
+    }
+    if params.linked_luminance {
+        println!(
+            "    {:<14}{}",
+            s.label.apply_to("Colour"),
+            s.value.apply_to("luminance only")
+        );
+    }
     if params.denoise.is_empty() {
         println!(
             "    {:<14}{}",
@@ -176,6 +236,11 @@ fn print_sharpening_section(
             s.label.apply_to("Denoise"),
             params.denoise
         );
+        println!(
+            "    {:<14}{}",
+            s.label.apply_to("Denoise mode"),
+            s.value.apply_to(params.denoise_mode)
+        );
     }
     println!();
 
@@ -192,6 +257,14 @@ fn print_sharpening_section(
             s.label.apply_to("PSF"),
             s.value.apply_to(&deconv.psf)
         );
+        if let Some(tiling) = &deconv.tiling {
+            println!(
+                "    {:<14}{}",
+                s.label.apply_to("Tiles"),
+                s.value
+                    .apply_to(format!("{} px, PSF {}", tiling.tile_size, tiling.psf))
+            );
+        }
         println!();
     } else {
         println!(
@@ -205,7 +278,9 @@ fn print_sharpening_section(
 
 fn print_stack_sub_params(s: &Styles, method: &StackMethod) {
     match method {
-        StackMethod::SigmaClip(SigmaClipParams { sigma, iterations }) => {
+        StackMethod::SigmaClip(SigmaClipParams { sigma, iterations })
+        | StackMethod::WinsorizedSigmaClip(SigmaClipParams { sigma, iterations })
+        | StackMethod::LinearFitClip(SigmaClipParams { sigma, iterations }) => {
             println!(
                 "    {:<12}{}",
                 s.label.apply_to("Sigma"),
@@ -217,6 +292,21 @@ fn print_stack_sub_params(s: &Styles, method: &StackMethod) {
                 s.value.apply_to(iterations)
             );
         }
+        StackMethod::Gesd(GesdParams {
+            max_outliers,
+            alpha,
+        }) => {
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Max Reject"),
+                s.value.apply_to(format!("{:.0}%", max_outliers * 100.0))
+            );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Alpha"),
+                s.value.apply_to(alpha)
+            );
+        }
         StackMethod::MultiPoint(MultiPointConfig {
             ap_size,
             search_radius,
@@ -224,6 +314,8 @@ fn print_stack_sub_params(s: &Styles, method: &StackMethod) {
             min_brightness,
             quality_metric,
             local_stack_method,
+            placement,
+            ..
         }) => {
             println!(
                 "    {:<12}{}",
@@ -256,6 +348,11 @@ fn print_stack_sub_params(s: &Styles, method: &StackMethod) {
                 s.label.apply_to("Local Stack"),
                 s.method.apply_to(local_stack_method)
             );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Placement"),
+                s.method.apply_to(placement)
+            );
         }
         StackMethod::Drizzle(cfg) => {
             println!(
@@ -302,6 +399,67 @@ fn print_stack_sub_params(s: &Styles, method: &StackMethod) {
                 s.label.apply_to("Min Bright"),
                 s.value.apply_to(cfg.min_brightness)
             );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Field"),
+                s.method.apply_to(&cfg.field_model)
+            );
+        }
+        StackMethod::OpticalFlow(cfg) => {
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Levels"),
+                s.value.apply_to(cfg.levels)
+            );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Window"),
+                s.value.apply_to(format!("{} px", cfg.window_radius))
+            );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Iterations"),
+                s.value.apply_to(cfg.iterations)
+            );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Smoothing"),
+                s.value.apply_to(cfg.smoothing)
+            );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Select"),
+                s.value
+                    .apply_to(format!("{}%", (cfg.select_percentage * 100.0) as u32))
+            );
+        }
+        StackMethod::SuperResolution(cfg) => {
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Scale"),
+                s.value.apply_to(format!("{}x", cfg.scale))
+            );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Iterations"),
+                s.value.apply_to(cfg.iterations)
+            );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("PSF"),
+                s.value.apply_to(&cfg.psf)
+            );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Prior"),
+                s.value.apply_to(cfg.regularization)
+            );
+            println!(
+                "    {:<12}{}",
+                s.label.apply_to("Weighted"),
+                s.value
+                    .apply_to(if cfg.quality_weighted { "yes" } else { "no" })
+            );
         }
         _ => {}
     }
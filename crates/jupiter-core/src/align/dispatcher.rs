@@ -1,45 +1,118 @@
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
+use ndarray::Array2;
 use rayon::prelude::*;
+use tracing::debug;
 
 use crate::compute::ComputeBackend;
 use crate::consts::PARALLEL_FRAME_THRESHOLD;
 use crate::error::{JupiterError, Result};
 use crate::frame::{AlignmentOffset, Frame};
 use crate::io::ser::SerReader;
-use crate::pipeline::config::{AlignmentConfig, AlignmentMethod};
+use crate::pipeline::config::{AlignmentConfig, AlignmentMethod, ReferenceRefinement};
+use crate::stack::reference::mean_of_shifted;
 
 use super::phase_correlation;
-use super::{centroid, enhanced_phase, gradient_correlation, pyramid, shift_frame};
+use super::{centroid, enhanced_phase, gradient_correlation, limb, pyramid, shift_frame};
 
 /// Compute alignment offset between two arrays using the configured method.
+///
+/// For many frames against one reference, use [`ReferenceAligner`].
 pub fn compute_offset_configured(
     reference: &ndarray::Array2<f32>,
     target: &ndarray::Array2<f32>,
     config: &AlignmentConfig,
     backend: &dyn ComputeBackend,
 ) -> Result<AlignmentOffset> {
-    match &config.method {
-        AlignmentMethod::PhaseCorrelation => {
-            if backend.is_gpu() {
-                phase_correlation::compute_offset_gpu(reference, target, backend)
-            } else {
-                phase_correlation::compute_offset_array(reference, target)
-            }
-        }
-        AlignmentMethod::EnhancedPhaseCorrelation(params) => {
-            enhanced_phase::compute_offset_enhanced(reference, target, params, backend)
-        }
-        AlignmentMethod::Centroid(params) => {
-            centroid::compute_offset_centroid(reference, target, params)
+    ReferenceAligner::new(reference, config, backend)?.offset(target)
+}
+
+/// Configured alignment against one fixed reference.
+///
+/// Methods that analyse the reference on its own (the limb fit) do so once
+/// here rather than once per frame.
+pub struct ReferenceAligner<'a> {
+    reference: &'a Array2<f32>,
+    config: &'a AlignmentConfig,
+    backend: &'a dyn ComputeBackend,
+    limb: Option<limb::LimbAligner<'a>>,
+}
+
+impl<'a> ReferenceAligner<'a> {
+    pub fn new(
+        reference: &'a Array2<f32>,
+        config: &'a AlignmentConfig,
+        backend: &'a dyn ComputeBackend,
+    ) -> Result<Self> {
+        let limb = match &config.method {
+            AlignmentMethod::LimbFit(params) => Some(limb::LimbAligner::new(reference, params)?),
+            _ => None,
+        };
+        Ok(Self {
+            reference,
+            config,
+            backend,
+            limb,
+        })
+    }
+
+    /// Offset that aligns `target` with the reference.
+    pub fn offset(&self, target: &Array2<f32>) -> Result<AlignmentOffset> {
+        if let Some(limb) = &self.limb {
+            return limb.offset(target);
         }
-        AlignmentMethod::GradientCorrelation => {
-            gradient_correlation::compute_offset_gradient(reference, target, backend)
+        let (reference, backend) = (self.reference, self.backend);
+        match &self.config.method {
+            AlignmentMethod::PhaseCorrelation => {
+                if backend.is_gpu() {
+                    phase_correlation::compute_offset_gpu(reference, target, backend)
+                } else {
+                    phase_correlation::compute_offset_array(reference, target)
+                }
+            }
+            AlignmentMethod::EnhancedPhaseCorrelation(params) => {
+                enhanced_phase::compute_offset_enhanced(reference, target, params, backend)
+            }
+            AlignmentMethod::Centroid(params) => {
+                centroid::compute_offset_centroid(reference, target, params)
+            }
+            AlignmentMethod::GradientCorrelation => {
+                gradient_correlation::compute_offset_gradient(reference, target, backend)
+            }
+            AlignmentMethod::Pyramid(params) => {
+                pyramid::compute_offset_pyramid(reference, target, params, backend)
+            }
+            // `new` fits the reference limb once and `self.limb` answers above.
+            AlignmentMethod::LimbFit(_) => unreachable!("limb fit uses the limb aligner"),
         }
-        AlignmentMethod::Pyramid(params) => {
-            pyramid::compute_offset_pyramid(reference, target, params, backend)
+    }
+
+    /// Registration confidence of `target` aligned with `offset`: the
+    /// correlation peak-to-mean ratio left after the shift.
+    ///
+    /// `None` for the centroid and limb fit, which do not register by
+    /// correlation and so have no peak to judge.
+    pub fn confidence(
+        &self,
+        target: &Array2<f32>,
+        offset: &AlignmentOffset,
+    ) -> Result<Option<f64>> {
+        if matches!(
+            self.config.method,
+            AlignmentMethod::Centroid(_) | AlignmentMethod::LimbFit(_)
+        ) {
+            return Ok(None);
         }
+        let (_, confidence) = if offset.is_zero() {
+            phase_correlation::compute_offset_with_confidence(self.reference, target)?
+        } else {
+            phase_correlation::compute_offset_with_confidence(
+                self.reference,
+                &phase_correlation::shift_array(target, offset),
+            )?
+        };
+        Ok(Some(confidence))
     }
 }
 
@@ -54,115 +127,109 @@ pub fn align_frames_configured_with_progress<F>(
 where
     F: Fn(usize) + Send + Sync,
 {
-    if frames.is_empty() {
-        return Err(JupiterError::EmptySequence);
-    }
-
-    let reference = &frames[reference_idx];
-    let counter = AtomicUsize::new(0);
-
-    if frames.len() >= PARALLEL_FRAME_THRESHOLD {
-        align_frames_configured_parallel(
-            frames,
-            reference,
-            reference_idx,
-            config,
-            backend,
-            &counter,
-            &on_frame_done,
-        )
-    } else {
-        align_frames_configured_sequential(
-            frames,
-            reference,
-            reference_idx,
-            config,
-            backend,
-            &counter,
-            &on_frame_done,
-        )
-    }
+    let offsets = compute_offsets_configured_with_progress(
+        frames,
+        reference_idx,
+        config,
+        backend.clone(),
+        on_frame_done,
+    )?;
+    Ok(apply_offsets(frames, &offsets, backend.as_ref()))
 }
 
-fn align_frames_configured_parallel<F>(
+/// Compute offsets of in-memory frames against `frames[reference_idx]`
+/// using the configured method. The reference gets a zero offset.
+///
+/// With [`AlignmentConfig::refinement`] set, `frames` must be ordered best
+/// first and the offsets are then refined against a stacked reference, so
+/// the reference frame itself may end up with a small non-zero offset.
+pub fn compute_offsets_configured_with_progress<F>(
     frames: &[Frame],
-    reference: &Frame,
     reference_idx: usize,
     config: &AlignmentConfig,
     backend: Arc<dyn ComputeBackend>,
-    counter: &AtomicUsize,
-    on_frame_done: &F,
-) -> Result<Vec<Frame>>
+    on_frame_done: F,
+) -> Result<Vec<AlignmentOffset>>
 where
     F: Fn(usize) + Send + Sync,
 {
-    let results: Vec<Result<Frame>> = frames
-        .par_iter()
-        .enumerate()
-        .map(|(i, frame)| {
-            let result = if i == reference_idx {
-                Ok(frame.clone())
-            } else {
-                let offset = compute_offset_configured(
-                    &reference.data,
-                    &frame.data,
-                    config,
-                    backend.as_ref(),
-                )?;
-                if backend.is_gpu() {
-                    let shifted_buf =
-                        backend.shift_bilinear(&backend.upload(&frame.data), offset.dx, offset.dy);
-                    let shifted_data = backend.download(&shifted_buf);
-                    Ok(Frame::new(shifted_data, frame.original_bit_depth))
-                } else {
-                    Ok(shift_frame(frame, &offset))
-                }
-            };
-            let done = counter.fetch_add(1, Ordering::Relaxed) + 1;
-            on_frame_done(done);
-            result
-        })
-        .collect();
-    results.into_iter().collect()
-}
+    if frames.is_empty() {
+        return Err(JupiterError::EmptySequence);
+    }
 
-fn align_frames_configured_sequential<F>(
-    frames: &[Frame],
-    reference: &Frame,
-    reference_idx: usize,
-    config: &AlignmentConfig,
-    backend: Arc<dyn ComputeBackend>,
-    counter: &AtomicUsize,
-    on_frame_done: &F,
-) -> Result<Vec<Frame>>
-where
-    F: Fn(usize) + Send + Sync,
-{
-    let mut aligned = Vec::with_capacity(frames.len());
-    for (i, frame) in frames.iter().enumerate() {
-        let result = if i == reference_idx {
-            frame.clone()
+    let aligner = ReferenceAligner::new(&frames[reference_idx].data, config, backend.as_ref())?;
+    let counter = AtomicUsize::new(0);
+    let offset_for = |i: usize, frame: &Frame| -> Result<AlignmentOffset> {
+        let offset = if i == reference_idx {
+            AlignmentOffset::default()
         } else {
-            let offset =
-                compute_offset_configured(&reference.data, &frame.data, config, backend.as_ref())?;
-            if backend.is_gpu() {
-                let shifted_buf =
-                    backend.shift_bilinear(&backend.upload(&frame.data), offset.dx, offset.dy);
-                let shifted_data = backend.download(&shifted_buf);
-                Frame::new(shifted_data, frame.original_bit_depth)
-            } else {
-                shift_frame(frame, &offset)
-            }
+            aligner.offset(&frame.data)?
         };
-        aligned.push(result);
         let done = counter.fetch_add(1, Ordering::Relaxed) + 1;
         on_frame_done(done);
+        Ok(offset)
+    };
+
+    let offsets: Vec<AlignmentOffset> = if frames.len() >= PARALLEL_FRAME_THRESHOLD {
+        frames
+            .par_iter()
+            .enumerate()
+            .map(|(i, frame)| offset_for(i, frame))
+            .collect::<Result<_>>()?
+    } else {
+        frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| offset_for(i, frame))
+            .collect::<Result<_>>()?
+    };
+
+    match config.refinement {
+        Some(ref refinement) => refine_offsets(
+            offsets,
+            |k| Ok(frames[k].data.clone()),
+            config,
+            refinement,
+            backend.as_ref(),
+        ),
+        None => Ok(offsets),
+    }
+}
+
+/// Shift every frame by its offset (frames with a zero offset are copied as-is).
+pub fn apply_offsets(
+    frames: &[Frame],
+    offsets: &[AlignmentOffset],
+    backend: &dyn ComputeBackend,
+) -> Vec<Frame> {
+    let shift = |(frame, offset): (&Frame, &AlignmentOffset)| {
+        if offset.is_zero() {
+            frame.clone()
+        } else if backend.is_gpu() {
+            let shifted_buf =
+                backend.shift_bilinear(&backend.upload(&frame.data), offset.dx, offset.dy);
+            Frame::new(backend.download(&shifted_buf), frame.original_bit_depth)
+        } else {
+            shift_frame(frame, offset)
+        }
+    };
+
+    if frames.len() >= PARALLEL_FRAME_THRESHOLD {
+        frames
+            .par_iter()
+            .zip(offsets.par_iter())
+            .map(shift)
+            .collect()
+    } else {
+        frames.iter().zip(offsets).map(shift).collect()
     }
-    Ok(aligned)
 }
 
 /// Compute alignment offsets by streaming frames from the SER reader,
 /// using the configured alignment method.
+///
+/// With [`AlignmentConfig::refinement`] set, `frame_indices` must be ordered
+/// best first; refinement passes re-read the frames from disk.
 pub fn compute_offsets_streaming_configured<F>(
     reader: &SerReader,
     frame_indices: &[usize],
@@ -179,91 +246,93 @@ where
     }
 
     let reference = reader.read_frame(frame_indices[reference_idx])?;
+    let aligner = ReferenceAligner::new(&reference.data, config, backend.as_ref())?;
     let counter = AtomicUsize::new(0);
-    let ctx = StreamingOffsetCtx {
-        reader,
-        reference_idx,
-        reference: &reference,
-        config,
-        backend,
-        counter: &counter,
+    let offset_for = |i: usize, frame_idx: usize| -> Result<AlignmentOffset> {
+        let offset = if i == reference_idx {
+            AlignmentOffset::default()
+        } else {
+            aligner.offset(&reader.read_frame(frame_idx)?.data)?
+        };
+        let done = counter.fetch_add(1, Ordering::Relaxed) + 1;
+        on_frame_done(done);
+        Ok(offset)
     };
 
-    let results: Vec<Result<AlignmentOffset>> = if frame_indices.len() >= PARALLEL_FRAME_THRESHOLD {
-        compute_offsets_streaming_configured_parallel(&ctx, frame_indices, &on_frame_done)
+    let offsets: Vec<AlignmentOffset> = if frame_indices.len() >= PARALLEL_FRAME_THRESHOLD {
+        frame_indices
+            .par_iter()
+            .enumerate()
+            .map(|(i, &frame_idx)| offset_for(i, frame_idx))
+            .collect::<Result<_>>()?
     } else {
-        compute_offsets_streaming_configured_sequential(&ctx, frame_indices, &on_frame_done)
+        frame_indices
+            .iter()
+            .enumerate()
+            .map(|(i, &frame_idx)| offset_for(i, frame_idx))
+            .collect::<Result<_>>()?
     };
 
-    results.into_iter().collect()
-}
-
-/// Shared context for streaming offset computation helpers.
-struct StreamingOffsetCtx<'a> {
-    reader: &'a SerReader,
-    reference_idx: usize,
-    reference: &'a Frame,
-    config: &'a AlignmentConfig,
-    backend: Arc<dyn ComputeBackend>,
-    counter: &'a AtomicUsize,
+    match config.refinement {
+        Some(ref refinement) => refine_offsets(
+            offsets,
+            |k| Ok(reader.read_frame(frame_indices[k])?.data),
+            config,
+            refinement,
+            backend.as_ref(),
+        ),
+        None => Ok(offsets),
+    }
 }
 
-fn compute_offsets_streaming_configured_parallel<F>(
-    ctx: &StreamingOffsetCtx<'_>,
-    frame_indices: &[usize],
-    on_frame_done: &F,
-) -> Vec<Result<AlignmentOffset>>
+/// Iteratively re-align frames against a mean of the best aligned frames.
+///
+/// `read(k)` yields the data of frame `k`; frames are assumed best first, so
+/// the reference is built from the leading `top_fraction` of them. The
+/// returned offsets stay in the coordinate frame of the initial reference.
+pub fn refine_offsets<R>(
+    mut offsets: Vec<AlignmentOffset>,
+    read: R,
+    config: &AlignmentConfig,
+    refinement: &ReferenceRefinement,
+    backend: &dyn ComputeBackend,
+) -> Result<Vec<AlignmentOffset>>
 where
-    F: Fn(usize) + Send + Sync,
+    R: Fn(usize) -> Result<Array2<f32>> + Sync,
 {
-    frame_indices
-        .par_iter()
-        .enumerate()
-        .map(|(i, &frame_idx)| {
-            let offset = if i == ctx.reference_idx {
-                AlignmentOffset::default()
-            } else {
-                let target = ctx.reader.read_frame(frame_idx)?;
-                compute_offset_configured(
-                    &ctx.reference.data,
-                    &target.data,
-                    ctx.config,
-                    ctx.backend.as_ref(),
-                )?
-            };
-            let done = ctx.counter.fetch_add(1, Ordering::Relaxed) + 1;
-            on_frame_done(done);
-            Ok(offset)
-        })
-        .collect()
-}
+    let count = offsets.len();
+    if count < 2 {
+        return Ok(offsets);
+    }
+    let top = ((count as f32 * refinement.top_fraction).ceil() as usize).clamp(1, count);
 
-fn compute_offsets_streaming_configured_sequential<F>(
-    ctx: &StreamingOffsetCtx<'_>,
-    frame_indices: &[usize],
-    on_frame_done: &F,
-) -> Vec<Result<AlignmentOffset>>
-where
-    F: Fn(usize) + Send + Sync,
-{
-    frame_indices
-        .iter()
-        .enumerate()
-        .map(|(i, &frame_idx)| {
-            let offset = if i == ctx.reference_idx {
-                AlignmentOffset::default()
-            } else {
-                let target = ctx.reader.read_frame(frame_idx)?;
-                compute_offset_configured(
-                    &ctx.reference.data,
-                    &target.data,
-                    ctx.config,
-                    ctx.backend.as_ref(),
-                )?
-            };
-            let done = ctx.counter.fetch_add(1, Ordering::Relaxed) + 1;
-            on_frame_done(done);
-            Ok(offset)
-        })
-        .collect()
+    for pass in 1..=refinement.max_passes.max(1) {
+        let reference = mean_of_shifted(&offsets[..top], &read)?;
+        let aligner = ReferenceAligner::new(&reference, config, backend)?;
+        let realign = |k: usize| -> Result<AlignmentOffset> { aligner.offset(&read(k)?) };
+        let refined: Vec<AlignmentOffset> = if count >= PARALLEL_FRAME_THRESHOLD {
+            (0..count)
+                .into_par_iter()
+                .map(realign)
+                .collect::<Result<_>>()?
+        } else {
+            (0..count).map(realign).collect::<Result<_>>()?
+        };
+
+        let max_change = offsets
+            .iter()
+            .zip(&refined)
+            .map(|(a, b)| (a.dx - b.dx).hypot(a.dy - b.dy))
+            .fold(0.0f64, f64::max);
+        offsets = refined;
+        debug!(
+            pass,
+            top, max_change, "Refined alignment against stacked reference"
+        );
+        if max_change < refinement.convergence {
+            break;
+        }
+    }
+
+    Ok(offsets)
 }
@@ -0,0 +1,536 @@
+//! Limb-fit alignment for crescent and featureless disks.
+//!
+//! Phase correlation has little texture to lock on to on Venus or Mercury, and
+//! the centroid of a crescent is pulled towards its lit side. The limb edge,
+//! though, is sharp and lies on a circle centred on the true disk centre.
+//!
+//! Edge points are found with Sobel gradients on a lightly blurred frame,
+//! thinned by non-maximum suppression along the gradient and refined to
+//! sub-pixel accuracy with a parabola through the gradient magnitude. A circle
+//! (or, for oblate disks, an ellipse) is fitted to them with RANSAC, which
+//! ignores the softer terminator and stray bright pixels, then refined by
+//! least squares on the inliers.
+//!
+//! When aligning a sequence the reference limb is fitted once
+//! ([`LimbAligner`]). A frame whose limb cannot be fitted falls back to phase
+//! correlation against the reference rather than failing the whole run.
+
+use std::f64::consts::FRAC_PI_2;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ndarray::Array2;
+use tracing::{debug, warn};
+
+use crate::consts::{LIMB_BLUR_SIGMA, LIMB_MAX_EDGE_POINTS};
+use crate::error::{JupiterError, Result};
+use crate::filters::gaussian_blur::gaussian_blur_array;
+use crate::frame::AlignmentOffset;
+use crate::pipeline::config::{LimbFitConfig, LimbModel};
+
+use super::phase_correlation::{bilinear_sample, compute_offset_array};
+
+/// Circle fitted to the limb of a disk.
+#[derive(Clone, Debug)]
+pub struct LimbCircle {
+    pub center_y: f64,
+    pub center_x: f64,
+    pub radius: f64,
+    /// Number of edge points within the inlier tolerance of the circle.
+    pub inliers: usize,
+}
+
+/// Ellipse fitted to the limb of an oblate disk.
+#[derive(Clone, Debug)]
+pub struct LimbEllipse {
+    pub center_y: f64,
+    pub center_x: f64,
+    pub semi_major: f64,
+    pub semi_minor: f64,
+    /// Angle of the major axis from the x (column) axis, in radians.
+    pub angle: f64,
+    /// Number of edge points within the inlier tolerance of the ellipse.
+    pub inliers: usize,
+}
+
+/// Limb-fit alignment against one reference frame, whose limb is fitted once.
+///
+/// Frames that fall back to phase correlation are counted and reported in
+/// one warning when the aligner is dropped.
+pub struct LimbAligner<'a> {
+    reference: &'a Array2<f32>,
+    center: (f64, f64),
+    config: &'a LimbFitConfig,
+    fallbacks: AtomicUsize,
+}
+
+impl<'a> LimbAligner<'a> {
+    /// Fit the reference limb. Fails if the reference has no usable limb.
+    pub fn new(reference: &'a Array2<f32>, config: &'a LimbFitConfig) -> Result<Self> {
+        let center = limb_center(reference, config)?;
+        Ok(Self {
+            reference,
+            center,
+            config,
+            fallbacks: AtomicUsize::new(0),
+        })
+    }
+
+    /// Offset that moves the limb centre of `target` onto the reference's.
+    ///
+    /// If the target limb cannot be fitted (a blank or clouded frame, too few
+    /// edge points), the offset comes from phase correlation instead.
+    pub fn offset(&self, target: &Array2<f32>) -> Result<AlignmentOffset> {
+        match limb_center(target, self.config) {
+            Ok((cy, cx)) => Ok(AlignmentOffset {
+                dx: self.center.1 - cx,
+                dy: self.center.0 - cy,
+            }),
+            Err(e) => {
+                debug!("{e}; using phase correlation for this frame");
+                self.fallbacks.fetch_add(1, Ordering::Relaxed);
+                compute_offset_array(self.reference, target)
+            }
+        }
+    }
+}
+
+impl Drop for LimbAligner<'_> {
+    fn drop(&mut self) {
+        let fallbacks = *self.fallbacks.get_mut();
+        if fallbacks > 0 {
+            warn!(
+                frames = fallbacks,
+                "Limb fit failed on some frames; they were aligned by phase correlation"
+            );
+        }
+    }
+}
+
+/// Compute alignment offset between two images from their fitted limbs.
+///
+/// Fits the reference on every call; use [`LimbAligner`] for a sequence.
+pub fn compute_offset_limb(
+    reference: &Array2<f32>,
+    target: &Array2<f32>,
+    config: &LimbFitConfig,
+) -> Result<AlignmentOffset> {
+    LimbAligner::new(reference, config)?.offset(target)
+}
+
+/// Disk centre `(y, x)` from the configured limb model.
+fn limb_center(data: &Array2<f32>, config: &LimbFitConfig) -> Result<(f64, f64)> {
+    match config.model {
+        LimbModel::Circle => fit_limb(data, config).map(|c| (c.center_y, c.center_x)),
+        LimbModel::Ellipse => fit_limb_ellipse(data, config).map(|e| (e.center_y, e.center_x)),
+    }
+}
+
+/// Fit a circle to the bright limb of a disk in `data`.
+pub fn fit_limb(data: &Array2<f32>, config: &LimbFitConfig) -> Result<LimbCircle> {
+    let points = limb_edge_points(data, config.edge_threshold);
+    if points.len() < 3 {
+        return Err(JupiterError::Pipeline(format!(
+            "Limb fit failed: only {} edge points found",
+            points.len()
+        )));
+    }
+
+    let (h, w) = data.dim();
+    let max_radius = h.max(w) as f64;
+    let tolerance = config.inlier_tolerance;
+    let inliers_of = |c: &Circle| -> Vec<(f64, f64)> {
+        points
+            .iter()
+            .filter(|&&(y, x)| ((y - c.cy).hypot(x - c.cx) - c.r).abs() <= tolerance)
+            .copied()
+            .collect()
+    };
+
+    // RANSAC over circles through three edge points.
+    let mut rng = Lcg(0x9e37_79b9_7f4a_7c15);
+    let mut best: Option<(Circle, usize)> = None;
+    for _ in 0..config.ransac_iterations.max(1) {
+        let a = points[rng.below(points.len())];
+        let b = points[rng.below(points.len())];
+        let c = points[rng.below(points.len())];
+        let Some(circle) = circumcircle(a, b, c).filter(|c| c.r < max_radius) else {
+            continue;
+        };
+        let count = inliers_of(&circle).len();
+        if best.as_ref().is_none_or(|(_, n)| count > *n) {
+            best = Some((circle, count));
+        }
+    }
+    let Some((mut circle, _)) = best else {
+        return Err(JupiterError::Pipeline(
+            "Limb fit failed: edge points are collinear".into(),
+        ));
+    };
+
+    // Least-squares refinement on the consensus set (twice, so points that
+    // move in or out of tolerance with the refined circle are accounted for).
+    for _ in 0..2 {
+        let inliers = inliers_of(&circle);
+        match least_squares_circle(&inliers) {
+            Some(refined) => circle = refined,
+            None => break,
+        }
+    }
+
+    Ok(LimbCircle {
+        center_y: circle.cy,
+        center_x: circle.cx,
+        radius: circle.r,
+        inliers: inliers_of(&circle).len(),
+    })
+}
+
+/// Fit an ellipse to the bright limb of a disk in `data`.
+///
+/// For oblate planets such as Jupiter and Saturn, where a circle fitted to a
+/// partly lit limb is pulled off the true centre.
+pub fn fit_limb_ellipse(data: &Array2<f32>, config: &LimbFitConfig) -> Result<LimbEllipse> {
+    let points = limb_edge_points(data, config.edge_threshold);
+    if points.len() < 5 {
+        return Err(JupiterError::Pipeline(format!(
+            "Limb fit failed: only {} edge points found",
+            points.len()
+        )));
+    }
+
+    // Fit in coordinates centred on the points and scaled to unit RMS radius,
+    // which keeps the conic equations well conditioned.
+    let n = points.len() as f64;
+    let my = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mx = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let scale = (points
+        .iter()
+        .map(|&(y, x)| (y - my).powi(2) + (x - mx).powi(2))
+        .sum::<f64>()
+        / n)
+        .sqrt();
+    if scale <= 0.0 {
+        return Err(JupiterError::Pipeline(
+            "Limb fit failed: edge points coincide".into(),
+        ));
+    }
+    let points: Vec<(f64, f64)> = points
+        .iter()
+        .map(|&(y, x)| ((y - my) / scale, (x - mx) / scale))
+        .collect();
+
+    let (h, w) = data.dim();
+    let max_axis = h.max(w) as f64 / scale;
+    let tolerance = config.inlier_tolerance / scale;
+    let inliers_of = |c: &Conic| -> Vec<(f64, f64)> {
+        points
+            .iter()
+            .filter(|&&p| c.sampson_distance(p) <= tolerance)
+            .copied()
+            .collect()
+    };
+    let shape_of = |c: &Conic| c.ellipse().filter(|e| e.semi_major < max_axis);
+
+    // RANSAC over conics through five edge points.
+    let mut rng = Lcg(0x9e37_79b9_7f4a_7c15);
+    let mut best: Option<(Conic, usize)> = None;
+    for _ in 0..config.ransac_iterations.max(1) {
+        let sample: [(f64, f64); 5] = std::array::from_fn(|_| points[rng.below(points.len())]);
+        let Some(conic) = Conic::fit(&sample).filter(|c| shape_of(c).is_some()) else {
+            continue;
+        };
+        let count = inliers_of(&conic).len();
+        if best.as_ref().is_none_or(|(_, n)| count > *n) {
+            best = Some((conic, count));
+        }
+    }
+    let Some((mut conic, _)) = best else {
+        return Err(JupiterError::Pipeline(
+            "Limb fit failed: no ellipse through the edge points".into(),
+        ));
+    };
+
+    for _ in 0..2 {
+        let inliers = inliers_of(&conic);
+        match Conic::fit(&inliers).filter(|c| shape_of(c).is_some()) {
+            Some(refined) => conic = refined,
+            None => break,
+        }
+    }
+
+    let ellipse = shape_of(&conic).expect("conic was checked to be an ellipse");
+    Ok(LimbEllipse {
+        center_y: my + ellipse.cy * scale,
+        center_x: mx + ellipse.cx * scale,
+        semi_major: ellipse.semi_major * scale,
+        semi_minor: ellipse.semi_minor * scale,
+        angle: ellipse.angle,
+        inliers: inliers_of(&conic).len(),
+    })
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Circle {
+    cy: f64,
+    cx: f64,
+    r: f64,
+}
+
+/// Sub-pixel edge points `(y, x)` whose gradient is at least `threshold`
+/// times the strongest gradient in the frame.
+fn limb_edge_points(data: &Array2<f32>, threshold: f32) -> Vec<(f64, f64)> {
+    let (h, w) = data.dim();
+    if h < 3 || w < 3 {
+        return Vec::new();
+    }
+    let blurred = gaussian_blur_array(data, LIMB_BLUR_SIGMA);
+
+    let mut gy = Array2::<f32>::zeros((h, w));
+    let mut gx = Array2::<f32>::zeros((h, w));
+    let mut magnitude = Array2::<f32>::zeros((h, w));
+    for r in 1..h - 1 {
+        for c in 1..w - 1 {
+            let p = |dr: usize, dc: usize| blurred[[r + dr - 1, c + dc - 1]];
+            let sx = (p(0, 2) + 2.0 * p(1, 2) + p(2, 2)) - (p(0, 0) + 2.0 * p(1, 0) + p(2, 0));
+            let sy = (p(2, 0) + 2.0 * p(2, 1) + p(2, 2)) - (p(0, 0) + 2.0 * p(0, 1) + p(0, 2));
+            gx[[r, c]] = sx;
+            gy[[r, c]] = sy;
+            magnitude[[r, c]] = sx.hypot(sy);
+        }
+    }
+
+    let max_mag = magnitude.iter().cloned().fold(0.0f32, f32::max);
+    if max_mag <= 0.0 {
+        return Vec::new();
+    }
+    let cutoff = threshold * max_mag;
+
+    let mut points = Vec::new();
+    for r in 1..h - 1 {
+        for c in 1..w - 1 {
+            let m0 = magnitude[[r, c]];
+            if m0 < cutoff {
+                continue;
+            }
+            let ny = (gy[[r, c]] / m0) as f64;
+            let nx = (gx[[r, c]] / m0) as f64;
+            let (y, x) = (r as f64, c as f64);
+            let before = bilinear_sample(&magnitude, y - ny, x - nx);
+            let after = bilinear_sample(&magnitude, y + ny, x + nx);
+            // Non-maximum suppression along the gradient direction.
+            if m0 < before || m0 <= after {
+                continue;
+            }
+            let denom = before - 2.0 * m0 + after;
+            let t = if denom < 0.0 {
+                (0.5 * (before - after) / denom).clamp(-0.5, 0.5) as f64
+            } else {
+                0.0
+            };
+            points.push((y + t * ny, x + t * nx));
+        }
+    }
+
+    if points.len() > LIMB_MAX_EDGE_POINTS {
+        let stride = points.len().div_ceil(LIMB_MAX_EDGE_POINTS);
+        points = points.into_iter().step_by(stride).collect();
+    }
+    points
+}
+
+/// Circle through three points, or `None` if they are (nearly) collinear.
+fn circumcircle(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> Option<Circle> {
+    let (ay, ax) = a;
+    let (by, bx) = (b.0 - ay, b.1 - ax);
+    let (cy, cx) = (c.0 - ay, c.1 - ax);
+    let d = 2.0 * (bx * cy - by * cx);
+    if d.abs() < 1e-9 {
+        return None;
+    }
+    let b2 = bx * bx + by * by;
+    let c2 = cx * cx + cy * cy;
+    let ux = (cy * b2 - by * c2) / d;
+    let uy = (bx * c2 - cx * b2) / d;
+    Some(Circle {
+        cy: ay + uy,
+        cx: ax + ux,
+        r: ux.hypot(uy),
+    })
+}
+
+/// Algebraic (Kasa) least-squares circle fit.
+fn least_squares_circle(points: &[(f64, f64)]) -> Option<Circle> {
+    if points.len() < 3 {
+        return None;
+    }
+    // Work relative to the mean for numerical stability.
+    let n = points.len() as f64;
+    let my = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mx = points.iter().map(|p| p.1).sum::<f64>() / n;
+
+    // Solve [Sxx Sxy Sx; Sxy Syy Sy; Sx Sy n] [D E F]^T = -[Sxz Syz Sz]
+    // for x^2 + y^2 + D x + E y + F = 0.
+    let mut m = [[0.0f64; 3]; 3];
+    let mut v = [0.0f64; 3];
+    for &(y, x) in points {
+        let (y, x) = (y - my, x - mx);
+        let z = x * x + y * y;
+        let row = [x, y, 1.0];
+        for i in 0..3 {
+            for j in 0..3 {
+                m[i][j] += row[i] * row[j];
+            }
+            v[i] -= row[i] * z;
+        }
+    }
+    let [d, e, f] = solve(m, v)?;
+    let (ux, uy) = (-d / 2.0, -e / 2.0);
+    let r2 = ux * ux + uy * uy - f;
+    (r2 > 0.0).then(|| Circle {
+        cy: my + uy,
+        cx: mx + ux,
+        r: r2.sqrt(),
+    })
+}
+
+/// Conic `a x^2 + b xy + c y^2 + d x + e y + f = 0`, normalised to
+/// `a + c = 1` (which every ellipse can be).
+#[derive(Clone, Copy, Debug)]
+struct Conic {
+    a: f64,
+    b: f64,
+    c: f64,
+    d: f64,
+    e: f64,
+    f: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Ellipse {
+    cy: f64,
+    cx: f64,
+    semi_major: f64,
+    semi_minor: f64,
+    angle: f64,
+}
+
+impl Conic {
+    /// Least-squares conic through `points` (exact for five points).
+    fn fit(points: &[(f64, f64)]) -> Option<Self> {
+        if points.len() < 5 {
+            return None;
+        }
+        // With c = 1 - a: a (x^2 - y^2) + b xy + d x + e y + f = -y^2.
+        let mut m = [[0.0f64; 5]; 5];
+        let mut v = [0.0f64; 5];
+        for &(y, x) in points {
+            let row = [x * x - y * y, x * y, x, y, 1.0];
+            for i in 0..5 {
+                for j in 0..5 {
+                    m[i][j] += row[i] * row[j];
+                }
+                v[i] -= row[i] * y * y;
+            }
+        }
+        let [a, b, d, e, f] = solve(m, v)?;
+        Some(Self {
+            a,
+            b,
+            c: 1.0 - a,
+            d,
+            e,
+            f,
+        })
+    }
+
+    /// First-order (Sampson) approximation of the distance of `(y, x)` from
+    /// the conic.
+    fn sampson_distance(&self, (y, x): (f64, f64)) -> f64 {
+        let value =
+            self.a * x * x + self.b * x * y + self.c * y * y + self.d * x + self.e * y + self.f;
+        let gx = 2.0 * self.a * x + self.b * y + self.d;
+        let gy = self.b * x + 2.0 * self.c * y + self.e;
+        let gradient = gx.hypot(gy);
+        if gradient > 0.0 {
+            value.abs() / gradient
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Centre, axes and orientation, or `None` if the conic is not a real
+    /// ellipse.
+    fn ellipse(&self) -> Option<Ellipse> {
+        let Self { a, b, c, d, e, f } = *self;
+        let det = 4.0 * a * c - b * b;
+        if det <= 0.0 {
+            return None;
+        }
+        let cx = (b * e - 2.0 * c * d) / det;
+        let cy = (b * d - 2.0 * a * e) / det;
+        // Value at the centre; the axes are sqrt(-f0 / eigenvalue).
+        let f0 = f + 0.5 * (d * cx + e * cy);
+        let radius_along = |theta: f64| {
+            let (s, co) = theta.sin_cos();
+            let q = a * co * co + b * co * s + c * s * s;
+            (-f0 / q > 0.0).then(|| (-f0 / q).sqrt())
+        };
+        let theta = 0.5 * b.atan2(a - c);
+        let r1 = radius_along(theta)?;
+        let r2 = radius_along(theta + FRAC_PI_2)?;
+        let (semi_major, semi_minor, angle) = if r1 >= r2 {
+            (r1, r2, theta)
+        } else if theta > 0.0 {
+            (r2, r1, theta - FRAC_PI_2)
+        } else {
+            (r2, r1, theta + FRAC_PI_2)
+        };
+        Some(Ellipse {
+            cy,
+            cx,
+            semi_major,
+            semi_minor,
+            angle,
+        })
+    }
+}
+
+/// Solve a small linear system by Gaussian elimination with partial
+/// pivoting, or `None` if it is (nearly) singular.
+fn solve<const N: usize>(mut m: [[f64; N]; N], mut v: [f64; N]) -> Option<[f64; N]> {
+    for col in 0..N {
+        let pivot = (col..N).max_by(|&i, &j| m[i][col].abs().total_cmp(&m[j][col].abs()))?;
+        if m[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        m.swap(col, pivot);
+        v.swap(col, pivot);
+        let pivot_row = m[col];
+        for row in col + 1..N {
+            let factor = m[row][col] / pivot_row[col];
+            for (value, p) in m[row].iter_mut().zip(pivot_row).skip(col) {
+                *value -= factor * p;
+            }
+            v[row] -= factor * v[col];
+        }
+    }
+    let mut out = [0.0; N];
+    for row in (0..N).rev() {
+        let tail: f64 = (row + 1..N).map(|k| m[row][k] * out[k]).sum();
+        out[row] = (v[row] - tail) / m[row][row];
+    }
+    Some(out)
+}
+
+/// Small deterministic generator so limb fits are reproducible.
+struct Lcg(u64);
+
+impl Lcg {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) % n as u64) as usize
+    }
+}
@@ -2,12 +2,17 @@ pub mod centroid;
 mod dispatcher;
 pub mod enhanced_phase;
 pub mod gradient_correlation;
+pub mod limb;
+pub mod outliers;
 pub mod phase_correlation;
 pub mod pyramid;
+pub mod similarity;
 pub mod subpixel;
 
 pub use dispatcher::{
-    align_frames_configured_with_progress, compute_offset_configured,
-    compute_offsets_streaming_configured,
+    align_frames_configured_with_progress, apply_offsets, compute_offset_configured,
+    compute_offsets_configured_with_progress, compute_offsets_streaming_configured, refine_offsets,
+    ReferenceAligner,
 };
+pub use outliers::{alignment_confidences, reject_outliers, AlignmentCheck};
 pub use phase_correlation::{bilinear_sample, shift_frame};
@@ -0,0 +1,200 @@
+//! Confidence and drift checks on global alignment results.
+//!
+//! Every frame is shifted by its offset and correlated against the
+//! reference; the correlation peak-to-mean ratio is its confidence. Methods
+//! that do not register by correlation (centroid, limb fit) have no
+//! confidence and are judged by the drift check alone. Offsets are compared
+//! with a drift track, the median offset of the nearest frames in capture
+//! order, so a single frame registered on a cosmic-ray hit or torn by a wind
+//! gust stands out from the slow drift of the target.
+
+use ndarray::Array2;
+use rayon::prelude::*;
+
+use crate::consts::PARALLEL_FRAME_THRESHOLD;
+use crate::error::Result;
+use crate::frame::AlignmentOffset;
+use crate::pipeline::config::OutlierRejection;
+
+use super::dispatcher::ReferenceAligner;
+use super::phase_correlation::shift_array;
+
+/// Outcome of [`reject_outliers`], parallel to the checked offsets.
+#[derive(Clone, Debug, Default)]
+pub struct AlignmentCheck {
+    /// Registration confidence of each frame (NaN for the reference).
+    pub confidences: Vec<f64>,
+    /// Frames that failed every check and should not be stacked.
+    pub rejected: Vec<bool>,
+    /// Number of suspect frames rescued by re-searching.
+    pub recovered: usize,
+}
+
+impl AlignmentCheck {
+    /// Number of rejected frames.
+    pub fn rejected_count(&self) -> usize {
+        self.rejected.iter().filter(|&&r| r).count()
+    }
+}
+
+/// Registration confidence of every frame with its offset, as judged by
+/// `aligner` against frame `reference_idx` (NaN for the reference itself and
+/// for methods without a confidence). `read(k)` yields the data of frame `k`.
+pub fn alignment_confidences<R>(
+    offsets: &[AlignmentOffset],
+    read: R,
+    reference_idx: usize,
+    aligner: &ReferenceAligner,
+) -> Result<Vec<f64>>
+where
+    R: Fn(usize) -> Result<Array2<f32>> + Sync,
+{
+    let confidence_of = |k: usize| -> Result<f64> {
+        if k == reference_idx {
+            return Ok(f64::NAN);
+        }
+        Ok(aligner
+            .confidence(&read(k)?, &offsets[k])?
+            .unwrap_or(f64::NAN))
+    };
+    if offsets.len() >= PARALLEL_FRAME_THRESHOLD {
+        (0..offsets.len())
+            .into_par_iter()
+            .map(confidence_of)
+            .collect()
+    } else {
+        (0..offsets.len()).map(confidence_of).collect()
+    }
+}
+
+/// Check `offsets` against `rejection`, re-searching suspect frames.
+///
+/// `aligner` is the configured alignment against frame `reference_idx`;
+/// it judges confidences and re-aligns suspects. `read(k)` yields the data
+/// of frame `k` and `times[k]` its frame number in the capture. Offsets of
+/// recovered frames are replaced in place; the reference frame is never
+/// rejected.
+pub fn reject_outliers<R>(
+    offsets: &mut [AlignmentOffset],
+    read: R,
+    times: &[usize],
+    reference_idx: usize,
+    rejection: &OutlierRejection,
+    aligner: &ReferenceAligner,
+) -> Result<AlignmentCheck>
+where
+    R: Fn(usize) -> Result<Array2<f32>> + Sync,
+{
+    let count = offsets.len();
+    let mut confidences = alignment_confidences(offsets, &read, reference_idx, aligner)?;
+
+    // Low confidence first, then distance from a track of the confident frames.
+    let low = |c: f64| c < rejection.min_confidence;
+    let mut suspect: Vec<bool> = confidences.iter().map(|&c| low(c)).collect();
+    let track = drift_track(offsets, times, &suspect, rejection.drift_window);
+    for k in 0..count {
+        if k != reference_idx && off_track(&offsets[k], track[k].as_ref(), rejection) {
+            suspect[k] = true;
+        }
+    }
+
+    // Re-search suspects against a track built from the trusted frames only,
+    // starting from where the track expects them.
+    let track = drift_track(offsets, times, &suspect, rejection.drift_window);
+    let research = |k: usize| -> Result<Option<(AlignmentOffset, f64)>> {
+        if !rejection.retry {
+            return Ok(None);
+        }
+        let target = read(k)?;
+        let offset = match &track[k] {
+            Some(expected) => {
+                let residual = aligner.offset(&shift_array(&target, expected))?;
+                AlignmentOffset {
+                    dx: expected.dx + residual.dx,
+                    dy: expected.dy + residual.dy,
+                }
+            }
+            None => aligner.offset(&target)?,
+        };
+        let confidence = aligner.confidence(&target, &offset)?.unwrap_or(f64::NAN);
+        let ok = !low(confidence) && !off_track(&offset, track[k].as_ref(), rejection);
+        Ok(ok.then_some((offset, confidence)))
+    };
+    let suspects: Vec<usize> = (0..count).filter(|&k| suspect[k]).collect();
+    let retried: Vec<Option<(AlignmentOffset, f64)>> = suspects
+        .par_iter()
+        .map(|&k| research(k))
+        .collect::<Result<_>>()?;
+
+    let mut rejected = vec![false; count];
+    let mut recovered = 0;
+    for (&k, retry) in suspects.iter().zip(retried) {
+        match retry {
+            Some((offset, confidence)) => {
+                offsets[k] = offset;
+                confidences[k] = confidence;
+                recovered += 1;
+            }
+            None => rejected[k] = true,
+        }
+    }
+
+    Ok(AlignmentCheck {
+        confidences,
+        rejected,
+        recovered,
+    })
+}
+
+fn off_track(
+    offset: &AlignmentOffset,
+    expected: Option<&AlignmentOffset>,
+    rejection: &OutlierRejection,
+) -> bool {
+    expected.is_some_and(|e| (offset.dx - e.dx).hypot(offset.dy - e.dy) > rejection.max_drift)
+}
+
+/// Expected offset of every frame: the median offset of its `window`
+/// nearest non-excluded neighbours in capture order (the frame itself is
+/// left out). `None` when fewer than three neighbours are available.
+fn drift_track(
+    offsets: &[AlignmentOffset],
+    times: &[usize],
+    excluded: &[bool],
+    window: usize,
+) -> Vec<Option<AlignmentOffset>> {
+    let mut order: Vec<usize> = (0..offsets.len()).filter(|&k| !excluded[k]).collect();
+    order.sort_by_key(|&k| times[k]);
+
+    (0..offsets.len())
+        .map(|k| {
+            // Where frame k falls among the trusted frames in time.
+            let pos = order.partition_point(|&j| times[j] < times[k]);
+            let half = window.div_ceil(2);
+            let lo = pos.saturating_sub(half);
+            let hi = (pos + half + 1).min(order.len());
+            let neighbours: Vec<&AlignmentOffset> = order[lo..hi]
+                .iter()
+                .filter(|&&j| j != k)
+                .map(|&j| &offsets[j])
+                .collect();
+            if neighbours.len() < 3 {
+                return None;
+            }
+            Some(AlignmentOffset {
+                dx: median(neighbours.iter().map(|o| o.dx).collect()),
+                dy: median(neighbours.iter().map(|o| o.dy).collect()),
+            })
+        })
+        .collect()
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
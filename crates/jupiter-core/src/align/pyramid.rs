@@ -64,7 +64,7 @@ pub fn compute_offset_pyramid(
 ///
 /// Returns a vector of `levels + 1` arrays, where index 0 is the original
 /// and index `levels` is the coarsest.
-fn build_pyramid(data: &Array2<f32>, levels: usize) -> Vec<Array2<f32>> {
+pub(crate) fn build_pyramid(data: &Array2<f32>, levels: usize) -> Vec<Array2<f32>> {
     let mut pyramid = Vec::with_capacity(levels + 1);
     pyramid.push(data.clone());
 
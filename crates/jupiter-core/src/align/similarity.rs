@@ -0,0 +1,207 @@
+//! Registration with rotation and scale (Fourier–Mellin).
+//!
+//! The magnitude of an image's spectrum does not change when the image is
+//! translated, and it rotates and scales (inversely) with the image. In
+//! log-polar coordinates those become shifts, so correlating the log-polar
+//! magnitude spectra gives the rotation and scale (Reddy & Chatterji,
+//! 1996). The spectrum is symmetric, which leaves a 180°
+//! ambiguity: both candidates are undone in turn and the one whose
+//! remaining translation correlates best is kept.
+
+use ndarray::{s, Array2, Zip};
+
+use crate::compute::cpu::{fft2d_forward, ifft2d_inverse};
+use crate::consts::{SIMILARITY_ANGLE_STEPS, SIMILARITY_RADIUS_STEPS};
+use crate::error::{JupiterError, Result};
+use crate::frame::{AlignmentOffset, Frame};
+
+use super::phase_correlation::{
+    apply_hann, bilinear_sample, compute_offset_with_confidence, find_peak,
+};
+
+/// Rotation, scale and translation that map a target image onto a reference.
+#[derive(Clone, Debug)]
+pub struct SimilarityTransform {
+    /// Rotation about the image centre, in radians.
+    pub rotation: f64,
+    /// Scale of the target relative to the reference.
+    pub scale: f64,
+    /// Shift applied after undoing rotation and scale, as in
+    /// [`shift_frame`](super::phase_correlation::shift_frame).
+    pub offset: AlignmentOffset,
+}
+
+impl Default for SimilarityTransform {
+    fn default() -> Self {
+        Self {
+            rotation: 0.0,
+            scale: 1.0,
+            offset: AlignmentOffset::default(),
+        }
+    }
+}
+
+/// Estimate the rotation, scale and translation of `target` relative to
+/// `reference`. Both must have the same size.
+pub fn compute_similarity(reference: &Frame, target: &Frame) -> Result<SimilarityTransform> {
+    if reference.data.dim() != target.data.dim() {
+        return Err(JupiterError::Pipeline(format!(
+            "Image size mismatch: {}x{} vs {}x{}",
+            reference.width(),
+            reference.height(),
+            target.width(),
+            target.height()
+        )));
+    }
+    let (h, w) = reference.data.dim();
+    let side = h.min(w);
+    let (top, left) = ((h - side) / 2, (w - side) / 2);
+    let square = |data: &Array2<f32>| {
+        data.slice(s![top..top + side, left..left + side])
+            .to_owned()
+    };
+    let reference_lp = log_polar_spectrum(&square(&reference.data));
+    let target_lp = log_polar_spectrum(&square(&target.data));
+    let shift = log_polar_shift(&reference_lp, &target_lp);
+
+    let rotation = -shift.dy * std::f64::consts::PI / SIMILARITY_ANGLE_STEPS as f64;
+    let scale = (shift.dx * log_radius_step(side)).exp();
+
+    let mut best: Option<(SimilarityTransform, f64)> = None;
+    for rotation in [rotation, rotation + std::f64::consts::PI] {
+        let unrotated = SimilarityTransform {
+            rotation,
+            scale,
+            offset: AlignmentOffset::default(),
+        };
+        let warped = warp_similarity(target, &unrotated);
+        let (offset, confidence) = compute_offset_with_confidence(&reference.data, &warped.data)?;
+        if best.as_ref().is_none_or(|(_, c)| confidence > *c) {
+            best = Some((
+                SimilarityTransform {
+                    rotation: wrap_angle(rotation),
+                    scale,
+                    offset,
+                },
+                confidence,
+            ));
+        }
+    }
+    Ok(best.expect("two candidates were tried").0)
+}
+
+/// Resample `target` onto the reference grid with `transform`.
+pub fn warp_similarity(target: &Frame, transform: &SimilarityTransform) -> Frame {
+    let (h, w) = target.data.dim();
+    let (cy, cx) = ((h as f64 - 1.0) / 2.0, (w as f64 - 1.0) / 2.0);
+    let (sin, cos) = transform.rotation.sin_cos();
+    let scale = transform.scale;
+    let offset = &transform.offset;
+    let data = Array2::from_shape_fn((h, w), |(row, col)| {
+        let y = row as f64 - offset.dy - cy;
+        let x = col as f64 - offset.dx - cx;
+        let src_x = cx + scale * (x * cos - y * sin);
+        let src_y = cy + scale * (x * sin + y * cos);
+        bilinear_sample(&target.data, src_y, src_x)
+    });
+    Frame::new(data, target.original_bit_depth)
+}
+
+/// High-passed magnitude spectrum of a square image resampled to
+/// log-polar coordinates: rows are angles over [0, π), columns log radii.
+fn log_polar_spectrum(data: &Array2<f32>) -> Array2<f32> {
+    let n = data.nrows();
+    let spectrum = fft2d_forward(&apply_hann(data));
+    let half = (n / 2) as isize;
+    let shifted = Array2::from_shape_fn((n, n), |(r, c)| {
+        let (fy, fx) = (r as isize - half, c as isize - half);
+        let value = spectrum[[
+            fy.rem_euclid(n as isize) as usize,
+            fx.rem_euclid(n as isize) as usize,
+        ]];
+        let x = (std::f64::consts::PI * fy as f64 / n as f64).cos()
+            * (std::f64::consts::PI * fx as f64 / n as f64).cos();
+        (value.norm().ln_1p() * (1.0 - x) * (2.0 - x)) as f32
+    });
+
+    let step = log_radius_step(n);
+    let centre = half as f64;
+    Array2::from_shape_fn(
+        (SIMILARITY_ANGLE_STEPS, SIMILARITY_RADIUS_STEPS),
+        |(a, r)| {
+            let theta = std::f64::consts::PI * a as f64 / SIMILARITY_ANGLE_STEPS as f64;
+            let radius = (r as f64 * step).exp();
+            let (sin, cos) = theta.sin_cos();
+            bilinear_sample(&shifted, centre + radius * sin, centre + radius * cos)
+        },
+    )
+}
+
+/// Shift of `target` relative to `reference` in log-polar coordinates, by
+/// cross-correlation. The angle axis is periodic, so only the radius axis
+/// is windowed; the correlation is not whitened, because the resampled
+/// spectra share fine interpolation structure that whitening would make
+/// dominate the peak.
+fn log_polar_shift(reference: &Array2<f32>, target: &Array2<f32>) -> AlignmentOffset {
+    let (h, w) = reference.dim();
+    let prepare = |data: &Array2<f32>| {
+        let mean = data.mean().unwrap_or(0.0);
+        Array2::from_shape_fn((h, w), |(r, c)| {
+            let window = 0.5 * (1.0 - (std::f32::consts::TAU * c as f32 / w as f32).cos());
+            (data[[r, c]] - mean) * window
+        })
+    };
+    let reference_fft = fft2d_forward(&prepare(reference));
+    let target_fft = fft2d_forward(&prepare(target));
+    let cross = Zip::from(&reference_fft)
+        .and(&target_fft)
+        .map_collect(|a, b| a * b.conj());
+    let correlation = ifft2d_inverse(&cross);
+    let (row, col, _) = find_peak(&correlation);
+    // Parabola through the peak and its (wrapped) neighbours.
+    let refine = |prev: f64, peak: f64, next: f64| {
+        let curvature = prev - 2.0 * peak + next;
+        if curvature.abs() > 1e-12 {
+            ((prev - next) / (2.0 * curvature)).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        }
+    };
+    let sub_dy = refine(
+        correlation[[(row + h - 1) % h, col]],
+        correlation[[row, col]],
+        correlation[[(row + 1) % h, col]],
+    );
+    let sub_dx = refine(
+        correlation[[row, (col + w - 1) % w]],
+        correlation[[row, col]],
+        correlation[[row, (col + 1) % w]],
+    );
+    let wrap = |i: usize, n: usize| {
+        if i > n / 2 {
+            i as f64 - n as f64
+        } else {
+            i as f64
+        }
+    };
+    AlignmentOffset {
+        dx: wrap(col, w) + sub_dx,
+        dy: wrap(row, h) + sub_dy,
+    }
+}
+
+/// Log-radius increment between log-polar columns for an `n`-pixel square.
+fn log_radius_step(n: usize) -> f64 {
+    ((n as f64 / 2.0 - 1.0).max(2.0)).ln() / SIMILARITY_RADIUS_STEPS as f64
+}
+
+/// Wrap an angle to (-π, π].
+fn wrap_angle(angle: f64) -> f64 {
+    let tau = std::f64::consts::TAU;
+    let wrapped = angle.rem_euclid(tau);
+    if wrapped > std::f64::consts::PI {
+        wrapped - tau
+    } else {
+        wrapped
+    }
+}
@@ -0,0 +1,147 @@
+//! Color composition from mono images taken through separate filters.
+//!
+//! Each input is a stacked mono image tagged with its filter. They are
+//! cropped to a common size, registered to a reference (the luminance image
+//! when there is one, otherwise the image in the green channel), optionally
+//! stretched to the full range, and placed in the red, green and blue
+//! channels. A luminance image then replaces the lightness of the result in
+//! CIE Lab, which keeps the colour of the RGB images while taking the detail
+//! of the (usually sharper, deeper) luminance image.
+
+use ndarray::Zip;
+use tracing::info;
+
+use crate::align::phase_correlation::{compute_offset, shift_frame};
+use crate::align::similarity::{compute_similarity, warp_similarity};
+use crate::color::lab::{lab_to_rgb, rgb_to_lab};
+use crate::consts::{COMPOSE_BLACK_PERCENTILE, COMPOSE_WHITE_PERCENTILE};
+use crate::error::{JupiterError, Result};
+use crate::filters::histogram::auto_stretch;
+use crate::frame::{ColorFrame, Frame};
+use crate::pipeline::config::{ChannelRole, CompositionConfig, RegistrationMode};
+
+/// Combine `images` (each tagged with its filter) into a color image.
+///
+/// Every role the configuration uses must be present; other images are
+/// ignored. Images of different sizes are cropped about their centres to
+/// the smallest common size.
+pub fn compose(images: &[(ChannelRole, Frame)], config: &CompositionConfig) -> Result<ColorFrame> {
+    let roles = config.required_roles();
+    let mut frames = Vec::with_capacity(roles.len());
+    for &role in &roles {
+        let frame = images
+            .iter()
+            .find(|(r, _)| *r == role)
+            .map(|(_, frame)| frame)
+            .ok_or_else(|| JupiterError::Pipeline(format!("Composition needs the {role} image")))?;
+        frames.push(frame);
+    }
+
+    let height = frames.iter().map(|f| f.height()).min().unwrap_or(0);
+    let width = frames.iter().map(|f| f.width()).min().unwrap_or(0);
+    if height == 0 || width == 0 {
+        return Err(JupiterError::Pipeline(
+            "Composition images are empty".into(),
+        ));
+    }
+    let mut frames: Vec<Frame> = frames
+        .into_iter()
+        .map(|f| center_crop(f, height, width))
+        .collect();
+
+    let reference = config.luminance.unwrap_or(config.green);
+    let reference_index = roles
+        .iter()
+        .position(|&r| r == reference)
+        .expect("reference role is required");
+    for i in 0..frames.len() {
+        if i != reference_index {
+            frames[i] = register(&frames[reference_index], &frames[i], roles[i], config)?;
+        }
+    }
+
+    if config.normalize {
+        for frame in &mut frames {
+            *frame = auto_stretch(frame, COMPOSE_BLACK_PERCENTILE, COMPOSE_WHITE_PERCENTILE);
+        }
+    }
+
+    let frame_for = |role: ChannelRole| {
+        let i = roles
+            .iter()
+            .position(|&r| r == role)
+            .expect("role is required");
+        frames[i].clone()
+    };
+    let color = ColorFrame {
+        red: frame_for(config.red),
+        green: frame_for(config.green),
+        blue: frame_for(config.blue),
+    };
+    Ok(match config.luminance {
+        Some(role) => layer_luminance(&color, &frame_for(role), config.luminance_weight),
+        None => color,
+    })
+}
+
+/// Replace the CIE Lab lightness of `color` with that of `luminance`,
+/// blended by `weight` (0 keeps the color image's own lightness).
+pub fn layer_luminance(color: &ColorFrame, luminance: &Frame, weight: f32) -> ColorFrame {
+    let weight = weight.clamp(0.0, 1.0);
+    let mut result = color.clone();
+    Zip::from(&mut result.red.data)
+        .and(&mut result.green.data)
+        .and(&mut result.blue.data)
+        .and(&luminance.data)
+        .par_for_each(|r, g, b, &y| {
+            let mut lab = rgb_to_lab([*r, *g, *b]);
+            let y = y.clamp(0.0, 1.0);
+            let lightness = rgb_to_lab([y, y, y])[0];
+            lab[0] += weight * (lightness - lab[0]);
+            let [nr, ng, nb] = lab_to_rgb(lab);
+            (*r, *g, *b) = (nr.clamp(0.0, 1.0), ng.clamp(0.0, 1.0), nb.clamp(0.0, 1.0));
+        });
+    result
+}
+
+/// Register `target` to `reference` as the configuration asks.
+fn register(
+    reference: &Frame,
+    target: &Frame,
+    role: ChannelRole,
+    config: &CompositionConfig,
+) -> Result<Frame> {
+    match config.registration {
+        RegistrationMode::None => Ok(target.clone()),
+        RegistrationMode::Translation => {
+            let offset = compute_offset(reference, target)?;
+            info!("{role}: shift ({:.2}, {:.2}) px", offset.dx, offset.dy);
+            Ok(shift_frame(target, &offset))
+        }
+        RegistrationMode::Similarity => {
+            let transform = compute_similarity(reference, target)?;
+            info!(
+                "{role}: shift ({:.2}, {:.2}) px, rotation {:.2}°, scale {:.4}",
+                transform.offset.dx,
+                transform.offset.dy,
+                transform.rotation.to_degrees(),
+                transform.scale
+            );
+            Ok(warp_similarity(target, &transform))
+        }
+    }
+}
+
+/// Crop `frame` about its centre to `height` × `width`.
+fn center_crop(frame: &Frame, height: usize, width: usize) -> Frame {
+    if frame.height() == height && frame.width() == width {
+        return frame.clone();
+    }
+    let top = (frame.height() - height) / 2;
+    let left = (frame.width() - width) / 2;
+    let data = frame
+        .data
+        .slice(ndarray::s![top..top + height, left..left + width])
+        .to_owned();
+    Frame::new(data, frame.original_bit_depth)
+}
@@ -0,0 +1,62 @@
+//! CIE Lab and LCh conversions for linear RGB pixel values (sRGB primaries,
+//! D65 white).
+
+use crate::consts::{D65_WHITE, LAB_EPSILON, LAB_KAPPA, RGB_TO_XYZ, XYZ_TO_RGB};
+
+/// Convert linear RGB to CIE Lab (`L` in 0–100).
+pub fn rgb_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let xyz = mul(&RGB_TO_XYZ, rgb);
+    let f = |t: f32| {
+        if t > LAB_EPSILON {
+            t.cbrt()
+        } else {
+            (LAB_KAPPA * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (
+        f(xyz[0] / D65_WHITE[0]),
+        f(xyz[1] / D65_WHITE[1]),
+        f(xyz[2] / D65_WHITE[2]),
+    );
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// Convert CIE Lab back to linear RGB.
+pub fn lab_to_rgb(lab: [f32; 3]) -> [f32; 3] {
+    let fy = (lab[0] + 16.0) / 116.0;
+    let fx = fy + lab[1] / 500.0;
+    let fz = fy - lab[2] / 200.0;
+    let inv = |f: f32| {
+        let cube = f * f * f;
+        if cube > LAB_EPSILON {
+            cube
+        } else {
+            (116.0 * f - 16.0) / LAB_KAPPA
+        }
+    };
+    let xyz = [
+        inv(fx) * D65_WHITE[0],
+        inv(fy) * D65_WHITE[1],
+        inv(fz) * D65_WHITE[2],
+    ];
+    mul(&XYZ_TO_RGB, xyz)
+}
+
+/// Convert Lab to LCh: lightness, chroma and hue angle in radians.
+pub fn lab_to_lch(lab: [f32; 3]) -> [f32; 3] {
+    [lab[0], lab[1].hypot(lab[2]), lab[2].atan2(lab[1])]
+}
+
+/// Inverse of [`lab_to_lch`].
+pub fn lch_to_lab(lch: [f32; 3]) -> [f32; 3] {
+    let (sin, cos) = lch[2].sin_cos();
+    [lch[0], lch[1] * cos, lch[1] * sin]
+}
+
+fn mul(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
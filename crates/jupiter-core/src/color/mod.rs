@@ -1,2 +1,4 @@
+pub mod compose;
 pub mod debayer;
+pub mod lab;
 pub mod process;
@@ -1,6 +1,7 @@
-use ndarray::Array2;
+use ndarray::{Array2, Zip};
 
 use crate::color::debayer::{debayer, luminance, DebayerMethod};
+use crate::consts::{EPSILON, LUMINANCE_B, LUMINANCE_G, LUMINANCE_R};
 use crate::error::{JupiterError, Result};
 use crate::frame::{ColorFrame, ColorMode, Frame};
 use crate::io::ser::SerReader;
@@ -71,6 +72,79 @@ where
     ColorFrame { red, green, blue }
 }
 
+/// Apply a fallible processing function to each channel in parallel,
+/// returning the first error.
+pub fn try_process_color_parallel<F>(color: &ColorFrame, process_fn: F) -> Result<ColorFrame>
+where
+    F: Fn(&Frame) -> Result<Frame> + Send + Sync,
+{
+    let (red, (green, blue)) = rayon::join(
+        || process_fn(&color.red),
+        || rayon::join(|| process_fn(&color.green), || process_fn(&color.blue)),
+    );
+    Ok(ColorFrame {
+        red: red?,
+        green: green?,
+        blue: blue?,
+    })
+}
+
+/// Split a color frame into its luminance `Y` (BT.601 weights) and the
+/// color differences `B - Y` and `R - Y`.
+pub fn to_luma_chroma(color: &ColorFrame) -> (Array2<f32>, Array2<f32>, Array2<f32>) {
+    let luma = luminance(color).data;
+    let cb = &color.blue.data - &luma;
+    let cr = &color.red.data - &luma;
+    (luma, cb, cr)
+}
+
+/// Inverse of [`to_luma_chroma`].
+pub fn from_luma_chroma(
+    luma: &Array2<f32>,
+    cb: &Array2<f32>,
+    cr: &Array2<f32>,
+    bit_depth: u8,
+) -> ColorFrame {
+    let red = luma + cr;
+    let blue = luma + cb;
+    let mut green = luma - &(&red * LUMINANCE_R) - &(&blue * LUMINANCE_B);
+    green /= LUMINANCE_G;
+    ColorFrame {
+        red: Frame::new(red, bit_depth),
+        green: Frame::new(green, bit_depth),
+        blue: Frame::new(blue, bit_depth),
+    }
+}
+
+/// Apply a processing function to the luminance of a color frame, scaling
+/// each pixel's channels by the change in luminance so that hue and
+/// saturation are kept. Black pixels become grey at the new luminance.
+pub fn process_luminance<F>(color: &ColorFrame, process_fn: F) -> ColorFrame
+where
+    F: FnOnce(&Frame) -> Frame,
+{
+    let luma = luminance(color);
+    let processed = process_fn(&luma);
+    let mut result = color.clone();
+    Zip::from(&mut result.red.data)
+        .and(&mut result.green.data)
+        .and(&mut result.blue.data)
+        .and(&luma.data)
+        .and(&processed.data)
+        .for_each(|r, g, b, &before, &after| {
+            if before > EPSILON {
+                let ratio = after / before;
+                *r = (*r * ratio).clamp(0.0, 1.0);
+                *g = (*g * ratio).clamp(0.0, 1.0);
+                *b = (*b * ratio).clamp(0.0, 1.0);
+            } else {
+                let grey = after.clamp(0.0, 1.0);
+                (*r, *g, *b) = (grey, grey, grey);
+            }
+        });
+    result
+}
+
 /// Create a ColorFrame from three separate mono frames.
 pub fn from_channels(red: Frame, green: Frame, blue: Frame) -> ColorFrame {
     ColorFrame { red, green, blue }
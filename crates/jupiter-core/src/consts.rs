@@ -11,6 +11,26 @@ pub const PARALLEL_FRAME_THRESHOLD: usize = 4;
 /// B3 spline 1D kernel coefficients: [1, 4, 6, 4, 1] / 16.
 pub const B3_KERNEL: [f32; 5] = [1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0];
 
+/// Sigma of the Gaussian wavelet kernel per unit of scale; the B3 spline has
+/// the same variance, so both kernels separate the same scales.
+pub const WAVELET_GAUSSIAN_SIGMA: f32 = 1.0;
+
+/// Tap radius of the bilateral layer denoise (taps are spaced by the layer scale).
+pub const WAVELET_BILATERAL_RADIUS: isize = 2;
+
+/// Spatial sigma of the bilateral layer denoise, in taps.
+pub const WAVELET_BILATERAL_SPATIAL_SIGMA: f32 = 1.0;
+
+/// Smoothing applied before measuring edges for the edge protection mask,
+/// and to widen the mask over the rim afterwards.
+pub const WAVELET_EDGE_SIGMA: f32 = 2.0;
+
+/// Gradient (relative to the strongest edge) where edge protection begins.
+pub const WAVELET_EDGE_LOW: f32 = 0.2;
+
+/// Gradient (relative to the strongest edge) of full edge protection.
+pub const WAVELET_EDGE_HIGH: f32 = 0.5;
+
 // --- Numeric ---
 
 /// Small epsilon to avoid division by zero in floating-point comparisons.
@@ -40,6 +60,16 @@ pub const LOW_MEMORY_THRESHOLD_BYTES: usize = 1_073_741_824;
 /// Balances memory usage vs. parallelism. At 4096x4096 f32, 8 frames = 512 MB.
 pub const STREAMING_BATCH_SIZE: usize = 8;
 
+/// Histogram bins per pixel in each refinement pass of the streaming median.
+/// Memory is `4 * (bins + 3)` bytes per pixel (28 with 4 bins), whatever the
+/// frame count; fewer bins trade memory for passes.
+pub const STREAMING_MEDIAN_BINS: usize = 4;
+
+/// Histogram passes of the streaming median after its mean/variance pass.
+/// Each narrows the bracket around the median by [`STREAMING_MEDIAN_BINS`],
+/// so four passes of 4 bins resolve it to 1/256 of the initial bracket.
+pub const STREAMING_MEDIAN_REFINE_PASSES: usize = 4;
+
 // --- Alignment ---
 
 /// Default upsampling factor for enhanced phase correlation (Guizar-Sicairos).
@@ -52,6 +82,36 @@ pub const DEFAULT_CENTROID_THRESHOLD: f32 = 0.1;
 /// Default number of Gaussian pyramid levels for coarse-to-fine alignment.
 pub const DEFAULT_PYRAMID_LEVELS: usize = 3;
 
+/// Default minimum limb edge strength, as a fraction of the strongest edge.
+pub const DEFAULT_LIMB_EDGE_THRESHOLD: f32 = 0.3;
+
+/// Default number of RANSAC circle hypotheses per frame for limb fitting.
+pub const DEFAULT_LIMB_RANSAC_ITERATIONS: usize = 300;
+
+/// Default maximum distance (pixels) of a limb inlier from the fitted circle.
+pub const DEFAULT_LIMB_INLIER_TOLERANCE: f64 = 1.0;
+
+/// Default fraction of the best selected frames averaged into the stacked
+/// reference when refining the alignment reference.
+pub const DEFAULT_REFINE_TOP_FRACTION: f32 = 0.25;
+
+/// Default maximum number of re-alignment passes against a stacked reference.
+pub const DEFAULT_REFINE_MAX_PASSES: usize = 3;
+
+/// Default convergence threshold (pixels): refinement stops once no offset
+/// moves by more than this between passes.
+pub const DEFAULT_REFINE_CONVERGENCE: f64 = 0.05;
+
+/// Default minimum correlation peak-to-mean ratio for a globally aligned frame.
+/// Uncorrelated noise peaks at roughly 5-6, well-registered frames at 30+.
+pub const DEFAULT_REJECT_MIN_CONFIDENCE: f64 = 8.0;
+
+/// Default number of neighbouring frames (capture order) in the drift track.
+pub const DEFAULT_REJECT_DRIFT_WINDOW: usize = 8;
+
+/// Default maximum distance (pixels) of a frame's offset from the drift track.
+pub const DEFAULT_REJECT_MAX_DRIFT: f64 = 3.0;
+
 /// Search window (in pixels) around the coarse peak for enhanced phase
 /// correlation upsampled DFT refinement.
 pub const ENHANCED_PHASE_SEARCH_WINDOW: f64 = 1.5;
@@ -59,6 +119,20 @@ pub const ENHANCED_PHASE_SEARCH_WINDOW: f64 = 1.5;
 /// Gaussian blur sigma used for building the pyramid in coarse-to-fine alignment.
 pub const PYRAMID_BLUR_SIGMA: f32 = 1.0;
 
+/// Gaussian blur sigma applied before limb edge detection.
+pub const LIMB_BLUR_SIGMA: f32 = 1.0;
+
+/// Upper bound on limb edge points fed to the RANSAC circle fit.
+pub const LIMB_MAX_EDGE_POINTS: usize = 2000;
+
+/// Angle samples over [0, π) of the log-polar spectrum used to register
+/// rotation and scale.
+pub const SIMILARITY_ANGLE_STEPS: usize = 360;
+
+/// Log-radius samples of the log-polar spectrum used to register rotation
+/// and scale.
+pub const SIMILARITY_RADIUS_STEPS: usize = 256;
+
 // --- Autocrop ---
 
 /// Default number of frames to sample for auto-crop planet detection.
@@ -119,3 +193,264 @@ pub const MEAN_REFERENCE_KEEP_FRACTION: f32 = 0.2;
 /// accept a local alignment result.  Below this threshold the frame/AP pair
 /// is skipped as unreliable.
 pub const MIN_CORRELATION_CONFIDENCE: f64 = 2.0;
+
+/// Default minimum gradient energy, as a fraction of the strongest candidate,
+/// for structure-driven AP placement.
+pub const DEFAULT_AP_MIN_ENERGY: f32 = 0.15;
+
+/// Default size in pixels of the small APs in multi-scale placement.
+pub const DEFAULT_AP_SMALL_SIZE: usize = 32;
+
+/// Smallest AP size accepted from a user-supplied AP list.
+pub const AP_MIN_SIZE: usize = 8;
+
+/// Blend weight below which a pixel is topped up from the background image,
+/// so pixels covered only by AP edges (or not at all) stay well defined.
+pub const AP_BLEND_MIN_COVERAGE: f64 = 0.25;
+
+/// Default thin-plate spline smoothing for surface-warp deformation fields,
+/// in AP-normalised units: 0 interpolates every AP shift exactly, larger
+/// values trade fidelity for a flatter field.
+pub const DEFAULT_WARP_SPLINE_SMOOTHING: f32 = 0.05;
+
+/// Reweighting passes used to down-weight outlying AP shifts in the spline fit.
+pub const WARP_SPLINE_ROBUST_PASSES: usize = 3;
+
+/// Tukey biweight cut-off in robust standard deviations: AP shifts further
+/// than this from the fitted field are dropped from the next pass.
+pub const WARP_SPLINE_TUKEY_C: f64 = 4.685;
+
+/// Floor (pixels) on the robust residual scale, so consistent AP shifts do
+/// not turn sub-0.1 px noise into outliers.
+pub const WARP_SPLINE_MIN_SCALE: f64 = 0.1;
+
+/// Spacing (pixels) of the lattice the spline is evaluated on before
+/// bilinear upsampling to full resolution.
+pub const WARP_SPLINE_EVAL_STEP: usize = 4;
+
+// --- Optical flow ---
+
+/// Default number of pyramid levels below full resolution for dense flow.
+pub const DEFAULT_FLOW_LEVELS: usize = 3;
+
+/// Default half-width (pixels) of the Lucas–Kanade integration window.
+pub const DEFAULT_FLOW_WINDOW_RADIUS: usize = 7;
+
+/// Default number of warp-and-refine iterations per pyramid level.
+pub const DEFAULT_FLOW_ITERATIONS: usize = 3;
+
+/// Default Gaussian sigma (pixels) used to smooth the flow after each
+/// iteration, suppressing noise-driven vectors in low-texture areas.
+pub const DEFAULT_FLOW_SMOOTHING: f32 = 2.0;
+
+/// Default maximum local displacement (pixels) on top of the global offset.
+pub const DEFAULT_FLOW_MAX_DISPLACEMENT: f32 = 8.0;
+
+/// Tikhonov term added to the Lucas–Kanade normal equations, as a fraction
+/// of the mean structure-tensor trace, so flat areas get no update instead
+/// of an ill-conditioned one.
+pub const FLOW_REGULARIZATION: f32 = 0.05;
+
+/// Coarsest pyramid level kept for dense flow (smaller side, pixels).
+pub const FLOW_MIN_LEVEL_SIZE: usize = 16;
+
+/// Progress steps reported for an optical-flow stack, which reports its
+/// progress as a fraction rather than a frame count.
+pub const FLOW_PROGRESS_STEPS: usize = 100;
+
+// --- Super-resolution ---
+
+/// Default output scale factor for multi-frame super-resolution.
+pub const DEFAULT_SR_SCALE: f32 = 2.0;
+
+/// Largest super-resolution scale accepted; beyond this the reconstruction
+/// is dominated by the regulariser rather than the data.
+pub const SR_MAX_SCALE: f32 = 4.0;
+
+/// Default number of back-projection iterations.
+pub const DEFAULT_SR_ITERATIONS: usize = 20;
+
+/// Default Gaussian PSF sigma (output pixels) of the super-resolution
+/// forward model.
+pub const DEFAULT_SR_PSF_SIGMA: f32 = 1.0;
+
+/// Default weight of the Laplacian smoothness prior in the MAP update.
+pub const DEFAULT_SR_REGULARIZATION: f32 = 0.01;
+
+// --- PSF estimation ---
+
+/// Background deviations above which a pixel counts as part of a measured
+/// point source.
+pub const PSF_NOISE_SIGMA: f32 = 3.0;
+
+/// Largest radius (px) of a PSF kernel cut out of an image.
+pub const PSF_MAX_RADIUS: usize = 32;
+
+/// Number of radial profiles sampled around the limb when estimating the PSF.
+pub const PSF_LIMB_PROFILES: usize = 360;
+
+/// Half-length (px) of each limb profile, either side of the fitted limb.
+pub const PSF_LIMB_PROFILE_HALF_WIDTH: f64 = 12.0;
+
+/// Sampling step (px) along a limb profile.
+pub const PSF_LIMB_SAMPLE_STEP: f64 = 0.25;
+
+/// Minimum edge contrast of a limb profile, as a fraction of the strongest
+/// one. Drops the terminator and the unlit side of a crescent.
+pub const PSF_LIMB_MIN_CONTRAST: f64 = 0.5;
+
+/// 10-90% rise distance of the edge-spread function of a unit-sigma
+/// Gaussian PSF (2 × 1.2816).
+pub const GAUSSIAN_ESF_10_90_WIDTH: f64 = 2.5631;
+
+/// Default side length (px) of the PSF support in blind deconvolution.
+pub const DEFAULT_BLIND_PSF_SIZE: usize = 15;
+
+/// Richardson-Lucy steps on the image per blind deconvolution round.
+pub const BLIND_IMAGE_STEPS: usize = 3;
+
+/// Richardson-Lucy steps on the PSF per blind deconvolution round.
+pub const BLIND_PSF_STEPS: usize = 3;
+
+/// Blank margin (px) around a PSF kernel saved as an image, so that
+/// re-extracting it finds a clean background border.
+pub const PSF_SAVE_MARGIN: usize = 4;
+
+// --- Regularized Richardson-Lucy ---
+
+/// Ratio of the noise sigma in the first à trous (B3-spline) detail layer to
+/// the image's noise sigma.
+pub const NOISE_FIRST_SCALE_FACTOR: f32 = 0.889;
+
+/// Exponent of the damping function of damped Richardson-Lucy (White, 1994):
+/// larger values switch the update on more abruptly at the threshold.
+pub const RL_DAMPING_EXPONENT: f32 = 10.0;
+
+/// Gradient magnitude floor in the total-variation term, so flat regions do
+/// not divide by zero.
+pub const RL_TV_EPSILON: f32 = 1e-3;
+
+/// Lower bound on the total-variation denominator `1 - λ div(∇u/|∇u|)`.
+/// Keeps strong weights from flipping the sign of the update.
+pub const RL_TV_MIN_DENOMINATOR: f32 = 0.2;
+
+/// Mean relative change per iteration below which automatic stopping ends
+/// Richardson-Lucy.
+pub const RL_STOP_MIN_CHANGE: f32 = 1e-3;
+
+/// Width (px) of the mirrored, tapered border added around the image when
+/// edge padding is enabled.
+pub const DECONV_EDGE_PAD: usize = 32;
+
+/// Margin (px) outside the fitted limb still deconvolved under the planet
+/// mask, so the limb itself is restored.
+pub const DECONV_MASK_MARGIN: f64 = 4.0;
+
+/// Width (px) over which the planet mask fades from the deconvolved to the
+/// observed image.
+pub const DECONV_MASK_FEATHER: f64 = 4.0;
+
+// --- Tiled deconvolution ---
+
+/// Default side length (px) of a deconvolution tile.
+pub const DEFAULT_DECONV_TILE_SIZE: usize = 128;
+
+/// Smallest allowed deconvolution tile.
+pub const DECONV_MIN_TILE_SIZE: usize = 32;
+
+/// Bounds on how far a tile's PSF may be scaled from the configured one.
+pub const TILE_PSF_MIN_SCALE: f64 = 0.5;
+pub const TILE_PSF_MAX_SCALE: f64 = 2.0;
+
+/// Gradient energy, as a fraction of the median over all tiles, below which
+/// a tile is too featureless to measure and keeps the configured PSF.
+pub const TILE_MIN_TEXTURE: f64 = 0.1;
+
+// --- Denoise ---
+
+/// Noise standard deviation in each B3 à trous detail layer for unit white
+/// noise (Starck & Murtagh); layers beyond the table halve each time.
+pub const ATROUS_NOISE_LEVELS: [f32; 6] = [0.889, 0.200, 0.086, 0.041, 0.020, 0.010];
+
+/// Bilateral filter window radius, in spatial sigmas.
+pub const BILATERAL_RADIUS_SIGMAS: f32 = 2.0;
+
+/// Offset added to the colour-difference channels while they are denoised,
+/// so they stay within the [0, 1] range the filters expect.
+pub const CHROMA_OFFSET: f32 = 0.5;
+
+// --- Tone and colour adjustment ---
+
+/// Entries in the lookup table a tone curve is sampled into.
+pub const CURVE_LUT_SIZE: usize = 4096;
+
+/// Linear sRGB (D65) to CIE XYZ.
+pub const RGB_TO_XYZ: [[f32; 3]; 3] = [
+    [0.412_456_4, 0.357_576_1, 0.180_437_5],
+    [0.212_672_9, 0.715_152_2, 0.072_175],
+    [0.019_333_9, 0.119_192, 0.950_304_1],
+];
+
+/// CIE XYZ to linear sRGB (D65).
+pub const XYZ_TO_RGB: [[f32; 3]; 3] = [
+    [3.240_454_2, -1.537_138_5, -0.498_531_4],
+    [-0.969_266, 1.876_010_8, 0.041_556],
+    [0.055_643_4, -0.204_025_9, 1.057_225_2],
+];
+
+/// D65 reference white in XYZ.
+pub const D65_WHITE: [f32; 3] = [0.950_47, 1.0, 1.088_83];
+
+/// CIE Lab threshold (6/29)³ between the cube-root and linear segments.
+pub const LAB_EPSILON: f32 = 216.0 / 24389.0;
+
+/// CIE Lab slope (29/3)³ of the linear segment.
+pub const LAB_KAPPA: f32 = 24389.0 / 27.0;
+
+/// LCh chroma at and above which vibrance no longer boosts saturation.
+pub const VIBRANCE_FULL_CHROMA: f32 = 60.0;
+
+/// Fraction of the fitted disk radius sampled by planet-disk white balance,
+/// leaving out the limb where colour fringes and limb darkening sit.
+pub const WHITE_BALANCE_DISK_FRACTION: f64 = 0.8;
+
+/// Largest gain automatic white balance applies to a channel.
+pub const WHITE_BALANCE_MAX_GAIN: f32 = 4.0;
+
+// --- Geometric transforms ---
+
+/// Lobes of the Lanczos resampling kernel.
+pub const LANCZOS_LOBES: usize = 3;
+
+/// Free parameter of the Keys cubic convolution kernel.
+pub const BICUBIC_A: f32 = -0.5;
+
+// --- Composition ---
+
+/// Percentile mapped to black when composition normalizes a channel.
+pub const COMPOSE_BLACK_PERCENTILE: f32 = 0.001;
+
+/// Percentile mapped to white when composition normalizes a channel.
+pub const COMPOSE_WHITE_PERCENTILE: f32 = 0.999;
+
+/// Default weight of the luminance image in LRGB composition.
+pub const DEFAULT_LUMINANCE_WEIGHT: f32 = 1.0;
+
+// --- Pixel rejection ---
+
+/// Clamp distance, in robust sigmas, used to winsorize a pixel stack before
+/// estimating its spread.
+pub const WINSORIZE_CUTOFF: f32 = 1.5;
+
+/// Maximum winsorization passes when estimating the robust sigma.
+pub const WINSORIZE_MAX_PASSES: usize = 10;
+
+/// Correction that turns the sigma of a 1.5σ-winsorized normal sample back
+/// into the sigma of the full distribution.
+pub const WINSORIZE_SIGMA_CORRECTION: f32 = 1.134;
+
+/// Default upper bound on the fraction of a pixel stack GESD may reject.
+pub const DEFAULT_GESD_MAX_OUTLIERS: f32 = 0.3;
+
+/// Default significance level of the GESD test.
+pub const DEFAULT_GESD_ALPHA: f32 = 0.05;
@@ -95,7 +95,7 @@ pub fn connected_components(mask: &Array2<bool>) -> Vec<ComponentStats> {
     }
 
     let mut components: Vec<ComponentStats> = stats_map.into_values().collect();
-    components.sort_unstable_by(|a, b| b.area.cmp(&a.area));
+    components.sort_unstable_by_key(|b| std::cmp::Reverse(b.area));
     components
 }
 
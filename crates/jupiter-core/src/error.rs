@@ -29,6 +29,9 @@ pub enum JupiterError {
     #[error("Invalid crop region: {0}")]
     InvalidCrop(String),
 
+    #[error("Invalid frame report: {0}")]
+    InvalidReport(String),
+
     #[error("GPU error: {0}")]
     GpuError(String),
 }
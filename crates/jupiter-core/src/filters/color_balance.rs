@@ -0,0 +1,101 @@
+//! Saturation and white balance for colour images.
+//!
+//! - [`saturation`] scales the chroma of each pixel in LCh, keeping its
+//!   lightness and hue; vibrance adds a boost that fades out towards
+//!   strongly coloured pixels.
+//! - [`white_balance`] multiplies each channel by a gain: given by hand, or
+//!   chosen so the channel means become equal over the whole image (grey
+//!   world) or over the fitted planet disk.
+
+use ndarray::Zip;
+use tracing::warn;
+
+use crate::align::limb::fit_limb;
+use crate::color::debayer::luminance;
+use crate::color::lab::{lab_to_lch, lab_to_rgb, lch_to_lab, rgb_to_lab};
+use crate::consts::{
+    EPSILON, VIBRANCE_FULL_CHROMA, WHITE_BALANCE_DISK_FRACTION, WHITE_BALANCE_MAX_GAIN,
+};
+use crate::frame::{ColorFrame, Frame};
+use crate::pipeline::config::{LimbFitConfig, WhiteBalance};
+
+/// Scale the LCh chroma of every pixel by `saturation`, plus up to
+/// `vibrance` more for pixels with little colour.
+pub fn saturation(color: &ColorFrame, saturation: f32, vibrance: f32) -> ColorFrame {
+    let mut result = color.clone();
+    Zip::from(&mut result.red.data)
+        .and(&mut result.green.data)
+        .and(&mut result.blue.data)
+        .par_for_each(|r, g, b| {
+            let mut lch = lab_to_lch(rgb_to_lab([*r, *g, *b]));
+            let weak = 1.0 - (lch[1] / VIBRANCE_FULL_CHROMA).min(1.0);
+            lch[1] *= (saturation * (1.0 + vibrance * weak)).max(0.0);
+            let [nr, ng, nb] = lab_to_rgb(lch_to_lab(lch));
+            (*r, *g, *b) = (nr.clamp(0.0, 1.0), ng.clamp(0.0, 1.0), nb.clamp(0.0, 1.0));
+        });
+    result
+}
+
+/// Apply the white balance gains of `balance`.
+pub fn white_balance(color: &ColorFrame, balance: &WhiteBalance) -> ColorFrame {
+    let gains = white_balance_gains(color, balance);
+    let scale = |frame: &Frame, gain: f32| {
+        Frame::new(
+            frame.data.mapv(|v| (v * gain).clamp(0.0, 1.0)),
+            frame.original_bit_depth,
+        )
+    };
+    ColorFrame {
+        red: scale(&color.red, gains[0]),
+        green: scale(&color.green, gains[1]),
+        blue: scale(&color.blue, gains[2]),
+    }
+}
+
+/// Red, green and blue gains of `balance` for this image. Automatic gains
+/// keep the mean of the three channel means.
+pub fn white_balance_gains(color: &ColorFrame, balance: &WhiteBalance) -> [f32; 3] {
+    let means = match balance {
+        WhiteBalance::Manual { red, green, blue } => return [*red, *green, *blue],
+        WhiteBalance::GreyWorld => channel_means(color, None),
+        WhiteBalance::PlanetDisk => {
+            match fit_limb(&luminance(color).data, &LimbFitConfig::default()) {
+                Ok(limb) => {
+                    let radius = limb.radius * WHITE_BALANCE_DISK_FRACTION;
+                    channel_means(color, Some((limb.center_y, limb.center_x, radius)))
+                }
+                Err(e) => {
+                    warn!("{e}; using grey-world white balance");
+                    channel_means(color, None)
+                }
+            }
+        }
+    };
+    let target = means.iter().sum::<f32>() / 3.0;
+    means.map(|m| {
+        if m > EPSILON {
+            (target / m).clamp(1.0 / WHITE_BALANCE_MAX_GAIN, WHITE_BALANCE_MAX_GAIN)
+        } else {
+            1.0
+        }
+    })
+}
+
+/// Mean of each channel, over the pixels within `disk` (centre row,
+/// centre column, radius) when given.
+fn channel_means(color: &ColorFrame, disk: Option<(f64, f64, f64)>) -> [f32; 3] {
+    let mut sums = [0.0f64; 3];
+    let mut count = 0usize;
+    for ((r, c), &red) in color.red.data.indexed_iter() {
+        if let Some((cy, cx, radius)) = disk {
+            if (r as f64 - cy).hypot(c as f64 - cx) > radius {
+                continue;
+            }
+        }
+        sums[0] += red as f64;
+        sums[1] += color.green.data[[r, c]] as f64;
+        sums[2] += color.blue.data[[r, c]] as f64;
+        count += 1;
+    }
+    sums.map(|s| (s / count.max(1) as f64) as f32)
+}
@@ -0,0 +1,95 @@
+//! Tone curves through user-placed control points.
+//!
+//! The points are joined by a monotone piecewise cubic (the Fritsch–Butland
+//! slopes used by PCHIP), so a rising set of points gives a rising curve
+//! with no overshoot between them. Inputs outside the first and last point
+//! take the value of the nearest end point.
+
+use crate::consts::{CURVE_LUT_SIZE, EPSILON};
+use crate::frame::Frame;
+
+/// Monotone cubic spline through a set of control points.
+#[derive(Clone, Debug)]
+pub struct ToneCurve {
+    xs: Vec<f32>,
+    ys: Vec<f32>,
+    slopes: Vec<f32>,
+}
+
+impl ToneCurve {
+    /// Curve through `points` (input, output pairs), in any order. Of points
+    /// sharing an input, the last one is kept. Fewer than two distinct
+    /// points give the identity curve.
+    pub fn new(points: &[[f32; 2]]) -> Self {
+        let mut sorted = points.to_vec();
+        sorted.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        let mut xs: Vec<f32> = Vec::with_capacity(sorted.len());
+        let mut ys: Vec<f32> = Vec::with_capacity(sorted.len());
+        for [x, y] in sorted {
+            if xs.last().is_some_and(|&last| x - last < EPSILON) {
+                *ys.last_mut().expect("ys grows with xs") = y;
+            } else {
+                xs.push(x);
+                ys.push(y);
+            }
+        }
+        if xs.len() < 2 {
+            (xs, ys) = (vec![0.0, 1.0], vec![0.0, 1.0]);
+        }
+
+        let n = xs.len();
+        let h: Vec<f32> = xs.windows(2).map(|w| w[1] - w[0]).collect();
+        let d: Vec<f32> = (0..n - 1).map(|k| (ys[k + 1] - ys[k]) / h[k]).collect();
+        let mut slopes = vec![0.0; n];
+        slopes[0] = d[0];
+        slopes[n - 1] = d[n - 2];
+        for k in 1..n - 1 {
+            if d[k - 1] * d[k] > 0.0 {
+                let w1 = 2.0 * h[k] + h[k - 1];
+                let w2 = h[k] + 2.0 * h[k - 1];
+                slopes[k] = (w1 + w2) / (w1 / d[k - 1] + w2 / d[k]);
+            }
+        }
+        Self { xs, ys, slopes }
+    }
+
+    /// Value of the curve at `x`.
+    pub fn eval(&self, x: f32) -> f32 {
+        let n = self.xs.len();
+        if x <= self.xs[0] {
+            return self.ys[0];
+        }
+        if x >= self.xs[n - 1] {
+            return self.ys[n - 1];
+        }
+        let k = self.xs.partition_point(|&xk| xk <= x) - 1;
+        let h = self.xs[k + 1] - self.xs[k];
+        let t = (x - self.xs[k]) / h;
+        let (t2, t3) = (t * t, t * t * t);
+        (2.0 * t3 - 3.0 * t2 + 1.0) * self.ys[k]
+            + (t3 - 2.0 * t2 + t) * h * self.slopes[k]
+            + (3.0 * t2 - 2.0 * t3) * self.ys[k + 1]
+            + (t3 - t2) * h * self.slopes[k + 1]
+    }
+
+    /// The curve over 0–1 sampled into a lookup table.
+    fn lut(&self) -> Vec<f32> {
+        let last = (CURVE_LUT_SIZE - 1) as f32;
+        (0..CURVE_LUT_SIZE)
+            .map(|i| self.eval(i as f32 / last).clamp(0.0, 1.0))
+            .collect()
+    }
+}
+
+/// Apply the tone curve through `points` to every pixel.
+pub fn apply_curve(frame: &Frame, points: &[[f32; 2]]) -> Frame {
+    let lut = ToneCurve::new(points).lut();
+    let last = (CURVE_LUT_SIZE - 1) as f32;
+    let data = frame.data.mapv(|v| {
+        let pos = v.clamp(0.0, 1.0) * last;
+        let i = (pos as usize).min(CURVE_LUT_SIZE - 2);
+        let frac = pos - i as f32;
+        lut[i] + frac * (lut[i + 1] - lut[i])
+    });
+    Frame::new(data, frame.original_bit_depth)
+}
@@ -0,0 +1,188 @@
+//! Noise reduction for stacked images.
+//!
+//! - [`non_local_means`] averages each pixel with pixels whose surrounding
+//!   patches look alike, wherever they are in the search window; the
+//!   strength is given in units of the estimated noise sigma.
+//! - [`bilateral`] averages neighbours weighted by distance and by similarity
+//!   of value, so edges are kept.
+//! - [`guided_filter`] is the self-guided filter of He et al. (2010): a local
+//!   linear fit that flattens regions with variance below `epsilon` and
+//!   keeps stronger structure.
+//! - [`wavelet_denoise`] soft-thresholds each à trous layer with the
+//!   BayesShrink threshold `σ_n² / σ_x` (Chang et al., 2000), computed from
+//!   the noise estimated in the finest layer.
+
+use ndarray::{Array2, Zip};
+use rayon::prelude::*;
+
+use crate::consts::{ATROUS_NOISE_LEVELS, BILATERAL_RADIUS_SIGMAS, EPSILON};
+use crate::frame::Frame;
+use crate::sharpen::regularized_rl::noise_sigma;
+use crate::sharpen::wavelet::{decompose, mirror_index, soft_threshold};
+
+/// Non-local means with `(2 * patch_radius + 1)²` patches compared over a
+/// `(2 * search_radius + 1)²` window. `strength` scales the estimated noise
+/// sigma into the filtering parameter `h` (about 1.0 for moderate noise).
+pub fn non_local_means(
+    frame: &Frame,
+    strength: f32,
+    patch_radius: usize,
+    search_radius: usize,
+) -> Frame {
+    let data = &frame.data;
+    let h = strength * noise_sigma(data);
+    if h <= EPSILON {
+        return frame.clone();
+    }
+    let h2 = h * h;
+    let (rows, cols) = data.dim();
+    let s = search_radius as isize;
+    let offsets: Vec<(isize, isize)> = (-s..=s)
+        .flat_map(|dy| (-s..=s).map(move |dx| (dy, dx)))
+        .collect();
+
+    let zeros = || {
+        (
+            Array2::<f32>::zeros((rows, cols)),
+            Array2::<f32>::zeros((rows, cols)),
+        )
+    };
+    let (sum, weights) = offsets
+        .par_iter()
+        .fold(zeros, |(mut sum, mut weights), &(dy, dx)| {
+            let shifted = Array2::from_shape_fn((rows, cols), |(r, c)| {
+                data[[
+                    mirror_index(r as isize + dy, rows),
+                    mirror_index(c as isize + dx, cols),
+                ]]
+            });
+            let squared = Zip::from(data)
+                .and(&shifted)
+                .map_collect(|&a, &b| (a - b) * (a - b));
+            let distance = box_mean(&squared, patch_radius);
+            Zip::from(&mut sum)
+                .and(&mut weights)
+                .and(&distance)
+                .and(&shifted)
+                .for_each(|s, w, &d, &v| {
+                    let k = (-d / h2).exp();
+                    *s += k * v;
+                    *w += k;
+                });
+            (sum, weights)
+        })
+        .reduce(zeros, |(s1, w1), (s2, w2)| (s1 + &s2, w1 + &w2));
+
+    let result = Zip::from(&sum)
+        .and(&weights)
+        .map_collect(|&s, &w| s / w.max(EPSILON));
+    Frame::new(result, frame.original_bit_depth)
+}
+
+/// Bilateral filter with spatial sigma in pixels and range sigma in
+/// intensity units.
+pub fn bilateral(frame: &Frame, spatial_sigma: f32, range_sigma: f32) -> Frame {
+    if spatial_sigma <= 0.0 || range_sigma <= 0.0 {
+        return frame.clone();
+    }
+    let data = &frame.data;
+    let (h, w) = data.dim();
+    let radius = (BILATERAL_RADIUS_SIGMAS * spatial_sigma).ceil() as isize;
+    let spatial = 2.0 * spatial_sigma * spatial_sigma;
+    let range = 2.0 * range_sigma * range_sigma;
+
+    let values: Vec<f32> = (0..h)
+        .into_par_iter()
+        .flat_map_iter(|row| {
+            (0..w).map(move |col| {
+                let center = data[[row, col]];
+                let (mut sum, mut weight_sum) = (0.0f32, 0.0f32);
+                for dy in -radius..=radius {
+                    let r = mirror_index(row as isize + dy, h);
+                    for dx in -radius..=radius {
+                        let v = data[[r, mirror_index(col as isize + dx, w)]];
+                        let weight = (-((dy * dy + dx * dx) as f32) / spatial
+                            - (v - center).powi(2) / range)
+                            .exp();
+                        sum += weight * v;
+                        weight_sum += weight;
+                    }
+                }
+                sum / weight_sum
+            })
+        })
+        .collect();
+    let result = Array2::from_shape_vec((h, w), values).expect("bilateral output matches input");
+    Frame::new(result, frame.original_bit_depth)
+}
+
+/// Self-guided filter over `(2 * radius + 1)²` windows; `epsilon` is the
+/// local variance below which a window is flattened.
+pub fn guided_filter(frame: &Frame, radius: usize, epsilon: f32) -> Frame {
+    let data = &frame.data;
+    let mean = box_mean(data, radius);
+    let mean_sq = box_mean(&data.mapv(|v| v * v), radius);
+    let a = Zip::from(&mean).and(&mean_sq).map_collect(|&m, &m2| {
+        let variance = (m2 - m * m).max(0.0);
+        variance / (variance + epsilon.max(EPSILON))
+    });
+    let b = Zip::from(&mean).and(&a).map_collect(|&m, &a| m - a * m);
+    let (mean_a, mean_b) = (box_mean(&a, radius), box_mean(&b, radius));
+    let result = Zip::from(data)
+        .and(&mean_a)
+        .and(&mean_b)
+        .map_collect(|&v, &a, &b| a * v + b);
+    Frame::new(result, frame.original_bit_depth)
+}
+
+/// Wavelet denoise over `layers` à trous layers with BayesShrink thresholds,
+/// scaled by `strength` (1.0 = the BayesShrink estimate).
+pub fn wavelet_denoise(frame: &Frame, layers: usize, strength: f32) -> Frame {
+    let sigma = noise_sigma(&frame.data);
+    if sigma <= EPSILON {
+        return frame.clone();
+    }
+    let (details, mut result) = decompose(&frame.data, layers);
+    for (j, layer) in details.iter().enumerate() {
+        let noise = sigma * layer_noise_level(j);
+        let variance = layer.iter().map(|v| v * v).sum::<f32>() / layer.len().max(1) as f32;
+        let signal = (variance - noise * noise).max(0.0).sqrt();
+        let threshold = if signal > EPSILON {
+            strength * noise * noise / signal
+        } else {
+            // Nothing but noise in this layer.
+            layer.iter().fold(0.0f32, |m, v| m.max(v.abs()))
+        };
+        result += &soft_threshold(layer, threshold);
+    }
+    result.mapv_inplace(|v| v.clamp(0.0, 1.0));
+    Frame::new(result, frame.original_bit_depth)
+}
+
+/// Noise sigma of à trous layer `j` relative to the image's.
+fn layer_noise_level(j: usize) -> f32 {
+    let last = ATROUS_NOISE_LEVELS.len() - 1;
+    ATROUS_NOISE_LEVELS[j.min(last)] / (1u32 << j.saturating_sub(last).min(31)) as f32
+}
+
+/// Mean over `(2 * radius + 1)²` windows, mirrored at the borders.
+fn box_mean(data: &Array2<f32>, radius: usize) -> Array2<f32> {
+    if radius == 0 {
+        return data.clone();
+    }
+    let (h, w) = data.dim();
+    let r = radius as isize;
+    let n = (2 * radius + 1) as f32;
+    let rows = Array2::from_shape_fn((h, w), |(row, col)| {
+        (-r..=r)
+            .map(|d| data[[row, mirror_index(col as isize + d, w)]])
+            .sum::<f32>()
+            / n
+    });
+    Array2::from_shape_fn((h, w), |(row, col)| {
+        (-r..=r)
+            .map(|d| rows[[mirror_index(row as isize + d, h), col]])
+            .sum::<f32>()
+            / n
+    })
+}
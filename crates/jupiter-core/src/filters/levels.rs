@@ -1,3 +1,4 @@
+use crate::consts::EPSILON;
 use crate::frame::Frame;
 
 /// Apply gamma correction: output = input^(1/gamma).
@@ -20,3 +21,14 @@ pub fn brightness_contrast(frame: &Frame, brightness: f32, contrast: f32) -> Fra
     });
     Frame::new(data, frame.original_bit_depth)
 }
+
+/// Levels: map [black_point, white_point] to [0.0, 1.0], then apply gamma
+/// as in [`gamma_correct`].
+pub fn levels(frame: &Frame, black_point: f32, white_point: f32, gamma: f32) -> Frame {
+    let range = (white_point - black_point).max(EPSILON);
+    let inv_gamma = 1.0 / gamma.max(EPSILON);
+    let data = frame
+        .data
+        .mapv(|v| ((v - black_point) / range).clamp(0.0, 1.0).powf(inv_gamma));
+    Frame::new(data, frame.original_bit_depth)
+}
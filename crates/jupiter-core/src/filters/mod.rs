@@ -1,5 +1,9 @@
+pub mod color_balance;
+pub mod curves;
+pub mod denoise;
 pub mod gaussian_blur;
 pub mod histogram;
 pub mod levels;
 pub mod rgb_align;
+pub mod transform;
 pub mod unsharp_mask;
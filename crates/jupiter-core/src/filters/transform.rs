@@ -0,0 +1,221 @@
+//! Geometric transforms: rotation, mirroring, resampling and a fixed-size
+//! canvas around the planet.
+//!
+//! Rotation and resampling interpolate with separable kernels (Lanczos or
+//! Keys cubic). When shrinking, the kernel is widened by the reduction
+//! factor so it also low-passes the image and fine detail does not alias.
+//! Results are clamped to 0–1, cutting off the kernels' slight overshoot.
+
+use ndarray::{s, Array2};
+use tracing::warn;
+
+use crate::consts::{BICUBIC_A, LANCZOS_LOBES};
+use crate::detection::config::DetectionConfig;
+use crate::detection::planet::detect_planet_in_frame;
+use crate::frame::Frame;
+use crate::pipeline::config::ResampleMethod;
+
+/// Rotate `frame` by `degrees` about its centre, counter-clockwise as
+/// displayed, with bicubic interpolation. Without `expand` the canvas is
+/// kept and the corners are cut off; with it the canvas grows to hold the
+/// whole rotated image. Uncovered areas are black.
+pub fn rotate(frame: &Frame, degrees: f32, expand: bool) -> Frame {
+    let (h, w) = frame.data.dim();
+    let (sin, cos) = (degrees as f64).to_radians().sin_cos();
+    let (out_h, out_w) = if expand {
+        // Small tolerance so right angles do not gain a pixel from rounding.
+        let fit = |v: f64| (v - 1e-6).ceil().max(1.0) as usize;
+        (
+            fit(w as f64 * sin.abs() + h as f64 * cos.abs()),
+            fit(w as f64 * cos.abs() + h as f64 * sin.abs()),
+        )
+    } else {
+        (h, w)
+    };
+    let (cy, cx) = ((h as f64 - 1.0) / 2.0, (w as f64 - 1.0) / 2.0);
+    let (out_cy, out_cx) = ((out_h as f64 - 1.0) / 2.0, (out_w as f64 - 1.0) / 2.0);
+    let data = Array2::from_shape_fn((out_h, out_w), |(row, col)| {
+        let (y, x) = (row as f64 - out_cy, col as f64 - out_cx);
+        let src_x = cx + x * cos - y * sin;
+        let src_y = cy + x * sin + y * cos;
+        sample(&frame.data, src_y, src_x, ResampleMethod::Bicubic)
+    });
+    Frame::new(data, frame.original_bit_depth)
+}
+
+/// Mirror `frame` left to right and/or top to bottom.
+pub fn flip(frame: &Frame, horizontal: bool, vertical: bool) -> Frame {
+    let data = match (horizontal, vertical) {
+        (true, true) => frame.data.slice(s![..;-1, ..;-1]).to_owned(),
+        (true, false) => frame.data.slice(s![.., ..;-1]).to_owned(),
+        (false, true) => frame.data.slice(s![..;-1, ..]).to_owned(),
+        (false, false) => frame.data.clone(),
+    };
+    Frame::new(data, frame.original_bit_depth)
+}
+
+/// Resize `frame` by `scale` with the given kernel. The output is at least
+/// one pixel in each direction.
+pub fn resample(frame: &Frame, scale: f32, method: ResampleMethod) -> Frame {
+    let (h, w) = frame.data.dim();
+    let size = |n: usize| ((n as f32 * scale).round() as usize).max(1);
+    let (out_h, out_w) = (size(h), size(w));
+
+    let columns = axis_weights(w, out_w, method);
+    let rows = axis_weights(h, out_h, method);
+    let horizontal = Array2::from_shape_fn((h, out_w), |(r, c)| {
+        let (start, ref weights) = columns[c];
+        weighted_sum(weights, start, w, |i| frame.data[[r, i]])
+    });
+    let data = Array2::from_shape_fn((out_h, out_w), |(r, c)| {
+        let (start, ref weights) = rows[r];
+        weighted_sum(weights, start, h, |i| horizontal[[i, c]]).clamp(0.0, 1.0)
+    });
+    Frame::new(data, frame.original_bit_depth)
+}
+
+/// Crop or pad `frame` to `width` × `height`, centred on the planet
+/// detected in it.
+pub fn canvas(frame: &Frame, width: usize, height: usize) -> Frame {
+    let (center_y, center_x) = planet_center(frame);
+    canvas_at(frame, width, height, center_y, center_x)
+}
+
+/// Crop or pad `frame` to `width` × `height` centred on (`center_y`,
+/// `center_x`), to the nearest pixel so no resampling is needed. Padding
+/// is black.
+pub fn canvas_at(
+    frame: &Frame,
+    width: usize,
+    height: usize,
+    center_y: f64,
+    center_x: f64,
+) -> Frame {
+    let (h, w) = frame.data.dim();
+    let top = (center_y - (height as f64 - 1.0) / 2.0).round() as isize;
+    let left = (center_x - (width as f64 - 1.0) / 2.0).round() as isize;
+    let data = Array2::from_shape_fn((height.max(1), width.max(1)), |(r, c)| {
+        let (y, x) = (r as isize + top, c as isize + left);
+        if y < 0 || x < 0 || y >= h as isize || x >= w as isize {
+            0.0
+        } else {
+            frame.data[[y as usize, x as usize]]
+        }
+    });
+    Frame::new(data, frame.original_bit_depth)
+}
+
+/// Centre (row, column) of the planet in `frame`, or of the frame when no
+/// planet is found.
+pub fn planet_center(frame: &Frame) -> (f64, f64) {
+    match detect_planet_in_frame(&frame.data, 0, &DetectionConfig::default()) {
+        Some(detection) => (detection.cy, detection.cx),
+        None => {
+            warn!("No planet found; centring the canvas on the image");
+            (
+                (frame.height() as f64 - 1.0) / 2.0,
+                (frame.width() as f64 - 1.0) / 2.0,
+            )
+        }
+    }
+}
+
+/// Interpolation kernel at distance `x` (in source pixels).
+fn kernel(method: ResampleMethod, x: f32) -> f32 {
+    let x = x.abs();
+    match method {
+        ResampleMethod::Lanczos3 => {
+            let lobes = LANCZOS_LOBES as f32;
+            if x < 1e-6 {
+                1.0
+            } else if x < lobes {
+                let px = std::f32::consts::PI * x;
+                lobes * px.sin() * (px / lobes).sin() / (px * px)
+            } else {
+                0.0
+            }
+        }
+        ResampleMethod::Bicubic => {
+            let a = BICUBIC_A;
+            if x <= 1.0 {
+                (a + 2.0) * x.powi(3) - (a + 3.0) * x.powi(2) + 1.0
+            } else if x < 2.0 {
+                a * x.powi(3) - 5.0 * a * x.powi(2) + 8.0 * a * x - 4.0 * a
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+/// Half-width of the kernel, in source pixels.
+fn support(method: ResampleMethod) -> f32 {
+    match method {
+        ResampleMethod::Lanczos3 => LANCZOS_LOBES as f32,
+        ResampleMethod::Bicubic => 2.0,
+    }
+}
+
+/// For each output index along an axis, the first source index and the
+/// normalized weights of the source samples from there on.
+fn axis_weights(src_len: usize, dst_len: usize, method: ResampleMethod) -> Vec<(isize, Vec<f32>)> {
+    let scale = dst_len as f32 / src_len as f32;
+    let stretch = (1.0 / scale).max(1.0);
+    let reach = support(method) * stretch;
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f32 + 0.5) / scale - 0.5;
+            let start = (center - reach).floor() as isize + 1;
+            let end = (center + reach).floor() as isize;
+            let mut weights: Vec<f32> = (start..=end)
+                .map(|j| kernel(method, (j as f32 - center) / stretch))
+                .collect();
+            let total: f32 = weights.iter().sum();
+            if total.abs() > f32::EPSILON {
+                weights.iter_mut().for_each(|w| *w /= total);
+            }
+            (start, weights)
+        })
+        .collect()
+}
+
+/// Sum of `weights` times the samples from `start`, with indices clamped
+/// to the `len` samples available.
+fn weighted_sum(weights: &[f32], start: isize, len: usize, value: impl Fn(usize) -> f32) -> f32 {
+    weights
+        .iter()
+        .enumerate()
+        .map(|(k, w)| {
+            let i = (start + k as isize).clamp(0, len as isize - 1) as usize;
+            w * value(i)
+        })
+        .sum()
+}
+
+/// Interpolate `data` at (`y`, `x`); zero outside the image.
+fn sample(data: &Array2<f32>, y: f64, x: f64, method: ResampleMethod) -> f32 {
+    let (h, w) = data.dim();
+    if y < -0.5 || x < -0.5 || y > h as f64 - 0.5 || x > w as f64 - 0.5 {
+        return 0.0;
+    }
+    let reach = support(method) as isize;
+    let (y0, x0) = (y.floor() as isize, x.floor() as isize);
+    let (fy, fx) = ((y - y0 as f64) as f32, (x - x0 as f64) as f32);
+    let mut sum = 0.0;
+    let mut total = 0.0;
+    for dy in (1 - reach)..=reach {
+        let wy = kernel(method, dy as f32 - fy);
+        let row = (y0 + dy).clamp(0, h as isize - 1) as usize;
+        for dx in (1 - reach)..=reach {
+            let weight = wy * kernel(method, dx as f32 - fx);
+            let col = (x0 + dx).clamp(0, w as isize - 1) as usize;
+            sum += weight * data[[row, col]];
+            total += weight;
+        }
+    }
+    if total.abs() > f32::EPSILON {
+        (sum / total).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
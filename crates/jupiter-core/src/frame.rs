@@ -39,7 +39,7 @@ pub struct FrameMetadata {
 }
 
 /// Quality assessment result for a single frame.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct QualityScore {
     pub laplacian_variance: f64,
     pub composite: f64,
@@ -54,12 +54,19 @@ pub struct ColorFrame {
 }
 
 /// Alignment offset for a frame relative to a reference.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct AlignmentOffset {
     pub dx: f64,
     pub dy: f64,
 }
 
+impl AlignmentOffset {
+    /// True when applying this offset would leave the frame unchanged.
+    pub fn is_zero(&self) -> bool {
+        self.dx == 0.0 && self.dy == 0.0
+    }
+}
+
 /// Color/Bayer mode of the source data.
 #[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum ColorMode {
@@ -105,7 +105,7 @@ pub fn crop_ser(
     // Copy timestamps if present
     let mut timestamps = Vec::new();
     for i in 0..total {
-        if let Some(ts) = read_timestamp(reader, i) {
+        if let Some(ts) = reader.timestamp(i) {
             timestamps.push(ts);
         } else {
             break;
@@ -118,11 +118,3 @@ pub fn crop_ser(
     writer.finalize()?;
     Ok(())
 }
-
-/// Read a timestamp from the SER trailer (mirrors SerReader::read_timestamp).
-fn read_timestamp(reader: &SerReader, index: usize) -> Option<u64> {
-    // We access the reader's public interface; the timestamp is read via read_frame's metadata.
-    // But we need raw access. Use a frame read to get the timestamp.
-    let frame = reader.read_frame(index).ok()?;
-    frame.metadata.timestamp_us
-}
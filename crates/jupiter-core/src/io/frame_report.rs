@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{JupiterError, Result};
+use crate::frame::{AlignmentOffset, QualityScore};
+use crate::io::ser::SerReader;
+use crate::pipeline::config::QualityMetric;
+
+/// Column header used for CSV reports.
+const CSV_HEADER: &str =
+    "frame_index,timestamp_us,laplacian,gradient,composite,dx,dy,confidence,selected";
+
+/// Per-frame entry of a [`FrameReport`].
+///
+/// Optional fields are left empty (CSV) or `null` (JSON) when the value was
+/// not computed for that frame, e.g. offsets for frames that were not aligned.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameRecord {
+    pub frame_index: usize,
+    #[serde(default)]
+    pub timestamp_us: Option<u64>,
+    #[serde(default)]
+    pub laplacian: Option<f64>,
+    #[serde(default)]
+    pub gradient: Option<f64>,
+    /// Score of the metric used for ranking.
+    #[serde(default)]
+    pub composite: f64,
+    #[serde(default)]
+    pub dx: Option<f64>,
+    #[serde(default)]
+    pub dy: Option<f64>,
+    /// Correlation peak-to-mean ratio of the alignment, when available.
+    #[serde(default)]
+    pub confidence: Option<f64>,
+    #[serde(default)]
+    pub selected: bool,
+}
+
+/// Machine-readable report of per-frame quality scores and alignment offsets.
+///
+/// Written as CSV or JSON (chosen by file extension). An edited report can be
+/// fed back to the pipeline via `FrameSelectionConfig::frame_list`, in which
+/// case the frames flagged `selected` replace the percentage-based selection.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameReport {
+    pub frames: Vec<FrameRecord>,
+}
+
+impl FrameReport {
+    /// Create an empty report with one record per frame.
+    pub fn new(frame_count: usize) -> Self {
+        Self {
+            frames: (0..frame_count)
+                .map(|frame_index| FrameRecord {
+                    frame_index,
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    /// Fill in capture timestamps from the SER trailer, if present.
+    pub fn record_timestamps(&mut self, reader: &SerReader) {
+        for rec in &mut self.frames {
+            rec.timestamp_us = reader.timestamp(rec.frame_index);
+        }
+    }
+
+    /// Record scores for `metric` and set `composite` to the same value.
+    pub fn record_scores(&mut self, metric: &QualityMetric, ranked: &[(usize, QualityScore)]) {
+        for (idx, score) in ranked {
+            if let Some(rec) = self.frames.get_mut(*idx) {
+                match metric {
+                    QualityMetric::Laplacian => rec.laplacian = Some(score.composite),
+                    QualityMetric::Gradient => rec.gradient = Some(score.composite),
+                }
+                rec.composite = score.composite;
+            }
+        }
+    }
+
+    /// Record alignment offsets (and optional confidences) for `indices`.
+    /// Non-finite confidences (e.g. the reference frame) are left empty.
+    pub fn record_offsets(
+        &mut self,
+        indices: &[usize],
+        offsets: &[AlignmentOffset],
+        confidences: Option<&[f64]>,
+    ) {
+        for (k, (&idx, offset)) in indices.iter().zip(offsets).enumerate() {
+            if let Some(rec) = self.frames.get_mut(idx) {
+                rec.dx = Some(offset.dx);
+                rec.dy = Some(offset.dy);
+                rec.confidence = confidences
+                    .and_then(|c| c.get(k).copied())
+                    .filter(|c| c.is_finite());
+            }
+        }
+    }
+
+    /// Flag exactly the frames in `indices` as selected.
+    pub fn mark_selected(&mut self, indices: &[usize]) {
+        let set: HashSet<usize> = indices.iter().copied().collect();
+        for rec in &mut self.frames {
+            rec.selected = set.contains(&rec.frame_index);
+        }
+    }
+
+    /// Frame indices flagged as selected, in file order.
+    pub fn selected_indices(&self) -> Vec<usize> {
+        self.frames
+            .iter()
+            .filter(|r| r.selected)
+            .map(|r| r.frame_index)
+            .collect()
+    }
+
+    /// Serialize to CSV with a header row.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::with_capacity(64 * (self.frames.len() + 1));
+        out.push_str(CSV_HEADER);
+        out.push('\n');
+        for r in &self.frames {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{}",
+                r.frame_index,
+                opt_field(r.timestamp_us),
+                opt_field(r.laplacian),
+                opt_field(r.gradient),
+                r.composite,
+                opt_field(r.dx),
+                opt_field(r.dy),
+                opt_field(r.confidence),
+                r.selected,
+            );
+        }
+        out
+    }
+
+    /// Parse a CSV report. Columns are matched by header name, so reordered
+    /// or trimmed spreadsheets are accepted as long as `frame_index` exists.
+    pub fn from_csv(text: &str) -> Result<Self> {
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        let header = lines
+            .next()
+            .ok_or_else(|| JupiterError::InvalidReport("empty CSV".into()))?;
+        let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+        let col = |name: &str| columns.iter().position(|c| *c == name);
+        let index_col = col("frame_index")
+            .ok_or_else(|| JupiterError::InvalidReport("missing frame_index column".into()))?;
+        let timestamp_col = col("timestamp_us");
+        let laplacian_col = col("laplacian");
+        let gradient_col = col("gradient");
+        let composite_col = col("composite");
+        let dx_col = col("dx");
+        let dy_col = col("dy");
+        let confidence_col = col("confidence");
+        let selected_col = col("selected");
+
+        let mut frames = Vec::new();
+        for (line_no, line) in lines.enumerate() {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let get = |c: Option<usize>| c.and_then(|c| fields.get(c).copied()).unwrap_or("");
+            let row = line_no + 2;
+            frames.push(FrameRecord {
+                frame_index: parse_field(get(Some(index_col)), row)?.ok_or_else(|| {
+                    JupiterError::InvalidReport(format!("line {row}: missing frame_index"))
+                })?,
+                timestamp_us: parse_field(get(timestamp_col), row)?,
+                laplacian: parse_field(get(laplacian_col), row)?,
+                gradient: parse_field(get(gradient_col), row)?,
+                composite: parse_field(get(composite_col), row)?.unwrap_or(0.0),
+                dx: parse_field(get(dx_col), row)?,
+                dy: parse_field(get(dy_col), row)?,
+                confidence: parse_field(get(confidence_col), row)?,
+                selected: parse_bool(get(selected_col), row)?,
+            });
+        }
+        Ok(Self { frames })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| JupiterError::InvalidReport(e.to_string()))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        serde_json::from_str(text).map_err(|e| JupiterError::InvalidReport(e.to_string()))
+    }
+
+    /// Write the report; `.json` selects JSON, anything else CSV.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = if is_json(path) {
+            self.to_json()?
+        } else {
+            self.to_csv()
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Read a report written by [`FrameReport::save`] (or edited by hand).
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        if is_json(path) {
+            Self::from_json(&text)
+        } else {
+            Self::from_csv(&text)
+        }
+    }
+}
+
+/// Restrict a quality ranking to an explicit frame list, preserving rank order.
+///
+/// Returns an error if the list selects no frames or references frames outside
+/// `0..total`.
+pub fn select_listed(
+    ranked: &[(usize, QualityScore)],
+    listed: &[usize],
+    total: usize,
+) -> Result<Vec<(usize, QualityScore)>> {
+    if let Some(&bad) = listed.iter().find(|&&i| i >= total) {
+        return Err(JupiterError::FrameIndexOutOfRange { index: bad, total });
+    }
+    let set: HashSet<usize> = listed.iter().copied().collect();
+    let kept: Vec<_> = ranked
+        .iter()
+        .filter(|(i, _)| set.contains(i))
+        .cloned()
+        .collect();
+    if kept.is_empty() {
+        return Err(JupiterError::InvalidReport("no frames selected".into()));
+    }
+    Ok(kept)
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+}
+
+fn opt_field<T: std::fmt::Display>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn parse_field<T: std::str::FromStr>(field: &str, row: usize) -> Result<Option<T>> {
+    if field.is_empty() {
+        return Ok(None);
+    }
+    field
+        .parse()
+        .map(Some)
+        .map_err(|_| JupiterError::InvalidReport(format!("line {row}: invalid value '{field}'")))
+}
+
+fn parse_bool(field: &str, row: usize) -> Result<bool> {
+    match field.to_ascii_lowercase().as_str() {
+        "" | "false" | "0" | "no" | "n" => Ok(false),
+        "true" | "1" | "yes" | "y" | "x" => Ok(true),
+        _ => Err(JupiterError::InvalidReport(format!(
+            "line {row}: invalid selected flag '{field}'"
+        ))),
+    }
+}
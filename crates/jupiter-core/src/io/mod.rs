@@ -1,5 +1,6 @@
 pub mod autocrop;
 pub mod crop;
+pub mod frame_report;
 pub mod image_io;
 pub mod ser;
 pub mod ser_writer;
@@ -1,5 +1,5 @@
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use byteorder::{LittleEndian, ReadBytesExt};
 use memmap2::Mmap;
@@ -72,60 +72,132 @@ impl SerHeader {
 }
 
 /// Memory-mapped SER file reader.
+///
+/// Several compatible files from one session can be opened as a single
+/// sequence with [`SerReader::open_all`]; frames are then numbered across
+/// the files in the order given, and `header` describes the whole sequence.
 pub struct SerReader {
-    mmap: Mmap,
+    files: Vec<SerFile>,
     pub header: SerHeader,
 }
 
+/// One file of a (possibly multi-file) sequence.
+struct SerFile {
+    mmap: Mmap,
+    header: SerHeader,
+    path: PathBuf,
+    /// Sequence index of the file's first frame.
+    first: usize,
+}
+
+/// How many frames one input file holds and how many of them were stacked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileContribution {
+    pub path: PathBuf,
+    pub frames: usize,
+    /// Frames stacked from the file; `None` when the stacking method picks
+    /// frames per region rather than for the whole image.
+    pub selected: Option<usize>,
+}
+
 impl SerReader {
     /// Open a SER file and parse its header.
     pub fn open(path: &Path) -> Result<Self> {
-        let file = File::open(path)?;
-        let mmap = unsafe { Mmap::map(&file)? };
-
-        if mmap.len() < SER_HEADER_SIZE {
-            return Err(JupiterError::InvalidSer(
-                "File too small for SER header".into(),
-            ));
-        }
+        let file = open_file(path, 0)?;
+        Ok(Self {
+            header: file.header.clone(),
+            files: vec![file],
+        })
+    }
 
-        if &mmap[0..14] != SER_MAGIC {
-            return Err(JupiterError::InvalidSer(
-                "Missing LUCAM-RECORDER magic".into(),
-            ));
+    /// Open several SER files as one sequence, frames numbered in the order
+    /// of `paths`.
+    ///
+    /// Every file must match the first in size, bit depth, colour mode and
+    /// byte order.
+    pub fn open_all(paths: &[PathBuf]) -> Result<Self> {
+        let Some((first, rest)) = paths.split_first() else {
+            return Err(JupiterError::InvalidSer("No SER files given".into()));
+        };
+        let mut reader = Self::open(first)?;
+        for path in rest {
+            let file = open_file(path, reader.frame_count())?;
+            let (a, b) = (&reader.header, &file.header);
+            if (
+                a.width,
+                a.height,
+                a.pixel_depth,
+                a.color_id,
+                a.little_endian,
+            ) != (
+                b.width,
+                b.height,
+                b.pixel_depth,
+                b.color_id,
+                b.little_endian,
+            ) {
+                return Err(JupiterError::InvalidSer(format!(
+                    "{} ({}) does not match {} ({})",
+                    path.display(),
+                    describe(b),
+                    first.display(),
+                    describe(a)
+                )));
+            }
+            reader.header.frame_count = reader
+                .header
+                .frame_count
+                .checked_add(b.frame_count)
+                .ok_or_else(|| JupiterError::InvalidSer("Too many frames".into()))?;
+            reader.files.push(file);
         }
+        Ok(reader)
+    }
 
-        let header = parse_header(&mmap[..SER_HEADER_SIZE])?;
+    pub fn frame_count(&self) -> usize {
+        self.header.frame_count as usize
+    }
 
-        let expected_data_size =
-            SER_HEADER_SIZE + header.frame_byte_size() * header.frame_count as usize;
-        if mmap.len() < expected_data_size {
-            return Err(JupiterError::InvalidSer(format!(
-                "File truncated: expected at least {} bytes, got {}",
-                expected_data_size,
-                mmap.len()
-            )));
-        }
+    /// Number of files in the sequence.
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
 
-        Ok(Self { mmap, header })
+    /// Frames per input file, and how many of `selected` (sequence indices
+    /// of the stacked frames) came from each.
+    pub fn file_contributions(&self, selected: Option<&[usize]>) -> Vec<FileContribution> {
+        self.files
+            .iter()
+            .map(|file| {
+                let frames = file.header.frame_count as usize;
+                let range = file.first..file.first + frames;
+                FileContribution {
+                    path: file.path.clone(),
+                    frames,
+                    selected: selected.map(|s| s.iter().filter(|i| range.contains(i)).count()),
+                }
+            })
+            .collect()
     }
 
-    pub fn frame_count(&self) -> usize {
-        self.header.frame_count as usize
+    /// The file holding sequence frame `index`, and the frame's index within it.
+    fn locate(&self, index: usize) -> Result<(&SerFile, usize)> {
+        self.files
+            .iter()
+            .find(|f| index >= f.first && index < f.first + f.header.frame_count as usize)
+            .map(|f| (f, index - f.first))
+            .ok_or(JupiterError::FrameIndexOutOfRange {
+                index,
+                total: self.frame_count(),
+            })
     }
 
     /// Get the raw bytes for a single frame (zero-copy from mmap).
     pub fn frame_raw(&self, index: usize) -> Result<&[u8]> {
-        let count = self.frame_count();
-        if index >= count {
-            return Err(JupiterError::FrameIndexOutOfRange {
-                index,
-                total: count,
-            });
-        }
-        let offset = SER_HEADER_SIZE + index * self.header.frame_byte_size();
-        let end = offset + self.header.frame_byte_size();
-        Ok(&self.mmap[offset..end])
+        let (file, local) = self.locate(index)?;
+        let offset = SER_HEADER_SIZE + local * file.header.frame_byte_size();
+        let end = offset + file.header.frame_byte_size();
+        Ok(&file.mmap[offset..end])
     }
 
     /// Read a single frame, converting to f32 in [0.0, 1.0].
@@ -164,18 +236,19 @@ impl SerReader {
         frame.metadata = FrameMetadata {
             frame_index: index,
             quality_score: None,
-            timestamp_us: self.read_timestamp(index),
+            timestamp_us: self.timestamp(index),
         };
         Ok(frame)
     }
 
-    /// Read per-frame timestamp from the optional trailer.
-    fn read_timestamp(&self, index: usize) -> Option<u64> {
+    /// Per-frame capture timestamp from the optional trailer, if present.
+    pub fn timestamp(&self, index: usize) -> Option<u64> {
+        let (file, local) = self.locate(index).ok()?;
         let trailer_offset =
-            SER_HEADER_SIZE + self.header.frame_byte_size() * self.header.frame_count as usize;
-        let ts_offset = trailer_offset + index * 8;
-        if ts_offset + 8 <= self.mmap.len() {
-            let bytes = &self.mmap[ts_offset..ts_offset + 8];
+            SER_HEADER_SIZE + file.header.frame_byte_size() * file.header.frame_count as usize;
+        let ts_offset = trailer_offset + local * 8;
+        if ts_offset + 8 <= file.mmap.len() {
+            let bytes = &file.mmap[ts_offset..ts_offset + 8];
             Some(u64::from_le_bytes(bytes.try_into().ok()?))
         } else {
             None
@@ -318,6 +391,59 @@ impl SerReader {
     }
 }
 
+/// Map and validate one SER file whose first frame is sequence frame `first`.
+fn open_file(path: &Path, first: usize) -> Result<SerFile> {
+    let file = File::open(path)?;
+    let mmap = unsafe { Mmap::map(&file)? };
+
+    if mmap.len() < SER_HEADER_SIZE {
+        return Err(JupiterError::InvalidSer(
+            "File too small for SER header".into(),
+        ));
+    }
+
+    if &mmap[0..14] != SER_MAGIC {
+        return Err(JupiterError::InvalidSer(
+            "Missing LUCAM-RECORDER magic".into(),
+        ));
+    }
+
+    let header = parse_header(&mmap[..SER_HEADER_SIZE])?;
+
+    let expected_data_size =
+        SER_HEADER_SIZE + header.frame_byte_size() * header.frame_count as usize;
+    if mmap.len() < expected_data_size {
+        return Err(JupiterError::InvalidSer(format!(
+            "File truncated: expected at least {} bytes, got {}",
+            expected_data_size,
+            mmap.len()
+        )));
+    }
+
+    Ok(SerFile {
+        mmap,
+        header,
+        path: path.to_path_buf(),
+        first,
+    })
+}
+
+/// Short description of a header's frame format, for mismatch errors.
+fn describe(header: &SerHeader) -> String {
+    format!(
+        "{}x{} {}-bit {:?}{}",
+        header.width,
+        header.height,
+        header.pixel_depth,
+        header.color_mode(),
+        if header.little_endian {
+            ""
+        } else {
+            " big-endian"
+        }
+    )
+}
+
 fn parse_header(buf: &[u8]) -> Result<SerHeader> {
     let mut cursor = std::io::Cursor::new(&buf[14..]); // skip magic
 
@@ -3,49 +3,51 @@ use std::sync::Arc;
 use tracing::info;
 
 use crate::color::debayer::{debayer, luminance, DebayerMethod};
-use crate::color::process::process_color_parallel;
+use crate::color::process::try_process_color_parallel;
 use crate::compute::ComputeBackend;
 use crate::error::Result;
-use crate::frame::{ColorFrame, ColorMode, Frame};
+use crate::frame::{AlignmentOffset, ColorFrame, ColorMode, Frame};
 use crate::io::image_io::save_color_image;
 use crate::io::ser::SerReader;
-use crate::quality::gradient::rank_frames_gradient_color_streaming;
-use crate::quality::laplacian::rank_frames_color_streaming;
-use crate::sharpen::deconvolution::{deconvolve, deconvolve_gpu};
+use crate::sharpen::deconvolution::{deconvolve_gpu, deconvolve_loaded, Psf};
 use crate::sharpen::wavelet;
 use crate::stack::drizzle::DrizzleConfig;
+use crate::stack::super_resolution::{super_resolve_with_progress, SuperResolutionConfig};
 
-use super::config::{AlignmentConfig, PipelineConfig, QualityMetric, StackMethod};
+use super::config::{PipelineConfig, StackMethod};
 use super::helpers::{
-    apply_filter_step, compute_offsets_with_progress, drizzle_color_channels_parallel,
-    rank_by_metric, select_frames, shift_color_frames, split_color_channels,
+    align_with_progress, apply_filter_step_color, drizzle_color_channels_parallel, rank_by_metric,
+    rank_color_by_metric_streaming, select_frames, shift_color_frames, split_color_channels,
     stack_color_channels_parallel,
 };
 use super::orchestrator::should_use_streaming;
+use super::session::StageMemo;
 use super::types::{PipelineOutput, PipelineStage, ProgressReporter};
 
-pub(super) fn run_color_pipeline(
+/// Colour path up to and including stacking; post-processing is applied by the caller.
+pub(super) fn stack_color(
     reader: &SerReader,
     config: &PipelineConfig,
     backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
     debayer_method: &DebayerMethod,
     color_mode: &ColorMode,
-    total: usize,
-) -> Result<PipelineOutput> {
+    memo: &StageMemo,
+) -> Result<ColorFrame> {
+    let total = reader.frame_count();
     let is_rgb_bgr = matches!(color_mode, ColorMode::RGB | ColorMode::BGR);
     let streaming = should_use_streaming(reader, config, true);
 
     if streaming {
         info!("Using low-memory streaming mode for color");
-        return run_color_pipeline_streaming(
+        return stack_color_streaming(
             reader,
             config,
             backend,
             reporter,
             debayer_method,
             color_mode,
-            total,
+            memo,
         );
     }
 
@@ -79,14 +81,15 @@ pub(super) fn run_color_pipeline(
 
     // Quality
     reporter.begin_stage(PipelineStage::QualityAssessment, Some(total));
-    let ranked = rank_by_metric(&lum_frames, &config.frame_selection.metric);
+    let ranked =
+        memo.ranked_or(|| Ok(rank_by_metric(&lum_frames, &config.frame_selection.metric)))?;
     reporter.finish_stage();
 
     // Selection
     reporter.begin_stage(PipelineStage::FrameSelection, None);
-    let (selected_indices, quality_scores) =
-        select_frames(&ranked, total, config.frame_selection.select_percentage);
-    let selected_color: Vec<ColorFrame> = selected_indices
+    let (selected_indices, mut quality_scores) =
+        select_frames(&ranked, total, &config.frame_selection)?;
+    let mut selected_color: Vec<ColorFrame> = selected_indices
         .iter()
         .map(|&i| color_frames[i].clone())
         .collect();
@@ -100,50 +103,70 @@ pub(super) fn run_color_pipeline(
     );
     reporter.finish_stage();
 
-    let stacked_color = if let StackMethod::Drizzle(ref drizzle_config) = config.stacking.method {
+    // Compute alignment offsets on luminance
+    let alignment = memo.alignment_or(&selected_indices, || {
+        align_with_progress(
+            &selected_lum,
+            &selected_indices,
+            &config.alignment,
+            backend,
+            reporter,
+        )
+    })?;
+    let offsets = alignment.kept().1;
+    alignment.retain_kept(&mut selected_color);
+    alignment.retain_kept(&mut quality_scores);
+
+    if let StackMethod::Drizzle(ref drizzle_config) = config.stacking.method {
         color_drizzle_flow(
             &selected_color,
-            &selected_lum,
+            &offsets,
             &quality_scores,
-            backend,
             reporter,
             drizzle_config,
-            &config.alignment,
-        )?
+        )
+    } else if let StackMethod::SuperResolution(ref sr_config) = config.stacking.method {
+        color_super_resolution_flow(
+            &selected_color,
+            &offsets,
+            &quality_scores,
+            backend,
+            reporter,
+            sr_config,
+        )
     } else {
-        color_standard_flow(&selected_color, &selected_lum, config, backend, reporter)?
-    };
-
-    apply_post_stack_color(stacked_color, config, backend, reporter)
+        color_standard_flow(&selected_color, &offsets, &quality_scores, config, reporter)
+    }
 }
 
 /// Streaming color pipeline: score via batched read-debayer-luminance-score-drop,
 /// then re-read only selected frames for stacking.
-fn run_color_pipeline_streaming(
+fn stack_color_streaming(
     reader: &SerReader,
     config: &PipelineConfig,
     backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
     debayer_method: &DebayerMethod,
     color_mode: &ColorMode,
-    total: usize,
-) -> Result<PipelineOutput> {
+    memo: &StageMemo,
+) -> Result<ColorFrame> {
+    let total = reader.frame_count();
     // Quality (streaming: read-debayer-luminance-score in batches)
     reporter.begin_stage(PipelineStage::QualityAssessment, Some(total));
-    let ranked = match config.frame_selection.metric {
-        QualityMetric::Laplacian => {
-            rank_frames_color_streaming(reader, color_mode, debayer_method)?
-        }
-        QualityMetric::Gradient => {
-            rank_frames_gradient_color_streaming(reader, color_mode, debayer_method)?
-        }
-    };
+    let ranked = memo.ranked_or(|| {
+        rank_color_by_metric_streaming(
+            reader,
+            &config.frame_selection.metric,
+            color_mode,
+            debayer_method,
+        )
+    })?;
     reporter.finish_stage();
 
     // Selection
     reporter.begin_stage(PipelineStage::FrameSelection, None);
-    let (selected_indices, quality_scores) =
-        select_frames(&ranked, total, config.frame_selection.select_percentage);
+    let (selected_indices, mut quality_scores) =
+        select_frames(&ranked, total, &config.frame_selection)?;
     info!(
         selected = selected_indices.len(),
         total, "Selected best frames (color streaming)"
@@ -152,50 +175,66 @@ fn run_color_pipeline_streaming(
 
     // Re-read only selected color frames from disk
     reporter.begin_stage(PipelineStage::Reading, Some(selected_indices.len()));
-    let selected_color: Vec<ColorFrame> = selected_indices
+    let mut selected_color: Vec<ColorFrame> = selected_indices
         .iter()
         .map(|&i| reader.read_frame_as_color(i, debayer_method))
         .collect::<Result<_>>()?;
     let selected_lum: Vec<Frame> = selected_color.iter().map(luminance).collect();
     reporter.finish_stage();
 
-    let stacked_color = if let StackMethod::Drizzle(ref drizzle_config) = config.stacking.method {
+    // Compute alignment offsets on luminance
+    let alignment = memo.alignment_or(&selected_indices, || {
+        align_with_progress(
+            &selected_lum,
+            &selected_indices,
+            &config.alignment,
+            backend,
+            reporter,
+        )
+    })?;
+    let offsets = alignment.kept().1;
+    alignment.retain_kept(&mut selected_color);
+    alignment.retain_kept(&mut quality_scores);
+
+    if let StackMethod::Drizzle(ref drizzle_config) = config.stacking.method {
         color_drizzle_flow(
             &selected_color,
-            &selected_lum,
+            &offsets,
             &quality_scores,
-            backend,
             reporter,
             drizzle_config,
-            &config.alignment,
-        )?
+        )
+    } else if let StackMethod::SuperResolution(ref sr_config) = config.stacking.method {
+        color_super_resolution_flow(
+            &selected_color,
+            &offsets,
+            &quality_scores,
+            backend,
+            reporter,
+            sr_config,
+        )
     } else {
-        color_standard_flow(&selected_color, &selected_lum, config, backend, reporter)?
-    };
-
-    apply_post_stack_color(stacked_color, config, backend, reporter)
+        color_standard_flow(&selected_color, &offsets, &quality_scores, config, reporter)
+    }
 }
 
 fn color_standard_flow(
     selected_color: &[ColorFrame],
-    selected_lum: &[Frame],
+    offsets: &[AlignmentOffset],
+    quality_scores: &[f64],
     config: &PipelineConfig,
-    backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
 ) -> Result<ColorFrame> {
-    // Compute alignment offsets on luminance
-    let offsets =
-        compute_offsets_with_progress(selected_lum, 0, &config.alignment, backend, reporter)?;
-
     // Apply offsets to each color channel
-    let aligned_color = shift_color_frames(selected_color, &offsets);
+    let aligned_color = shift_color_frames(selected_color, offsets);
 
     // Stack per-channel
     let stack_count = aligned_color.len();
     reporter.begin_stage(PipelineStage::Stacking, Some(stack_count));
     let (red, green, blue) = split_color_channels(&aligned_color);
     let method = &config.stacking.method;
-    let result = stack_color_channels_parallel(&red, &green, &blue, method, reporter)?;
+    let weights = config.stacking.quality_weighted.then_some(quality_scores);
+    let result = stack_color_channels_parallel(&red, &green, &blue, method, weights, reporter)?;
     info!(method = ?method, "Color stacking complete");
     reporter.finish_stage();
 
@@ -204,17 +243,11 @@ fn color_standard_flow(
 
 fn color_drizzle_flow(
     selected_color: &[ColorFrame],
-    selected_lum: &[Frame],
+    offsets: &[AlignmentOffset],
     quality_scores: &[f64],
-    backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
     drizzle_config: &DrizzleConfig,
-    alignment_config: &AlignmentConfig,
 ) -> Result<ColorFrame> {
-    // Compute offsets on luminance
-    let offsets =
-        compute_offsets_with_progress(selected_lum, 0, alignment_config, backend, reporter)?;
-
     // Drizzle per channel
     let drizzle_count = selected_color.len();
     reporter.begin_stage(PipelineStage::Stacking, Some(drizzle_count));
@@ -228,7 +261,7 @@ fn color_drizzle_flow(
         &red,
         &green,
         &blue,
-        &offsets,
+        offsets,
         drizzle_config,
         scores,
         reporter,
@@ -243,6 +276,69 @@ fn color_drizzle_flow(
     Ok(result)
 }
 
+fn color_super_resolution_flow(
+    selected_color: &[ColorFrame],
+    offsets: &[AlignmentOffset],
+    quality_scores: &[f64],
+    backend: &Arc<dyn ComputeBackend>,
+    reporter: &Arc<dyn ProgressReporter>,
+    sr_config: &SuperResolutionConfig,
+) -> Result<ColorFrame> {
+    // Reconstruct per channel; progress follows the red channel
+    reporter.begin_stage(PipelineStage::Stacking, Some(sr_config.iterations));
+    let (red, green, blue) = split_color_channels(selected_color);
+    let scores = Some(quality_scores);
+    let r = reporter.clone();
+    let (sr, (sg, sb)) = rayon::join(
+        || {
+            super_resolve_with_progress(
+                &red,
+                offsets,
+                sr_config,
+                scores,
+                backend.as_ref(),
+                move |done| r.advance(done),
+            )
+        },
+        || {
+            rayon::join(
+                || {
+                    super_resolve_with_progress(
+                        &green,
+                        offsets,
+                        sr_config,
+                        scores,
+                        backend.as_ref(),
+                        |_| {},
+                    )
+                },
+                || {
+                    super_resolve_with_progress(
+                        &blue,
+                        offsets,
+                        sr_config,
+                        scores,
+                        backend.as_ref(),
+                        |_| {},
+                    )
+                },
+            )
+        },
+    );
+    info!(
+        method = "SuperResolution",
+        scale = sr_config.scale,
+        "Color super-resolution stacking complete"
+    );
+    reporter.finish_stage();
+
+    Ok(ColorFrame {
+        red: sr?,
+        green: sg?,
+        blue: sb?,
+    })
+}
+
 /// Post-stacking processing for color path: sharpen -> filter -> write -> return.
 pub(super) fn apply_post_stack_color(
     stacked: ColorFrame,
@@ -253,17 +349,19 @@ pub(super) fn apply_post_stack_color(
     // Sharpening (per-channel)
     let mut result = if let Some(ref sharpening_config) = config.sharpening {
         reporter.begin_stage(PipelineStage::Sharpening, None);
-        let sharpened = process_color_parallel(&stacked, |frame| {
-            let mut f = frame.clone();
-            if let Some(ref deconv_config) = sharpening_config.deconvolution {
+        let deconvolved = if let Some(ref deconv_config) = sharpening_config.deconvolution {
+            let psf = Psf::load(&deconv_config.psf)?;
+            try_process_color_parallel(&stacked, |frame| {
                 if backend.is_gpu() {
-                    f = deconvolve_gpu(&f, deconv_config, &**backend);
+                    deconvolve_gpu(frame, deconv_config, &psf, &**backend)
                 } else {
-                    f = deconvolve(&f, deconv_config);
+                    deconvolve_loaded(frame, deconv_config, &psf).map(|(restored, _)| restored)
                 }
-            }
-            wavelet::sharpen(&f, &sharpening_config.wavelet)
-        });
+            })?
+        } else {
+            stacked
+        };
+        let sharpened = wavelet::sharpen_color(&deconvolved, &sharpening_config.wavelet);
         info!("Color sharpening complete");
         reporter.finish_stage();
         sharpened
@@ -276,7 +374,7 @@ pub(super) fn apply_post_stack_color(
         let total_filters = config.filters.len();
         reporter.begin_stage(PipelineStage::Filtering, Some(total_filters));
         for (i, step) in config.filters.iter().enumerate() {
-            result = process_color_parallel(&result, |frame| apply_filter_step(frame, step));
+            result = apply_filter_step_color(&result, step);
             reporter.advance(i + 1);
         }
         info!(count = total_filters, "Color filters applied");
@@ -6,12 +6,20 @@ use serde::{Deserialize, Serialize};
 use crate::color::debayer::DebayerMethod;
 use crate::compute::DevicePreference;
 use crate::consts::{
-    DEFAULT_CENTROID_THRESHOLD, DEFAULT_ENHANCED_PHASE_UPSAMPLE, DEFAULT_PYRAMID_LEVELS,
+    DEFAULT_BLIND_PSF_SIZE, DEFAULT_CENTROID_THRESHOLD, DEFAULT_DECONV_TILE_SIZE,
+    DEFAULT_ENHANCED_PHASE_UPSAMPLE, DEFAULT_LIMB_EDGE_THRESHOLD, DEFAULT_LIMB_INLIER_TOLERANCE,
+    DEFAULT_LIMB_RANSAC_ITERATIONS, DEFAULT_LUMINANCE_WEIGHT, DEFAULT_PYRAMID_LEVELS,
+    DEFAULT_REFINE_CONVERGENCE, DEFAULT_REFINE_MAX_PASSES, DEFAULT_REFINE_TOP_FRACTION,
+    DEFAULT_REJECT_DRIFT_WINDOW, DEFAULT_REJECT_MAX_DRIFT, DEFAULT_REJECT_MIN_CONFIDENCE,
 };
+use crate::sharpen::regularized_rl::RlOptions;
 use crate::sharpen::wavelet::WaveletParams;
 use crate::stack::drizzle::DrizzleConfig;
 use crate::stack::multi_point::{LocalStackMethod, MultiPointConfig};
+use crate::stack::optical_flow::OpticalFlowConfig;
+use crate::stack::rejection::{GesdParams, Rejection};
 use crate::stack::sigma_clip::SigmaClipParams;
+use crate::stack::super_resolution::SuperResolutionConfig;
 use crate::stack::surface_warp::SurfaceWarpConfig;
 
 /// Memory usage strategy for the pipeline.
@@ -40,6 +48,10 @@ impl fmt::Display for MemoryStrategy {
 pub struct PipelineConfig {
     #[serde(default)]
     pub input: PathBuf,
+    /// Further SER files from the same session, stacked with `input` as one
+    /// sequence: frames are scored and selected across all files together.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_inputs: Vec<PathBuf>,
     #[serde(default)]
     pub output: PathBuf,
     #[serde(default)]
@@ -66,6 +78,37 @@ pub struct PipelineConfig {
     pub filters: Vec<FilterStep>,
 }
 
+impl PipelineConfig {
+    /// `input` followed by `extra_inputs`.
+    pub fn inputs(&self) -> Vec<PathBuf> {
+        std::iter::once(self.input.clone())
+            .chain(self.extra_inputs.iter().cloned())
+            .collect()
+    }
+}
+
+/// Post-processing recipe: the sharpening and filter sections of a
+/// [`PipelineConfig`], applied to an image that is already stacked.
+///
+/// A full pipeline config deserializes as a recipe; its other sections are
+/// ignored.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PostProcessConfig {
+    #[serde(default)]
+    pub sharpening: Option<SharpeningConfig>,
+    #[serde(default)]
+    pub filters: Vec<FilterStep>,
+}
+
+impl From<&PipelineConfig> for PostProcessConfig {
+    fn from(config: &PipelineConfig) -> Self {
+        Self {
+            sharpening: config.sharpening.clone(),
+            filters: config.filters.clone(),
+        }
+    }
+}
+
 /// Configuration for debayering (demosaicing) raw Bayer data.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DebayerConfig {
@@ -81,6 +124,10 @@ pub struct FrameSelectionConfig {
     /// Quality metric to use.
     #[serde(default)]
     pub metric: QualityMetric,
+    /// Explicit frame selection: a CSV/JSON frame report whose `selected`
+    /// flags replace `select_percentage` for the global stacking paths.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_list: Option<PathBuf>,
 }
 
 impl Default for FrameSelectionConfig {
@@ -88,6 +135,7 @@ impl Default for FrameSelectionConfig {
         Self {
             select_percentage: 0.25,
             metric: QualityMetric::default(),
+            frame_list: None,
         }
     }
 }
@@ -117,6 +165,9 @@ pub enum AlignmentMethod {
     /// Coarse-to-fine Gaussian pyramid alignment. Handles large
     /// displacements that exceed FFT wrap-around.
     Pyramid(PyramidConfig),
+    /// Fits a circle or ellipse to the bright limb and aligns on its centre.
+    /// For crescent or featureless disks (Venus, Mercury, crescent Mars).
+    LimbFit(LimbFitConfig),
 }
 
 /// Parameters for enhanced phase correlation (Guizar-Sicairos method).
@@ -181,23 +232,189 @@ impl Default for PyramidConfig {
     }
 }
 
+/// Shape fitted to the limb in limb-fit alignment.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LimbModel {
+    /// Round disks (Venus, Mercury, Mars).
+    #[default]
+    Circle,
+    /// Oblate disks (Jupiter, Saturn's globe).
+    Ellipse,
+}
+
+impl fmt::Display for LimbModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimbModel::Circle => write!(f, "Circle"),
+            LimbModel::Ellipse => write!(f, "Ellipse"),
+        }
+    }
+}
+
+/// Parameters for limb-fit alignment.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LimbFitConfig {
+    /// Shape fitted to the limb.
+    #[serde(default)]
+    pub model: LimbModel,
+    /// Minimum edge strength of a limb point, as a fraction of the strongest
+    /// edge in the frame (0.0-1.0). Keeps the sharp limb and drops the soft
+    /// terminator.
+    #[serde(default = "default_limb_edge_threshold")]
+    pub edge_threshold: f32,
+    /// Number of RANSAC circle or ellipse hypotheses tried per frame.
+    #[serde(default = "default_limb_ransac_iterations")]
+    pub ransac_iterations: usize,
+    /// Maximum distance (px) of an inlier from the fitted circle.
+    #[serde(default = "default_limb_inlier_tolerance")]
+    pub inlier_tolerance: f64,
+}
+
+fn default_limb_edge_threshold() -> f32 {
+    DEFAULT_LIMB_EDGE_THRESHOLD
+}
+
+fn default_limb_ransac_iterations() -> usize {
+    DEFAULT_LIMB_RANSAC_ITERATIONS
+}
+
+fn default_limb_inlier_tolerance() -> f64 {
+    DEFAULT_LIMB_INLIER_TOLERANCE
+}
+
+impl Default for LimbFitConfig {
+    fn default() -> Self {
+        Self {
+            model: LimbModel::default(),
+            edge_threshold: DEFAULT_LIMB_EDGE_THRESHOLD,
+            ransac_iterations: DEFAULT_LIMB_RANSAC_ITERATIONS,
+            inlier_tolerance: DEFAULT_LIMB_INLIER_TOLERANCE,
+        }
+    }
+}
+
 /// Alignment configuration for the pipeline.
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct AlignmentConfig {
     /// The alignment algorithm to use.
     #[serde(default)]
     pub method: AlignmentMethod,
+    /// Re-align against a stacked reference instead of a single frame.
+    /// `None` aligns everything to the best frame only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refinement: Option<ReferenceRefinement>,
+    /// Reject misregistered frames before stacking. `None` keeps every frame.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<OutlierRejection>,
+}
+
+/// Multi-pass alignment against a stacked reference.
+///
+/// After the initial pass against the best frame, the best `top_fraction` of
+/// the selected frames are shifted and averaged into a reference and every
+/// frame is re-aligned to it. Further passes rebuild the reference from the
+/// new offsets until no offset moves by more than `convergence` pixels.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReferenceRefinement {
+    /// Fraction of the best selected frames averaged into the reference.
+    #[serde(default = "default_refine_top_fraction")]
+    pub top_fraction: f32,
+    /// Maximum number of re-alignment passes (1 = build the reference once).
+    #[serde(default = "default_refine_max_passes")]
+    pub max_passes: usize,
+    /// Stop once the largest offset change between passes is below this (px).
+    #[serde(default = "default_refine_convergence")]
+    pub convergence: f64,
+}
+
+fn default_refine_top_fraction() -> f32 {
+    DEFAULT_REFINE_TOP_FRACTION
+}
+
+fn default_refine_max_passes() -> usize {
+    DEFAULT_REFINE_MAX_PASSES
+}
+
+fn default_refine_convergence() -> f64 {
+    DEFAULT_REFINE_CONVERGENCE
+}
+
+impl Default for ReferenceRefinement {
+    fn default() -> Self {
+        Self {
+            top_fraction: DEFAULT_REFINE_TOP_FRACTION,
+            max_passes: DEFAULT_REFINE_MAX_PASSES,
+            convergence: DEFAULT_REFINE_CONVERGENCE,
+        }
+    }
+}
+
+/// Per-frame checks on global alignment results.
+///
+/// A frame is suspect when its registration confidence is below
+/// `min_confidence` or its offset lies more than `max_drift` pixels from the
+/// drift track (median offset of its `drift_window` nearest frames in capture
+/// order). Suspect frames are re-aligned with pyramid alignment when `retry`
+/// is set and rejected if they still fail.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OutlierRejection {
+    /// Minimum correlation peak-to-mean ratio of a registered frame. Not
+    /// applied to centroid and limb-fit alignment, which have no peak.
+    #[serde(default = "default_reject_min_confidence")]
+    pub min_confidence: f64,
+    /// Number of neighbouring frames the drift track is smoothed over.
+    #[serde(default = "default_reject_drift_window")]
+    pub drift_window: usize,
+    /// Maximum distance (px) of an offset from the drift track.
+    #[serde(default = "default_reject_max_drift")]
+    pub max_drift: f64,
+    /// Re-align suspect frames with the configured method, starting from the
+    /// drift track, before rejecting them.
+    #[serde(default = "default_true")]
+    pub retry: bool,
+}
+
+fn default_reject_min_confidence() -> f64 {
+    DEFAULT_REJECT_MIN_CONFIDENCE
+}
+
+fn default_reject_drift_window() -> usize {
+    DEFAULT_REJECT_DRIFT_WINDOW
+}
+
+fn default_reject_max_drift() -> f64 {
+    DEFAULT_REJECT_MAX_DRIFT
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for OutlierRejection {
+    fn default() -> Self {
+        Self {
+            min_confidence: DEFAULT_REJECT_MIN_CONFIDENCE,
+            drift_window: DEFAULT_REJECT_DRIFT_WINDOW,
+            max_drift: DEFAULT_REJECT_MAX_DRIFT,
+            retry: true,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StackingConfig {
     pub method: StackMethod,
+    /// Weight each frame by its quality score in mean, median and rejection
+    /// stacking (drizzle and super-resolution have their own switch).
+    #[serde(default)]
+    pub quality_weighted: bool,
 }
 
 impl Default for StackingConfig {
     fn default() -> Self {
         Self {
             method: StackMethod::Mean,
+            quality_weighted: false,
         }
     }
 }
@@ -207,9 +424,77 @@ pub enum StackMethod {
     Mean,
     Median,
     SigmaClip(SigmaClipParams),
+    /// Winsorized sigma clipping (see [`Rejection::Winsorized`]).
+    WinsorizedSigmaClip(SigmaClipParams),
+    /// Linear-fit clipping (see [`Rejection::LinearFit`]).
+    LinearFitClip(SigmaClipParams),
+    /// Generalized extreme studentized deviate rejection.
+    Gesd(GesdParams),
     MultiPoint(MultiPointConfig),
     Drizzle(DrizzleConfig),
     SurfaceWarp(SurfaceWarpConfig),
+    OpticalFlow(OpticalFlowConfig),
+    SuperResolution(SuperResolutionConfig),
+}
+
+impl StackMethod {
+    /// The per-pixel rejection this method applies, or `None` for methods
+    /// that are not per-pixel rejection stacks (mean, median and the
+    /// alignment-aware methods).
+    pub fn rejection(&self) -> Option<Rejection> {
+        match self {
+            StackMethod::SigmaClip(params) => Some(params.rejection()),
+            StackMethod::WinsorizedSigmaClip(params) => Some(Rejection::Winsorized {
+                sigma: params.sigma,
+                iterations: params.iterations,
+            }),
+            StackMethod::LinearFitClip(params) => Some(Rejection::LinearFit {
+                sigma: params.sigma,
+                iterations: params.iterations,
+            }),
+            StackMethod::Gesd(params) => Some(params.rejection()),
+            _ => None,
+        }
+    }
+
+    /// Whether the low-memory mono path replaces this method with a
+    /// streaming approximation (histogram median, running sigma clip).
+    pub fn streams_approximately(&self) -> bool {
+        matches!(self, StackMethod::Median | StackMethod::SigmaClip(_))
+    }
+
+    /// Whether the method scores and selects frames itself (multi-point,
+    /// surface warp and optical flow) rather than stacking a global selection.
+    pub fn selects_own_frames(&self) -> bool {
+        matches!(
+            self,
+            StackMethod::MultiPoint(_) | StackMethod::SurfaceWarp(_) | StackMethod::OpticalFlow(_)
+        )
+    }
+
+    /// The metric the method ranks frames with, for the methods that select
+    /// their own frames; the others use the frame selection's metric.
+    pub fn own_quality_metric(&self) -> Option<&QualityMetric> {
+        match self {
+            StackMethod::MultiPoint(c) => Some(&c.quality_metric),
+            StackMethod::SurfaceWarp(c) => Some(&c.quality_metric),
+            StackMethod::OpticalFlow(c) => Some(&c.quality_metric),
+            _ => None,
+        }
+    }
+
+    /// This method restricted to an explicit frame list, for the methods
+    /// that select frames themselves. Other methods are returned unchanged.
+    pub fn with_frames(&self, frames: Vec<usize>) -> Self {
+        let mut method = self.clone();
+        match method {
+            StackMethod::MultiPoint(ref mut c) => c.frames = Some(frames),
+            StackMethod::SurfaceWarp(ref mut c) => c.frames = Some(frames),
+            StackMethod::OpticalFlow(ref mut c) => c.frames = Some(frames),
+            _ => {}
+        }
+        method
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
@@ -221,21 +506,106 @@ pub struct SharpeningConfig {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum PsfModel {
-    Gaussian { sigma: f32 },
-    Kolmogorov { seeing: f32 },
-    Airy { radius: f32 },
+    Gaussian {
+        sigma: f32,
+    },
+    Kolmogorov {
+        seeing: f32,
+    },
+    Airy {
+        radius: f32,
+    },
+    /// PSF cut out of an image of a point source: a stacked star, or a small
+    /// moon such as Io or Ganymede. Without a `region` the whole image is used.
+    Measured {
+        path: PathBuf,
+        #[serde(default)]
+        region: Option<PsfRegion>,
+    },
+    /// Gaussian whose width is estimated from the edge-spread function of the
+    /// planet's limb in the image being deconvolved.
+    Limb,
+}
+
+/// Rectangle of an image holding the point source for a measured PSF.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PsfRegion {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum DeconvolutionMethod {
-    RichardsonLucy { iterations: usize },
-    Wiener { noise_ratio: f32 },
+    /// Richardson-Lucy, optionally regularized, damped, edge-padded and
+    /// stopped early (see [`RlOptions`]); `iterations` is then an upper
+    /// bound.
+    RichardsonLucy {
+        iterations: usize,
+        #[serde(flatten)]
+        options: RlOptions,
+    },
+    Wiener {
+        noise_ratio: f32,
+    },
+    /// Richardson-Lucy that also estimates the PSF, starting from the
+    /// configured model and confined to a `psf_size` x `psf_size` support.
+    BlindRichardsonLucy {
+        iterations: usize,
+        #[serde(default = "default_blind_psf_size")]
+        psf_size: usize,
+    },
+}
+
+fn default_blind_psf_size() -> usize {
+    DEFAULT_BLIND_PSF_SIZE
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DeconvolutionConfig {
     pub method: DeconvolutionMethod,
     pub psf: PsfModel,
+    /// Deconvolve in overlapping tiles, each with its own PSF, instead of
+    /// over the whole image at once.
+    #[serde(default)]
+    pub tiling: Option<TiledDeconvolution>,
+}
+
+/// Spatially varying deconvolution over overlapping tiles.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TiledDeconvolution {
+    /// Tile side length in pixels; tiles overlap by half.
+    #[serde(default = "default_deconv_tile_size")]
+    pub tile_size: usize,
+    /// How each tile's PSF is chosen.
+    #[serde(default)]
+    pub psf: TilePsf,
+}
+
+fn default_deconv_tile_size() -> usize {
+    DEFAULT_DECONV_TILE_SIZE
+}
+
+impl Default for TiledDeconvolution {
+    fn default() -> Self {
+        Self {
+            tile_size: DEFAULT_DECONV_TILE_SIZE,
+            psf: TilePsf::default(),
+        }
+    }
+}
+
+/// PSF assignment for tiled deconvolution.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TilePsf {
+    /// The configured PSF in every tile. Blind deconvolution still refines
+    /// it tile by tile.
+    Uniform,
+    /// The configured PSF, widened or narrowed per tile by the tile's local
+    /// sharpness relative to the average tile.
+    #[default]
+    LocalQuality,
 }
 
 /// A single post-processing filter step.
@@ -260,6 +630,261 @@ pub enum FilterStep {
     },
     /// Gaussian blur.
     GaussianBlur { sigma: f32 },
+    /// Non-local means denoise; `strength` is in units of the estimated
+    /// noise sigma.
+    NonLocalMeans {
+        strength: f32,
+        patch_radius: usize,
+        search_radius: usize,
+        /// On color images, denoise the color differences only.
+        #[serde(default)]
+        chroma_only: bool,
+    },
+    /// Edge-preserving bilateral filter.
+    Bilateral {
+        spatial_sigma: f32,
+        range_sigma: f32,
+        #[serde(default)]
+        chroma_only: bool,
+    },
+    /// Self-guided filter.
+    GuidedFilter {
+        radius: usize,
+        epsilon: f32,
+        #[serde(default)]
+        chroma_only: bool,
+    },
+    /// Wavelet denoise with automatic (BayesShrink) per-layer thresholds.
+    WaveletDenoise {
+        layers: usize,
+        strength: f32,
+        #[serde(default)]
+        chroma_only: bool,
+    },
+    /// Tone curve through `points` (input, output pairs in 0–1), interpolated
+    /// with a monotone cubic spline.
+    Curves {
+        #[serde(default)]
+        channel: ColorChannel,
+        points: Vec<[f32; 2]>,
+    },
+    /// Black and white points followed by gamma correction.
+    Levels {
+        #[serde(default)]
+        channel: ColorChannel,
+        black_point: f32,
+        white_point: f32,
+        gamma: f32,
+    },
+    /// Saturation and vibrance in LCh; `vibrance` boosts weakly coloured
+    /// pixels more than strongly coloured ones. No effect on mono images.
+    Saturation {
+        saturation: f32,
+        #[serde(default)]
+        vibrance: f32,
+    },
+    /// Per-channel gains. No effect on mono images.
+    WhiteBalance(WhiteBalance),
+    /// Rotation about the image centre, counter-clockwise as displayed.
+    /// `expand` grows the canvas so the corners are kept.
+    Rotate {
+        degrees: f32,
+        #[serde(default)]
+        expand: bool,
+    },
+    /// Mirror left to right and/or top to bottom.
+    Flip { horizontal: bool, vertical: bool },
+    /// Resize by `scale` (2.0 doubles the width and height).
+    Resample {
+        scale: f32,
+        #[serde(default)]
+        method: ResampleMethod,
+    },
+    /// Crop or pad to a `width` × `height` canvas centred on the detected
+    /// planet (on the image centre when none is found).
+    Canvas { width: usize, height: usize },
+}
+
+/// Interpolation kernel of a resample step.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResampleMethod {
+    /// Windowed sinc over three lobes: sharpest, with slight ringing at
+    /// hard edges.
+    #[default]
+    Lanczos3,
+    /// Keys cubic convolution: softer, with less ringing.
+    Bicubic,
+}
+
+/// Channel of a color image that a curve or levels step applies to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColorChannel {
+    /// Every channel alike; the only choice that matters for mono images.
+    #[default]
+    All,
+    Red,
+    Green,
+    Blue,
+    /// The luminance; the channels are scaled together, keeping hue and
+    /// saturation.
+    Luminance,
+}
+
+impl ColorChannel {
+    pub const ALL: &[Self] = &[
+        Self::All,
+        Self::Red,
+        Self::Green,
+        Self::Blue,
+        Self::Luminance,
+    ];
+}
+
+/// How a white balance step chooses its channel gains.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WhiteBalance {
+    /// Fixed gains per channel.
+    Manual { red: f32, green: f32, blue: f32 },
+    /// Equalize the channel means over the whole image.
+    GreyWorld,
+    /// Equalize the channel means over the fitted planet disk; falls back to
+    /// grey world when no disk is found.
+    PlanetDisk,
+}
+
+/// Filter a mono image was captured through, for channel composition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ChannelRole {
+    Luminance,
+    Red,
+    Green,
+    Blue,
+    /// Near infrared (IR-pass filter).
+    Infrared,
+    /// Methane absorption band (CH4, 889 nm).
+    Methane,
+}
+
+impl ChannelRole {
+    pub const ALL: &[Self] = &[
+        Self::Luminance,
+        Self::Red,
+        Self::Green,
+        Self::Blue,
+        Self::Infrared,
+        Self::Methane,
+    ];
+}
+
+/// How the images of a composition are registered to each other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegistrationMode {
+    /// Use the images as they are.
+    None,
+    /// Phase correlation shift.
+    #[default]
+    Translation,
+    /// Shift, rotation and scale (Fourier–Mellin), for images taken with
+    /// the camera rotated or refocused between filters.
+    Similarity,
+}
+
+/// How stacked mono images are combined into a color image.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompositionConfig {
+    /// Image placed in the red channel.
+    pub red: ChannelRole,
+    /// Image placed in the green channel.
+    pub green: ChannelRole,
+    /// Image placed in the blue channel.
+    pub blue: ChannelRole,
+    /// Image whose brightness replaces the lightness of the color image
+    /// (in CIE Lab), if any.
+    pub luminance: Option<ChannelRole>,
+    /// Blend between the color image's own lightness (0) and that of the
+    /// luminance image (1).
+    pub luminance_weight: f32,
+    pub registration: RegistrationMode,
+    /// Stretch each image to the full range before combining, so
+    /// differences in filter transmission and exposure do not tint the
+    /// result.
+    pub normalize: bool,
+}
+
+impl CompositionConfig {
+    /// Red, green and blue images in their own channels.
+    pub fn rgb() -> Self {
+        Self {
+            red: ChannelRole::Red,
+            green: ChannelRole::Green,
+            blue: ChannelRole::Blue,
+            luminance: None,
+            luminance_weight: DEFAULT_LUMINANCE_WEIGHT,
+            registration: RegistrationMode::default(),
+            normalize: true,
+        }
+    }
+
+    /// RGB with a luminance image layered on top.
+    pub fn lrgb() -> Self {
+        Self {
+            luminance: Some(ChannelRole::Luminance),
+            ..Self::rgb()
+        }
+    }
+
+    /// RGB with an infrared image as the luminance.
+    pub fn ir_rgb() -> Self {
+        Self {
+            luminance: Some(ChannelRole::Infrared),
+            ..Self::rgb()
+        }
+    }
+
+    /// RGB with the methane image in place of the `replace` channel
+    /// (`Red`, `Green` or `Blue`).
+    pub fn false_color(replace: ChannelRole) -> Self {
+        let mut config = Self::rgb();
+        match replace {
+            ChannelRole::Red => config.red = ChannelRole::Methane,
+            ChannelRole::Green => config.green = ChannelRole::Methane,
+            _ => config.blue = ChannelRole::Methane,
+        }
+        config
+    }
+
+    /// The images this composition needs, without repeats.
+    pub fn required_roles(&self) -> Vec<ChannelRole> {
+        let mut roles = Vec::new();
+        for role in [self.red, self.green, self.blue]
+            .into_iter()
+            .chain(self.luminance)
+        {
+            if !roles.contains(&role) {
+                roles.push(role);
+            }
+        }
+        roles
+    }
+}
+
+impl Default for CompositionConfig {
+    fn default() -> Self {
+        Self::rgb()
+    }
+}
+
+impl FilterStep {
+    /// Whether this step only touches the color differences of a color image.
+    pub fn chroma_only(&self) -> bool {
+        match self {
+            FilterStep::NonLocalMeans { chroma_only, .. }
+            | FilterStep::Bilateral { chroma_only, .. }
+            | FilterStep::GuidedFilter { chroma_only, .. }
+            | FilterStep::WaveletDenoise { chroma_only, .. } => *chroma_only,
+            _ => false,
+        }
+    }
 }
 
 // --- Display implementations ---
@@ -287,6 +912,9 @@ impl fmt::Display for AlignmentMethod {
             AlignmentMethod::Pyramid(cfg) => {
                 write!(f, "Pyramid ({} levels)", cfg.levels)
             }
+            AlignmentMethod::LimbFit(cfg) => {
+                write!(f, "Limb Fit ({}, edge={})", cfg.model, cfg.edge_threshold)
+            }
         }
     }
 }
@@ -297,11 +925,31 @@ impl fmt::Display for StackMethod {
             StackMethod::Mean => write!(f, "Mean"),
             StackMethod::Median => write!(f, "Median"),
             StackMethod::SigmaClip(_) => write!(f, "Sigma Clip"),
+            StackMethod::WinsorizedSigmaClip(_) => write!(f, "Winsorized Sigma Clip"),
+            StackMethod::LinearFitClip(_) => write!(f, "Linear Fit Clip"),
+            StackMethod::Gesd(_) => write!(f, "GESD"),
             StackMethod::MultiPoint(_) => write!(f, "Multi-Point"),
             StackMethod::Drizzle(cfg) => {
                 write!(f, "Drizzle ({}x, pixfrac={})", cfg.scale, cfg.pixfrac)
             }
             StackMethod::SurfaceWarp(_) => write!(f, "Surface Warp"),
+            StackMethod::OpticalFlow(_) => write!(f, "Optical Flow"),
+            StackMethod::SuperResolution(cfg) => {
+                write!(
+                    f,
+                    "Super-Resolution ({}x, {} iterations)",
+                    cfg.scale, cfg.iterations
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for TilePsf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TilePsf::Uniform => write!(f, "Uniform"),
+            TilePsf::LocalQuality => write!(f, "From local quality"),
         }
     }
 }
@@ -309,12 +957,38 @@ impl fmt::Display for StackMethod {
 impl fmt::Display for DeconvolutionMethod {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            DeconvolutionMethod::RichardsonLucy { iterations } => {
-                write!(f, "Richardson-Lucy ({iterations} iterations)")
+            DeconvolutionMethod::RichardsonLucy {
+                iterations,
+                options,
+            } => {
+                write!(f, "Richardson-Lucy ({iterations} iterations")?;
+                if options.tv_weight > 0.0 {
+                    write!(f, ", TV {}", options.tv_weight)?;
+                }
+                if options.damping > 0.0 {
+                    write!(f, ", damped at {}\u{03c3}", options.damping)?;
+                }
+                if options.auto_stop {
+                    write!(f, ", auto-stop")?;
+                }
+                if options.pad_edges {
+                    write!(f, ", padded")?;
+                }
+                if options.planet_mask {
+                    write!(f, ", planet mask")?;
+                }
+                write!(f, ")")
             }
             DeconvolutionMethod::Wiener { noise_ratio } => {
                 write!(f, "Wiener (noise ratio={noise_ratio})")
             }
+            DeconvolutionMethod::BlindRichardsonLucy {
+                iterations,
+                psf_size,
+            } => write!(
+                f,
+                "Blind Richardson-Lucy ({iterations} rounds, PSF {psf_size}x{psf_size} px)"
+            ),
         }
     }
 }
@@ -325,10 +999,24 @@ impl fmt::Display for PsfModel {
             PsfModel::Gaussian { sigma } => write!(f, "Gaussian (\u{03c3}={sigma} px)"),
             PsfModel::Kolmogorov { seeing } => write!(f, "Kolmogorov (seeing={seeing} px)"),
             PsfModel::Airy { radius } => write!(f, "Airy (radius={radius} px)"),
+            PsfModel::Measured { path, region } => {
+                let name = path.file_name().unwrap_or(path.as_os_str());
+                match region {
+                    Some(r) => write!(f, "Measured ({}, region {r})", name.to_string_lossy()),
+                    None => write!(f, "Measured ({})", name.to_string_lossy()),
+                }
+            }
+            PsfModel::Limb => write!(f, "Estimated from limb"),
         }
     }
 }
 
+impl fmt::Display for PsfRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{} {}x{}", self.x, self.y, self.width, self.height)
+    }
+}
+
 impl fmt::Display for FilterStep {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -357,6 +1045,124 @@ impl fmt::Display for FilterStep {
                 threshold,
             } => write!(f, "Unsharp Mask (r={radius}, a={amount}, t={threshold})"),
             FilterStep::GaussianBlur { sigma } => write!(f, "Gaussian Blur (\u{03c3}={sigma})"),
+            FilterStep::NonLocalMeans {
+                strength,
+                patch_radius,
+                search_radius,
+                ..
+            } => write!(
+                f,
+                "Non-Local Means (h={strength}, patch={patch_radius}, search={search_radius})"
+            ),
+            FilterStep::Bilateral {
+                spatial_sigma,
+                range_sigma,
+                ..
+            } => write!(
+                f,
+                "Bilateral (\u{03c3}s={spatial_sigma}, \u{03c3}r={range_sigma})"
+            ),
+            FilterStep::GuidedFilter {
+                radius, epsilon, ..
+            } => write!(f, "Guided Filter (r={radius}, \u{03b5}={epsilon})"),
+            FilterStep::WaveletDenoise {
+                layers, strength, ..
+            } => write!(f, "Wavelet Denoise ({layers} layers, strength={strength})"),
+            FilterStep::Curves { channel, points } => {
+                write!(f, "Curves ({channel}, {} points)", points.len())
+            }
+            FilterStep::Levels {
+                channel,
+                black_point,
+                white_point,
+                gamma,
+            } => write!(
+                f,
+                "Levels ({channel}: black={black_point}, white={white_point}, gamma={gamma})"
+            ),
+            FilterStep::Saturation {
+                saturation,
+                vibrance,
+            } => write!(f, "Saturation (s={saturation}, vibrance={vibrance})"),
+            FilterStep::WhiteBalance(balance) => write!(f, "White Balance ({balance})"),
+            FilterStep::Rotate { degrees, expand } => {
+                write!(f, "Rotate ({degrees}\u{00b0}")?;
+                if *expand {
+                    write!(f, ", expand")?;
+                }
+                write!(f, ")")
+            }
+            FilterStep::Flip {
+                horizontal,
+                vertical,
+            } => match (horizontal, vertical) {
+                (true, true) => write!(f, "Flip (both)"),
+                (true, false) => write!(f, "Flip (horizontal)"),
+                (false, true) => write!(f, "Flip (vertical)"),
+                (false, false) => write!(f, "Flip (none)"),
+            },
+            FilterStep::Resample { scale, method } => write!(f, "Resample ({scale}x, {method})"),
+            FilterStep::Canvas { width, height } => write!(f, "Canvas ({width}x{height})"),
+        }?;
+        if self.chroma_only() {
+            write!(f, " [chroma]")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ColorChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorChannel::All => write!(f, "RGB"),
+            ColorChannel::Red => write!(f, "Red"),
+            ColorChannel::Green => write!(f, "Green"),
+            ColorChannel::Blue => write!(f, "Blue"),
+            ColorChannel::Luminance => write!(f, "Luminance"),
+        }
+    }
+}
+
+impl fmt::Display for ResampleMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResampleMethod::Lanczos3 => write!(f, "Lanczos"),
+            ResampleMethod::Bicubic => write!(f, "Bicubic"),
+        }
+    }
+}
+
+impl fmt::Display for WhiteBalance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WhiteBalance::Manual { red, green, blue } => {
+                write!(f, "R={red}, G={green}, B={blue}")
+            }
+            WhiteBalance::GreyWorld => write!(f, "Grey World"),
+            WhiteBalance::PlanetDisk => write!(f, "Planet Disk"),
+        }
+    }
+}
+
+impl fmt::Display for ChannelRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelRole::Luminance => write!(f, "L"),
+            ChannelRole::Red => write!(f, "R"),
+            ChannelRole::Green => write!(f, "G"),
+            ChannelRole::Blue => write!(f, "B"),
+            ChannelRole::Infrared => write!(f, "IR"),
+            ChannelRole::Methane => write!(f, "CH4"),
+        }
+    }
+}
+
+impl fmt::Display for RegistrationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistrationMode::None => write!(f, "None"),
+            RegistrationMode::Translation => write!(f, "Translation"),
+            RegistrationMode::Similarity => write!(f, "Rotation + Scale"),
         }
     }
 }
@@ -369,6 +1175,16 @@ impl fmt::Display for LocalStackMethod {
             LocalStackMethod::SigmaClip { sigma, iterations } => {
                 write!(f, "Sigma Clip (\u{03c3}={sigma}, {iterations} iter)")
             }
+            LocalStackMethod::WinsorizedSigmaClip { sigma, iterations } => {
+                write!(f, "Winsorized (\u{03c3}={sigma}, {iterations} iter)")
+            }
+            LocalStackMethod::LinearFitClip { sigma, iterations } => {
+                write!(f, "Linear Fit (\u{03c3}={sigma}, {iterations} iter)")
+            }
+            LocalStackMethod::Gesd {
+                max_outliers,
+                alpha,
+            } => write!(f, "GESD (max={max_outliers}, \u{03b1}={alpha})"),
         }
     }
 }
@@ -1,24 +1,43 @@
 use std::sync::Arc;
 
+use ndarray::Array2;
+
 use tracing::info;
 
-use crate::align::{compute_offset_configured, shift_frame};
+use crate::align::{compute_offsets_configured_with_progress, shift_frame};
+use crate::color::debayer::{luminance, DebayerMethod};
+use crate::color::process::{
+    from_luma_chroma, process_color_parallel, process_luminance, read_luminance_frame,
+    to_luma_chroma,
+};
 use crate::compute::ComputeBackend;
+use crate::consts::CHROMA_OFFSET;
 use crate::error::Result;
+use crate::filters::color_balance::{saturation, white_balance};
+use crate::filters::curves::apply_curve;
+use crate::filters::denoise::{bilateral, guided_filter, non_local_means, wavelet_denoise};
 use crate::filters::gaussian_blur::gaussian_blur;
 use crate::filters::histogram::{auto_stretch, histogram_stretch};
-use crate::filters::levels::{brightness_contrast, gamma_correct};
+use crate::filters::levels::{brightness_contrast, gamma_correct, levels};
+use crate::filters::transform::{canvas, canvas_at, flip, planet_center, resample, rotate};
 use crate::filters::unsharp_mask::unsharp_mask;
-use crate::frame::{AlignmentOffset, ColorFrame, Frame, QualityScore};
+use crate::frame::{AlignmentOffset, ColorFrame, ColorMode, Frame, QualityScore};
+use crate::io::frame_report::{select_listed, FrameReport};
 use crate::io::ser::SerReader;
-use crate::quality::gradient::{rank_frames_gradient, rank_frames_gradient_streaming};
-use crate::quality::laplacian::{rank_frames, rank_frames_streaming};
+use crate::quality::gradient::{
+    rank_frames_gradient, rank_frames_gradient_color_streaming, rank_frames_gradient_streaming,
+};
+use crate::quality::laplacian::{rank_frames, rank_frames_color_streaming, rank_frames_streaming};
 use crate::stack::drizzle::{drizzle_stack_with_progress, DrizzleConfig};
-use crate::stack::mean::mean_stack_with_progress;
-use crate::stack::median::median_stack;
-use crate::stack::sigma_clip::sigma_clip_stack;
+use crate::stack::global::{align_candidates, global_candidates, GlobalAlignment};
+use crate::stack::mean::{mean_stack_with_progress, weighted_mean_stack_with_progress};
+use crate::stack::median::{median_stack, weighted_median_stack};
+use crate::stack::rejection::rejection_stack;
 
-use super::config::{AlignmentConfig, FilterStep, QualityMetric, StackMethod};
+use super::config::{
+    AlignmentConfig, ColorChannel, FilterStep, FrameSelectionConfig, QualityMetric, StackMethod,
+};
+use super::session::{CachedAlignment, StageMemo};
 use super::types::{PipelineStage, ProgressReporter};
 
 pub(super) fn rank_by_metric(
@@ -42,72 +61,198 @@ pub(super) fn rank_by_metric_streaming(
     }
 }
 
+/// Colour streaming variant: score the luminance of each debayered frame.
+pub(super) fn rank_color_by_metric_streaming(
+    reader: &SerReader,
+    metric: &QualityMetric,
+    color_mode: &ColorMode,
+    debayer_method: &DebayerMethod,
+) -> Result<Vec<(usize, QualityScore)>> {
+    match metric {
+        QualityMetric::Laplacian => rank_frames_color_streaming(reader, color_mode, debayer_method),
+        QualityMetric::Gradient => {
+            rank_frames_gradient_color_streaming(reader, color_mode, debayer_method)
+        }
+    }
+}
+
+/// Global alignment for the methods that select their own frames, reusing
+/// the memo's ranking and offsets when they are cached.
+///
+/// Frames are scored with `metric` on luminance (`debayer_method` is `Some`
+/// for colour sources). The whole sequence is ranked, as the other flows do,
+/// so the ranking can be shared with them; only `frames` are aligned when
+/// given.
+pub(super) fn global_align_memo(
+    reader: &SerReader,
+    frames: Option<&[usize]>,
+    metric: &QualityMetric,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+    debayer: Option<(&DebayerMethod, &ColorMode)>,
+    memo: &StageMemo,
+) -> Result<GlobalAlignment> {
+    let total = reader.frame_count();
+    let candidates = global_candidates(total, frames)?;
+    let ranked = memo.ranked_or(|| match debayer {
+        Some((method, color_mode)) => {
+            rank_color_by_metric_streaming(reader, metric, color_mode, method)
+        }
+        None => rank_by_metric_streaming(reader, metric),
+    })?;
+    let mut scores = vec![0.0; total];
+    for (i, score) in ranked {
+        if candidates.binary_search(&i).is_ok() {
+            scores[i] = score.composite;
+        }
+    }
+
+    let aligned = memo.alignment_or(&candidates, || {
+        let read = |i: usize| match debayer {
+            Some((method, color_mode)) => {
+                Ok(read_luminance_frame(reader, i, color_mode, method)?.data)
+            }
+            None => Ok(reader.read_frame(i)?.data),
+        };
+        align_candidates(&candidates, &scores, read, alignment, backend)
+    })?;
+    Ok(GlobalAlignment::from_parts(
+        scores,
+        &aligned,
+        frames.is_some(),
+    ))
+}
+
+/// Pick the frames to stack, best first.
+///
+/// Uses the explicit frame list when one is configured, otherwise keeps the
+/// top `select_percentage` of the ranking.
 pub(super) fn select_frames(
     ranked: &[(usize, QualityScore)],
     total: usize,
-    select_percentage: f32,
-) -> (Vec<usize>, Vec<f64>) {
-    let keep = (total as f32 * select_percentage).ceil() as usize;
-    let keep = keep.max(1).min(total);
-    let top: Vec<_> = ranked.iter().take(keep).collect();
+    selection: &FrameSelectionConfig,
+) -> Result<(Vec<usize>, Vec<f64>)> {
+    let top: Vec<(usize, QualityScore)> = match &selection.frame_list {
+        Some(path) => {
+            let report = FrameReport::load(path)?;
+            let listed = select_listed(ranked, &report.selected_indices(), total)?;
+            info!(path = %path.display(), selected = listed.len(), "Using explicit frame list");
+            listed
+        }
+        None => {
+            let keep = (total as f32 * selection.select_percentage).ceil() as usize;
+            let keep = keep.max(1).min(total);
+            ranked.iter().take(keep).cloned().collect()
+        }
+    };
     let indices: Vec<usize> = top.iter().map(|(i, _)| *i).collect();
     let scores: Vec<f64> = top.iter().map(|(_, s)| s.composite).collect();
-    (indices, scores)
+    Ok((indices, scores))
 }
 
+/// The configured stacking method, with the explicit frame list handed to
+/// the methods that select frames themselves.
+pub(super) fn method_with_frame_list(
+    method: &StackMethod,
+    selection: &FrameSelectionConfig,
+) -> Result<StackMethod> {
+    match selection.frame_list {
+        Some(ref path) if method.selects_own_frames() => {
+            let frames = FrameReport::load(path)?.selected_indices();
+            info!(path = %path.display(), selected = frames.len(), "Using explicit frame list");
+            Ok(method.with_frames(frames))
+        }
+        _ => Ok(method.clone()),
+    }
+}
+
+/// Stack aligned frames with one of the standard per-pixel methods.
+///
+/// `weights` are the frames' quality scores when the stack is
+/// quality-weighted, `None` for equal weights.
 pub(super) fn stack_frames_with_progress(
     frames: &[Frame],
     method: &StackMethod,
+    weights: Option<&[f64]>,
     on_progress: impl Fn(usize),
 ) -> Result<Frame> {
-    match method {
-        StackMethod::Mean => mean_stack_with_progress(frames, on_progress),
-        StackMethod::Median => {
-            let result = median_stack(frames);
+    match (method, weights) {
+        (StackMethod::Mean, None) => mean_stack_with_progress(frames, on_progress),
+        (StackMethod::Mean, Some(weights)) => {
+            weighted_mean_stack_with_progress(frames, weights, on_progress)
+        }
+        (StackMethod::Median, _) => {
+            let result = match weights {
+                Some(weights) => weighted_median_stack(frames, weights),
+                None => median_stack(frames),
+            };
             on_progress(frames.len());
             result
         }
-        StackMethod::SigmaClip(params) => {
-            let result = sigma_clip_stack(frames, params);
+        (
+            StackMethod::SigmaClip(_)
+            | StackMethod::WinsorizedSigmaClip(_)
+            | StackMethod::LinearFitClip(_)
+            | StackMethod::Gesd(_),
+            _,
+        ) => {
+            let rejection = method.rejection().expect("rejection method");
+            let result = rejection_stack(frames, &rejection, weights).map(|(frame, map)| {
+                info!(
+                    rejected = map.total(),
+                    fraction = map.fraction(),
+                    "Pixel rejection"
+                );
+                frame
+            });
             on_progress(frames.len());
             result
         }
-        StackMethod::MultiPoint(_) | StackMethod::Drizzle(_) | StackMethod::SurfaceWarp(_) => {
-            unreachable!("multi-point, drizzle, and surface-warp handled separately")
+        (
+            StackMethod::MultiPoint(_)
+            | StackMethod::Drizzle(_)
+            | StackMethod::SurfaceWarp(_)
+            | StackMethod::OpticalFlow(_)
+            | StackMethod::SuperResolution(_),
+            _,
+        ) => {
+            unreachable!(
+                "multi-point, drizzle, surface-warp, optical flow and super-resolution handled separately"
+            )
         }
     }
 }
 
-/// Compute alignment offsets for a set of frames against a reference frame.
+/// Align the `selected` frames (best first) against the first one.
 ///
-/// Reports progress per-frame via the reporter and propagates alignment errors.
-pub(super) fn compute_offsets_with_progress(
+/// Reports progress per-frame via the reporter and propagates alignment
+/// errors; outliers are rejected when the alignment config asks for it.
+pub(super) fn align_with_progress(
     frames: &[Frame],
-    reference_idx: usize,
+    selected: &[usize],
     alignment_config: &AlignmentConfig,
     backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
-) -> Result<Vec<AlignmentOffset>> {
-    let frame_count = frames.len();
-    reporter.begin_stage(PipelineStage::Alignment, Some(frame_count));
-    let reference = &frames[reference_idx];
-    let mut offsets = Vec::with_capacity(frame_count);
-    for (i, frame) in frames.iter().enumerate() {
-        let offset = if i == reference_idx {
-            AlignmentOffset::default()
-        } else {
-            compute_offset_configured(
-                &reference.data,
-                &frame.data,
-                alignment_config,
-                backend.as_ref(),
-            )?
-        };
-        offsets.push(offset);
-        reporter.advance(i + 1);
-    }
+) -> Result<CachedAlignment> {
+    reporter.begin_stage(PipelineStage::Alignment, Some(frames.len()));
+    let r = reporter.clone();
+    let offsets = compute_offsets_configured_with_progress(
+        frames,
+        0,
+        alignment_config,
+        backend.clone(),
+        move |done| r.advance(done),
+    )?;
+    let alignment = CachedAlignment::checked(
+        selected,
+        offsets,
+        |k| Ok(frames[k].data.clone()),
+        alignment_config,
+        backend.as_ref(),
+        false,
+    )?;
     reporter.finish_stage();
-    Ok(offsets)
+    Ok(alignment)
 }
 
 /// Split a slice of color frames into per-channel frame vectors (R, G, B).
@@ -124,15 +269,16 @@ pub(super) fn stack_color_channels_parallel(
     green: &[Frame],
     blue: &[Frame],
     method: &StackMethod,
+    weights: Option<&[f64]>,
     reporter: &Arc<dyn ProgressReporter>,
 ) -> Result<ColorFrame> {
     let r = reporter.clone();
     let (sr, (sg, sb)) = rayon::join(
-        || stack_frames_with_progress(red, method, |done| r.advance(done)),
+        || stack_frames_with_progress(red, method, weights, |done| r.advance(done)),
         || {
             rayon::join(
-                || stack_frames_with_progress(green, method, |_| {}),
-                || stack_frames_with_progress(blue, method, |_| {}),
+                || stack_frames_with_progress(green, method, weights, |_| {}),
+                || stack_frames_with_progress(blue, method, weights, |_| {}),
             )
         },
     );
@@ -196,16 +342,17 @@ pub(super) fn drizzle_flow(
     backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
     drizzle_config: &DrizzleConfig,
-    total: usize,
+    memo: &StageMemo,
 ) -> Result<Frame> {
+    let total = frames.len();
     reporter.begin_stage(PipelineStage::QualityAssessment, Some(total));
-    let ranked = rank_by_metric(frames, &config.frame_selection.metric);
+    let ranked = memo.ranked_or(|| Ok(rank_by_metric(frames, &config.frame_selection.metric)))?;
     reporter.finish_stage();
 
     reporter.begin_stage(PipelineStage::FrameSelection, None);
-    let (selected_indices, quality_scores) =
-        select_frames(&ranked, total, config.frame_selection.select_percentage);
-    let selected_frames: Vec<Frame> = selected_indices
+    let (selected_indices, mut quality_scores) =
+        select_frames(&ranked, total, &config.frame_selection)?;
+    let mut selected_frames: Vec<Frame> = selected_indices
         .iter()
         .map(|&i| frames[i].clone())
         .collect();
@@ -216,8 +363,18 @@ pub(super) fn drizzle_flow(
     reporter.finish_stage();
 
     // Compute alignment offsets
-    let offsets =
-        compute_offsets_with_progress(&selected_frames, 0, &config.alignment, backend, reporter)?;
+    let alignment = memo.alignment_or(&selected_indices, || {
+        align_with_progress(
+            &selected_frames,
+            &selected_indices,
+            &config.alignment,
+            backend,
+            reporter,
+        )
+    })?;
+    let offsets = alignment.kept().1;
+    alignment.retain_kept(&mut selected_frames);
+    alignment.retain_kept(&mut quality_scores);
     info!("Alignment offsets computed for drizzle");
 
     let drizzle_count = selected_frames.len();
@@ -269,5 +426,108 @@ pub fn apply_filter_step(frame: &Frame, step: &FilterStep) -> Frame {
             threshold,
         } => unsharp_mask(frame, *radius, *amount, *threshold),
         FilterStep::GaussianBlur { sigma } => gaussian_blur(frame, *sigma),
+        FilterStep::NonLocalMeans {
+            strength,
+            patch_radius,
+            search_radius,
+            ..
+        } => non_local_means(frame, *strength, *patch_radius, *search_radius),
+        FilterStep::Bilateral {
+            spatial_sigma,
+            range_sigma,
+            ..
+        } => bilateral(frame, *spatial_sigma, *range_sigma),
+        FilterStep::GuidedFilter {
+            radius, epsilon, ..
+        } => guided_filter(frame, *radius, *epsilon),
+        FilterStep::WaveletDenoise {
+            layers, strength, ..
+        } => wavelet_denoise(frame, *layers, *strength),
+        FilterStep::Curves { points, .. } => apply_curve(frame, points),
+        FilterStep::Levels {
+            black_point,
+            white_point,
+            gamma,
+            ..
+        } => levels(frame, *black_point, *white_point, *gamma),
+        FilterStep::Rotate { degrees, expand } => rotate(frame, *degrees, *expand),
+        FilterStep::Flip {
+            horizontal,
+            vertical,
+        } => flip(frame, *horizontal, *vertical),
+        FilterStep::Resample { scale, method } => resample(frame, *scale, *method),
+        FilterStep::Canvas { width, height } => canvas(frame, *width, *height),
+        // Color-only adjustments.
+        FilterStep::Saturation { .. } | FilterStep::WhiteBalance(_) => frame.clone(),
+    }
+}
+
+/// Apply a filter step to a color frame: to each channel, to the channel or
+/// luminance chosen by a curves or levels step, or only to the color
+/// differences for a denoiser with `chroma_only` set.
+pub fn apply_filter_step_color(color: &ColorFrame, step: &FilterStep) -> ColorFrame {
+    match step {
+        FilterStep::Curves { channel, .. } | FilterStep::Levels { channel, .. } => {
+            return apply_to_channel(color, *channel, |frame| apply_filter_step(frame, step));
+        }
+        FilterStep::Saturation {
+            saturation: s,
+            vibrance,
+        } => return saturation(color, *s, *vibrance),
+        FilterStep::WhiteBalance(balance) => return white_balance(color, balance),
+        // The planet is found once, in the luminance, so every channel is
+        // cut from the same place.
+        FilterStep::Canvas { width, height } => {
+            let (center_y, center_x) = planet_center(&luminance(color));
+            return process_color_parallel(color, |frame| {
+                canvas_at(frame, *width, *height, center_y, center_x)
+            });
+        }
+        _ => {}
+    }
+    if !step.chroma_only() {
+        return process_color_parallel(color, |frame| apply_filter_step(frame, step));
+    }
+    let bit_depth = color.red.original_bit_depth;
+    let (luma, cb, cr) = to_luma_chroma(color);
+    let denoise = |chroma: Array2<f32>| {
+        let shifted = Frame::new(chroma.mapv(|v| v + CHROMA_OFFSET), bit_depth);
+        apply_filter_step(&shifted, step)
+            .data
+            .mapv(|v| v - CHROMA_OFFSET)
+    };
+    let (cb, cr) = rayon::join(|| denoise(cb), || denoise(cr));
+    let mut result = from_luma_chroma(&luma, &cb, &cr, bit_depth);
+    for channel in [&mut result.red, &mut result.green, &mut result.blue] {
+        channel.data.mapv_inplace(|v| v.clamp(0.0, 1.0));
+    }
+    result
+}
+
+/// Apply a processing function to the chosen channel(s) of a color frame.
+fn apply_to_channel<F>(color: &ColorFrame, channel: ColorChannel, process_fn: F) -> ColorFrame
+where
+    F: Fn(&Frame) -> Frame + Send + Sync,
+{
+    let only = |red: bool, green: bool, blue: bool| {
+        let pick = |apply: bool, frame: &Frame| {
+            if apply {
+                process_fn(frame)
+            } else {
+                frame.clone()
+            }
+        };
+        ColorFrame {
+            red: pick(red, &color.red),
+            green: pick(green, &color.green),
+            blue: pick(blue, &color.blue),
+        }
+    };
+    match channel {
+        ColorChannel::All => process_color_parallel(color, &process_fn),
+        ColorChannel::Luminance => process_luminance(color, &process_fn),
+        ColorChannel::Red => only(true, false, false),
+        ColorChannel::Green => only(false, true, false),
+        ColorChannel::Blue => only(false, false, true),
     }
 }
@@ -3,8 +3,9 @@ pub mod config;
 mod helpers;
 mod mono;
 mod orchestrator;
+pub mod session;
 mod types;
 
-pub use helpers::apply_filter_step;
-pub use orchestrator::{run_pipeline, run_pipeline_reported};
+pub use helpers::{apply_filter_step, apply_filter_step_color};
+pub use orchestrator::{resolve_debayer, run_pipeline, run_pipeline_cached, run_pipeline_reported};
 pub use types::{PipelineOutput, PipelineStage, ProgressReporter};
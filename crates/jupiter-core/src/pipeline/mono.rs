@@ -3,53 +3,58 @@ use std::sync::Arc;
 use tracing::info;
 
 use crate::align::{
-    align_frames_configured_with_progress, compute_offsets_streaming_configured, shift_frame,
+    apply_offsets, compute_offsets_configured_with_progress, compute_offsets_streaming_configured,
+    shift_frame,
 };
 use crate::compute::ComputeBackend;
 use crate::error::Result;
-use crate::frame::Frame;
+use crate::frame::{AlignmentOffset, Frame};
 use crate::io::image_io::save_image;
 use crate::io::ser::SerReader;
-use crate::sharpen::deconvolution::{deconvolve, deconvolve_gpu};
+use crate::sharpen::deconvolution::{deconvolve, deconvolve_gpu, Psf};
 use crate::sharpen::wavelet;
 use crate::stack::drizzle::{drizzle_stack_streaming, DrizzleConfig};
 use crate::stack::mean::StreamingMeanStacker;
+use crate::stack::median::StreamingMedianStacker;
+use crate::stack::sigma_clip::StreamingSigmaClipStacker;
+use crate::stack::streaming::{stack_streaming, MultiPassStacker};
+use crate::stack::super_resolution::{super_resolve_with_progress, SuperResolutionConfig};
 
 use super::config::PipelineConfig;
 use super::config::StackMethod;
 use super::helpers::{
-    apply_filter_step, drizzle_flow, rank_by_metric, rank_by_metric_streaming, select_frames,
-    stack_frames_with_progress,
+    align_with_progress, apply_filter_step, drizzle_flow, rank_by_metric, rank_by_metric_streaming,
+    select_frames, stack_frames_with_progress,
 };
+use super::session::{CachedAlignment, StageMemo};
 use super::types::{PipelineOutput, PipelineStage, ProgressReporter};
 
-/// The existing mono pipeline path (unchanged logic).
-pub(super) fn run_mono_pipeline(
+/// Mono path up to and including stacking; post-processing is applied by the caller.
+pub(super) fn stack_mono(
     reader: &SerReader,
     config: &PipelineConfig,
     backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
-    total: usize,
-) -> Result<PipelineOutput> {
+    memo: &StageMemo,
+) -> Result<Frame> {
     let streaming = super::orchestrator::should_use_streaming(reader, config, false);
     if streaming {
         info!("Using low-memory streaming mode");
     }
 
-    let stacked = if let StackMethod::Drizzle(ref drizzle_config) = config.stacking.method {
+    if let StackMethod::SuperResolution(ref sr_config) = config.stacking.method {
+        run_mono_super_resolution(reader, config, backend, reporter, sr_config, memo)
+    } else if let StackMethod::Drizzle(ref drizzle_config) = config.stacking.method {
         if streaming {
-            run_mono_drizzle_streaming(reader, config, backend, reporter, drizzle_config, total)?
+            run_mono_drizzle_streaming(reader, config, backend, reporter, drizzle_config, memo)
         } else {
-            run_mono_drizzle(reader, config, backend, reporter, drizzle_config, total)?
+            run_mono_drizzle(reader, config, backend, reporter, drizzle_config, memo)
         }
     } else if streaming {
-        run_mono_standard_streaming(reader, config, backend, reporter, total)?
+        run_mono_standard_streaming(reader, config, backend, reporter, memo)
     } else {
-        run_mono_standard(reader, config, backend, reporter, total)?
-    };
-
-    let output = apply_post_stack_mono(stacked, config, backend, reporter)?;
-    Ok(output)
+        run_mono_standard(reader, config, backend, reporter, memo)
+    }
 }
 
 fn run_mono_standard(
@@ -57,8 +62,9 @@ fn run_mono_standard(
     config: &PipelineConfig,
     backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
-    total: usize,
+    memo: &StageMemo,
 ) -> Result<Frame> {
+    let total = reader.frame_count();
     // Read
     reporter.begin_stage(PipelineStage::Reading, Some(total));
     let frames: Vec<Frame> = reader.frames().collect::<Result<_>>()?;
@@ -66,13 +72,13 @@ fn run_mono_standard(
 
     // Quality
     reporter.begin_stage(PipelineStage::QualityAssessment, Some(total));
-    let ranked = rank_by_metric(&frames, &config.frame_selection.metric);
+    let ranked = memo.ranked_or(|| Ok(rank_by_metric(&frames, &config.frame_selection.metric)))?;
     reporter.finish_stage();
 
     // Selection
     reporter.begin_stage(PipelineStage::FrameSelection, None);
-    let (selected_indices, _) =
-        select_frames(&ranked, total, config.frame_selection.select_percentage);
+    let (selected_indices, mut quality_scores) =
+        select_frames(&ranked, total, &config.frame_selection)?;
     let selected_frames: Vec<Frame> = selected_indices
         .iter()
         .map(|&i| frames[i].clone())
@@ -88,15 +94,29 @@ fn run_mono_standard(
     reporter.begin_stage(PipelineStage::Alignment, Some(frame_count));
     let aligned = if frame_count > 1 {
         let r = reporter.clone();
-        align_frames_configured_with_progress(
-            &selected_frames,
-            0,
-            &config.alignment,
-            backend.clone(),
-            move |done| {
-                r.advance(done);
-            },
-        )?
+        let alignment = memo.alignment_or(&selected_indices, || {
+            let offsets = compute_offsets_configured_with_progress(
+                &selected_frames,
+                0,
+                &config.alignment,
+                backend.clone(),
+                move |done| {
+                    r.advance(done);
+                },
+            )?;
+            CachedAlignment::checked(
+                &selected_indices,
+                offsets,
+                |k| Ok(selected_frames[k].data.clone()),
+                &config.alignment,
+                backend.as_ref(),
+                false,
+            )
+        })?;
+        let mut selected_frames = selected_frames;
+        alignment.retain_kept(&mut selected_frames);
+        alignment.retain_kept(&mut quality_scores);
+        apply_offsets(&selected_frames, &alignment.kept().1, backend.as_ref())
     } else {
         selected_frames
     };
@@ -106,9 +126,14 @@ fn run_mono_standard(
     let frame_count = aligned.len();
     reporter.begin_stage(PipelineStage::Stacking, Some(frame_count));
     let r = reporter.clone();
-    let result = stack_frames_with_progress(&aligned, &config.stacking.method, move |done| {
-        r.advance(done);
-    })?;
+    let weights = config
+        .stacking
+        .quality_weighted
+        .then_some(quality_scores.as_slice());
+    let result =
+        stack_frames_with_progress(&aligned, &config.stacking.method, weights, move |done| {
+            r.advance(done);
+        })?;
     info!(method = ?config.stacking.method, "Stacking complete");
     reporter.finish_stage();
 
@@ -117,117 +142,202 @@ fn run_mono_standard(
 
 /// Streaming mono pipeline: score -> select -> load-shift-stack one at a time.
 ///
-/// For Mean: fully streaming -- each frame is loaded, shifted, accumulated, then dropped.
-/// For Median/SigmaClip: semi-streaming -- offsets computed streaming, then M selected
-/// frames loaded+shifted for the per-pixel stacking pass.
+/// For Mean, Median and SigmaClip: fully streaming -- each frame is loaded,
+/// shifted, accumulated, then dropped, once per pass of the stacker.
+/// For the other rejection methods: semi-streaming -- offsets computed
+/// streaming, then M selected frames loaded+shifted for the per-pixel pass.
 fn run_mono_standard_streaming(
     reader: &SerReader,
     config: &PipelineConfig,
     backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
-    total: usize,
+    memo: &StageMemo,
 ) -> Result<Frame> {
+    let total = reader.frame_count();
     // Quality (streaming: one batch at a time)
     reporter.begin_stage(PipelineStage::QualityAssessment, Some(total));
-    let ranked = rank_by_metric_streaming(reader, &config.frame_selection.metric)?;
+    let ranked =
+        memo.ranked_or(|| rank_by_metric_streaming(reader, &config.frame_selection.metric))?;
     reporter.finish_stage();
 
     // Selection
     reporter.begin_stage(PipelineStage::FrameSelection, None);
-    let (selected_indices, _) =
-        select_frames(&ranked, total, config.frame_selection.select_percentage);
+    let (selected_indices, mut quality_scores) =
+        select_frames(&ranked, total, &config.frame_selection)?;
     info!(
         selected = selected_indices.len(),
         total, "Selected best frames (streaming)"
     );
     reporter.finish_stage();
 
-    let frame_count = selected_indices.len();
+    // Alignment offsets (streaming)
+    reporter.begin_stage(PipelineStage::Alignment, Some(selected_indices.len()));
+    let r = reporter.clone();
+    let (selected_indices, offsets) = align_streaming(
+        reader,
+        &selected_indices,
+        &mut quality_scores,
+        config,
+        backend,
+        memo,
+        move |done| {
+            r.advance(done);
+        },
+    )?;
+    reporter.finish_stage();
+
+    let weights = config
+        .stacking
+        .quality_weighted
+        .then_some(quality_scores.as_slice());
+    let h = reader.header.height as usize;
+    let w = reader.header.width as usize;
+    let bit_depth = reader.header.pixel_depth as u8;
 
-    match &config.stacking.method {
+    let result = match &config.stacking.method {
         StackMethod::Mean => {
-            // Fully streaming: compute offsets, then load-shift-accumulate one at a time
-            reporter.begin_stage(PipelineStage::Alignment, Some(frame_count));
-            let r = reporter.clone();
-            let offsets = compute_offsets_streaming_configured(
+            let stacker = StreamingMeanStacker::new(h, w, bit_depth);
+            stack_from_disk(
                 reader,
                 &selected_indices,
-                0,
-                &config.alignment,
-                backend.clone(),
-                move |done| {
-                    r.advance(done);
-                },
-            )?;
-            reporter.finish_stage();
-
-            reporter.begin_stage(PipelineStage::Stacking, Some(frame_count));
-            let h = reader.header.height as usize;
-            let w = reader.header.width as usize;
-            let bit_depth = reader.header.pixel_depth as u8;
-            let mut stacker = StreamingMeanStacker::new(h, w, bit_depth);
-            for (i, (&frame_idx, offset)) in selected_indices.iter().zip(offsets.iter()).enumerate()
-            {
-                let frame = reader.read_frame(frame_idx)?;
-                let shifted = if i == 0 {
-                    frame
-                } else {
-                    shift_frame(&frame, offset)
-                };
-                stacker.add(&shifted);
-                reporter.advance(i + 1);
-                // frame + shifted dropped here
-            }
-            let result = stacker.finalize()?;
-            info!(method = "Mean", "Streaming stacking complete");
-            reporter.finish_stage();
-            Ok(result)
+                &offsets,
+                weights,
+                stacker,
+                reporter,
+            )?
         }
-        _ => {
-            // Median/SigmaClip: compute offsets streaming, then load M selected + shift
-            reporter.begin_stage(PipelineStage::Alignment, Some(frame_count));
-            let r = reporter.clone();
-            let offsets = compute_offsets_streaming_configured(
+        StackMethod::Median => {
+            let stacker = StreamingMedianStacker::new(h, w, bit_depth);
+            stack_from_disk(
                 reader,
                 &selected_indices,
-                0,
-                &config.alignment,
-                backend.clone(),
-                move |done| {
-                    r.advance(done);
-                },
-            )?;
-            reporter.finish_stage();
-
+                &offsets,
+                weights,
+                stacker,
+                reporter,
+            )?
+        }
+        StackMethod::SigmaClip(params) => {
+            let stacker = StreamingSigmaClipStacker::new(h, w, bit_depth, params);
+            stack_from_disk(
+                reader,
+                &selected_indices,
+                &offsets,
+                weights,
+                stacker,
+                reporter,
+            )?
+        }
+        _ => {
             // Load and shift selected frames
-            reporter.begin_stage(PipelineStage::Reading, Some(frame_count));
-            let mut aligned = Vec::with_capacity(frame_count);
+            reporter.begin_stage(PipelineStage::Reading, Some(selected_indices.len()));
+            let mut aligned = Vec::with_capacity(selected_indices.len());
             for (i, (&frame_idx, offset)) in selected_indices.iter().zip(offsets.iter()).enumerate()
             {
-                let frame = reader.read_frame(frame_idx)?;
-                let shifted = if i == 0 {
-                    frame
-                } else {
-                    shift_frame(&frame, offset)
-                };
-                aligned.push(shifted);
+                aligned.push(read_shifted(reader, frame_idx, offset)?);
                 reporter.advance(i + 1);
             }
             reporter.finish_stage();
 
             // Stack
-            let stack_count = aligned.len();
-            reporter.begin_stage(PipelineStage::Stacking, Some(stack_count));
+            reporter.begin_stage(PipelineStage::Stacking, Some(aligned.len()));
             let r = reporter.clone();
-            let result =
-                stack_frames_with_progress(&aligned, &config.stacking.method, move |done| {
+            let result = stack_frames_with_progress(
+                &aligned,
+                &config.stacking.method,
+                weights,
+                move |done| {
                     r.advance(done);
-                })?;
-            info!(method = ?config.stacking.method, "Streaming stacking complete");
+                },
+            )?;
             reporter.finish_stage();
-            Ok(result)
+            result
         }
-    }
+    };
+    info!(method = ?config.stacking.method, "Streaming stacking complete");
+    Ok(result)
+}
+
+/// Read frame `frame_idx` and apply its alignment offset.
+fn read_shifted(reader: &SerReader, frame_idx: usize, offset: &AlignmentOffset) -> Result<Frame> {
+    let frame = reader.read_frame(frame_idx)?;
+    Ok(if offset.is_zero() {
+        frame
+    } else {
+        shift_frame(&frame, offset)
+    })
+}
+
+/// Feed the selected frames through `stacker`, re-reading and re-shifting
+/// each one per pass so only one frame is held at a time.
+fn stack_from_disk(
+    reader: &SerReader,
+    selected: &[usize],
+    offsets: &[AlignmentOffset],
+    weights: Option<&[f64]>,
+    stacker: impl MultiPassStacker,
+    reporter: &Arc<dyn ProgressReporter>,
+) -> Result<Frame> {
+    reporter.begin_stage(
+        PipelineStage::Stacking,
+        Some(stacker.passes() * selected.len()),
+    );
+    let r = reporter.clone();
+    let result = stack_streaming(
+        stacker,
+        selected.len(),
+        weights,
+        |i| read_shifted(reader, selected[i], &offsets[i]),
+        move |done| {
+            r.advance(done);
+        },
+    )?;
+    reporter.finish_stage();
+    Ok(result)
+}
+
+/// Offsets of `selected` streamed from disk, with outliers checked when configured.
+fn streaming_alignment(
+    reader: &SerReader,
+    selected: &[usize],
+    config: &PipelineConfig,
+    backend: &Arc<dyn ComputeBackend>,
+    on_frame_done: impl Fn(usize) + Send + Sync,
+) -> Result<CachedAlignment> {
+    let offsets = compute_offsets_streaming_configured(
+        reader,
+        selected,
+        0,
+        &config.alignment,
+        backend.clone(),
+        on_frame_done,
+    )?;
+    CachedAlignment::checked(
+        selected,
+        offsets,
+        |k| Ok(reader.read_frame(selected[k])?.data),
+        &config.alignment,
+        backend.as_ref(),
+        false,
+    )
+}
+
+/// Memoized [`streaming_alignment`], returning only the frames that were kept
+/// and dropping the rejected frames' entries from `scores`.
+fn align_streaming(
+    reader: &SerReader,
+    selected: &[usize],
+    scores: &mut Vec<f64>,
+    config: &PipelineConfig,
+    backend: &Arc<dyn ComputeBackend>,
+    memo: &StageMemo,
+    on_frame_done: impl Fn(usize) + Send + Sync,
+) -> Result<(Vec<usize>, Vec<AlignmentOffset>)> {
+    let alignment = memo.alignment_or(selected, || {
+        streaming_alignment(reader, selected, config, backend, on_frame_done)
+    })?;
+    alignment.retain_kept(scores);
+    Ok(alignment.kept())
 }
 
 /// Streaming mono drizzle: score -> select -> stream offsets -> stream drizzle.
@@ -237,17 +347,19 @@ fn run_mono_drizzle_streaming(
     backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
     drizzle_config: &DrizzleConfig,
-    total: usize,
+    memo: &StageMemo,
 ) -> Result<Frame> {
+    let total = reader.frame_count();
     // Quality (streaming)
     reporter.begin_stage(PipelineStage::QualityAssessment, Some(total));
-    let ranked = rank_by_metric_streaming(reader, &config.frame_selection.metric)?;
+    let ranked =
+        memo.ranked_or(|| rank_by_metric_streaming(reader, &config.frame_selection.metric))?;
     reporter.finish_stage();
 
     // Selection
     reporter.begin_stage(PipelineStage::FrameSelection, None);
     let (selected_indices, quality_scores) =
-        select_frames(&ranked, total, config.frame_selection.select_percentage);
+        select_frames(&ranked, total, &config.frame_selection)?;
     info!(
         selected = selected_indices.len(),
         total, "Selected best frames for drizzle (streaming)"
@@ -258,16 +370,14 @@ fn run_mono_drizzle_streaming(
     let frame_count = selected_indices.len();
     reporter.begin_stage(PipelineStage::Alignment, Some(frame_count));
     let r = reporter.clone();
-    let offsets = compute_offsets_streaming_configured(
-        reader,
-        &selected_indices,
-        0,
-        &config.alignment,
-        backend.clone(),
-        move |done| {
+    let alignment = memo.alignment_or(&selected_indices, || {
+        streaming_alignment(reader, &selected_indices, config, backend, move |done| {
             r.advance(done);
-        },
-    )?;
+        })
+    })?;
+    let (selected_indices, offsets) = alignment.kept();
+    let mut quality_scores = quality_scores;
+    alignment.retain_kept(&mut quality_scores);
     info!("Alignment offsets computed for drizzle (streaming)");
     reporter.finish_stage();
 
@@ -296,15 +406,83 @@ fn run_mono_drizzle(
     backend: &Arc<dyn ComputeBackend>,
     reporter: &Arc<dyn ProgressReporter>,
     drizzle_config: &DrizzleConfig,
-    total: usize,
+    memo: &StageMemo,
 ) -> Result<Frame> {
+    let total = reader.frame_count();
     // Read
     reporter.begin_stage(PipelineStage::Reading, Some(total));
     let frames: Vec<Frame> = reader.frames().collect::<Result<_>>()?;
     reporter.finish_stage();
 
     // Quality + selection + offsets + drizzle
-    drizzle_flow(&frames, config, backend, reporter, drizzle_config, total)
+    drizzle_flow(&frames, config, backend, reporter, drizzle_config, memo)
+}
+
+/// Mono super-resolution: score -> select -> read selected -> align -> reconstruct.
+///
+/// Scoring streams from disk and only the selected frames are held, since
+/// every iteration revisits all of them; the same path serves both memory
+/// strategies.
+fn run_mono_super_resolution(
+    reader: &SerReader,
+    config: &PipelineConfig,
+    backend: &Arc<dyn ComputeBackend>,
+    reporter: &Arc<dyn ProgressReporter>,
+    sr_config: &SuperResolutionConfig,
+    memo: &StageMemo,
+) -> Result<Frame> {
+    let total = reader.frame_count();
+    reporter.begin_stage(PipelineStage::QualityAssessment, Some(total));
+    let ranked =
+        memo.ranked_or(|| rank_by_metric_streaming(reader, &config.frame_selection.metric))?;
+    reporter.finish_stage();
+
+    reporter.begin_stage(PipelineStage::FrameSelection, None);
+    let (selected_indices, mut quality_scores) =
+        select_frames(&ranked, total, &config.frame_selection)?;
+    info!(
+        selected = selected_indices.len(),
+        total, "Selected best frames for super-resolution"
+    );
+    reporter.finish_stage();
+
+    reporter.begin_stage(PipelineStage::Reading, Some(selected_indices.len()));
+    let mut selected_frames: Vec<Frame> = selected_indices
+        .iter()
+        .map(|&i| reader.read_frame(i))
+        .collect::<Result<_>>()?;
+    reporter.finish_stage();
+
+    let alignment = memo.alignment_or(&selected_indices, || {
+        align_with_progress(
+            &selected_frames,
+            &selected_indices,
+            &config.alignment,
+            backend,
+            reporter,
+        )
+    })?;
+    let offsets = alignment.kept().1;
+    alignment.retain_kept(&mut selected_frames);
+    alignment.retain_kept(&mut quality_scores);
+
+    reporter.begin_stage(PipelineStage::Stacking, Some(sr_config.iterations));
+    let r = reporter.clone();
+    let result = super_resolve_with_progress(
+        &selected_frames,
+        &offsets,
+        sr_config,
+        Some(&quality_scores),
+        backend.as_ref(),
+        move |done| r.advance(done),
+    )?;
+    info!(
+        method = "SuperResolution",
+        scale = sr_config.scale,
+        "Super-resolution stacking complete"
+    );
+    reporter.finish_stage();
+    Ok(result)
 }
 
 /// Post-stacking processing for mono path: sharpen -> filter -> write -> return.
@@ -320,9 +498,10 @@ pub(super) fn apply_post_stack_mono(
         let mut sharpened = stacked;
         if let Some(ref deconv_config) = sharpening_config.deconvolution {
             if backend.is_gpu() {
-                sharpened = deconvolve_gpu(&sharpened, deconv_config, &**backend);
+                let psf = Psf::load(&deconv_config.psf)?;
+                sharpened = deconvolve_gpu(&sharpened, deconv_config, &psf, &**backend)?;
             } else {
-                sharpened = deconvolve(&sharpened, deconv_config);
+                sharpened = deconvolve(&sharpened, deconv_config)?;
             }
             info!("Deconvolution complete");
         }
@@ -4,25 +4,40 @@ use tracing::info;
 
 use crate::color::debayer::{is_bayer, DebayerMethod};
 use crate::compute::ComputeBackend;
-use crate::consts::{COLOR_CHANNEL_COUNT, LOW_MEMORY_THRESHOLD_BYTES};
-use crate::error::Result;
+use crate::consts::{COLOR_CHANNEL_COUNT, FLOW_PROGRESS_STEPS, LOW_MEMORY_THRESHOLD_BYTES};
+use crate::error::{JupiterError, Result};
 use crate::frame::ColorMode;
 use crate::io::ser::SerReader;
-use crate::stack::multi_point::{multi_point_stack, multi_point_stack_color};
-use crate::stack::surface_warp::{surface_warp_stack, surface_warp_stack_color};
+use crate::stack::ap_diagnostics::ApDiagnostics;
+use crate::stack::multi_point::{
+    multi_point_stack_color_from_global, multi_point_stack_from_global,
+};
+use crate::stack::optical_flow::{
+    optical_flow_stack_color_from_global, optical_flow_stack_from_global,
+};
+use crate::stack::surface_warp::{
+    surface_warp_stack_color_from_global, surface_warp_stack_from_global,
+};
 
 use super::color::apply_post_stack_color;
-use super::config::{MemoryStrategy, PipelineConfig, StackMethod};
+use super::config::{DebayerConfig, MemoryStrategy, PipelineConfig, StackMethod};
+use super::helpers::global_align_memo;
 use super::mono::apply_post_stack_mono;
+use super::session::{SessionCache, StageMemo};
 use super::types::{NoOpReporter, PipelineOutput, PipelineStage, ProgressReporter};
 
-/// Resolve which debayer method (if any) to use given the config and SER header.
-fn resolve_debayer(config: &PipelineConfig, reader: &SerReader) -> Option<DebayerMethod> {
-    if config.force_mono {
+/// Resolve which debayer method (if any) to use for a source colour mode.
+///
+/// `None` means the input is processed as mono.
+pub fn resolve_debayer(
+    debayer: Option<&DebayerConfig>,
+    force_mono: bool,
+    mode: &ColorMode,
+) -> Option<DebayerMethod> {
+    if force_mono {
         return None;
     }
-    let mode = reader.header.color_mode();
-    if !is_bayer(&mode) && !matches!(mode, ColorMode::RGB | ColorMode::BGR) {
+    if !is_bayer(mode) && !matches!(mode, ColorMode::RGB | ColorMode::BGR) {
         return None;
     }
     // For RGB/BGR, no debayering needed but we still process as color.
@@ -31,7 +46,7 @@ fn resolve_debayer(config: &PipelineConfig, reader: &SerReader) -> Option<Debaye
         return Some(DebayerMethod::Bilinear);
     }
     // Bayer: use explicit config or auto-detect with default.
-    match &config.debayer {
+    match debayer {
         Some(db) => Some(db.method),
         None => Some(DebayerMethod::default()),
     }
@@ -43,78 +58,275 @@ pub fn run_pipeline_reported(
     backend: Arc<dyn ComputeBackend>,
     reporter: Arc<dyn ProgressReporter>,
 ) -> Result<PipelineOutput> {
-    let reader = SerReader::open(&config.input)?;
+    run_pipeline_inner(config, backend, reporter, None)
+}
+
+/// Run the pipeline, reusing and updating an on-disk session cache.
+///
+/// Stages whose inputs are unchanged since the session was written are
+/// skipped: a cached stack jumps straight to sharpening, otherwise cached
+/// rankings and offsets replace scoring and alignment.
+pub fn run_pipeline_cached(
+    config: &PipelineConfig,
+    backend: Arc<dyn ComputeBackend>,
+    reporter: Arc<dyn ProgressReporter>,
+    session: &mut SessionCache,
+) -> Result<PipelineOutput> {
+    run_pipeline_inner(config, backend, reporter, Some(session))
+}
+
+fn run_pipeline_inner(
+    config: &PipelineConfig,
+    backend: Arc<dyn ComputeBackend>,
+    reporter: Arc<dyn ProgressReporter>,
+    mut session: Option<&mut SessionCache>,
+) -> Result<PipelineOutput> {
+    let reader = SerReader::open_all(&config.inputs())?;
     let total = reader.frame_count();
     info!(
         total_frames = total,
+        files = reader.file_count(),
         device = backend.name(),
         "Reading SER file"
     );
 
-    let debayer_method = resolve_debayer(config, &reader);
-    let use_color = debayer_method.is_some();
     let color_mode = reader.header.color_mode();
+    let debayer_method = resolve_debayer(config.debayer.as_ref(), config.force_mono, &color_mode);
+    let use_color = debayer_method.is_some();
 
     if use_color {
         info!(mode = ?color_mode, "Color processing enabled");
     }
 
+    // Only the mono low-memory path approximates (colour streams exactly).
+    let approximate = !use_color
+        && config.stacking.method.streams_approximately()
+        && should_use_streaming(&reader, config, false);
+    let keys = match session.as_deref() {
+        Some(s) => Some(s.keys(config, debayer_method.as_ref(), approximate)?),
+        None => None,
+    };
+
+    // Cached stack: skip straight to post-processing
+    if let (Some(s), Some(k)) = (session.as_deref_mut(), keys.as_ref()) {
+        if let Some(stacked) = s.stacked(&k.stack) {
+            info!(dir = %s.dir().display(), "Reusing cached stack");
+            let kept = s
+                .alignment(&k.alignment)
+                .filter(|_| !config.stacking.method.selects_own_frames())
+                .map(|a| a.kept().0);
+            report_contributions(&reader, kept.as_deref(), &reporter);
+            s.store_settings(config);
+            s.save()?;
+            return apply_post_stack(stacked, config, &backend, &reporter);
+        }
+    }
+
+    let memo = match (session.as_deref(), keys.as_ref()) {
+        (Some(s), Some(k)) => StageMemo::seeded(
+            s.ranking(&k.quality).map(<[_]>::to_vec),
+            s.alignment(&k.alignment).cloned(),
+        ),
+        _ => StageMemo::default(),
+    };
+
+    let (stacked, ap_diagnostics) = stack(
+        &reader,
+        config,
+        &backend,
+        &reporter,
+        debayer_method.as_ref(),
+        &color_mode,
+        &memo,
+    )?;
+    // Methods that select frames per region stack no single global selection.
+    let kept = memo
+        .kept_frames()
+        .filter(|_| !config.stacking.method.selects_own_frames());
+    report_contributions(&reader, kept.as_deref(), &reporter);
+
+    if let (Some(s), Some(k)) = (session, keys) {
+        let (ranked, alignment) = memo.into_parts();
+        if let Some(ranked) = ranked {
+            s.store_ranking(k.quality, ranked);
+        }
+        if let Some(alignment) = alignment {
+            s.store_alignment(k.alignment, alignment);
+        }
+        s.store_stack(k.stack, &stacked, ap_diagnostics)?;
+        s.store_settings(config);
+        s.save()?;
+    }
+
+    apply_post_stack(stacked, config, &backend, &reporter)
+}
+
+/// Log how many of the stacked frames (`kept`, when the method stacks a
+/// global selection) came from each input file, and pass it to the reporter.
+/// Silent for single-file input.
+fn report_contributions(
+    reader: &SerReader,
+    kept: Option<&[usize]>,
+    reporter: &Arc<dyn ProgressReporter>,
+) {
+    if reader.file_count() < 2 {
+        return;
+    }
+    let contributions = reader.file_contributions(kept);
+    for c in &contributions {
+        info!(
+            file = %c.path.display(),
+            frames = c.frames,
+            selected = ?c.selected,
+            "File contribution"
+        );
+    }
+    reporter.file_contributions(&contributions);
+}
+
+/// Everything up to and including stacking, for every method and colour mode.
+fn stack(
+    reader: &SerReader,
+    config: &PipelineConfig,
+    backend: &Arc<dyn ComputeBackend>,
+    reporter: &Arc<dyn ProgressReporter>,
+    debayer_method: Option<&DebayerMethod>,
+    color_mode: &ColorMode,
+    memo: &StageMemo,
+) -> Result<(PipelineOutput, Option<ApDiagnostics>)> {
+    let method =
+        super::helpers::method_with_frame_list(&config.stacking.method, &config.frame_selection)?;
+
     // Multi-point: dedicated flow (color or mono)
-    if let StackMethod::MultiPoint(ref mp_config) = config.stacking.method {
+    if let StackMethod::MultiPoint(ref mp_config) = method {
+        if mp_config.local_stack_method.needs_all_patches()
+            && should_use_streaming(reader, config, debayer_method.is_some())
+        {
+            return Err(JupiterError::Pipeline(format!(
+                "Local {} keeps every selected frame in memory, which low-memory mode \
+                 does not allow; use eager memory, or a local Mean or Sigma Clip",
+                mp_config.local_stack_method
+            )));
+        }
         reporter.begin_stage(PipelineStage::Stacking, None);
-        if use_color {
-            let result = multi_point_stack_color(
-                &reader,
+        let global = global_align_memo(
+            reader,
+            mp_config.frames.as_deref(),
+            &mp_config.quality_metric,
+            &config.alignment,
+            backend.as_ref(),
+            debayer_method.map(|m| (m, color_mode)),
+            memo,
+        )?;
+        let (result, diagnostics) = if let Some(method) = debayer_method {
+            let (cf, diagnostics) = multi_point_stack_color_from_global(
+                reader,
                 mp_config,
-                &color_mode,
-                &debayer_method.unwrap(),
+                global,
+                color_mode,
+                method,
                 |_progress| {},
             )?;
             info!("Multi-point color stacking complete");
-            reporter.finish_stage();
-            return apply_post_stack_color(result, config, &backend, &reporter);
+            (PipelineOutput::Color(cf), diagnostics)
         } else {
-            let result = multi_point_stack(&reader, mp_config, |_progress| {})?;
+            let (frame, diagnostics) =
+                multi_point_stack_from_global(reader, mp_config, global, |_progress| {})?;
             info!("Multi-point stacking complete");
-            reporter.finish_stage();
-            return apply_post_stack_mono(result, config, &backend, &reporter);
-        }
+            (PipelineOutput::Mono(frame), diagnostics)
+        };
+        reporter.finish_stage();
+        return Ok((result, Some(diagnostics)));
     }
 
     // Surface warp: dedicated flow (color or mono)
-    if let StackMethod::SurfaceWarp(ref sw_config) = config.stacking.method {
+    if let StackMethod::SurfaceWarp(ref sw_config) = method {
         reporter.begin_stage(PipelineStage::Stacking, None);
-        if use_color {
-            let result = surface_warp_stack_color(
-                &reader,
+        let global = global_align_memo(
+            reader,
+            sw_config.frames.as_deref(),
+            &sw_config.quality_metric,
+            &config.alignment,
+            backend.as_ref(),
+            debayer_method.map(|m| (m, color_mode)),
+            memo,
+        )?;
+        let (result, diagnostics) = if let Some(method) = debayer_method {
+            let (cf, diagnostics) = surface_warp_stack_color_from_global(
+                reader,
                 sw_config,
-                &color_mode,
-                &debayer_method.unwrap(),
+                global,
+                color_mode,
+                method,
                 |_progress| {},
             )?;
             info!("Surface warp color stacking complete");
-            reporter.finish_stage();
-            return apply_post_stack_color(result, config, &backend, &reporter);
+            (PipelineOutput::Color(cf), diagnostics)
         } else {
-            let result = surface_warp_stack(&reader, sw_config, |_progress| {})?;
+            let (frame, diagnostics) =
+                surface_warp_stack_from_global(reader, sw_config, global, |_progress| {})?;
             info!("Surface warp stacking complete");
-            reporter.finish_stage();
-            return apply_post_stack_mono(result, config, &backend, &reporter);
-        }
+            (PipelineOutput::Mono(frame), diagnostics)
+        };
+        reporter.finish_stage();
+        return Ok((result, Some(diagnostics)));
     }
 
-    if use_color {
-        super::color::run_color_pipeline(
-            &reader,
-            config,
-            &backend,
-            &reporter,
-            &debayer_method.unwrap(),
-            &color_mode,
-            total,
-        )
+    // Optical flow: dedicated flow (color or mono)
+    if let StackMethod::OpticalFlow(ref flow_config) = method {
+        reporter.begin_stage(PipelineStage::Stacking, Some(FLOW_PROGRESS_STEPS));
+        let on_progress =
+            |fraction: f32| reporter.advance((fraction * FLOW_PROGRESS_STEPS as f32) as usize);
+        let global = global_align_memo(
+            reader,
+            flow_config.frames.as_deref(),
+            &flow_config.quality_metric,
+            &config.alignment,
+            backend.as_ref(),
+            debayer_method.map(|m| (m, color_mode)),
+            memo,
+        )?;
+        let result = if let Some(method) = debayer_method {
+            let cf = optical_flow_stack_color_from_global(
+                reader,
+                flow_config,
+                global,
+                color_mode,
+                method,
+                on_progress,
+            )?;
+            info!("Optical flow color stacking complete");
+            PipelineOutput::Color(cf)
+        } else {
+            let frame = optical_flow_stack_from_global(reader, flow_config, global, on_progress)?;
+            info!("Optical flow stacking complete");
+            PipelineOutput::Mono(frame)
+        };
+        reporter.finish_stage();
+        return Ok((result, None));
+    }
+
+    let result = if let Some(method) = debayer_method {
+        PipelineOutput::Color(super::color::stack_color(
+            reader, config, backend, reporter, method, color_mode, memo,
+        )?)
     } else {
-        super::mono::run_mono_pipeline(&reader, config, &backend, &reporter, total)
+        PipelineOutput::Mono(super::mono::stack_mono(
+            reader, config, backend, reporter, memo,
+        )?)
+    };
+    Ok((result, None))
+}
+
+fn apply_post_stack(
+    stacked: PipelineOutput,
+    config: &PipelineConfig,
+    backend: &Arc<dyn ComputeBackend>,
+    reporter: &Arc<dyn ProgressReporter>,
+) -> Result<PipelineOutput> {
+    match stacked {
+        PipelineOutput::Mono(frame) => apply_post_stack_mono(frame, config, backend, reporter),
+        PipelineOutput::Color(cf) => apply_post_stack_color(cf, config, backend, reporter),
     }
 }
 
@@ -0,0 +1,633 @@
+//! On-disk session cache for intermediate pipeline results.
+//!
+//! A session lives in a sidecar directory next to the input (`capture.ser` ->
+//! `capture.jupiter/`) and holds the quality ranking, the selected frames with
+//! their alignment offsets, and the stacked float image (plus the AP grid for
+//! multi-point). Each stage is stored under a key derived from the input
+//! fingerprint and every config section that influences it, chained so that a
+//! change upstream invalidates everything downstream:
+//!
+//! quality   = input + metric + colour handling
+//! alignment = quality + frame selection + alignment
+//! stack     = alignment + stacking
+//!
+//! Multi-point, surface warp and optical flow rank with their own metric and
+//! cache their global alignment (every frame against the best one) under an
+//! alignment key of its own.
+//!
+//! Sharpening and filters are never cached; they are cheap to re-run and are
+//! what users iterate on most.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::align::{alignment_confidences, reject_outliers, ReferenceAligner};
+use crate::color::debayer::DebayerMethod;
+use crate::compute::ComputeBackend;
+use crate::error::{JupiterError, Result};
+use crate::frame::{AlignmentOffset, ColorFrame, Frame, QualityScore};
+use crate::stack::ap_diagnostics::ApDiagnostics;
+
+use super::config::{
+    AlignmentConfig, FrameSelectionConfig, PipelineConfig, QualityMetric, StackingConfig,
+};
+use super::types::PipelineOutput;
+
+/// Bump when the on-disk layout changes; older sessions are discarded.
+const SESSION_VERSION: u32 = 4;
+const MANIFEST_FILE: &str = "session.json";
+const STACK_FILE: &str = "stack.f32";
+
+/// Bytes sampled from the input file when fingerprinting.
+const FINGERPRINT_SAMPLES: u64 = 64;
+const FINGERPRINT_SAMPLE_LEN: usize = 4096;
+
+/// Opaque identifier of one cached stage.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageKey(String);
+
+/// Keys for every cacheable stage of one pipeline configuration.
+#[derive(Clone, Debug)]
+pub struct SessionKeys {
+    pub quality: StageKey,
+    pub alignment: StageKey,
+    pub stack: StageKey,
+}
+
+/// Selected frames (best first) and their offsets relative to the first one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CachedAlignment {
+    pub selected: Vec<usize>,
+    pub offsets: Vec<AlignmentOffset>,
+    /// Selected frames left out of the stack by outlier rejection.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rejected: Vec<usize>,
+    /// Registration confidence (correlation peak-to-mean ratio) of each
+    /// selected frame; `None` for the reference.
+    #[serde(default)]
+    pub confidences: Vec<Option<f64>>,
+}
+
+impl CachedAlignment {
+    /// Drop the entries of `items` (parallel to `selected`) whose frame was rejected.
+    pub fn retain_kept<T>(&self, items: &mut Vec<T>) {
+        if self.rejected.is_empty() {
+            return;
+        }
+        let mut frames = self.selected.iter();
+        items.retain(|_| frames.next().is_some_and(|i| !self.rejected.contains(i)));
+    }
+
+    /// Package freshly computed offsets of the `selected` frames, running
+    /// outlier rejection first when `config` asks for it. `read(k)` yields
+    /// the data of the `k`-th selected frame.
+    ///
+    /// Confidences come with rejection; without it they cost a correlation
+    /// per frame and are only measured when `with_confidences` is set (for a
+    /// frame report).
+    pub fn checked<R>(
+        selected: &[usize],
+        mut offsets: Vec<AlignmentOffset>,
+        read: R,
+        config: &AlignmentConfig,
+        backend: &dyn ComputeBackend,
+        with_confidences: bool,
+    ) -> Result<Self>
+    where
+        R: Fn(usize) -> Result<Array2<f32>> + Sync,
+    {
+        let rejection = config.rejection.as_ref().filter(|_| selected.len() > 1);
+        let (rejected, confidences) = if rejection.is_none() && !with_confidences {
+            (Vec::new(), Vec::new())
+        } else {
+            let reference = read(0)?;
+            let aligner = ReferenceAligner::new(&reference, config, backend)?;
+            match rejection {
+                Some(rejection) => {
+                    let check =
+                        reject_outliers(&mut offsets, read, selected, 0, rejection, &aligner)?;
+                    let rejected: Vec<usize> = selected
+                        .iter()
+                        .zip(&check.rejected)
+                        .filter(|(_, &r)| r)
+                        .map(|(&i, _)| i)
+                        .collect();
+                    info!(
+                        rejected = rejected.len(),
+                        recovered = check.recovered,
+                        "Checked alignment for outliers"
+                    );
+                    (rejected, check.confidences)
+                }
+                None => (
+                    Vec::new(),
+                    alignment_confidences(&offsets, read, 0, &aligner)?,
+                ),
+            }
+        };
+        let alignment = Self {
+            selected: selected.to_vec(),
+            offsets,
+            rejected,
+            confidences: confidences
+                .into_iter()
+                .map(|c| Some(c).filter(|c| c.is_finite()))
+                .collect(),
+        };
+        if let Some((min, median)) = alignment.confidence_summary() {
+            info!(min, median, "Alignment confidence");
+        }
+        Ok(alignment)
+    }
+
+    /// Minimum and median confidence of the kept frames, excluding the
+    /// reference; `None` when there are none.
+    pub fn confidence_summary(&self) -> Option<(f64, f64)> {
+        let mut confidences = self.confidences.clone();
+        self.retain_kept(&mut confidences);
+        let mut values: Vec<f64> = confidences.into_iter().flatten().collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        Some((values[0], values[values.len() / 2]))
+    }
+
+    /// Confidences of the selected frames, NaN for the reference and for
+    /// alignments cached without them, as [`FrameReport::record_offsets`]
+    /// expects.
+    ///
+    /// [`FrameReport::record_offsets`]: crate::io::frame_report::FrameReport::record_offsets
+    pub fn confidence_values(&self) -> Vec<f64> {
+        (0..self.selected.len())
+            .map(|k| {
+                self.confidences
+                    .get(k)
+                    .copied()
+                    .flatten()
+                    .unwrap_or(f64::NAN)
+            })
+            .collect()
+    }
+
+    /// Selected frames and offsets with the rejected frames removed.
+    pub fn kept(&self) -> (Vec<usize>, Vec<AlignmentOffset>) {
+        let mut selected = self.selected.clone();
+        let mut offsets = self.offsets.clone();
+        self.retain_kept(&mut selected);
+        self.retain_kept(&mut offsets);
+        (selected, offsets)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct QualityEntry {
+    key: StageKey,
+    ranked: Vec<(usize, QualityScore)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AlignmentEntry {
+    key: StageKey,
+    alignment: CachedAlignment,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StackEntry {
+    key: StageKey,
+    width: usize,
+    height: usize,
+    channels: usize,
+    bit_depth: u8,
+    #[serde(default)]
+    ap_diagnostics: Option<ApDiagnostics>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Manifest {
+    version: u32,
+    fingerprint: String,
+    quality: Option<QualityEntry>,
+    alignment: Option<AlignmentEntry>,
+    stack: Option<StackEntry>,
+    /// Settings of the last run, so a frontend can resume with them.
+    #[serde(default)]
+    settings: Option<PipelineConfig>,
+}
+
+/// Session cache bound to one input file.
+pub struct SessionCache {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl SessionCache {
+    /// Sidecar directory used for `input`.
+    pub fn sidecar_dir(input: &Path) -> PathBuf {
+        input.with_extension("jupiter")
+    }
+
+    /// Sidecar directory used for a multi-file sequence: next to the first
+    /// file, named after it and the number of further files (`a.ser` + 2 more
+    /// -> `a+2.jupiter/`). A single input uses [`SessionCache::sidecar_dir`].
+    pub fn sidecar_dir_all(inputs: &[PathBuf]) -> Result<PathBuf> {
+        match inputs {
+            [] => Err(JupiterError::Pipeline("No input files".into())),
+            [input] => Ok(Self::sidecar_dir(input)),
+            [first, rest @ ..] => {
+                let stem = first.file_stem().unwrap_or_default().to_string_lossy();
+                Ok(first.with_file_name(format!("{stem}+{}.jupiter", rest.len())))
+            }
+        }
+    }
+
+    /// Open (or start) the session for `input`.
+    ///
+    /// A stored session whose fingerprint no longer matches the file, or that
+    /// was written by an incompatible version, is discarded.
+    pub fn open(input: &Path) -> Result<Self> {
+        Self::open_at(Self::sidecar_dir(input), fingerprint_file(input)?)
+    }
+
+    /// Open (or start) the session for a multi-file sequence.
+    ///
+    /// The session is fingerprinted on every file, in order, and lives in
+    /// [`SessionCache::sidecar_dir_all`]. A single input behaves exactly like
+    /// [`SessionCache::open`].
+    pub fn open_all(inputs: &[PathBuf]) -> Result<Self> {
+        let dir = Self::sidecar_dir_all(inputs)?;
+        let fingerprints = inputs
+            .iter()
+            .map(|p| fingerprint_file(p))
+            .collect::<Result<Vec<_>>>()?;
+        Self::open_at(dir, fingerprints.join("+"))
+    }
+
+    fn open_at(dir: PathBuf, fingerprint: String) -> Result<Self> {
+        let manifest = std::fs::read_to_string(dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|text| serde_json::from_str::<Manifest>(&text).ok())
+            .filter(|m| m.version == SESSION_VERSION && m.fingerprint == fingerprint)
+            .unwrap_or_else(|| Manifest {
+                version: SESSION_VERSION,
+                fingerprint,
+                ..Default::default()
+            });
+        Ok(Self { dir, manifest })
+    }
+
+    /// Remove the sidecar directory for `inputs` (one file or a multi-file
+    /// sequence), if any.
+    pub fn clear(inputs: &[PathBuf]) -> Result<()> {
+        let dir = Self::sidecar_dir_all(inputs)?;
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// All stage keys for a pipeline config. `debayer` is the resolved colour
+    /// handling (`None` for mono processing); `approximate` is whether the
+    /// stack will be a streaming approximation (see [`Self::stack_key`]).
+    pub fn keys(
+        &self,
+        config: &PipelineConfig,
+        debayer: Option<&DebayerMethod>,
+        approximate: bool,
+    ) -> Result<SessionKeys> {
+        let method = &config.stacking.method;
+        let metric = method
+            .own_quality_metric()
+            .unwrap_or(&config.frame_selection.metric);
+        let quality = self.quality_key(metric, debayer);
+        let mut alignment =
+            Self::alignment_key(&quality, &config.frame_selection, &config.alignment)?;
+        if method.selects_own_frames() {
+            // Global alignment against the best frame, with its own checks.
+            let mut h = Fnv64::new();
+            h.write_str(&alignment.0);
+            h.write_str("global");
+            alignment = h.key();
+        }
+        let stack = Self::stack_key(&alignment, &config.stacking, approximate);
+        Ok(SessionKeys {
+            quality,
+            alignment,
+            stack,
+        })
+    }
+
+    pub fn quality_key(&self, metric: &QualityMetric, debayer: Option<&DebayerMethod>) -> StageKey {
+        let mut h = Fnv64::new();
+        h.write_str(&self.manifest.fingerprint);
+        h.write_str(&format!("{metric:?}|{debayer:?}"));
+        h.key()
+    }
+
+    /// Key for the alignment stage. An explicit frame list is keyed on its
+    /// contents, so editing the file invalidates the cached selection.
+    pub fn alignment_key(
+        quality: &StageKey,
+        selection: &FrameSelectionConfig,
+        alignment: &AlignmentConfig,
+    ) -> Result<StageKey> {
+        let mut h = Fnv64::new();
+        h.write_str(&quality.0);
+        h.write_str(&to_json(selection)?);
+        if let Some(ref list) = selection.frame_list {
+            h.write(&std::fs::read(list)?);
+        }
+        h.write_str(&to_json(alignment)?);
+        Ok(h.key())
+    }
+
+    /// Key for the stacking stage. `approximate` marks a stack produced by a
+    /// low-memory streaming approximation
+    /// ([`StackMethod::streams_approximately`](super::config::StackMethod::streams_approximately)), so it is never served in
+    /// place of the exact stack of the same config, or the other way round.
+    pub fn stack_key(
+        alignment: &StageKey,
+        stacking: &StackingConfig,
+        approximate: bool,
+    ) -> StageKey {
+        let mut h = Fnv64::new();
+        h.write_str(&alignment.0);
+        h.write_str(&to_json(stacking).unwrap_or_default());
+        if approximate {
+            h.write_str("streaming-approximation");
+        }
+        h.key()
+    }
+
+    pub fn ranking(&self, key: &StageKey) -> Option<&[(usize, QualityScore)]> {
+        self.manifest
+            .quality
+            .as_ref()
+            .filter(|e| &e.key == key)
+            .map(|e| e.ranked.as_slice())
+    }
+
+    pub fn store_ranking(&mut self, key: StageKey, ranked: Vec<(usize, QualityScore)>) {
+        self.manifest.quality = Some(QualityEntry { key, ranked });
+    }
+
+    pub fn alignment(&self, key: &StageKey) -> Option<&CachedAlignment> {
+        self.manifest
+            .alignment
+            .as_ref()
+            .filter(|e| &e.key == key)
+            .map(|e| &e.alignment)
+    }
+
+    pub fn store_alignment(&mut self, key: StageKey, alignment: CachedAlignment) {
+        self.manifest.alignment = Some(AlignmentEntry { key, alignment });
+    }
+
+    /// Whether a stacked image is stored under `key`.
+    pub fn has_stack(&self, key: &StageKey) -> bool {
+        self.manifest.stack.as_ref().is_some_and(|e| &e.key == key)
+    }
+
+    /// Load the stacked image stored under `key`. A missing or truncated
+    /// image file is treated as a cache miss.
+    pub fn stacked(&self, key: &StageKey) -> Option<PipelineOutput> {
+        let entry = self.manifest.stack.as_ref().filter(|e| &e.key == key)?;
+        let bytes = match std::fs::read(self.dir.join(STACK_FILE)) {
+            Ok(b) => b,
+            Err(e) => {
+                warn!(error = %e, "Cached stack unreadable, ignoring");
+                return None;
+            }
+        };
+        let plane = entry.width * entry.height;
+        if bytes.len() != plane * entry.channels * 4 {
+            warn!("Cached stack has unexpected size, ignoring");
+            return None;
+        }
+        let mut planes = bytes
+            .chunks_exact(plane * 4)
+            .map(|chunk| {
+                let data: Vec<f32> = chunk
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect();
+                Array2::from_shape_vec((entry.height, entry.width), data)
+                    .map(|a| Frame::new(a, entry.bit_depth))
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .ok()?;
+        match entry.channels {
+            1 => planes.pop().map(PipelineOutput::Mono),
+            3 => {
+                let blue = planes.pop()?;
+                let green = planes.pop()?;
+                let red = planes.pop()?;
+                Some(PipelineOutput::Color(ColorFrame { red, green, blue }))
+            }
+            _ => None,
+        }
+    }
+
+    /// AP diagnostics stored with the stack under `key` (multi-point and
+    /// surface warp only).
+    pub fn ap_diagnostics(&self, key: &StageKey) -> Option<&ApDiagnostics> {
+        self.manifest
+            .stack
+            .as_ref()
+            .filter(|e| &e.key == key)
+            .and_then(|e| e.ap_diagnostics.as_ref())
+    }
+
+    /// AP diagnostics of the most recently stored stack, whatever its key.
+    pub fn last_ap_diagnostics(&self) -> Option<&ApDiagnostics> {
+        self.manifest
+            .stack
+            .as_ref()
+            .and_then(|e| e.ap_diagnostics.as_ref())
+    }
+
+    /// Store the stacked image; it is written to disk immediately.
+    pub fn store_stack(
+        &mut self,
+        key: StageKey,
+        stacked: &PipelineOutput,
+        ap_diagnostics: Option<ApDiagnostics>,
+    ) -> Result<()> {
+        let planes: Vec<&Frame> = match stacked {
+            PipelineOutput::Mono(f) => vec![f],
+            PipelineOutput::Color(cf) => vec![&cf.red, &cf.green, &cf.blue],
+        };
+        let (height, width) = planes[0].data.dim();
+        let mut bytes = Vec::with_capacity(width * height * planes.len() * 4);
+        for plane in &planes {
+            for v in plane.data.iter() {
+                bytes.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.dir.join(STACK_FILE), bytes)?;
+        self.manifest.stack = Some(StackEntry {
+            key,
+            width,
+            height,
+            channels: planes.len(),
+            bit_depth: planes[0].original_bit_depth,
+            ap_diagnostics,
+        });
+        Ok(())
+    }
+
+    /// Settings the session was last run with.
+    pub fn settings(&self) -> Option<&PipelineConfig> {
+        self.manifest.settings.as_ref()
+    }
+
+    pub fn store_settings(&mut self, config: &PipelineConfig) {
+        self.manifest.settings = Some(config.clone());
+    }
+
+    /// Write the manifest to the sidecar directory.
+    pub fn save(&self) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let text = serde_json::to_string(&self.manifest)
+            .map_err(|e| JupiterError::Pipeline(format!("Failed to encode session: {e}")))?;
+        std::fs::write(self.dir.join(MANIFEST_FILE), text)?;
+        debug!(dir = %self.dir.display(), "Session saved");
+        Ok(())
+    }
+}
+
+/// Fingerprint a file from its length and evenly spaced content samples.
+///
+/// Reading the whole capture would cost as much as scoring it, so only the
+/// header, the tail and [`FINGERPRINT_SAMPLES`] blocks in between are hashed
+/// (about 260 KiB however long the capture). The trade-off: an in-place edit
+/// that keeps the file length and misses every sampled block goes unnoticed
+/// and the stale session is reused. Recaptures, trims and appends change the
+/// length or the header timestamps and are always caught; for anything else
+/// `--clear-cache` discards the session.
+fn fingerprint_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut h = Fnv64::new();
+    h.write(&len.to_le_bytes());
+
+    let mut buf = vec![0u8; FINGERPRINT_SAMPLE_LEN];
+    let step = (len / FINGERPRINT_SAMPLES).max(1);
+    let mut positions: Vec<u64> = (0..FINGERPRINT_SAMPLES)
+        .map(|i| i * step)
+        .filter(|&p| p < len)
+        .collect();
+    positions.push(len.saturating_sub(FINGERPRINT_SAMPLE_LEN as u64));
+    for pos in positions {
+        file.seek(SeekFrom::Start(pos))?;
+        let n = file.read(&mut buf)?;
+        h.write(&buf[..n]);
+    }
+    Ok(format!("{:016x}", h.0))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value)
+        .map_err(|e| JupiterError::Pipeline(format!("Failed to encode config: {e}")))
+}
+
+/// 64-bit FNV-1a; stable across runs and Rust versions, unlike `DefaultHasher`.
+struct Fnv64(u64);
+
+impl Fnv64 {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write(s.as_bytes());
+        self.write(&[0xff]);
+    }
+
+    fn key(&self) -> StageKey {
+        StageKey(format!("{:016x}", self.0))
+    }
+}
+
+/// Stage results shared between the orchestrator and the stacking flows.
+///
+/// The orchestrator seeds it from a [`SessionCache`] hit; the flows reuse
+/// seeded values instead of recomputing and record whatever they compute so
+/// it can be written back afterwards.
+#[derive(Default)]
+pub(super) struct StageMemo {
+    ranked: RefCell<Option<Vec<(usize, QualityScore)>>>,
+    alignment: RefCell<Option<CachedAlignment>>,
+}
+
+impl StageMemo {
+    pub(super) fn seeded(
+        ranked: Option<Vec<(usize, QualityScore)>>,
+        alignment: Option<CachedAlignment>,
+    ) -> Self {
+        Self {
+            ranked: RefCell::new(ranked),
+            alignment: RefCell::new(alignment),
+        }
+    }
+
+    /// Cached ranking, or the result of `compute` (which is then recorded).
+    pub(super) fn ranked_or(
+        &self,
+        compute: impl FnOnce() -> Result<Vec<(usize, QualityScore)>>,
+    ) -> Result<Vec<(usize, QualityScore)>> {
+        if let Some(ref ranked) = *self.ranked.borrow() {
+            info!("Reusing cached quality ranking");
+            return Ok(ranked.clone());
+        }
+        let ranked = compute()?;
+        *self.ranked.borrow_mut() = Some(ranked.clone());
+        Ok(ranked)
+    }
+
+    /// Cached alignment for exactly `selected`, or the result of `compute`.
+    pub(super) fn alignment_or(
+        &self,
+        selected: &[usize],
+        compute: impl FnOnce() -> Result<CachedAlignment>,
+    ) -> Result<CachedAlignment> {
+        if let Some(ref cached) = *self.alignment.borrow() {
+            if cached.selected == selected {
+                info!("Reusing cached alignment offsets");
+                return Ok(cached.clone());
+            }
+        }
+        let alignment = compute()?;
+        *self.alignment.borrow_mut() = Some(alignment.clone());
+        Ok(alignment)
+    }
+
+    /// Frames the recorded alignment kept, if a flow aligned any.
+    pub(super) fn kept_frames(&self) -> Option<Vec<usize>> {
+        self.alignment.borrow().as_ref().map(|a| a.kept().0)
+    }
+
+    pub(super) fn into_parts(
+        self,
+    ) -> (Option<Vec<(usize, QualityScore)>>, Option<CachedAlignment>) {
+        (self.ranked.into_inner(), self.alignment.into_inner())
+    }
+}
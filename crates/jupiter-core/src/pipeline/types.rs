@@ -1,5 +1,6 @@
 use crate::color::debayer::luminance;
 use crate::frame::{ColorFrame, Frame};
+use crate::io::ser::FileContribution;
 
 /// Pipeline processing stage, used for progress reporting.
 #[derive(Clone, Copy, Debug)]
@@ -65,6 +66,10 @@ pub trait ProgressReporter: Send + Sync {
 
     /// The current stage is finished.
     fn finish_stage(&self) {}
+
+    /// Several input files were stacked as one sequence; `contributions`
+    /// lists each in input order with the number of its frames stacked.
+    fn file_contributions(&self, _contributions: &[FileContribution]) {}
 }
 
 /// No-op progress reporter, used when `run_pipeline` delegates.
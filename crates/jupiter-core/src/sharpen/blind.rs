@@ -0,0 +1,152 @@
+//! Blind deconvolution: estimate the PSF together with the image.
+//!
+//! Alternating Richardson-Lucy (Fish et al., 1995). Each round runs a few RL
+//! steps on the image with the PSF held fixed, then a few on the PSF with the
+//! image held fixed; the PSF step is the same multiplicative update with the
+//! roles of image and kernel swapped. The PSF starts from the configured model
+//! and lives on a small square support centred on the origin. After every
+//! step it is clipped to be non-negative and renormalized, which is what keeps
+//! the estimate physical.
+//!
+//! Without a prior on the image the problem is ambiguous: a PSF that is too
+//! narrow is always consistent with the data, because the image simply keeps
+//! the remaining blur. The PSF steps therefore reliably shrink a starting
+//! guess that is too wide, but will not widen one that is too narrow, so the
+//! configured model should err on the wide side.
+//!
+//! All transforms go through the [`ComputeBackend`], so the same code runs on
+//! the CPU and the GPU. Backends may zero-pad to their preferred FFT size, so
+//! kernels are laid out at the size of the transform actually used.
+
+use ndarray::Array2;
+
+use crate::compute::{ComputeBackend, GpuBuffer};
+use crate::consts::{BLIND_IMAGE_STEPS, BLIND_PSF_STEPS, EPSILON};
+use crate::frame::Frame;
+use crate::sharpen::psf::{crop_kernel, wrap_kernel};
+
+/// Jointly deconvolve `frame` and estimate its PSF.
+///
+/// `initial_psf` is a full-size PSF in the wrap-around layout (as from
+/// [`generate_psf`](super::deconvolution::generate_psf)); its centre is cut to
+/// `psf_size` x `psf_size` (rounded up to odd) to start the estimate.
+/// Returns the restored frame and the estimated PSF as a centred kernel
+/// summing to 1.
+pub fn blind_richardson_lucy(
+    frame: &Frame,
+    initial_psf: &Array2<f32>,
+    iterations: usize,
+    psf_size: usize,
+    backend: &dyn ComputeBackend,
+) -> (Frame, Array2<f32>) {
+    let (h, w) = frame.data.dim();
+    let observed = backend.upload(&frame.data);
+    let spectrum = backend.fft2d(&observed);
+    let transform = Transform {
+        backend,
+        dims: (h, w),
+        padded: (spectrum.height, spectrum.width),
+    };
+
+    let mut kernel = crop_kernel(initial_psf, psf_size.max(1));
+    let mut estimate = frame.data.clone();
+
+    for _ in 0..iterations {
+        // Image steps with the PSF fixed.
+        let otf = transform.otf(&kernel);
+        let otf_flipped = transform.otf(&flip(&kernel));
+        let mut current = backend.upload(&estimate);
+        for _ in 0..BLIND_IMAGE_STEPS {
+            let blurred = transform.convolve(&current, &otf);
+            let ratio = backend.divide_real(&observed, &blurred, EPSILON);
+            let correction = transform.convolve(&ratio, &otf_flipped);
+            current = backend.multiply_real(&current, &correction);
+        }
+        estimate = backend.download(&current);
+
+        // PSF steps with the image fixed.
+        let total = estimate.sum().max(EPSILON);
+        let estimate_fft = backend.fft2d(&current);
+        let flipped_fft = backend.fft2d(&backend.upload(&transform.flip_padded(&estimate)));
+        for _ in 0..BLIND_PSF_STEPS {
+            let blurred = transform.convolve_spectrum(&estimate_fft, &transform.otf(&kernel));
+            let ratio = backend.divide_real(&observed, &blurred, EPSILON);
+            let correlation = backend.download(&backend.ifft2d_real(
+                &backend.complex_mul(&backend.fft2d(&ratio), &flipped_fft),
+                transform.padded.0,
+                transform.padded.1,
+            ));
+            update_kernel(&mut kernel, &correlation, total);
+        }
+    }
+
+    estimate.mapv_inplace(|v| v.clamp(0.0, 1.0));
+    (Frame::new(estimate, frame.original_bit_depth), kernel)
+}
+
+/// FFT convolution helpers at the backend's transform size.
+struct Transform<'a> {
+    backend: &'a dyn ComputeBackend,
+    /// Image size.
+    dims: (usize, usize),
+    /// Size the backend's FFT works at (the image size, or larger if padded).
+    padded: (usize, usize),
+}
+
+impl Transform<'_> {
+    /// Transfer function of a centred kernel.
+    fn otf(&self, kernel: &Array2<f32>) -> GpuBuffer {
+        let (ph, pw) = self.padded;
+        self.backend
+            .fft2d(&self.backend.upload(&wrap_kernel(kernel, ph, pw)))
+    }
+
+    fn convolve(&self, data: &GpuBuffer, otf: &GpuBuffer) -> GpuBuffer {
+        self.convolve_spectrum(&self.backend.fft2d(data), otf)
+    }
+
+    fn convolve_spectrum(&self, spectrum: &GpuBuffer, otf: &GpuBuffer) -> GpuBuffer {
+        let (h, w) = self.dims;
+        self.backend
+            .ifft2d_real(&self.backend.complex_mul(spectrum, otf), h, w)
+    }
+
+    /// `data` mirrored through the origin at the transform size, so that
+    /// its spectrum is the conjugate of the spectrum of `data` and a product
+    /// with it is a correlation.
+    fn flip_padded(&self, data: &Array2<f32>) -> Array2<f32> {
+        let (h, w) = self.dims;
+        let (ph, pw) = self.padded;
+        Array2::from_shape_fn((ph, pw), |(r, c)| {
+            let (sr, sc) = ((ph - r) % ph, (pw - c) % pw);
+            if sr < h && sc < w {
+                data[[sr, sc]]
+            } else {
+                0.0
+            }
+        })
+    }
+}
+
+/// Multiplicative RL update of a centred kernel from the correlation of the
+/// image with the ratio `observed / blurred` (wrap-around layout), then
+/// clip to non-negative and renormalize.
+fn update_kernel(kernel: &mut Array2<f32>, correlation: &Array2<f32>, total: f32) {
+    let (ph, pw) = correlation.dim();
+    let half = (kernel.nrows() / 2) as isize;
+    for ((r, c), k) in kernel.indexed_iter_mut() {
+        let row = (r as isize - half).rem_euclid(ph as isize) as usize;
+        let col = (c as isize - half).rem_euclid(pw as isize) as usize;
+        *k = (*k * correlation[[row, col]] / total).max(0.0);
+    }
+    let sum: f32 = kernel.iter().sum();
+    if sum > EPSILON {
+        kernel.mapv_inplace(|v| v / sum);
+    }
+}
+
+/// A centred kernel mirrored through its centre.
+fn flip(kernel: &Array2<f32>) -> Array2<f32> {
+    let (kh, kw) = kernel.dim();
+    Array2::from_shape_fn((kh, kw), |(r, c)| kernel[[kh - 1 - r, kw - 1 - c]])
+}
@@ -1,35 +1,168 @@
 use ndarray::Array2;
 use num_complex::Complex;
+use tracing::info;
 
-use crate::compute::cpu::{fft2d_forward, ifft2d_inverse};
+use crate::compute::cpu::{fft2d_forward, ifft2d_inverse, CpuBackend};
 use crate::compute::ComputeBackend;
 use crate::consts::EPSILON;
+use crate::error::{JupiterError, Result};
 use crate::frame::Frame;
-use crate::pipeline::config::{DeconvolutionConfig, DeconvolutionMethod, PsfModel};
+use crate::pipeline::config::{DeconvolutionConfig, DeconvolutionMethod, LimbFitConfig, PsfModel};
+use crate::sharpen::blind::blind_richardson_lucy;
+use crate::sharpen::psf::{estimate_limb_sigma, kernel_sigma, load_psf, wrap_kernel};
+use crate::sharpen::regularized_rl::{
+    apply_mask, crop_padding, edge_pad, pad_apodized, planet_mask, RlControl, RlOptions,
+};
+use crate::sharpen::tiled::deconvolve_tiled;
 
 /// Generate PSF and dispatch to the appropriate deconvolution algorithm.
-pub fn deconvolve(frame: &Frame, config: &DeconvolutionConfig) -> Frame {
-    let (h, w) = frame.data.dim();
-    let psf = generate_psf(&config.psf, h, w);
+pub fn deconvolve(frame: &Frame, config: &DeconvolutionConfig) -> Result<Frame> {
+    deconvolve_with_psf(frame, config).map(|(restored, _)| restored)
+}
 
-    match &config.method {
-        DeconvolutionMethod::RichardsonLucy { iterations } => {
-            richardson_lucy(frame, &psf, *iterations)
-        }
-        DeconvolutionMethod::Wiener { noise_ratio } => wiener_filter(frame, &psf, *noise_ratio),
+/// Like [`deconvolve`], also returning the PSF estimated by blind
+/// deconvolution as a centred kernel (`None` for methods that only use the
+/// configured PSF, and for tiled deconvolution, which estimates one per tile).
+pub fn deconvolve_with_psf(
+    frame: &Frame,
+    config: &DeconvolutionConfig,
+) -> Result<(Frame, Option<Array2<f32>>)> {
+    deconvolve_loaded(frame, config, &Psf::load(&config.psf)?)
+}
+
+/// Like [`deconvolve_with_psf`], with the configured PSF already loaded, so
+/// the channels of a colour image share one read of a measured PSF.
+pub fn deconvolve_loaded(
+    frame: &Frame,
+    config: &DeconvolutionConfig,
+    psf: &Psf,
+) -> Result<(Frame, Option<Array2<f32>>)> {
+    if let Some(tiling) = &config.tiling {
+        return Ok((deconvolve_tiled(frame, config, tiling, psf, None)?, None));
     }
+    let psf = psf.for_image(&frame.data)?.generate(frame.data.dim())?;
+    run_method(frame, &psf, &config.method, None)
+}
+
+/// Deconvolve `frame` with a full-size `psf` (wrap-around layout) by
+/// `method`, on the CPU or, given a backend, through it.
+pub(crate) fn run_method(
+    frame: &Frame,
+    psf: &Array2<f32>,
+    method: &DeconvolutionMethod,
+    backend: Option<&dyn ComputeBackend>,
+) -> Result<(Frame, Option<Array2<f32>>)> {
+    Ok(match method {
+        DeconvolutionMethod::RichardsonLucy {
+            iterations,
+            options,
+        } => (
+            richardson_lucy_frame(frame, psf, *iterations, options, backend)?,
+            None,
+        ),
+        DeconvolutionMethod::Wiener { noise_ratio } => {
+            let restored = match backend {
+                Some(backend) => wiener_filter_gpu(frame, psf, *noise_ratio, backend),
+                None => wiener_filter(frame, psf, *noise_ratio),
+            };
+            (restored, None)
+        }
+        DeconvolutionMethod::BlindRichardsonLucy {
+            iterations,
+            psf_size,
+        } => {
+            let (restored, kernel) = blind_richardson_lucy(
+                frame,
+                psf,
+                *iterations,
+                *psf_size,
+                backend.unwrap_or(&CpuBackend),
+            );
+            log_estimate(&kernel);
+            (restored, Some(kernel))
+        }
+    })
+}
+
+fn log_estimate(kernel: &Array2<f32>) {
+    info!(
+        sigma = kernel_sigma(kernel),
+        size = kernel.nrows(),
+        "Blind deconvolution PSF estimate"
+    );
 }
 
 // ---------------------------------------------------------------------------
 // PSF generation
 // ---------------------------------------------------------------------------
 
-pub fn generate_psf(model: &PsfModel, height: usize, width: usize) -> Array2<f32> {
-    match model {
+/// The configured PSF with its file input loaded, so a measured PSF is read
+/// once per run rather than once per channel or tile.
+#[derive(Clone, Debug)]
+pub enum Psf {
+    /// An analytic model, or [`PsfModel::Limb`] until resolved against an
+    /// image with [`Psf::for_image`].
+    Model(PsfModel),
+    /// A measured kernel, centred.
+    Kernel(Array2<f32>),
+}
+
+impl Psf {
+    /// Read a measured PSF from its file; other models are kept as they are.
+    pub fn load(model: &PsfModel) -> Result<Self> {
+        Ok(match model {
+            PsfModel::Measured { path, region } => Self::Kernel(load_psf(path, region.as_ref())?),
+            other => Self::Model(other.clone()),
+        })
+    }
+
+    /// This PSF for deconvolving `image`: the limb model becomes the Gaussian
+    /// estimated from the image ([`estimate_limb_psf`]).
+    pub fn for_image(&self, image: &Array2<f32>) -> Result<Self> {
+        match self {
+            Self::Model(PsfModel::Limb) => estimate_limb_psf(image).map(Self::Model),
+            other => Ok(other.clone()),
+        }
+    }
+
+    /// The PSF at `dims` in the FFT-ready wrap-around layout.
+    pub fn generate(&self, dims: (usize, usize)) -> Result<Array2<f32>> {
+        match self {
+            Self::Model(model) => generate_psf(model, dims),
+            Self::Kernel(kernel) => Ok(wrap_kernel(kernel, dims.0, dims.1)),
+        }
+    }
+}
+
+/// Gaussian PSF whose width is measured from the planet's limb in `image`.
+pub fn estimate_limb_psf(image: &Array2<f32>) -> Result<PsfModel> {
+    let sigma = estimate_limb_sigma(image, &LimbFitConfig::default())?;
+    info!(sigma, "PSF sigma estimated from limb");
+    Ok(PsfModel::Gaussian {
+        sigma: sigma as f32,
+    })
+}
+
+/// Build the PSF for `model` at `(height, width)` in the FFT-ready
+/// wrap-around layout.
+///
+/// A measured PSF is read from its file on every call (see [`Psf::load`] to
+/// reuse it). [`PsfModel::Limb`] depends on the image and must first be
+/// turned into a Gaussian with [`estimate_limb_psf`].
+pub fn generate_psf(model: &PsfModel, (height, width): (usize, usize)) -> Result<Array2<f32>> {
+    Ok(match model {
         PsfModel::Gaussian { sigma } => generate_gaussian_psf(*sigma, height, width),
         PsfModel::Kolmogorov { seeing } => generate_kolmogorov_psf(*seeing, height, width),
         PsfModel::Airy { radius } => generate_airy_psf(*radius, height, width),
-    }
+        PsfModel::Measured { path, region } => {
+            wrap_kernel(&load_psf(path, region.as_ref())?, height, width)
+        }
+        PsfModel::Limb => {
+            return Err(JupiterError::Pipeline(
+                "The limb PSF depends on the image; estimate it first".into(),
+            ))
+        }
+    })
 }
 
 /// Gaussian PSF centered at (0,0) with wrap-around (FFT-ready layout), normalized to sum=1.
@@ -221,8 +354,52 @@ pub fn bessel_j1(x: f64) -> f64 {
 // Richardson-Lucy deconvolution
 // ---------------------------------------------------------------------------
 
-fn richardson_lucy(frame: &Frame, psf: &Array2<f32>, iterations: usize) -> Frame {
+/// Richardson-Lucy with the refinements in `options` (see
+/// [`regularized_rl`](super::regularized_rl)), on the CPU or, given a
+/// backend, through it.
+fn richardson_lucy_frame(
+    frame: &Frame,
+    psf: &Array2<f32>,
+    iterations: usize,
+    options: &RlOptions,
+    backend: Option<&dyn ComputeBackend>,
+) -> Result<Frame> {
+    let mask = if options.planet_mask {
+        Some(planet_mask(&frame.data)?)
+    } else {
+        None
+    };
+    let control = RlControl::new(&frame.data, options);
+
     let (h, w) = frame.data.dim();
+    let pad = if options.pad_edges { edge_pad(h, w) } else { 0 };
+    let observed = pad_apodized(&frame.data, pad);
+    let psf = if pad > 0 {
+        rewrap_psf_padded(psf, h + 2 * pad, w + 2 * pad)
+    } else {
+        psf.clone()
+    };
+
+    let restored = match backend {
+        Some(backend) => richardson_lucy_gpu(&observed, &psf, iterations, &control, backend),
+        None => richardson_lucy(&observed, &psf, iterations, &control),
+    };
+    let mut restored = crop_padding(restored, pad);
+    if let Some(mask) = mask {
+        apply_mask(&mut restored, &frame.data, &mask);
+    }
+
+    restored.mapv_inplace(|v| v.clamp(0.0, 1.0));
+    Ok(Frame::new(restored, frame.original_bit_depth))
+}
+
+fn richardson_lucy(
+    observed: &Array2<f32>,
+    psf: &Array2<f32>,
+    iterations: usize,
+    control: &RlControl,
+) -> Array2<f32> {
+    let (h, w) = observed.dim();
     // Pre-compute H = FFT(psf) and H_flip = FFT(psf_flipped)
     let h_fft = fft2d_forward(psf);
 
@@ -238,48 +415,36 @@ fn richardson_lucy(frame: &Frame, psf: &Array2<f32>, iterations: usize) -> Frame
     let h_flip_fft = fft2d_forward(&psf_flipped);
 
     // Initial estimate = observed image
-    let mut estimate = frame.data.clone();
+    let mut estimate = observed.clone();
 
     for _iter in 0..iterations {
         // Forward model: blurred = IFFT(FFT(estimate) * H)
         let est_fft = fft2d_forward(&estimate);
-        let mut blurred_fft = Array2::<Complex<f64>>::zeros((h, w));
-        for row in 0..h {
-            for col in 0..w {
-                blurred_fft[[row, col]] = est_fft[[row, col]] * h_fft[[row, col]];
-            }
-        }
-        let blurred = ifft2d_inverse(&blurred_fft);
-
-        // ratio = observed / (blurred + epsilon)
-        let mut ratio = Array2::<f32>::zeros((h, w));
-        for row in 0..h {
-            for col in 0..w {
-                ratio[[row, col]] = frame.data[[row, col]] / (blurred[[row, col]] as f32 + EPSILON);
-            }
+        let blurred_fft = &est_fft * &h_fft;
+        let blurred = ifft2d_inverse(&blurred_fft).mapv(|v| v as f32);
+        if control.fits_noise(observed, &blurred) {
+            break;
         }
 
+        // ratio = observed / (blurred + epsilon), damped if enabled
+        let ratio = control.ratio(observed, &blurred);
+
         // correction = IFFT(FFT(ratio) * H_flip)
         let ratio_fft = fft2d_forward(&ratio);
-        let mut corr_fft = Array2::<Complex<f64>>::zeros((h, w));
-        for row in 0..h {
-            for col in 0..w {
-                corr_fft[[row, col]] = ratio_fft[[row, col]] * h_flip_fft[[row, col]];
-            }
-        }
-        let correction = ifft2d_inverse(&corr_fft);
+        let corr_fft = &ratio_fft * &h_flip_fft;
+        let mut correction = ifft2d_inverse(&corr_fft).mapv(|v| v as f32);
+        control.regularize(&mut correction, &estimate);
 
         // Multiplicative update
-        for row in 0..h {
-            for col in 0..w {
-                estimate[[row, col]] *= correction[[row, col]] as f32;
-            }
+        let next = &estimate * &correction;
+        let converged = control.converged(&estimate, &next);
+        estimate = next;
+        if converged {
+            break;
         }
     }
 
-    // Clamp to [0.0, 1.0]
-    estimate.mapv_inplace(|v| v.clamp(0.0, 1.0));
-    Frame::new(estimate, frame.original_bit_depth)
+    estimate
 }
 
 // ---------------------------------------------------------------------------
@@ -313,34 +478,33 @@ fn wiener_filter(frame: &Frame, psf: &Array2<f32>, noise_ratio: f32) -> Frame {
 // GPU-accelerated deconvolution
 // ---------------------------------------------------------------------------
 
-/// GPU-accelerated deconvolution dispatch.
+/// GPU-accelerated deconvolution dispatch, with the configured PSF loaded
+/// by [`Psf::load`].
 pub fn deconvolve_gpu(
     frame: &Frame,
     config: &DeconvolutionConfig,
+    psf: &Psf,
     backend: &dyn ComputeBackend,
-) -> Frame {
-    let (h, w) = frame.data.dim();
-    let psf = generate_psf(&config.psf, h, w);
-
-    match &config.method {
-        DeconvolutionMethod::RichardsonLucy { iterations } => {
-            richardson_lucy_gpu(frame, &psf, *iterations, backend)
-        }
-        DeconvolutionMethod::Wiener { noise_ratio } => {
-            wiener_filter_gpu(frame, &psf, *noise_ratio, backend)
-        }
+) -> Result<Frame> {
+    if let Some(tiling) = &config.tiling {
+        return deconvolve_tiled(frame, config, tiling, psf, Some(backend));
     }
+    let psf = psf.for_image(&frame.data)?.generate(frame.data.dim())?;
+    run_method(frame, &psf, &config.method, Some(backend)).map(|(restored, _)| restored)
 }
 
 /// GPU Richardson-Lucy: FFT/IFFT and complex_mul stay on GPU,
 /// element-wise real operations use backend divide_real/multiply_real.
+/// Damping, regularization and stopping run on the host, so with any of them
+/// enabled the model and correction are downloaded every iteration.
 fn richardson_lucy_gpu(
-    frame: &Frame,
+    observed_data: &Array2<f32>,
     psf: &Array2<f32>,
     iterations: usize,
+    control: &RlControl,
     backend: &dyn ComputeBackend,
-) -> Frame {
-    let (h, w) = frame.data.dim();
+) -> Array2<f32> {
+    let (h, w) = observed_data.dim();
 
     // GPU fft2d() zero-pads to next power-of-2. Re-wrap the PSF at the padded
     // dimensions so that the circular wrap-around structure is preserved.
@@ -349,7 +513,7 @@ fn richardson_lucy_gpu(
     let pw = w.next_power_of_two();
 
     // Upload observed image (zero-padded by fft2d — fine for images)
-    let observed = backend.upload(&frame.data);
+    let observed = backend.upload(observed_data);
 
     // Re-wrap PSF to (ph, pw), then FFT — stays on GPU
     let psf_padded = rewrap_psf_padded(psf, ph, pw);
@@ -370,7 +534,7 @@ fn richardson_lucy_gpu(
     let h_flip_fft = backend.fft2d(&psf_flip_gpu);
 
     // Initial estimate = observed image (on GPU)
-    let mut estimate = backend.upload(&frame.data);
+    let mut estimate = backend.upload(observed_data);
 
     for _iter in 0..iterations {
         // Forward model: blurred = IFFT(FFT(estimate) * H)
@@ -378,22 +542,39 @@ fn richardson_lucy_gpu(
         let blurred_fft = backend.complex_mul(&est_fft, &h_fft);
         let blurred = backend.ifft2d_real(&blurred_fft, h, w);
 
-        // ratio = observed / (blurred + epsilon)
-        let ratio = backend.divide_real(&observed, &blurred, EPSILON);
+        if control.is_plain() {
+            // ratio = observed / (blurred + epsilon)
+            let ratio = backend.divide_real(&observed, &blurred, EPSILON);
 
-        // correction = IFFT(FFT(ratio) * H_flip)
-        let ratio_fft = backend.fft2d(&ratio);
-        let corr_fft = backend.complex_mul(&ratio_fft, &h_flip_fft);
-        let correction = backend.ifft2d_real(&corr_fft, h, w);
+            // correction = IFFT(FFT(ratio) * H_flip)
+            let ratio_fft = backend.fft2d(&ratio);
+            let corr_fft = backend.complex_mul(&ratio_fft, &h_flip_fft);
+            let correction = backend.ifft2d_real(&corr_fft, h, w);
+
+            // Multiplicative update: estimate *= correction
+            estimate = backend.multiply_real(&estimate, &correction);
+            continue;
+        }
 
-        // Multiplicative update: estimate *= correction
-        estimate = backend.multiply_real(&estimate, &correction);
+        let blurred = backend.download(&blurred);
+        if control.fits_noise(observed_data, &blurred) {
+            break;
+        }
+        let ratio = backend.upload(&control.ratio(observed_data, &blurred));
+        let corr_fft = backend.complex_mul(&backend.fft2d(&ratio), &h_flip_fft);
+        let mut correction = backend.download(&backend.ifft2d_real(&corr_fft, h, w));
+
+        let current = backend.download(&estimate);
+        control.regularize(&mut correction, &current);
+        let next = &current * &correction;
+        let converged = control.converged(&current, &next);
+        estimate = backend.upload(&next);
+        if converged {
+            break;
+        }
     }
 
-    // Download and clamp
-    let mut result = backend.download(&estimate);
-    result.mapv_inplace(|v| v.clamp(0.0, 1.0));
-    Frame::new(result, frame.original_bit_depth)
+    backend.download(&estimate)
 }
 
 /// GPU Wiener filter: single-pass FFT-based deconvolution.
@@ -1,2 +1,6 @@
+pub mod blind;
 pub mod deconvolution;
+pub mod psf;
+pub mod regularized_rl;
+pub mod tiled;
 pub mod wavelet;
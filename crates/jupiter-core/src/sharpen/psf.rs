@@ -0,0 +1,335 @@
+//! Point-spread functions taken from the data instead of a hand-tuned model.
+//!
+//! A measured PSF is cut out of an image of a point source — a stacked star,
+//! or a moon small enough to be unresolved such as Io or Ganymede — with the
+//! sky background removed, recentred on its centroid and normalized.
+//!
+//! The limb estimate needs no extra image: the edge of a planet disk is
+//! nearly a step, so its profile in the frame is the seeing's edge-spread
+//! function. Radial profiles across the fitted limb give the 10-90% rise
+//! distance, which fixes the sigma of an equivalent Gaussian PSF.
+
+use std::f64::consts::TAU;
+use std::path::Path;
+
+use ndarray::{s, Array2};
+
+use crate::align::limb::fit_limb;
+use crate::align::phase_correlation::bilinear_sample;
+use crate::consts::{
+    EPSILON, GAUSSIAN_ESF_10_90_WIDTH, PSF_LIMB_MIN_CONTRAST, PSF_LIMB_PROFILES,
+    PSF_LIMB_PROFILE_HALF_WIDTH, PSF_LIMB_SAMPLE_STEP, PSF_MAX_RADIUS, PSF_NOISE_SIGMA,
+    PSF_SAVE_MARGIN,
+};
+use crate::error::{JupiterError, Result};
+use crate::frame::Frame;
+use crate::io::image_io::{load_image, save_image};
+use crate::pipeline::config::{LimbFitConfig, PsfRegion};
+
+/// Load an image and extract the PSF from it (see [`extract_psf`]).
+pub fn load_psf(path: &Path, region: Option<&PsfRegion>) -> Result<Array2<f32>> {
+    let frame = load_image(path)?;
+    extract_psf(&frame.data, region).map_err(|e| match e {
+        JupiterError::Pipeline(msg) => {
+            JupiterError::Pipeline(format!("{msg} ({})", path.display()))
+        }
+        other => other,
+    })
+}
+
+/// Extract a normalized PSF kernel from the point source in `region` of
+/// `data` (the whole image when `None`).
+///
+/// The background level and noise come from the median and MAD of the
+/// region's border. The kernel is centred on the source's centroid (found
+/// from its core, pixels above half the peak, then refined over the whole
+/// source), has odd size, is cut off where the azimuthal profile falls into
+/// the noise and sums to 1.
+pub fn extract_psf(data: &Array2<f32>, region: Option<&PsfRegion>) -> Result<Array2<f32>> {
+    let (h, w) = data.dim();
+    let region = region.copied().unwrap_or(PsfRegion {
+        x: 0,
+        y: 0,
+        width: w,
+        height: h,
+    });
+    if region.width < 3
+        || region.height < 3
+        || region.x + region.width > w
+        || region.y + region.height > h
+    {
+        return Err(JupiterError::Pipeline(format!(
+            "PSF region ({region}) does not fit in the {w}x{h} image"
+        )));
+    }
+
+    let patch = data.slice(s![
+        region.y..region.y + region.height,
+        region.x..region.x + region.width
+    ]);
+    let (ph, pw) = patch.dim();
+    let border: Vec<f32> = patch
+        .indexed_iter()
+        .filter(|&((r, c), _)| r == 0 || c == 0 || r == ph - 1 || c == pw - 1)
+        .map(|(_, &v)| v)
+        .collect();
+    let background = median(border.clone());
+    let noise = 1.4826 * median(border.iter().map(|v| (v - background).abs()).collect());
+    let threshold = (PSF_NOISE_SIGMA * noise).max(EPSILON);
+
+    let signal = patch.mapv(|v| (v - background).max(0.0));
+    let peak = signal.iter().copied().fold(0.0f32, f32::max);
+    if peak <= threshold {
+        return Err(JupiterError::Pipeline(format!(
+            "No point source above the background in PSF region ({region})"
+        )));
+    }
+
+    // Centroid of the core only, so stray noise far out cannot pull it.
+    let (mut sy, mut sx, mut sw) = (0.0f64, 0.0f64, 0.0f64);
+    for ((r, c), &v) in signal.indexed_iter() {
+        if v >= 0.5 * peak {
+            sy += r as f64 * v as f64;
+            sx += c as f64 * v as f64;
+            sw += v as f64;
+        }
+    }
+    let (cy, cx) = (sy / sw, sx / sw);
+
+    // Extend the kernel until the mean of a one-pixel annulus drops into
+    // the noise, staying inside the region.
+    let reach = cy
+        .min(cx)
+        .min(ph as f64 - 1.0 - cy)
+        .min(pw as f64 - 1.0 - cx);
+    let max_radius = (reach.floor().max(1.0) as usize).min(PSF_MAX_RADIUS);
+    let mut annuli = vec![(0.0f64, 0usize); max_radius + 1];
+    for ((r, c), &v) in signal.indexed_iter() {
+        let ring = (r as f64 - cy).hypot(c as f64 - cx).round() as usize;
+        if ring <= max_radius {
+            annuli[ring].0 += v as f64;
+            annuli[ring].1 += 1;
+        }
+    }
+    let radius = (1..=max_radius)
+        .find(|&ring| {
+            let (sum, n) = annuli[ring];
+            n > 0 && sum / (n as f64) < threshold as f64
+        })
+        .unwrap_or(max_radius);
+
+    // Refine the centre with the whole source: the core alone is biased
+    // towards the pixel grid when the star sits between pixels.
+    let (mut sy, mut sx, mut sw) = (0.0f64, 0.0f64, 0.0f64);
+    for ((r, c), &v) in signal.indexed_iter() {
+        if (r as f64 - cy).hypot(c as f64 - cx) <= radius as f64 {
+            sy += r as f64 * v as f64;
+            sx += c as f64 * v as f64;
+            sw += v as f64;
+        }
+    }
+    let (cy, cx) = (sy / sw, sx / sw);
+
+    let size = 2 * radius + 1;
+    let mut kernel = Array2::from_shape_fn((size, size), |(r, c)| {
+        let dy = r as f64 - radius as f64;
+        let dx = c as f64 - radius as f64;
+        if dy.hypot(dx) > radius as f64 + 0.5 {
+            0.0
+        } else {
+            bilinear_sample(&signal, cy + dy, cx + dx)
+        }
+    });
+
+    let sum: f32 = kernel.iter().sum();
+    let inv = 1.0 / sum;
+    kernel.mapv_inplace(|v| v * inv);
+    Ok(kernel)
+}
+
+/// Lay a centred kernel out at `(height, width)` with its centre at (0,0)
+/// and wrap-around for negative offsets, the layout the PSF generators use.
+/// Parts of the kernel that do not fit are dropped and the result is
+/// renormalized to sum 1.
+pub fn wrap_kernel(kernel: &Array2<f32>, height: usize, width: usize) -> Array2<f32> {
+    let (kh, kw) = kernel.dim();
+    let (ky, kx) = ((kh / 2) as isize, (kw / 2) as isize);
+    let fits = |d: isize, n: usize| d <= (n / 2) as isize && -d <= ((n - 1) / 2) as isize;
+
+    let mut psf = Array2::<f32>::zeros((height, width));
+    let mut sum = 0.0f64;
+    for ((r, c), &v) in kernel.indexed_iter() {
+        let dy = r as isize - ky;
+        let dx = c as isize - kx;
+        if fits(dy, height) && fits(dx, width) {
+            let row = dy.rem_euclid(height as isize) as usize;
+            let col = dx.rem_euclid(width as isize) as usize;
+            psf[[row, col]] = v;
+            sum += v as f64;
+        }
+    }
+
+    if sum > 0.0 {
+        let inv = 1.0 / sum as f32;
+        psf.mapv_inplace(|v| v * inv);
+    }
+    psf
+}
+
+/// Cut the central `size` x `size` kernel (`size` rounded up to odd) out of
+/// a wrap-around PSF; the inverse of [`wrap_kernel`]. The kernel is
+/// renormalized to sum 1.
+pub fn crop_kernel(psf: &Array2<f32>, size: usize) -> Array2<f32> {
+    let (h, w) = psf.dim();
+    let half = (size / 2) as isize;
+    let side = 2 * half as usize + 1;
+    let mut kernel = Array2::from_shape_fn((side, side), |(r, c)| {
+        let row = (r as isize - half).rem_euclid(h as isize) as usize;
+        let col = (c as isize - half).rem_euclid(w as isize) as usize;
+        psf[[row, col]].max(0.0)
+    });
+    let sum: f32 = kernel.iter().sum();
+    if sum > 0.0 {
+        kernel.mapv_inplace(|v| v / sum);
+    }
+    kernel
+}
+
+/// RMS width (px) per axis of a centred kernel, about its centroid. Equals
+/// sigma for a Gaussian.
+pub fn kernel_sigma(kernel: &Array2<f32>) -> f64 {
+    let (mut sy, mut sx, mut syy, mut sxx, mut sum) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for ((r, c), &v) in kernel.indexed_iter() {
+        let (y, x, v) = (r as f64, c as f64, v as f64);
+        sy += y * v;
+        sx += x * v;
+        syy += y * y * v;
+        sxx += x * x * v;
+        sum += v;
+    }
+    if sum <= 0.0 {
+        return 0.0;
+    }
+    let var_y = syy / sum - (sy / sum).powi(2);
+    let var_x = sxx / sum - (sx / sum).powi(2);
+    (0.5 * (var_y + var_x)).max(0.0).sqrt()
+}
+
+/// Save a centred kernel as a 16-bit image scaled to a peak of 1, with a
+/// blank margin, so it can be reused as a [`Measured`] PSF.
+///
+/// [`Measured`]: crate::pipeline::config::PsfModel::Measured
+pub fn save_psf(kernel: &Array2<f32>, path: &Path) -> Result<()> {
+    let (kh, kw) = kernel.dim();
+    let peak = kernel.iter().copied().fold(0.0f32, f32::max).max(EPSILON);
+    let mut data = Array2::<f32>::zeros((kh + 2 * PSF_SAVE_MARGIN, kw + 2 * PSF_SAVE_MARGIN));
+    data.slice_mut(s![
+        PSF_SAVE_MARGIN..PSF_SAVE_MARGIN + kh,
+        PSF_SAVE_MARGIN..PSF_SAVE_MARGIN + kw
+    ])
+    .assign(&kernel.mapv(|v| (v / peak).clamp(0.0, 1.0)));
+    save_image(&Frame::new(data, 16), path)
+}
+
+/// Estimate the sigma (px) of a Gaussian PSF from the limb of the planet
+/// disk in `data`.
+///
+/// Radial profiles are sampled across the fitted limb circle; each gives a
+/// 10-90% rise distance, and the median over the high-contrast profiles is
+/// converted to sigma. Profiles across the terminator or the unlit limb of
+/// a crescent have little contrast and are ignored.
+pub fn estimate_limb_sigma(data: &Array2<f32>, config: &LimbFitConfig) -> Result<f64> {
+    let circle = fit_limb(data, config)?;
+    let (h, w) = data.dim();
+    let half = PSF_LIMB_PROFILE_HALF_WIDTH.min(circle.radius / 2.0);
+    let steps = (2.0 * half / PSF_LIMB_SAMPLE_STEP).round() as usize;
+
+    let mut edges = Vec::with_capacity(PSF_LIMB_PROFILES);
+    for k in 0..PSF_LIMB_PROFILES {
+        let (sin, cos) = (TAU * k as f64 / PSF_LIMB_PROFILES as f64).sin_cos();
+        let point = |i: usize| {
+            let r = circle.radius - half + i as f64 * PSF_LIMB_SAMPLE_STEP;
+            (circle.center_y + r * sin, circle.center_x + r * cos)
+        };
+        let inside =
+            |(y, x): (f64, f64)| y >= 0.0 && x >= 0.0 && y <= (h - 1) as f64 && x <= (w - 1) as f64;
+        if !inside(point(0)) || !inside(point(steps)) {
+            continue;
+        }
+        let profile: Vec<f64> = (0..=steps)
+            .map(|i| {
+                let (y, x) = point(i);
+                bilinear_sample(data, y, x) as f64
+            })
+            .collect();
+        edges.extend(edge_width(&profile));
+    }
+
+    let strongest = edges.iter().map(|e| e.0).fold(0.0f64, f64::max);
+    let widths: Vec<f64> = edges
+        .iter()
+        .filter(|e| e.0 >= PSF_LIMB_MIN_CONTRAST * strongest)
+        .map(|e| e.1)
+        .collect();
+    if strongest <= 0.0 || widths.is_empty() {
+        return Err(JupiterError::Pipeline(
+            "No usable limb profile for PSF estimation".into(),
+        ));
+    }
+
+    Ok(median_f64(widths) * PSF_LIMB_SAMPLE_STEP / GAUSSIAN_ESF_10_90_WIDTH)
+}
+
+/// Contrast and 10-90% rise distance (in samples) of an edge profile that
+/// runs from inside the disk to the sky. The bright and dark levels are the
+/// means of the profile's first and last quarters.
+fn edge_width(profile: &[f64]) -> Option<(f64, f64)> {
+    let n = profile.len();
+    let quarter = n / 4;
+    if quarter == 0 {
+        return None;
+    }
+    let bright = profile[..quarter].iter().sum::<f64>() / quarter as f64;
+    let dark = profile[n - quarter..].iter().sum::<f64>() / quarter as f64;
+    let contrast = bright - dark;
+    if contrast <= 0.0 {
+        return None;
+    }
+
+    // Sub-pixel position where the profile falls through `fraction` of the
+    // edge between samples `i` and `i + 1`, if it does.
+    let fall = |i: usize, fraction: f64| -> Option<f64> {
+        let level = dark + fraction * contrast;
+        let (a, b) = (profile[i], profile[i + 1]);
+        (a >= level && b < level).then(|| i as f64 + (a - level) / (a - b))
+    };
+
+    let mid = n / 2;
+    let t50 = (0..n - 1)
+        .filter_map(|i| fall(i, 0.5))
+        .min_by(|a, b| (a - mid as f64).abs().total_cmp(&(b - mid as f64).abs()))?;
+    let i50 = t50 as usize;
+    let t90 = (0..=i50).rev().find_map(|i| fall(i, 0.9))?;
+    let t10 = (i50..n - 1).find_map(|i| fall(i, 0.1))?;
+    Some((contrast, t10 - t90))
+}
+
+fn median(mut values: Vec<f32>) -> f32 {
+    values.sort_by(f32::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+fn median_f64(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
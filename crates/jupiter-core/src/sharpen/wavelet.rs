@@ -1,9 +1,16 @@
-use ndarray::Array2;
+use ndarray::{Array2, Zip};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::consts::{B3_KERNEL, PARALLEL_PIXEL_THRESHOLD};
-use crate::frame::Frame;
+use crate::color::debayer::luminance;
+use crate::color::process::process_color_parallel;
+use crate::consts::{
+    B3_KERNEL, EPSILON, PARALLEL_PIXEL_THRESHOLD, WAVELET_BILATERAL_RADIUS,
+    WAVELET_BILATERAL_SPATIAL_SIGMA, WAVELET_EDGE_HIGH, WAVELET_EDGE_LOW, WAVELET_EDGE_SIGMA,
+    WAVELET_GAUSSIAN_SIGMA,
+};
+use crate::filters::gaussian_blur::gaussian_blur_array;
+use crate::frame::{ColorFrame, Frame};
 
 /// Parameters for wavelet sharpening.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -16,6 +23,24 @@ pub struct WaveletParams {
     /// Small coefficients in each detail layer below threshold are zeroed out.
     #[serde(default)]
     pub denoise: Vec<f32>,
+    /// How the `denoise` thresholds are applied to each layer.
+    #[serde(default)]
+    pub denoise_mode: LayerDenoise,
+    /// Smoothing kernel of the decomposition.
+    #[serde(default)]
+    pub kernel: WaveletKernel,
+    /// Spacing of the layer scales.
+    #[serde(default)]
+    pub scaling: WaveletScaling,
+    /// Strength (0–1) with which the coefficients are pulled back to 1.0 on
+    /// the strongest edges, such as the limb, so that boosts don't leave a
+    /// bright rim. 0.0 = off.
+    #[serde(default)]
+    pub edge_protection: f32,
+    /// For colour images, sharpen the luminance and add the same detail to
+    /// every channel instead of sharpening the channels separately.
+    #[serde(default)]
+    pub linked_luminance: bool,
 }
 
 impl Default for WaveletParams {
@@ -24,6 +49,80 @@ impl Default for WaveletParams {
             num_layers: 6,
             coefficients: vec![1.5, 1.3, 1.2, 1.1, 1.0, 1.0],
             denoise: vec![],
+            denoise_mode: LayerDenoise::default(),
+            kernel: WaveletKernel::default(),
+            scaling: WaveletScaling::default(),
+            edge_protection: 0.0,
+            linked_luminance: false,
+        }
+    }
+}
+
+/// Smoothing kernel used between decomposition layers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WaveletKernel {
+    /// 5-tap B3 spline, dilated with holes (à trous).
+    #[default]
+    B3Spline,
+    /// Gaussian of the same width; smoother, with less ringing.
+    Gaussian,
+}
+
+impl std::fmt::Display for WaveletKernel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WaveletKernel::B3Spline => write!(f, "B3 spline"),
+            WaveletKernel::Gaussian => write!(f, "Gaussian"),
+        }
+    }
+}
+
+/// Scale of each decomposition layer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WaveletScaling {
+    /// Scales 1, 2, 4, 8, ... pixels.
+    #[default]
+    Dyadic,
+    /// Scales 1, 1 + step, 1 + 2·step, ... pixels, for finer control over
+    /// the small scales.
+    Linear { step: usize },
+}
+
+impl WaveletScaling {
+    /// Scale in pixels of layer `layer` (0-based).
+    pub fn scale(&self, layer: usize) -> usize {
+        match self {
+            WaveletScaling::Dyadic => 1 << layer,
+            WaveletScaling::Linear { step } => 1 + layer * (*step).max(1),
+        }
+    }
+}
+
+impl std::fmt::Display for WaveletScaling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WaveletScaling::Dyadic => write!(f, "Dyadic"),
+            WaveletScaling::Linear { step } => write!(f, "Linear (step {step})"),
+        }
+    }
+}
+
+/// How a layer's denoise threshold is applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LayerDenoise {
+    /// Shrink every coefficient towards zero by the threshold.
+    #[default]
+    SoftThreshold,
+    /// Average each coefficient with neighbours of similar value (range
+    /// sigma = threshold), which smooths noise but keeps edges.
+    Bilateral,
+}
+
+impl std::fmt::Display for LayerDenoise {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayerDenoise::SoftThreshold => write!(f, "Soft threshold"),
+            LayerDenoise::Bilateral => write!(f, "Bilateral"),
         }
     }
 }
@@ -33,11 +132,32 @@ impl Default for WaveletParams {
 /// Returns (detail_layers, residual) where the original can be reconstructed as:
 /// original = sum(detail_layers) + residual
 pub fn decompose(data: &Array2<f32>, num_layers: usize) -> (Vec<Array2<f32>>, Array2<f32>) {
+    decompose_with(
+        data,
+        num_layers,
+        WaveletKernel::B3Spline,
+        WaveletScaling::Dyadic,
+    )
+}
+
+/// [`decompose`] with a choice of kernel and layer scales.
+pub fn decompose_with(
+    data: &Array2<f32>,
+    num_layers: usize,
+    kernel: WaveletKernel,
+    scaling: WaveletScaling,
+) -> (Vec<Array2<f32>>, Array2<f32>) {
     let mut layers = Vec::with_capacity(num_layers);
     let mut current = data.clone();
 
-    for scale in 0..num_layers {
-        let smoothed = atrous_convolve(&current, scale);
+    for layer in 0..num_layers {
+        let scale = scaling.scale(layer);
+        let smoothed = match kernel {
+            WaveletKernel::B3Spline => atrous_convolve(&current, scale),
+            WaveletKernel::Gaussian => {
+                gaussian_blur_array(&current, WAVELET_GAUSSIAN_SIGMA * scale as f32)
+            }
+        };
         let detail = &current - &smoothed;
         layers.push(detail);
         current = smoothed;
@@ -60,16 +180,7 @@ pub fn reconstruct(
         let threshold = denoise.get(i).copied().unwrap_or(0.0);
 
         if threshold > 0.0 {
-            // Soft-thresholding: sign(w) * max(0, |w| - threshold)
-            let denoised = layer.mapv(|w| {
-                let abs_w = w.abs();
-                if abs_w <= threshold {
-                    0.0
-                } else {
-                    w.signum() * (abs_w - threshold)
-                }
-            });
-            result += &(denoised * coeff);
+            result += &(soft_threshold(layer, threshold) * coeff);
         } else {
             result += &(layer * coeff);
         }
@@ -82,18 +193,143 @@ pub fn reconstruct(
 
 /// Sharpen a frame using a trous wavelet decomposition.
 pub fn sharpen(frame: &Frame, params: &WaveletParams) -> Frame {
-    let (layers, residual) = decompose(&frame.data, params.num_layers);
-    let sharpened = reconstruct(&layers, &residual, &params.coefficients, &params.denoise);
+    let mut sharpened = sharpen_unclamped(&frame.data, params);
+    sharpened.mapv_inplace(|v| v.clamp(0.0, 1.0));
     Frame::new(sharpened, frame.original_bit_depth)
 }
 
+/// Sharpen a colour frame: per channel, or through its luminance when
+/// `params.linked_luminance` is set.
+pub fn sharpen_color(color: &ColorFrame, params: &WaveletParams) -> ColorFrame {
+    if !params.linked_luminance {
+        return process_color_parallel(color, |frame| sharpen(frame, params));
+    }
+    let lum = luminance(color);
+    let detail = sharpen_unclamped(&lum.data, params) - &lum.data;
+    process_color_parallel(color, |frame| {
+        let mut data = &frame.data + &detail;
+        data.mapv_inplace(|v| v.clamp(0.0, 1.0));
+        Frame::new(data, frame.original_bit_depth)
+    })
+}
+
+fn sharpen_unclamped(data: &Array2<f32>, params: &WaveletParams) -> Array2<f32> {
+    let (layers, mut result) =
+        decompose_with(data, params.num_layers, params.kernel, params.scaling);
+    let protection = (params.edge_protection > 0.0)
+        .then(|| edge_protection_mask(data, params.edge_protection.min(1.0)));
+
+    for (i, layer) in layers.iter().enumerate() {
+        let coeff = params.coefficients.get(i).copied().unwrap_or(1.0);
+        let threshold = params.denoise.get(i).copied().unwrap_or(0.0);
+        let denoised;
+        let layer = if threshold > 0.0 {
+            denoised = match params.denoise_mode {
+                LayerDenoise::SoftThreshold => soft_threshold(layer, threshold),
+                LayerDenoise::Bilateral => {
+                    bilateral_layer(layer, threshold, params.scaling.scale(i))
+                }
+            };
+            &denoised
+        } else {
+            layer
+        };
+
+        match &protection {
+            Some(mask) => Zip::from(&mut result)
+                .and(layer)
+                .and(mask)
+                .for_each(|r, &w, &m| *r += w * (coeff + (1.0 - coeff) * m)),
+            None => result.scaled_add(coeff, layer),
+        }
+    }
+    result
+}
+
+/// Soft-thresholding: sign(w) * max(0, |w| - threshold)
+pub(crate) fn soft_threshold(layer: &Array2<f32>, threshold: f32) -> Array2<f32> {
+    layer.mapv(|w| {
+        let abs_w = w.abs();
+        if abs_w <= threshold {
+            0.0
+        } else {
+            w.signum() * (abs_w - threshold)
+        }
+    })
+}
+
+/// Bilateral smoothing of a detail layer, with taps spaced `scale` apart and
+/// `threshold` as the range sigma.
+fn bilateral_layer(layer: &Array2<f32>, threshold: f32, scale: usize) -> Array2<f32> {
+    let (h, w) = layer.dim();
+    let radius = WAVELET_BILATERAL_RADIUS;
+    let spatial = 2.0 * WAVELET_BILATERAL_SPATIAL_SIGMA * WAVELET_BILATERAL_SPATIAL_SIGMA;
+    let range = 2.0 * threshold * threshold;
+    let step = scale as isize;
+
+    let filter_row = |row: usize| -> Vec<f32> {
+        (0..w)
+            .map(|col| {
+                let center = layer[[row, col]];
+                let (mut sum, mut weight_sum) = (0.0f32, 0.0f32);
+                for dy in -radius..=radius {
+                    let r = mirror_index(row as isize + dy * step, h);
+                    for dx in -radius..=radius {
+                        let c = mirror_index(col as isize + dx * step, w);
+                        let v = layer[[r, c]];
+                        let weight = (-((dy * dy + dx * dx) as f32) / spatial
+                            - (v - center).powi(2) / range)
+                            .exp();
+                        sum += weight * v;
+                        weight_sum += weight;
+                    }
+                }
+                sum / weight_sum
+            })
+            .collect()
+    };
+    let values: Vec<f32> = if h * w >= PARALLEL_PIXEL_THRESHOLD {
+        (0..h).into_par_iter().flat_map(filter_row).collect()
+    } else {
+        (0..h).flat_map(filter_row).collect()
+    };
+    Array2::from_shape_vec((h, w), values).expect("bilateral output matches layer shape")
+}
+
+/// Edge protection weight of each pixel: `strength` on the strongest edges
+/// of the image (the limb), fading to 0 on ordinary detail.
+fn edge_protection_mask(data: &Array2<f32>, strength: f32) -> Array2<f32> {
+    let smooth = gaussian_blur_array(data, WAVELET_EDGE_SIGMA);
+    let (h, w) = smooth.dim();
+    let gradient = Array2::from_shape_fn((h, w), |(r, c)| {
+        let at = |dr: isize, dc: isize| {
+            smooth[[
+                mirror_index(r as isize + dr, h),
+                mirror_index(c as isize + dc, w),
+            ]]
+        };
+        let gx = (at(0, 1) - at(0, -1)) / 2.0;
+        let gy = (at(1, 0) - at(-1, 0)) / 2.0;
+        gx.hypot(gy)
+    });
+    let max = gradient.iter().copied().fold(0.0f32, f32::max);
+    if max <= EPSILON {
+        return Array2::zeros((h, w));
+    }
+    let mask = gradient.mapv(|g| {
+        strength
+            * ((g / max - WAVELET_EDGE_LOW) / (WAVELET_EDGE_HIGH - WAVELET_EDGE_LOW))
+                .clamp(0.0, 1.0)
+    });
+    // Widen the mask over the whole rim, where the fine layers overshoot.
+    gaussian_blur_array(&mask, WAVELET_EDGE_SIGMA)
+}
+
 /// A trous convolution at a given scale.
 ///
 /// The B3 spline kernel is applied separably (rows then columns)
-/// with a dilation factor of 2^scale (reading input at intervals of 2^scale).
-fn atrous_convolve(data: &Array2<f32>, scale: usize) -> Array2<f32> {
-    let step = 1usize << scale; // 2^scale
-
+/// with a dilation factor of `step` (reading input at intervals of `step`).
+fn atrous_convolve(data: &Array2<f32>, step: usize) -> Array2<f32> {
     // Convolve rows first
     let row_convolved = convolve_rows(data, &B3_KERNEL, step);
     // Then convolve columns
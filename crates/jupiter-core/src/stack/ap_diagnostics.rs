@@ -0,0 +1,314 @@
+//! Per-AP diagnostics for multi-point and surface-warp stacking.
+//!
+//! When a multi-point stack comes out soft or blotchy the cause is usually
+//! local: APs on featureless areas whose local alignment is rejected for most
+//! frames, or APs whose shifts point in inconsistent directions. These
+//! diagnostics record, for every AP, how many frames were selected and
+//! actually used, the mean local shift (on top of the global offset) and the
+//! mean AP quality, plus every AP/frame pair dropped by the confidence check.
+//! They can be rendered as a quiver plot of the shifts and a heatmap of frame
+//! usage, or saved next to the stack.
+
+use std::path::Path;
+
+use ndarray::Array2;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{JupiterError, Result};
+use crate::frame::{AlignmentOffset, ColorFrame, Frame};
+use crate::io::image_io::save_color_png;
+use crate::stack::ap_grid::ApGrid;
+
+/// File names written by [`ApDiagnostics::save`].
+pub const DIAGNOSTICS_JSON: &str = "ap_diagnostics.json";
+pub const SHIFT_QUIVER_PNG: &str = "ap_shifts.png";
+pub const USAGE_HEATMAP_PNG: &str = "ap_usage.png";
+
+/// Statistics of one AP.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ApStats {
+    /// Frames selected for this AP.
+    pub selected_frames: usize,
+    /// Selected frames whose local alignment passed the confidence check.
+    pub used_frames: usize,
+    /// Mean local shift over the used frames, relative to the global offset.
+    pub mean_shift: AlignmentOffset,
+    /// Mean quality score of the AP region over the selected frames.
+    pub quality: f64,
+}
+
+impl ApStats {
+    /// Fraction of the selected frames that were used (0 when none were).
+    pub fn usage(&self) -> f64 {
+        if self.selected_frames == 0 {
+            0.0
+        } else {
+            self.used_frames as f64 / self.selected_frames as f64
+        }
+    }
+}
+
+/// Everything recorded about the APs of one stack.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApDiagnostics {
+    pub width: usize,
+    pub height: usize,
+    pub grid: ApGrid,
+    /// Per-AP statistics, indexed by AP index.
+    pub aps: Vec<ApStats>,
+    /// `(ap_index, frame_index)` pairs whose local alignment was rejected.
+    pub rejected: Vec<(usize, usize)>,
+}
+
+impl ApDiagnostics {
+    /// Largest mean local shift of any AP, in pixels.
+    pub fn max_shift(&self) -> f64 {
+        self.aps
+            .iter()
+            .map(|s| s.mean_shift.dx.hypot(s.mean_shift.dy))
+            .fold(0.0, f64::max)
+    }
+
+    /// Quiver plot of the mean local shifts over `background` (or black).
+    ///
+    /// Arrows start at the AP centres and are scaled so the longest one spans
+    /// half the nominal AP size; their colour goes from green (all selected
+    /// frames used) to red (none used). AP centres with no shift get a dot.
+    pub fn shift_quiver(&self, background: Option<&Array2<f32>>) -> ColorFrame {
+        let mut canvas = Canvas::new(self.height, self.width, background);
+        let max_shift = self.max_shift();
+        let scale = if max_shift > 0.0 {
+            (self.grid.ap_size as f64 / 2.0) / max_shift
+        } else {
+            0.0
+        };
+
+        for ap in &self.grid.points {
+            let stats = &self.aps[ap.index];
+            let color = usage_color(stats.usage());
+            let (y0, x0) = (ap.cy as f64, ap.cx as f64);
+            let (y1, x1) = (
+                y0 + stats.mean_shift.dy * scale,
+                x0 + stats.mean_shift.dx * scale,
+            );
+            canvas.dot(y0, x0, color);
+            if (y1 - y0).hypot(x1 - x0) >= 1.0 {
+                canvas.arrow(y0, x0, y1, x1, color);
+            }
+        }
+        canvas.into_color_frame()
+    }
+
+    /// Heatmap of the fraction of selected frames each AP used, blended
+    /// across overlapping APs with the same Hann window used for stacking.
+    /// Pixels no AP covers are black.
+    pub fn usage_heatmap(&self) -> ColorFrame {
+        let (h, w) = (self.height, self.width);
+        let mut weighted = Array2::<f64>::zeros((h, w));
+        let mut weights = Array2::<f64>::zeros((h, w));
+        for ap in &self.grid.points {
+            let usage = self.aps[ap.index].usage();
+            let half = ap.size / 2;
+            for dr in 0..ap.size {
+                for dc in 0..ap.size {
+                    let (Some(r), Some(c)) = (
+                        (ap.cy + dr).checked_sub(half),
+                        (ap.cx + dc).checked_sub(half),
+                    ) else {
+                        continue;
+                    };
+                    if r >= h || c >= w {
+                        continue;
+                    }
+                    let wt = hann(dr, ap.size) * hann(dc, ap.size);
+                    weighted[[r, c]] += usage * wt;
+                    weights[[r, c]] += wt;
+                }
+            }
+        }
+
+        let mut canvas = Canvas::new(h, w, None);
+        for r in 0..h {
+            for c in 0..w {
+                if weights[[r, c]] > 1e-9 {
+                    canvas.set(r, c, heat_color(weighted[[r, c]] / weights[[r, c]]));
+                }
+            }
+        }
+        canvas.into_color_frame()
+    }
+
+    /// Write [`DIAGNOSTICS_JSON`], [`SHIFT_QUIVER_PNG`] (over `background`)
+    /// and [`USAGE_HEATMAP_PNG`] into `dir`, creating it if needed.
+    pub fn save(&self, dir: &Path, background: Option<&Array2<f32>>) -> Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| JupiterError::Pipeline(format!("AP diagnostics: {e}")))?;
+        std::fs::write(dir.join(DIAGNOSTICS_JSON), json)?;
+        save_color_png(&self.shift_quiver(background), &dir.join(SHIFT_QUIVER_PNG))?;
+        save_color_png(&self.usage_heatmap(), &dir.join(USAGE_HEATMAP_PNG))?;
+        Ok(())
+    }
+}
+
+/// Running statistics of one AP while stacking.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ApTally {
+    selected: usize,
+    quality_sum: f64,
+    used: usize,
+    shift_sum: (f64, f64),
+    rejected: Vec<usize>,
+}
+
+impl ApTally {
+    /// Tally with the AP's selected frames `(frame_index, score)`.
+    pub(crate) fn selected(selection: &[(usize, f64)]) -> Self {
+        let mut tally = Self::default();
+        for &(_, score) in selection {
+            tally.select(score);
+        }
+        tally
+    }
+
+    pub(crate) fn select(&mut self, score: f64) {
+        self.selected += 1;
+        self.quality_sum += score;
+    }
+
+    /// A frame whose local alignment passed, with its local offset.
+    pub(crate) fn accept(&mut self, local: &AlignmentOffset) {
+        self.used += 1;
+        self.shift_sum.0 += local.dy;
+        self.shift_sum.1 += local.dx;
+    }
+
+    /// A frame whose local alignment was rejected.
+    pub(crate) fn reject(&mut self, frame_index: usize) {
+        self.rejected.push(frame_index);
+    }
+}
+
+impl ApDiagnostics {
+    /// Collect the tallies of `grid`'s APs (indexed by AP index).
+    pub(crate) fn from_tallies(grid: &ApGrid, dims: (usize, usize), tallies: Vec<ApTally>) -> Self {
+        let (height, width) = dims;
+        let mut rejected = Vec::new();
+        let aps = tallies
+            .into_iter()
+            .enumerate()
+            .map(|(ap_index, t)| {
+                rejected.extend(t.rejected.iter().map(|&f| (ap_index, f)));
+                let used = t.used.max(1) as f64;
+                ApStats {
+                    selected_frames: t.selected,
+                    used_frames: t.used,
+                    mean_shift: AlignmentOffset {
+                        dy: t.shift_sum.0 / used,
+                        dx: t.shift_sum.1 / used,
+                    },
+                    quality: t.quality_sum / t.selected.max(1) as f64,
+                }
+            })
+            .collect();
+        rejected.sort_unstable();
+        Self {
+            width,
+            height,
+            grid: grid.clone(),
+            aps,
+            rejected,
+        }
+    }
+}
+
+type Rgb = [f32; 3];
+
+/// Green at full usage through yellow to red at none.
+fn usage_color(usage: f64) -> Rgb {
+    let u = usage.clamp(0.0, 1.0) as f32;
+    [(2.0 * (1.0 - u)).min(1.0), (2.0 * u).min(1.0), 0.0]
+}
+
+/// "Hot" colour map: black, red, yellow, white.
+fn heat_color(v: f64) -> Rgb {
+    let v = v.clamp(0.0, 1.0) as f32 * 3.0;
+    [
+        v.min(1.0),
+        (v - 1.0).clamp(0.0, 1.0),
+        (v - 2.0).clamp(0.0, 1.0),
+    ]
+}
+
+/// Raised cosine weight of `pos` in a window of `size`.
+fn hann(pos: usize, size: usize) -> f64 {
+    0.5 * (1.0 - (std::f64::consts::TAU * pos as f64 / size.max(1) as f64).cos())
+}
+
+/// Minimal RGB raster for the diagnostic plots.
+struct Canvas {
+    planes: [Array2<f32>; 3],
+}
+
+impl Canvas {
+    /// Blank canvas, or `background` dimmed to half brightness in grey.
+    fn new(h: usize, w: usize, background: Option<&Array2<f32>>) -> Self {
+        let base = match background {
+            Some(bg) if bg.dim() == (h, w) => bg.mapv(|v| 0.5 * v.clamp(0.0, 1.0)),
+            _ => Array2::zeros((h, w)),
+        };
+        Self {
+            planes: [base.clone(), base.clone(), base],
+        }
+    }
+
+    fn set(&mut self, r: usize, c: usize, color: Rgb) {
+        for (plane, v) in self.planes.iter_mut().zip(color) {
+            plane[[r, c]] = v;
+        }
+    }
+
+    fn plot(&mut self, y: f64, x: f64, color: Rgb) {
+        let (h, w) = self.planes[0].dim();
+        let (r, c) = (y.round(), x.round());
+        if r >= 0.0 && c >= 0.0 && (r as usize) < h && (c as usize) < w {
+            self.set(r as usize, c as usize, color);
+        }
+    }
+
+    fn line(&mut self, y0: f64, x0: f64, y1: f64, x1: f64, color: Rgb) {
+        let steps = (y1 - y0).abs().max((x1 - x0).abs()).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            self.plot(y0 + t * (y1 - y0), x0 + t * (x1 - x0), color);
+        }
+    }
+
+    fn dot(&mut self, y: f64, x: f64, color: Rgb) {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                self.plot(y + dy as f64, x + dx as f64, color);
+            }
+        }
+    }
+
+    /// Line with a two-stroke head at `(y1, x1)`.
+    fn arrow(&mut self, y0: f64, x0: f64, y1: f64, x1: f64, color: Rgb) {
+        self.line(y0, x0, y1, x1, color);
+        let angle = (y1 - y0).atan2(x1 - x0);
+        let head = ((y1 - y0).hypot(x1 - x0) * 0.3).clamp(2.0, 6.0);
+        for side in [-0.5f64, 0.5] {
+            let a = angle + std::f64::consts::PI + side;
+            self.line(y1, x1, y1 + head * a.sin(), x1 + head * a.cos(), color);
+        }
+    }
+
+    fn into_color_frame(self) -> ColorFrame {
+        let [red, green, blue] = self.planes;
+        ColorFrame {
+            red: Frame::new(red, 8),
+            green: Frame::new(green, 8),
+            blue: Frame::new(blue, 8),
+        }
+    }
+}
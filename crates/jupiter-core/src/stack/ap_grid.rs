@@ -2,9 +2,14 @@ use ndarray::Array2;
 use serde::{Deserialize, Serialize};
 
 use crate::align::phase_correlation::bilinear_sample;
-use crate::consts::{AUTO_AP_DIVISOR, AUTO_AP_SIZE_ALIGN, AUTO_AP_SIZE_MAX, AUTO_AP_SIZE_MIN};
+use crate::consts::{
+    AUTO_AP_DIVISOR, AUTO_AP_SIZE_ALIGN, AUTO_AP_SIZE_MAX, AUTO_AP_SIZE_MIN, DEFAULT_AP_MIN_ENERGY,
+    DEFAULT_AP_SMALL_SIZE,
+};
 use crate::frame::AlignmentOffset;
 use crate::pipeline::config::QualityMetric;
+use crate::stack::ap_placement;
+use crate::stack::rejection::Rejection;
 
 /// Local stacking method for per-AP patches.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -16,6 +21,115 @@ pub enum LocalStackMethod {
         sigma: f32,
         iterations: usize,
     },
+    WinsorizedSigmaClip {
+        sigma: f32,
+        iterations: usize,
+    },
+    LinearFitClip {
+        sigma: f32,
+        iterations: usize,
+    },
+    Gesd {
+        max_outliers: f32,
+        alpha: f32,
+    },
+}
+
+impl LocalStackMethod {
+    /// The per-pixel rejection applied to each AP's patches (`None` keeps
+    /// every patch; Median is handled separately).
+    pub fn rejection(&self) -> Rejection {
+        match *self {
+            Self::Mean | Self::Median => Rejection::None,
+            Self::SigmaClip { sigma, iterations } => Rejection::SigmaClip { sigma, iterations },
+            Self::WinsorizedSigmaClip { sigma, iterations } => {
+                Rejection::Winsorized { sigma, iterations }
+            }
+            Self::LinearFitClip { sigma, iterations } => Rejection::LinearFit { sigma, iterations },
+            Self::Gesd {
+                max_outliers,
+                alpha,
+            } => Rejection::Gesd {
+                max_outliers,
+                alpha,
+            },
+        }
+    }
+
+    /// Whether every patch must be held in memory to stack an AP (median
+    /// and the order-statistic rejections), rather than accumulated.
+    ///
+    /// Multi-point then caches every frame any AP selected, so its memory
+    /// grows with the frame count; the pipeline refuses these methods in
+    /// low-memory mode.
+    pub fn needs_all_patches(&self) -> bool {
+        !matches!(self, Self::Mean | Self::SigmaClip { .. })
+    }
+}
+
+/// How alignment points are laid out over the reference frame.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub enum ApPlacement {
+    /// Regular grid with 50% overlap, skipping dark regions.
+    #[default]
+    Grid,
+    /// APs where the reference has the most gradient energy (festoons,
+    /// belts), none on featureless areas.
+    Structure {
+        /// Minimum gradient energy as a fraction of the strongest candidate.
+        min_energy: f32,
+    },
+    /// Regular grid plus a ring of APs centred on the fitted limb.
+    Limb,
+    /// User-supplied AP centres and sizes.
+    Custom { points: Vec<ApSpec> },
+    /// Large APs of `ap_size` on the regular grid plus small,
+    /// structure-placed APs where there is detail to track.
+    MultiScale {
+        /// Size of the small APs in pixels.
+        small_size: usize,
+        /// Minimum gradient energy of a small AP (see `Structure`).
+        min_energy: f32,
+    },
+}
+
+impl ApPlacement {
+    /// Structure-driven placement with the default energy threshold.
+    pub fn structure() -> Self {
+        Self::Structure {
+            min_energy: DEFAULT_AP_MIN_ENERGY,
+        }
+    }
+
+    /// Multi-scale placement with the default small AP size and threshold.
+    pub fn multi_scale() -> Self {
+        Self::MultiScale {
+            small_size: DEFAULT_AP_SMALL_SIZE,
+            min_energy: DEFAULT_AP_MIN_ENERGY,
+        }
+    }
+}
+
+impl std::fmt::Display for ApPlacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Grid => write!(f, "Grid"),
+            Self::Structure { min_energy } => write!(f, "Structure (energy={min_energy:.2})"),
+            Self::Limb => write!(f, "Limb"),
+            Self::Custom { points } => write!(f, "Custom ({} APs)", points.len()),
+            Self::MultiScale { small_size, .. } => write!(f, "Multi-scale (small={small_size})"),
+        }
+    }
+}
+
+/// One user-supplied alignment point.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ApSpec {
+    pub cy: usize,
+    pub cx: usize,
+    /// AP size in pixels; `None` uses the configured `ap_size`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
 }
 
 /// Configuration for multi-alignment-point stacking.
@@ -34,6 +148,13 @@ pub struct MultiPointConfig {
     /// Local stacking method for each AP.
     #[serde(default)]
     pub local_stack_method: LocalStackMethod,
+    /// How APs are placed over the reference.
+    #[serde(default)]
+    pub placement: ApPlacement,
+    /// Explicit frames to stack (e.g. from a frame report) instead of the
+    /// best `select_percentage`; every listed frame is used at every AP.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames: Option<Vec<usize>>,
 }
 
 impl Default for MultiPointConfig {
@@ -45,6 +166,8 @@ impl Default for MultiPointConfig {
             min_brightness: 0.05,
             quality_metric: QualityMetric::Laplacian,
             local_stack_method: LocalStackMethod::Mean,
+            placement: ApPlacement::Grid,
+            frames: None,
         }
     }
 }
@@ -67,7 +190,7 @@ pub fn auto_ap_size_from_frame(width: usize, height: usize) -> usize {
 }
 
 /// A single alignment point on the grid.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AlignmentPoint {
     /// Center row in the image.
     pub cy: usize,
@@ -75,12 +198,15 @@ pub struct AlignmentPoint {
     pub cx: usize,
     /// Index in the AP list.
     pub index: usize,
+    /// Side length in pixels (even).
+    pub size: usize,
 }
 
 /// The grid of alignment points.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ApGrid {
     pub points: Vec<AlignmentPoint>,
+    /// Nominal AP size; individual points may differ with non-grid placement.
     pub ap_size: usize,
 }
 
@@ -123,16 +249,70 @@ pub fn extract_region_shifted(
     region
 }
 
-/// Build the AP grid over the reference frame.
-/// APs are placed with stride = ap_size/2 (50% overlap).
-/// APs with mean brightness below `min_brightness` are skipped.
+/// Build the AP layout over the reference frame with `config.placement`.
 pub fn build_ap_grid(reference: &Array2<f32>, config: &MultiPointConfig) -> ApGrid {
+    let centres = match &config.placement {
+        ApPlacement::Grid => regular_grid(reference, config.ap_size, config.min_brightness),
+        ApPlacement::Structure { min_energy } => ap_placement::structure_points(
+            reference,
+            config.ap_size,
+            config.min_brightness,
+            *min_energy,
+        ),
+        ApPlacement::Limb => {
+            let mut points = regular_grid(reference, config.ap_size, config.min_brightness);
+            points.extend(ap_placement::limb_points(
+                reference,
+                config.ap_size,
+                &points,
+            ));
+            points
+        }
+        ApPlacement::Custom { points } => {
+            ap_placement::custom_points(reference.dim(), points, config.ap_size)
+        }
+        ApPlacement::MultiScale {
+            small_size,
+            min_energy,
+        } => {
+            let mut points = regular_grid(reference, config.ap_size, config.min_brightness);
+            points.extend(ap_placement::structure_points(
+                reference,
+                (*small_size).max(2) & !1,
+                config.min_brightness,
+                *min_energy,
+            ));
+            points
+        }
+    };
+
+    ApGrid {
+        points: centres
+            .into_iter()
+            .enumerate()
+            .map(|(index, (cy, cx, size))| AlignmentPoint {
+                cy,
+                cx,
+                index,
+                size,
+            })
+            .collect(),
+        ap_size: config.ap_size,
+    }
+}
+
+/// AP centres `(cy, cx, size)` with stride = ap_size/2 (50% overlap).
+/// APs with mean brightness below `min_brightness` are skipped.
+fn regular_grid(
+    reference: &Array2<f32>,
+    ap_size: usize,
+    min_brightness: f32,
+) -> Vec<(usize, usize, usize)> {
     let (h, w) = reference.dim();
-    let half = config.ap_size / 2;
-    let stride = half; // 50% overlap
+    let half = ap_size / 2;
+    let stride = half.max(1); // 50% overlap
 
     let mut points = Vec::new();
-    let mut index = 0;
 
     // Place APs starting from half (center of first AP)
     let mut cy = half;
@@ -143,9 +323,8 @@ pub fn build_ap_grid(reference: &Array2<f32>, config: &MultiPointConfig) -> ApGr
             let region = extract_region(reference, cy, cx, half);
             let mean_brightness = region.mean().unwrap_or(0.0);
 
-            if mean_brightness >= config.min_brightness {
-                points.push(AlignmentPoint { cy, cx, index });
-                index += 1;
+            if mean_brightness >= min_brightness {
+                points.push((cy, cx, half * 2));
             }
 
             cx += stride;
@@ -153,8 +332,5 @@ pub fn build_ap_grid(reference: &Array2<f32>, config: &MultiPointConfig) -> ApGr
         cy += stride;
     }
 
-    ApGrid {
-        points,
-        ap_size: config.ap_size,
-    }
+    points
 }
@@ -3,17 +3,21 @@ use std::collections::HashMap;
 use ndarray::Array2;
 
 use crate::align::phase_correlation::{bilinear_sample, compute_offset_with_confidence};
-use crate::consts::{EPSILON, MIN_CORRELATION_CONFIDENCE};
+use crate::consts::{AP_BLEND_MIN_COVERAGE, MIN_CORRELATION_CONFIDENCE};
 use crate::frame::{AlignmentOffset, ColorFrame, Frame};
+use crate::stack::ap_diagnostics::ApTally;
 use crate::stack::ap_grid::{
     extract_region, extract_region_shifted, AlignmentPoint, LocalStackMethod, MultiPointConfig,
 };
+use crate::stack::median::weighted_median;
+use crate::stack::rejection::reject_patches;
 
 /// Stack one AP using pre-cached frames (for parallel execution).
 ///
 /// Includes correlation confidence check: frames whose local alignment
 /// peak-to-mean ratio is below [`MIN_CORRELATION_CONFIDENCE`] are skipped.
-/// When using Mean stacking, quality weights from `selected_frames` are applied.
+/// Every local method is weighted by the quality scores in `selected_frames`.
+/// Returns the stacked patch and the AP's tally for diagnostics.
 pub(crate) fn stack_ap_cached(
     frame_cache: &HashMap<usize, Frame>,
     ap: &AlignmentPoint,
@@ -21,11 +25,12 @@ pub(crate) fn stack_ap_cached(
     global_offsets: &[AlignmentOffset],
     reference_data: &Array2<f32>,
     config: &MultiPointConfig,
-) -> Array2<f32> {
-    let half = config.ap_size / 2;
+) -> (Array2<f32>, ApTally) {
+    let half = ap.size / 2;
     let search_half = half + config.search_radius;
 
     let ref_search = extract_region(reference_data, ap.cy, ap.cx, search_half);
+    let mut tally = ApTally::selected(selected_frames);
 
     let mut patches: Vec<Array2<f32>> = Vec::with_capacity(selected_frames.len());
     let mut weights: Vec<f64> = Vec::with_capacity(selected_frames.len());
@@ -49,8 +54,10 @@ pub(crate) fn stack_ap_cached(
             .unwrap_or((AlignmentOffset::default(), 0.0));
 
         if confidence < MIN_CORRELATION_CONFIDENCE {
+            tally.reject(frame_idx);
             continue; // skip unreliable alignment
         }
+        tally.accept(&local_offset);
 
         let search_size = search_half * 2;
         let center = search_size as f64 / 2.0;
@@ -72,23 +79,22 @@ pub(crate) fn stack_ap_cached(
 
     if patches.is_empty() {
         // All frames rejected by confidence check — fall back to reference region
-        return extract_region(reference_data, ap.cy, ap.cx, half);
+        return (extract_region(reference_data, ap.cy, ap.cx, half), tally);
     }
 
-    match config.local_stack_method {
-        LocalStackMethod::Mean => mean_stack_arrays_weighted(&patches, &weights),
-        LocalStackMethod::Median => median_stack_arrays(&patches),
-        LocalStackMethod::SigmaClip { sigma, iterations } => {
-            sigma_clip_stack_arrays(&patches, sigma, iterations)
-        }
-    }
+    let stacked = stack_patches(&patches, &weights, &config.local_stack_method);
+    (stacked, tally)
 }
 
+/// Stacked R, G and B patches of one AP.
+type RgbPatches = (Array2<f32>, Array2<f32>, Array2<f32>);
+
 /// Stack one AP using pre-cached color frames.
 ///
 /// Local alignment is computed on luminance with confidence check.
 /// R/G/B patches are extracted and stacked independently using the
-/// configured local method (quality-weighted for Mean).
+/// configured quality-weighted local method. Returns the stacked
+/// channels and the AP's tally for diagnostics.
 pub(crate) fn stack_ap_cached_color(
     frame_cache: &HashMap<usize, (Frame, ColorFrame)>,
     ap: &AlignmentPoint,
@@ -96,11 +102,12 @@ pub(crate) fn stack_ap_cached_color(
     global_offsets: &[AlignmentOffset],
     reference_lum: &Array2<f32>,
     config: &MultiPointConfig,
-) -> (Array2<f32>, Array2<f32>, Array2<f32>) {
-    let half = config.ap_size / 2;
+) -> (RgbPatches, ApTally) {
+    let half = ap.size / 2;
     let search_half = half + config.search_radius;
 
     let ref_search = extract_region(reference_lum, ap.cy, ap.cx, search_half);
+    let mut tally = ApTally::selected(selected_frames);
 
     let mut red_patches: Vec<Array2<f32>> = Vec::with_capacity(selected_frames.len());
     let mut green_patches: Vec<Array2<f32>> = Vec::with_capacity(selected_frames.len());
@@ -126,8 +133,10 @@ pub(crate) fn stack_ap_cached_color(
             .unwrap_or((AlignmentOffset::default(), 0.0));
 
         if confidence < MIN_CORRELATION_CONFIDENCE {
+            tally.reject(frame_idx);
             continue; // skip unreliable alignment
         }
+        tally.accept(&local_offset);
 
         let patch_half = half;
         let patch_size = patch_half * 2;
@@ -159,17 +168,11 @@ pub(crate) fn stack_ap_cached_color(
     if red_patches.is_empty() {
         // All frames rejected — fall back to reference region (luminance as proxy)
         let fallback = extract_region(reference_lum, ap.cy, ap.cx, half);
-        return (fallback.clone(), fallback.clone(), fallback);
+        return ((fallback.clone(), fallback.clone(), fallback), tally);
     }
 
     let stack_fn = |patches: &[Array2<f32>], wts: &[f64]| -> Array2<f32> {
-        match config.local_stack_method {
-            LocalStackMethod::Mean => mean_stack_arrays_weighted(patches, wts),
-            LocalStackMethod::Median => median_stack_arrays(patches),
-            LocalStackMethod::SigmaClip { sigma, iterations } => {
-                sigma_clip_stack_arrays(patches, sigma, iterations)
-            }
-        }
+        stack_patches(patches, wts, &config.local_stack_method)
     };
 
     // Stack R/G/B in parallel
@@ -183,7 +186,7 @@ pub(crate) fn stack_ap_cached_color(
         },
     );
 
-    (stacked_r, stacked_g, stacked_b)
+    ((stacked_r, stacked_g, stacked_b), tally)
 }
 
 /// Bilinear sample from an Array2<f32> (uses f64 coordinates, returns 0 for out-of-bounds).
@@ -250,127 +253,50 @@ pub(crate) fn mean_stack_arrays_weighted(patches: &[Array2<f32>], weights: &[f64
     weighted_sum.mapv(|v| (v / total_weight) as f32)
 }
 
-/// Median-stack a set of Array2 patches.
-pub(crate) fn median_stack_arrays(patches: &[Array2<f32>]) -> Array2<f32> {
-    let (h, w) = patches[0].dim();
-    let n = patches.len();
-    let mut result = Array2::<f32>::zeros((h, w));
-    let mut vals = vec![0.0f32; n];
-
-    for row in 0..h {
-        for col in 0..w {
-            for (i, p) in patches.iter().enumerate() {
-                vals[i] = p[[row, col]];
-            }
-            result[[row, col]] = if n == 1 {
-                vals[0]
-            } else if n % 2 == 1 {
-                let mid = n / 2;
-                *vals.select_nth_unstable_by(mid, |a, b| a.total_cmp(b)).1
-            } else {
-                let mid = n / 2;
-                let (_, upper, _) = vals.select_nth_unstable_by(mid, |a, b| a.total_cmp(b));
-                let upper_val = *upper;
-                let lower_val = vals[..mid]
-                    .iter()
-                    .copied()
-                    .max_by(|a, b| a.total_cmp(b))
-                    .unwrap();
-                (lower_val + upper_val) / 2.0
-            };
-        }
-    }
-
-    result
+/// Quality-weighted median of a set of Array2 patches.
+fn weighted_median_arrays(patches: &[Array2<f32>], weights: &[f64]) -> Array2<f32> {
+    let mut pairs = Vec::with_capacity(patches.len());
+    Array2::from_shape_fn(patches[0].dim(), |p| {
+        pairs.clear();
+        pairs.extend(
+            patches
+                .iter()
+                .zip(weights)
+                .map(|(patch, &wt)| (patch[p], wt.max(0.0) as f32)),
+        );
+        weighted_median(&mut pairs)
+    })
 }
 
-/// Sigma-clip mean stack of Array2 patches.
-pub(crate) fn sigma_clip_stack_arrays(
+/// Stack one AP's patches with the configured local method, weighting each
+/// patch by its frame's quality score.
+fn stack_patches(
     patches: &[Array2<f32>],
-    sigma: f32,
-    iterations: usize,
+    weights: &[f64],
+    method: &LocalStackMethod,
 ) -> Array2<f32> {
-    let (h, w) = patches[0].dim();
-    let n = patches.len();
-    let mut result = Array2::<f32>::zeros((h, w));
-    let mut vals = vec![0.0f32; n];
-    let mut mask = vec![true; n];
-
-    for row in 0..h {
-        for col in 0..w {
-            for (i, p) in patches.iter().enumerate() {
-                vals[i] = p[[row, col]];
-                mask[i] = true;
-            }
-
-            for _ in 0..iterations {
-                let (mean, stddev) = masked_mean_stddev(&vals, &mask);
-                if stddev < EPSILON {
-                    break;
-                }
-                let lo = mean - sigma * stddev;
-                let hi = mean + sigma * stddev;
-                for i in 0..n {
-                    if mask[i] && (vals[i] < lo || vals[i] > hi) {
-                        mask[i] = false;
-                    }
-                }
-            }
-
-            let mut sum = 0.0f32;
-            let mut count = 0u32;
-            for i in 0..n {
-                if mask[i] {
-                    sum += vals[i];
-                    count += 1;
-                }
-            }
-            result[[row, col]] = if count > 0 {
-                sum / count as f32
-            } else {
-                vals.iter().sum::<f32>() / n as f32
-            };
-        }
+    match method {
+        LocalStackMethod::Mean => mean_stack_arrays_weighted(patches, weights),
+        LocalStackMethod::Median => weighted_median_arrays(patches, weights),
+        _ => reject_patches(patches, weights, &method.rejection()),
     }
-
-    result
-}
-
-fn masked_mean_stddev(values: &[f32], mask: &[bool]) -> (f32, f32) {
-    let mut sum = 0.0f32;
-    let mut count = 0u32;
-    for (i, &v) in values.iter().enumerate() {
-        if mask[i] {
-            sum += v;
-            count += 1;
-        }
-    }
-    if count == 0 {
-        return (0.0, 0.0);
-    }
-    let mean = sum / count as f32;
-    let mut var_sum = 0.0f32;
-    for (i, &v) in values.iter().enumerate() {
-        if mask[i] {
-            let d = v - mean;
-            var_sum += d * d;
-        }
-    }
-    let stddev = (var_sum / count as f32).sqrt();
-    (mean, stddev)
 }
 
 /// Blend per-AP stacked patches using raised-cosine (Hann) weighting.
-/// With 50% overlap, cosine weights form a partition of unity.
+///
+/// Each patch is weighted by a Hann window of its own size, so APs of
+/// different sizes and irregular layouts blend smoothly; on a regular grid
+/// with 50% overlap the weights form a partition of unity. Pixels whose total
+/// weight falls below [`AP_BLEND_MIN_COVERAGE`] (image borders, gaps between
+/// structure-placed APs, skipped dark regions) are topped up from
+/// `background`, which also sets the output size.
 pub fn blend_ap_stacks(
     stacks: &[(AlignmentPoint, Array2<f32>)],
-    h: usize,
-    w: usize,
-    ap_size: usize,
+    background: &Array2<f32>,
 ) -> Array2<f32> {
+    let (h, w) = background.dim();
     let mut weighted_sum = Array2::<f64>::zeros((h, w));
     let mut weight_sum = Array2::<f64>::zeros((h, w));
-    let half = ap_size / 2;
 
     for (ap, patch) in stacks {
         let patch_size = patch.dim().0;
@@ -397,8 +323,8 @@ pub fn blend_ap_stacks(
                     continue;
                 }
 
-                let wy = hann_weight(dr, half);
-                let wx = hann_weight(dc, half);
+                let wy = hann_weight(dr, patch_half);
+                let wx = hann_weight(dc, patch_half);
                 let weight = (wy * wx) as f64;
 
                 weighted_sum[[img_r, img_c]] += patch[[dr, dc]] as f64 * weight;
@@ -407,18 +333,16 @@ pub fn blend_ap_stacks(
         }
     }
 
-    let mut result = Array2::<f32>::zeros((h, w));
-    for row in 0..h {
-        for col in 0..w {
-            result[[row, col]] = if weight_sum[[row, col]] > 1e-12 {
-                (weighted_sum[[row, col]] / weight_sum[[row, col]]) as f32
-            } else {
-                0.0
-            };
+    Array2::from_shape_fn((h, w), |(row, col)| {
+        let covered = weight_sum[[row, col]];
+        let fill = (AP_BLEND_MIN_COVERAGE - covered).max(0.0);
+        let total = covered + fill;
+        if total > 1e-12 {
+            ((weighted_sum[[row, col]] + fill * background[[row, col]] as f64) / total) as f32
+        } else {
+            background[[row, col]]
         }
-    }
-
-    result
+    })
 }
 
 /// Raised cosine (Hann) weight for position `pos` within a window of `half_size`.
@@ -0,0 +1,233 @@
+//! Alignment-point placement strategies beyond the regular grid.
+//!
+//! A regular grid spends APs on featureless sky and disk interior while
+//! leaving the limb and small features to whatever cell happens to cover
+//! them. Structure placement puts APs where the reference has the most
+//! gradient energy; limb placement adds a ring of APs on the fitted disk
+//! edge; custom placement takes centres (and sizes) from the user.
+//!
+//! All strategies return `(cy, cx, size)` with the AP fully inside the frame.
+
+use std::path::Path;
+
+use ndarray::Array2;
+use tracing::warn;
+
+use crate::align::limb::fit_limb;
+use crate::consts::AP_MIN_SIZE;
+use crate::error::{JupiterError, Result};
+use crate::pipeline::config::LimbFitConfig;
+use crate::stack::ap_grid::ApSpec;
+
+/// APs of `size` centred on the strongest structure in `reference`.
+///
+/// Candidates on a `size / 4` lattice are scored by mean gradient magnitude;
+/// those below `min_energy` times the strongest candidate, or darker than
+/// `min_brightness`, are dropped. The rest are picked strongest first, with
+/// no two centres closer than half an AP in either axis, so overlap never
+/// exceeds that of the regular grid.
+pub fn structure_points(
+    reference: &Array2<f32>,
+    size: usize,
+    min_brightness: f32,
+    min_energy: f32,
+) -> Vec<(usize, usize, usize)> {
+    let (h, w) = reference.dim();
+    let half = size / 2;
+    if half == 0 || size > h || size > w {
+        return Vec::new();
+    }
+
+    let energy = SummedArea::new(&gradient_magnitude(reference));
+    let brightness = SummedArea::new(reference);
+
+    let stride = (size / 4).max(1);
+    let mut candidates: Vec<(usize, usize, f64)> = Vec::new();
+    let mut cy = half;
+    while cy + half <= h {
+        let mut cx = half;
+        while cx + half <= w {
+            let (r0, c0) = (cy - half, cx - half);
+            if brightness.mean(r0, c0, size) >= min_brightness as f64 {
+                candidates.push((cy, cx, energy.mean(r0, c0, size)));
+            }
+            cx += stride;
+        }
+        cy += stride;
+    }
+
+    let strongest = candidates.iter().map(|c| c.2).fold(0.0f64, f64::max);
+    if strongest <= 0.0 {
+        return Vec::new();
+    }
+    let cutoff = min_energy.max(0.0) as f64 * strongest;
+    candidates.retain(|c| c.2 >= cutoff);
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    let mut picked: Vec<(usize, usize, usize)> = Vec::new();
+    for (cy, cx, _) in candidates {
+        let crowded = picked
+            .iter()
+            .any(|&(py, px, _)| py.abs_diff(cy) < half && px.abs_diff(cx) < half);
+        if !crowded {
+            picked.push((cy, cx, size));
+        }
+    }
+    picked
+}
+
+/// APs of `size` spaced half an AP apart along the limb of the disk in
+/// `reference`, skipping any that would sit on top of an AP in `existing`.
+///
+/// Returns nothing if no limb can be fitted.
+pub fn limb_points(
+    reference: &Array2<f32>,
+    size: usize,
+    existing: &[(usize, usize, usize)],
+) -> Vec<(usize, usize, usize)> {
+    let circle = match fit_limb(reference, &LimbFitConfig::default()) {
+        Ok(c) => c,
+        Err(e) => {
+            warn!("Limb AP placement skipped: {e}");
+            return Vec::new();
+        }
+    };
+
+    let (h, w) = reference.dim();
+    let half = size / 2;
+    let spacing = half.max(1) as f64;
+    let steps = ((std::f64::consts::TAU * circle.radius / spacing).ceil() as usize).max(1);
+    // Closer than this to another AP and the limb AP adds nothing; grid APs
+    // further off the limb than this are not centred on it.
+    let min_gap = (half / 4).max(1);
+
+    let mut points: Vec<(usize, usize, usize)> = Vec::new();
+    for k in 0..steps {
+        let theta = std::f64::consts::TAU * k as f64 / steps as f64;
+        let y = (circle.center_y + circle.radius * theta.sin()).round();
+        let x = (circle.center_x + circle.radius * theta.cos()).round();
+        if y < half as f64 || x < half as f64 {
+            continue;
+        }
+        let (cy, cx) = (y as usize, x as usize);
+        if cy + half > h || cx + half > w {
+            continue;
+        }
+        let taken = existing
+            .iter()
+            .chain(points.iter())
+            .any(|&(py, px, _)| py.abs_diff(cy) < min_gap && px.abs_diff(cx) < min_gap);
+        if !taken {
+            points.push((cy, cx, size));
+        }
+    }
+    points
+}
+
+/// User-supplied APs, sized `default_size` unless given, rounded down to an
+/// even size of at least [`AP_MIN_SIZE`]. APs that do not fit in a frame of
+/// `dims` are dropped with a warning.
+pub fn custom_points(
+    dims: (usize, usize),
+    specs: &[ApSpec],
+    default_size: usize,
+) -> Vec<(usize, usize, usize)> {
+    let (h, w) = dims;
+    specs
+        .iter()
+        .filter_map(|spec| {
+            let size = spec.size.unwrap_or(default_size).max(AP_MIN_SIZE) & !1;
+            let half = size / 2;
+            let fits =
+                spec.cy >= half && spec.cx >= half && spec.cy + half <= h && spec.cx + half <= w;
+            if !fits {
+                warn!(
+                    "Dropping AP at ({}, {}): a {size} px AP does not fit in {w}x{h}",
+                    spec.cy, spec.cx
+                );
+            }
+            fits.then_some((spec.cy, spec.cx, size))
+        })
+        .collect()
+}
+
+/// Read an AP list: one `y,x` or `y,x,size` per line, `#` starts a comment.
+pub fn load_ap_list(path: &Path) -> Result<Vec<ApSpec>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut specs = Vec::new();
+    for (lineno, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let bad = || {
+            JupiterError::Pipeline(format!(
+                "{}:{}: expected `y,x` or `y,x,size`, got `{line}`",
+                path.display(),
+                lineno + 1
+            ))
+        };
+        let fields: Vec<usize> = line
+            .split(',')
+            .map(|f| f.trim().parse::<usize>().map_err(|_| bad()))
+            .collect::<Result<_>>()?;
+        let spec = match fields[..] {
+            [cy, cx] => ApSpec { cy, cx, size: None },
+            [cy, cx, size] => ApSpec {
+                cy,
+                cx,
+                size: Some(size),
+            },
+            _ => return Err(bad()),
+        };
+        specs.push(spec);
+    }
+    if specs.is_empty() {
+        return Err(JupiterError::Pipeline(format!(
+            "AP list {} contains no points",
+            path.display()
+        )));
+    }
+    Ok(specs)
+}
+
+/// Central-difference gradient magnitude (zero on the border).
+fn gradient_magnitude(data: &Array2<f32>) -> Array2<f32> {
+    let (h, w) = data.dim();
+    let mut out = Array2::<f32>::zeros((h, w));
+    for r in 1..h.saturating_sub(1) {
+        for c in 1..w.saturating_sub(1) {
+            let gy = data[[r + 1, c]] - data[[r - 1, c]];
+            let gx = data[[r, c + 1]] - data[[r, c - 1]];
+            out[[r, c]] = 0.5 * gx.hypot(gy);
+        }
+    }
+    out
+}
+
+/// Summed-area table for O(1) window means.
+struct SummedArea {
+    table: Array2<f64>,
+}
+
+impl SummedArea {
+    fn new(data: &Array2<f32>) -> Self {
+        let (h, w) = data.dim();
+        let mut table = Array2::<f64>::zeros((h + 1, w + 1));
+        for r in 0..h {
+            let mut row_sum = 0.0;
+            for c in 0..w {
+                row_sum += data[[r, c]] as f64;
+                table[[r + 1, c + 1]] = table[[r, c + 1]] + row_sum;
+            }
+        }
+        Self { table }
+    }
+
+    /// Mean of the `size` x `size` window with top-left corner `(r0, c0)`.
+    fn mean(&self, r0: usize, c0: usize, size: usize) -> f64 {
+        let (r1, c1) = (r0 + size, c0 + size);
+        let t = &self.table;
+        (t[[r1, c1]] - t[[r0, c1]] - t[[r1, c0]] + t[[r0, c0]]) / (size * size) as f64
+    }
+}
@@ -0,0 +1,395 @@
+//! Frame-major multi-point stacking with memory bounded by the AP grid.
+//!
+//! Each selected frame is read once per pass, locally aligned at every AP
+//! that selected it and added to that AP's running sums, so memory holds the
+//! per-AP accumulators and one frame rather than every selected frame. Mean
+//! stacking takes a single pass; sigma clipping takes one more pass per
+//! clipping iteration (both quality-weighted), re-deriving the (deterministic) local offsets instead
+//! of storing them.
+
+use std::collections::BTreeMap;
+
+use ndarray::{Array2, Zip};
+use rayon::prelude::*;
+
+use crate::align::phase_correlation::{bilinear_sample, compute_offset_with_confidence};
+use crate::consts::{EPSILON, MIN_CORRELATION_CONFIDENCE};
+use crate::error::Result;
+use crate::frame::AlignmentOffset;
+use crate::stack::ap_diagnostics::ApTally;
+use crate::stack::ap_grid::{
+    extract_region, extract_region_shifted, AlignmentPoint, ApGrid, LocalStackMethod,
+    MultiPointConfig,
+};
+
+/// Floor on quality weights, so an AP whose frames all score zero still gets
+/// a plain mean instead of nothing.
+const MIN_WEIGHT: f64 = 1e-12;
+
+/// One frame as seen by the streaming stacker.
+pub(crate) struct StreamedFrame {
+    /// Data used for local alignment.
+    pub luminance: Array2<f32>,
+    /// R/G/B planes for colour sources; `None` stacks the luminance itself.
+    pub color: Option<[Array2<f32>; 3]>,
+}
+
+impl StreamedFrame {
+    fn channels(&self) -> Vec<&Array2<f32>> {
+        match &self.color {
+            Some(planes) => planes.iter().collect(),
+            None => vec![&self.luminance],
+        }
+    }
+}
+
+/// Running sums of one AP, per channel.
+struct ApSums {
+    /// Quality-weighted sum of the values added.
+    sum: Vec<Array2<f64>>,
+    /// Sigma clipping only: per-pixel total weight, and the unweighted sum,
+    /// sum of squares and count the clipping bounds are derived from.
+    weights: Vec<Array2<f64>>,
+    raw_sum: Vec<Array2<f64>>,
+    sum_sq: Vec<Array2<f64>>,
+    count: Vec<Array2<f64>>,
+    /// Total quality weight of the frames added (mean pass).
+    weight: f64,
+    frames: usize,
+}
+
+impl ApSums {
+    fn new(channels: usize, patch_size: usize, clipping: bool) -> Self {
+        let zeros = |n: usize| vec![Array2::<f64>::zeros((patch_size, patch_size)); n];
+        let clip = if clipping { channels } else { 0 };
+        Self {
+            sum: zeros(channels),
+            weights: zeros(clip),
+            raw_sum: zeros(clip),
+            sum_sq: zeros(clip),
+            count: zeros(clip),
+            weight: 0.0,
+            frames: 0,
+        }
+    }
+
+    fn add_weighted(&mut self, patches: &[Array2<f32>], weight: f64) {
+        for (sum, patch) in self.sum.iter_mut().zip(patches) {
+            Zip::from(sum)
+                .and(patch)
+                .for_each(|s, &v| *s += v as f64 * weight);
+        }
+        self.weight += weight;
+        self.frames += 1;
+    }
+
+    /// Add the values of `patches` that fall inside `bounds` (all of them
+    /// when `bounds` is `None`) with the frame's quality weight.
+    fn add_clipped(&mut self, patches: &[Array2<f32>], bounds: Option<&[Bounds]>, weight: f64) {
+        for (c, patch) in patches.iter().enumerate() {
+            for (p, &v) in patch.indexed_iter() {
+                if let Some(b) = bounds {
+                    if v < b[c].lo[p] || v > b[c].hi[p] {
+                        continue;
+                    }
+                }
+                let v = v as f64;
+                self.sum[c][p] += weight * v;
+                self.weights[c][p] += weight;
+                self.raw_sum[c][p] += v;
+                self.sum_sq[c][p] += v * v;
+                self.count[c][p] += 1.0;
+            }
+        }
+        self.frames += 1;
+    }
+
+    fn mean(&self) -> Vec<Array2<f32>> {
+        let weight = self.weight;
+        self.sum
+            .iter()
+            .map(|s| s.mapv(|v| (v / weight) as f32))
+            .collect()
+    }
+}
+
+/// Adds one frame's aligned patches (one per channel) to an AP's sums,
+/// given the AP index and the frame's quality score.
+/// Stacked patches, per AP then per channel.
+pub(crate) type ApStacks = Vec<Vec<Array2<f32>>>;
+
+type AddPatches<'a> = dyn Fn(&mut ApSums, usize, &[Array2<f32>], f64) + Sync + 'a;
+
+/// Per-pixel clipping bounds of one channel of one AP.
+#[derive(Clone)]
+struct Bounds {
+    lo: Array2<f32>,
+    hi: Array2<f32>,
+}
+
+/// Stack every AP frame-major. Returns the stacked patches of each AP, one
+/// per channel (three for colour sources, one otherwise), and the AP tallies
+/// for diagnostics.
+///
+/// `read(i)` supplies frame `i`; `progress` receives the fraction of frame
+/// reads done. Frames whose local alignment at an AP falls below
+/// [`MIN_CORRELATION_CONFIDENCE`] are skipped for that AP, and an AP no frame
+/// contributed to falls back to the reference region. Only Mean and
+/// SigmaClip are supported: median and the order-statistic rejections need
+/// every patch at once (see [`LocalStackMethod::needs_all_patches`]).
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn stream_ap_stacks<R>(
+    grid: &ApGrid,
+    selections: &[Vec<(usize, f64)>],
+    global_offsets: &[AlignmentOffset],
+    reference: &Array2<f32>,
+    config: &MultiPointConfig,
+    channels: usize,
+    read: R,
+    progress: &mut dyn FnMut(f32),
+) -> Result<(ApStacks, Vec<ApTally>)>
+where
+    R: Fn(usize) -> Result<StreamedFrame>,
+{
+    // APs may differ in size, so every window is sized per AP.
+    let search_half = |ap: &AlignmentPoint| ap.size / 2 + config.search_radius;
+    let ref_search: Vec<Array2<f32>> = grid
+        .points
+        .iter()
+        .map(|ap| extract_region(reference, ap.cy, ap.cx, search_half(ap)))
+        .collect();
+
+    // Which APs use each frame, and with what quality weight.
+    let mut by_frame: BTreeMap<usize, Vec<(usize, f64)>> = BTreeMap::new();
+    for ap in &grid.points {
+        for &(frame_idx, score) in &selections[ap.index] {
+            by_frame
+                .entry(frame_idx)
+                .or_default()
+                .push((ap.index, score));
+        }
+    }
+
+    let (clip_sigma, clip_passes) = match config.local_stack_method {
+        LocalStackMethod::SigmaClip { sigma, iterations } => (Some(sigma), iterations),
+        _ => (None, 0),
+    };
+    let total_reads = (by_frame.len() * (clip_passes + 1)).max(1);
+    let mut reads_done = 0;
+
+    // One pass over the selected frames, adding each aligned patch with `add`.
+    // Returns what each AP's local alignment accepted and rejected.
+    let mut run_pass = |sums: &mut [ApSums], add: &AddPatches| -> Result<Vec<ApTally>> {
+        let mut tallies: Vec<ApTally> = selections.iter().map(|s| ApTally::selected(s)).collect();
+        let mut uses: Vec<Option<f64>> = vec![None; grid.points.len()];
+        for (&frame_idx, aps) in &by_frame {
+            let frame = read(frame_idx)?;
+            let planes = frame.channels();
+            for &(ap_index, score) in aps {
+                uses[ap_index] = Some(score);
+            }
+            let global = &global_offsets[frame_idx];
+            sums.par_iter_mut()
+                .zip(tallies.par_iter_mut())
+                .zip(grid.points.par_iter())
+                .for_each(|((acc, tally), ap)| {
+                    let Some(score) = uses[ap.index] else {
+                        return;
+                    };
+                    let tgt_search = extract_region_shifted(
+                        &frame.luminance,
+                        ap.cy,
+                        ap.cx,
+                        search_half(ap),
+                        global,
+                    );
+                    let Some(local) = confident_offset(&ref_search[ap.index], &tgt_search) else {
+                        tally.reject(frame_idx);
+                        return;
+                    };
+                    tally.accept(&local);
+                    let combined = AlignmentOffset {
+                        dx: global.dx + local.dx,
+                        dy: global.dy + local.dy,
+                    };
+                    let patches: Vec<Array2<f32>> = planes
+                        .iter()
+                        .map(|plane| sample_patch(plane, ap, &combined))
+                        .collect();
+                    add(acc, ap.index, &patches, score);
+                });
+            for &(ap_index, _) in aps {
+                uses[ap_index] = None;
+            }
+            reads_done += 1;
+            progress(reads_done as f32 / total_reads as f32);
+        }
+        Ok(tallies)
+    };
+
+    let clipping = clip_sigma.is_some();
+    let new_sums = || -> Vec<ApSums> {
+        grid.points
+            .iter()
+            .map(|ap| ApSums::new(channels, ap.size, clipping))
+            .collect()
+    };
+
+    // Local alignment is deterministic, so every pass accepts the same
+    // frames; the tallies of the first pass are kept.
+    let tallies: Vec<ApTally>;
+    let stacked: Vec<Vec<Array2<f32>>> = match clip_sigma {
+        None => {
+            let mut sums = new_sums();
+            tallies = run_pass(&mut sums, &|acc, _, patches, score| {
+                acc.add_weighted(patches, score.max(MIN_WEIGHT))
+            })?;
+            sums.iter()
+                .map(|acc| {
+                    if acc.frames > 0 {
+                        acc.mean()
+                    } else {
+                        Vec::new()
+                    }
+                })
+                .collect()
+        }
+        Some(sigma) => {
+            // First pass: unclipped statistics, kept as the fallback for
+            // pixels where every value ends up clipped.
+            let mut sums = new_sums();
+            tallies = run_pass(&mut sums, &|acc, _, patches, score| {
+                acc.add_clipped(patches, None, score.max(MIN_WEIGHT))
+            })?;
+            let fallback: Vec<Vec<Array2<f32>>> = sums.iter().map(weighted_mean).collect();
+            let mut bounds: Vec<Vec<Bounds>> = sums
+                .iter()
+                .zip(&grid.points)
+                .map(|(_, ap)| {
+                    vec![
+                        Bounds {
+                            lo: Array2::from_elem((ap.size, ap.size), f32::NEG_INFINITY),
+                            hi: Array2::from_elem((ap.size, ap.size), f32::INFINITY),
+                        };
+                        channels
+                    ]
+                })
+                .collect();
+
+            for _ in 0..clip_passes {
+                if !tighten_bounds(&sums, &mut bounds, sigma) {
+                    break;
+                }
+                sums = new_sums();
+                run_pass(&mut sums, &|acc, ap_index, patches, score| {
+                    acc.add_clipped(patches, Some(&bounds[ap_index]), score.max(MIN_WEIGHT))
+                })?;
+            }
+
+            sums.iter()
+                .zip(fallback)
+                .map(|(acc, fallback)| {
+                    if acc.frames == 0 {
+                        return Vec::new();
+                    }
+                    acc.sum
+                        .iter()
+                        .zip(&acc.weights)
+                        .zip(fallback)
+                        .map(|((s, w), mut out)| {
+                            Zip::from(&mut out).and(s).and(w).for_each(|o, &s, &w| {
+                                if w > 0.0 {
+                                    *o = (s / w) as f32;
+                                }
+                            });
+                            out
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    };
+
+    // APs no frame contributed to fall back to the reference region.
+    let stacked = stacked
+        .into_iter()
+        .zip(&grid.points)
+        .map(|(patches, ap)| {
+            if patches.is_empty() {
+                vec![extract_region(reference, ap.cy, ap.cx, ap.size / 2); channels]
+            } else {
+                patches
+            }
+        })
+        .collect();
+    Ok((stacked, tallies))
+}
+
+/// Local offset of `target` onto `reference`, if the correlation is trusted.
+fn confident_offset(reference: &Array2<f32>, target: &Array2<f32>) -> Option<AlignmentOffset> {
+    let (offset, confidence) = compute_offset_with_confidence(reference, target).ok()?;
+    (confidence >= MIN_CORRELATION_CONFIDENCE).then_some(offset)
+}
+
+/// The AP-sized square patch at `ap`, sampled with `offset` applied.
+fn sample_patch(data: &Array2<f32>, ap: &AlignmentPoint, offset: &AlignmentOffset) -> Array2<f32> {
+    let half = ap.size / 2;
+    let size = half * 2;
+    Array2::from_shape_fn((size, size), |(dr, dc)| {
+        let src_y = (ap.cy as f64 + dr as f64 - half as f64) - offset.dy;
+        let src_x = (ap.cx as f64 + dc as f64 - half as f64) - offset.dx;
+        bilinear_sample(data, src_y, src_x)
+    })
+}
+
+/// Weighted mean of everything added, per channel.
+fn weighted_mean(acc: &ApSums) -> Vec<Array2<f32>> {
+    acc.sum
+        .iter()
+        .zip(&acc.weights)
+        .map(|(s, w)| {
+            Array2::from_shape_fn(s.dim(), |p| {
+                if w[p] > 0.0 {
+                    (s[p] / w[p]) as f32
+                } else {
+                    0.0
+                }
+            })
+        })
+        .collect()
+}
+
+/// Narrow every pixel's bounds to mean ± sigma·stddev of the last pass
+/// (unweighted, like [`rejection`](crate::stack::rejection)).
+/// Pixels whose spread has collapsed keep their bounds. Returns `false` when
+/// no pixel changed, so further passes would be identical.
+fn tighten_bounds(sums: &[ApSums], bounds: &mut [Vec<Bounds>], sigma: f32) -> bool {
+    let mut changed = false;
+    for (acc, ap_bounds) in sums.iter().zip(bounds.iter_mut()) {
+        for (c, b) in ap_bounds.iter_mut().enumerate() {
+            Zip::from(&mut b.lo)
+                .and(&mut b.hi)
+                .and(&acc.raw_sum[c])
+                .and(&acc.sum_sq[c])
+                .and(&acc.count[c])
+                .for_each(|lo, hi, &s, &sq, &n| {
+                    if n <= 0.0 {
+                        return;
+                    }
+                    let mean = s / n;
+                    let stddev = (sq / n - mean * mean).max(0.0).sqrt() as f32;
+                    if stddev < EPSILON {
+                        return;
+                    }
+                    let new_lo = mean as f32 - sigma * stddev;
+                    let new_hi = mean as f32 + sigma * stddev;
+                    if new_lo != *lo || new_hi != *hi {
+                        *lo = new_lo;
+                        *hi = new_hi;
+                        changed = true;
+                    }
+                });
+        }
+    }
+    changed
+}
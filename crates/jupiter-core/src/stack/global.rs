@@ -0,0 +1,255 @@
+//! Global alignment step shared by multi-point and surface-warp stacking.
+//!
+//! Every frame is scored with the configured quality metric and the best one
+//! becomes the reference; the others are registered against it with the
+//! configured alignment method. Offsets are then checked with the same
+//! peak-to-mean confidence test the local AP step uses, at a whole-frame
+//! threshold (or with the full outlier rejection when enabled), and frames
+//! that fail are left out of the mean reference, AP scoring and stacking.
+//!
+//! Reference refinement is not applied here: the mean reference built from
+//! the best frames already plays that role.
+//!
+//! With an explicit frame list only the listed frames are scored and
+//! aligned; the others count as rejected, and every listed frame that passes
+//! the checks is selected instead of the best `select_percentage`.
+//!
+//! The pipeline runs the scoring and [`align_candidates`] steps through its
+//! session cache, so a re-run with other stacking settings reuses both.
+
+use ndarray::Array2;
+use rayon::prelude::*;
+use tracing::info;
+
+use crate::align::{reject_outliers, ReferenceAligner};
+use crate::compute::ComputeBackend;
+use crate::error::{JupiterError, Result};
+use crate::frame::AlignmentOffset;
+use crate::pipeline::config::{AlignmentConfig, OutlierRejection, QualityMetric};
+use crate::pipeline::session::CachedAlignment;
+use crate::quality::score_with_metric;
+use crate::stack::reference::mean_of_shifted;
+
+/// Result of [`global_align`], indexed by frame number.
+#[derive(Clone, Debug)]
+pub struct GlobalAlignment {
+    /// Frame the others were registered against (the best-scoring one).
+    pub reference_idx: usize,
+    /// Correcting offset of every frame relative to the reference.
+    pub offsets: Vec<AlignmentOffset>,
+    /// Whole-frame quality score of every frame.
+    pub scores: Vec<f64>,
+    /// Frames whose registration was not trusted, or that were not listed.
+    pub rejected: Vec<bool>,
+    /// Whether only an explicit frame list was aligned.
+    pub listed: bool,
+}
+
+impl GlobalAlignment {
+    /// Assemble the result from per-frame `scores` and the checked offsets
+    /// of the aligned frames (see [`align_candidates`]), which may come from
+    /// a session cache. `listed` is whether the frames were an explicit list.
+    pub fn from_parts(scores: Vec<f64>, aligned: &CachedAlignment, listed: bool) -> Self {
+        // Scatter back to frame numbers; frames not aligned stay rejected.
+        let frame_count = scores.len();
+        let mut offsets = vec![AlignmentOffset::default(); frame_count];
+        let mut rejected = vec![true; frame_count];
+        for (&i, offset) in aligned.selected.iter().zip(&aligned.offsets) {
+            offsets[i] = offset.clone();
+            rejected[i] = aligned.rejected.contains(&i);
+        }
+        let reference_idx = aligned.selected[best_candidate(&aligned.selected, &scores)];
+        Self {
+            reference_idx,
+            offsets,
+            scores,
+            rejected,
+            listed,
+        }
+    }
+
+    /// Number of frames that passed the confidence check.
+    pub fn kept_count(&self) -> usize {
+        self.rejected.iter().filter(|&&r| !r).count()
+    }
+
+    /// The best `fraction` of the kept frames as `(frame_index, score)`,
+    /// best first. At least one frame is always returned.
+    pub fn best_frames(&self, fraction: f32) -> Vec<(usize, f64)> {
+        let mut ranked: Vec<(usize, f64)> = self
+            .scores
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !self.rejected[i])
+            .map(|(i, &s)| (i, s))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let kept = ranked.len();
+        let keep = ((kept as f32 * fraction).ceil() as usize).max(1).min(kept);
+        ranked.truncate(keep);
+        ranked
+    }
+
+    /// Fraction of the kept frames to stack: `select_percentage`, or all of
+    /// them when an explicit frame list was aligned.
+    pub fn selection_fraction(&self, select_percentage: f32) -> f32 {
+        if self.listed {
+            1.0
+        } else {
+            select_percentage
+        }
+    }
+
+    /// Mean of the best `keep_fraction` of the kept frames, each shifted by
+    /// its offset. `read(i)` yields the data of frame `i`.
+    pub fn mean_reference<R>(&self, keep_fraction: f32, read: R) -> Result<Array2<f32>>
+    where
+        R: Fn(usize) -> Result<Array2<f32>>,
+    {
+        let best = self.best_frames(keep_fraction);
+        let offsets: Vec<AlignmentOffset> =
+            best.iter().map(|&(i, _)| self.offsets[i].clone()).collect();
+        mean_of_shifted(&offsets, |k| read(best[k].0))
+    }
+}
+
+/// Register `frame_count` frames against the best-scoring one.
+///
+/// `read(i)` yields the (luminance) data of frame `i`. Frames are scored with
+/// `quality_metric`, aligned with `alignment.method` and checked with
+/// `alignment.rejection` when set, otherwise on confidence alone with the
+/// default threshold. The reference is never rejected. When `frames` is
+/// given, only those frames take part.
+pub fn global_align<R>(
+    frame_count: usize,
+    frames: Option<&[usize]>,
+    read: R,
+    quality_metric: &QualityMetric,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+) -> Result<GlobalAlignment>
+where
+    R: Fn(usize) -> Result<Array2<f32>> + Sync,
+{
+    let candidates = global_candidates(frame_count, frames)?;
+    let candidate_scores: Vec<f64> = candidates
+        .par_iter()
+        .map(|&i| Ok(score_with_metric(&read(i)?, quality_metric)))
+        .collect::<Result<_>>()?;
+    let mut scores = vec![0.0; frame_count];
+    for (&i, &score) in candidates.iter().zip(&candidate_scores) {
+        scores[i] = score;
+    }
+    let aligned = align_candidates(&candidates, &scores, read, alignment, backend)?;
+    Ok(GlobalAlignment::from_parts(
+        scores,
+        &aligned,
+        frames.is_some(),
+    ))
+}
+
+/// Frames taking part in a global alignment of `frame_count` frames: the
+/// listed ones, sorted and deduplicated, or all of them.
+pub fn global_candidates(frame_count: usize, frames: Option<&[usize]>) -> Result<Vec<usize>> {
+    if frame_count == 0 {
+        return Err(JupiterError::EmptySequence);
+    }
+    match frames {
+        Some(listed) => {
+            if let Some(&bad) = listed.iter().find(|&&i| i >= frame_count) {
+                return Err(JupiterError::FrameIndexOutOfRange {
+                    index: bad,
+                    total: frame_count,
+                });
+            }
+            let mut listed = listed.to_vec();
+            listed.sort_unstable();
+            listed.dedup();
+            if listed.is_empty() {
+                return Err(JupiterError::InvalidReport("no frames selected".into()));
+            }
+            Ok(listed)
+        }
+        None => Ok((0..frame_count).collect()),
+    }
+}
+
+/// Align `candidates` against the best-scoring one and check the offsets,
+/// as [`global_align`] does. `scores` holds the quality of every frame.
+pub fn align_candidates<R>(
+    candidates: &[usize],
+    scores: &[f64],
+    read: R,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+) -> Result<CachedAlignment>
+where
+    R: Fn(usize) -> Result<Array2<f32>> + Sync,
+{
+    let reference_k = best_candidate(candidates, scores);
+    let reference_idx = candidates[reference_k];
+
+    info!(
+        "Global alignment of {} frames against frame {} ({})",
+        candidates.len(),
+        reference_idx,
+        alignment.method
+    );
+    let reference = read(reference_idx)?;
+    let aligner = ReferenceAligner::new(&reference, alignment, backend)?;
+    let mut offsets: Vec<AlignmentOffset> = candidates
+        .par_iter()
+        .map(|&i| {
+            if i == reference_idx {
+                return Ok(AlignmentOffset::default());
+            }
+            aligner.offset(&read(i)?)
+        })
+        .collect::<Result<_>>()?;
+
+    let rejection = alignment.rejection.clone().unwrap_or(OutlierRejection {
+        max_drift: f64::INFINITY,
+        retry: false,
+        ..Default::default()
+    });
+    let check = reject_outliers(
+        &mut offsets,
+        |k| read(candidates[k]),
+        candidates,
+        reference_k,
+        &rejection,
+        &aligner,
+    )?;
+    if check.rejected_count() > 0 || check.recovered > 0 {
+        info!(
+            "Global alignment: rejected {} of {} frames, recovered {}",
+            check.rejected_count(),
+            candidates.len(),
+            check.recovered
+        );
+    }
+
+    Ok(CachedAlignment {
+        selected: candidates.to_vec(),
+        offsets,
+        rejected: candidates
+            .iter()
+            .zip(&check.rejected)
+            .filter(|(_, &r)| r)
+            .map(|(&i, _)| i)
+            .collect(),
+        confidences: check
+            .confidences
+            .into_iter()
+            .map(|c| Some(c).filter(|c| c.is_finite()))
+            .collect(),
+    })
+}
+
+/// Position in `candidates` of the best-scoring frame.
+fn best_candidate(candidates: &[usize], scores: &[f64]) -> usize {
+    (0..candidates.len())
+        .max_by(|&a, &b| scores[candidates[a]].total_cmp(&scores[candidates[b]]))
+        .expect("candidates are non-empty")
+}
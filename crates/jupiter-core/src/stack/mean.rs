@@ -2,6 +2,8 @@ use ndarray::Array2;
 
 use crate::error::{JupiterError, Result};
 use crate::frame::Frame;
+use crate::stack::rejection::frame_weights;
+use crate::stack::streaming::MultiPassStacker;
 
 /// Stack frames by computing the mean at each pixel.
 pub fn mean_stack(frames: &[Frame]) -> Result<Frame> {
@@ -31,6 +33,34 @@ pub fn mean_stack_with_progress(frames: &[Frame], on_progress: impl Fn(usize)) -
     Ok(Frame::new(sum, frames[0].original_bit_depth))
 }
 
+/// Stack frames by the quality-weighted mean at each pixel.
+///
+/// `weights[i]` is frame `i`'s quality score; negative scores count as zero,
+/// and all-zero scores fall back to a plain mean.
+pub fn weighted_mean_stack_with_progress(
+    frames: &[Frame],
+    weights: &[f64],
+    on_progress: impl Fn(usize),
+) -> Result<Frame> {
+    if frames.is_empty() {
+        return Err(JupiterError::EmptySequence);
+    }
+    let weights = frame_weights(frames.len(), Some(weights))?;
+    let total: f32 = weights.iter().sum();
+    if total <= 0.0 {
+        return mean_stack_with_progress(frames, on_progress);
+    }
+
+    let (h, w) = frames[0].data.dim();
+    let mut sum = Array2::<f32>::zeros((h, w));
+    for (i, (frame, &weight)) in frames.iter().zip(&weights).enumerate() {
+        sum.scaled_add(weight / total, &frame.data);
+        on_progress(i + 1);
+    }
+
+    Ok(Frame::new(sum, frames[0].original_bit_depth))
+}
+
 /// Streaming mean stacker that accumulates one frame at a time.
 ///
 /// Memory usage is O(h*w) regardless of frame count — only the running sum
@@ -38,6 +68,7 @@ pub fn mean_stack_with_progress(frames: &[Frame], on_progress: impl Fn(usize)) -
 pub struct StreamingMeanStacker {
     sum: Array2<f32>,
     count: usize,
+    weight: f32,
     bit_depth: u8,
 }
 
@@ -46,22 +77,51 @@ impl StreamingMeanStacker {
         Self {
             sum: Array2::zeros((height, width)),
             count: 0,
+            weight: 0.0,
             bit_depth,
         }
     }
 
     /// Add one frame to the running sum.
     pub fn add(&mut self, frame: &Frame) {
-        self.sum += &frame.data;
+        self.add_weighted(frame, 1.0);
+    }
+
+    /// Add one frame with a quality weight (negative weights count as zero).
+    pub fn add_weighted(&mut self, frame: &Frame, weight: f32) {
+        let weight = weight.max(0.0);
+        self.sum.scaled_add(weight, &frame.data);
+        self.weight += weight;
         self.count += 1;
     }
 
-    /// Produce the final mean-stacked frame.
+    /// Produce the final (weighted) mean-stacked frame.
     pub fn finalize(mut self) -> Result<Frame> {
         if self.count == 0 {
             return Err(JupiterError::EmptySequence);
         }
-        self.sum /= self.count as f32;
+        if self.weight <= 0.0 {
+            return Err(JupiterError::Pipeline(
+                "All streamed frames have zero quality weight".into(),
+            ));
+        }
+        self.sum /= self.weight;
         Ok(Frame::new(self.sum, self.bit_depth))
     }
 }
+
+impl MultiPassStacker for StreamingMeanStacker {
+    fn passes(&self) -> usize {
+        1
+    }
+
+    fn add_weighted(&mut self, frame: &Frame, weight: f32) {
+        StreamingMeanStacker::add_weighted(self, frame, weight);
+    }
+
+    fn end_pass(&mut self) {}
+
+    fn finalize(self) -> Result<Frame> {
+        StreamingMeanStacker::finalize(self)
+    }
+}
@@ -1,9 +1,13 @@
-use ndarray::Array2;
+use ndarray::{Array2, Array3, Axis, Zip};
 use rayon::prelude::*;
 
-use crate::consts::PARALLEL_PIXEL_THRESHOLD;
+use crate::consts::{
+    PARALLEL_PIXEL_THRESHOLD, STREAMING_MEDIAN_BINS, STREAMING_MEDIAN_REFINE_PASSES,
+};
 use crate::error::{JupiterError, Result};
 use crate::frame::Frame;
+use crate::stack::rejection::frame_weights;
+use crate::stack::streaming::MultiPassStacker;
 
 /// Stack frames by computing the median at each pixel position.
 ///
@@ -24,6 +28,72 @@ pub fn median_stack(frames: &[Frame]) -> Result<Frame> {
     }
 }
 
+/// Stack frames by the quality-weighted median at each pixel: the value
+/// where the cumulative weight of the sorted stack reaches half the total.
+///
+/// With equal weights this is the plain median. Negative scores count as
+/// zero, and all-zero scores fall back to [`median_stack`].
+pub fn weighted_median_stack(frames: &[Frame], weights: &[f64]) -> Result<Frame> {
+    if frames.is_empty() {
+        return Err(JupiterError::EmptySequence);
+    }
+    let weights = frame_weights(frames.len(), Some(weights))?;
+    if weights.iter().sum::<f32>() <= 0.0 {
+        return median_stack(frames);
+    }
+
+    let (h, w) = frames[0].data.dim();
+    let mut result = Array2::<f32>::zeros((h, w));
+    result
+        .axis_iter_mut(Axis(0))
+        .into_par_iter()
+        .enumerate()
+        .for_each_init(
+            || Vec::with_capacity(frames.len()),
+            |pairs, (row, mut out)| {
+                for (col, px) in out.iter_mut().enumerate() {
+                    pairs.clear();
+                    pairs.extend(
+                        frames
+                            .iter()
+                            .zip(&weights)
+                            .map(|(f, &wt)| (f.data[[row, col]], wt)),
+                    );
+                    *px = weighted_median(pairs);
+                }
+            },
+        );
+    Ok(Frame::new(result, frames[0].original_bit_depth))
+}
+
+/// Weighted median of `(value, weight)` pairs, which are sorted in place.
+///
+/// When the cumulative weight lands exactly on half the total, the two
+/// straddling values are averaged, matching the plain median for equal
+/// weights.
+pub(crate) fn weighted_median(pairs: &mut [(f32, f32)]) -> f32 {
+    pairs.sort_unstable_by(|a, b| a.0.total_cmp(&b.0));
+    let total: f32 = pairs.iter().map(|p| p.1).sum();
+    if total <= 0.0 {
+        let values: Vec<f32> = pairs.iter().map(|p| p.0).collect();
+        let mut values = values;
+        let n = values.len();
+        return compute_median(&mut values, n);
+    }
+    let half = total / 2.0;
+    let mut cumulative = 0.0;
+    for (i, &(value, weight)) in pairs.iter().enumerate() {
+        cumulative += weight;
+        if cumulative >= half {
+            if (cumulative - half).abs() <= half * 1e-6 && i + 1 < pairs.len() {
+                return (value + pairs[i + 1].0) / 2.0;
+            }
+            return value;
+        }
+    }
+    pairs[pairs.len() - 1].0
+}
+
 fn median_stack_parallel(frames: &[Frame], h: usize, w: usize, n: usize) -> Result<Frame> {
     // Row-parallel: each row allocates its own pixel_values
     let rows: Vec<Vec<f32>> = (0..h)
@@ -81,3 +151,181 @@ fn compute_median(pixel_values: &mut [f32], n: usize) -> f32 {
         (pixel_values[mid - 1] + pixel_values[mid]) / 2.0
     }
 }
+
+/// Per-pixel state of [`StreamingMedianStacker`]. During the first pass `lo`
+/// and `hi` hold the running mean and sum of squared deviations; afterwards
+/// they bracket the median, and `below` is the weight under the bracket.
+#[derive(Clone, Copy, Default)]
+struct Bracket {
+    lo: f32,
+    hi: f32,
+    below: f32,
+}
+
+/// Streaming approximation of the (weighted) median stack.
+///
+/// The first pass accumulates each pixel's weighted mean and variance
+/// (Welford). The median of any distribution lies within one standard
+/// deviation of its mean, so that interval brackets it; each further pass
+/// histograms the values inside the bracket into [`STREAMING_MEDIAN_BINS`]
+/// bins and narrows the bracket to the bin where the cumulative weight
+/// reaches half the total. The result interpolates within the last bin, so
+/// it is within `2σ / bins^refine_passes` of the median; where the cumulative weight
+/// lands exactly on half, it takes the lower value rather than averaging.
+///
+/// Memory is `4 * (STREAMING_MEDIAN_BINS + 3)` bytes per pixel regardless of
+/// frame count: 28 bytes, or about 450 MiB for a 4096x4096 frame, as much as
+/// seven frames held as `f32`. The cost is [`STREAMING_MEDIAN_REFINE_PASSES`]
+/// `+ 1` passes over the frames, so the eager median is cheaper for stacks of
+/// fewer than about seven frames.
+pub struct StreamingMedianStacker {
+    brackets: Array2<Bracket>,
+    /// Weight histogram per pixel, allocated after the first pass.
+    histogram: Array3<f32>,
+    pass: usize,
+    count: usize,
+    weight: f32,
+    bit_depth: u8,
+}
+
+impl StreamingMedianStacker {
+    pub fn new(height: usize, width: usize, bit_depth: u8) -> Self {
+        Self {
+            brackets: Array2::default((height, width)),
+            histogram: Array3::zeros((0, 0, 0)),
+            pass: 0,
+            count: 0,
+            weight: 0.0,
+            bit_depth,
+        }
+    }
+
+    /// Add one frame to the current pass with a quality weight (negative
+    /// weights count as zero).
+    pub fn add_weighted(&mut self, frame: &Frame, weight: f32) {
+        let weight = weight.max(0.0);
+        if self.pass == 0 {
+            self.count += 1;
+            if weight <= 0.0 {
+                return;
+            }
+            self.weight += weight;
+            let ratio = weight / self.weight;
+            Zip::from(&mut self.brackets)
+                .and(&frame.data)
+                .par_for_each(|b, &v| {
+                    let delta = v - b.lo;
+                    b.lo += delta * ratio;
+                    b.hi += weight * delta * (v - b.lo);
+                });
+            return;
+        }
+
+        let bins = STREAMING_MEDIAN_BINS as f32;
+        Zip::from(&mut self.brackets)
+            .and(self.histogram.lanes_mut(Axis(2)))
+            .and(&frame.data)
+            .par_for_each(|b, mut histogram, &v| {
+                if v < b.lo {
+                    b.below += weight;
+                } else if v <= b.hi {
+                    let span = b.hi - b.lo;
+                    let bin = if span > 0.0 {
+                        (((v - b.lo) / span * bins) as usize).min(STREAMING_MEDIAN_BINS - 1)
+                    } else {
+                        0
+                    };
+                    histogram[bin] += weight;
+                }
+            });
+    }
+
+    /// Close the current pass: derive the initial bracket after the first,
+    /// narrow it (or, after the last, place the estimate) after the others.
+    pub fn end_pass(&mut self) {
+        if self.pass == 0 {
+            let total = self.weight;
+            self.brackets.par_map_inplace(|b| {
+                let sigma = if total > 0.0 {
+                    (b.hi / total).max(0.0).sqrt()
+                } else {
+                    0.0
+                };
+                let mean = b.lo;
+                *b = Bracket {
+                    lo: mean - sigma,
+                    hi: mean + sigma,
+                    below: 0.0,
+                };
+            });
+            let (h, w) = self.brackets.dim();
+            self.histogram = Array3::zeros((h, w, STREAMING_MEDIAN_BINS));
+        } else {
+            let half = self.weight / 2.0;
+            let last = self.pass == STREAMING_MEDIAN_REFINE_PASSES;
+            Zip::from(&mut self.brackets)
+                .and(self.histogram.lanes_mut(Axis(2)))
+                .par_for_each(|b, mut histogram| {
+                    let width = (b.hi - b.lo) / STREAMING_MEDIAN_BINS as f32;
+                    let mut cumulative = b.below;
+                    let (mut bin, mut fraction) = (STREAMING_MEDIAN_BINS - 1, 1.0);
+                    for (k, &mass) in histogram.iter().enumerate() {
+                        if mass > 0.0 && cumulative + mass >= half {
+                            bin = k;
+                            fraction = ((half - cumulative) / mass).clamp(0.0, 1.0);
+                            break;
+                        }
+                        cumulative += mass;
+                    }
+                    let bin_lo = b.lo + bin as f32 * width;
+                    if last {
+                        b.lo = bin_lo + fraction * width;
+                    } else {
+                        b.lo = bin_lo;
+                        b.hi = bin_lo + width;
+                    }
+                    b.below = 0.0;
+                    histogram.fill(0.0);
+                });
+        }
+        self.pass += 1;
+    }
+
+    /// Produce the median estimate after the last pass.
+    pub fn finalize(self) -> Result<Frame> {
+        if self.count == 0 {
+            return Err(JupiterError::EmptySequence);
+        }
+        if self.weight <= 0.0 {
+            return Err(JupiterError::Pipeline(
+                "All streamed frames have zero quality weight".into(),
+            ));
+        }
+        if self.pass <= STREAMING_MEDIAN_REFINE_PASSES {
+            return Err(JupiterError::Pipeline(format!(
+                "Streaming median finalized after {} of {} passes",
+                self.pass,
+                STREAMING_MEDIAN_REFINE_PASSES + 1
+            )));
+        }
+        Ok(Frame::new(self.brackets.mapv(|b| b.lo), self.bit_depth))
+    }
+}
+
+impl MultiPassStacker for StreamingMedianStacker {
+    fn passes(&self) -> usize {
+        STREAMING_MEDIAN_REFINE_PASSES + 1
+    }
+
+    fn add_weighted(&mut self, frame: &Frame, weight: f32) {
+        StreamingMedianStacker::add_weighted(self, frame, weight);
+    }
+
+    fn end_pass(&mut self) {
+        StreamingMedianStacker::end_pass(self);
+    }
+
+    fn finalize(self) -> Result<Frame> {
+        StreamingMedianStacker::finalize(self)
+    }
+}
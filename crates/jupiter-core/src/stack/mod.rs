@@ -1,9 +1,18 @@
+pub mod ap_diagnostics;
 pub mod ap_grid;
 pub mod ap_local;
+pub mod ap_placement;
+mod ap_stream;
 pub mod drizzle;
+pub mod global;
 pub mod mean;
 pub mod median;
 pub mod multi_point;
+pub mod optical_flow;
 pub mod reference;
+pub mod rejection;
 pub mod sigma_clip;
+pub mod streaming;
+pub mod super_resolution;
 pub mod surface_warp;
+mod thin_plate;
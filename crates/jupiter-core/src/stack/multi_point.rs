@@ -4,84 +4,122 @@ use ndarray::Array2;
 use rayon::prelude::*;
 use tracing::info;
 
+use crate::align::phase_correlation::shift_array;
 use crate::color::debayer::{luminance, DebayerMethod};
 use crate::color::process::{read_color_frame, read_luminance_frame};
+use crate::compute::ComputeBackend;
 use crate::consts::MEAN_REFERENCE_KEEP_FRACTION;
 use crate::error::{JupiterError, Result};
-use crate::frame::{AlignmentOffset, ColorFrame, ColorMode, Frame};
+use crate::frame::{ColorFrame, ColorMode, Frame};
 use crate::io::ser::SerReader;
+use crate::pipeline::config::AlignmentConfig;
 use crate::quality::score_with_metric;
+use crate::stack::ap_diagnostics::{ApDiagnostics, ApTally};
 use crate::stack::ap_local::{stack_ap_cached, stack_ap_cached_color};
+use crate::stack::ap_stream::{stream_ap_stacks, StreamedFrame};
+use crate::stack::global::{global_align, GlobalAlignment};
 
 // Re-export public types so external code can continue to use `stack::multi_point::*`.
 pub use crate::stack::ap_grid::{
     auto_ap_size, auto_ap_size_from_frame, build_ap_grid, extract_region, extract_region_shifted,
-    AlignmentPoint, ApGrid, LocalStackMethod, MultiPointConfig,
+    AlignmentPoint, ApGrid, ApPlacement, ApSpec, LocalStackMethod, MultiPointConfig,
 };
 pub use crate::stack::ap_local::blend_ap_stacks;
 pub use crate::stack::reference::{build_mean_reference, build_mean_reference_color};
 
 /// Score all APs across all frames using frame-major loop (read each frame once).
+/// Frames rejected by the global alignment are skipped.
 /// Returns `quality_matrix[ap_index]` = Vec of (frame_index, score), sorted descending.
 pub fn score_all_aps(
     reader: &SerReader,
     grid: &ApGrid,
-    offsets: &[AlignmentOffset],
+    global: &GlobalAlignment,
     config: &MultiPointConfig,
 ) -> Result<Vec<Vec<(usize, f64)>>> {
-    let total_frames = reader.frame_count();
+    score_aps_with(grid, global, config, |i| Ok(reader.read_frame(i)?.data))
+}
+
+/// Frame-major AP scoring shared by the mono and color paths.
+fn score_aps_with<R>(
+    grid: &ApGrid,
+    global: &GlobalAlignment,
+    config: &MultiPointConfig,
+    read: R,
+) -> Result<Vec<Vec<(usize, f64)>>>
+where
+    R: Fn(usize) -> Result<Array2<f32>>,
+{
     let num_aps = grid.points.len();
-    let half = config.ap_size / 2;
 
-    // quality_matrix[ap][frame] = score
-    let mut quality_matrix: Vec<Vec<f64>> = vec![vec![0.0; total_frames]; num_aps];
+    // quality_matrix[ap] = (frame, score) for every kept frame
+    let mut quality_matrix: Vec<Vec<(usize, f64)>> = vec![Vec::new(); num_aps];
 
     // Frame-major: read each frame once, score all APs
-    for frame_idx in 0..total_frames {
-        let frame = reader.read_frame(frame_idx)?;
+    for (frame_idx, offset) in global.offsets.iter().enumerate() {
+        if global.rejected[frame_idx] {
+            continue;
+        }
+        let data = read(frame_idx)?;
 
         for ap in &grid.points {
-            let region =
-                extract_region_shifted(&frame.data, ap.cy, ap.cx, half, &offsets[frame_idx]);
+            let region = extract_region_shifted(&data, ap.cy, ap.cx, ap.size / 2, offset);
 
             let score = score_with_metric(&region, &config.quality_metric);
 
-            quality_matrix[ap.index][frame_idx] = score;
+            quality_matrix[ap.index].push((frame_idx, score));
         }
     }
 
     // For each AP, sort frames by score descending, return top N
-    let keep_count = ((total_frames as f32 * config.select_percentage).ceil() as usize)
-        .max(1)
-        .min(total_frames);
-
-    let mut result = Vec::with_capacity(num_aps);
-    for ap_scores in &quality_matrix {
-        let mut indexed: Vec<(usize, f64)> =
-            ap_scores.iter().enumerate().map(|(i, &s)| (i, s)).collect();
+    let kept = global.kept_count();
+    let fraction = global.selection_fraction(config.select_percentage);
+    let keep_count = ((kept as f32 * fraction).ceil() as usize).max(1).min(kept);
+
+    for indexed in &mut quality_matrix {
         indexed.sort_by(|a, b| b.1.total_cmp(&a.1));
         indexed.truncate(keep_count);
-        result.push(indexed);
     }
 
-    Ok(result)
+    Ok(quality_matrix)
 }
 
 /// Top-level orchestrator for multi-alignment-point stacking.
 ///
 /// Pipeline:
-/// 1. Global align all frames vs frame 0 (offsets only, no shifting)
+/// 1. Global align all frames vs the best-scoring frame with the configured
+///    method, rejecting low-confidence frames
 /// 2. Build mean reference from top-quality frames
 /// 3. Build AP grid on mean reference
 /// 4. Score quality per-AP per-frame (frame-major loop)
 /// 5. Select best frames per-AP
-/// 6. Local align + stack each AP (with confidence check + quality weighting)
-/// 7. Blend AP stacks with cosine weighting
+/// 6. Local align + stack each AP (with confidence check + quality weighting),
+///    frame-major so each selected frame is read once and memory is bounded
+///    by the per-AP accumulators (median caches the selected frames instead)
+/// 7. Blend AP stacks with cosine weighting, filling uncovered pixels from
+///    the mean reference
 pub fn multi_point_stack<F>(
     reader: &SerReader,
     config: &MultiPointConfig,
-    mut on_progress: F,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+    on_progress: F,
 ) -> Result<Frame>
+where
+    F: FnMut(f32),
+{
+    multi_point_stack_with_diagnostics(reader, config, alignment, backend, on_progress)
+        .map(|(frame, _)| frame)
+}
+
+/// Same as [`multi_point_stack`], additionally returning the AP grid with
+/// per-AP frame usage, shifts and quality.
+pub fn multi_point_stack_with_diagnostics<F>(
+    reader: &SerReader,
+    config: &MultiPointConfig,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+    on_progress: F,
+) -> Result<(Frame, ApDiagnostics)>
 where
     F: FnMut(f32),
 {
@@ -90,27 +128,33 @@ where
         return Err(JupiterError::EmptySequence);
     }
 
-    let reference = reader.read_frame(0)?;
-    let (h, w) = reference.data.dim();
-
-    // Step 1: Global alignment — compute offsets in parallel
-    info!(
-        "Computing global alignment offsets for {} frames",
-        total_frames
-    );
-    let rest_offsets: Vec<Result<AlignmentOffset>> = (1..total_frames)
-        .into_par_iter()
-        .map(|i| {
-            let frame = reader.read_frame(i)?;
-            crate::align::phase_correlation::compute_offset(&reference, &frame)
-        })
-        .collect();
+    // Step 1: Global alignment against the best frame
+    let read = |i: usize| Ok(reader.read_frame(i)?.data);
+    let global = global_align(
+        total_frames,
+        config.frames.as_deref(),
+        read,
+        &config.quality_metric,
+        alignment,
+        backend,
+    )?;
+    multi_point_stack_from_global(reader, config, global, on_progress)
+}
 
-    let mut global_offsets = Vec::with_capacity(total_frames);
-    global_offsets.push(AlignmentOffset::default());
-    for offset_result in rest_offsets {
-        global_offsets.push(offset_result?);
-    }
+/// [`multi_point_stack`] on frames already registered by [`global_align`] (or
+/// restored from a session cache).
+pub(crate) fn multi_point_stack_from_global<F>(
+    reader: &SerReader,
+    config: &MultiPointConfig,
+    global: GlobalAlignment,
+    mut on_progress: F,
+) -> Result<(Frame, ApDiagnostics)>
+where
+    F: FnMut(f32),
+{
+    let read = |i: usize| Ok(reader.read_frame(i)?.data);
+    let reference = reader.read_frame(global.reference_idx)?;
+    let dims = reference.data.dim();
     on_progress(0.1);
 
     // Step 2: Build mean reference from top-quality frames
@@ -118,12 +162,7 @@ where
         "Building mean reference from top {}% frames",
         (MEAN_REFERENCE_KEEP_FRACTION * 100.0) as u32
     );
-    let mean_ref = build_mean_reference(
-        reader,
-        &global_offsets,
-        &config.quality_metric,
-        MEAN_REFERENCE_KEEP_FRACTION,
-    )?;
+    let mean_ref = global.mean_reference(MEAN_REFERENCE_KEEP_FRACTION, read)?;
     on_progress(0.2);
 
     // Step 3: Build AP grid on mean reference
@@ -141,48 +180,91 @@ where
     info!(
         "Scoring {} APs across {} frames",
         grid.points.len(),
-        total_frames
+        global.kept_count()
     );
-    let ap_selections = score_all_aps(reader, &grid, &global_offsets, config)?;
+    let ap_selections = score_all_aps(reader, &grid, &global, config)?;
     on_progress(0.4);
 
-    // Step 5 & 6: Per-AP local alignment + stacking (parallel)
+    // Step 5 & 6: Per-AP local alignment + stacking
     info!("Stacking {} alignment points", grid.points.len());
-
-    // Pre-read all frames needed by any AP into a cache for parallel access
-    let needed_frames: BTreeSet<usize> = ap_selections
-        .iter()
-        .flat_map(|sel| sel.iter().map(|(idx, _)| *idx))
-        .collect();
-
-    let mut frame_cache: HashMap<usize, Frame> = HashMap::with_capacity(needed_frames.len());
-    for &idx in &needed_frames {
-        frame_cache.insert(idx, reader.read_frame(idx)?);
-    }
-
-    let ap_stacks: Vec<(AlignmentPoint, Array2<f32>)> = grid
-        .points
-        .par_iter()
-        .map(|ap| {
-            let stacked_patch = stack_ap_cached(
-                &frame_cache,
-                ap,
-                &ap_selections[ap.index],
-                &global_offsets,
+    let (ap_stacks, tallies): (Vec<(AlignmentPoint, Array2<f32>)>, Vec<ApTally>) =
+        if config.local_stack_method.needs_all_patches() {
+            // Median and the order-statistic rejections need every patch of
+            // an AP at once: cache the selected frames.
+            let needed_frames: BTreeSet<usize> = ap_selections
+                .iter()
+                .flat_map(|sel| sel.iter().map(|(idx, _)| *idx))
+                .collect();
+            log_frame_cache(&config.local_stack_method, needed_frames.len(), dims, 1);
+
+            let mut frame_cache: HashMap<usize, Frame> =
+                HashMap::with_capacity(needed_frames.len());
+            for &idx in &needed_frames {
+                frame_cache.insert(idx, reader.read_frame(idx)?);
+            }
+
+            grid.points
+                .par_iter()
+                .map(|ap| {
+                    let (stacked_patch, tally) = stack_ap_cached(
+                        &frame_cache,
+                        ap,
+                        &ap_selections[ap.index],
+                        &global.offsets,
+                        &mean_ref,
+                        config,
+                    );
+                    ((ap.clone(), stacked_patch), tally)
+                })
+                .unzip()
+        } else {
+            // Frame-major: each selected frame is read once per pass.
+            let (stacks, tallies) = stream_ap_stacks(
+                &grid,
+                &ap_selections,
+                &global.offsets,
                 &mean_ref,
                 config,
-            );
-            (ap.clone(), stacked_patch)
-        })
-        .collect();
+                1,
+                |i| {
+                    Ok(StreamedFrame {
+                        luminance: reader.read_frame(i)?.data,
+                        color: None,
+                    })
+                },
+                &mut |done| on_progress(0.4 + 0.5 * done),
+            )?;
+            let stacks = grid
+                .points
+                .iter()
+                .cloned()
+                .zip(stacks.into_iter().map(|mut channels| channels.remove(0)))
+                .collect();
+            (stacks, tallies)
+        };
     on_progress(0.9);
 
     // Step 7: Blend
     info!("Blending alignment point stacks");
-    let blended = blend_ap_stacks(&ap_stacks, h, w, config.ap_size);
+    let blended = blend_ap_stacks(&ap_stacks, &mean_ref);
     on_progress(1.0);
 
-    Ok(Frame::new(blended, reference.original_bit_depth))
+    let diagnostics = ApDiagnostics::from_tallies(&grid, dims, tallies);
+    Ok((
+        Frame::new(blended, reference.original_bit_depth),
+        diagnostics,
+    ))
+}
+
+/// Log the size of the frame cache that the all-patches local methods need.
+fn log_frame_cache(
+    method: &LocalStackMethod,
+    frames: usize,
+    (h, w): (usize, usize),
+    planes: usize,
+) {
+    let mib = frames * h * w * planes * std::mem::size_of::<f32>() / (1 << 20);
+    info!(frames, mib, "Caching selected frames for local {method}");
 }
 
 /// Score all APs across all frames for color input.
@@ -192,65 +274,65 @@ where
 fn score_all_aps_color(
     reader: &SerReader,
     grid: &ApGrid,
-    offsets: &[AlignmentOffset],
+    global: &GlobalAlignment,
     config: &MultiPointConfig,
     color_mode: &ColorMode,
     debayer_method: &DebayerMethod,
 ) -> Result<Vec<Vec<(usize, f64)>>> {
-    let total_frames = reader.frame_count();
-    let num_aps = grid.points.len();
-    let half = config.ap_size / 2;
-
-    let mut quality_matrix: Vec<Vec<f64>> = vec![vec![0.0; total_frames]; num_aps];
-
-    for frame_idx in 0..total_frames {
-        // Read and convert to luminance — only one color frame in memory at a time
-        let lum = read_luminance_frame(reader, frame_idx, color_mode, debayer_method)?;
-
-        for ap in &grid.points {
-            let region = extract_region_shifted(&lum.data, ap.cy, ap.cx, half, &offsets[frame_idx]);
-
-            let score = score_with_metric(&region, &config.quality_metric);
-
-            quality_matrix[ap.index][frame_idx] = score;
-        }
-    }
-
-    let keep_count = ((total_frames as f32 * config.select_percentage).ceil() as usize)
-        .max(1)
-        .min(total_frames);
-
-    let mut result = Vec::with_capacity(num_aps);
-    for ap_scores in &quality_matrix {
-        let mut indexed: Vec<(usize, f64)> =
-            ap_scores.iter().enumerate().map(|(i, &s)| (i, s)).collect();
-        indexed.sort_by(|a, b| b.1.total_cmp(&a.1));
-        indexed.truncate(keep_count);
-        result.push(indexed);
-    }
-
-    Ok(result)
+    // Only one color frame in memory at a time
+    score_aps_with(grid, global, config, |i| {
+        Ok(read_luminance_frame(reader, i, color_mode, debayer_method)?.data)
+    })
 }
 
 /// Top-level orchestrator for multi-alignment-point stacking with color support.
 ///
 /// Pipeline:
-/// 1. Read frame 0 -> debayer -> luminance as reference
-/// 2. Global alignment on luminance
+/// 1. Global alignment on luminance vs the best-scoring frame, rejecting
+///    low-confidence frames
+/// 2. Read the reference frame -> debayer for size and bit depth
 /// 3. Build mean reference from top-quality frames (luminance)
 /// 4. Build AP grid on mean reference
 /// 5. Score APs on luminance (frame-major, memory-efficient)
-/// 6. Build frame cache with (luminance, ColorFrame) for needed frames
+/// 6. Stream the selected frames (or cache them, for median)
 /// 7. Per-AP local alignment (on luminance) + stack (R/G/B independently)
-/// 8. Blend AP stacks per channel
+/// 8. Blend AP stacks per channel over a per-channel mean background
 /// 9. Return ColorFrame
 pub fn multi_point_stack_color<F>(
     reader: &SerReader,
     config: &MultiPointConfig,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
     color_mode: &ColorMode,
     debayer_method: &DebayerMethod,
-    mut on_progress: F,
+    on_progress: F,
 ) -> Result<ColorFrame>
+where
+    F: FnMut(f32),
+{
+    multi_point_stack_color_with_diagnostics(
+        reader,
+        config,
+        alignment,
+        backend,
+        color_mode,
+        debayer_method,
+        on_progress,
+    )
+    .map(|(frame, _)| frame)
+}
+
+/// Same as [`multi_point_stack_color`], additionally returning the AP grid
+/// with per-AP frame usage, shifts and quality.
+pub fn multi_point_stack_color_with_diagnostics<F>(
+    reader: &SerReader,
+    config: &MultiPointConfig,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+    color_mode: &ColorMode,
+    debayer_method: &DebayerMethod,
+    on_progress: F,
+) -> Result<(ColorFrame, ApDiagnostics)>
 where
     F: FnMut(f32),
 {
@@ -259,29 +341,44 @@ where
         return Err(JupiterError::EmptySequence);
     }
 
-    // Step 1: Read reference frame and get luminance
-    let ref_color = read_color_frame(reader, 0, color_mode, debayer_method)?;
-    let ref_lum = luminance(&ref_color);
-    let (h, w) = ref_lum.data.dim();
+    // Step 1: Global alignment on luminance against the best frame
+    let read = |i: usize| Ok(read_luminance_frame(reader, i, color_mode, debayer_method)?.data);
+    let global = global_align(
+        total_frames,
+        config.frames.as_deref(),
+        read,
+        &config.quality_metric,
+        alignment,
+        backend,
+    )?;
+    multi_point_stack_color_from_global(
+        reader,
+        config,
+        global,
+        color_mode,
+        debayer_method,
+        on_progress,
+    )
+}
 
-    // Step 2: Global alignment — compute offsets on luminance
-    info!(
-        "Computing global alignment offsets for {} color frames",
-        total_frames
-    );
-    let rest_offsets: Vec<Result<AlignmentOffset>> = (1..total_frames)
-        .into_par_iter()
-        .map(|i| {
-            let lum = read_luminance_frame(reader, i, color_mode, debayer_method)?;
-            crate::align::phase_correlation::compute_offset(&ref_lum, &lum)
-        })
-        .collect();
+/// [`multi_point_stack_color`] on frames already registered by [`global_align`] (or
+/// restored from a session cache).
+pub(crate) fn multi_point_stack_color_from_global<F>(
+    reader: &SerReader,
+    config: &MultiPointConfig,
+    global: GlobalAlignment,
+    color_mode: &ColorMode,
+    debayer_method: &DebayerMethod,
+    mut on_progress: F,
+) -> Result<(ColorFrame, ApDiagnostics)>
+where
+    F: FnMut(f32),
+{
+    let read = |i: usize| Ok(read_luminance_frame(reader, i, color_mode, debayer_method)?.data);
 
-    let mut global_offsets = Vec::with_capacity(total_frames);
-    global_offsets.push(AlignmentOffset::default());
-    for offset_result in rest_offsets {
-        global_offsets.push(offset_result?);
-    }
+    // Step 2: Reference frame size and bit depth
+    let ref_color = read_color_frame(reader, global.reference_idx, color_mode, debayer_method)?;
+    let dims = ref_color.red.data.dim();
     on_progress(0.1);
 
     // Step 3: Build mean reference from top-quality frames (luminance)
@@ -289,14 +386,7 @@ where
         "Building mean reference from top {}% color frames",
         (MEAN_REFERENCE_KEEP_FRACTION * 100.0) as u32
     );
-    let mean_ref = build_mean_reference_color(
-        reader,
-        &global_offsets,
-        &config.quality_metric,
-        MEAN_REFERENCE_KEEP_FRACTION,
-        color_mode,
-        debayer_method,
-    )?;
+    let mean_ref = global.mean_reference(MEAN_REFERENCE_KEEP_FRACTION, read)?;
     on_progress(0.2);
 
     // Step 4: Build AP grid on mean reference
@@ -314,56 +404,84 @@ where
     info!(
         "Scoring {} APs across {} color frames",
         grid.points.len(),
-        total_frames
+        global.kept_count()
     );
-    let ap_selections = score_all_aps_color(
-        reader,
-        &grid,
-        &global_offsets,
-        config,
-        color_mode,
-        debayer_method,
-    )?;
+    let ap_selections =
+        score_all_aps_color(reader, &grid, &global, config, color_mode, debayer_method)?;
     on_progress(0.4);
 
-    // Step 6: Build frame cache — (luminance, ColorFrame) for needed frames
-    info!("Loading selected color frames into cache");
-    let needed_frames: BTreeSet<usize> = ap_selections
-        .iter()
-        .flat_map(|sel| sel.iter().map(|(idx, _)| *idx))
-        .collect();
-
-    let mut frame_cache: HashMap<usize, (Frame, ColorFrame)> =
-        HashMap::with_capacity(needed_frames.len());
-    for &idx in &needed_frames {
-        let cf = read_color_frame(reader, idx, color_mode, debayer_method)?;
-        let lum = luminance(&cf);
-        frame_cache.insert(idx, (lum, cf));
-    }
-
-    // Step 7: Per-AP local alignment + stacking (parallel)
+    // Step 6 & 7: Per-AP local alignment (on luminance) + stacking
     info!("Stacking {} alignment points (color)", grid.points.len());
     type ColorApStack = (AlignmentPoint, (Array2<f32>, Array2<f32>, Array2<f32>));
-    let ap_stacks: Vec<ColorApStack> = grid
-        .points
-        .par_iter()
-        .map(|ap| {
-            let stacked = stack_ap_cached_color(
-                &frame_cache,
-                ap,
-                &ap_selections[ap.index],
-                &global_offsets,
+    let (ap_stacks, tallies): (Vec<ColorApStack>, Vec<ApTally>) =
+        if config.local_stack_method.needs_all_patches() {
+            // Median and the order-statistic rejections need every patch of
+            // an AP at once: cache the selected frames.
+            let needed_frames: BTreeSet<usize> = ap_selections
+                .iter()
+                .flat_map(|sel| sel.iter().map(|(idx, _)| *idx))
+                .collect();
+            // Luminance plus three colour planes per cached frame.
+            log_frame_cache(&config.local_stack_method, needed_frames.len(), dims, 4);
+
+            let mut frame_cache: HashMap<usize, (Frame, ColorFrame)> =
+                HashMap::with_capacity(needed_frames.len());
+            for &idx in &needed_frames {
+                let cf = read_color_frame(reader, idx, color_mode, debayer_method)?;
+                let lum = luminance(&cf);
+                frame_cache.insert(idx, (lum, cf));
+            }
+
+            grid.points
+                .par_iter()
+                .map(|ap| {
+                    let (stacked, tally) = stack_ap_cached_color(
+                        &frame_cache,
+                        ap,
+                        &ap_selections[ap.index],
+                        &global.offsets,
+                        &mean_ref,
+                        config,
+                    );
+                    ((ap.clone(), stacked), tally)
+                })
+                .unzip()
+        } else {
+            // Frame-major: each selected frame is read once per pass.
+            let (stacks, tallies) = stream_ap_stacks(
+                &grid,
+                &ap_selections,
+                &global.offsets,
                 &mean_ref,
                 config,
-            );
-            (ap.clone(), stacked)
-        })
-        .collect();
+                3,
+                |i| {
+                    let cf = read_color_frame(reader, i, color_mode, debayer_method)?;
+                    Ok(StreamedFrame {
+                        luminance: luminance(&cf).data,
+                        color: Some([cf.red.data, cf.green.data, cf.blue.data]),
+                    })
+                },
+                &mut |done| on_progress(0.4 + 0.5 * done),
+            )?;
+            let stacks = grid
+                .points
+                .iter()
+                .cloned()
+                .zip(stacks.into_iter().map(|channels| {
+                    let [r, g, b]: [Array2<f32>; 3] =
+                        channels.try_into().expect("three colour channels");
+                    (r, g, b)
+                }))
+                .collect();
+            (stacks, tallies)
+        };
     on_progress(0.9);
 
     // Step 8: Blend per channel
     info!("Blending color alignment point stacks");
     let bit_depth = ref_color.red.original_bit_depth;
+    let [bg_r, bg_g, bg_b] = color_background(reader, &global, color_mode, debayer_method)?;
 
     let red_stacks: Vec<(AlignmentPoint, Array2<f32>)> = ap_stacks
         .iter()
@@ -379,19 +497,46 @@ where
         .collect();
 
     let (blended_r, (blended_g, blended_b)) = rayon::join(
-        || blend_ap_stacks(&red_stacks, h, w, config.ap_size),
+        || blend_ap_stacks(&red_stacks, &bg_r),
         || {
             rayon::join(
-                || blend_ap_stacks(&green_stacks, h, w, config.ap_size),
-                || blend_ap_stacks(&blue_stacks, h, w, config.ap_size),
+                || blend_ap_stacks(&green_stacks, &bg_g),
+                || blend_ap_stacks(&blue_stacks, &bg_b),
             )
         },
     );
     on_progress(1.0);
 
-    Ok(ColorFrame {
+    let stacked = ColorFrame {
         red: Frame::new(blended_r, bit_depth),
         green: Frame::new(blended_g, bit_depth),
         blue: Frame::new(blended_b, bit_depth),
-    })
+    };
+    Ok((stacked, ApDiagnostics::from_tallies(&grid, dims, tallies)))
+}
+
+/// Per-channel mean of the best globally aligned colour frames: the
+/// background [`blend_ap_stacks`] falls back to where no AP covers.
+fn color_background(
+    reader: &SerReader,
+    global: &GlobalAlignment,
+    color_mode: &ColorMode,
+    debayer_method: &DebayerMethod,
+) -> Result<[Array2<f32>; 3]> {
+    let best = global.best_frames(MEAN_REFERENCE_KEEP_FRACTION);
+    let mut sums: Option<[Array2<f64>; 3]> = None;
+    for &(idx, _) in &best {
+        let cf = read_color_frame(reader, idx, color_mode, debayer_method)?;
+        let offset = &global.offsets[idx];
+        let planes = [cf.red.data, cf.green.data, cf.blue.data]
+            .map(|plane| shift_array(&plane, offset).mapv(|v| v as f64));
+        match sums {
+            Some(ref mut acc) => acc.iter_mut().zip(&planes).for_each(|(a, p)| *a += p),
+            None => sums = Some(planes),
+        }
+    }
+    let n = best.len() as f64;
+    Ok(sums
+        .expect("best_frames returns at least one frame")
+        .map(|acc| acc.mapv(|v| (v / n) as f32)))
 }
@@ -0,0 +1,477 @@
+//! Dense optical-flow registration stacking.
+//!
+//! Surface warp interpolates its deformation field from a coarse AP grid, so
+//! it cannot follow seeing distortions smaller than an AP. Here every
+//! selected frame gets its own per-pixel displacement field, estimated
+//! against the mean reference with pyramidal Lucas–Kanade and seeded with
+//! the frame's global offset. Frames are warped with [`warp_frame`] and
+//! combined with a quality-weighted mean. Frames are read one at a time, so
+//! memory stays at one frame plus the accumulators whatever the sequence
+//! length.
+
+use ndarray::{Array2, Zip};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::align::phase_correlation::bilinear_sample;
+use crate::align::pyramid::build_pyramid;
+use crate::color::debayer::{luminance, DebayerMethod};
+use crate::color::process::{read_color_frame, read_luminance_frame};
+use crate::compute::ComputeBackend;
+use crate::consts::{
+    DEFAULT_FLOW_ITERATIONS, DEFAULT_FLOW_LEVELS, DEFAULT_FLOW_MAX_DISPLACEMENT,
+    DEFAULT_FLOW_SMOOTHING, DEFAULT_FLOW_WINDOW_RADIUS, FLOW_MIN_LEVEL_SIZE, FLOW_REGULARIZATION,
+    MEAN_REFERENCE_KEEP_FRACTION,
+};
+use crate::error::{JupiterError, Result};
+use crate::filters::gaussian_blur::gaussian_blur_array;
+use crate::frame::{AlignmentOffset, ColorFrame, ColorMode, Frame};
+use crate::io::ser::SerReader;
+use crate::pipeline::config::{AlignmentConfig, QualityMetric};
+use crate::stack::global::{global_align, GlobalAlignment};
+use crate::stack::surface_warp::warp_frame;
+
+/// Configuration for dense optical-flow stacking.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OpticalFlowConfig {
+    /// Fraction of frames to select (0.0–1.0, default: 0.25).
+    pub select_percentage: f32,
+    /// Quality metric for frame scoring.
+    pub quality_metric: QualityMetric,
+    /// Pyramid levels below full resolution (default: 3). Each level doubles
+    /// the largest displacement the flow can follow.
+    #[serde(default = "default_flow_levels")]
+    pub levels: usize,
+    /// Half-width of the Lucas–Kanade window in pixels (default: 7). Larger
+    /// windows are more robust to noise but blur the field.
+    #[serde(default = "default_flow_window_radius")]
+    pub window_radius: usize,
+    /// Warp-and-refine iterations per pyramid level (default: 3).
+    #[serde(default = "default_flow_iterations")]
+    pub iterations: usize,
+    /// Gaussian sigma in pixels for smoothing the flow (default: 2.0; 0 = off).
+    #[serde(default = "default_flow_smoothing")]
+    pub smoothing: f32,
+    /// Maximum local displacement in pixels on top of the global offset
+    /// (default: 8.0).
+    #[serde(default = "default_flow_max_displacement")]
+    pub max_displacement: f32,
+    /// Explicit frames to stack (e.g. from a frame report) instead of the
+    /// best `select_percentage`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames: Option<Vec<usize>>,
+}
+
+fn default_flow_levels() -> usize {
+    DEFAULT_FLOW_LEVELS
+}
+
+fn default_flow_window_radius() -> usize {
+    DEFAULT_FLOW_WINDOW_RADIUS
+}
+
+fn default_flow_iterations() -> usize {
+    DEFAULT_FLOW_ITERATIONS
+}
+
+fn default_flow_smoothing() -> f32 {
+    DEFAULT_FLOW_SMOOTHING
+}
+
+fn default_flow_max_displacement() -> f32 {
+    DEFAULT_FLOW_MAX_DISPLACEMENT
+}
+
+impl Default for OpticalFlowConfig {
+    fn default() -> Self {
+        Self {
+            select_percentage: 0.25,
+            quality_metric: QualityMetric::Laplacian,
+            levels: DEFAULT_FLOW_LEVELS,
+            window_radius: DEFAULT_FLOW_WINDOW_RADIUS,
+            iterations: DEFAULT_FLOW_ITERATIONS,
+            smoothing: DEFAULT_FLOW_SMOOTHING,
+            max_displacement: DEFAULT_FLOW_MAX_DISPLACEMENT,
+            frames: None,
+        }
+    }
+}
+
+/// Dense displacement field of `target` against `reference`.
+///
+/// Returns `(shift_y, shift_x)` in the convention of [`warp_frame`]:
+/// `warp_frame(target, &shift_y, &shift_x)` lines `target` up with
+/// `reference`. The field starts at `initial` (the global offset) on the
+/// coarsest level and each local displacement on top of it is limited to
+/// `config.max_displacement`.
+pub fn dense_flow(
+    reference: &Array2<f32>,
+    target: &Array2<f32>,
+    initial: &AlignmentOffset,
+    config: &OpticalFlowConfig,
+) -> (Array2<f64>, Array2<f64>) {
+    let (h, w) = reference.dim();
+    let mut levels = config.levels;
+    while levels > 0 && h.min(w) >> levels < FLOW_MIN_LEVEL_SIZE {
+        levels -= 1;
+    }
+    let ref_pyramid = build_pyramid(reference, levels);
+    let tgt_pyramid = build_pyramid(target, levels);
+
+    let coarse_scale = (1u32 << levels) as f32;
+    let (ch, cw) = ref_pyramid[levels].dim();
+    let mut flow_y = Array2::from_elem((ch, cw), initial.dy as f32 / coarse_scale);
+    let mut flow_x = Array2::from_elem((ch, cw), initial.dx as f32 / coarse_scale);
+
+    for level in (0..=levels).rev() {
+        let reference = &ref_pyramid[level];
+        let target = &tgt_pyramid[level];
+        if level < levels {
+            flow_y = upsample_flow(&flow_y, reference.dim());
+            flow_x = upsample_flow(&flow_x, reference.dim());
+        }
+
+        let scale = (1u32 << level) as f32;
+        let base = (initial.dy as f32 / scale, initial.dx as f32 / scale);
+        let limit = config.max_displacement / scale;
+        // Window and smoothing stay fixed in level pixels, as in standard
+        // pyramidal Lucas–Kanade: coarse levels need at least as much
+        // regularization as fine ones.
+        let radius = config.window_radius.max(1);
+        let smoothing = config.smoothing;
+
+        for _ in 0..config.iterations.max(1) {
+            let (dy, dx) = lucas_kanade_step(reference, target, &flow_y, &flow_x, radius);
+            Zip::from(&mut flow_y)
+                .and(&mut flow_x)
+                .and(&dy)
+                .and(&dx)
+                .for_each(|fy, fx, &dy, &dx| {
+                    // Linearisation only holds for small steps.
+                    *fy += dy.clamp(-1.0, 1.0);
+                    *fx += dx.clamp(-1.0, 1.0);
+                    let (ly, lx) = (*fy - base.0, *fx - base.1);
+                    let len = ly.hypot(lx);
+                    if len > limit {
+                        *fy = base.0 + ly * limit / len;
+                        *fx = base.1 + lx * limit / len;
+                    }
+                });
+            if smoothing > 0.0 {
+                flow_y = gaussian_blur_array(&flow_y, smoothing);
+                flow_x = gaussian_blur_array(&flow_x, smoothing);
+            }
+        }
+    }
+
+    (flow_y.mapv(f64::from), flow_x.mapv(f64::from))
+}
+
+/// One Lucas–Kanade update of the flow: warp `target`, linearise it around
+/// the current field and solve the windowed 2x2 normal equations per pixel.
+fn lucas_kanade_step(
+    reference: &Array2<f32>,
+    target: &Array2<f32>,
+    flow_y: &Array2<f32>,
+    flow_x: &Array2<f32>,
+    radius: usize,
+) -> (Array2<f32>, Array2<f32>) {
+    let warped = warp_frame(target, &flow_y.mapv(f64::from), &flow_x.mapv(f64::from));
+    let (h, w) = warped.dim();
+    let inside = |r: usize, c: usize| {
+        let (y, x) = (r as f32 - flow_y[[r, c]], c as f32 - flow_x[[r, c]]);
+        y >= 0.0 && x >= 0.0 && y <= (h - 1) as f32 && x <= (w - 1) as f32
+    };
+
+    // Products of the warped image's gradients and the residual; pixels
+    // sampled from outside the frame carry no information.
+    let mut products: [Array2<f32>; 5] = std::array::from_fn(|_| Array2::zeros((h, w)));
+    for r in 0..h {
+        for c in 0..w {
+            if !inside(r, c) {
+                continue;
+            }
+            let gy = 0.5 * (warped[[(r + 1).min(h - 1), c]] - warped[[r.saturating_sub(1), c]]);
+            let gx = 0.5 * (warped[[r, (c + 1).min(w - 1)]] - warped[[r, c.saturating_sub(1)]]);
+            let it = warped[[r, c]] - reference[[r, c]];
+            products[0][[r, c]] = gx * gx;
+            products[1][[r, c]] = gx * gy;
+            products[2][[r, c]] = gy * gy;
+            products[3][[r, c]] = gx * it;
+            products[4][[r, c]] = gy * it;
+        }
+    }
+    let [sxx, sxy, syy, sxt, syt] = products.map(|p| box_mean(&p, radius));
+
+    let mean_trace = (sxx.sum() + syy.sum()) / (h * w) as f64;
+    let lambda = FLOW_REGULARIZATION as f64 * mean_trace + f64::MIN_POSITIVE;
+
+    let mut dy = Array2::<f32>::zeros((h, w));
+    let mut dx = Array2::<f32>::zeros((h, w));
+    for r in 0..h {
+        for c in 0..w {
+            let (a, b, d) = (sxx[[r, c]] + lambda, sxy[[r, c]], syy[[r, c]] + lambda);
+            let det = a * d - b * b;
+            if det <= 0.0 {
+                continue;
+            }
+            let (bx, by) = (sxt[[r, c]], syt[[r, c]]);
+            dx[[r, c]] = ((d * bx - b * by) / det) as f32;
+            dy[[r, c]] = ((a * by - b * bx) / det) as f32;
+        }
+    }
+    (dy, dx)
+}
+
+/// Mean over a `(2 * radius + 1)²` window, clipped at the borders.
+fn box_mean(data: &Array2<f32>, radius: usize) -> Array2<f64> {
+    let (h, w) = data.dim();
+    let mut table = Array2::<f64>::zeros((h + 1, w + 1));
+    for r in 0..h {
+        let mut row_sum = 0.0;
+        for c in 0..w {
+            row_sum += data[[r, c]] as f64;
+            table[[r + 1, c + 1]] = table[[r, c + 1]] + row_sum;
+        }
+    }
+    Array2::from_shape_fn((h, w), |(r, c)| {
+        let (r0, r1) = (r.saturating_sub(radius), (r + radius + 1).min(h));
+        let (c0, c1) = (c.saturating_sub(radius), (c + radius + 1).min(w));
+        let sum = table[[r1, c1]] - table[[r0, c1]] - table[[r1, c0]] + table[[r0, c0]];
+        sum / ((r1 - r0) * (c1 - c0)) as f64
+    })
+}
+
+/// Upsample a flow component to `dims`, doubling its values.
+fn upsample_flow(flow: &Array2<f32>, dims: (usize, usize)) -> Array2<f32> {
+    let (h, w) = flow.dim();
+    Array2::from_shape_fn(dims, |(r, c)| {
+        let y = (r as f64 / 2.0).min((h - 1) as f64);
+        let x = (c as f64 / 2.0).min((w - 1) as f64);
+        2.0 * bilinear_sample(flow, y, x)
+    })
+}
+
+/// Top-level orchestrator for optical-flow stacking (mono).
+///
+/// Pipeline:
+/// 1. Global align all frames vs the best-scoring frame with the configured
+///    method, rejecting low-confidence frames
+/// 2. Build mean reference from top-quality frames
+/// 3. Select the top N% of the kept frames by global quality
+/// 4. For each selected frame: dense flow vs the mean reference → warp → accumulate
+/// 5. Quality-weighted mean of all warped frames (uniform when every score is
+///    zero or negative)
+pub fn optical_flow_stack<F>(
+    reader: &SerReader,
+    config: &OpticalFlowConfig,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+    on_progress: F,
+) -> Result<Frame>
+where
+    F: FnMut(f32),
+{
+    let total_frames = reader.frame_count();
+    if total_frames == 0 {
+        return Err(JupiterError::EmptySequence);
+    }
+
+    info!("Optical flow: global alignment of {} frames", total_frames);
+    let read = |i: usize| Ok(reader.read_frame(i)?.data);
+    let global = global_align(
+        total_frames,
+        config.frames.as_deref(),
+        read,
+        &config.quality_metric,
+        alignment,
+        backend,
+    )?;
+    optical_flow_stack_from_global(reader, config, global, on_progress)
+}
+
+/// [`optical_flow_stack`] on frames already registered by [`global_align`] (or
+/// restored from a session cache).
+pub(crate) fn optical_flow_stack_from_global<F>(
+    reader: &SerReader,
+    config: &OpticalFlowConfig,
+    global: GlobalAlignment,
+    mut on_progress: F,
+) -> Result<Frame>
+where
+    F: FnMut(f32),
+{
+    let read = |i: usize| Ok(reader.read_frame(i)?.data);
+    let bit_depth = reader.read_frame(global.reference_idx)?.original_bit_depth;
+    on_progress(0.1);
+
+    info!("Optical flow: building mean reference");
+    let mean_ref = global.mean_reference(MEAN_REFERENCE_KEEP_FRACTION, read)?;
+    on_progress(0.2);
+
+    let mut stacked = flow_stack(&global, &mean_ref, config, &mut on_progress, |i| {
+        let data = reader.read_frame(i)?.data;
+        Ok((data.clone(), vec![data]))
+    })?;
+
+    on_progress(1.0);
+    Ok(Frame::new(stacked.remove(0), bit_depth))
+}
+
+/// Top-level orchestrator for optical-flow stacking (color).
+///
+/// The flow is estimated on luminance and applied to each channel.
+pub fn optical_flow_stack_color<F>(
+    reader: &SerReader,
+    config: &OpticalFlowConfig,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+    color_mode: &ColorMode,
+    debayer_method: &DebayerMethod,
+    on_progress: F,
+) -> Result<ColorFrame>
+where
+    F: FnMut(f32),
+{
+    let total_frames = reader.frame_count();
+    if total_frames == 0 {
+        return Err(JupiterError::EmptySequence);
+    }
+
+    info!(
+        "Optical flow color: global alignment of {} frames",
+        total_frames
+    );
+    let read = |i: usize| Ok(read_luminance_frame(reader, i, color_mode, debayer_method)?.data);
+    let global = global_align(
+        total_frames,
+        config.frames.as_deref(),
+        read,
+        &config.quality_metric,
+        alignment,
+        backend,
+    )?;
+    optical_flow_stack_color_from_global(
+        reader,
+        config,
+        global,
+        color_mode,
+        debayer_method,
+        on_progress,
+    )
+}
+
+/// [`optical_flow_stack_color`] on frames already registered by [`global_align`] (or
+/// restored from a session cache).
+pub(crate) fn optical_flow_stack_color_from_global<F>(
+    reader: &SerReader,
+    config: &OpticalFlowConfig,
+    global: GlobalAlignment,
+    color_mode: &ColorMode,
+    debayer_method: &DebayerMethod,
+    mut on_progress: F,
+) -> Result<ColorFrame>
+where
+    F: FnMut(f32),
+{
+    let read = |i: usize| Ok(read_luminance_frame(reader, i, color_mode, debayer_method)?.data);
+    let bit_depth = read_color_frame(reader, global.reference_idx, color_mode, debayer_method)?
+        .red
+        .original_bit_depth;
+    on_progress(0.1);
+
+    info!("Optical flow color: building mean reference");
+    let mean_ref = global.mean_reference(MEAN_REFERENCE_KEEP_FRACTION, read)?;
+    on_progress(0.2);
+
+    let stacked = flow_stack(&global, &mean_ref, config, &mut on_progress, |i| {
+        let cf = read_color_frame(reader, i, color_mode, debayer_method)?;
+        let lum = luminance(&cf).data;
+        Ok((lum, vec![cf.red.data, cf.green.data, cf.blue.data]))
+    })?;
+
+    on_progress(1.0);
+    let [red, green, blue]: [Array2<f32>; 3] = stacked
+        .try_into()
+        .map_err(|_| JupiterError::Pipeline("Expected three color channels".into()))?;
+    Ok(ColorFrame {
+        red: Frame::new(red, bit_depth),
+        green: Frame::new(green, bit_depth),
+        blue: Frame::new(blue, bit_depth),
+    })
+}
+
+/// Warp and accumulate the selected frames. `read(i)` returns frame `i`'s
+/// luminance (for the flow) and the channels to stack.
+fn flow_stack<R>(
+    global: &GlobalAlignment,
+    mean_ref: &Array2<f32>,
+    config: &OpticalFlowConfig,
+    on_progress: &mut dyn FnMut(f32),
+    read: R,
+) -> Result<Vec<Array2<f32>>>
+where
+    R: Fn(usize) -> Result<(Array2<f32>, Vec<Array2<f32>>)>,
+{
+    let selected = global.best_frames(global.selection_fraction(config.select_percentage));
+    let frame_count = selected.len();
+    info!(
+        "Optical flow: warping and stacking {} selected frames",
+        frame_count
+    );
+
+    let dims = mean_ref.dim();
+    let mut accumulators: Vec<Array2<f64>> = Vec::new();
+    let mut total_weight = 0.0f64;
+    let mut flow_sum = 0.0f64;
+    // All-zero scores would leave an empty (black) stack; weight uniformly.
+    let uniform = selected.iter().all(|&(_, score)| score <= 0.0);
+
+    for (i, &(frame_idx, quality_score)) in selected.iter().enumerate() {
+        let (lum, channels) = read(frame_idx)?;
+        if lum.dim() != dims {
+            return Err(JupiterError::Pipeline(format!(
+                "Frame {frame_idx} is {:?}, expected {:?}",
+                lum.dim(),
+                dims
+            )));
+        }
+        let global_offset = &global.offsets[frame_idx];
+        let (field_dy, field_dx) = dense_flow(mean_ref, &lum, global_offset, config);
+        flow_sum += Zip::from(&field_dy)
+            .and(&field_dx)
+            .fold(0.0, |acc, &fy, &fx| {
+                acc + (fy - global_offset.dy).hypot(fx - global_offset.dx)
+            })
+            / (dims.0 * dims.1) as f64;
+
+        if accumulators.is_empty() {
+            accumulators = vec![Array2::zeros(dims); channels.len()];
+        }
+        let weight = if uniform { 1.0 } else { quality_score.max(0.0) };
+        total_weight += weight;
+        for (acc, channel) in accumulators.iter_mut().zip(&channels) {
+            let warped = warp_frame(channel, &field_dy, &field_dx);
+            Zip::from(acc)
+                .and(&warped)
+                .for_each(|a, &v| *a += v as f64 * weight);
+        }
+
+        on_progress(0.2 + 0.75 * (i + 1) as f32 / frame_count as f32);
+    }
+
+    info!(
+        "Optical flow: mean local displacement {:.2} px",
+        flow_sum / frame_count.max(1) as f64
+    );
+    Ok(accumulators
+        .into_iter()
+        .map(|acc| {
+            if total_weight > 1e-15 {
+                acc.mapv(|v| (v / total_weight) as f32)
+            } else {
+                acc.mapv(|v| v as f32)
+            }
+        })
+        .collect())
+}
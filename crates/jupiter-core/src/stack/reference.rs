@@ -3,7 +3,7 @@ use ndarray::Array2;
 use crate::align::phase_correlation::shift_array;
 use crate::color::debayer::DebayerMethod;
 use crate::color::process::read_luminance_frame;
-use crate::error::Result;
+use crate::error::{JupiterError, Result};
 use crate::frame::{AlignmentOffset, ColorMode};
 use crate::io::ser::SerReader;
 use crate::pipeline::config::QualityMetric;
@@ -42,22 +42,11 @@ pub fn build_mean_reference(
         .min(total);
     scores.truncate(keep);
 
-    let frame0 = reader.read_frame(0)?;
-    let (h, w) = frame0.data.dim();
-    let mut accumulator = Array2::<f64>::zeros((h, w));
-
-    for &(idx, _) in &scores {
-        let frame = reader.read_frame(idx)?;
-        let shifted = if idx == 0 {
-            frame.data
-        } else {
-            shift_array(&frame.data, &offsets[idx])
-        };
-        accumulator += &shifted.mapv(|v| v as f64);
-    }
-
-    let n = scores.len() as f64;
-    Ok(accumulator.mapv(|v| (v / n) as f32))
+    let kept: Vec<AlignmentOffset> = scores
+        .iter()
+        .map(|&(idx, _)| offsets[idx].clone())
+        .collect();
+    mean_of_shifted(&kept, |k| Ok(reader.read_frame(scores[k].0)?.data))
 }
 
 /// Build a mean reference from color frames (returns luminance).
@@ -86,20 +75,43 @@ pub fn build_mean_reference_color(
     scores.truncate(keep);
 
     // Average luminance of the best frames (shifted)
-    let first_lum = read_luminance_frame(reader, 0, color_mode, debayer_method)?;
-    let (h, w) = first_lum.data.dim();
-    let mut accumulator = Array2::<f64>::zeros((h, w));
+    let kept: Vec<AlignmentOffset> = scores
+        .iter()
+        .map(|&(idx, _)| offsets[idx].clone())
+        .collect();
+    mean_of_shifted(&kept, |k| {
+        Ok(read_luminance_frame(reader, scores[k].0, color_mode, debayer_method)?.data)
+    })
+}
+
+/// Shift frames by their offsets and average them into a reference.
+///
+/// `read(k)` supplies the data of the frame belonging to `offsets[k]`, so the
+/// frames can come from memory or be streamed from disk one at a time.
+pub fn mean_of_shifted<F>(offsets: &[AlignmentOffset], read: F) -> Result<Array2<f32>>
+where
+    F: Fn(usize) -> Result<Array2<f32>>,
+{
+    if offsets.is_empty() {
+        return Err(JupiterError::EmptySequence);
+    }
 
-    for &(idx, _) in &scores {
-        let lum = read_luminance_frame(reader, idx, color_mode, debayer_method)?;
-        let shifted = if idx == 0 {
-            lum.data
+    let mut accumulator: Option<Array2<f64>> = None;
+    for (k, offset) in offsets.iter().enumerate() {
+        let data = read(k)?;
+        let shifted = if offset.is_zero() {
+            data
         } else {
-            shift_array(&lum.data, &offsets[idx])
+            shift_array(&data, offset)
         };
-        accumulator += &shifted.mapv(|v| v as f64);
+        match accumulator {
+            Some(ref mut acc) => *acc += &shifted.mapv(|v| v as f64),
+            None => accumulator = Some(shifted.mapv(|v| v as f64)),
+        }
     }
 
-    let n = scores.len() as f64;
-    Ok(accumulator.mapv(|v| (v / n) as f32))
+    let n = offsets.len() as f64;
+    Ok(accumulator
+        .expect("offsets is non-empty")
+        .mapv(|v| (v / n) as f32))
 }
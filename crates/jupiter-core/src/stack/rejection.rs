@@ -0,0 +1,547 @@
+//! Per-pixel outlier rejection and quality-weighted combination.
+//!
+//! Every rejection algorithm works on one pixel's stack of values (one per
+//! frame) and flags the values it rejects; the survivors are then averaged
+//! with the frames' quality weights. The same engine serves whole-frame
+//! stacking and the per-AP patches of multi-point stacking.
+//!
+//! Rejection itself is unweighted: a transient (satellite, hot pixel, cosmic
+//! ray) usually raises a frame's sharpness score, and letting that weight
+//! pull the statistics towards the outlier would shield it from rejection.
+
+use ndarray::{Array2, Axis};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::consts::{
+    DEFAULT_GESD_ALPHA, DEFAULT_GESD_MAX_OUTLIERS, EPSILON, WINSORIZE_CUTOFF, WINSORIZE_MAX_PASSES,
+    WINSORIZE_SIGMA_CORRECTION,
+};
+use crate::error::{JupiterError, Result};
+use crate::frame::Frame;
+
+/// How outliers are removed from each pixel's stack before averaging.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Rejection {
+    /// Keep every value.
+    None,
+    /// Reject values more than `sigma` standard deviations from the mean,
+    /// repeated `iterations` times.
+    SigmaClip { sigma: f32, iterations: usize },
+    /// Like sigma clipping, but centred on the median with a spread measured
+    /// on the winsorized stack, so the outliers being hunted cannot inflate
+    /// the threshold that is meant to catch them. Preferred for 10+ frames.
+    Winsorized { sigma: f32, iterations: usize },
+    /// Fit a straight line to the sorted stack and reject values more than
+    /// `sigma` mean deviations from it. Suits large stacks whose values drift
+    /// (changing transparency, gradients) rather than scatter around a mean.
+    LinearFit { sigma: f32, iterations: usize },
+    /// Generalized extreme studentized deviate test: up to `max_outliers`
+    /// (a fraction of the stack) values are tested one at a time at
+    /// significance `alpha`. Needs no threshold tuning; best for 25+ frames.
+    Gesd { max_outliers: f32, alpha: f32 },
+}
+
+/// Parameters for GESD rejection stacking.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GesdParams {
+    /// Largest fraction of a pixel's values that may be rejected (default: 0.3).
+    pub max_outliers: f32,
+    /// Significance level of each test (default: 0.05).
+    pub alpha: f32,
+}
+
+impl Default for GesdParams {
+    fn default() -> Self {
+        Self {
+            max_outliers: DEFAULT_GESD_MAX_OUTLIERS,
+            alpha: DEFAULT_GESD_ALPHA,
+        }
+    }
+}
+
+impl GesdParams {
+    /// These parameters as a [`Rejection`].
+    pub fn rejection(&self) -> Rejection {
+        Rejection::Gesd {
+            max_outliers: self.max_outliers,
+            alpha: self.alpha,
+        }
+    }
+}
+
+/// How many values were rejected at each pixel.
+#[derive(Clone, Debug)]
+pub struct RejectionMap {
+    pub counts: Array2<u32>,
+    /// Number of frames stacked, the largest possible count.
+    pub frames: usize,
+}
+
+impl RejectionMap {
+    /// Total number of rejected values.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().map(|&c| c as u64).sum()
+    }
+
+    /// Rejected fraction of all stacked values.
+    pub fn fraction(&self) -> f64 {
+        let values = self.counts.len() * self.frames;
+        if values == 0 {
+            0.0
+        } else {
+            self.total() as f64 / values as f64
+        }
+    }
+
+    /// The map as an image: the fraction of frames rejected at each pixel,
+    /// 0 (none) to 1 (all).
+    pub fn to_frame(&self) -> Frame {
+        let frames = self.frames.max(1) as f32;
+        Frame::new(self.counts.mapv(|c| c as f32 / frames), 16)
+    }
+}
+
+/// Stack frames with per-pixel rejection, averaging the surviving values
+/// with `weights` (per-frame quality scores; uniform when `None`).
+///
+/// Returns the stacked frame and the count of rejected values per pixel.
+/// A pixel whose values are all rejected takes the weighted mean of all.
+pub fn rejection_stack(
+    frames: &[Frame],
+    rejection: &Rejection,
+    weights: Option<&[f64]>,
+) -> Result<(Frame, RejectionMap)> {
+    if frames.is_empty() {
+        return Err(JupiterError::EmptySequence);
+    }
+    let weights = frame_weights(frames.len(), weights)?;
+    let (h, w) = frames[0].data.dim();
+    if frames.iter().any(|f| f.data.dim() != (h, w)) {
+        return Err(JupiterError::Pipeline("Frame size mismatch".into()));
+    }
+
+    let mut data = Array2::<f32>::zeros((h, w));
+    let mut counts = Array2::<u32>::zeros((h, w));
+    data.axis_iter_mut(Axis(0))
+        .into_par_iter()
+        .zip(counts.axis_iter_mut(Axis(0)))
+        .enumerate()
+        .for_each_init(
+            || PixelStack::new(&weights),
+            |stack, (row, (mut out, mut rejected))| {
+                for col in 0..w {
+                    stack.load(frames.iter().map(|f| f.data[[row, col]]));
+                    let (value, count) = stack.combine(rejection);
+                    out[col] = value;
+                    rejected[col] = count;
+                }
+            },
+        );
+
+    Ok((
+        Frame::new(data, frames[0].original_bit_depth),
+        RejectionMap {
+            counts,
+            frames: frames.len(),
+        },
+    ))
+}
+
+/// Per-frame weights as `f32`, clamped at zero; uniform when `None`.
+pub(crate) fn frame_weights(count: usize, weights: Option<&[f64]>) -> Result<Vec<f32>> {
+    match weights {
+        None => Ok(vec![1.0; count]),
+        Some(w) if w.len() != count => Err(JupiterError::Pipeline(format!(
+            "Got {} quality weights for {count} frames",
+            w.len()
+        ))),
+        Some(w) => Ok(w.iter().map(|&v| v.max(0.0) as f32).collect()),
+    }
+}
+
+/// Reject and combine AP patches pixel by pixel (multi-point local stacking).
+pub(crate) fn reject_patches(
+    patches: &[Array2<f32>],
+    weights: &[f64],
+    rejection: &Rejection,
+) -> Array2<f32> {
+    let weights: Vec<f32> = weights.iter().map(|&v| v.max(0.0) as f32).collect();
+    let mut stack = PixelStack::new(&weights);
+    Array2::from_shape_fn(patches[0].dim(), |p| {
+        stack.load(patches.iter().map(|patch| patch[p]));
+        stack.combine(rejection).0
+    })
+}
+
+/// One pixel's values across the stack, with working buffers reused from
+/// pixel to pixel.
+pub(crate) struct PixelStack {
+    values: Vec<f32>,
+    weights: Vec<f32>,
+    keep: Vec<bool>,
+    scratch: Vec<f32>,
+    order: Vec<usize>,
+}
+
+impl PixelStack {
+    pub(crate) fn new(weights: &[f32]) -> Self {
+        let n = weights.len();
+        Self {
+            values: Vec::with_capacity(n),
+            weights: weights.to_vec(),
+            keep: vec![true; n],
+            scratch: Vec::with_capacity(n),
+            order: Vec::with_capacity(n),
+        }
+    }
+
+    pub(crate) fn load(&mut self, values: impl Iterator<Item = f32>) {
+        self.values.clear();
+        self.values.extend(values);
+        self.keep.fill(true);
+    }
+
+    /// Apply `rejection`, then return the weighted mean of the survivors and
+    /// the number of values rejected.
+    pub(crate) fn combine(&mut self, rejection: &Rejection) -> (f32, u32) {
+        match *rejection {
+            Rejection::None => {}
+            Rejection::SigmaClip { sigma, iterations } => self.sigma_clip(sigma, iterations),
+            Rejection::Winsorized { sigma, iterations } => self.winsorized(sigma, iterations),
+            Rejection::LinearFit { sigma, iterations } => self.linear_fit(sigma, iterations),
+            Rejection::Gesd {
+                max_outliers,
+                alpha,
+            } => self.gesd(max_outliers, alpha),
+        }
+        let rejected = self.keep.iter().filter(|&&k| !k).count() as u32;
+        let value = match self.weighted_mean(true) {
+            Some(mean) => mean,
+            None => self.weighted_mean(false).unwrap_or(0.0),
+        };
+        (value, rejected)
+    }
+
+    /// Weighted mean of the kept values (all values when `kept_only` is
+    /// false); uniform weights when theirs sum to zero. `None` when no value
+    /// is kept.
+    fn weighted_mean(&self, kept_only: bool) -> Option<f32> {
+        let mut sum = 0.0f64;
+        let mut weight = 0.0f64;
+        let mut plain = 0.0f64;
+        let mut count = 0usize;
+        for ((&v, &w), &k) in self.values.iter().zip(&self.weights).zip(&self.keep) {
+            if kept_only && !k {
+                continue;
+            }
+            sum += v as f64 * w as f64;
+            weight += w as f64;
+            plain += v as f64;
+            count += 1;
+        }
+        if count == 0 {
+            None
+        } else if weight > 0.0 {
+            Some((sum / weight) as f32)
+        } else {
+            Some((plain / count as f64) as f32)
+        }
+    }
+
+    /// Mean and population standard deviation of the kept values,
+    /// unweighted (see the module docs).
+    fn kept_stats(&self) -> (f32, f32) {
+        let (mut sum, mut count) = (0.0f64, 0usize);
+        for v in self.kept_values() {
+            sum += v as f64;
+            count += 1;
+        }
+        if count == 0 {
+            return (0.0, 0.0);
+        }
+        let mean = sum / count as f64;
+        let var = self
+            .kept_values()
+            .map(|v| (v as f64 - mean).powi(2))
+            .sum::<f64>()
+            / count as f64;
+        (mean as f32, var.sqrt() as f32)
+    }
+
+    fn kept_values(&self) -> impl Iterator<Item = f32> + '_ {
+        self.values
+            .iter()
+            .zip(&self.keep)
+            .filter(|(_, &k)| k)
+            .map(|(&v, _)| v)
+    }
+
+    /// Reject kept values outside `[lo, hi]`. Returns whether any was.
+    fn reject_outside(&mut self, lo: f32, hi: f32) -> bool {
+        let mut any = false;
+        for (&v, k) in self.values.iter().zip(self.keep.iter_mut()) {
+            if *k && (v < lo || v > hi) {
+                *k = false;
+                any = true;
+            }
+        }
+        any
+    }
+
+    fn sigma_clip(&mut self, sigma: f32, iterations: usize) {
+        for _ in 0..iterations {
+            let (mean, stddev) = self.kept_stats();
+            if stddev < EPSILON {
+                break;
+            }
+            if !self.reject_outside(mean - sigma * stddev, mean + sigma * stddev) {
+                break;
+            }
+        }
+    }
+
+    fn winsorized(&mut self, sigma: f32, iterations: usize) {
+        for _ in 0..iterations {
+            self.scratch.clear();
+            self.scratch.extend(
+                self.values
+                    .iter()
+                    .zip(&self.keep)
+                    .filter(|(_, &k)| k)
+                    .map(|(&v, _)| v),
+            );
+            if self.scratch.len() < 3 {
+                break;
+            }
+            let median = median(&mut self.scratch);
+            let mut spread = stddev(&self.scratch);
+            for _ in 0..WINSORIZE_MAX_PASSES {
+                if spread < EPSILON {
+                    break;
+                }
+                let (lo, hi) = (
+                    median - WINSORIZE_CUTOFF * spread,
+                    median + WINSORIZE_CUTOFF * spread,
+                );
+                self.scratch.clear();
+                self.scratch.extend(
+                    self.values
+                        .iter()
+                        .zip(&self.keep)
+                        .filter(|(_, &k)| k)
+                        .map(|(&v, _)| v.clamp(lo, hi)),
+                );
+                let next = WINSORIZE_SIGMA_CORRECTION * stddev(&self.scratch);
+                let converged = (next - spread).abs() <= 5e-4 * spread;
+                spread = next;
+                if converged {
+                    break;
+                }
+            }
+            if spread < EPSILON
+                || !self.reject_outside(median - sigma * spread, median + sigma * spread)
+            {
+                break;
+            }
+        }
+    }
+
+    fn linear_fit(&mut self, sigma: f32, iterations: usize) {
+        for _ in 0..iterations {
+            self.order.clear();
+            self.order
+                .extend((0..self.values.len()).filter(|&i| self.keep[i]));
+            let n = self.order.len();
+            if n < 3 {
+                break;
+            }
+            let values = &self.values;
+            self.order
+                .sort_unstable_by(|&a, &b| values[a].total_cmp(&values[b]));
+
+            // Least-squares line through (rank, value).
+            let mean_x = (n - 1) as f64 / 2.0;
+            let mean_y = self.order.iter().map(|&i| values[i] as f64).sum::<f64>() / n as f64;
+            let (mut sxy, mut sxx) = (0.0, 0.0);
+            for (rank, &i) in self.order.iter().enumerate() {
+                let dx = rank as f64 - mean_x;
+                sxy += dx * (values[i] as f64 - mean_y);
+                sxx += dx * dx;
+            }
+            let slope = sxy / sxx;
+            let fit = |rank: usize| mean_y + slope * (rank as f64 - mean_x);
+            let deviation = self
+                .order
+                .iter()
+                .enumerate()
+                .map(|(rank, &i)| (values[i] as f64 - fit(rank)).abs())
+                .sum::<f64>()
+                / n as f64;
+            if deviation < EPSILON as f64 {
+                break;
+            }
+
+            let limit = sigma as f64 * deviation;
+            let mut any = false;
+            for (rank, &i) in self.order.iter().enumerate() {
+                if (values[i] as f64 - fit(rank)).abs() > limit {
+                    self.keep[i] = false;
+                    any = true;
+                }
+            }
+            if !any {
+                break;
+            }
+        }
+    }
+
+    fn gesd(&mut self, max_outliers: f32, alpha: f32) {
+        self.order.clear();
+        self.order
+            .extend((0..self.values.len()).filter(|&i| self.keep[i]));
+        let n = self.order.len();
+        // At least three values must remain for the last test to have a
+        // degree of freedom.
+        let tests = ((max_outliers.clamp(0.0, 1.0) * n as f32) as usize).min(n.saturating_sub(3));
+
+        let mut candidates = Vec::with_capacity(tests);
+        let mut outliers = 0;
+        for i in 1..=tests {
+            let remaining = self.order.len();
+            let mean = self
+                .order
+                .iter()
+                .map(|&k| self.values[k] as f64)
+                .sum::<f64>()
+                / remaining as f64;
+            let var = self
+                .order
+                .iter()
+                .map(|&k| (self.values[k] as f64 - mean).powi(2))
+                .sum::<f64>()
+                / (remaining - 1) as f64;
+            let sd = var.sqrt();
+            if sd < EPSILON as f64 {
+                break;
+            }
+            let (pos, deviation) = self
+                .order
+                .iter()
+                .map(|&k| (self.values[k] as f64 - mean).abs())
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .expect("stack is not empty");
+            candidates.push(self.order.swap_remove(pos));
+
+            let df = (n - i - 1) as f64;
+            let p = 1.0 - alpha as f64 / (2.0 * (n - i + 1) as f64);
+            let t = student_t_quantile(p, df);
+            let critical = (n - i) as f64 * t / ((df + t * t) * (n - i + 1) as f64).sqrt();
+            if deviation / sd > critical {
+                outliers = i;
+            }
+        }
+        for &k in &candidates[..outliers] {
+            self.keep[k] = false;
+        }
+    }
+}
+
+fn median(values: &mut [f32]) -> f32 {
+    let n = values.len();
+    let mid = n / 2;
+    let upper = *values.select_nth_unstable_by(mid, f32::total_cmp).1;
+    if n % 2 == 1 {
+        upper
+    } else {
+        let lower = values[..mid]
+            .iter()
+            .copied()
+            .max_by(f32::total_cmp)
+            .unwrap_or(upper);
+        (lower + upper) / 2.0
+    }
+}
+
+fn stddev(values: &[f32]) -> f32 {
+    let n = values.len() as f64;
+    let mean = values.iter().map(|&v| v as f64).sum::<f64>() / n;
+    let var = values
+        .iter()
+        .map(|&v| (v as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    var.sqrt() as f32
+}
+
+/// Quantile of Student's t distribution with `df` degrees of freedom.
+///
+/// Exact for one and two degrees of freedom, otherwise the Cornish–Fisher
+/// expansion around the normal quantile (Abramowitz & Stegun 26.7.5).
+fn student_t_quantile(p: f64, df: f64) -> f64 {
+    if df <= 1.0 {
+        return (std::f64::consts::PI * (p - 0.5)).tan();
+    }
+    if df <= 2.0 {
+        return (2.0 * p - 1.0) / (2.0 * p * (1.0 - p)).sqrt();
+    }
+    let z = normal_quantile(p);
+    let z2 = z * z;
+    let g1 = (z2 + 1.0) * z / 4.0;
+    let g2 = ((5.0 * z2 + 16.0) * z2 + 3.0) * z / 96.0;
+    let g3 = (((3.0 * z2 + 19.0) * z2 + 17.0) * z2 - 15.0) * z / 384.0;
+    let g4 = ((((79.0 * z2 + 776.0) * z2 + 1482.0) * z2 - 1920.0) * z2 - 945.0) * z / 92160.0;
+    z + g1 / df + g2 / df.powi(2) + g3 / df.powi(3) + g4 / df.powi(4)
+}
+
+/// Quantile of the standard normal distribution (Acklam's rational
+/// approximation, relative error below 1.2e-9).
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    let p = p.clamp(1e-300, 1.0 - 1e-16);
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    }
+}
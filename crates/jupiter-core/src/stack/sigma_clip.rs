@@ -1,10 +1,11 @@
-use ndarray::Array2;
-use rayon::prelude::*;
+use ndarray::{Array2, Zip};
 use serde::{Deserialize, Serialize};
 
-use crate::consts::{EPSILON, PARALLEL_PIXEL_THRESHOLD};
+use crate::consts::EPSILON;
 use crate::error::{JupiterError, Result};
 use crate::frame::Frame;
+use crate::stack::rejection::{rejection_stack, Rejection};
+use crate::stack::streaming::MultiPassStacker;
 
 /// Parameters for sigma-clipped mean stacking.
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -28,158 +29,178 @@ impl Default for SigmaClipParams {
 ///
 /// Per pixel: compute mean and stddev, reject values more than `sigma` standard
 /// deviations from the mean, then recompute the mean from remaining values.
-/// Repeat for the configured number of iterations.
-/// Parallelizes at the row level for images >= 256x256.
+/// Repeat for the configured number of iterations. See
+/// [`rejection_stack`] for quality weighting and the rejection map.
 pub fn sigma_clip_stack(frames: &[Frame], params: &SigmaClipParams) -> Result<Frame> {
-    if frames.is_empty() {
-        return Err(JupiterError::EmptySequence);
-    }
-
-    let (h, w) = frames[0].data.dim();
-    let n = frames.len();
+    rejection_stack(frames, &params.rejection(), None).map(|(frame, _)| frame)
+}
 
-    if h * w >= PARALLEL_PIXEL_THRESHOLD && n > 1 {
-        sigma_clip_stack_parallel(frames, params, h, w, n)
-    } else {
-        sigma_clip_stack_sequential(frames, params, h, w, n)
+impl SigmaClipParams {
+    /// These parameters as classic sigma clipping.
+    pub fn rejection(&self) -> Rejection {
+        Rejection::SigmaClip {
+            sigma: self.sigma,
+            iterations: self.iterations,
+        }
     }
 }
 
-fn sigma_clip_stack_parallel(
-    frames: &[Frame],
-    params: &SigmaClipParams,
-    h: usize,
-    w: usize,
-    n: usize,
-) -> Result<Frame> {
-    // Row-parallel: each row allocates its own pixel_values and mask
-    let rows: Vec<Vec<f32>> = (0..h)
-        .into_par_iter()
-        .map(|row| {
-            let mut pixel_values = vec![0.0f32; n];
-            let mut mask = vec![true; n];
-            let mut row_result = vec![0.0f32; w];
-            for (col, result) in row_result.iter_mut().enumerate() {
-                sigma_clip_pixel(
-                    frames,
-                    row,
-                    col,
-                    n,
-                    params,
-                    &mut pixel_values,
-                    &mut mask,
-                    result,
-                );
-            }
-            row_result
-        })
-        .collect();
-
-    let mut result = Array2::<f32>::zeros((h, w));
-    for (row, row_data) in rows.into_iter().enumerate() {
-        for (col, val) in row_data.into_iter().enumerate() {
-            result[[row, col]] = val;
+/// Per-pixel state of [`StreamingSigmaClipStacker`]: the clipping bounds of
+/// the current pass, unweighted Welford statistics of the values inside
+/// them, and their quality-weighted sum.
+#[derive(Clone, Copy)]
+struct ClipAccumulator {
+    lo: f32,
+    hi: f32,
+    count: f32,
+    mean: f32,
+    m2: f32,
+    sum: f32,
+    weight: f32,
+    /// Weighted mean of all values, for a pixel whose values all end up
+    /// rejected.
+    fallback: f32,
+}
+
+impl Default for ClipAccumulator {
+    fn default() -> Self {
+        Self {
+            lo: f32::NEG_INFINITY,
+            hi: f32::INFINITY,
+            count: 0.0,
+            mean: 0.0,
+            m2: 0.0,
+            sum: 0.0,
+            weight: 0.0,
+            fallback: 0.0,
         }
     }
-    Ok(Frame::new(result, frames[0].original_bit_depth))
 }
 
-fn sigma_clip_stack_sequential(
-    frames: &[Frame],
-    params: &SigmaClipParams,
-    h: usize,
-    w: usize,
-    n: usize,
-) -> Result<Frame> {
-    // Sequential for small images
-    let mut result = Array2::<f32>::zeros((h, w));
-    let mut pixel_values = vec![0.0f32; n];
-    let mut mask = vec![true; n];
-
-    for row in 0..h {
-        for col in 0..w {
-            sigma_clip_pixel(
-                frames,
-                row,
-                col,
-                n,
-                params,
-                &mut pixel_values,
-                &mut mask,
-                &mut result[[row, col]],
-            );
+impl ClipAccumulator {
+    fn value(&self) -> f32 {
+        if self.weight > 0.0 {
+            self.sum / self.weight
+        } else if self.count > 0.0 {
+            self.mean
+        } else {
+            self.fallback
         }
     }
-    Ok(Frame::new(result, frames[0].original_bit_depth))
 }
 
-#[allow(clippy::too_many_arguments)]
-fn sigma_clip_pixel(
-    frames: &[Frame],
-    row: usize,
-    col: usize,
-    n: usize,
-    params: &SigmaClipParams,
-    pixel_values: &mut [f32],
-    mask: &mut [bool],
-    out: &mut f32,
-) {
-    for (i, frame) in frames.iter().enumerate() {
-        pixel_values[i] = frame.data[[row, col]];
-        mask[i] = true;
-    }
+/// Streaming sigma-clipped mean stack, matching [`sigma_clip_stack`] (and
+/// its quality-weighted form) without holding the frames.
+///
+/// Each pass accumulates running statistics of the values inside the current
+/// bounds; the bounds for the next pass are their mean ± `sigma` standard
+/// deviations, intersected with the current ones. As in [`rejection_stack`], the statistics are unweighted and
+/// the quality weights only apply to the surviving values. Takes
+/// `iterations + 1` passes over the frames.
+pub struct StreamingSigmaClipStacker {
+    pixels: Array2<ClipAccumulator>,
+    params: SigmaClipParams,
+    pass: usize,
+    count: usize,
+    bit_depth: u8,
+}
 
-    for _ in 0..params.iterations {
-        let (mean, stddev) = mean_stddev(pixel_values, mask);
-        if stddev < EPSILON {
-            break;
+impl StreamingSigmaClipStacker {
+    pub fn new(height: usize, width: usize, bit_depth: u8, params: &SigmaClipParams) -> Self {
+        Self {
+            pixels: Array2::default((height, width)),
+            params: params.clone(),
+            pass: 0,
+            count: 0,
+            bit_depth,
         }
-        let lo = mean - params.sigma * stddev;
-        let hi = mean + params.sigma * stddev;
-        for i in 0..n {
-            if mask[i] && (pixel_values[i] < lo || pixel_values[i] > hi) {
-                mask[i] = false;
-            }
+    }
+
+    /// Add one frame to the current pass with a quality weight (negative
+    /// weights count as zero).
+    pub fn add_weighted(&mut self, frame: &Frame, weight: f32) {
+        let weight = weight.max(0.0);
+        if self.pass == 0 {
+            self.count += 1;
         }
+        Zip::from(&mut self.pixels)
+            .and(&frame.data)
+            .par_for_each(|p, &v| {
+                if v < p.lo || v > p.hi {
+                    return;
+                }
+                p.count += 1.0;
+                let delta = v - p.mean;
+                p.mean += delta / p.count;
+                p.m2 += delta * (v - p.mean);
+                p.sum += weight * v;
+                p.weight += weight;
+            });
     }
 
-    let mut sum = 0.0f32;
-    let mut count = 0u32;
-    for i in 0..n {
-        if mask[i] {
-            sum += pixel_values[i];
-            count += 1;
+    /// Close the current pass, deriving the next pass's bounds unless this
+    /// was the last.
+    pub fn end_pass(&mut self) {
+        let first = self.pass == 0;
+        let last = self.pass == self.params.iterations;
+        let sigma = self.params.sigma;
+        if !last {
+            self.pixels.par_map_inplace(|p| {
+                if first {
+                    p.fallback = p.value();
+                }
+                let stddev = if p.count > 0.0 {
+                    (p.m2 / p.count).max(0.0).sqrt()
+                } else {
+                    0.0
+                };
+                // A flat stack has nothing to reject; keep its bounds. Bounds
+                // only ever narrow, so a rejected value stays rejected.
+                if stddev >= EPSILON {
+                    p.lo = p.lo.max(p.mean - sigma * stddev);
+                    p.hi = p.hi.min(p.mean + sigma * stddev);
+                }
+                *p = ClipAccumulator {
+                    lo: p.lo,
+                    hi: p.hi,
+                    fallback: p.fallback,
+                    ..Default::default()
+                };
+            });
         }
+        self.pass += 1;
     }
 
-    *out = if count > 0 {
-        sum / count as f32
-    } else {
-        pixel_values.iter().sum::<f32>() / n as f32
-    };
+    /// Produce the clipped mean after the last pass.
+    pub fn finalize(self) -> Result<Frame> {
+        if self.count == 0 {
+            return Err(JupiterError::EmptySequence);
+        }
+        if self.pass <= self.params.iterations {
+            return Err(JupiterError::Pipeline(format!(
+                "Streaming sigma clip finalized after {} of {} passes",
+                self.pass,
+                self.params.iterations + 1
+            )));
+        }
+        Ok(Frame::new(self.pixels.mapv(|p| p.value()), self.bit_depth))
+    }
 }
 
-fn mean_stddev(values: &[f32], mask: &[bool]) -> (f32, f32) {
-    let mut sum = 0.0f32;
-    let mut count = 0u32;
-    for (i, &v) in values.iter().enumerate() {
-        if mask[i] {
-            sum += v;
-            count += 1;
-        }
+impl MultiPassStacker for StreamingSigmaClipStacker {
+    fn passes(&self) -> usize {
+        self.params.iterations + 1
     }
-    if count == 0 {
-        return (0.0, 0.0);
+
+    fn add_weighted(&mut self, frame: &Frame, weight: f32) {
+        StreamingSigmaClipStacker::add_weighted(self, frame, weight);
     }
-    let mean = sum / count as f32;
 
-    let mut var_sum = 0.0f32;
-    for (i, &v) in values.iter().enumerate() {
-        if mask[i] {
-            let d = v - mean;
-            var_sum += d * d;
-        }
+    fn end_pass(&mut self) {
+        StreamingSigmaClipStacker::end_pass(self);
+    }
+
+    fn finalize(self) -> Result<Frame> {
+        StreamingSigmaClipStacker::finalize(self)
     }
-    let stddev = (var_sum / count as f32).sqrt();
-    (mean, stddev)
 }
@@ -0,0 +1,59 @@
+//! Stacking with memory bounded by the frame size, not the frame count.
+//!
+//! A [`MultiPassStacker`] sees the aligned frames one at a time and keeps
+//! only per-pixel accumulators. Robust statistics need more than one look at
+//! the data, so such stackers ask for several passes over the same frames;
+//! the caller re-reads (and re-shifts) each frame per pass instead of
+//! holding them all.
+
+use crate::error::Result;
+use crate::frame::Frame;
+use crate::stack::rejection::frame_weights;
+
+/// A stacker fed one frame at a time, possibly over several passes.
+pub trait MultiPassStacker {
+    /// How many times every frame must be streamed through
+    /// [`add_weighted`](Self::add_weighted).
+    fn passes(&self) -> usize;
+
+    /// Add one frame to the current pass with its quality weight.
+    fn add_weighted(&mut self, frame: &Frame, weight: f32);
+
+    /// Close the current pass once every frame has been added.
+    fn end_pass(&mut self);
+
+    /// Produce the stacked frame after the last pass.
+    fn finalize(self) -> Result<Frame>;
+}
+
+/// Drive `stacker` through all its passes over `count` frames, loading
+/// frame `i` with `load(i)` each time it is needed.
+///
+/// `weights` are per-frame quality scores (uniform when `None`; negative
+/// scores count as zero and all-zero scores fall back to uniform).
+/// `on_progress` receives the cumulative number of frames added, across
+/// passes, out of `stacker.passes() * count`.
+pub fn stack_streaming<S: MultiPassStacker>(
+    mut stacker: S,
+    count: usize,
+    weights: Option<&[f64]>,
+    mut load: impl FnMut(usize) -> Result<Frame>,
+    on_progress: impl Fn(usize),
+) -> Result<Frame> {
+    let mut weights = frame_weights(count, weights)?;
+    if weights.iter().sum::<f32>() <= 0.0 {
+        weights.fill(1.0);
+    }
+
+    let mut done = 0;
+    for _ in 0..stacker.passes() {
+        for (i, &weight) in weights.iter().enumerate() {
+            let frame = load(i)?;
+            stacker.add_weighted(&frame, weight);
+            done += 1;
+            on_progress(done);
+        }
+        stacker.end_pass();
+    }
+    stacker.finalize()
+}
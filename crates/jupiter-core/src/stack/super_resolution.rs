@@ -0,0 +1,294 @@
+//! Multi-frame super-resolution reconstruction.
+//!
+//! Drizzle redistributes input pixels onto a finer grid but never undoes the
+//! blur or the aliasing of the undersampled frames. Here the output is the
+//! image that best explains every selected frame under an explicit forward
+//! model — blur with a PSF, shift by the frame's sub-pixel alignment offset,
+//! sample at the input resolution — with a Laplacian smoothness prior
+//! (regularised MAP, solved by iterative back-projection).
+//!
+//! The PSF convolutions run on the [`ComputeBackend`] FFT primitives, so they
+//! move to the GPU when one is available; the sampling and back-projection
+//! steps are per-pixel and stay on the CPU.
+
+use ndarray::{Array2, Zip};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::align::phase_correlation::bilinear_sample;
+use crate::compute::{ComputeBackend, GpuBuffer};
+use crate::consts::{
+    DEFAULT_SR_ITERATIONS, DEFAULT_SR_PSF_SIGMA, DEFAULT_SR_REGULARIZATION, DEFAULT_SR_SCALE,
+    SR_MAX_SCALE,
+};
+use crate::error::{JupiterError, Result};
+use crate::frame::{AlignmentOffset, Frame};
+use crate::pipeline::config::PsfModel;
+use crate::sharpen::deconvolution::{rewrap_psf_padded, Psf};
+
+/// Configuration for multi-frame super-resolution stacking.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SuperResolutionConfig {
+    /// Output upscale factor (1.0–4.0, default: 2.0).
+    pub scale: f32,
+    /// Back-projection iterations (default: 20).
+    pub iterations: usize,
+    /// Blur of the forward model, in output pixels. Symmetric models only:
+    /// the back-projection reuses the PSF as its own adjoint.
+    pub psf: PsfModel,
+    /// Weight of the smoothness prior (default: 0.01; 0 = plain
+    /// back-projection). Higher values suppress noise and ringing at the cost
+    /// of fine detail.
+    pub regularization: f32,
+    /// Weight each frame by its quality score.
+    #[serde(default = "default_true")]
+    pub quality_weighted: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for SuperResolutionConfig {
+    fn default() -> Self {
+        Self {
+            scale: DEFAULT_SR_SCALE,
+            iterations: DEFAULT_SR_ITERATIONS,
+            psf: PsfModel::Gaussian {
+                sigma: DEFAULT_SR_PSF_SIGMA,
+            },
+            regularization: DEFAULT_SR_REGULARIZATION,
+            quality_weighted: true,
+        }
+    }
+}
+
+/// Reconstruct a `scale`× image from aligned-offset frames.
+///
+/// `offsets[k]` is frame `k`'s alignment offset as returned by
+/// [`compute_offset`](crate::align::compute_offset) and applied by
+/// [`shift_frame`](crate::align::shift_frame): input pixel `(r, c)` of frame
+/// `k` lies at output position `((r + dy) * scale, (c + dx) * scale)`.
+///
+/// Returns a `Frame` of `(ceil(h * scale), ceil(w * scale))`.
+pub fn super_resolve(
+    frames: &[Frame],
+    offsets: &[AlignmentOffset],
+    config: &SuperResolutionConfig,
+    quality_scores: Option<&[f64]>,
+    backend: &dyn ComputeBackend,
+) -> Result<Frame> {
+    super_resolve_with_progress(frames, offsets, config, quality_scores, backend, |_| {})
+}
+
+/// [`super_resolve`] with progress reporting: `on_progress` is called with
+/// the number of iterations completed.
+pub fn super_resolve_with_progress(
+    frames: &[Frame],
+    offsets: &[AlignmentOffset],
+    config: &SuperResolutionConfig,
+    quality_scores: Option<&[f64]>,
+    backend: &dyn ComputeBackend,
+    on_progress: impl Fn(usize) + Send + Sync,
+) -> Result<Frame> {
+    if frames.is_empty() {
+        return Err(JupiterError::EmptySequence);
+    }
+    if frames.len() != offsets.len() {
+        return Err(JupiterError::Pipeline(
+            "Frame count must match offset count".into(),
+        ));
+    }
+    if !(1.0..=SR_MAX_SCALE).contains(&config.scale) {
+        return Err(JupiterError::Pipeline(format!(
+            "Invalid super-resolution scale: {} (must be in [1.0, {SR_MAX_SCALE}])",
+            config.scale
+        )));
+    }
+    let (h, w) = frames[0].data.dim();
+    if frames.iter().any(|f| f.data.dim() != (h, w)) {
+        return Err(JupiterError::Pipeline("Frame size mismatch".into()));
+    }
+
+    let scale = config.scale as f64;
+    let out_dims = (
+        (h as f64 * scale).ceil() as usize,
+        (w as f64 * scale).ceil() as usize,
+    );
+    let weights = frame_weights(frames.len(), config.quality_weighted, quality_scores);
+    let observations = Observations {
+        frames,
+        offsets,
+        weights: &weights,
+        scale,
+        out_dims,
+    };
+    info!(
+        frames = frames.len(),
+        scale = config.scale,
+        iterations = config.iterations,
+        "Super-resolution reconstruction"
+    );
+
+    let coverage = observations.back_project(|_, _| 1.0);
+    let mut estimate = observations.shift_and_add();
+    let blur = Blur::new(backend, &config.psf, &estimate)?;
+    let lambda = config.regularization.max(0.0);
+
+    for iteration in 0..config.iterations {
+        let blurred = blur.apply(&estimate);
+        let residual =
+            observations.back_project(|value, (y, x)| value - bilinear_sample(&blurred, y, x));
+        let normalized = Zip::from(&residual).and(&coverage).map_collect(|&r, &c| {
+            if c > f32::EPSILON {
+                r / c
+            } else {
+                0.0
+            }
+        });
+        let correction = blur.apply(&normalized);
+        let smoothness = laplacian(&estimate);
+        Zip::from(&mut estimate)
+            .and(&correction)
+            .and(&smoothness)
+            .for_each(|e, &c, &l| *e = (*e + c + lambda * l).clamp(0.0, 1.0));
+        on_progress(iteration + 1);
+    }
+
+    Ok(Frame::new(estimate, frames[0].original_bit_depth))
+}
+
+/// Per-frame weights: quality scores when requested, else uniform.
+fn frame_weights(count: usize, quality_weighted: bool, scores: Option<&[f64]>) -> Vec<f32> {
+    match scores {
+        Some(scores) if quality_weighted && scores.iter().any(|&s| s > 0.0) => {
+            scores.iter().map(|&s| s.max(0.0) as f32).collect()
+        }
+        _ => vec![1.0; count],
+    }
+}
+
+/// The input frames and where each of their pixels lands on the output grid.
+struct Observations<'a> {
+    frames: &'a [Frame],
+    offsets: &'a [AlignmentOffset],
+    weights: &'a [f32],
+    scale: f64,
+    out_dims: (usize, usize),
+}
+
+impl Observations<'_> {
+    /// Output-grid position of input pixel `(row, col)` of frame `k`, or
+    /// `None` when it falls outside the output grid.
+    fn project(&self, k: usize, row: usize, col: usize) -> Option<(f64, f64)> {
+        let y = (row as f64 + self.offsets[k].dy) * self.scale;
+        let x = (col as f64 + self.offsets[k].dx) * self.scale;
+        let (oh, ow) = self.out_dims;
+        (y >= 0.0 && x >= 0.0 && y <= (oh - 1) as f64 && x <= (ow - 1) as f64).then_some((y, x))
+    }
+
+    /// `Σ_k w_k · Dₖᵀ v_k`: spread `value(pixel, position)` of every input
+    /// pixel onto its four output neighbours with bilinear weights — the
+    /// adjoint of sampling the output grid with [`bilinear_sample`].
+    fn back_project<V>(&self, value: V) -> Array2<f32>
+    where
+        V: Fn(f32, (f64, f64)) -> f32 + Sync,
+    {
+        let (oh, ow) = self.out_dims;
+        (0..self.frames.len())
+            .into_par_iter()
+            .fold(
+                || Array2::<f32>::zeros((oh, ow)),
+                |mut acc, k| {
+                    let weight = self.weights[k];
+                    if weight <= 0.0 {
+                        return acc;
+                    }
+                    for ((row, col), &v) in self.frames[k].data.indexed_iter() {
+                        let Some((y, x)) = self.project(k, row, col) else {
+                            continue;
+                        };
+                        let e = weight * value(v, (y, x));
+                        let (y0, x0) = (y.floor() as usize, x.floor() as usize);
+                        let (fy, fx) = ((y - y0 as f64) as f32, (x - x0 as f64) as f32);
+                        let (y1, x1) = ((y0 + 1).min(oh - 1), (x0 + 1).min(ow - 1));
+                        acc[[y0, x0]] += e * (1.0 - fy) * (1.0 - fx);
+                        acc[[y0, x1]] += e * (1.0 - fy) * fx;
+                        acc[[y1, x0]] += e * fy * (1.0 - fx);
+                        acc[[y1, x1]] += e * fy * fx;
+                    }
+                    acc
+                },
+            )
+            .reduce(|| Array2::zeros((oh, ow)), |a, b| a + b)
+    }
+
+    /// Starting estimate: every frame resampled onto the output grid and
+    /// averaged with its weight.
+    fn shift_and_add(&self) -> Array2<f32> {
+        let (oh, ow) = self.out_dims;
+        let total: f32 = self.weights.iter().sum();
+        let (h, w) = self.frames[0].data.dim();
+        let (max_y, max_x) = ((h - 1) as f64, (w - 1) as f64);
+        let mut out = Array2::<f32>::zeros((oh, ow));
+        out.axis_iter_mut(ndarray::Axis(0))
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(row, mut line)| {
+                for (col, px) in line.iter_mut().enumerate() {
+                    let mut sum = 0.0;
+                    for (k, frame) in self.frames.iter().enumerate() {
+                        // Clamp so the uncovered output border repeats the edge
+                        // instead of fading to black.
+                        let y = (row as f64 / self.scale - self.offsets[k].dy).clamp(0.0, max_y);
+                        let x = (col as f64 / self.scale - self.offsets[k].dx).clamp(0.0, max_x);
+                        sum += self.weights[k] * bilinear_sample(&frame.data, y, x);
+                    }
+                    *px = sum / total;
+                }
+            });
+        out
+    }
+}
+
+/// FFT convolution with a fixed PSF on the compute backend.
+struct Blur<'a> {
+    backend: &'a dyn ComputeBackend,
+    otf: GpuBuffer,
+    dims: (usize, usize),
+}
+
+impl<'a> Blur<'a> {
+    fn new(backend: &'a dyn ComputeBackend, psf: &PsfModel, sample: &Array2<f32>) -> Result<Self> {
+        let dims = sample.dim();
+        // Backends may zero-pad to their preferred FFT size; lay the PSF's
+        // wrap-around out at whatever size the transform actually uses.
+        let spectrum = backend.fft2d(&backend.upload(sample));
+        let psf = Psf::load(psf)?.for_image(sample)?.generate(dims)?;
+        let psf = rewrap_psf_padded(&psf, spectrum.height, spectrum.width);
+        let otf = backend.fft2d(&backend.upload(&psf));
+        Ok(Self { backend, otf, dims })
+    }
+
+    fn apply(&self, data: &Array2<f32>) -> Array2<f32> {
+        let spectrum = self.backend.fft2d(&self.backend.upload(data));
+        let product = self.backend.complex_mul(&spectrum, &self.otf);
+        let (h, w) = self.dims;
+        self.backend
+            .download(&self.backend.ifft2d_real(&product, h, w))
+    }
+}
+
+/// Five-point Laplacian with replicated borders.
+fn laplacian(data: &Array2<f32>) -> Array2<f32> {
+    let (h, w) = data.dim();
+    Array2::from_shape_fn((h, w), |(r, c)| {
+        let v = data[[r, c]];
+        data[[r.saturating_sub(1), c]]
+            + data[[(r + 1).min(h - 1), c]]
+            + data[[r, c.saturating_sub(1)]]
+            + data[[r, (c + 1).min(w - 1)]]
+            - 4.0 * v
+    })
+}
@@ -1,21 +1,27 @@
 use std::collections::HashMap;
 
 use ndarray::Array2;
-use rayon::prelude::*;
 use tracing::info;
 
 use crate::align::phase_correlation::{bilinear_sample, compute_offset_with_confidence};
 use crate::color::debayer::DebayerMethod;
 use crate::color::process::{read_color_frame, read_luminance_frame};
-use crate::consts::{MEAN_REFERENCE_KEEP_FRACTION, MIN_CORRELATION_CONFIDENCE};
+use crate::compute::ComputeBackend;
+use crate::consts::{
+    DEFAULT_WARP_SPLINE_SMOOTHING, MEAN_REFERENCE_KEEP_FRACTION, MIN_CORRELATION_CONFIDENCE,
+    WARP_SPLINE_EVAL_STEP,
+};
 use crate::error::{JupiterError, Result};
 use crate::frame::{AlignmentOffset, ColorFrame, ColorMode, Frame};
 use crate::io::ser::SerReader;
+use crate::pipeline::config::{AlignmentConfig, QualityMetric};
 use crate::quality::score_with_metric;
+use crate::stack::ap_diagnostics::{ApDiagnostics, ApTally};
 use crate::stack::ap_grid::{
     build_ap_grid, extract_region, extract_region_shifted, ApGrid, MultiPointConfig,
 };
-use crate::stack::reference::{build_mean_reference, build_mean_reference_color};
+use crate::stack::global::{global_align, GlobalAlignment};
+use crate::stack::thin_plate::ThinPlateSpline;
 
 /// Configuration for surface-model warping stacking.
 ///
@@ -35,6 +41,48 @@ pub struct SurfaceWarpConfig {
     pub min_brightness: f32,
     /// Quality metric for frame scoring.
     pub quality_metric: crate::pipeline::config::QualityMetric,
+    /// How per-AP shifts become a per-pixel deformation field.
+    #[serde(default)]
+    pub field_model: ShiftFieldModel,
+    /// Explicit frames to stack (e.g. from a frame report) instead of the
+    /// best `select_percentage`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames: Option<Vec<usize>>,
+}
+
+/// Model used to turn per-AP shifts into a per-pixel deformation field.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ShiftFieldModel {
+    /// Bilinear interpolation on the AP grid; APs without a reliable shift
+    /// fall back to the global offset.
+    #[default]
+    Bilinear,
+    /// Thin-plate spline fitted to the reliable AP shifts, with outlying
+    /// shifts down-weighted. Smooth everywhere and free of grid artefacts.
+    ThinPlateSpline {
+        /// Regularisation in AP-normalised units (0 = interpolate exactly).
+        smoothing: f32,
+    },
+}
+
+impl ShiftFieldModel {
+    /// Thin-plate spline with the default smoothing.
+    pub fn thin_plate_spline() -> Self {
+        Self::ThinPlateSpline {
+            smoothing: DEFAULT_WARP_SPLINE_SMOOTHING,
+        }
+    }
+}
+
+impl std::fmt::Display for ShiftFieldModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bilinear => write!(f, "Bilinear"),
+            Self::ThinPlateSpline { smoothing } => {
+                write!(f, "Thin-plate spline (smoothing={smoothing:.3})")
+            }
+        }
+    }
 }
 
 impl Default for SurfaceWarpConfig {
@@ -45,6 +93,8 @@ impl Default for SurfaceWarpConfig {
             select_percentage: 0.25,
             min_brightness: 0.05,
             quality_metric: crate::pipeline::config::QualityMetric::Laplacian,
+            field_model: ShiftFieldModel::default(),
+            frames: None,
         }
     }
 }
@@ -160,6 +210,115 @@ pub fn interpolate_shift_field(
     (field_dy, field_dx)
 }
 
+/// Fit a thin-plate spline to the per-AP shifts and evaluate it as a
+/// per-pixel deformation field.
+///
+/// Only APs present in `local_offsets` (those that passed the confidence
+/// check) constrain the spline; gaps are bridged smoothly instead of
+/// snapping to the global offset. Outlying shifts are down-weighted (see
+/// [`ShiftFieldModel::ThinPlateSpline`]) and the local part of the field is
+/// capped at the largest accepted AP shift, so the affine extrapolation off
+/// the disk stays bounded. With fewer than three usable APs this falls back
+/// to their mean shift.
+///
+/// Returns `(shift_y, shift_x)` as [`interpolate_shift_field`] does.
+pub fn spline_shift_field(
+    grid: &ApGrid,
+    local_offsets: &HashMap<usize, AlignmentOffset>,
+    global_offset: &AlignmentOffset,
+    h: usize,
+    w: usize,
+    smoothing: f32,
+) -> (Array2<f64>, Array2<f64>) {
+    let measured: Vec<_> = grid
+        .points
+        .iter()
+        .filter_map(|ap| {
+            let local = local_offsets.get(&ap.index)?;
+            Some(((ap.cy as f64, ap.cx as f64), [local.dy, local.dx]))
+        })
+        .collect();
+    let (points, values): (Vec<_>, Vec<_>) = measured.into_iter().unzip();
+
+    let fitted = ThinPlateSpline::fit_robust(
+        &points,
+        &values,
+        smoothing.max(0.0) as f64,
+        grid.ap_size.max(1) as f64,
+    );
+    let Some((spline, weights)) = fitted else {
+        let n = values.len().max(1) as f64;
+        let (dy, dx) = values
+            .iter()
+            .fold((0.0, 0.0), |(y, x), v| (y + v[0] / n, x + v[1] / n));
+        return (
+            Array2::from_elem((h, w), global_offset.dy + dy),
+            Array2::from_elem((h, w), global_offset.dx + dx),
+        );
+    };
+    let cap = values
+        .iter()
+        .zip(&weights)
+        .filter(|(_, &wt)| wt > 0.0)
+        .fold(0.0f64, |m, (v, _)| m.max(v[0].hypot(v[1])));
+
+    // Evaluate on a coarse lattice reaching one step past the last pixel,
+    // then interpolate: the spline is far smoother than the lattice spacing.
+    let step = WARP_SPLINE_EVAL_STEP;
+    let (lh, lw) = ((h.max(1) - 1) / step + 2, (w.max(1) - 1) / step + 2);
+    let lattice = Array2::from_shape_fn((lh, lw), |(r, c)| {
+        let [dy, dx] = spline.eval((r * step) as f64, (c * step) as f64);
+        let len = dy.hypot(dx);
+        if len > cap {
+            [dy * cap / len, dx * cap / len]
+        } else {
+            [dy, dx]
+        }
+    });
+
+    let mut field_dy = Array2::<f64>::zeros((h, w));
+    let mut field_dx = Array2::<f64>::zeros((h, w));
+    for row in 0..h {
+        let (r0, fy) = (row / step, (row % step) as f64 / step as f64);
+        for col in 0..w {
+            let (c0, fx) = (col / step, (col % step) as f64 / step as f64);
+            let corners = [
+                (&lattice[[r0, c0]], (1.0 - fy) * (1.0 - fx)),
+                (&lattice[[r0, c0 + 1]], (1.0 - fy) * fx),
+                (&lattice[[r0 + 1, c0]], fy * (1.0 - fx)),
+                (&lattice[[r0 + 1, c0 + 1]], fy * fx),
+            ];
+            let (mut dy, mut dx) = (0.0, 0.0);
+            for (v, wt) in corners {
+                dy += v[0] * wt;
+                dx += v[1] * wt;
+            }
+            field_dy[[row, col]] = global_offset.dy + dy;
+            field_dx[[row, col]] = global_offset.dx + dx;
+        }
+    }
+    (field_dy, field_dx)
+}
+
+/// Per-pixel deformation field for one frame under `model`.
+fn shift_field(
+    model: &ShiftFieldModel,
+    grid: &ApGrid,
+    local_offsets: &HashMap<usize, AlignmentOffset>,
+    global_offset: &AlignmentOffset,
+    h: usize,
+    w: usize,
+) -> (Array2<f64>, Array2<f64>) {
+    match *model {
+        ShiftFieldModel::Bilinear => {
+            interpolate_shift_field(grid, local_offsets, global_offset, h, w)
+        }
+        ShiftFieldModel::ThinPlateSpline { smoothing } => {
+            spline_shift_field(grid, local_offsets, global_offset, h, w, smoothing)
+        }
+    }
+}
+
 /// Find the bracketing interval and interpolation fraction for `val` in a
 /// sorted list of positions.
 fn find_interval(positions: &[usize], val: usize) -> (usize, usize, f64) {
@@ -244,20 +403,61 @@ fn compute_local_shifts(
     shifts
 }
 
+/// Record one frame's local alignment results and AP quality in `tallies`.
+fn tally_frame(
+    tallies: &mut [ApTally],
+    grid: &ApGrid,
+    frame_data: &Array2<f32>,
+    frame_idx: usize,
+    global_offset: &AlignmentOffset,
+    local_shifts: &HashMap<usize, AlignmentOffset>,
+    metric: &QualityMetric,
+) {
+    for ap in &grid.points {
+        let region = extract_region_shifted(frame_data, ap.cy, ap.cx, ap.size / 2, global_offset);
+        let tally = &mut tallies[ap.index];
+        tally.select(score_with_metric(&region, metric));
+        match local_shifts.get(&ap.index) {
+            Some(local) => tally.accept(local),
+            None => tally.reject(frame_idx),
+        }
+    }
+}
+
 /// Top-level orchestrator for surface-model warping stacking (mono).
 ///
 /// Pipeline:
-/// 1. Global align all frames vs frame 0
+/// 1. Global align all frames vs the best-scoring frame with the configured
+///    method, rejecting low-confidence frames
 /// 2. Build mean reference from top-quality frames
 /// 3. Build AP grid on mean reference
-/// 4. Score all frames globally, select top N%
-/// 5. For each selected frame: compute local shifts → interpolate → warp → accumulate
+/// 4. Select the top N% of the kept frames by global quality
+/// 5. For each selected frame: compute local shifts → deformation field
+///    (bilinear or thin-plate spline) → warp → accumulate
 /// 6. Quality-weighted mean of all warped frames
 pub fn surface_warp_stack<F>(
     reader: &SerReader,
     config: &SurfaceWarpConfig,
-    mut on_progress: F,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+    on_progress: F,
 ) -> Result<Frame>
+where
+    F: FnMut(f32),
+{
+    surface_warp_stack_with_diagnostics(reader, config, alignment, backend, on_progress)
+        .map(|(frame, _)| frame)
+}
+
+/// Same as [`surface_warp_stack`], additionally returning the AP grid with
+/// per-AP frame usage, shifts and quality.
+pub fn surface_warp_stack_with_diagnostics<F>(
+    reader: &SerReader,
+    config: &SurfaceWarpConfig,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+    on_progress: F,
+) -> Result<(Frame, ApDiagnostics)>
 where
     F: FnMut(f32),
 {
@@ -266,35 +466,41 @@ where
         return Err(JupiterError::EmptySequence);
     }
 
-    let reference = reader.read_frame(0)?;
-    let (h, w) = reference.data.dim();
-
     // Step 1: Global alignment
     info!("Surface warp: global alignment of {} frames", total_frames);
-    let rest_offsets: Vec<Result<AlignmentOffset>> = (1..total_frames)
-        .into_par_iter()
-        .map(|i| {
-            let frame = reader.read_frame(i)?;
-            crate::align::phase_correlation::compute_offset(&reference, &frame)
-        })
-        .collect();
+    let read = |i: usize| Ok(reader.read_frame(i)?.data);
+    let global = global_align(
+        total_frames,
+        config.frames.as_deref(),
+        read,
+        &config.quality_metric,
+        alignment,
+        backend,
+    )?;
+    surface_warp_stack_from_global(reader, config, global, on_progress)
+}
 
-    let mut global_offsets = Vec::with_capacity(total_frames);
-    global_offsets.push(AlignmentOffset::default());
-    for r in rest_offsets {
-        global_offsets.push(r?);
-    }
+/// [`surface_warp_stack`] on frames already registered by [`global_align`] (or
+/// restored from a session cache).
+pub(crate) fn surface_warp_stack_from_global<F>(
+    reader: &SerReader,
+    config: &SurfaceWarpConfig,
+    global: GlobalAlignment,
+    mut on_progress: F,
+) -> Result<(Frame, ApDiagnostics)>
+where
+    F: FnMut(f32),
+{
+    let read = |i: usize| Ok(reader.read_frame(i)?.data);
+    let global_offsets = &global.offsets;
+    let reference = reader.read_frame(global.reference_idx)?;
+    let (h, w) = reference.data.dim();
     on_progress(0.1);
 
     // Step 2: Mean reference
     let mp_config = to_mp_config(config);
     info!("Surface warp: building mean reference");
-    let mean_ref = build_mean_reference(
-        reader,
-        &global_offsets,
-        &config.quality_metric,
-        MEAN_REFERENCE_KEEP_FRACTION,
-    )?;
+    let mean_ref = global.mean_reference(MEAN_REFERENCE_KEEP_FRACTION, read)?;
     on_progress(0.2);
 
     // Step 3: AP grid
@@ -304,8 +510,8 @@ where
         return Err(JupiterError::Pipeline("No alignment points created".into()));
     }
 
-    // Step 4: Score + select
-    let selected = score_and_select_frames(reader, &global_offsets, config)?;
+    // Step 4: Select
+    let selected = global.best_frames(global.selection_fraction(config.select_percentage));
     let frame_count = selected.len();
     info!("Surface warp: selected {} frames", frame_count);
     on_progress(0.3);
@@ -314,6 +520,7 @@ where
     info!("Surface warp: warping and stacking {} frames", frame_count);
     let mut accumulator = Array2::<f64>::zeros((h, w));
     let mut total_weight: f64 = 0.0;
+    let mut tallies = vec![ApTally::default(); grid.points.len()];
 
     for (i, &(frame_idx, quality_score)) in selected.iter().enumerate() {
         let frame = reader.read_frame(frame_idx)?;
@@ -325,9 +532,24 @@ where
             &global_offsets[frame_idx],
             config.search_radius,
         );
+        tally_frame(
+            &mut tallies,
+            &grid,
+            &frame.data,
+            frame_idx,
+            &global_offsets[frame_idx],
+            &local_shifts,
+            &config.quality_metric,
+        );
 
-        let (field_dy, field_dx) =
-            interpolate_shift_field(&grid, &local_shifts, &global_offsets[frame_idx], h, w);
+        let (field_dy, field_dx) = shift_field(
+            &config.field_model,
+            &grid,
+            &local_shifts,
+            &global_offsets[frame_idx],
+            h,
+            w,
+        );
 
         let warped = warp_frame(&frame.data, &field_dy, &field_dx);
 
@@ -349,17 +571,49 @@ where
     };
 
     on_progress(1.0);
-    Ok(Frame::new(result, reference.original_bit_depth))
+    let diagnostics = ApDiagnostics::from_tallies(&grid, (h, w), tallies);
+    Ok((
+        Frame::new(result, reference.original_bit_depth),
+        diagnostics,
+    ))
 }
 
 /// Top-level orchestrator for surface-model warping stacking (color).
 pub fn surface_warp_stack_color<F>(
     reader: &SerReader,
     config: &SurfaceWarpConfig,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
     color_mode: &ColorMode,
     debayer_method: &DebayerMethod,
-    mut on_progress: F,
+    on_progress: F,
 ) -> Result<ColorFrame>
+where
+    F: FnMut(f32),
+{
+    surface_warp_stack_color_with_diagnostics(
+        reader,
+        config,
+        alignment,
+        backend,
+        color_mode,
+        debayer_method,
+        on_progress,
+    )
+    .map(|(frame, _)| frame)
+}
+
+/// Same as [`surface_warp_stack_color`], additionally returning the AP grid
+/// with per-AP frame usage, shifts and quality.
+pub fn surface_warp_stack_color_with_diagnostics<F>(
+    reader: &SerReader,
+    config: &SurfaceWarpConfig,
+    alignment: &AlignmentConfig,
+    backend: &dyn ComputeBackend,
+    color_mode: &ColorMode,
+    debayer_method: &DebayerMethod,
+    on_progress: F,
+) -> Result<(ColorFrame, ApDiagnostics)>
 where
     F: FnMut(f32),
 {
@@ -368,43 +622,56 @@ where
         return Err(JupiterError::EmptySequence);
     }
 
-    // Step 1: Get luminance reference for alignment
-    let ref_color = read_color_frame(reader, 0, color_mode, debayer_method)?;
-    let ref_lum = crate::color::debayer::luminance(&ref_color);
-    let (h, w) = ref_lum.data.dim();
-    let bit_depth = ref_color.red.original_bit_depth;
-
-    // Step 2: Global alignment on luminance
+    // Step 1: Global alignment on luminance
     info!(
         "Surface warp color: global alignment of {} frames",
         total_frames
     );
-    let rest_offsets: Vec<Result<AlignmentOffset>> = (1..total_frames)
-        .into_par_iter()
-        .map(|i| {
-            let lum = read_luminance_frame(reader, i, color_mode, debayer_method)?;
-            crate::align::phase_correlation::compute_offset(&ref_lum, &lum)
-        })
-        .collect();
+    let read = |i: usize| Ok(read_luminance_frame(reader, i, color_mode, debayer_method)?.data);
+    let global = global_align(
+        total_frames,
+        config.frames.as_deref(),
+        read,
+        &config.quality_metric,
+        alignment,
+        backend,
+    )?;
+    surface_warp_stack_color_from_global(
+        reader,
+        config,
+        global,
+        color_mode,
+        debayer_method,
+        on_progress,
+    )
+}
 
-    let mut global_offsets = Vec::with_capacity(total_frames);
-    global_offsets.push(AlignmentOffset::default());
-    for r in rest_offsets {
-        global_offsets.push(r?);
-    }
+/// [`surface_warp_stack_color`] on frames already registered by [`global_align`] (or
+/// restored from a session cache).
+pub(crate) fn surface_warp_stack_color_from_global<F>(
+    reader: &SerReader,
+    config: &SurfaceWarpConfig,
+    global: GlobalAlignment,
+    color_mode: &ColorMode,
+    debayer_method: &DebayerMethod,
+    mut on_progress: F,
+) -> Result<(ColorFrame, ApDiagnostics)>
+where
+    F: FnMut(f32),
+{
+    let read = |i: usize| Ok(read_luminance_frame(reader, i, color_mode, debayer_method)?.data);
+    let global_offsets = &global.offsets;
+
+    // Step 2: Reference frame size and bit depth
+    let ref_color = read_color_frame(reader, global.reference_idx, color_mode, debayer_method)?;
+    let (h, w) = ref_color.red.data.dim();
+    let bit_depth = ref_color.red.original_bit_depth;
     on_progress(0.1);
 
     // Step 3: Mean reference (luminance)
     let mp_config = to_mp_config(config);
     info!("Surface warp color: building mean reference");
-    let mean_ref = build_mean_reference_color(
-        reader,
-        &global_offsets,
-        &config.quality_metric,
-        MEAN_REFERENCE_KEEP_FRACTION,
-        color_mode,
-        debayer_method,
-    )?;
+    let mean_ref = global.mean_reference(MEAN_REFERENCE_KEEP_FRACTION, read)?;
     on_progress(0.2);
 
     // Step 4: AP grid
@@ -414,9 +681,8 @@ where
         return Err(JupiterError::Pipeline("No alignment points created".into()));
     }
 
-    // Step 5: Score + select (on luminance)
-    let selected =
-        score_and_select_frames_color(reader, &global_offsets, config, color_mode, debayer_method)?;
+    // Step 5: Select (scored on luminance)
+    let selected = global.best_frames(global.selection_fraction(config.select_percentage));
     let frame_count = selected.len();
     info!("Surface warp color: selected {} frames", frame_count);
     on_progress(0.3);
@@ -426,6 +692,7 @@ where
     let mut acc_g = Array2::<f64>::zeros((h, w));
     let mut acc_b = Array2::<f64>::zeros((h, w));
     let mut total_weight: f64 = 0.0;
+    let mut tallies = vec![ApTally::default(); grid.points.len()];
 
     for (i, &(frame_idx, quality_score)) in selected.iter().enumerate() {
         // Read color frame
@@ -440,9 +707,24 @@ where
             &global_offsets[frame_idx],
             config.search_radius,
         );
+        tally_frame(
+            &mut tallies,
+            &grid,
+            &lum.data,
+            frame_idx,
+            &global_offsets[frame_idx],
+            &local_shifts,
+            &config.quality_metric,
+        );
 
-        let (field_dy, field_dx) =
-            interpolate_shift_field(&grid, &local_shifts, &global_offsets[frame_idx], h, w);
+        let (field_dy, field_dx) = shift_field(
+            &config.field_model,
+            &grid,
+            &local_shifts,
+            &global_offsets[frame_idx],
+            h,
+            w,
+        );
 
         // Warp each channel
         let warped_r = warp_frame(&cf.red.data, &field_dy, &field_dx);
@@ -475,11 +757,12 @@ where
     let result_b = finalize(acc_b);
 
     on_progress(1.0);
-    Ok(ColorFrame {
+    let stacked = ColorFrame {
         red: Frame::new(result_r, bit_depth),
         green: Frame::new(result_g, bit_depth),
         blue: Frame::new(result_b, bit_depth),
-    })
+    };
+    Ok((stacked, ApDiagnostics::from_tallies(&grid, (h, w), tallies)))
 }
 
 // ---------------------------------------------------------------------------
@@ -494,56 +777,7 @@ fn to_mp_config(config: &SurfaceWarpConfig) -> MultiPointConfig {
         select_percentage: config.select_percentage,
         min_brightness: config.min_brightness,
         quality_metric: config.quality_metric,
+        frames: config.frames.clone(),
         ..Default::default()
     }
 }
-
-/// Score all frames globally and return the top N% as `(frame_index, score)`.
-fn score_and_select_frames(
-    reader: &SerReader,
-    _offsets: &[AlignmentOffset],
-    config: &SurfaceWarpConfig,
-) -> Result<Vec<(usize, f64)>> {
-    let total = reader.frame_count();
-
-    let mut scores: Vec<(usize, f64)> = (0..total)
-        .map(|i| {
-            let frame = reader.read_frame(i).unwrap();
-            let score = score_with_metric(&frame.data, &config.quality_metric);
-            (i, score)
-        })
-        .collect();
-
-    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
-
-    let keep = ((total as f32 * config.select_percentage).ceil() as usize)
-        .max(1)
-        .min(total);
-    scores.truncate(keep);
-    Ok(scores)
-}
-
-/// Score all color frames on luminance and return top N%.
-fn score_and_select_frames_color(
-    reader: &SerReader,
-    _offsets: &[AlignmentOffset],
-    config: &SurfaceWarpConfig,
-    color_mode: &ColorMode,
-    debayer_method: &DebayerMethod,
-) -> Result<Vec<(usize, f64)>> {
-    let total = reader.frame_count();
-
-    let mut scores: Vec<(usize, f64)> = Vec::with_capacity(total);
-    for i in 0..total {
-        let lum = read_luminance_frame(reader, i, color_mode, debayer_method)?;
-        let score = score_with_metric(&lum.data, &config.quality_metric);
-        scores.push((i, score));
-    }
-
-    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
-    let keep = ((total as f32 * config.select_percentage).ceil() as usize)
-        .max(1)
-        .min(total);
-    scores.truncate(keep);
-    Ok(scores)
-}
@@ -0,0 +1,191 @@
+//! Robust thin-plate spline fit of scattered 2-D displacements.
+//!
+//! Used by surface warp to turn sparse, noisy AP shift measurements into a
+//! smooth deformation field. Coordinates are normalised by a caller-chosen
+//! scale (the AP size) so the smoothing parameter means the same thing for
+//! any image size.
+
+use crate::consts::{WARP_SPLINE_MIN_SCALE, WARP_SPLINE_ROBUST_PASSES, WARP_SPLINE_TUKEY_C};
+
+/// A fitted spline mapping `(y, x)` to a `[dy, dx]` displacement.
+pub(crate) struct ThinPlateSpline {
+    centres: Vec<(f64, f64)>,
+    /// Radial basis coefficients per centre, `[dy, dx]`.
+    coefficients: Vec<[f64; 2]>,
+    /// Affine part: constant, y and x terms, `[dy, dx]` each.
+    affine: [[f64; 2]; 3],
+    scale: f64,
+}
+
+impl ThinPlateSpline {
+    /// Fit with down-weighting of outliers.
+    ///
+    /// Starts from uniform weights, then reweights each point with Tukey's
+    /// biweight on its residual against the previous fit. Points beyond the
+    /// cut-off are dropped entirely, so outliers are rejected even when
+    /// `smoothing` is zero. Returns the spline and the final weights, or
+    /// `None` when fewer than three usable points remain or the points are
+    /// collinear.
+    pub(crate) fn fit_robust(
+        points: &[(f64, f64)],
+        values: &[[f64; 2]],
+        smoothing: f64,
+        scale: f64,
+    ) -> Option<(Self, Vec<f64>)> {
+        let mut weights = vec![1.0; points.len()];
+        let mut spline = Self::fit(points, values, &weights, smoothing, scale)?;
+
+        for _ in 0..WARP_SPLINE_ROBUST_PASSES {
+            let residuals: Vec<f64> = points
+                .iter()
+                .zip(values)
+                .map(|(&(y, x), v)| {
+                    let f = spline.eval(y, x);
+                    (v[0] - f[0]).hypot(v[1] - f[1])
+                })
+                .collect();
+            let sigma = (1.4826 * median(residuals.clone())).max(WARP_SPLINE_MIN_SCALE);
+            let cutoff = WARP_SPLINE_TUKEY_C * sigma;
+            let next: Vec<f64> = residuals
+                .iter()
+                .map(|&r| {
+                    let u = r / cutoff;
+                    if u < 1.0 {
+                        (1.0 - u * u).powi(2)
+                    } else {
+                        0.0
+                    }
+                })
+                .collect();
+            if next == weights {
+                break;
+            }
+            weights = next;
+            spline = Self::fit(points, values, &weights, smoothing, scale)?;
+        }
+        Some((spline, weights))
+    }
+
+    /// Weighted smoothing fit: solves
+    /// `[K + λ W⁻¹, P; Pᵀ, 0] [c; a] = [v; 0]` over points with non-zero weight.
+    fn fit(
+        points: &[(f64, f64)],
+        values: &[[f64; 2]],
+        weights: &[f64],
+        smoothing: f64,
+        scale: f64,
+    ) -> Option<Self> {
+        let used: Vec<usize> = (0..points.len()).filter(|&i| weights[i] > 0.0).collect();
+        let n = used.len();
+        if n < 3 {
+            return None;
+        }
+        let centres: Vec<(f64, f64)> = used
+            .iter()
+            .map(|&i| (points[i].0 / scale, points[i].1 / scale))
+            .collect();
+
+        let size = n + 3;
+        let mut matrix = vec![0.0; size * size];
+        let mut rhs = vec![[0.0; 2]; size];
+        for (row, &(yi, xi)) in centres.iter().enumerate() {
+            for (col, &(yj, xj)) in centres.iter().enumerate() {
+                matrix[row * size + col] = kernel((yi - yj).hypot(xi - xj));
+            }
+            matrix[row * size + row] += smoothing / weights[used[row]];
+            for (k, p) in [1.0, yi, xi].into_iter().enumerate() {
+                matrix[row * size + n + k] = p;
+                matrix[(n + k) * size + row] = p;
+            }
+            rhs[row] = values[used[row]];
+        }
+
+        let solution = solve(&mut matrix, &mut rhs, size)?;
+        Some(Self {
+            centres,
+            coefficients: solution[..n].to_vec(),
+            affine: [solution[n], solution[n + 1], solution[n + 2]],
+            scale,
+        })
+    }
+
+    /// Displacement `[dy, dx]` at pixel `(y, x)`.
+    pub(crate) fn eval(&self, y: f64, x: f64) -> [f64; 2] {
+        let (y, x) = (y / self.scale, x / self.scale);
+        let mut out = [0.0; 2];
+        for (k, v) in out.iter_mut().enumerate() {
+            *v = self.affine[0][k] + self.affine[1][k] * y + self.affine[2][k] * x;
+        }
+        for (&(cy, cx), c) in self.centres.iter().zip(&self.coefficients) {
+            let u = kernel((y - cy).hypot(x - cx));
+            out[0] += c[0] * u;
+            out[1] += c[1] * u;
+        }
+        out
+    }
+}
+
+/// Thin-plate radial basis `r² ln r`.
+fn kernel(r: f64) -> f64 {
+    if r > 0.0 {
+        r * r * r.ln()
+    } else {
+        0.0
+    }
+}
+
+/// Gaussian elimination with partial pivoting on a row-major `size × size`
+/// matrix with two right-hand sides.
+fn solve(matrix: &mut [f64], rhs: &mut [[f64; 2]], size: usize) -> Option<Vec<[f64; 2]>> {
+    let norm = matrix.iter().fold(0.0f64, |m, v| m.max(v.abs()));
+    for col in 0..size {
+        let pivot = (col..size).max_by(|&a, &b| {
+            matrix[a * size + col]
+                .abs()
+                .total_cmp(&matrix[b * size + col].abs())
+        })?;
+        if matrix[pivot * size + col].abs() <= 1e-12 * norm.max(1.0) {
+            return None;
+        }
+        if pivot != col {
+            for k in 0..size {
+                matrix.swap(pivot * size + k, col * size + k);
+            }
+            rhs.swap(pivot, col);
+        }
+        for row in col + 1..size {
+            let factor = matrix[row * size + col] / matrix[col * size + col];
+            if factor == 0.0 {
+                continue;
+            }
+            for k in col..size {
+                matrix[row * size + k] -= factor * matrix[col * size + k];
+            }
+            let pivot_rhs = rhs[col];
+            rhs[row][0] -= factor * pivot_rhs[0];
+            rhs[row][1] -= factor * pivot_rhs[1];
+        }
+    }
+
+    let mut solution = vec![[0.0; 2]; size];
+    for row in (0..size).rev() {
+        let mut acc = rhs[row];
+        for k in row + 1..size {
+            acc[0] -= matrix[row * size + k] * solution[k][0];
+            acc[1] -= matrix[row * size + k] * solution[k][1];
+        }
+        let d = matrix[row * size + row];
+        solution[row] = [acc[0] / d, acc[1] / d];
+    }
+    Some(solution)
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
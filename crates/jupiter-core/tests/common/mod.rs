@@ -1,4 +1,8 @@
+use std::path::{Path, PathBuf};
+
 use jupiter_core::io::ser::SER_HEADER_SIZE;
+use jupiter_core::pipeline::config::{FrameSelectionConfig, PipelineConfig};
+use ndarray::{Array2, ArrayView2};
 
 /// Build a SER file header for mono 8-bit frames.
 ///
@@ -71,3 +75,83 @@ pub fn write_test_ser(data: &[u8]) -> tempfile::NamedTempFile {
     f.flush().expect("flush");
     f
 }
+
+/// Deterministic uniform noise in [-1, 1) from a 64-bit LCG.
+pub fn noise(seed: u64) -> impl FnMut() -> f32 {
+    let mut state = seed;
+    move || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        2.0 * ((state >> 40) as f32 / (1u64 << 24) as f32) - 1.0
+    }
+}
+
+/// Add deterministic uniform noise in [-amplitude, amplitude].
+pub fn add_noise(data: &mut Array2<f32>, seed: u64, amplitude: f32) {
+    let mut next = noise(seed);
+    for v in data.iter_mut() {
+        *v += amplitude * next();
+    }
+}
+
+/// Add deterministic, roughly Gaussian noise (sum of four uniforms) with
+/// standard deviation `sigma`.
+pub fn add_gaussian_noise(data: &mut Array2<f32>, seed: u64, sigma: f32) {
+    let scale = sigma / (4.0f32 / 3.0).sqrt();
+    let mut next = noise(seed);
+    for v in data.iter_mut() {
+        *v += scale * (0..4).map(|_| next()).sum::<f32>();
+    }
+}
+
+/// 64x64 planet-like disk of radius 16 with a few surface features.
+pub fn planet() -> Array2<f32> {
+    Array2::from_shape_fn((64, 64), |(r, c)| {
+        let (y, x) = (r as f64, c as f64);
+        let disk = if (y - 32.0).hypot(x - 32.0) < 16.0 {
+            0.6
+        } else {
+            0.02
+        };
+        let features: f64 = [(26.0, 28.0, 0.3), (37.0, 36.0, -0.2), (30.0, 41.0, 0.25)]
+            .iter()
+            .map(|&(cy, cx, amp)| amp * (-((y - cy).powi(2) + (x - cx).powi(2)) / 8.0).exp())
+            .sum();
+        (disk + features) as f32
+    })
+}
+
+/// Root-mean-square difference between two equally sized arrays.
+pub fn rms(a: ArrayView2<f32>, b: ArrayView2<f32>) -> f32 {
+    let sum: f32 = a.iter().zip(b.iter()).map(|(x, y)| (x - y).powi(2)).sum();
+    (sum / a.len() as f32).sqrt()
+}
+
+/// Population standard deviation of an array.
+pub fn std_dev(data: ArrayView2<f32>) -> f32 {
+    let mean = data.mean().unwrap();
+    (data.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / data.len() as f32).sqrt()
+}
+
+/// Pipeline config that mean-stacks the best half of `inputs` (read as one
+/// sequence) and writes into `dir`.
+pub fn config_for(inputs: &[PathBuf], dir: &Path) -> PipelineConfig {
+    PipelineConfig {
+        input: inputs[0].clone(),
+        extra_inputs: inputs[1..].to_vec(),
+        output: dir.join("out.tiff"),
+        device: Default::default(),
+        memory: Default::default(),
+        debayer: None,
+        force_mono: false,
+        frame_selection: FrameSelectionConfig {
+            select_percentage: 0.5,
+            ..Default::default()
+        },
+        alignment: Default::default(),
+        stacking: Default::default(),
+        sharpening: None,
+        filters: vec![],
+    }
+}
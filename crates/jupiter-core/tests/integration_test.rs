@@ -76,6 +76,7 @@ fn test_full_pipeline_end_to_end() {
 
     let config = PipelineConfig {
         input: ser_file.path().to_path_buf(),
+        extra_inputs: vec![],
         output: output_path.clone(),
         device: Default::default(),
         memory: Default::default(),
@@ -117,6 +118,7 @@ fn test_pipeline_without_sharpening() {
 
     let config = PipelineConfig {
         input: ser_file.path().to_path_buf(),
+        extra_inputs: vec![],
         output: output_path.clone(),
         device: Default::default(),
         memory: Default::default(),
@@ -207,6 +209,7 @@ fn test_align_stack_sharpen_manual_pipeline() {
         num_layers: 4,
         coefficients: vec![1.5, 1.3, 1.1, 1.0],
         denoise: vec![],
+        ..Default::default()
     };
     let sharpened = wavelet::sharpen(&stacked, &params);
     assert_eq!(sharpened.width(), 64);
@@ -1,3 +1,6 @@
+#[allow(dead_code)]
+mod common;
+
 use std::sync::Arc;
 
 use ndarray::Array2;
@@ -321,6 +324,162 @@ fn test_pyramid_large_shift() {
     );
 }
 
+// ===== Limb fit =====
+
+/// Anti-aliased crescent: a uniformly lit disk of radius `r` centred at
+/// (`cy`, `cx`) whose left part beyond a soft elliptical terminator is dark.
+fn make_crescent(h: usize, w: usize, cy: f64, cx: f64, r: f64) -> Array2<f32> {
+    const SS: usize = 4;
+    Array2::from_shape_fn((h, w), |(row, col)| {
+        let mut sum = 0.0;
+        for i in 0..SS {
+            for j in 0..SS {
+                let y = row as f64 + (i as f64 + 0.5) / SS as f64 - 0.5 - cy;
+                let x = col as f64 + (j as f64 + 0.5) / SS as f64 - 0.5 - cx;
+                if y.hypot(x) >= r {
+                    continue;
+                }
+                // Terminator at x = 0.4 * half-chord, softened over ~3 px.
+                let terminator = 0.4 * (r * r - y * y).max(0.0).sqrt();
+                sum += ((x - terminator) / 3.0 + 0.5).clamp(0.0, 1.0);
+            }
+        }
+        (0.02 + 0.8 * sum / (SS * SS) as f64) as f32
+    })
+}
+
+#[test]
+fn test_limb_fit_finds_crescent_centre() {
+    use jupiter_core::align::limb::fit_limb;
+    use jupiter_core::pipeline::config::LimbFitConfig;
+
+    let data = make_crescent(96, 96, 47.3, 45.6, 30.0);
+    let circle = fit_limb(&data, &LimbFitConfig::default()).unwrap();
+    assert!(
+        (circle.center_y - 47.3).abs() < 0.3 && (circle.center_x - 45.6).abs() < 0.3,
+        "centre ({:.2}, {:.2})",
+        circle.center_y,
+        circle.center_x
+    );
+    assert!(
+        (circle.radius - 30.0).abs() < 0.5,
+        "radius {:.2}",
+        circle.radius
+    );
+}
+
+#[test]
+fn test_limb_fit_subpixel_shift_ignores_hot_pixels() {
+    use jupiter_core::align::limb::compute_offset_limb;
+    use jupiter_core::pipeline::config::LimbFitConfig;
+
+    let reference = make_crescent(96, 96, 48.0, 48.0, 30.0);
+    let mut target = make_crescent(96, 96, 49.4, 46.7, 30.0);
+    for (r, c) in [(5, 7), (88, 12), (10, 90), (80, 85)] {
+        target[[r, c]] = 1.0;
+    }
+    let offset = compute_offset_limb(&reference, &target, &LimbFitConfig::default()).unwrap();
+    // Correcting offset: shifting the target by it re-centres the disk.
+    assert!((offset.dy + 1.4).abs() < 0.3, "dy = {:.2}", offset.dy);
+    assert!((offset.dx - 1.3).abs() < 0.3, "dx = {:.2}", offset.dx);
+}
+
+#[test]
+fn test_limb_fit_fails_on_blank_frame() {
+    use jupiter_core::align::limb::fit_limb;
+    use jupiter_core::pipeline::config::LimbFitConfig;
+
+    let blank = Array2::<f32>::from_elem((32, 32), 0.5);
+    assert!(fit_limb(&blank, &LimbFitConfig::default()).is_err());
+}
+
+/// Anti-aliased uniformly lit ellipse with semi-axes `rx` (columns) and `ry`
+/// (rows) centred at (`cy`, `cx`).
+fn make_oblate_disk(h: usize, w: usize, cy: f64, cx: f64, rx: f64, ry: f64) -> Array2<f32> {
+    const SS: usize = 4;
+    Array2::from_shape_fn((h, w), |(row, col)| {
+        let mut inside = 0;
+        for i in 0..SS {
+            for j in 0..SS {
+                let y = row as f64 + (i as f64 + 0.5) / SS as f64 - 0.5 - cy;
+                let x = col as f64 + (j as f64 + 0.5) / SS as f64 - 0.5 - cx;
+                if (x / rx).powi(2) + (y / ry).powi(2) < 1.0 {
+                    inside += 1;
+                }
+            }
+        }
+        (0.02 + 0.8 * inside as f64 / (SS * SS) as f64) as f32
+    })
+}
+
+#[test]
+fn test_limb_ellipse_fit_finds_oblate_centre() {
+    use jupiter_core::align::limb::fit_limb_ellipse;
+    use jupiter_core::pipeline::config::LimbFitConfig;
+
+    let data = make_oblate_disk(112, 112, 55.4, 57.2, 40.0, 34.0);
+    let ellipse = fit_limb_ellipse(&data, &LimbFitConfig::default()).unwrap();
+    assert!(
+        (ellipse.center_y - 55.4).abs() < 0.3 && (ellipse.center_x - 57.2).abs() < 0.3,
+        "centre ({:.2}, {:.2})",
+        ellipse.center_y,
+        ellipse.center_x
+    );
+    assert!(
+        (ellipse.semi_major - 40.0).abs() < 0.5 && (ellipse.semi_minor - 34.0).abs() < 0.5,
+        "axes {:.2} x {:.2}",
+        ellipse.semi_major,
+        ellipse.semi_minor
+    );
+    assert!(ellipse.angle.abs() < 0.05, "angle {:.3}", ellipse.angle);
+}
+
+#[test]
+fn test_limb_ellipse_offset() {
+    use jupiter_core::align::limb::compute_offset_limb;
+    use jupiter_core::pipeline::config::{LimbFitConfig, LimbModel};
+
+    let config = LimbFitConfig {
+        model: LimbModel::Ellipse,
+        ..Default::default()
+    };
+    let reference = make_oblate_disk(112, 112, 56.0, 56.0, 40.0, 34.0);
+    let target = make_oblate_disk(112, 112, 57.3, 54.1, 40.0, 34.0);
+    let offset = compute_offset_limb(&reference, &target, &config).unwrap();
+    assert!((offset.dy + 1.3).abs() < 0.3, "dy = {:.2}", offset.dy);
+    assert!((offset.dx - 1.9).abs() < 0.3, "dx = {:.2}", offset.dx);
+}
+
+#[test]
+fn test_limb_fit_sequence_survives_frame_without_limb() {
+    use jupiter_core::align::compute_offsets_configured_with_progress;
+    use jupiter_core::pipeline::config::LimbFitConfig;
+
+    // A blank frame has no limb; it must not abort the sequence.
+    let frames = vec![
+        Frame::new(make_crescent(96, 96, 48.0, 48.0, 30.0), 16),
+        Frame::new(Array2::from_elem((96, 96), 0.5), 16),
+        Frame::new(make_crescent(96, 96, 49.4, 46.7, 30.0), 16),
+    ];
+    let config = AlignmentConfig {
+        method: AlignmentMethod::LimbFit(LimbFitConfig::default()),
+        ..Default::default()
+    };
+    let offsets =
+        compute_offsets_configured_with_progress(&frames, 0, &config, cpu(), |_| {}).unwrap();
+    assert_eq!(offsets.len(), 3);
+    assert!(
+        (offsets[2].dy + 1.4).abs() < 0.3,
+        "dy = {:.2}",
+        offsets[2].dy
+    );
+    assert!(
+        (offsets[2].dx - 1.3).abs() < 0.3,
+        "dx = {:.2}",
+        offsets[2].dx
+    );
+}
+
 // ===== Dispatcher Tests =====
 
 #[test]
@@ -328,6 +487,8 @@ fn test_dispatcher_routes_phase_correlation() {
     let img = make_bright_square(64, 64, 32, 32, 16);
     let config = AlignmentConfig {
         method: AlignmentMethod::PhaseCorrelation,
+        refinement: None,
+        rejection: None,
     };
     let backend = cpu();
 
@@ -343,6 +504,8 @@ fn test_dispatcher_routes_enhanced_phase() {
         method: AlignmentMethod::EnhancedPhaseCorrelation(EnhancedPhaseConfig {
             upsample_factor: 10,
         }),
+        refinement: None,
+        rejection: None,
     };
     let backend = cpu();
 
@@ -356,6 +519,8 @@ fn test_dispatcher_routes_centroid() {
     let img = make_bright_disk(64, 64, 32.0, 32.0, 15.0);
     let config = AlignmentConfig {
         method: AlignmentMethod::Centroid(CentroidConfig { threshold: 0.1 }),
+        refinement: None,
+        rejection: None,
     };
     let backend = cpu();
 
@@ -369,6 +534,8 @@ fn test_dispatcher_routes_gradient() {
     let img = make_bright_square(64, 64, 32, 32, 16);
     let config = AlignmentConfig {
         method: AlignmentMethod::GradientCorrelation,
+        refinement: None,
+        rejection: None,
     };
     let backend = cpu();
 
@@ -382,6 +549,8 @@ fn test_dispatcher_routes_pyramid() {
     let img = make_bright_square(64, 64, 32, 32, 16);
     let config = AlignmentConfig {
         method: AlignmentMethod::Pyramid(PyramidConfig { levels: 2 }),
+        refinement: None,
+        rejection: None,
     };
     let backend = cpu();
 
@@ -392,6 +561,19 @@ fn test_dispatcher_routes_pyramid() {
 
 // ===== Frame-level alignment =====
 
+#[test]
+fn test_dispatcher_routes_limb_fit() {
+    let reference = make_crescent(96, 96, 48.0, 48.0, 30.0);
+    let target = make_crescent(96, 96, 50.0, 47.0, 30.0);
+    let config = AlignmentConfig {
+        method: AlignmentMethod::LimbFit(Default::default()),
+        ..Default::default()
+    };
+    let offset = compute_offset_configured(&reference, &target, &config, &CpuBackend).unwrap();
+    assert!((offset.dy + 2.0).abs() < 0.3);
+    assert!((offset.dx - 1.0).abs() < 0.3);
+}
+
 #[test]
 fn test_align_frames_configured_preserves_reference() {
     use jupiter_core::align::align_frames_configured_with_progress;
@@ -410,3 +592,130 @@ fn test_align_frames_configured_preserves_reference() {
     // Reference frame should be unchanged
     assert_eq!(aligned[0].data, frames[0].data);
 }
+
+// ===== Stacked-reference refinement =====
+
+/// Frames of a textured disk with known shifts; frame 0 (the "best" frame
+/// and initial reference) is much noisier than the rest.
+fn noisy_reference_sequence() -> (Vec<Frame>, Vec<(f64, f64)>) {
+    let mut base = make_bright_disk(64, 64, 32.0, 32.0, 14.0);
+    // Surface features so correlation has more than the limb to lock on to
+    for (cy, cx, amp) in [(26.0, 28.0, 0.4), (36.0, 35.0, -0.3), (30.0, 40.0, 0.3)] {
+        for r in 0..64 {
+            for c in 0..64 {
+                let d2 = (r as f64 - cy).powi(2) + (c as f64 - cx).powi(2);
+                base[[r, c]] += (amp * (-d2 / 8.0).exp()) as f32;
+            }
+        }
+    }
+    let shifts: Vec<(f64, f64)> = (0..12)
+        .map(|k| {
+            let k = k as f64;
+            ((k * 0.37) % 2.0 - 1.0, (k * 0.61) % 2.0 - 1.0)
+        })
+        .collect();
+    let frames = shifts
+        .iter()
+        .enumerate()
+        .map(|(k, &(dy, dx))| {
+            let mut data = shift_array(&base, dy, dx);
+            common::add_noise(&mut data, k as u64, if k == 0 { 0.3 } else { 0.03 });
+            Frame::new(data, 16)
+        })
+        .collect();
+    (frames, shifts)
+}
+
+/// RMS error of the offsets relative to the true shifts, ignoring a common
+/// translation (the choice of reference coordinates).
+fn relative_rms_error(
+    offsets: &[jupiter_core::frame::AlignmentOffset],
+    shifts: &[(f64, f64)],
+) -> f64 {
+    let errors: Vec<(f64, f64)> = offsets
+        .iter()
+        .zip(shifts)
+        .map(|(o, &(dy, dx))| (o.dy + dy, o.dx + dx))
+        .collect();
+    let n = errors.len() as f64;
+    let (my, mx) = errors
+        .iter()
+        .fold((0.0, 0.0), |(a, b), (y, x)| (a + y / n, b + x / n));
+    (errors
+        .iter()
+        .map(|(y, x)| (y - my).powi(2) + (x - mx).powi(2))
+        .sum::<f64>()
+        / n)
+        .sqrt()
+}
+
+#[test]
+fn test_refinement_reduces_noisy_reference_error() {
+    use jupiter_core::align::compute_offsets_configured_with_progress;
+    use jupiter_core::pipeline::config::ReferenceRefinement;
+
+    let (frames, shifts) = noisy_reference_sequence();
+    let single = AlignmentConfig {
+        method: AlignmentMethod::EnhancedPhaseCorrelation(EnhancedPhaseConfig::default()),
+        refinement: None,
+        rejection: None,
+    };
+    let refined = AlignmentConfig {
+        refinement: Some(ReferenceRefinement {
+            top_fraction: 0.5,
+            ..Default::default()
+        }),
+        ..single.clone()
+    };
+
+    let before =
+        compute_offsets_configured_with_progress(&frames, 0, &single, cpu(), |_| {}).unwrap();
+    let after =
+        compute_offsets_configured_with_progress(&frames, 0, &refined, cpu(), |_| {}).unwrap();
+
+    let err_before = relative_rms_error(&before, &shifts);
+    let err_after = relative_rms_error(&after, &shifts);
+    assert!(
+        err_after < err_before,
+        "refinement should reduce error: {err_before:.3} -> {err_after:.3}"
+    );
+    // Offsets stay in the initial reference's coordinates.
+    assert!(after[0].dx.abs() < 0.5 && after[0].dy.abs() < 0.5);
+}
+
+#[test]
+fn test_refinement_never_hurts_any_method() {
+    use jupiter_core::align::compute_offsets_configured_with_progress;
+    use jupiter_core::pipeline::config::ReferenceRefinement;
+
+    let (frames, shifts) = noisy_reference_sequence();
+    let methods = [
+        AlignmentMethod::PhaseCorrelation,
+        AlignmentMethod::EnhancedPhaseCorrelation(EnhancedPhaseConfig::default()),
+        AlignmentMethod::Centroid(CentroidConfig::default()),
+        AlignmentMethod::GradientCorrelation,
+        AlignmentMethod::Pyramid(PyramidConfig { levels: 2 }),
+    ];
+    for method in methods {
+        let single = AlignmentConfig {
+            method: method.clone(),
+            refinement: None,
+            rejection: None,
+        };
+        let refined = AlignmentConfig {
+            refinement: Some(ReferenceRefinement::default()),
+            ..single.clone()
+        };
+        let before =
+            compute_offsets_configured_with_progress(&frames, 0, &single, cpu(), |_| {}).unwrap();
+        let after =
+            compute_offsets_configured_with_progress(&frames, 0, &refined, cpu(), |_| {}).unwrap();
+        assert_eq!(after.len(), frames.len());
+        let err_before = relative_rms_error(&before, &shifts);
+        let err_after = relative_rms_error(&after, &shifts);
+        assert!(
+            err_after <= err_before + 0.05,
+            "{method:?}: refinement made alignment worse ({err_before:.3} -> {err_after:.3})"
+        );
+    }
+}
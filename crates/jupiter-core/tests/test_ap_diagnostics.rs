@@ -0,0 +1,147 @@
+#[allow(dead_code)]
+mod common;
+
+use ndarray::Array2;
+
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::AlignmentConfig;
+use jupiter_core::stack::ap_diagnostics::{
+    ApDiagnostics, DIAGNOSTICS_JSON, SHIFT_QUIVER_PNG, USAGE_HEATMAP_PNG,
+};
+use jupiter_core::stack::multi_point::{multi_point_stack_with_diagnostics, MultiPointConfig};
+use jupiter_core::stack::surface_warp::{surface_warp_stack_with_diagnostics, SurfaceWarpConfig};
+
+const SIZE: usize = 128;
+const FRAMES: usize = 4;
+
+/// Ripples on the left half, flat grey on the right.
+fn half_textured() -> Array2<f32> {
+    Array2::from_shape_fn((SIZE, SIZE), |(r, c)| {
+        if c < SIZE / 2 {
+            (0.5 + 0.3 * (c as f64 / 3.0).sin() * (r as f64 / 4.0).cos()) as f32
+        } else {
+            0.5
+        }
+    })
+}
+
+fn static_ser(scene: &Array2<f32>) -> tempfile::NamedTempFile {
+    let bytes: Vec<u8> = scene.iter().map(|&v| (v * 255.0) as u8).collect();
+    common::write_test_ser(&common::build_ser_with_frames(
+        SIZE as u32,
+        SIZE as u32,
+        &vec![bytes; FRAMES],
+    ))
+}
+
+fn multi_point_diagnostics() -> ApDiagnostics {
+    let ser = static_ser(&half_textured());
+    let reader = SerReader::open(ser.path()).unwrap();
+    let config = MultiPointConfig {
+        ap_size: 32,
+        search_radius: 8,
+        select_percentage: 1.0,
+        min_brightness: 0.1,
+        ..Default::default()
+    };
+    let (_, diagnostics) = multi_point_stack_with_diagnostics(
+        &reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |_| {},
+    )
+    .unwrap();
+    diagnostics
+}
+
+#[test]
+fn test_multi_point_diagnostics_static_scene() {
+    let d = multi_point_diagnostics();
+    assert_eq!((d.height, d.width), (SIZE, SIZE));
+    assert_eq!(d.aps.len(), d.grid.points.len());
+
+    for ap in &d.grid.points {
+        let stats = &d.aps[ap.index];
+        assert_eq!(stats.selected_frames, FRAMES);
+        assert!(stats.used_frames <= stats.selected_frames);
+        if ap.cx + ap.size / 2 <= SIZE / 2 {
+            // Fully on the ripples: every frame used, nothing moved.
+            assert_eq!(stats.used_frames, FRAMES, "AP {}", ap.index);
+            let shift = stats.mean_shift.dx.hypot(stats.mean_shift.dy);
+            assert!(shift < 0.5, "AP {} shift {shift:.2}", ap.index);
+        }
+    }
+
+    // Rejections are listed once per AP/frame pair and match the counts.
+    let rejected: usize = d
+        .aps
+        .iter()
+        .map(|s| s.selected_frames - s.used_frames)
+        .sum();
+    assert_eq!(d.rejected.len(), rejected);
+    for &(ap, frame) in &d.rejected {
+        assert!(ap < d.aps.len() && frame < FRAMES);
+    }
+}
+
+#[test]
+fn test_diagnostic_images_and_save() {
+    let d = multi_point_diagnostics();
+    let background = half_textured();
+
+    let quiver = d.shift_quiver(Some(&background));
+    assert_eq!(quiver.red.data.dim(), (SIZE, SIZE));
+    let heatmap = d.usage_heatmap();
+    assert_eq!(heatmap.green.data.dim(), (SIZE, SIZE));
+    // The heatmap is black outside every AP and lit inside a fully used one.
+    let used = d
+        .grid
+        .points
+        .iter()
+        .find(|ap| d.aps[ap.index].usage() == 1.0)
+        .expect("no fully used AP");
+    assert!(heatmap.red.data[[used.cy, used.cx]] > 0.9);
+
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("diag");
+    d.save(&out, Some(&background)).unwrap();
+    for name in [DIAGNOSTICS_JSON, SHIFT_QUIVER_PNG, USAGE_HEATMAP_PNG] {
+        assert!(out.join(name).is_file(), "{name} missing");
+    }
+    let json = std::fs::read_to_string(out.join(DIAGNOSTICS_JSON)).unwrap();
+    let loaded: ApDiagnostics = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.aps.len(), d.aps.len());
+    assert_eq!(loaded.rejected, d.rejected);
+}
+
+#[test]
+fn test_surface_warp_diagnostics() {
+    let ser = static_ser(&half_textured());
+    let reader = SerReader::open(ser.path()).unwrap();
+    let config = SurfaceWarpConfig {
+        ap_size: 32,
+        search_radius: 8,
+        select_percentage: 1.0,
+        min_brightness: 0.1,
+        ..Default::default()
+    };
+    let (frame, d) = surface_warp_stack_with_diagnostics(
+        &reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |_| {},
+    )
+    .unwrap();
+
+    assert_eq!(frame.data.dim(), (d.height, d.width));
+    assert_eq!(d.aps.len(), d.grid.points.len());
+    assert!(!d.aps.is_empty());
+    for stats in &d.aps {
+        assert_eq!(stats.selected_frames, FRAMES);
+        assert!(stats.used_frames <= FRAMES);
+    }
+    assert!(d.aps.iter().any(|s| s.used_frames == FRAMES));
+}
@@ -0,0 +1,208 @@
+#[allow(dead_code)]
+mod common;
+
+use std::io::Write;
+
+use ndarray::Array2;
+
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::AlignmentConfig;
+use jupiter_core::stack::ap_placement::load_ap_list;
+use jupiter_core::stack::multi_point::{
+    blend_ap_stacks, build_ap_grid, multi_point_stack, AlignmentPoint, ApPlacement, ApSpec,
+    MultiPointConfig,
+};
+
+const SIZE: usize = 128;
+
+/// Disk of radius 48 with ripples on its left half only.
+fn half_textured_disk() -> Array2<f32> {
+    Array2::from_shape_fn((SIZE, SIZE), |(r, c)| {
+        let (y, x) = (r as f64 - 64.0, c as f64 - 64.0);
+        if y.hypot(x) >= 48.0 {
+            return 0.0;
+        }
+        let texture = if x < 0.0 {
+            0.2 * (x / 3.0).sin() * (y / 4.0).cos()
+        } else {
+            0.0
+        };
+        (0.5 + texture) as f32
+    })
+}
+
+fn config(placement: ApPlacement) -> MultiPointConfig {
+    MultiPointConfig {
+        ap_size: 32,
+        min_brightness: 0.1,
+        placement,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_structure_placement_follows_detail() {
+    let reference = half_textured_disk();
+    let grid = build_ap_grid(
+        &reference,
+        &config(ApPlacement::Structure { min_energy: 0.5 }),
+    );
+
+    assert!(!grid.points.is_empty());
+    // Nothing on the flat right half of the disk (the limb there is a
+    // weaker edge than the ripples).
+    for ap in &grid.points {
+        assert!(ap.cx < 64 + 8, "AP at cx={} sits on flat disk", ap.cx);
+        assert_eq!(ap.size, 32);
+    }
+    // No two APs overlap by more than half.
+    for a in &grid.points {
+        for b in grid.points.iter().filter(|b| b.index != a.index) {
+            assert!(a.cy.abs_diff(b.cy) >= 16 || a.cx.abs_diff(b.cx) >= 16);
+        }
+    }
+}
+
+#[test]
+fn test_limb_placement_adds_ring() {
+    let reference = half_textured_disk();
+    let grid_only = build_ap_grid(&reference, &config(ApPlacement::Grid));
+    let limb = build_ap_grid(&reference, &config(ApPlacement::Limb));
+
+    let extra = &limb.points[grid_only.points.len()..];
+    assert!(extra.len() >= 8, "only {} limb APs", extra.len());
+    for ap in extra {
+        let r = (ap.cy as f64 - 64.0).hypot(ap.cx as f64 - 64.0);
+        assert!((r - 48.0).abs() < 2.0, "limb AP at radius {r:.1}");
+    }
+}
+
+#[test]
+fn test_custom_placement_sizes_and_bounds() {
+    let points = vec![
+        ApSpec {
+            cy: 64,
+            cx: 64,
+            size: None,
+        },
+        ApSpec {
+            cy: 40,
+            cx: 50,
+            size: Some(17),
+        },
+        // Does not fit: dropped.
+        ApSpec {
+            cy: 4,
+            cx: 64,
+            size: Some(32),
+        },
+    ];
+    let grid = build_ap_grid(
+        &half_textured_disk(),
+        &config(ApPlacement::Custom { points }),
+    );
+
+    let placed: Vec<(usize, usize, usize)> =
+        grid.points.iter().map(|p| (p.cy, p.cx, p.size)).collect();
+    assert_eq!(placed, vec![(64, 64, 32), (40, 50, 16)]);
+}
+
+#[test]
+fn test_multi_scale_mixes_sizes() {
+    let grid = build_ap_grid(
+        &half_textured_disk(),
+        &config(ApPlacement::MultiScale {
+            small_size: 16,
+            min_energy: 0.3,
+        }),
+    );
+    assert!(grid.points.iter().any(|p| p.size == 32));
+    assert!(grid.points.iter().any(|p| p.size == 16));
+    assert!(grid.points.iter().enumerate().all(|(i, p)| p.index == i));
+}
+
+#[test]
+fn test_load_ap_list() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    writeln!(file, "# y,x[,size]\n10, 20\n\n30,40,48  # big one").unwrap();
+    let specs = load_ap_list(file.path()).unwrap();
+    assert_eq!(
+        specs,
+        vec![
+            ApSpec {
+                cy: 10,
+                cx: 20,
+                size: None
+            },
+            ApSpec {
+                cy: 30,
+                cx: 40,
+                size: Some(48)
+            },
+        ]
+    );
+
+    let mut bad = tempfile::NamedTempFile::new().unwrap();
+    writeln!(bad, "10;20").unwrap();
+    assert!(load_ap_list(bad.path()).is_err());
+}
+
+#[test]
+fn test_blend_mixed_sizes_with_background() {
+    let background = Array2::from_elem((64, 64), 0.2f32);
+    let ap = |cy, cx, size, index| AlignmentPoint {
+        cy,
+        cx,
+        index,
+        size,
+    };
+    let stacks = vec![
+        (ap(24, 24, 32, 0), Array2::from_elem((32, 32), 0.7f32)),
+        (ap(30, 30, 16, 1), Array2::from_elem((16, 16), 0.7f32)),
+    ];
+    let blended = blend_ap_stacks(&stacks, &background);
+
+    // Well inside the APs: the patches alone.
+    assert!((blended[[24, 24]] - 0.7).abs() < 1e-5);
+    assert!((blended[[30, 30]] - 0.7).abs() < 1e-5);
+    // Outside every AP: the background.
+    assert!((blended[[60, 60]] - 0.2).abs() < 1e-6);
+    // Near an AP edge: a smooth mix, not a hard cut to zero.
+    let edge = blended[[24, 9]];
+    assert!(edge > 0.2 && edge < 0.7, "edge value {edge}");
+}
+
+#[test]
+fn test_multi_scale_stack_reproduces_static_scene() {
+    let scene = half_textured_disk();
+    let bytes: Vec<u8> = scene.iter().map(|&v| (v * 255.0) as u8).collect();
+    let ser = common::write_test_ser(&common::build_ser_with_frames(
+        SIZE as u32,
+        SIZE as u32,
+        &vec![bytes.clone(); 4],
+    ));
+    let reader = SerReader::open(ser.path()).unwrap();
+
+    let stacked = multi_point_stack(
+        &reader,
+        &MultiPointConfig {
+            search_radius: 8,
+            select_percentage: 1.0,
+            ..config(ApPlacement::multi_scale())
+        },
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |_| {},
+    )
+    .unwrap();
+
+    // Every pixel, covered or not, matches the (static) input.
+    let worst = stacked
+        .data
+        .iter()
+        .zip(&bytes)
+        .map(|(&a, &b)| (a - b as f32 / 255.0).abs())
+        .fold(0.0f32, f32::max);
+    assert!(worst < 0.01, "max error {worst:.4}");
+}
@@ -0,0 +1,123 @@
+use ndarray::Array2;
+
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::filters::gaussian_blur::gaussian_blur_array;
+use jupiter_core::frame::Frame;
+use jupiter_core::pipeline::config::{DeconvolutionConfig, DeconvolutionMethod, PsfModel};
+use jupiter_core::sharpen::blind::blind_richardson_lucy;
+use jupiter_core::sharpen::deconvolution::{deconvolve_with_psf, generate_psf};
+use jupiter_core::sharpen::psf::{kernel_sigma, load_psf, save_psf};
+
+const TRUE_SIGMA: f32 = 2.0;
+
+/// Bright spots and bars on a dark background, blurred by a Gaussian PSF.
+fn scene() -> Array2<f32> {
+    let mut data = Array2::<f32>::from_elem((64, 64), 0.05);
+    for &(r, c) in &[(12, 14), (20, 48), (44, 22), (50, 50), (30, 32)] {
+        data[[r, c]] = 0.9;
+    }
+    for r in 36..40 {
+        for c in 38..58 {
+            data[[r, c]] = 0.6;
+        }
+    }
+    for r in 6..26 {
+        for c in 28..31 {
+            data[[r, c]] = 0.5;
+        }
+    }
+    data
+}
+
+fn blind_config(start_sigma: f32, iterations: usize) -> DeconvolutionConfig {
+    DeconvolutionConfig {
+        method: DeconvolutionMethod::BlindRichardsonLucy {
+            iterations,
+            psf_size: 15,
+        },
+        psf: PsfModel::Gaussian { sigma: start_sigma },
+        tiling: None,
+    }
+}
+
+#[test]
+fn test_blind_rl_refines_psf_estimate() {
+    let observed = Frame::new(gaussian_blur_array(&scene(), TRUE_SIGMA), 16);
+
+    // A generous starting guess shrinks towards the true blur.
+    let (restored, estimate) = deconvolve_with_psf(&observed, &blind_config(3.0, 15)).unwrap();
+    let kernel = estimate.expect("blind deconvolution reports its PSF");
+
+    assert_eq!(kernel.dim(), (15, 15));
+    assert!((kernel.sum() - 1.0).abs() < 1e-4);
+    assert!(kernel.iter().all(|&v| v >= 0.0));
+    assert!(restored.data.iter().all(|&v| (0.0..=1.0).contains(&v)));
+
+    let sigma = kernel_sigma(&kernel);
+    assert!(
+        sigma < 2.7 && sigma > TRUE_SIGMA as f64 - 0.2,
+        "started at 3.0, estimated {sigma:.3}"
+    );
+
+    // A narrow guess is never pushed past the true blur.
+    let (_, estimate) = deconvolve_with_psf(&observed, &blind_config(1.0, 15)).unwrap();
+    let sigma = kernel_sigma(&estimate.unwrap());
+    assert!(
+        sigma < TRUE_SIGMA as f64,
+        "started at 1.0, estimated {sigma:.3}"
+    );
+}
+
+#[test]
+fn test_blind_rl_restores_detail() {
+    let truth = scene();
+    let observed = Frame::new(gaussian_blur_array(&truth, TRUE_SIGMA), 16);
+    let (restored, _) = deconvolve_with_psf(&observed, &blind_config(1.5, 15)).unwrap();
+
+    let error = |data: &Array2<f32>| -> f32 {
+        data.iter()
+            .zip(truth.iter())
+            .map(|(a, b)| (a - b).powi(2))
+            .sum::<f32>()
+    };
+    assert!(
+        error(&restored.data) < error(&observed.data),
+        "blind RL should move the image towards the sharp scene"
+    );
+    // The brightest point source gets its peak back in part.
+    assert!(restored.data[[30, 32]] > observed.data[[30, 32]]);
+}
+
+#[test]
+fn test_blind_rl_psf_support_and_reuse() {
+    let observed = Frame::new(gaussian_blur_array(&scene(), TRUE_SIGMA), 16);
+    let initial = generate_psf(&PsfModel::Gaussian { sigma: 1.5 }, observed.data.dim()).unwrap();
+
+    // Even sizes are rounded up to odd so the kernel has a centre.
+    let (_, kernel) = blind_richardson_lucy(&observed, &initial, 2, 8, &CpuBackend);
+    assert_eq!(kernel.dim(), (9, 9));
+
+    let (_, kernel) = blind_richardson_lucy(&observed, &initial, 5, 15, &CpuBackend);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("psf.tiff");
+    save_psf(&kernel, &path).unwrap();
+
+    let reloaded = load_psf(&path, None).unwrap();
+    assert!(
+        (kernel_sigma(&reloaded) - kernel_sigma(&kernel)).abs() < 0.15,
+        "saved {:.3}, reloaded {:.3}",
+        kernel_sigma(&kernel),
+        kernel_sigma(&reloaded)
+    );
+
+    // Non-blind methods have no estimate to report.
+    let rl = DeconvolutionConfig {
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 2,
+            options: Default::default(),
+        },
+        psf: PsfModel::Gaussian { sigma: 1.5 },
+        tiling: None,
+    };
+    assert!(deconvolve_with_psf(&observed, &rl).unwrap().1.is_none());
+}
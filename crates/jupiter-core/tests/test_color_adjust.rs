@@ -0,0 +1,207 @@
+use ndarray::Array2;
+
+use jupiter_core::color::lab::{lab_to_rgb, rgb_to_lab};
+use jupiter_core::filters::color_balance::{saturation, white_balance, white_balance_gains};
+use jupiter_core::filters::curves::{apply_curve, ToneCurve};
+use jupiter_core::filters::levels::levels;
+use jupiter_core::frame::{ColorFrame, Frame};
+use jupiter_core::pipeline::apply_filter_step_color;
+use jupiter_core::pipeline::config::{ColorChannel, FilterStep, WhiteBalance};
+
+fn ramp() -> Frame {
+    Frame::new(
+        Array2::from_shape_fn((16, 64), |(_, c)| c as f32 / 63.0),
+        16,
+    )
+}
+
+fn color(red: f32, green: f32, blue: f32) -> ColorFrame {
+    let make = |v: f32| Frame::new(Array2::from_elem((16, 16), v), 16);
+    ColorFrame {
+        red: make(red),
+        green: make(green),
+        blue: make(blue),
+    }
+}
+
+/// Bright disk of the given colour on a dark, bluish sky.
+fn disk(red: f32, green: f32, blue: f32) -> ColorFrame {
+    let inside = |r: usize, c: usize| (r as f32 - 48.0).hypot(c as f32 - 48.0) < 30.0;
+    let channel = |disk_value: f32, sky: f32| {
+        Frame::new(
+            Array2::from_shape_fn(
+                (96, 96),
+                |(r, c)| if inside(r, c) { disk_value } else { sky },
+            ),
+            16,
+        )
+    };
+    ColorFrame {
+        red: channel(red, 0.02),
+        green: channel(green, 0.02),
+        blue: channel(blue, 0.1),
+    }
+}
+
+#[test]
+fn test_curve_passes_through_points_without_overshoot() {
+    let points = [[0.0, 0.0], [0.2, 0.5], [0.25, 0.55], [1.0, 1.0]];
+    let curve = ToneCurve::new(&points);
+    for [x, y] in points {
+        assert!((curve.eval(x) - y).abs() < 1e-5);
+    }
+    let mut previous = curve.eval(0.0);
+    for i in 1..=200 {
+        let v = curve.eval(i as f32 / 200.0);
+        assert!(v >= previous - 1e-6, "curve falls at {i}");
+        assert!(v <= 1.0 + 1e-6);
+        previous = v;
+    }
+
+    let identity = apply_curve(&ramp(), &[[0.0, 0.0], [1.0, 1.0]]);
+    let diff = identity
+        .data
+        .iter()
+        .zip(ramp().data.iter())
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0f32, f32::max);
+    assert!(diff < 1e-3, "identity curve changed the image by {diff}");
+}
+
+#[test]
+fn test_levels_maps_black_white_and_gamma() {
+    let result = levels(&ramp(), 0.25, 0.75, 2.0);
+    let at = |c: usize| result.data[[0, c]];
+    assert_eq!(at(0), 0.0);
+    assert_eq!(at(10), 0.0);
+    assert_eq!(at(63), 1.0);
+    // Input 0.5 is halfway between the points: 0.5^(1/2).
+    let mid = levels(
+        &Frame::new(Array2::from_elem((1, 1), 0.5), 16),
+        0.25,
+        0.75,
+        2.0,
+    );
+    assert!((mid.data[[0, 0]] - 0.5f32.sqrt()).abs() < 1e-5);
+}
+
+#[test]
+fn test_lab_round_trip() {
+    for rgb in [
+        [0.0, 0.0, 0.0],
+        [1.0, 1.0, 1.0],
+        [0.8, 0.3, 0.1],
+        [0.05, 0.4, 0.9],
+    ] {
+        let back = lab_to_rgb(rgb_to_lab(rgb));
+        for (a, b) in rgb.iter().zip(back.iter()) {
+            assert!((a - b).abs() < 1e-4, "{rgb:?} -> {back:?}");
+        }
+    }
+    let white = rgb_to_lab([1.0, 1.0, 1.0]);
+    assert!((white[0] - 100.0).abs() < 0.01 && white[1].abs() < 0.01 && white[2].abs() < 0.01);
+}
+
+#[test]
+fn test_saturation_and_vibrance() {
+    let image = color(0.7, 0.4, 0.2);
+    let unchanged = saturation(&image, 1.0, 0.0);
+    assert!((unchanged.red.data[[0, 0]] - 0.7).abs() < 1e-4);
+    assert!((unchanged.blue.data[[0, 0]] - 0.2).abs() < 1e-4);
+
+    let grey = saturation(&image, 0.0, 0.0);
+    let (r, g, b) = (
+        grey.red.data[[0, 0]],
+        grey.green.data[[0, 0]],
+        grey.blue.data[[0, 0]],
+    );
+    assert!((r - g).abs() < 1e-3 && (g - b).abs() < 1e-3, "{r} {g} {b}");
+    // Lightness is kept.
+    let before = rgb_to_lab([0.7, 0.4, 0.2])[0];
+    assert!((rgb_to_lab([r, g, b])[0] - before).abs() < 0.1);
+
+    // Vibrance boosts a dull colour more than a vivid one.
+    let spread = |c: &ColorFrame| c.red.data[[0, 0]] - c.blue.data[[0, 0]];
+    let dull = color(0.5, 0.45, 0.4);
+    let vivid = color(0.9, 0.3, 0.05);
+    let gain = |c: &ColorFrame| spread(&saturation(c, 1.0, 1.0)) / spread(c);
+    assert!(gain(&dull) > 1.5, "dull colour boosted by {}", gain(&dull));
+    assert!((gain(&vivid) - 1.0).abs() < 0.05);
+}
+
+#[test]
+fn test_white_balance_modes() {
+    let cast = color(0.6, 0.4, 0.2);
+    let balanced = white_balance(&cast, &WhiteBalance::GreyWorld);
+    let means = [&balanced.red, &balanced.green, &balanced.blue].map(|f| f.data[[0, 0]]);
+    assert!((means[0] - 0.4).abs() < 1e-4 && (means[2] - 0.4).abs() < 1e-4);
+
+    let manual = WhiteBalance::Manual {
+        red: 0.5,
+        green: 1.0,
+        blue: 2.0,
+    };
+    assert_eq!(white_balance_gains(&cast, &manual), [0.5, 1.0, 2.0]);
+
+    // The bluish sky skews grey world, but not the disk-based balance.
+    let planet = disk(0.6, 0.4, 0.3);
+    let [r, g, b] = white_balance_gains(&planet, &WhiteBalance::PlanetDisk);
+    assert!((0.6 * r - 0.4 * g).abs() < 1e-3 && (0.4 * g - 0.3 * b).abs() < 1e-3);
+    let [r, _, b] = white_balance_gains(&planet, &WhiteBalance::GreyWorld);
+    assert!((0.6 * r - 0.3 * b).abs() > 0.01);
+}
+
+#[test]
+fn test_channel_targeting() {
+    let image = color(0.5, 0.4, 0.3);
+    let darken = vec![[0.0, 0.0], [0.5, 0.25], [1.0, 1.0]];
+
+    let red_only = apply_filter_step_color(
+        &image,
+        &FilterStep::Curves {
+            channel: ColorChannel::Red,
+            points: darken.clone(),
+        },
+    );
+    assert!(red_only.red.data[[0, 0]] < 0.3);
+    assert_eq!(red_only.green.data[[0, 0]], 0.4);
+    assert_eq!(red_only.blue.data[[0, 0]], 0.3);
+
+    // Luminance keeps the channel ratios.
+    let lum = apply_filter_step_color(
+        &image,
+        &FilterStep::Levels {
+            channel: ColorChannel::Luminance,
+            black_point: 0.0,
+            white_point: 1.0,
+            gamma: 0.5,
+        },
+    );
+    let (r, g, b) = (
+        lum.red.data[[0, 0]],
+        lum.green.data[[0, 0]],
+        lum.blue.data[[0, 0]],
+    );
+    assert!(r < 0.5);
+    assert!((r / g - 1.25).abs() < 1e-3 && (g / b - 0.4 / 0.3).abs() < 1e-3);
+}
+
+#[test]
+fn test_color_step_serde_and_display() {
+    let step: FilterStep =
+        serde_json::from_str(r#"{"Curves":{"points":[[0.0,0.0],[0.5,0.6],[1.0,1.0]]}}"#).unwrap();
+    assert!(matches!(
+        step,
+        FilterStep::Curves {
+            channel: ColorChannel::All,
+            ..
+        }
+    ));
+    assert_eq!(format!("{step}"), "Curves (RGB, 3 points)");
+
+    let step: FilterStep = serde_json::from_str(r#"{"WhiteBalance":"PlanetDisk"}"#).unwrap();
+    assert_eq!(format!("{step}"), "White Balance (Planet Disk)");
+
+    let step: FilterStep = serde_json::from_str(r#"{"Saturation":{"saturation":1.2}}"#).unwrap();
+    assert!(matches!(step, FilterStep::Saturation { vibrance, .. } if vibrance == 0.0));
+}
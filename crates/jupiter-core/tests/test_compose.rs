@@ -0,0 +1,174 @@
+#[allow(dead_code)]
+mod common;
+
+use ndarray::Array2;
+
+use jupiter_core::align::phase_correlation::bilinear_sample;
+use jupiter_core::align::similarity::{compute_similarity, warp_similarity};
+use jupiter_core::color::compose::compose;
+use jupiter_core::color::lab::rgb_to_lab;
+use jupiter_core::frame::Frame;
+use jupiter_core::pipeline::config::{ChannelRole, CompositionConfig, RegistrationMode};
+
+/// Rotate `frame` by `rotation` radians and scale it by `scale` about its
+/// centre, then shift it by (`dx`, `dy`).
+fn transform(frame: &Frame, rotation: f64, scale: f64, dx: f64, dy: f64) -> Frame {
+    let (h, w) = frame.data.dim();
+    let (cy, cx) = ((h as f64 - 1.0) / 2.0, (w as f64 - 1.0) / 2.0);
+    let (sin, cos) = rotation.sin_cos();
+    let data = Array2::from_shape_fn((h, w), |(r, c)| {
+        let (y, x) = (r as f64 - dy - cy, c as f64 - dx - cx);
+        let src_x = cx + (x * cos + y * sin) / scale;
+        let src_y = cy + (-x * sin + y * cos) / scale;
+        bilinear_sample(&frame.data, src_y, src_x)
+    });
+    Frame::new(data, frame.original_bit_depth)
+}
+
+fn mean_abs_diff(a: &Frame, b: &Frame, margin: usize) -> f32 {
+    let (h, w) = a.data.dim();
+    let mut sum = 0.0;
+    let mut n = 0;
+    for r in margin..h - margin {
+        for c in margin..w - margin {
+            sum += (a.data[[r, c]] - b.data[[r, c]]).abs();
+            n += 1;
+        }
+    }
+    sum / n as f32
+}
+
+#[test]
+fn test_similarity_recovers_rotation_and_scale() {
+    let reference = Frame::new(common::planet(), 16);
+    let target = transform(&reference, 0.2, 1.05, 3.0, -2.0);
+
+    let found = compute_similarity(&reference, &target).unwrap();
+    assert!(
+        (found.rotation - 0.2).abs() < 0.02,
+        "rotation {}",
+        found.rotation
+    );
+    assert!((found.scale - 1.05).abs() < 0.02, "scale {}", found.scale);
+
+    let registered = warp_similarity(&target, &found);
+    let before = mean_abs_diff(&reference, &target, 16);
+    let after = mean_abs_diff(&reference, &registered, 16);
+    assert!(after < before * 0.25, "difference {before} -> {after}");
+}
+
+#[test]
+fn test_similarity_identity() {
+    let reference = Frame::new(common::planet(), 16);
+    let found = compute_similarity(&reference, &reference).unwrap();
+    assert!(found.rotation.abs() < 0.01, "rotation {}", found.rotation);
+    assert!((found.scale - 1.0).abs() < 0.01, "scale {}", found.scale);
+    assert!(found.offset.dx.abs() < 0.5 && found.offset.dy.abs() < 0.5);
+}
+
+fn flat(value: f32) -> Frame {
+    Frame::new(Array2::from_elem((32, 32), value), 16)
+}
+
+fn unnormalized(config: CompositionConfig) -> CompositionConfig {
+    CompositionConfig {
+        registration: RegistrationMode::None,
+        normalize: false,
+        ..config
+    }
+}
+
+#[test]
+fn test_compose_maps_channels() {
+    let images = [
+        (ChannelRole::Red, flat(0.6)),
+        (ChannelRole::Green, flat(0.4)),
+        (ChannelRole::Blue, flat(0.2)),
+        (ChannelRole::Methane, flat(0.9)),
+    ];
+    let rgb = compose(&images, &unnormalized(CompositionConfig::rgb())).unwrap();
+    assert_eq!(rgb.red.data[[0, 0]], 0.6);
+    assert_eq!(rgb.green.data[[0, 0]], 0.4);
+    assert_eq!(rgb.blue.data[[0, 0]], 0.2);
+
+    let config = unnormalized(CompositionConfig::false_color(ChannelRole::Green));
+    let false_color = compose(&images, &config).unwrap();
+    assert_eq!(false_color.red.data[[0, 0]], 0.6);
+    assert_eq!(false_color.green.data[[0, 0]], 0.9);
+    assert_eq!(false_color.blue.data[[0, 0]], 0.2);
+}
+
+#[test]
+fn test_lrgb_takes_lightness_and_keeps_hue() {
+    let images = [
+        (ChannelRole::Luminance, flat(0.8)),
+        (ChannelRole::Red, flat(0.3)),
+        (ChannelRole::Green, flat(0.2)),
+        (ChannelRole::Blue, flat(0.1)),
+    ];
+    let lrgb = compose(&images, &unnormalized(CompositionConfig::lrgb())).unwrap();
+    let pixel = [&lrgb.red, &lrgb.green, &lrgb.blue].map(|f| f.data[[0, 0]]);
+    let lab = rgb_to_lab(pixel);
+    let target = rgb_to_lab([0.8, 0.8, 0.8])[0];
+    assert!((lab[0] - target).abs() < 0.5, "L* {} vs {target}", lab[0]);
+    let before = rgb_to_lab([0.3, 0.2, 0.1]);
+    assert!((lab[2].atan2(lab[1]) - before[2].atan2(before[1])).abs() < 0.05);
+
+    let unlayered = CompositionConfig {
+        luminance_weight: 0.0,
+        ..unnormalized(CompositionConfig::lrgb())
+    };
+    let unchanged = compose(&images, &unlayered).unwrap();
+    assert!((unchanged.red.data[[0, 0]] - 0.3).abs() < 1e-3);
+}
+
+#[test]
+fn test_compose_registers_and_crops() {
+    let reference = Frame::new(common::planet(), 16);
+    let shifted = transform(&reference, 0.0, 1.0, 4.0, -3.0);
+    let images = [
+        (ChannelRole::Red, shifted.clone()),
+        (ChannelRole::Green, reference.clone()),
+        (ChannelRole::Blue, shifted),
+    ];
+    let config = CompositionConfig {
+        normalize: false,
+        ..CompositionConfig::rgb()
+    };
+    let color = compose(&images, &config).unwrap();
+    assert!(mean_abs_diff(&color.red, &reference, 12) < 0.01);
+
+    // A larger image is cropped about its centre.
+    let mut padded = Array2::from_elem((68, 72), 0.02f32);
+    padded
+        .slice_mut(ndarray::s![2..66, 4..68])
+        .assign(&reference.data);
+    let images = [
+        (ChannelRole::Red, Frame::new(padded, 16)),
+        (ChannelRole::Green, reference.clone()),
+        (ChannelRole::Blue, reference.clone()),
+    ];
+    let color = compose(&images, &unnormalized(CompositionConfig::rgb())).unwrap();
+    assert_eq!(color.red.data.dim(), (64, 64));
+    assert!(mean_abs_diff(&color.red, &reference, 0) < 1e-6);
+}
+
+#[test]
+fn test_compose_requires_roles() {
+    let images = [
+        (ChannelRole::Red, flat(0.5)),
+        (ChannelRole::Green, flat(0.5)),
+        (ChannelRole::Blue, flat(0.5)),
+    ];
+    let err = compose(&images, &CompositionConfig::ir_rgb()).unwrap_err();
+    assert!(err.to_string().contains("IR"), "{err}");
+    assert_eq!(
+        CompositionConfig::ir_rgb().required_roles(),
+        vec![
+            ChannelRole::Red,
+            ChannelRole::Green,
+            ChannelRole::Blue,
+            ChannelRole::Infrared
+        ]
+    );
+}
@@ -132,7 +132,10 @@ fn test_stack_method_display_drizzle() {
 
 #[test]
 fn test_deconvolution_method_display_rl() {
-    let m = DeconvolutionMethod::RichardsonLucy { iterations: 10 };
+    let m = DeconvolutionMethod::RichardsonLucy {
+        iterations: 10,
+        options: Default::default(),
+    };
     let s = format!("{}", m);
     assert!(s.contains("Richardson-Lucy"), "got: {s}");
     assert!(s.contains("10"), "got: {s}");
@@ -315,3 +318,29 @@ fn test_pipeline_output_to_mono_from_color() {
         );
     }
 }
+
+// ---------------------------------------------------------------------------
+// PostProcessConfig
+// ---------------------------------------------------------------------------
+
+#[test]
+fn test_post_process_config_reads_pipeline_config() {
+    use jupiter_core::pipeline::config::PostProcessConfig;
+
+    let recipe: PostProcessConfig = serde_json::from_str(
+        r#"{
+            "input": "jupiter.ser",
+            "stacking": {"method": "Median"},
+            "sharpening": {"wavelet": {"num_layers": 4, "coefficients": [1.5, 1.2, 1.0, 1.0], "denoise": []}},
+            "filters": [{"Gamma": 1.2}, {"AutoStretch": {"low_percentile": 0.001, "high_percentile": 0.999}}]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(recipe.sharpening.unwrap().wavelet.num_layers, 4);
+    assert_eq!(recipe.filters.len(), 2);
+    assert!(matches!(recipe.filters[0], FilterStep::Gamma(g) if (g - 1.2).abs() < 1e-6));
+
+    let empty: PostProcessConfig = serde_json::from_str("{}").unwrap();
+    assert!(empty.sharpening.is_none());
+    assert!(empty.filters.is_empty());
+}
@@ -421,6 +421,7 @@ fn test_color_pipeline_end_to_end() {
 
     let config = PipelineConfig {
         input: ser_file.path().to_path_buf(),
+        extra_inputs: vec![],
         output: output_path.clone(),
         device: Default::default(),
         memory: Default::default(),
@@ -464,6 +465,7 @@ fn test_force_mono_ignores_bayer() {
 
     let config = PipelineConfig {
         input: ser_file.path().to_path_buf(),
+        extra_inputs: vec![],
         output: output_path.clone(),
         device: Default::default(),
         memory: Default::default(),
@@ -18,13 +18,17 @@ fn flat_frame(h: usize, w: usize, value: f32) -> Frame {
     make_frame(Array2::from_elem((h, w), value))
 }
 
+fn analytic_psf(model: &PsfModel, h: usize, w: usize) -> Array2<f32> {
+    generate_psf(model, (h, w)).unwrap()
+}
+
 // ---------------------------------------------------------------------------
 // PSF generation tests
 // ---------------------------------------------------------------------------
 
 #[test]
 fn gaussian_psf_sums_to_one() {
-    let psf = generate_psf(&PsfModel::Gaussian { sigma: 2.0 }, 64, 64);
+    let psf = analytic_psf(&PsfModel::Gaussian { sigma: 2.0 }, 64, 64);
     let sum: f32 = psf.iter().sum();
     assert!(
         (sum - 1.0).abs() < 1e-4,
@@ -34,7 +38,7 @@ fn gaussian_psf_sums_to_one() {
 
 #[test]
 fn kolmogorov_psf_sums_to_one() {
-    let psf = generate_psf(&PsfModel::Kolmogorov { seeing: 3.0 }, 64, 64);
+    let psf = analytic_psf(&PsfModel::Kolmogorov { seeing: 3.0 }, 64, 64);
     let sum: f32 = psf.iter().sum();
     assert!(
         (sum - 1.0).abs() < 1e-3,
@@ -44,7 +48,7 @@ fn kolmogorov_psf_sums_to_one() {
 
 #[test]
 fn airy_psf_sums_to_one() {
-    let psf = generate_psf(&PsfModel::Airy { radius: 2.5 }, 64, 64);
+    let psf = analytic_psf(&PsfModel::Airy { radius: 2.5 }, 64, 64);
     let sum: f32 = psf.iter().sum();
     assert!(
         (sum - 1.0).abs() < 1e-3,
@@ -54,7 +58,7 @@ fn airy_psf_sums_to_one() {
 
 #[test]
 fn gaussian_psf_peak_at_origin() {
-    let psf = generate_psf(&PsfModel::Gaussian { sigma: 2.0 }, 64, 64);
+    let psf = analytic_psf(&PsfModel::Gaussian { sigma: 2.0 }, 64, 64);
     let max_val = *psf.iter().max_by(|a, b| a.total_cmp(b)).unwrap();
     assert!(
         (psf[[0, 0]] - max_val).abs() < 1e-6,
@@ -65,7 +69,7 @@ fn gaussian_psf_peak_at_origin() {
 
 #[test]
 fn airy_psf_peak_at_origin() {
-    let psf = generate_psf(&PsfModel::Airy { radius: 3.0 }, 64, 64);
+    let psf = analytic_psf(&PsfModel::Airy { radius: 3.0 }, 64, 64);
     let max_val = *psf.iter().max_by(|a, b| a.total_cmp(b)).unwrap();
     assert!(
         (psf[[0, 0]] - max_val).abs() < 1e-6,
@@ -75,7 +79,7 @@ fn airy_psf_peak_at_origin() {
 
 #[test]
 fn gaussian_psf_is_symmetric() {
-    let psf = generate_psf(&PsfModel::Gaussian { sigma: 1.5 }, 32, 32);
+    let psf = analytic_psf(&PsfModel::Gaussian { sigma: 1.5 }, 32, 32);
     // psf[r, c] should equal psf[h-r, c] and psf[r, w-c] (wrap-around symmetry)
     for r in 1..16 {
         let mirror_r = 32 - r;
@@ -94,7 +98,7 @@ fn gaussian_psf_is_symmetric() {
 
 #[test]
 fn gaussian_psf_all_nonnegative() {
-    let psf = generate_psf(&PsfModel::Gaussian { sigma: 1.0 }, 32, 32);
+    let psf = analytic_psf(&PsfModel::Gaussian { sigma: 1.0 }, 32, 32);
     assert!(
         psf.iter().all(|&v| v >= 0.0),
         "Gaussian PSF should have no negative values"
@@ -103,7 +107,7 @@ fn gaussian_psf_all_nonnegative() {
 
 #[test]
 fn kolmogorov_psf_all_nonnegative() {
-    let psf = generate_psf(&PsfModel::Kolmogorov { seeing: 2.0 }, 32, 32);
+    let psf = analytic_psf(&PsfModel::Kolmogorov { seeing: 2.0 }, 32, 32);
     assert!(
         psf.iter().all(|&v| v >= 0.0),
         "Kolmogorov PSF should have no negative values"
@@ -112,7 +116,7 @@ fn kolmogorov_psf_all_nonnegative() {
 
 #[test]
 fn airy_psf_all_nonnegative() {
-    let psf = generate_psf(&PsfModel::Airy { radius: 2.0 }, 32, 32);
+    let psf = analytic_psf(&PsfModel::Airy { radius: 2.0 }, 32, 32);
     assert!(
         psf.iter().all(|&v| v >= 0.0),
         "Airy PSF should have no negative values"
@@ -123,7 +127,7 @@ fn airy_psf_all_nonnegative() {
 fn psf_different_sizes() {
     // Ensure PSF generation works for non-square and various sizes
     for &(h, w) in &[(32, 64), (64, 32), (17, 17), (128, 128)] {
-        let psf = generate_psf(&PsfModel::Gaussian { sigma: 2.0 }, h, w);
+        let psf = analytic_psf(&PsfModel::Gaussian { sigma: 2.0 }, h, w);
         assert_eq!(psf.dim(), (h, w));
         let sum: f32 = psf.iter().sum();
         assert!((sum - 1.0).abs() < 1e-3, "PSF sum for {h}x{w} = {sum}");
@@ -189,10 +193,14 @@ fn bessel_j1_large_argument() {
 fn rl_flat_image_unchanged() {
     let frame = flat_frame(32, 32, 0.5);
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 10 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 10,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
 
     for r in 0..32 {
         for c in 0..32 {
@@ -211,8 +219,9 @@ fn wiener_flat_image_unchanged() {
     let config = DeconvolutionConfig {
         method: DeconvolutionMethod::Wiener { noise_ratio: 0.001 },
         psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
 
     for r in 0..32 {
         for c in 0..32 {
@@ -239,10 +248,14 @@ fn rl_output_in_valid_range() {
     }
     let frame = make_frame(data);
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 20 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 20,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 1.5 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
 
     assert!(
         result.data.iter().all(|&v| (0.0..=1.0).contains(&v)),
@@ -262,8 +275,9 @@ fn wiener_output_in_valid_range() {
     let config = DeconvolutionConfig {
         method: DeconvolutionMethod::Wiener { noise_ratio: 0.01 },
         psf: PsfModel::Gaussian { sigma: 1.5 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
 
     assert!(
         result.data.iter().all(|&v| (0.0..=1.0).contains(&v)),
@@ -285,10 +299,14 @@ fn rl_zero_iterations_returns_input() {
     }
     let frame = make_frame(data.clone());
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 0 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 0,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
 
     for r in 0..32 {
         for c in 0..32 {
@@ -310,10 +328,14 @@ fn rl_zero_iterations_returns_input() {
 fn deconvolve_preserves_bit_depth() {
     let frame = Frame::new(Array2::from_elem((16, 16), 0.5f32), 16);
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 5 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 5,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 1.0 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
     assert_eq!(result.original_bit_depth, 16);
 }
 
@@ -327,8 +349,9 @@ fn deconvolve_preserves_dimensions() {
     let config = DeconvolutionConfig {
         method: DeconvolutionMethod::Wiener { noise_ratio: 0.01 },
         psf: PsfModel::Kolmogorov { seeing: 3.0 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
     assert_eq!(result.data.dim(), (48, 64));
 }
 
@@ -348,10 +371,14 @@ fn deconvolve_dispatch_all_psf_models() {
 
     for psf in psf_models {
         let config = DeconvolutionConfig {
-            method: DeconvolutionMethod::RichardsonLucy { iterations: 3 },
+            method: DeconvolutionMethod::RichardsonLucy {
+                iterations: 3,
+                options: Default::default(),
+            },
             psf: psf.clone(),
+            tiling: None,
         };
-        let result = deconvolve(&frame, &config);
+        let result = deconvolve(&frame, &config).unwrap();
         assert_eq!(result.data.dim(), (32, 32), "Failed for PSF {:?}", psf);
     }
 }
@@ -361,7 +388,10 @@ fn deconvolve_dispatch_both_methods() {
     let frame = flat_frame(32, 32, 0.4);
 
     let methods = vec![
-        DeconvolutionMethod::RichardsonLucy { iterations: 3 },
+        DeconvolutionMethod::RichardsonLucy {
+            iterations: 3,
+            options: Default::default(),
+        },
         DeconvolutionMethod::Wiener { noise_ratio: 0.01 },
     ];
 
@@ -369,8 +399,9 @@ fn deconvolve_dispatch_both_methods() {
         let config = DeconvolutionConfig {
             method: method.clone(),
             psf: PsfModel::Gaussian { sigma: 1.5 },
+            tiling: None,
         };
-        let result = deconvolve(&frame, &config);
+        let result = deconvolve(&frame, &config).unwrap();
         assert_eq!(
             result.data.dim(),
             (32, 32),
@@ -423,10 +454,14 @@ fn rl_recovers_sharpness_from_blurred_image() {
     // Deconvolve
     let frame = make_frame(blurred);
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 15 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 15,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
 
     let restored_grad = (result.data[[32, 21]] - result.data[[32, 19]]).abs();
 
@@ -476,8 +511,9 @@ fn wiener_recovers_sharpness_from_blurred_image() {
     let config = DeconvolutionConfig {
         method: DeconvolutionMethod::Wiener { noise_ratio: 0.001 },
         psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
 
     let restored_grad = (result.data[[32, 21]] - result.data[[32, 19]]).abs();
 
@@ -495,10 +531,14 @@ fn wiener_recovers_sharpness_from_blurred_image() {
 fn rl_black_image_stays_black() {
     let frame = flat_frame(32, 32, 0.0);
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 10 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 10,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
 
     let max_val = *result.data.iter().max_by(|a, b| a.total_cmp(b)).unwrap();
     assert!(
@@ -513,8 +553,9 @@ fn wiener_black_image_stays_black() {
     let config = DeconvolutionConfig {
         method: DeconvolutionMethod::Wiener { noise_ratio: 0.01 },
         psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
 
     let max_val = *result.data.iter().max_by(|a, b| a.total_cmp(b)).unwrap();
     assert!(
@@ -530,8 +571,12 @@ fn wiener_black_image_stays_black() {
 #[test]
 fn deconvolution_config_serde_roundtrip() {
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 25 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 25,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 1.8 },
+        tiling: None,
     };
     let json = serde_json::to_string(&config).unwrap();
     let restored: DeconvolutionConfig = serde_json::from_str(&json).unwrap();
@@ -544,6 +589,7 @@ fn deconvolution_config_serde_wiener_kolmogorov() {
     let config = DeconvolutionConfig {
         method: DeconvolutionMethod::Wiener { noise_ratio: 0.005 },
         psf: PsfModel::Kolmogorov { seeing: 3.5 },
+        tiling: None,
     };
     let json = serde_json::to_string(&config).unwrap();
     let restored: DeconvolutionConfig = serde_json::from_str(&json).unwrap();
@@ -553,8 +599,12 @@ fn deconvolution_config_serde_wiener_kolmogorov() {
 #[test]
 fn deconvolution_config_serde_airy() {
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 10 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 10,
+            options: Default::default(),
+        },
         psf: PsfModel::Airy { radius: 2.5 },
+        tiling: None,
     };
     let json = serde_json::to_string(&config).unwrap();
     let restored: DeconvolutionConfig = serde_json::from_str(&json).unwrap();
@@ -577,16 +627,24 @@ fn rl_more_iterations_sharper() {
     let frame = make_frame(data);
 
     let config_few = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 3 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 3,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 1.5 },
+        tiling: None,
     };
     let config_many = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 20 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 20,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 1.5 },
+        tiling: None,
     };
 
-    let result_few = deconvolve(&frame, &config_few);
-    let result_many = deconvolve(&frame, &config_many);
+    let result_few = deconvolve(&frame, &config_few).unwrap();
+    let result_many = deconvolve(&frame, &config_many).unwrap();
 
     // More iterations should produce sharper edges (higher gradient at boundary)
     let grad_few = (result_few.data[[32, 21]] - result_few.data[[32, 19]]).abs();
@@ -618,14 +676,16 @@ fn wiener_higher_noise_ratio_smoother() {
             noise_ratio: 0.0001,
         },
         psf: PsfModel::Gaussian { sigma: 1.5 },
+        tiling: None,
     };
     let config_high = DeconvolutionConfig {
         method: DeconvolutionMethod::Wiener { noise_ratio: 0.1 },
         psf: PsfModel::Gaussian { sigma: 1.5 },
+        tiling: None,
     };
 
-    let result_low = deconvolve(&frame, &config_low);
-    let result_high = deconvolve(&frame, &config_high);
+    let result_low = deconvolve(&frame, &config_low).unwrap();
+    let result_high = deconvolve(&frame, &config_high).unwrap();
 
     // Higher noise_ratio suppresses deconvolution, so edge gradient should be less
     let grad_low = (result_low.data[[32, 21]] - result_low.data[[32, 19]]).abs();
@@ -651,10 +711,14 @@ fn rl_with_kolmogorov_psf() {
     }
     let frame = make_frame(data);
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 10 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 10,
+            options: Default::default(),
+        },
         psf: PsfModel::Kolmogorov { seeing: 3.0 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
     assert!(result.data.iter().all(|&v| (0.0..=1.0).contains(&v)));
 }
 
@@ -670,8 +734,9 @@ fn wiener_with_airy_psf() {
     let config = DeconvolutionConfig {
         method: DeconvolutionMethod::Wiener { noise_ratio: 0.01 },
         psf: PsfModel::Airy { radius: 2.5 },
+        tiling: None,
     };
-    let result = deconvolve(&frame, &config);
+    let result = deconvolve(&frame, &config).unwrap();
     assert!(result.data.iter().all(|&v| (0.0..=1.0).contains(&v)));
 }
 
@@ -682,7 +747,7 @@ fn wiener_with_airy_psf() {
 #[test]
 fn rewrap_psf_padded_identity_when_power_of_two() {
     // 32x32 is already a power of 2 — rewrap to same dims is a no-op.
-    let psf = generate_psf(&PsfModel::Gaussian { sigma: 2.0 }, 32, 32);
+    let psf = analytic_psf(&PsfModel::Gaussian { sigma: 2.0 }, 32, 32);
     let rewrapped = rewrap_psf_padded(&psf, 32, 32);
     for r in 0..32 {
         for c in 0..32 {
@@ -696,7 +761,7 @@ fn rewrap_psf_padded_identity_when_power_of_two() {
 
 #[test]
 fn rewrap_psf_padded_preserves_sum() {
-    let psf = generate_psf(&PsfModel::Gaussian { sigma: 2.0 }, 48, 48);
+    let psf = analytic_psf(&PsfModel::Gaussian { sigma: 2.0 }, 48, 48);
     let rewrapped = rewrap_psf_padded(&psf, 64, 64);
     let sum_orig: f32 = psf.iter().sum();
     let sum_rewrapped: f32 = rewrapped.iter().sum();
@@ -708,7 +773,7 @@ fn rewrap_psf_padded_preserves_sum() {
 
 #[test]
 fn rewrap_psf_padded_origin_preserved() {
-    let psf = generate_psf(&PsfModel::Gaussian { sigma: 2.0 }, 48, 48);
+    let psf = analytic_psf(&PsfModel::Gaussian { sigma: 2.0 }, 48, 48);
     let rewrapped = rewrap_psf_padded(&psf, 64, 64);
     // Origin [0,0] should be preserved (peak of the PSF)
     assert!(
@@ -724,7 +789,7 @@ fn rewrap_psf_padded_origin_preserved() {
 
 #[test]
 fn rewrap_psf_padded_wraparound_quadrant() {
-    let psf = generate_psf(&PsfModel::Gaussian { sigma: 2.0 }, 48, 48);
+    let psf = analytic_psf(&PsfModel::Gaussian { sigma: 2.0 }, 48, 48);
     let rewrapped = rewrap_psf_padded(&psf, 64, 64);
     // PSF[47, 47] (row=47, col=47 in 48x48) represents spatial offset (-1, -1).
     // In 64x64 layout, that should be at [63, 63].
@@ -750,7 +815,7 @@ fn rewrap_psf_padded_wraparound_quadrant() {
 #[test]
 fn gpu_rl_matches_cpu_rl_non_power_of_two() {
     use jupiter_core::compute::{create_backend, DevicePreference};
-    use jupiter_core::sharpen::deconvolution::deconvolve_gpu;
+    use jupiter_core::sharpen::deconvolution::{deconvolve_gpu, Psf};
 
     let backend = create_backend(&DevicePreference::Gpu);
     if !backend.is_gpu() {
@@ -768,12 +833,17 @@ fn gpu_rl_matches_cpu_rl_non_power_of_two() {
     let frame = make_frame(data);
 
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 5 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 5,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
     };
 
-    let cpu_result = deconvolve(&frame, &config);
-    let gpu_result = deconvolve_gpu(&frame, &config, &*backend);
+    let cpu_result = deconvolve(&frame, &config).unwrap();
+    let gpu_result =
+        deconvolve_gpu(&frame, &config, &Psf::load(&config.psf).unwrap(), &*backend).unwrap();
 
     // GPU uses f32, CPU uses f64 internally — allow some tolerance
     let max_diff = cpu_result
@@ -793,7 +863,7 @@ fn gpu_rl_matches_cpu_rl_non_power_of_two() {
 #[test]
 fn gpu_rl_flat_image_stable_non_power_of_two() {
     use jupiter_core::compute::{create_backend, DevicePreference};
-    use jupiter_core::sharpen::deconvolution::deconvolve_gpu;
+    use jupiter_core::sharpen::deconvolution::{deconvolve_gpu, Psf};
 
     let backend = create_backend(&DevicePreference::Gpu);
     if !backend.is_gpu() {
@@ -804,10 +874,15 @@ fn gpu_rl_flat_image_stable_non_power_of_two() {
     // so we check only interior pixels (margin of 8 pixels = 4x sigma).
     let frame = flat_frame(48, 48, 0.5);
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 10 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 10,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
     };
-    let result = deconvolve_gpu(&frame, &config, &*backend);
+    let result =
+        deconvolve_gpu(&frame, &config, &Psf::load(&config.psf).unwrap(), &*backend).unwrap();
 
     let margin = 8;
     for r in margin..48 - margin {
@@ -825,7 +900,7 @@ fn gpu_rl_flat_image_stable_non_power_of_two() {
 #[test]
 fn gpu_rl_recovers_sharpness_non_power_of_two() {
     use jupiter_core::compute::{create_backend, DevicePreference};
-    use jupiter_core::sharpen::deconvolution::deconvolve_gpu;
+    use jupiter_core::sharpen::deconvolution::{deconvolve_gpu, Psf};
 
     let backend = create_backend(&DevicePreference::Gpu);
     if !backend.is_gpu() {
@@ -865,10 +940,15 @@ fn gpu_rl_recovers_sharpness_non_power_of_two() {
 
     let frame = make_frame(blurred);
     let config = DeconvolutionConfig {
-        method: DeconvolutionMethod::RichardsonLucy { iterations: 15 },
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 15,
+            options: Default::default(),
+        },
         psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
     };
-    let result = deconvolve_gpu(&frame, &config, &*backend);
+    let result =
+        deconvolve_gpu(&frame, &config, &Psf::load(&config.psf).unwrap(), &*backend).unwrap();
 
     let restored_grad = (result.data[[24, 15]] - result.data[[24, 13]]).abs();
 
@@ -0,0 +1,128 @@
+#[allow(dead_code)]
+mod common;
+
+use ndarray::Array2;
+
+use jupiter_core::color::debayer::luminance;
+use jupiter_core::filters::denoise::{bilateral, guided_filter, non_local_means, wavelet_denoise};
+use jupiter_core::frame::{ColorFrame, Frame};
+use jupiter_core::pipeline::config::FilterStep;
+use jupiter_core::pipeline::{apply_filter_step, apply_filter_step_color};
+
+/// Gentle gradient with a sharp vertical step in the middle.
+fn scene() -> Array2<f32> {
+    Array2::from_shape_fn((64, 64), |(r, c)| {
+        let base = 0.25 + 0.002 * r as f32;
+        if c < 32 {
+            base
+        } else {
+            base + 0.4
+        }
+    })
+}
+
+/// `data` plus Gaussian-like noise of standard deviation 0.03.
+fn with_noise(data: &Array2<f32>, seed: u64) -> Array2<f32> {
+    let mut noisy = data.clone();
+    common::add_gaussian_noise(&mut noisy, seed, 0.03);
+    noisy
+}
+
+#[test]
+fn test_denoisers_reduce_noise_and_keep_the_edge() {
+    let clean = scene();
+    let noisy = Frame::new(with_noise(&clean, 1), 16);
+    let before = common::rms(noisy.data.view(), clean.view());
+    let jump = |f: &Frame| {
+        (0..64)
+            .map(|r| f.data[[r, 33]] - f.data[[r, 30]])
+            .sum::<f32>()
+            / 64.0
+    };
+
+    let results = [
+        ("nlm", non_local_means(&noisy, 1.0, 2, 5)),
+        ("bilateral", bilateral(&noisy, 2.0, 0.06)),
+        ("guided", guided_filter(&noisy, 3, 0.004)),
+        ("wavelet", wavelet_denoise(&noisy, 4, 1.0)),
+    ];
+    for (name, result) in &results {
+        let after = common::rms(result.data.view(), clean.view());
+        assert!(after < 0.7 * before, "{name}: rms {after} vs {before}");
+        assert!(jump(result) > 0.8 * 0.4, "{name}: edge {}", jump(result));
+        assert_eq!(result.data.dim(), (64, 64));
+    }
+}
+
+#[test]
+fn test_denoisers_leave_flat_image_unchanged() {
+    let flat = Frame::new(Array2::from_elem((32, 32), 0.4), 16);
+    for step in [
+        FilterStep::NonLocalMeans {
+            strength: 1.0,
+            patch_radius: 2,
+            search_radius: 4,
+            chroma_only: false,
+        },
+        FilterStep::Bilateral {
+            spatial_sigma: 2.0,
+            range_sigma: 0.05,
+            chroma_only: false,
+        },
+        FilterStep::GuidedFilter {
+            radius: 3,
+            epsilon: 0.001,
+            chroma_only: false,
+        },
+        FilterStep::WaveletDenoise {
+            layers: 4,
+            strength: 1.0,
+            chroma_only: false,
+        },
+    ] {
+        let result = apply_filter_step(&flat, &step);
+        assert!(
+            common::rms(result.data.view(), flat.data.view()) < 1e-5,
+            "{step}"
+        );
+    }
+}
+
+#[test]
+fn test_chroma_only_denoise_keeps_luminance() {
+    let base = scene().mapv(|v| v * 0.8 + 0.05);
+    let color = ColorFrame {
+        red: Frame::new(with_noise(&base, 2), 16),
+        green: Frame::new(base.clone(), 16),
+        blue: Frame::new(with_noise(&base, 3), 16),
+    };
+    let step = FilterStep::WaveletDenoise {
+        layers: 4,
+        strength: 1.0,
+        chroma_only: true,
+    };
+    let result = apply_filter_step_color(&color, &step);
+
+    let luma_change = common::rms(
+        luminance(&result).data.view(),
+        luminance(&color).data.view(),
+    );
+    assert!(luma_change < 1e-4, "luminance changed by {luma_change}");
+    let chroma = |c: &ColorFrame| &c.red.data - &c.green.data;
+    assert!(common::std_dev(chroma(&result).view()) < 0.7 * common::std_dev(chroma(&color).view()));
+}
+
+#[test]
+fn test_denoise_step_serde_and_display() {
+    let step: FilterStep =
+        serde_json::from_str(r#"{"Bilateral":{"spatial_sigma":2.0,"range_sigma":0.05}}"#).unwrap();
+    assert!(!step.chroma_only());
+    assert!(format!("{step}").starts_with("Bilateral"));
+
+    let step: FilterStep = serde_json::from_str(
+        r#"{"NonLocalMeans":{"strength":1.0,"patch_radius":2,"search_radius":5,"chroma_only":true}}"#,
+    )
+    .unwrap();
+    assert!(step.chroma_only());
+    assert!(format!("{step}").ends_with("[chroma]"));
+}
@@ -0,0 +1,191 @@
+#[allow(dead_code)]
+mod common;
+
+use std::sync::Arc;
+use tempfile::TempDir;
+
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::error::JupiterError;
+use jupiter_core::frame::{AlignmentOffset, QualityScore};
+use jupiter_core::io::frame_report::{select_listed, FrameReport};
+use jupiter_core::pipeline::config::{
+    FrameSelectionConfig, PipelineConfig, QualityMetric, StackMethod, StackingConfig,
+};
+use jupiter_core::pipeline::run_pipeline;
+use jupiter_core::stack::multi_point::MultiPointConfig;
+use jupiter_core::stack::optical_flow::OpticalFlowConfig;
+use jupiter_core::stack::surface_warp::SurfaceWarpConfig;
+
+fn score(v: f64) -> QualityScore {
+    QualityScore {
+        laplacian_variance: v,
+        composite: v,
+    }
+}
+
+fn sample_report() -> FrameReport {
+    let mut report = FrameReport::new(4);
+    let ranked = vec![
+        (2, score(0.9)),
+        (0, score(0.7)),
+        (3, score(0.4)),
+        (1, score(0.1)),
+    ];
+    report.record_scores(&QualityMetric::Laplacian, &ranked);
+    report.mark_selected(&[2, 0]);
+    report.record_offsets(
+        &[2, 0],
+        &[
+            AlignmentOffset::default(),
+            AlignmentOffset { dx: 1.25, dy: -0.5 },
+        ],
+        Some(&[f64::INFINITY, 7.5]),
+    );
+    report
+}
+
+#[test]
+fn test_csv_roundtrip() {
+    let report = sample_report();
+    let csv = report.to_csv();
+    assert!(csv.starts_with("frame_index,"));
+    let parsed = FrameReport::from_csv(&csv).unwrap();
+    assert_eq!(parsed, report);
+    assert_eq!(parsed.selected_indices(), vec![0, 2]);
+    assert_eq!(
+        parsed.frames[2].confidence, None,
+        "reference has no confidence"
+    );
+    assert_eq!(parsed.frames[0].confidence, Some(7.5));
+}
+
+#[test]
+fn test_json_roundtrip_via_files() {
+    let report = sample_report();
+    let dir = TempDir::new().unwrap();
+    for name in ["frames.json", "frames.csv"] {
+        let path = dir.path().join(name);
+        report.save(&path).unwrap();
+        assert_eq!(FrameReport::load(&path).unwrap(), report);
+    }
+}
+
+#[test]
+fn test_csv_accepts_edited_columns() {
+    // Reordered, trimmed, spreadsheet-style flags.
+    let csv = "selected,frame_index\n1,3\n,0\nyes,1\n";
+    let report = FrameReport::from_csv(csv).unwrap();
+    assert_eq!(report.selected_indices(), vec![3, 1]);
+
+    assert!(matches!(
+        FrameReport::from_csv("composite\n0.5\n"),
+        Err(JupiterError::InvalidReport(_))
+    ));
+    assert!(matches!(
+        FrameReport::from_csv("frame_index,selected\n0,maybe\n"),
+        Err(JupiterError::InvalidReport(_))
+    ));
+}
+
+#[test]
+fn test_select_listed_keeps_rank_order() {
+    let ranked = vec![
+        (2, score(0.9)),
+        (0, score(0.7)),
+        (3, score(0.4)),
+        (1, score(0.1)),
+    ];
+    let kept = select_listed(&ranked, &[1, 2], 4).unwrap();
+    let order: Vec<usize> = kept.iter().map(|(i, _)| *i).collect();
+    assert_eq!(order, vec![2, 1]);
+
+    assert!(select_listed(&ranked, &[], 4).is_err());
+    assert!(matches!(
+        select_listed(&ranked, &[7], 4),
+        Err(JupiterError::FrameIndexOutOfRange { index: 7, total: 4 })
+    ));
+}
+
+/// Stack six frames of increasing brightness with `method`, listing only
+/// frame 0, and return the centre value of the result.
+fn stack_listed_frame(method: StackMethod) -> f32 {
+    let frames: Vec<Vec<u8>> = (0..6)
+        .map(|i| {
+            let mut f = vec![10u8; 64 * 64];
+            for r in 16..48 {
+                for c in 16..48 {
+                    f[r * 64 + c] = 60 + 30 * i as u8 + 20 * ((r / 4 + c / 4) % 2) as u8;
+                }
+            }
+            f
+        })
+        .collect();
+    let ser_file = common::write_test_ser(&common::build_ser_with_frames(64, 64, &frames));
+    let dir = TempDir::new().unwrap();
+
+    let mut report = FrameReport::new(6);
+    report.mark_selected(&[0]);
+    let list_path = dir.path().join("frames.csv");
+    report.save(&list_path).unwrap();
+
+    let config = PipelineConfig {
+        input: ser_file.path().to_path_buf(),
+        extra_inputs: vec![],
+        output: dir.path().join("out.tiff"),
+        device: Default::default(),
+        memory: Default::default(),
+        debayer: None,
+        force_mono: false,
+        frame_selection: FrameSelectionConfig {
+            select_percentage: 1.0,
+            frame_list: Some(list_path),
+            ..Default::default()
+        },
+        alignment: Default::default(),
+        stacking: StackingConfig {
+            method,
+            ..Default::default()
+        },
+        sharpening: None,
+        filters: vec![],
+    };
+
+    let result = run_pipeline(&config, Arc::new(CpuBackend), |_, _| {})
+        .unwrap()
+        .to_mono();
+    result.data[[33, 33]]
+}
+
+#[test]
+fn test_pipeline_uses_frame_list() {
+    // Only the dimmest frame is listed; the stacked square must match it.
+    let expected = 60.0 / 255.0;
+    let value = stack_listed_frame(StackMethod::Mean);
+    assert!(
+        (value - expected).abs() < 1e-3,
+        "expected only frame 0 stacked, got {value}"
+    );
+}
+
+#[test]
+fn test_frame_list_reaches_self_selecting_methods() {
+    let expected = 60.0 / 255.0;
+    for method in [
+        StackMethod::MultiPoint(MultiPointConfig {
+            ap_size: 32,
+            ..Default::default()
+        }),
+        StackMethod::SurfaceWarp(SurfaceWarpConfig {
+            ap_size: 32,
+            ..Default::default()
+        }),
+        StackMethod::OpticalFlow(OpticalFlowConfig::default()),
+    ] {
+        let name = format!("{method:?}");
+        let value = stack_listed_frame(method);
+        assert!(
+            (value - expected).abs() < 0.02,
+            "{name}: expected only frame 0 stacked, got {value}"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+#[allow(dead_code)]
+mod common;
+
+use ndarray::Array2;
+
+use jupiter_core::align::shift_frame;
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::filters::gaussian_blur::gaussian_blur_array;
+use jupiter_core::frame::{AlignmentOffset, Frame};
+use jupiter_core::pipeline::config::{
+    AlignmentConfig, AlignmentMethod, LimbFitConfig, QualityMetric,
+};
+use jupiter_core::stack::global::global_align;
+
+/// 10 jittered frames, all softened by seeing except frame `sharp`.
+/// Returns the frames and the shift applied to each.
+fn jittered_sequence(sharp: usize) -> (Vec<Array2<f32>>, Vec<AlignmentOffset>) {
+    let base = Frame::new(common::planet(), 16);
+    let shifts: Vec<AlignmentOffset> = (0..10)
+        .map(|k| AlignmentOffset {
+            dx: [0.0, 1.5, -2.0, 0.5, 2.5, -1.0, 1.0, -2.5, 0.0, 2.0][k],
+            dy: [0.0, -1.0, 0.5, 2.0, -1.5, 1.0, -2.0, 0.5, 1.5, -0.5][k],
+        })
+        .collect();
+    let frames = shifts
+        .iter()
+        .enumerate()
+        .map(|(k, s)| {
+            let mut data = shift_frame(&base, s).data;
+            if k != sharp {
+                data = gaussian_blur_array(&data, 1.5);
+            }
+            common::add_noise(&mut data, k as u64, 0.01);
+            data
+        })
+        .collect();
+    (frames, shifts)
+}
+
+#[test]
+fn test_global_align_uses_best_frame_as_reference() {
+    let (frames, shifts) = jittered_sequence(6);
+    let global = global_align(
+        frames.len(),
+        None,
+        |i| Ok(frames[i].clone()),
+        &QualityMetric::Laplacian,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+    )
+    .unwrap();
+
+    assert_eq!(global.reference_idx, 6);
+    assert!(global.offsets[6].is_zero());
+    assert_eq!(global.kept_count(), 10);
+    assert_eq!(global.best_frames(0.2)[0].0, 6);
+
+    // Offsets are relative to the chosen reference, not frame 0.
+    for (k, s) in shifts.iter().enumerate() {
+        let dx = shifts[6].dx - s.dx;
+        let dy = shifts[6].dy - s.dy;
+        let err = (global.offsets[k].dx - dx).hypot(global.offsets[k].dy - dy);
+        assert!(err < 0.75, "frame {k} off by {err:.2} px");
+    }
+}
+
+#[test]
+fn test_global_align_rejects_unregistrable_frame() {
+    let (mut frames, _) = jittered_sequence(0);
+    // A frame with no planet in it (e.g. a cloud passing over).
+    let mut blank = Array2::from_elem((64, 64), 0.3f32);
+    common::add_noise(&mut blank, 99, 0.01);
+    frames[4] = blank;
+
+    let global = global_align(
+        frames.len(),
+        None,
+        |i| Ok(frames[i].clone()),
+        &QualityMetric::Laplacian,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+    )
+    .unwrap();
+
+    assert_eq!(global.reference_idx, 0);
+    assert!(global.rejected[4]);
+    assert_eq!(global.kept_count(), 9);
+    assert!(global.best_frames(1.0).iter().all(|&(i, _)| i != 4));
+}
+
+#[test]
+fn test_global_align_listed_frames_only() {
+    let (frames, _) = jittered_sequence(6);
+    let read = |i: usize| Ok(frames[i].clone());
+    let global = global_align(
+        frames.len(),
+        Some(&[8, 1, 3, 1]),
+        read,
+        &QualityMetric::Laplacian,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+    )
+    .unwrap();
+
+    // The best listed frame is the reference; unlisted frames are left out
+    // and every listed one is selected.
+    assert!([1, 3, 8].contains(&global.reference_idx));
+    assert_eq!(global.kept_count(), 3);
+    assert!((0..frames.len()).all(|i| global.rejected[i] != [1, 3, 8].contains(&i)));
+    let fraction = global.selection_fraction(0.25);
+    let mut selected: Vec<usize> = global
+        .best_frames(fraction)
+        .iter()
+        .map(|&(i, _)| i)
+        .collect();
+    selected.sort_unstable();
+    assert_eq!(selected, vec![1, 3, 8]);
+
+    assert!(global_align(
+        frames.len(),
+        Some(&[2, 40]),
+        read,
+        &QualityMetric::Laplacian,
+        &AlignmentConfig::default(),
+        &CpuBackend
+    )
+    .is_err());
+}
+
+#[test]
+fn test_global_align_honours_configured_method() {
+    // Featureless frames: phase correlation returns something, a limb fit
+    // finds no edge and must fail rather than fall back silently.
+    let frames = vec![Array2::from_elem((32, 32), 0.5f32); 3];
+    let limb = AlignmentConfig {
+        method: AlignmentMethod::LimbFit(LimbFitConfig::default()),
+        ..Default::default()
+    };
+    let read = |i: usize| Ok(frames[i].clone());
+
+    assert!(global_align(3, None, read, &QualityMetric::Laplacian, &limb, &CpuBackend).is_err());
+    assert!(global_align(
+        3,
+        None,
+        read,
+        &QualityMetric::Laplacian,
+        &AlignmentConfig::default(),
+        &CpuBackend
+    )
+    .is_ok());
+}
@@ -9,7 +9,8 @@ use jupiter_core::color::debayer::DebayerMethod;
 use jupiter_core::compute::cpu::CpuBackend;
 use jupiter_core::frame::ColorMode;
 use jupiter_core::pipeline::config::{
-    DebayerConfig, FrameSelectionConfig, PipelineConfig, StackMethod, StackingConfig,
+    AlignmentConfig, DebayerConfig, FrameSelectionConfig, PipelineConfig, StackMethod,
+    StackingConfig,
 };
 use jupiter_core::pipeline::{run_pipeline, PipelineOutput};
 use jupiter_core::stack::multi_point::{
@@ -84,6 +85,8 @@ fn test_multi_point_stack_color_basic() {
     let result = multi_point_stack_color(
         &reader,
         &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
         &ColorMode::BayerRGGB,
         &DebayerMethod::Bilinear,
         |_| {},
@@ -124,6 +127,8 @@ fn test_multi_point_stack_color_single_frame() {
     let result = multi_point_stack_color(
         &reader,
         &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
         &ColorMode::BayerRGGB,
         &DebayerMethod::Bilinear,
         |_| {},
@@ -153,6 +158,8 @@ fn test_multi_point_stack_color_channels_independent() {
     let cf = multi_point_stack_color(
         &reader,
         &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
         &ColorMode::BayerRGGB,
         &DebayerMethod::Bilinear,
         |_| {},
@@ -199,6 +206,7 @@ fn test_multi_point_stack_color_pipeline_integration() {
 
     let config = PipelineConfig {
         input: ser_file.path().to_path_buf(),
+        extra_inputs: vec![],
         output: output_path.clone(),
         device: Default::default(),
         memory: Default::default(),
@@ -218,6 +226,7 @@ fn test_multi_point_stack_color_pipeline_integration() {
                 min_brightness: 0.01,
                 ..Default::default()
             }),
+            ..Default::default()
         },
         sharpening: None,
         filters: vec![],
@@ -249,6 +258,7 @@ fn test_multi_point_stack_color_force_mono() {
 
     let config = PipelineConfig {
         input: ser_file.path().to_path_buf(),
+        extra_inputs: vec![],
         output: output_path.clone(),
         device: Default::default(),
         memory: Default::default(),
@@ -268,6 +278,7 @@ fn test_multi_point_stack_color_force_mono() {
                 min_brightness: 0.01,
                 ..Default::default()
             }),
+            ..Default::default()
         },
         sharpening: None,
         filters: vec![],
@@ -315,6 +326,8 @@ fn test_multi_point_stack_color_local_methods() {
         let result = multi_point_stack_color(
             &reader,
             &config,
+            &AlignmentConfig::default(),
+            &CpuBackend,
             &ColorMode::BayerRGGB,
             &DebayerMethod::Bilinear,
             |_| {},
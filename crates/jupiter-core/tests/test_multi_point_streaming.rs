@@ -0,0 +1,180 @@
+#[allow(dead_code)]
+mod common;
+
+use std::sync::Arc;
+
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::frame::Frame;
+use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::{
+    AlignmentConfig, FrameSelectionConfig, MemoryStrategy, PipelineConfig, StackMethod,
+    StackingConfig,
+};
+use jupiter_core::pipeline::run_pipeline;
+use jupiter_core::stack::multi_point::{multi_point_stack, LocalStackMethod, MultiPointConfig};
+
+const SIZE: usize = 96;
+
+/// Textured disk, 8-bit.
+fn scene() -> Vec<u8> {
+    let mut data = vec![0u8; SIZE * SIZE];
+    for r in 0..SIZE {
+        for c in 0..SIZE {
+            let (y, x) = (r as f64 - 48.0, c as f64 - 48.0);
+            let v = if y.hypot(x) < 36.0 {
+                0.5 + 0.2 * (x / 5.0).sin() * (y / 7.0).cos()
+            } else {
+                0.05
+            };
+            data[r * SIZE + c] = (v * 255.0) as u8;
+        }
+    }
+    data
+}
+
+fn write_sequence(frames: &[Vec<u8>]) -> tempfile::NamedTempFile {
+    common::write_test_ser(&common::build_ser_with_frames(
+        SIZE as u32,
+        SIZE as u32,
+        frames,
+    ))
+}
+
+fn stack(reader: &SerReader, method: LocalStackMethod) -> Frame {
+    let config = MultiPointConfig {
+        ap_size: 32,
+        search_radius: 8,
+        select_percentage: 1.0,
+        min_brightness: 0.01,
+        local_stack_method: method,
+        ..Default::default()
+    };
+    multi_point_stack(
+        reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |_| {},
+    )
+    .unwrap()
+}
+
+/// Largest difference from the scene over the centre of the disk.
+fn max_error(frame: &Frame, scene: &[u8]) -> f32 {
+    let mut worst = 0.0f32;
+    for r in 24..72 {
+        for c in 24..72 {
+            let expected = scene[r * SIZE + c] as f32 / 255.0;
+            worst = worst.max((frame.data[[r, c]] - expected).abs());
+        }
+    }
+    worst
+}
+
+#[test]
+fn test_streaming_mean_matches_cached_median_on_static_scene() {
+    let scene = scene();
+    let ser = write_sequence(&vec![scene.clone(); 6]);
+    let reader = SerReader::open(ser.path()).unwrap();
+
+    let mean = stack(&reader, LocalStackMethod::Mean);
+    let median = stack(&reader, LocalStackMethod::Median);
+
+    assert!(max_error(&mean, &scene) < 0.01);
+    assert!(max_error(&median, &scene) < 0.01);
+}
+
+#[test]
+fn test_streaming_sigma_clip_rejects_transient() {
+    let scene = scene();
+    let mut frames = vec![scene.clone(); 8];
+    // A satellite streak through one frame.
+    for c in 30..66 {
+        frames[3][40 * SIZE + c] = 255;
+        frames[3][41 * SIZE + c] = 255;
+    }
+    let ser = write_sequence(&frames);
+    let reader = SerReader::open(ser.path()).unwrap();
+
+    let mean = stack(&reader, LocalStackMethod::Mean);
+    let clipped = stack(
+        &reader,
+        LocalStackMethod::SigmaClip {
+            sigma: 2.0,
+            iterations: 2,
+        },
+    );
+
+    assert!(
+        max_error(&mean, &scene) > 0.03,
+        "streak should survive a mean"
+    );
+    let err = max_error(&clipped, &scene);
+    assert!(err < 0.01, "streak left in sigma-clipped stack: {err:.3}");
+}
+
+#[test]
+fn test_streaming_progress_is_monotonic() {
+    let ser = write_sequence(&vec![scene(); 4]);
+    let reader = SerReader::open(ser.path()).unwrap();
+    let config = MultiPointConfig {
+        ap_size: 32,
+        search_radius: 8,
+        min_brightness: 0.01,
+        local_stack_method: LocalStackMethod::SigmaClip {
+            sigma: 2.5,
+            iterations: 1,
+        },
+        ..Default::default()
+    };
+
+    let mut reports = Vec::new();
+    multi_point_stack(
+        &reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |p| reports.push(p),
+    )
+    .unwrap();
+
+    assert!(reports.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(reports.last().copied(), Some(1.0));
+}
+
+#[test]
+fn test_low_memory_refuses_cached_local_methods() {
+    let ser = write_sequence(&vec![scene(); 4]);
+    let dir = tempfile::tempdir().unwrap();
+    let run = |local_stack_method, memory| {
+        let config = PipelineConfig {
+            input: ser.path().to_path_buf(),
+            extra_inputs: vec![],
+            output: dir.path().join("out.tiff"),
+            device: Default::default(),
+            memory,
+            debayer: None,
+            force_mono: true,
+            frame_selection: FrameSelectionConfig::default(),
+            alignment: AlignmentConfig::default(),
+            stacking: StackingConfig {
+                method: StackMethod::MultiPoint(MultiPointConfig {
+                    ap_size: 32,
+                    search_radius: 8,
+                    min_brightness: 0.01,
+                    local_stack_method,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            sharpening: None,
+            filters: vec![],
+        };
+        run_pipeline(&config, Arc::new(CpuBackend), |_, _| {})
+    };
+
+    let err = run(LocalStackMethod::Median, MemoryStrategy::LowMemory).unwrap_err();
+    assert!(err.to_string().contains("low-memory"), "{err}");
+    assert!(run(LocalStackMethod::Median, MemoryStrategy::Eager).is_ok());
+    assert!(run(LocalStackMethod::Mean, MemoryStrategy::LowMemory).is_ok());
+}
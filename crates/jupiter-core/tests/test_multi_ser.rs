@@ -0,0 +1,156 @@
+#[allow(dead_code)]
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use tempfile::TempDir;
+
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::io::ser::{FileContribution, SerReader};
+use jupiter_core::pipeline::config::{MemoryStrategy, PipelineConfig};
+use jupiter_core::pipeline::session::SessionCache;
+use jupiter_core::pipeline::{run_pipeline_reported, ProgressReporter};
+
+/// Records the per-file contributions the pipeline reports.
+#[derive(Default)]
+struct ContributionLog(Mutex<Vec<FileContribution>>);
+
+impl ProgressReporter for ContributionLog {
+    fn file_contributions(&self, contributions: &[FileContribution]) {
+        *self.0.lock().unwrap() = contributions.to_vec();
+    }
+}
+
+/// Six 32x32 frames with a jittered square; `contrast` sets how bright (and
+/// so how sharp) the square is.
+fn capture_frames(contrast: u8) -> Vec<Vec<u8>> {
+    (0..6)
+        .map(|i| {
+            let mut f = vec![20u8; 32 * 32];
+            for r in (12 + i % 2)..(20 + i % 2) {
+                for c in (12 + i % 3)..(20 + i % 3) {
+                    f[r * 32 + c] = contrast;
+                }
+            }
+            f
+        })
+        .collect()
+}
+
+fn write_ser(dir: &Path, name: &str, frames: &[Vec<u8>]) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, common::build_ser_with_frames(32, 32, frames)).unwrap();
+    path
+}
+
+#[test]
+fn test_open_all_concatenates_files() {
+    let dir = TempDir::new().unwrap();
+    let (soft, sharp) = (capture_frames(120), capture_frames(255));
+    let a = write_ser(dir.path(), "a.ser", &soft);
+    let b = write_ser(dir.path(), "b.ser", &sharp);
+
+    let reader = SerReader::open_all(&[a.clone(), b.clone()]).unwrap();
+    assert_eq!(reader.frame_count(), 12);
+    assert_eq!(reader.file_count(), 2);
+    assert_eq!(reader.header.frame_count, 12);
+
+    let single = SerReader::open(&b).unwrap();
+    let frame = reader.read_frame(8).unwrap();
+    assert_eq!(frame.data, single.read_frame(2).unwrap().data);
+    assert_eq!(frame.metadata.frame_index, 8);
+    assert!(reader.read_frame(12).is_err());
+
+    let contributions = reader.file_contributions(Some(&[7, 8, 1]));
+    assert_eq!(contributions.len(), 2);
+    assert_eq!(contributions[0].path, a);
+    assert_eq!(contributions[0].frames, 6);
+    assert_eq!(contributions[0].selected, Some(1));
+    assert_eq!(contributions[1].selected, Some(2));
+    assert_eq!(reader.file_contributions(None)[1].selected, None);
+}
+
+#[test]
+fn test_open_all_rejects_incompatible_files() {
+    let dir = TempDir::new().unwrap();
+    let a = write_ser(dir.path(), "a.ser", &capture_frames(200));
+    let small = dir.path().join("small.ser");
+    std::fs::write(
+        &small,
+        common::build_ser_with_frames(24, 24, &[vec![0u8; 24 * 24]]),
+    )
+    .unwrap();
+
+    let err = SerReader::open_all(&[a, small]).err().unwrap();
+    assert!(err.to_string().contains("does not match"), "{err}");
+    assert!(SerReader::open_all(&[]).is_err());
+}
+
+#[test]
+fn test_pipeline_stacks_best_frames_across_files() {
+    let dir = TempDir::new().unwrap();
+    let (soft, sharp) = (capture_frames(120), capture_frames(255));
+    let a = write_ser(dir.path(), "a.ser", &soft);
+    let b = write_ser(dir.path(), "b.ser", &sharp);
+    let joined: Vec<Vec<u8>> = soft.iter().chain(&sharp).cloned().collect();
+    let both = write_ser(dir.path(), "both.ser", &joined);
+
+    let single = run_pipeline_reported(
+        &PipelineConfig {
+            memory: MemoryStrategy::Eager,
+            ..common::config_for(&[both], dir.path())
+        },
+        Arc::new(CpuBackend),
+        Arc::new(ContributionLog::default()),
+    )
+    .unwrap()
+    .to_mono();
+
+    for memory in [MemoryStrategy::Eager, MemoryStrategy::LowMemory] {
+        let log = Arc::new(ContributionLog::default());
+        let result = run_pipeline_reported(
+            &PipelineConfig {
+                memory,
+                ..common::config_for(&[a.clone(), b.clone()], dir.path())
+            },
+            Arc::new(CpuBackend),
+            log.clone(),
+        )
+        .unwrap()
+        .to_mono();
+
+        let diff = result
+            .data
+            .iter()
+            .zip(single.data.iter())
+            .map(|(x, y)| (x - y).abs())
+            .fold(0.0f32, f32::max);
+        assert!(diff < 1e-6, "multi-file stack differs by {diff}");
+
+        // The sharp file wins every one of the six selected slots.
+        let contributions = log.0.lock().unwrap().clone();
+        assert_eq!(contributions.len(), 2);
+        assert_eq!(contributions[0].selected, Some(0));
+        assert_eq!(contributions[1].selected, Some(6));
+    }
+}
+
+#[test]
+fn test_multi_file_session_sidecar() {
+    let dir = TempDir::new().unwrap();
+    let a = write_ser(dir.path(), "a.ser", &capture_frames(120));
+    let b = write_ser(dir.path(), "b.ser", &capture_frames(255));
+
+    let single = SessionCache::open_all(std::slice::from_ref(&a)).unwrap();
+    assert_eq!(single.dir(), SessionCache::sidecar_dir(&a));
+
+    let inputs = [a, b];
+    let session = SessionCache::open_all(&inputs).unwrap();
+    assert_eq!(session.dir(), dir.path().join("a+1.jupiter"));
+
+    session.save().unwrap();
+    assert!(session.dir().is_dir());
+    SessionCache::clear(&inputs).unwrap();
+    assert!(!session.dir().exists());
+}
@@ -0,0 +1,280 @@
+#[allow(dead_code)]
+mod common;
+
+use std::f64::consts::TAU;
+use std::sync::Arc;
+
+use ndarray::Array2;
+
+use jupiter_core::color::debayer::DebayerMethod;
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::frame::{AlignmentOffset, ColorMode};
+use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::{
+    AlignmentConfig, FrameSelectionConfig, PipelineConfig, StackMethod, StackingConfig,
+};
+use jupiter_core::pipeline::{run_pipeline, PipelineOutput};
+use jupiter_core::stack::optical_flow::{
+    dense_flow, optical_flow_stack, optical_flow_stack_color, OpticalFlowConfig,
+};
+use jupiter_core::stack::surface_warp::warp_frame;
+
+const SIZE: usize = 128;
+
+/// Spots scattered over the disk as `(y, x, sigma, amplitude)`.
+fn spots() -> Vec<(f64, f64, f64, f64)> {
+    let mut noise = common::noise(12345);
+    let mut next = move || 0.5 * (noise() as f64 + 1.0);
+    (0..60)
+        .map(|_| {
+            let (y, x) = (20.0 + 88.0 * next(), 20.0 + 88.0 * next());
+            (y, x, 2.0 + 3.0 * next(), 0.3 * next() - 0.15)
+        })
+        .collect()
+}
+
+/// Disk with a soft limb and non-repeating spots at scales a 7 px window
+/// can lock onto (a periodic texture would alias global alignment).
+fn scene_with(spots: &[(f64, f64, f64, f64)], y: f64, x: f64) -> f64 {
+    let r = (y - 64.0).hypot(x - 64.0);
+    let disk = ((50.0 - r) / 3.0).clamp(0.0, 1.0);
+    let texture: f64 = spots
+        .iter()
+        .map(|&(sy, sx, sigma, amp)| {
+            amp * (-((y - sy).powi(2) + (x - sx).powi(2)) / (2.0 * sigma * sigma)).exp()
+        })
+        .sum();
+    0.05 + disk * (0.45 + texture)
+}
+
+thread_local! {
+    static SPOTS: Vec<(f64, f64, f64, f64)> = spots();
+}
+
+fn scene(y: f64, x: f64) -> f64 {
+    SPOTS.with(|s| scene_with(s, y, x))
+}
+
+/// Local seeing distortion: up to 1 px, varying over ~64 px.
+fn distortion(y: f64, x: f64, phase: f64) -> (f64, f64) {
+    (
+        1.0 * (TAU * x / 64.0 + phase).sin(),
+        0.8 * (TAU * y / 64.0 + phase).cos(),
+    )
+}
+
+/// `scene` as seen through `distortion` and a global shift: the frame the
+/// flow must map back onto the scene.
+fn distorted(phase: f64, global: (f64, f64)) -> Array2<f32> {
+    Array2::from_shape_fn((SIZE, SIZE), |(r, c)| {
+        let (y, x) = (r as f64, c as f64);
+        let (dy, dx) = distortion(y, x, phase);
+        scene(y + dy + global.0, x + dx + global.1) as f32
+    })
+}
+
+fn truth() -> Array2<f32> {
+    Array2::from_shape_fn((SIZE, SIZE), |(r, c)| scene(r as f64, c as f64) as f32)
+}
+
+/// Worst absolute difference over the disk, away from the limb.
+fn disk_error(a: &Array2<f32>, b: &Array2<f32>) -> f32 {
+    let mut worst = 0.0f32;
+    for ((r, c), &v) in a.indexed_iter() {
+        if (r as f64 - 64.0).hypot(c as f64 - 64.0) < 40.0 {
+            worst = worst.max((v - b[[r, c]]).abs());
+        }
+    }
+    worst
+}
+
+fn to_bytes(data: &Array2<f32>) -> Vec<u8> {
+    data.iter().map(|&v| (v * 255.0).round() as u8).collect()
+}
+
+#[test]
+fn test_dense_flow_recovers_distortion() {
+    let reference = truth();
+    let target = distorted(0.0, (-2.0, 1.0));
+    // Global alignment would have found the frame-wide part of the shift.
+    let global = AlignmentOffset { dy: 2.0, dx: -1.0 };
+    let (fy, fx) = dense_flow(&reference, &target, &global, &OpticalFlowConfig::default());
+
+    let rigid = warp_frame(
+        &target,
+        &Array2::from_elem((SIZE, SIZE), global.dy),
+        &Array2::from_elem((SIZE, SIZE), global.dx),
+    );
+    let warped = warp_frame(&target, &fy, &fx);
+    let before = disk_error(&rigid, &reference);
+    let after = disk_error(&warped, &reference);
+    assert!(
+        after < before / 4.0,
+        "residual {after:.4} vs {before:.4} rigid"
+    );
+}
+
+#[test]
+fn test_dense_flow_recovers_translation_without_seed() {
+    let reference = truth();
+    let target = Array2::from_shape_fn((SIZE, SIZE), |(r, c)| {
+        scene(r as f64 + 1.3, c as f64 - 0.7) as f32
+    });
+    let (fy, fx) = dense_flow(
+        &reference,
+        &target,
+        &AlignmentOffset::default(),
+        &OpticalFlowConfig::default(),
+    );
+    // Flat gaps between spots carry no flow information, so judge the mean.
+    let (mut sum, mut count) = (0.0f64, 0usize);
+    for ((r, c), &dy) in fy.indexed_iter() {
+        if (r as f64 - 64.0).hypot(c as f64 - 64.0) < 40.0 {
+            sum += (dy - 1.3).hypot(fx[[r, c]] + 0.7);
+            count += 1;
+        }
+    }
+    let mean = sum / count as f64;
+    assert!(mean < 0.1, "mean flow error {mean:.3}");
+}
+
+#[test]
+fn test_optical_flow_stack_beats_plain_mean() {
+    // Frame 0 is the sharp, undistorted scene and becomes the reference; the
+    // others are jittered, locally distorted and slightly blurred by seeing.
+    // The plain mean smears the spots, the flow-warped stack keeps them.
+    let jitter = [
+        (0.0, 0.0),
+        (0.6, -0.4),
+        (-0.5, 0.7),
+        (0.3, 0.5),
+        (-0.7, -0.3),
+    ];
+    let frames: Vec<Vec<u8>> = jitter
+        .iter()
+        .enumerate()
+        .map(|(i, &(gy, gx))| {
+            if i == 0 {
+                return to_bytes(&truth());
+            }
+            let mut blurred = Array2::<f32>::zeros((SIZE, SIZE));
+            for (by, bx) in [(-0.4, -0.4), (-0.4, 0.4), (0.4, -0.4), (0.4, 0.4)] {
+                blurred += &distorted(i as f64, (gy + by, gx + bx));
+            }
+            to_bytes(&(blurred / 4.0))
+        })
+        .collect();
+    let ser = common::write_test_ser(&common::build_ser_with_frames(
+        SIZE as u32,
+        SIZE as u32,
+        &frames,
+    ));
+    let reader = SerReader::open(ser.path()).unwrap();
+    let config = OpticalFlowConfig {
+        select_percentage: 1.0,
+        ..Default::default()
+    };
+    let stacked = optical_flow_stack(
+        &reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |_| {},
+    )
+    .unwrap();
+
+    let n = jitter.len();
+    let mut mean = Array2::<f32>::zeros((SIZE, SIZE));
+    for i in 0..n {
+        mean += &reader.read_frame(i).unwrap().data;
+    }
+    mean /= n as f32;
+
+    let truth = truth();
+    let flow_error = disk_error(&stacked.data, &truth);
+    let mean_error = disk_error(&mean, &truth);
+    assert!(
+        flow_error < mean_error / 2.0,
+        "optical flow {flow_error:.4} vs plain mean {mean_error:.4}"
+    );
+}
+
+#[test]
+fn test_optical_flow_color_and_pipeline() {
+    // RGB frames: the same distorted texture scaled per channel.
+    let frames: Vec<Array2<f32>> = (0..4).map(|i| distorted(i as f64, (0.0, 0.0))).collect();
+    let mut buf = common::build_ser_header_full(SIZE as u32, SIZE as u32, 8, frames.len(), 100);
+    for frame in &frames {
+        for &v in frame.iter() {
+            buf.extend([(v * 255.0) as u8, (v * 200.0) as u8, (v * 120.0) as u8]);
+        }
+    }
+    let ser = common::write_test_ser(&buf);
+    let reader = SerReader::open(ser.path()).unwrap();
+    let config = OpticalFlowConfig {
+        select_percentage: 1.0,
+        ..Default::default()
+    };
+
+    let cf = optical_flow_stack_color(
+        &reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        &ColorMode::RGB,
+        &DebayerMethod::Bilinear,
+        |_| {},
+    )
+    .unwrap();
+    assert_eq!(cf.red.data.dim(), (SIZE, SIZE));
+    let (r, b) = (cf.red.data.mean().unwrap(), cf.blue.data.mean().unwrap());
+    assert!(r > b * 1.5, "red {r:.3} blue {b:.3}");
+
+    let dir = tempfile::tempdir().unwrap();
+    let pipeline = PipelineConfig {
+        input: ser.path().to_path_buf(),
+        extra_inputs: vec![],
+        output: dir.path().join("out.tiff"),
+        device: Default::default(),
+        memory: Default::default(),
+        debayer: None,
+        force_mono: true,
+        frame_selection: FrameSelectionConfig::default(),
+        alignment: AlignmentConfig::default(),
+        stacking: StackingConfig {
+            method: StackMethod::OpticalFlow(config),
+            ..Default::default()
+        },
+        sharpening: None,
+        filters: vec![],
+    };
+    let output = run_pipeline(&pipeline, Arc::new(CpuBackend), |_, _| {}).unwrap();
+    assert!(matches!(output, PipelineOutput::Mono(ref f) if f.data.dim() == (SIZE, SIZE)));
+}
+
+#[test]
+fn test_optical_flow_stack_zero_scores_fall_back_to_uniform() {
+    // Featureless frames all score zero; the stack must still be the frames'
+    // level, not black.
+    let frames = vec![vec![128u8; SIZE * SIZE]; 3];
+    let ser = common::write_test_ser(&common::build_ser_with_frames(
+        SIZE as u32,
+        SIZE as u32,
+        &frames,
+    ));
+    let reader = SerReader::open(ser.path()).unwrap();
+    let config = OpticalFlowConfig {
+        select_percentage: 1.0,
+        ..Default::default()
+    };
+    let stacked = optical_flow_stack(
+        &reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |_| {},
+    )
+    .unwrap();
+    let mean = stacked.data.mean().unwrap();
+    assert!((mean - 128.0 / 255.0).abs() < 0.01, "mean {mean:.4}");
+}
@@ -0,0 +1,218 @@
+#[allow(dead_code)]
+mod common;
+
+use ndarray::Array2;
+
+use jupiter_core::align::{
+    compute_offset_configured, reject_outliers, shift_frame, ReferenceAligner,
+};
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::frame::{AlignmentOffset, Frame};
+use jupiter_core::io::frame_report::FrameReport;
+use jupiter_core::pipeline::config::{AlignmentConfig, AlignmentMethod, OutlierRejection};
+use jupiter_core::pipeline::session::CachedAlignment;
+
+/// 16 frames drifting slowly to the right, with light noise. Returns the
+/// frames and the true correcting offsets.
+fn drifting_sequence() -> (Vec<Frame>, Vec<AlignmentOffset>) {
+    let base = Frame::new(common::planet(), 16);
+    let drift: Vec<AlignmentOffset> = (0..16)
+        .map(|k| AlignmentOffset {
+            dx: 0.25 * k as f64,
+            dy: 0.1 * k as f64,
+        })
+        .collect();
+    let frames = drift
+        .iter()
+        .enumerate()
+        .map(|(k, d)| {
+            let mut frame = shift_frame(&base, d);
+            common::add_noise(&mut frame.data, k as u64, 0.02);
+            frame
+        })
+        .collect();
+    let correcting = drift
+        .iter()
+        .map(|d| AlignmentOffset {
+            dx: -d.dx,
+            dy: -d.dy,
+        })
+        .collect();
+    (frames, correcting)
+}
+
+fn offsets_of(frames: &[Frame]) -> Vec<AlignmentOffset> {
+    let config = AlignmentConfig::default();
+    frames
+        .iter()
+        .map(|f| compute_offset_configured(&frames[0].data, &f.data, &config, &CpuBackend).unwrap())
+        .collect()
+}
+
+fn check(
+    frames: &[Frame],
+    offsets: &mut [AlignmentOffset],
+    rejection: &OutlierRejection,
+) -> jupiter_core::align::AlignmentCheck {
+    check_with(frames, offsets, rejection, &AlignmentConfig::default())
+}
+
+fn check_with(
+    frames: &[Frame],
+    offsets: &mut [AlignmentOffset],
+    rejection: &OutlierRejection,
+    config: &AlignmentConfig,
+) -> jupiter_core::align::AlignmentCheck {
+    let times: Vec<usize> = (0..frames.len()).collect();
+    let aligner = ReferenceAligner::new(&frames[0].data, config, &CpuBackend).unwrap();
+    reject_outliers(
+        offsets,
+        |k| Ok(frames[k].data.clone()),
+        &times,
+        0,
+        rejection,
+        &aligner,
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_clean_sequence_keeps_every_frame() {
+    let (frames, _) = drifting_sequence();
+    let mut offsets = offsets_of(&frames);
+    let result = check(&frames, &mut offsets, &OutlierRejection::default());
+    assert_eq!(result.rejected_count(), 0);
+    assert!(result.confidences[0].is_nan());
+    assert!(result.confidences[1..]
+        .iter()
+        .all(|&c| c >= OutlierRejection::default().min_confidence));
+}
+
+#[test]
+fn test_garbage_frame_is_rejected() {
+    let (mut frames, _) = drifting_sequence();
+    let mut garbage = Array2::from_elem((64, 64), 0.3f32);
+    common::add_noise(&mut garbage, 99, 0.3);
+    frames[7].data = garbage;
+    let mut offsets = offsets_of(&frames);
+    let result = check(&frames, &mut offsets, &OutlierRejection::default());
+    assert!(result.confidences[7] < OutlierRejection::default().min_confidence);
+    assert!(result.rejected[7]);
+    assert_eq!(result.rejected_count(), 1);
+}
+
+#[test]
+fn test_misregistered_frame_is_re_searched() {
+    let (frames, truth) = drifting_sequence();
+    let mut offsets = offsets_of(&frames);
+    offsets[9].dx += 12.0;
+    let result = check(&frames, &mut offsets, &OutlierRejection::default());
+    assert_eq!(result.rejected_count(), 0);
+    assert_eq!(result.recovered, 1);
+    let error = (offsets[9].dx - truth[9].dx).hypot(offsets[9].dy - truth[9].dy);
+    assert!(error < 0.5, "re-searched offset off by {error:.2} px");
+
+    // Without re-search the same frame is rejected instead.
+    let mut offsets = offsets_of(&frames);
+    offsets[9].dx += 12.0;
+    let no_retry = OutlierRejection {
+        retry: false,
+        ..Default::default()
+    };
+    let result = check(&frames, &mut offsets, &no_retry);
+    assert_eq!(result.rejected, (0..16).map(|k| k == 9).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_limb_fit_is_checked_with_its_own_method() {
+    // No correlation confidence for the limb fit; the misregistered frame is
+    // caught by the drift check and re-aligned by the limb fit itself.
+    let (frames, truth) = drifting_sequence();
+    let config = AlignmentConfig {
+        method: AlignmentMethod::LimbFit(Default::default()),
+        ..Default::default()
+    };
+    let mut offsets: Vec<AlignmentOffset> = frames
+        .iter()
+        .map(|f| compute_offset_configured(&frames[0].data, &f.data, &config, &CpuBackend).unwrap())
+        .collect();
+    offsets[9].dx += 12.0;
+    let result = check_with(&frames, &mut offsets, &OutlierRejection::default(), &config);
+    assert!(result.confidences.iter().all(|c| c.is_nan()));
+    assert_eq!(result.rejected_count(), 0);
+    assert_eq!(result.recovered, 1);
+    let error = (offsets[9].dx - truth[9].dx).hypot(offsets[9].dy - truth[9].dy);
+    assert!(error < 0.5, "re-aligned offset off by {error:.2} px");
+}
+
+#[test]
+fn test_rejected_frames_are_dropped_from_alignment() {
+    let (mut frames, _) = drifting_sequence();
+    frames[4].data = Array2::from_elem((64, 64), 0.3f32);
+    common::add_noise(&mut frames[4].data, 7, 0.3);
+    let selected: Vec<usize> = (100..116).collect();
+    let config = AlignmentConfig {
+        rejection: Some(OutlierRejection::default()),
+        ..Default::default()
+    };
+    let alignment = CachedAlignment::checked(
+        &selected,
+        offsets_of(&frames),
+        |k| Ok(frames[k].data.clone()),
+        &config,
+        &CpuBackend,
+        false,
+    )
+    .unwrap();
+    assert_eq!(alignment.rejected, vec![104]);
+
+    let (kept, offsets) = alignment.kept();
+    assert_eq!(kept.len(), 15);
+    assert!(!kept.contains(&104));
+    assert_eq!(offsets.len(), 15);
+    let mut scores: Vec<usize> = (0..16).collect();
+    alignment.retain_kept(&mut scores);
+    assert!(!scores.contains(&4));
+
+    // Confidences are kept for the report; the summary skips rejected frames.
+    let min_confidence = OutlierRejection::default().min_confidence;
+    assert_eq!(alignment.confidences.len(), 16);
+    assert_eq!(alignment.confidences[0], None);
+    assert!(alignment.confidences[4].unwrap() < min_confidence);
+    let (min, median) = alignment.confidence_summary().unwrap();
+    assert!(min >= min_confidence && median >= min);
+}
+
+#[test]
+fn test_confidences_recorded_without_rejection() {
+    let (frames, _) = drifting_sequence();
+    let selected: Vec<usize> = (100..116).collect();
+    let checked = |with_confidences| {
+        CachedAlignment::checked(
+            &selected,
+            offsets_of(&frames),
+            |k| Ok(frames[k].data.clone()),
+            &AlignmentConfig::default(),
+            &CpuBackend,
+            with_confidences,
+        )
+        .unwrap()
+    };
+
+    // Not measured unless asked for.
+    let unmeasured = checked(false);
+    assert!(unmeasured.confidences.is_empty());
+    assert!(unmeasured.confidence_values().iter().all(|c| c.is_nan()));
+
+    let alignment = checked(true);
+    assert!(alignment.rejected.is_empty());
+    assert_eq!(alignment.confidences[0], None);
+    assert!(alignment.confidences[1..].iter().all(|c| c.unwrap() > 0.0));
+
+    let values = alignment.confidence_values();
+    assert!(values[0].is_nan());
+    let mut report = FrameReport::new(116);
+    report.record_offsets(&alignment.selected, &alignment.offsets, Some(&values));
+    assert_eq!(report.frames[100].confidence, None);
+    assert_eq!(report.frames[101].confidence, alignment.confidences[1]);
+}
@@ -0,0 +1,183 @@
+use ndarray::Array2;
+
+use jupiter_core::filters::gaussian_blur::gaussian_blur_array;
+use jupiter_core::frame::Frame;
+use jupiter_core::io::image_io::save_image;
+use jupiter_core::pipeline::config::{
+    DeconvolutionConfig, DeconvolutionMethod, LimbFitConfig, PsfModel, PsfRegion,
+};
+use jupiter_core::sharpen::deconvolution::{
+    deconvolve, deconvolve_loaded, estimate_limb_psf, generate_psf, Psf,
+};
+use jupiter_core::sharpen::psf::{estimate_limb_sigma, extract_psf, wrap_kernel};
+
+/// Gaussian star of width `sigma` at (`cy`, `cx`) on a 0.1 sky.
+fn star_field(h: usize, w: usize, cy: f64, cx: f64, sigma: f64) -> Array2<f32> {
+    Array2::from_shape_fn((h, w), |(r, c)| {
+        let d2 = (r as f64 - cy).powi(2) + (c as f64 - cx).powi(2);
+        (0.1 + 0.8 * (-d2 / (2.0 * sigma * sigma)).exp()) as f32
+    })
+}
+
+/// Centroid and RMS width of a centred kernel.
+fn moments(kernel: &Array2<f32>) -> (f64, f64, f64) {
+    let (mut sy, mut sx, mut s2, mut sum) = (0.0, 0.0, 0.0, 0.0);
+    let (ky, kx) = ((kernel.nrows() / 2) as f64, (kernel.ncols() / 2) as f64);
+    for ((r, c), &v) in kernel.indexed_iter() {
+        let (dy, dx) = (r as f64 - ky, c as f64 - kx);
+        sy += dy * v as f64;
+        sx += dx * v as f64;
+        s2 += (dy * dy + dx * dx) * v as f64;
+        sum += v as f64;
+    }
+    (sy / sum, sx / sum, (s2 / sum / 2.0).sqrt())
+}
+
+/// Bright disk of radius 40 with an anti-aliased edge, blurred by `sigma`.
+fn blurred_disk(sigma: f32) -> Array2<f32> {
+    let disk = Array2::from_shape_fn((128, 128), |(r, c)| {
+        let d = (r as f64 - 63.3).hypot(c as f64 - 64.6);
+        (0.05 + 0.75 * (40.5 - d).clamp(0.0, 1.0)) as f32
+    });
+    gaussian_blur_array(&disk, sigma)
+}
+
+#[test]
+fn test_extract_psf_recentres_and_normalizes() {
+    // Star off-centre inside the region, another bright source outside it.
+    let mut data = star_field(64, 64, 21.4, 26.7, 1.5);
+    data[[50, 50]] = 1.0;
+    let region = PsfRegion {
+        x: 8,
+        y: 6,
+        width: 36,
+        height: 32,
+    };
+
+    let kernel = extract_psf(&data, Some(&region)).unwrap();
+    assert_eq!(kernel.nrows() % 2, 1);
+    assert_eq!(kernel.nrows(), kernel.ncols());
+    assert!((kernel.sum() - 1.0).abs() < 1e-5);
+    assert!(kernel.iter().all(|&v| v >= 0.0));
+
+    let (dy, dx, sigma) = moments(&kernel);
+    assert!(
+        dy.abs() < 0.1 && dx.abs() < 0.1,
+        "off centre: {dy:.3}, {dx:.3}"
+    );
+    assert!((sigma - 1.5).abs() < 0.25, "sigma {sigma:.3}");
+
+    let outside = PsfRegion { x: 40, ..region };
+    assert!(extract_psf(&data, Some(&outside)).is_err());
+    assert!(extract_psf(&Array2::from_elem((32, 32), 0.2), None).is_err());
+}
+
+#[test]
+fn test_wrap_kernel_places_centre_at_origin() {
+    let mut kernel = Array2::<f32>::zeros((5, 5));
+    kernel[[2, 2]] = 2.0;
+    kernel[[1, 2]] = 1.0;
+    kernel[[2, 4]] = 1.0;
+
+    let psf = wrap_kernel(&kernel, 16, 12);
+    assert_eq!(psf.dim(), (16, 12));
+    assert!((psf[[0, 0]] - 0.5).abs() < 1e-6);
+    assert!((psf[[15, 0]] - 0.25).abs() < 1e-6);
+    assert!((psf[[0, 2]] - 0.25).abs() < 1e-6);
+
+    // Too small for the kernel: the overhang is dropped, the rest renormalized.
+    let psf = wrap_kernel(&kernel, 3, 3);
+    assert!((psf.sum() - 1.0).abs() < 1e-6);
+    assert!((psf[[0, 0]] - 2.0 / 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_measured_psf_matches_analytic_deconvolution() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("star.tiff");
+    save_image(&Frame::new(star_field(48, 48, 24.0, 24.0, 2.0), 16), &path).unwrap();
+
+    let mut scene = Array2::<f32>::from_elem((64, 64), 0.1);
+    for r in 24..40 {
+        for c in 20..44 {
+            scene[[r, c]] = 0.7;
+        }
+    }
+    let frame = Frame::new(gaussian_blur_array(&scene, 2.0), 16);
+
+    let run = |psf: PsfModel| {
+        let config = DeconvolutionConfig {
+            method: DeconvolutionMethod::RichardsonLucy {
+                iterations: 15,
+                options: Default::default(),
+            },
+            psf,
+            tiling: None,
+        };
+        deconvolve(&frame, &config).unwrap()
+    };
+    let measured = run(PsfModel::Measured {
+        path: path.clone(),
+        region: None,
+    });
+    let analytic = run(PsfModel::Gaussian { sigma: 2.0 });
+
+    let diff = measured
+        .data
+        .iter()
+        .zip(analytic.data.iter())
+        .map(|(a, b)| (a - b).abs())
+        .fold(0.0f32, f32::max);
+    assert!(diff < 0.03, "measured and analytic PSFs differ by {diff}");
+
+    // Loaded once, the kernel no longer needs its file.
+    let config = DeconvolutionConfig {
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 15,
+            options: Default::default(),
+        },
+        psf: PsfModel::Measured {
+            path: path.clone(),
+            region: None,
+        },
+        tiling: None,
+    };
+    let psf = Psf::load(&config.psf).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let (loaded, _) = deconvolve_loaded(&frame, &config, &psf).unwrap();
+    assert_eq!(loaded.data, measured.data);
+
+    let missing = PsfModel::Measured {
+        path: dir.path().join("missing.tiff"),
+        region: None,
+    };
+    assert!(Psf::load(&missing).is_err());
+    assert!(generate_psf(&missing, frame.data.dim()).is_err());
+}
+
+#[test]
+fn test_limb_estimate_recovers_blur() {
+    for sigma in [1.5f32, 2.5] {
+        let estimate =
+            estimate_limb_sigma(&blurred_disk(sigma), &LimbFitConfig::default()).unwrap();
+        assert!(
+            (estimate - sigma as f64).abs() < 0.3,
+            "blur {sigma}: estimated {estimate:.3}"
+        );
+    }
+
+    // The Limb model deconvolves with the estimated Gaussian.
+    let data = blurred_disk(2.0);
+    let psf = Psf::Model(PsfModel::Limb)
+        .for_image(&data)
+        .unwrap()
+        .generate(data.dim())
+        .unwrap();
+    assert!((psf.sum() - 1.0).abs() < 1e-4);
+    let reference = generate_psf(&PsfModel::Gaussian { sigma: 2.0 }, data.dim()).unwrap();
+    assert!((psf[[0, 0]] - reference[[0, 0]]).abs() / reference[[0, 0]] < 0.3);
+
+    assert!(estimate_limb_psf(&Array2::from_elem((64, 64), 0.3)).is_err());
+    // Without an image there is nothing to estimate the limb PSF from.
+    assert!(generate_psf(&PsfModel::Limb, data.dim()).is_err());
+}
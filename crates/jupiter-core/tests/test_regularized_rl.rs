@@ -0,0 +1,204 @@
+#[allow(dead_code)]
+mod common;
+
+use ndarray::{s, Array2};
+
+use jupiter_core::filters::gaussian_blur::gaussian_blur_array;
+use jupiter_core::frame::Frame;
+use jupiter_core::pipeline::config::{DeconvolutionConfig, DeconvolutionMethod, PsfModel};
+use jupiter_core::sharpen::deconvolution::deconvolve;
+use jupiter_core::sharpen::regularized_rl::{noise_sigma, RlOptions};
+
+fn rl(iterations: usize, options: RlOptions) -> DeconvolutionConfig {
+    DeconvolutionConfig {
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations,
+            options,
+        },
+        psf: PsfModel::Gaussian { sigma: 1.5 },
+        tiling: None,
+    }
+}
+
+#[test]
+fn test_noise_sigma_estimate() {
+    let mut data = Array2::<f32>::from_elem((128, 128), 0.3);
+    common::add_gaussian_noise(&mut data, 7, 0.02);
+    let sigma = noise_sigma(&data);
+    assert!((sigma - 0.02).abs() < 0.004, "estimated {sigma}");
+
+    let smooth = gaussian_blur_array(&common::planet(), 1.5);
+    assert!(noise_sigma(&smooth) < 0.005);
+}
+
+#[test]
+fn test_tv_and_damping_suppress_noise() {
+    let truth = common::planet();
+    let mut observed = gaussian_blur_array(&truth, 1.5);
+    common::add_gaussian_noise(&mut observed, 3, 0.01);
+    let frame = Frame::new(observed, 16);
+
+    let plain = deconvolve(&frame, &rl(40, RlOptions::default())).unwrap();
+    let regularized = deconvolve(
+        &frame,
+        &rl(
+            40,
+            RlOptions {
+                tv_weight: 0.005,
+                damping: 2.0,
+                ..Default::default()
+            },
+        ),
+    )
+    .unwrap();
+
+    // Background noise, far from the disk.
+    let sky = s![2..14, 2..14];
+    assert!(
+        common::std_dev(regularized.data.slice(sky)) < 0.7 * common::std_dev(plain.data.slice(sky)),
+        "regularized {} vs plain {}",
+        common::std_dev(regularized.data.slice(sky)),
+        common::std_dev(plain.data.slice(sky))
+    );
+    assert!(
+        common::rms(regularized.data.view(), truth.view())
+            < common::rms(plain.data.view(), truth.view())
+    );
+}
+
+#[test]
+fn test_auto_stop_ends_before_noise_fitting() {
+    let truth = common::planet();
+    let mut observed = gaussian_blur_array(&truth, 1.5);
+    common::add_gaussian_noise(&mut observed, 11, 0.01);
+    let frame = Frame::new(observed, 16);
+
+    let stopped = rl(
+        200,
+        RlOptions {
+            auto_stop: true,
+            ..Default::default()
+        },
+    );
+    let overrun = deconvolve(&frame, &rl(200, RlOptions::default())).unwrap();
+    let stopped = deconvolve(&frame, &stopped).unwrap();
+    assert!(
+        common::rms(stopped.data.view(), truth.view())
+            < common::rms(overrun.data.view(), truth.view())
+    );
+
+    // A noiseless image never reaches its (zero) noise floor, so it runs
+    // until the updates stop changing the estimate.
+    let clean = Frame::new(gaussian_blur_array(&truth, 1.5), 16);
+    let result = deconvolve(
+        &clean,
+        &rl(
+            5,
+            RlOptions {
+                auto_stop: true,
+                ..Default::default()
+            },
+        ),
+    )
+    .unwrap();
+    let fixed = deconvolve(&clean, &rl(5, RlOptions::default())).unwrap();
+    assert!(common::rms(result.data.view(), fixed.data.view()) < 1e-5);
+}
+
+#[test]
+fn test_edge_padding_removes_wraparound() {
+    // Bright band along the left edge, dark sky along the right edge: with
+    // periodic boundaries the two blur into each other across the seam.
+    let truth = Array2::from_shape_fn((64, 64), |(_, c)| if c < 12 { 0.9 } else { 0.05 });
+    let frame = Frame::new(gaussian_blur_array(&truth, 2.0), 16);
+    let config = |pad_edges| DeconvolutionConfig {
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 20,
+            options: RlOptions {
+                pad_edges,
+                ..Default::default()
+            },
+        },
+        psf: PsfModel::Gaussian { sigma: 2.0 },
+        tiling: None,
+    };
+
+    let wrapped = deconvolve(&frame, &config(false)).unwrap();
+    let padded = deconvolve(&frame, &config(true)).unwrap();
+    let right_edge = s![.., 58..];
+    let error = |data: &Array2<f32>| {
+        data.slice(right_edge)
+            .iter()
+            .map(|v| (v - 0.05).abs())
+            .fold(0.0f32, f32::max)
+    };
+    assert!(
+        error(&padded.data) < 0.5 * error(&wrapped.data),
+        "padded {} vs wrapped {}",
+        error(&padded.data),
+        error(&wrapped.data)
+    );
+    assert_eq!(padded.data.dim(), (64, 64));
+}
+
+#[test]
+fn test_planet_mask_leaves_sky_untouched() {
+    let mut observed = gaussian_blur_array(&common::planet(), 1.5);
+    common::add_gaussian_noise(&mut observed, 5, 0.01);
+    let frame = Frame::new(observed, 16);
+
+    let masked = deconvolve(
+        &frame,
+        &rl(
+            20,
+            RlOptions {
+                planet_mask: true,
+                ..Default::default()
+            },
+        ),
+    )
+    .unwrap();
+    let sky = s![0..12, 0..12];
+    let observed_sky = frame.data.slice(sky).mapv(|v| v.clamp(0.0, 1.0));
+    assert_eq!(masked.data.slice(sky), observed_sky);
+    // The disk itself is still deconvolved.
+    assert!((masked.data[[32, 32]] - frame.data[[32, 32]]).abs() > 1e-4);
+
+    let empty = Frame::new(Array2::from_elem((64, 64), 0.2), 16);
+    let config = rl(
+        5,
+        RlOptions {
+            planet_mask: true,
+            ..Default::default()
+        },
+    );
+    assert!(deconvolve(&empty, &config).is_err());
+}
+
+#[test]
+fn test_rl_options_serde_defaults() {
+    let plain: DeconvolutionMethod =
+        serde_json::from_str(r#"{"RichardsonLucy":{"iterations":12}}"#).unwrap();
+    match plain {
+        DeconvolutionMethod::RichardsonLucy {
+            iterations,
+            options,
+        } => {
+            assert_eq!(iterations, 12);
+            assert_eq!(options, RlOptions::default());
+        }
+        other => panic!("unexpected {other:?}"),
+    }
+
+    let tuned: DeconvolutionMethod = serde_json::from_str(
+        r#"{"RichardsonLucy":{"iterations":30,"tv_weight":0.002,"pad_edges":true}}"#,
+    )
+    .unwrap();
+    let DeconvolutionMethod::RichardsonLucy { options, .. } = tuned else {
+        panic!("unexpected {tuned:?}");
+    };
+    assert_eq!(options.tv_weight, 0.002);
+    assert!(options.pad_edges);
+    assert!(!options.auto_stop);
+    assert!(format!("{tuned}").contains("TV 0.002"));
+}
@@ -0,0 +1,246 @@
+#[allow(dead_code)]
+mod common;
+
+use std::sync::Arc;
+
+use ndarray::Array2;
+
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::frame::Frame;
+use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::{
+    AlignmentConfig, FrameSelectionConfig, PipelineConfig, StackMethod, StackingConfig,
+};
+use jupiter_core::pipeline::{run_pipeline, PipelineOutput};
+use jupiter_core::stack::mean::{weighted_mean_stack_with_progress, StreamingMeanStacker};
+use jupiter_core::stack::median::weighted_median_stack;
+use jupiter_core::stack::multi_point::{multi_point_stack, LocalStackMethod, MultiPointConfig};
+use jupiter_core::stack::rejection::{rejection_stack, GesdParams, Rejection};
+
+const SIZE: usize = 32;
+const STREAK_ROW: usize = 10;
+
+fn make_frame(fill: f32) -> Frame {
+    Frame::new(Array2::from_elem((8, 8), fill), 8)
+}
+
+/// Twelve noisy frames of a flat 0.5 field; frame 5 has a bright streak
+/// across row [`STREAK_ROW`].
+fn streaked_frames() -> Vec<Frame> {
+    let mut next = common::noise(7);
+    (0..12)
+        .map(|i| {
+            let mut data = Array2::from_shape_fn((SIZE, SIZE), |_| 0.5 + 0.02 * next());
+            if i == 5 {
+                data.row_mut(STREAK_ROW).fill(1.0);
+            }
+            Frame::new(data, 8)
+        })
+        .collect()
+}
+
+#[test]
+fn test_weighted_mean_and_median() {
+    let frames = vec![make_frame(0.2), make_frame(0.4), make_frame(0.9)];
+    let weights = [1.0, 1.0, 4.0];
+
+    let mean = weighted_mean_stack_with_progress(&frames, &weights, |_| {}).unwrap();
+    assert!(mean.data.iter().all(|&v| (v - 0.7).abs() < 1e-5));
+
+    let mut stacker = StreamingMeanStacker::new(8, 8, 8);
+    for (frame, &w) in frames.iter().zip(&weights) {
+        stacker.add_weighted(frame, w as f32);
+    }
+    let streamed = stacker.finalize().unwrap();
+    assert!(streamed.data.iter().all(|&v| (v - 0.7).abs() < 1e-5));
+
+    // Cumulative weights 1, 2, 6: half the total (3) is reached at 0.9.
+    let median = weighted_median_stack(&frames, &weights).unwrap();
+    assert!(median.data.iter().all(|&v| (v - 0.9).abs() < 1e-5));
+    // Equal weights give the plain median, averaging the middle pair.
+    let even = [
+        make_frame(0.1),
+        make_frame(0.3),
+        make_frame(0.7),
+        make_frame(0.9),
+    ];
+    let median = weighted_median_stack(&even, &[1.0; 4]).unwrap();
+    assert!(median.data.iter().all(|&v| (v - 0.5).abs() < 1e-5));
+
+    assert!(weighted_median_stack(&frames, &weights[..2]).is_err());
+    assert!(rejection_stack(&frames, &Rejection::None, Some(&weights[..1])).is_err());
+}
+
+#[test]
+fn test_rejection_methods_remove_streak() {
+    let frames = streaked_frames();
+    let methods = [
+        Rejection::SigmaClip {
+            sigma: 2.5,
+            iterations: 2,
+        },
+        Rejection::Winsorized {
+            sigma: 2.5,
+            iterations: 3,
+        },
+        Rejection::LinearFit {
+            sigma: 2.5,
+            iterations: 2,
+        },
+        GesdParams::default().rejection(),
+    ];
+
+    let (plain, map) = rejection_stack(&frames, &Rejection::None, None).unwrap();
+    assert_eq!(map.total(), 0);
+    assert!(plain.data[[STREAK_ROW, 0]] > 0.53, "streak should survive");
+
+    for method in methods {
+        let (stacked, map) = rejection_stack(&frames, &method, None).unwrap();
+        for c in 0..SIZE {
+            let v = stacked.data[[STREAK_ROW, c]];
+            assert!((v - 0.5).abs() < 0.02, "{method:?}: streak left ({v:.3})");
+            assert!(map.counts[[STREAK_ROW, c]] >= 1, "{method:?}: not counted");
+        }
+        // Pure noise elsewhere: only a small fraction may be rejected.
+        let clean: u32 = map.counts.iter().sum::<u32>() - map.counts.row(STREAK_ROW).sum();
+        let fraction = clean as f64 / ((SIZE - 1) * SIZE * frames.len()) as f64;
+        assert!(
+            fraction < 0.05,
+            "{method:?}: rejected {fraction:.3} of noise"
+        );
+
+        let fraction_map = map.to_frame();
+        assert_eq!(fraction_map.data.dim(), (SIZE, SIZE));
+        assert!(fraction_map.data[[STREAK_ROW, 0]] > 0.0);
+    }
+}
+
+#[test]
+fn test_quality_weights_favour_good_frames() {
+    // Two good frames at 0.6 with high scores, three poor ones at 0.3.
+    let frames: Vec<Frame> = [0.6, 0.6, 0.3, 0.3, 0.3]
+        .iter()
+        .map(|&v| make_frame(v))
+        .collect();
+    let scores = [10.0, 10.0, 1.0, 1.0, 1.0];
+
+    let (unweighted, _) = rejection_stack(&frames, &Rejection::None, None).unwrap();
+    let (weighted, _) = rejection_stack(&frames, &Rejection::None, Some(&scores)).unwrap();
+    assert!((unweighted.data[[0, 0]] - 0.42).abs() < 1e-5);
+    assert!((weighted.data[[0, 0]] - (12.0 + 0.9) / 23.0).abs() < 1e-5);
+
+    let median = weighted_median_stack(&frames, &scores).unwrap();
+    assert!((median.data[[0, 0]] - 0.6).abs() < 1e-5);
+}
+
+/// Textured disk, 8-bit, for the multi-point runs.
+fn disk() -> Vec<u8> {
+    let mut data = vec![0u8; 96 * 96];
+    for r in 0..96 {
+        for c in 0..96 {
+            let (y, x) = (r as f64 - 48.0, c as f64 - 48.0);
+            let v = if y.hypot(x) < 36.0 {
+                0.5 + 0.2 * (x / 5.0).sin() * (y / 7.0).cos()
+            } else {
+                0.05
+            };
+            data[r * 96 + c] = (v * 255.0) as u8;
+        }
+    }
+    data
+}
+
+/// Ten copies of [`disk`], a satellite streak through one of them.
+fn streaked_disk_ser() -> tempfile::NamedTempFile {
+    let mut frames = vec![disk(); 10];
+    for c in 30..66 {
+        frames[4][40 * 96 + c] = 255;
+        frames[4][41 * 96 + c] = 255;
+    }
+    common::write_test_ser(&common::build_ser_with_frames(96, 96, &frames))
+}
+
+/// Largest difference from [`disk`] over the centre of the disk.
+fn disk_error(frame: &Frame) -> f32 {
+    let scene = disk();
+    let mut worst = 0.0f32;
+    for r in 24..72 {
+        for c in 24..72 {
+            let expected = scene[r * 96 + c] as f32 / 255.0;
+            worst = worst.max((frame.data[[r, c]] - expected).abs());
+        }
+    }
+    worst
+}
+
+#[test]
+fn test_multi_point_local_rejection() {
+    let ser = streaked_disk_ser();
+    let reader = SerReader::open(ser.path()).unwrap();
+
+    for method in [
+        LocalStackMethod::WinsorizedSigmaClip {
+            sigma: 2.5,
+            iterations: 3,
+        },
+        LocalStackMethod::LinearFitClip {
+            sigma: 2.5,
+            iterations: 2,
+        },
+        LocalStackMethod::Gesd {
+            max_outliers: 0.3,
+            alpha: 0.05,
+        },
+    ] {
+        let config = MultiPointConfig {
+            ap_size: 32,
+            search_radius: 8,
+            select_percentage: 1.0,
+            min_brightness: 0.01,
+            local_stack_method: method.clone(),
+            ..Default::default()
+        };
+        let result = multi_point_stack(
+            &reader,
+            &config,
+            &AlignmentConfig::default(),
+            &CpuBackend,
+            |_| {},
+        )
+        .unwrap();
+        let worst = disk_error(&result);
+        assert!(worst < 0.01, "{method}: streak left ({worst:.3})");
+    }
+}
+
+#[test]
+fn test_quality_weighted_rejection_pipeline() {
+    let ser = streaked_disk_ser();
+    let dir = tempfile::tempdir().unwrap();
+    let config = PipelineConfig {
+        input: ser.path().to_path_buf(),
+        extra_inputs: vec![],
+        output: dir.path().join("out.tiff"),
+        device: Default::default(),
+        memory: Default::default(),
+        debayer: None,
+        force_mono: true,
+        frame_selection: FrameSelectionConfig {
+            select_percentage: 1.0,
+            ..Default::default()
+        },
+        alignment: AlignmentConfig::default(),
+        stacking: StackingConfig {
+            method: StackMethod::WinsorizedSigmaClip(Default::default()),
+            quality_weighted: true,
+        },
+        sharpening: None,
+        filters: vec![],
+    };
+    let output = run_pipeline(&config, Arc::new(CpuBackend), |_, _| {}).unwrap();
+    let PipelineOutput::Mono(frame) = output else {
+        panic!("expected mono output");
+    };
+    let worst = disk_error(&frame);
+    assert!(worst < 0.01, "streak left ({worst:.3})");
+}
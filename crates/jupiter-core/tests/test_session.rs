@@ -0,0 +1,292 @@
+#[allow(dead_code)]
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+use jupiter_core::compute::cpu::CpuBackend;
+use jupiter_core::frame::{AlignmentOffset, ColorFrame, Frame, QualityScore};
+use jupiter_core::pipeline::config::{QualityMetric, StackMethod};
+use jupiter_core::pipeline::session::{CachedAlignment, SessionCache};
+use jupiter_core::pipeline::{
+    run_pipeline, run_pipeline_cached, PipelineOutput, PipelineStage, ProgressReporter,
+};
+use jupiter_core::stack::optical_flow::OpticalFlowConfig;
+
+/// Records which stages ran.
+#[derive(Default)]
+struct StageLog(Mutex<Vec<PipelineStage>>);
+
+impl ProgressReporter for StageLog {
+    fn begin_stage(&self, stage: PipelineStage, _total_items: Option<usize>) {
+        self.0.lock().unwrap().push(stage);
+    }
+}
+
+impl StageLog {
+    fn ran(&self, pred: impl Fn(&PipelineStage) -> bool) -> bool {
+        self.0.lock().unwrap().iter().any(pred)
+    }
+}
+
+fn write_capture(dir: &Path) -> PathBuf {
+    let frames: Vec<Vec<u8>> = (0..6)
+        .map(|i| {
+            let mut f = vec![10u8; 32 * 32];
+            for r in 12..20 {
+                for c in (12 + i % 2)..(20 + i % 2) {
+                    f[r * 32 + c] = 200;
+                }
+            }
+            f
+        })
+        .collect();
+    let path = dir.join("capture.ser");
+    std::fs::write(&path, common::build_ser_with_frames(32, 32, &frames)).unwrap();
+    path
+}
+
+fn constant_frame(value: f32) -> Frame {
+    Frame::new(ndarray::Array2::from_elem((32, 32), value), 8)
+}
+
+#[test]
+fn test_keys_chain_downstream() {
+    let dir = TempDir::new().unwrap();
+    let input = write_capture(dir.path());
+    let session = SessionCache::open(&input).unwrap();
+    let config = common::config_for(std::slice::from_ref(&input), dir.path());
+    let base = session.keys(&config, None, false).unwrap();
+
+    let mut stacking = config.clone();
+    stacking.stacking.method = StackMethod::Median;
+    let k = session.keys(&stacking, None, false).unwrap();
+    assert_eq!(k.quality, base.quality);
+    assert_eq!(k.alignment, base.alignment);
+    assert_ne!(k.stack, base.stack);
+
+    // A streaming approximation of the median never stands in for the exact one.
+    let approximate = session.keys(&stacking, None, true).unwrap();
+    assert_eq!(approximate.alignment, k.alignment);
+    assert_ne!(approximate.stack, k.stack);
+
+    let mut selection = config.clone();
+    selection.frame_selection.select_percentage = 0.8;
+    let k = session.keys(&selection, None, false).unwrap();
+    assert_eq!(k.quality, base.quality);
+    assert_ne!(k.alignment, base.alignment);
+    assert_ne!(k.stack, base.stack);
+
+    let mut metric = config.clone();
+    metric.frame_selection.metric = QualityMetric::Gradient;
+    let k = session.keys(&metric, None, false).unwrap();
+    assert_ne!(k.quality, base.quality);
+    assert_ne!(k.alignment, base.alignment);
+
+    // Sharpening is not part of any key.
+    let mut sharpen = config.clone();
+    sharpen.sharpening = Some(Default::default());
+    let k = session.keys(&sharpen, None, false).unwrap();
+    assert_eq!(k.stack, base.stack);
+}
+
+#[test]
+fn test_stages_roundtrip_on_disk() {
+    let dir = TempDir::new().unwrap();
+    let input = write_capture(dir.path());
+    let config = common::config_for(std::slice::from_ref(&input), dir.path());
+
+    let mut session = SessionCache::open(&input).unwrap();
+    let keys = session.keys(&config, None, false).unwrap();
+    let score = |v: f64| QualityScore {
+        laplacian_variance: v,
+        composite: v,
+    };
+    session.store_ranking(keys.quality.clone(), vec![(3, score(0.9)), (1, score(0.2))]);
+    session.store_alignment(
+        keys.alignment.clone(),
+        CachedAlignment {
+            selected: vec![3, 1],
+            offsets: vec![
+                AlignmentOffset::default(),
+                AlignmentOffset { dx: 0.5, dy: -1.0 },
+            ],
+            rejected: vec![],
+            confidences: vec![None, Some(12.5)],
+        },
+    );
+    let color = PipelineOutput::Color(ColorFrame {
+        red: constant_frame(0.1),
+        green: constant_frame(0.2),
+        blue: constant_frame(0.3),
+    });
+    session
+        .store_stack(keys.stack.clone(), &color, None)
+        .unwrap();
+    session.save().unwrap();
+    assert!(SessionCache::sidecar_dir(&input)
+        .join("session.json")
+        .exists());
+
+    let reopened = SessionCache::open(&input).unwrap();
+    let ranked = reopened.ranking(&keys.quality).unwrap();
+    assert_eq!(ranked[0].0, 3);
+    assert_eq!(ranked[1].1.composite, 0.2);
+    let alignment = reopened.alignment(&keys.alignment).unwrap();
+    assert_eq!(alignment.selected, vec![3, 1]);
+    assert_eq!(alignment.offsets[1].dy, -1.0);
+    assert_eq!(alignment.confidences, vec![None, Some(12.5)]);
+    assert_eq!(alignment.confidence_summary(), Some((12.5, 12.5)));
+    match reopened.stacked(&keys.stack).unwrap() {
+        PipelineOutput::Color(cf) => {
+            assert_eq!(cf.red.data[[0, 0]], 0.1);
+            assert_eq!(cf.blue.data[[31, 31]], 0.3);
+        }
+        PipelineOutput::Mono(_) => panic!("expected color stack"),
+    }
+
+    // A different stacking config misses.
+    let mut other = config.clone();
+    other.stacking.method = StackMethod::Median;
+    let other_keys = reopened.keys(&other, None, false).unwrap();
+    assert!(reopened.stacked(&other_keys.stack).is_none());
+    assert!(reopened.alignment(&other_keys.alignment).is_some());
+}
+
+#[test]
+fn test_pipeline_skips_cached_stages() {
+    let dir = TempDir::new().unwrap();
+    let input = write_capture(dir.path());
+    let config = common::config_for(std::slice::from_ref(&input), dir.path());
+    let backend = Arc::new(CpuBackend);
+
+    let mut session = SessionCache::open(&input).unwrap();
+    let first = Arc::new(StageLog::default());
+    run_pipeline_cached(&config, backend.clone(), first.clone(), &mut session).unwrap();
+    assert!(first.ran(|s| matches!(s, PipelineStage::QualityAssessment)));
+
+    // Replace the cached stack so a reuse is observable in the output.
+    let keys = session.keys(&config, None, false).unwrap();
+    let marker = PipelineOutput::Mono(constant_frame(0.25));
+    session.store_stack(keys.stack, &marker, None).unwrap();
+    session.save().unwrap();
+
+    // Only sharpening-related settings changed: the stack is reused.
+    let mut session = SessionCache::open(&input).unwrap();
+    let second = Arc::new(StageLog::default());
+    let out = run_pipeline_cached(&config, backend.clone(), second.clone(), &mut session)
+        .unwrap()
+        .to_mono();
+    assert_eq!(out.data[[5, 5]], 0.25);
+    assert!(!second.ran(|s| matches!(s, PipelineStage::QualityAssessment)));
+    assert!(!second.ran(|s| matches!(s, PipelineStage::Stacking)));
+
+    // New stacking method: the cached alignment is reused and stacking reruns.
+    // Shift every cached offset so the reuse shows up in the output.
+    let mut median = config.clone();
+    median.stacking.method = StackMethod::Median;
+    let cached = session.alignment(&keys.alignment).unwrap().clone();
+    let shifted = CachedAlignment {
+        offsets: vec![AlignmentOffset { dx: 3.0, dy: 0.0 }; cached.selected.len()],
+        ..cached
+    };
+    session.store_alignment(keys.alignment.clone(), shifted);
+    let third = Arc::new(StageLog::default());
+    let out = run_pipeline_cached(&median, backend.clone(), third.clone(), &mut session)
+        .unwrap()
+        .to_mono();
+    let fresh = run_pipeline(&median, backend, |_, _| {}).unwrap().to_mono();
+    assert!(third.ran(|s| matches!(s, PipelineStage::Stacking)));
+    let diff: f32 = (&out.data - &fresh.data).mapv(f32::abs).sum();
+    assert!(diff > 1.0, "cached offsets were not used");
+}
+
+#[test]
+fn test_self_selecting_methods_reuse_cached_alignment() {
+    let dir = TempDir::new().unwrap();
+    let input = write_capture(dir.path());
+    let mut config = common::config_for(std::slice::from_ref(&input), dir.path());
+    let flow = OpticalFlowConfig {
+        select_percentage: 0.5,
+        ..Default::default()
+    };
+    config.stacking.method = StackMethod::OpticalFlow(flow.clone());
+    let backend = Arc::new(CpuBackend);
+
+    let mut session = SessionCache::open(&input).unwrap();
+    run_pipeline_cached(
+        &config,
+        backend.clone(),
+        Arc::new(StageLog::default()),
+        &mut session,
+    )
+    .unwrap();
+    let keys = session.keys(&config, None, false).unwrap();
+    let mean = session
+        .keys(
+            &common::config_for(std::slice::from_ref(&input), dir.path()),
+            None,
+            false,
+        )
+        .unwrap();
+    assert_eq!(keys.quality, mean.quality);
+    assert_ne!(keys.alignment, mean.alignment);
+    assert!(session.ranking(&keys.quality).is_some());
+    let cached = session.alignment(&keys.alignment).unwrap().clone();
+    assert_eq!(cached.selected, (0..6).collect::<Vec<_>>());
+
+    // Only a flow setting changed: the global alignment comes from the cache.
+    let mut retuned = config.clone();
+    retuned.stacking.method = StackMethod::OpticalFlow(OpticalFlowConfig {
+        iterations: 2,
+        ..flow
+    });
+    let shifted = CachedAlignment {
+        offsets: vec![AlignmentOffset { dx: 3.0, dy: 0.0 }; cached.selected.len()],
+        ..cached
+    };
+    session.store_alignment(keys.alignment, shifted);
+    let log = Arc::new(StageLog::default());
+    let out = run_pipeline_cached(&retuned, backend.clone(), log.clone(), &mut session)
+        .unwrap()
+        .to_mono();
+    let fresh = run_pipeline(&retuned, backend, |_, _| {})
+        .unwrap()
+        .to_mono();
+    assert!(log.ran(|s| matches!(s, PipelineStage::Stacking)));
+    let diff: f32 = (&out.data - &fresh.data).mapv(f32::abs).sum();
+    assert!(diff > 1.0, "cached offsets were not used");
+}
+
+#[test]
+fn test_changed_input_discards_session() {
+    let dir = TempDir::new().unwrap();
+    let input = write_capture(dir.path());
+    let config = common::config_for(std::slice::from_ref(&input), dir.path());
+
+    let mut session = SessionCache::open(&input).unwrap();
+    run_pipeline_cached(
+        &config,
+        Arc::new(CpuBackend),
+        Arc::new(StageLog::default()),
+        &mut session,
+    )
+    .unwrap();
+    let old = session.keys(&config, None, false).unwrap();
+    assert!(session.ranking(&old.quality).is_some());
+    assert!(session.settings().is_some());
+
+    // Rewrite a pixel of the last frame.
+    let mut bytes = std::fs::read(&input).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    std::fs::write(&input, bytes).unwrap();
+
+    let reopened = SessionCache::open(&input).unwrap();
+    let new = reopened.keys(&config, None, false).unwrap();
+    assert_ne!(new.quality, old.quality);
+    assert!(reopened.ranking(&new.quality).is_none());
+    assert!(reopened.settings().is_none());
+    assert!(!reopened.has_stack(&new.stack));
+}
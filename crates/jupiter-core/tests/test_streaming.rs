@@ -8,9 +8,10 @@ use jupiter_core::align::phase_correlation::{compute_offset, compute_offsets_str
 use jupiter_core::color::debayer::{luminance, DebayerMethod};
 use jupiter_core::compute::cpu::CpuBackend;
 use jupiter_core::consts::LOW_MEMORY_THRESHOLD_BYTES;
+use jupiter_core::frame::Frame;
 use jupiter_core::io::ser::SerReader;
 use jupiter_core::pipeline::config::{
-    FrameSelectionConfig, MemoryStrategy, PipelineConfig, StackingConfig,
+    FrameSelectionConfig, MemoryStrategy, PipelineConfig, StackMethod, StackingConfig,
 };
 use jupiter_core::pipeline::run_pipeline;
 use jupiter_core::quality::gradient::{
@@ -20,6 +21,10 @@ use jupiter_core::quality::laplacian::{
     rank_frames, rank_frames_color_streaming, rank_frames_streaming,
 };
 use jupiter_core::stack::mean::{mean_stack, StreamingMeanStacker};
+use jupiter_core::stack::median::{median_stack, weighted_median_stack, StreamingMedianStacker};
+use jupiter_core::stack::rejection::rejection_stack;
+use jupiter_core::stack::sigma_clip::{SigmaClipParams, StreamingSigmaClipStacker};
+use jupiter_core::stack::streaming::stack_streaming;
 
 fn build_test_ser(width: u32, height: u32, num_frames: usize) -> Vec<u8> {
     let mut buf = common::build_ser_header(width, height, num_frames);
@@ -208,6 +213,137 @@ fn test_streaming_mean_stacker_empty_returns_error() {
     assert!(stacker.finalize().is_err());
 }
 
+// --- Streaming median / sigma-clip tests ---
+
+/// Fifteen 24x24 frames of smooth noise around a gradient; frame 3 carries
+/// a bright streak along row 5.
+fn noisy_frames() -> Vec<Frame> {
+    (0..15)
+        .map(|i| {
+            let mut data =
+                ndarray::Array2::from_shape_fn((24, 24), |(r, c)| 0.3 + 0.01 * (r + c) as f32);
+            common::add_noise(&mut data, 11 + i as u64, 0.02);
+            if i == 3 {
+                data.row_mut(5).fill(1.0);
+            }
+            Frame::new(data, 16)
+        })
+        .collect()
+}
+
+fn max_diff(a: &Frame, b: &Frame) -> f32 {
+    assert_eq!(a.data.dim(), b.data.dim());
+    a.data
+        .iter()
+        .zip(b.data.iter())
+        .map(|(x, y)| (x - y).abs())
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn test_streaming_median_matches_batch() {
+    let frames = noisy_frames();
+    let load = |i: usize| Ok(frames[i].clone());
+
+    let streamed = stack_streaming(
+        StreamingMedianStacker::new(24, 24, 16),
+        frames.len(),
+        None,
+        load,
+        |_| {},
+    )
+    .unwrap();
+    let diff = max_diff(&streamed, &median_stack(&frames).unwrap());
+    assert!(diff < 1e-3, "median differs by {diff}");
+
+    // Irregular weights, so no cumulative sum lands exactly on half the
+    // total (where the batch median averages two values).
+    let weights: Vec<f64> = (0..frames.len()).map(|i| 1.0 + (i as f64).sqrt()).collect();
+    let streamed = stack_streaming(
+        StreamingMedianStacker::new(24, 24, 16),
+        frames.len(),
+        Some(&weights),
+        load,
+        |_| {},
+    )
+    .unwrap();
+    let diff = max_diff(
+        &streamed,
+        &weighted_median_stack(&frames, &weights).unwrap(),
+    );
+    assert!(diff < 1e-3, "weighted median differs by {diff}");
+}
+
+#[test]
+fn test_streaming_sigma_clip_matches_batch() {
+    let frames = noisy_frames();
+    let params = SigmaClipParams::default();
+    let weights: Vec<f64> = (0..frames.len()).map(|i| 1.0 + i as f64).collect();
+
+    for weights in [None, Some(weights.as_slice())] {
+        let progress = std::cell::Cell::new(0);
+        let streamed = stack_streaming(
+            StreamingSigmaClipStacker::new(24, 24, 16, &params),
+            frames.len(),
+            weights,
+            |i| Ok(frames[i].clone()),
+            |done| progress.set(done),
+        )
+        .unwrap();
+        assert_eq!(progress.get(), (params.iterations + 1) * frames.len());
+        let (batch, _) = rejection_stack(&frames, &params.rejection(), weights).unwrap();
+        let diff = max_diff(&streamed, &batch);
+        assert!(diff < 1e-4, "sigma clip differs by {diff}");
+        assert!((streamed.data[[5, 10]] - batch.data[[5, 10]]).abs() < 1e-4);
+        assert!(streamed.data[[5, 10]] < 0.5, "streak survived");
+    }
+}
+
+#[test]
+fn test_streaming_sigma_clip_keeps_values_rejected() {
+    // The first pass clips 0.23 and 0.96; the mean of the rest rises enough
+    // that the second pass's bounds alone would let 0.96 back in. The batch
+    // stack keeps it out, so the streaming bounds must not widen.
+    let values = [0.95, 0.47, 0.35, 0.23, 0.93, 0.23, 0.66, 0.96];
+    let frames: Vec<Frame> = values
+        .iter()
+        .map(|&v| Frame::new(ndarray::Array2::from_elem((4, 4), v), 16))
+        .collect();
+    let params = SigmaClipParams {
+        iterations: 2,
+        sigma: 1.2,
+    };
+
+    let streamed = stack_streaming(
+        StreamingSigmaClipStacker::new(4, 4, 16, &params),
+        frames.len(),
+        None,
+        |i| Ok(frames[i].clone()),
+        |_| {},
+    )
+    .unwrap();
+    let (batch, _) = rejection_stack(&frames, &params.rejection(), None).unwrap();
+    let diff = max_diff(&streamed, &batch);
+    assert!(diff < 1e-4, "sigma clip differs by {diff}");
+    assert!((streamed.data[[0, 0]] - 0.7525).abs() < 1e-4);
+}
+
+#[test]
+fn test_streaming_stackers_need_every_pass() {
+    let frames = noisy_frames();
+    let mut median = StreamingMedianStacker::new(24, 24, 16);
+    for frame in &frames {
+        median.add_weighted(frame, 1.0);
+    }
+    median.end_pass();
+    assert!(median.finalize().is_err());
+
+    let params = SigmaClipParams::default();
+    assert!(StreamingSigmaClipStacker::new(24, 24, 16, &params)
+        .finalize()
+        .is_err());
+}
+
 // --- Streaming offset computation test ---
 
 #[test]
@@ -277,6 +413,7 @@ fn test_streaming_pipeline_mean_matches_eager() {
     // Eager pipeline
     let config_eager = PipelineConfig {
         input: ser_file.path().to_path_buf(),
+        extra_inputs: vec![],
         output: out_eager.path().join("eager.tiff"),
         device: Default::default(),
         memory: MemoryStrategy::Eager,
@@ -297,6 +434,7 @@ fn test_streaming_pipeline_mean_matches_eager() {
     // Streaming pipeline (force low-memory)
     let config_streaming = PipelineConfig {
         input: ser_file.path().to_path_buf(),
+        extra_inputs: vec![],
         output: out_streaming.path().join("streaming.tiff"),
         device: Default::default(),
         memory: MemoryStrategy::LowMemory,
@@ -336,6 +474,49 @@ fn test_streaming_pipeline_mean_matches_eager() {
     );
 }
 
+#[test]
+fn test_streaming_pipeline_median_and_sigma_clip_match_eager() {
+    let ser_file = write_test_ser_file(11);
+    let out = TempDir::new().unwrap();
+    let backend = Arc::new(CpuBackend);
+
+    let run = |method: StackMethod, memory: MemoryStrategy| {
+        let config = PipelineConfig {
+            input: ser_file.path().to_path_buf(),
+            extra_inputs: vec![],
+            output: out.path().join("out.tiff"),
+            device: Default::default(),
+            memory,
+            debayer: None,
+            force_mono: false,
+            frame_selection: FrameSelectionConfig {
+                select_percentage: 1.0,
+                ..Default::default()
+            },
+            alignment: Default::default(),
+            stacking: StackingConfig {
+                method,
+                quality_weighted: true,
+            },
+            sharpening: None,
+            filters: vec![],
+        };
+        run_pipeline(&config, backend.clone(), |_, _| {})
+            .unwrap()
+            .to_mono()
+    };
+
+    for (method, tolerance) in [
+        (StackMethod::SigmaClip(SigmaClipParams::default()), 1e-4),
+        (StackMethod::Median, 2.0 / 255.0),
+    ] {
+        let eager = run(method.clone(), MemoryStrategy::Eager);
+        let streaming = run(method.clone(), MemoryStrategy::LowMemory);
+        let diff = max_diff(&eager, &streaming);
+        assert!(diff < tolerance, "{method}: streaming differs by {diff}");
+    }
+}
+
 #[test]
 fn test_memory_strategy_display() {
     assert_eq!(format!("{}", MemoryStrategy::Auto), "Auto");
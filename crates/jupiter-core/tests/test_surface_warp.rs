@@ -12,12 +12,13 @@ use jupiter_core::compute::cpu::CpuBackend;
 use jupiter_core::frame::AlignmentOffset;
 use jupiter_core::io::ser::SER_HEADER_SIZE;
 use jupiter_core::pipeline::config::{
-    FrameSelectionConfig, PipelineConfig, StackMethod, StackingConfig,
+    AlignmentConfig, FrameSelectionConfig, PipelineConfig, StackMethod, StackingConfig,
 };
 use jupiter_core::pipeline::{run_pipeline, PipelineOutput};
 use jupiter_core::stack::multi_point::{build_ap_grid, MultiPointConfig};
 use jupiter_core::stack::surface_warp::{
-    interpolate_shift_field, surface_warp_stack, warp_frame, SurfaceWarpConfig,
+    interpolate_shift_field, spline_shift_field, surface_warp_stack, warp_frame, ShiftFieldModel,
+    SurfaceWarpConfig,
 };
 
 /// Build a synthetic SER file with 8-bit mono pixels.
@@ -89,7 +90,13 @@ fn test_surface_warp_identity() {
         ..Default::default()
     };
 
-    let result = surface_warp_stack(&reader, &config, |_| {});
+    let result = surface_warp_stack(
+        &reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |_| {},
+    );
     assert!(
         result.is_ok(),
         "surface_warp_stack failed: {:?}",
@@ -122,7 +129,13 @@ fn test_surface_warp_shifted_frames() {
         ..Default::default()
     };
 
-    let result = surface_warp_stack(&reader, &config, |_| {});
+    let result = surface_warp_stack(
+        &reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |_| {},
+    );
     assert!(result.is_ok(), "shifted frames failed: {:?}", result.err());
 
     let frame = result.unwrap();
@@ -186,6 +199,86 @@ fn test_shift_field_interpolation() {
     );
 }
 
+#[test]
+fn test_spline_field_bridges_gaps_and_rejects_outliers() {
+    let (h, w) = (160, 160);
+    let mp_config = MultiPointConfig {
+        ap_size: 32,
+        search_radius: 8,
+        min_brightness: 0.0,
+        ..Default::default()
+    };
+    let grid = build_ap_grid(&Array2::<f32>::from_elem((h, w), 0.5), &mp_config);
+    // A smooth tilt in the local shifts.
+    let truth = |y: f64, x: f64| (0.5 + 0.01 * x, -0.3 + 0.008 * y);
+
+    let centre = |i: usize| (grid.points[i].cy as f64, grid.points[i].cx as f64);
+    let outlier = grid
+        .points
+        .iter()
+        .position(|ap| (ap.cy, ap.cx) == (80, 80))
+        .unwrap();
+    let gap = grid
+        .points
+        .iter()
+        .position(|ap| (ap.cy, ap.cx) == (48, 112))
+        .unwrap();
+    let mut local_offsets = HashMap::new();
+    for ap in &grid.points {
+        let (dy, dx) = truth(ap.cy as f64, ap.cx as f64);
+        local_offsets.insert(ap.index, AlignmentOffset { dy, dx });
+    }
+    local_offsets.insert(outlier, AlignmentOffset { dy: 6.0, dx: -5.0 });
+    local_offsets.remove(&gap);
+
+    let global = AlignmentOffset { dy: 1.0, dx: 2.0 };
+    let (fy, fx) = spline_shift_field(&grid, &local_offsets, &global, h, w, 0.05);
+    assert_eq!(fy.dim(), (h, w));
+    for i in [outlier, gap] {
+        let (y, x) = centre(i);
+        let (ty, tx) = truth(y, x);
+        let (ey, ex) = (
+            fy[[y as usize, x as usize]] - 1.0,
+            fx[[y as usize, x as usize]] - 2.0,
+        );
+        assert!(
+            (ey - ty).hypot(ex - tx) < 0.1,
+            "AP at ({y}, {x}): ({ey:.2}, {ex:.2}) vs ({ty:.2}, {tx:.2})"
+        );
+    }
+
+    // Bilinear follows the outlier and drops the gap to the global offset.
+    let (by, _) = interpolate_shift_field(&grid, &local_offsets, &global, h, w);
+    assert!((by[[80, 80]] - 7.0).abs() < 1e-9);
+    assert!((by[[48, 112]] - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_surface_warp_spline_field() {
+    let ser_file = write_mono_ser(64, 64, 8, true);
+    let reader = jupiter_core::io::ser::SerReader::open(ser_file.path()).unwrap();
+    let config = SurfaceWarpConfig {
+        ap_size: 32,
+        search_radius: 8,
+        select_percentage: 0.5,
+        min_brightness: 0.01,
+        field_model: ShiftFieldModel::thin_plate_spline(),
+        ..Default::default()
+    };
+
+    let frame = surface_warp_stack(
+        &reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |_| {},
+    )
+    .unwrap();
+    assert_eq!(frame.data.dim(), (64, 64));
+    assert!(frame.data[[32, 32]] > 0.5);
+    assert!(frame.data[[4, 4]] < 0.3);
+}
+
 #[test]
 fn test_confidence_check() {
     // If we feed noise, the confidence should be low and some frames should be
@@ -214,7 +307,13 @@ fn test_confidence_check() {
     };
 
     // Should not panic even with noisy data
-    let result = surface_warp_stack(&reader, &config, |_| {});
+    let result = surface_warp_stack(
+        &reader,
+        &config,
+        &AlignmentConfig::default(),
+        &CpuBackend,
+        |_| {},
+    );
     assert!(
         result.is_ok(),
         "noisy data should still succeed: {:?}",
@@ -316,6 +415,7 @@ fn test_surface_warp_pipeline_integration() {
 
     let config = PipelineConfig {
         input: ser_file.path().to_path_buf(),
+        extra_inputs: vec![],
         output: output_path.clone(),
         device: Default::default(),
         memory: Default::default(),
@@ -333,6 +433,7 @@ fn test_surface_warp_pipeline_integration() {
                 min_brightness: 0.01,
                 ..Default::default()
             }),
+            ..Default::default()
         },
         sharpening: None,
         filters: vec![],
@@ -0,0 +1,131 @@
+#[allow(dead_code)]
+mod common;
+
+use ndarray::{s, Array2};
+
+use jupiter_core::filters::gaussian_blur::gaussian_blur_array;
+use jupiter_core::frame::Frame;
+use jupiter_core::pipeline::config::{
+    DeconvolutionConfig, DeconvolutionMethod, PsfModel, TilePsf, TiledDeconvolution,
+};
+use jupiter_core::sharpen::deconvolution::deconvolve;
+use jupiter_core::sharpen::tiled::blur_measure;
+
+/// Deterministic white-noise texture in [0.2, 0.8], lightly smoothed.
+fn texture(h: usize, w: usize) -> Array2<f32> {
+    let mut noise = Array2::from_elem((h, w), 0.5);
+    common::add_noise(&mut noise, 0x2545_f491_4f6c_dd1d, 0.3);
+    gaussian_blur_array(&noise, 0.7)
+}
+
+/// `truth` blurred by `left` on the left half and by `right` on the right.
+fn split_blur(truth: &Array2<f32>, left: f32, right: f32) -> Array2<f32> {
+    let (a, b) = (
+        gaussian_blur_array(truth, left),
+        gaussian_blur_array(truth, right),
+    );
+    let half = truth.ncols() / 2;
+    Array2::from_shape_fn(
+        truth.dim(),
+        |(r, c)| {
+            if c < half {
+                a[[r, c]]
+            } else {
+                b[[r, c]]
+            }
+        },
+    )
+}
+
+fn config(sigma: f32, tiling: Option<TiledDeconvolution>) -> DeconvolutionConfig {
+    DeconvolutionConfig {
+        method: DeconvolutionMethod::RichardsonLucy {
+            iterations: 15,
+            options: Default::default(),
+        },
+        psf: PsfModel::Gaussian { sigma },
+        tiling,
+    }
+}
+
+#[test]
+fn test_blur_measure_tracks_blur_width() {
+    let truth = texture(64, 64);
+    let (narrow, _) = blur_measure(&gaussian_blur_array(&truth, 1.0)).unwrap();
+    let (wide, _) = blur_measure(&gaussian_blur_array(&truth, 2.0)).unwrap();
+    let ratio = wide / narrow;
+    assert!((1.4..2.4).contains(&ratio), "ratio {ratio:.3}");
+
+    // Contrast does not change the blur scale.
+    let faint = gaussian_blur_array(&truth, 2.0).mapv(|v| 0.3 * v);
+    let (scaled, _) = blur_measure(&faint).unwrap();
+    assert!((scaled - wide).abs() < 1e-4);
+
+    assert!(blur_measure(&Array2::from_elem((32, 32), 0.4)).is_none());
+}
+
+#[test]
+fn test_uniform_tiles_blend_seamlessly() {
+    let truth = texture(128, 128);
+    let frame = Frame::new(gaussian_blur_array(&truth, 1.5), 16);
+
+    let whole = deconvolve(&frame, &config(1.5, None)).unwrap();
+    let tiling = TiledDeconvolution {
+        tile_size: 48,
+        psf: TilePsf::Uniform,
+    };
+    let tiled = deconvolve(&frame, &config(1.5, Some(tiling))).unwrap();
+    assert_eq!(tiled.data.dim(), (128, 128));
+
+    // Away from the image border the tiles reproduce the global result.
+    let inner = s![16..112, 16..112];
+    let diff = common::rms(tiled.data.slice(inner), whole.data.slice(inner));
+    assert!(diff < 0.01, "tiled differs from global by {diff}");
+}
+
+#[test]
+fn test_local_quality_tiles_follow_varying_seeing() {
+    let truth = texture(128, 192);
+    let frame = Frame::new(split_blur(&truth, 1.0, 2.0), 16);
+
+    let global = deconvolve(&frame, &config(1.5, None)).unwrap();
+    let tiling = TiledDeconvolution {
+        tile_size: 64,
+        psf: TilePsf::LocalQuality,
+    };
+    let tiled = deconvolve(&frame, &config(1.5, Some(tiling))).unwrap();
+
+    // Compare away from the seam between the two seeing regions. The
+    // configured PSF is too wide for the steady half, which the tiles
+    // sharpen properly, and about right for the blurred half, where they
+    // should do no worse.
+    let error = |data: &Array2<f32>, region| common::rms(data.slice(region), truth.slice(region));
+    let (steady, blurred) = (s![8..120, 8..80], s![8..120, 112..184]);
+    let (global_steady, tiled_steady) = (error(&global.data, steady), error(&tiled.data, steady));
+    assert!(
+        tiled_steady < 0.85 * global_steady,
+        "steady: tiled {tiled_steady:.4} vs global {global_steady:.4}"
+    );
+    let (global_blurred, tiled_blurred) =
+        (error(&global.data, blurred), error(&tiled.data, blurred));
+    assert!(
+        tiled_blurred < 1.02 * global_blurred,
+        "blurred: tiled {tiled_blurred:.4} vs global {global_blurred:.4}"
+    );
+}
+
+#[test]
+fn test_tiling_serde_defaults() {
+    let parsed: DeconvolutionConfig = serde_json::from_str(
+        r#"{"method":{"Wiener":{"noise_ratio":0.01}},"psf":{"Gaussian":{"sigma":1.5}}}"#,
+    )
+    .unwrap();
+    assert!(parsed.tiling.is_none());
+
+    let parsed: DeconvolutionConfig = serde_json::from_str(
+        r#"{"method":{"Wiener":{"noise_ratio":0.01}},"psf":{"Gaussian":{"sigma":1.5}},"tiling":{}}"#,
+    )
+    .unwrap();
+    assert_eq!(parsed.tiling, Some(TiledDeconvolution::default()));
+    assert_eq!(parsed.tiling.unwrap().psf, TilePsf::LocalQuality);
+}
@@ -0,0 +1,149 @@
+use ndarray::Array2;
+
+use jupiter_core::filters::transform::{canvas, flip, resample, rotate};
+use jupiter_core::frame::{ColorFrame, Frame};
+use jupiter_core::pipeline::apply_filter_step_color;
+use jupiter_core::pipeline::config::{FilterStep, ResampleMethod};
+
+/// Black 40x60 frame with one bright pixel at (row, col).
+fn marker(row: usize, col: usize) -> Frame {
+    let mut data = Array2::zeros((40, 60));
+    data[[row, col]] = 1.0;
+    Frame::new(data, 16)
+}
+
+fn brightest(frame: &Frame) -> (usize, usize) {
+    frame
+        .data
+        .indexed_iter()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(index, _)| index)
+        .unwrap()
+}
+
+/// Disk of radius 10 centred at (row, col) on a 100x120 dark sky.
+fn planet_at(row: f32, col: f32) -> Frame {
+    let data = Array2::from_shape_fn((100, 120), |(r, c)| {
+        if (r as f32 - row).hypot(c as f32 - col) < 10.0 {
+            0.8
+        } else {
+            0.02
+        }
+    });
+    Frame::new(data, 16)
+}
+
+#[test]
+fn test_flip() {
+    let frame = marker(5, 10);
+    assert_eq!(brightest(&flip(&frame, true, false)), (5, 49));
+    assert_eq!(brightest(&flip(&frame, false, true)), (34, 10));
+    assert_eq!(brightest(&flip(&frame, true, true)), (34, 49));
+    assert_eq!(flip(&frame, false, false).data, frame.data);
+}
+
+#[test]
+fn test_rotate_right_angle_and_identity() {
+    let frame = marker(10, 40);
+    let same = rotate(&frame, 0.0, false);
+    assert!(same
+        .data
+        .iter()
+        .zip(frame.data.iter())
+        .all(|(a, b)| (a - b).abs() < 1e-6));
+
+    // Counter-clockwise: a point right of centre moves above it, and the
+    // expanded canvas swaps width and height.
+    let turned = rotate(&frame, 90.0, true);
+    assert_eq!(turned.data.dim(), (60, 40));
+    let (cy, cx) = (19.5, 29.5);
+    let (y, x) = (10.0 - cy, 40.0 - cx);
+    let expected = ((29.5 - x) as usize, (19.5 + y) as usize);
+    assert_eq!(brightest(&turned), expected);
+    assert_eq!(rotate(&frame, 90.0, false).data.dim(), (40, 60));
+}
+
+#[test]
+fn test_resample_sizes_and_levels() {
+    let ramp = Frame::new(
+        Array2::from_shape_fn((32, 48), |(_, c)| c as f32 / 47.0),
+        16,
+    );
+    for method in [ResampleMethod::Lanczos3, ResampleMethod::Bicubic] {
+        let up = resample(&ramp, 2.0, method);
+        assert_eq!(up.data.dim(), (64, 96));
+        // A linear ramp stays linear away from the edges.
+        for c in 8..88 {
+            let expected = ((c as f32 + 0.5) / 2.0 - 0.5) / 47.0;
+            assert!(
+                (up.data[[10, c]] - expected).abs() < 1e-3,
+                "{method} col {c}"
+            );
+        }
+
+        let down = resample(&ramp, 0.5, method);
+        assert_eq!(down.data.dim(), (16, 24));
+        let mean = |f: &Frame| f.data.mean().unwrap();
+        assert!((mean(&down) - mean(&ramp)).abs() < 0.01);
+    }
+}
+
+#[test]
+fn test_canvas_centres_planet() {
+    let frame = planet_at(30.0, 80.0);
+    let cropped = canvas(&frame, 41, 41);
+    assert_eq!(cropped.data.dim(), (41, 41));
+    assert!(cropped.data[[20, 20]] > 0.5);
+    for (r, c) in [(20, 12), (20, 28), (12, 20), (28, 20)] {
+        assert!(cropped.data[[r, c]] > 0.5, "({r}, {c})");
+    }
+    assert!(cropped.data[[0, 0]] < 0.1 && cropped.data[[40, 40]] < 0.1);
+
+    // Padding beyond the frame is black.
+    let padded = canvas(&frame, 300, 300);
+    assert_eq!(padded.data.dim(), (300, 300));
+    assert!(padded.data[[150, 150]] > 0.5);
+    assert_eq!(padded.data[[0, 0]], 0.0);
+}
+
+#[test]
+fn test_color_canvas_uses_one_centre() {
+    // The planet is only bright in red; all channels must be cut alike.
+    let red = planet_at(40.0, 50.0);
+    let dark = Frame::new(Array2::from_elem((100, 120), 0.02), 16);
+    let color = ColorFrame {
+        red,
+        green: dark.clone(),
+        blue: dark,
+    };
+    let step = FilterStep::Canvas {
+        width: 31,
+        height: 31,
+    };
+    let result = apply_filter_step_color(&color, &step);
+    assert_eq!(result.green.data.dim(), (31, 31));
+    assert!(result.red.data[[15, 15]] > 0.5);
+    assert_eq!(result.green.data[[15, 15]], 0.02);
+}
+
+#[test]
+fn test_transform_step_serde_and_display() {
+    let step: FilterStep = serde_json::from_str(r#"{"Rotate":{"degrees":12.5}}"#).unwrap();
+    assert_eq!(format!("{step}"), "Rotate (12.5\u{00b0})");
+
+    let step: FilterStep = serde_json::from_str(r#"{"Resample":{"scale":1.5}}"#).unwrap();
+    assert!(matches!(
+        step,
+        FilterStep::Resample {
+            method: ResampleMethod::Lanczos3,
+            ..
+        }
+    ));
+    assert_eq!(format!("{step}"), "Resample (1.5x, Lanczos)");
+
+    let step = FilterStep::Flip {
+        horizontal: true,
+        vertical: false,
+    };
+    assert_eq!(format!("{step}"), "Flip (horizontal)");
+}
@@ -1,7 +1,27 @@
 use ndarray::Array2;
 
-use jupiter_core::frame::Frame;
-use jupiter_core::sharpen::wavelet::{self, decompose, mirror_index, reconstruct, WaveletParams};
+use jupiter_core::filters::gaussian_blur::gaussian_blur_array;
+use jupiter_core::frame::{ColorFrame, Frame};
+use jupiter_core::sharpen::wavelet::{
+    self, decompose, decompose_with, mirror_index, reconstruct, LayerDenoise, WaveletKernel,
+    WaveletParams, WaveletScaling,
+};
+
+/// Deterministic noise in [-amplitude, amplitude].
+fn noise(h: usize, w: usize, seed: u64, amplitude: f32) -> Array2<f32> {
+    let mut state = seed;
+    Array2::from_shape_fn((h, w), |_| {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        amplitude * (2.0 * ((state >> 40) as f32 / (1u64 << 24) as f32) - 1.0)
+    })
+}
+
+fn std_dev(data: ndarray::ArrayView2<f32>) -> f32 {
+    let mean = data.mean().unwrap();
+    (data.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / data.len() as f32).sqrt()
+}
 
 #[test]
 fn test_decompose_reconstruct_identity() {
@@ -43,6 +63,7 @@ fn test_sharpening_increases_contrast() {
         num_layers: 4,
         coefficients: vec![2.0, 1.5, 1.0, 1.0],
         denoise: vec![],
+        ..Default::default()
     };
     let sharpened = wavelet::sharpen(&frame, &params);
 
@@ -82,3 +103,144 @@ fn test_mirror_index() {
     assert_eq!(mirror_index(10, 10), 9);
     assert_eq!(mirror_index(11, 10), 8);
 }
+
+#[test]
+fn test_gaussian_kernel_and_linear_scales_reconstruct() {
+    assert_eq!(WaveletScaling::Dyadic.scale(3), 8);
+    assert_eq!(WaveletScaling::Linear { step: 2 }.scale(3), 7);
+
+    let data = Array2::from_shape_fn((40, 40), |(r, c)| {
+        (r as f32 * 0.3).sin() * (c as f32 * 0.2).cos() * 0.4 + 0.5
+    });
+    for (kernel, scaling) in [
+        (WaveletKernel::Gaussian, WaveletScaling::Dyadic),
+        (WaveletKernel::B3Spline, WaveletScaling::Linear { step: 1 }),
+        (WaveletKernel::Gaussian, WaveletScaling::Linear { step: 2 }),
+    ] {
+        let (layers, residual) = decompose_with(&data, 5, kernel, scaling);
+        assert_eq!(layers.len(), 5);
+        let sum = layers.iter().fold(residual, |acc, layer| acc + layer);
+        let max_diff = (&sum - &data).iter().fold(0.0f32, |m, v| m.max(v.abs()));
+        assert!(max_diff < 1e-4, "{kernel:?} {scaling:?}: {max_diff}");
+    }
+
+    // Linear scales keep more of the fine detail out of the residual.
+    let (_, dyadic) = decompose_with(&data, 4, WaveletKernel::B3Spline, WaveletScaling::Dyadic);
+    let (_, linear) = decompose_with(
+        &data,
+        4,
+        WaveletKernel::B3Spline,
+        WaveletScaling::Linear { step: 1 },
+    );
+    assert!(std_dev(linear.view()) > std_dev(dyadic.view()));
+}
+
+#[test]
+fn test_edge_protection_suppresses_limb_rim() {
+    let disk = Array2::from_shape_fn((64, 64), |(r, c)| {
+        if (r as f32 - 32.0).hypot(c as f32 - 32.0) < 20.0 {
+            0.5
+        } else {
+            0.02
+        }
+    });
+    let frame = Frame::new(gaussian_blur_array(&disk, 1.5), 16);
+    let params = |edge_protection| WaveletParams {
+        num_layers: 4,
+        coefficients: vec![3.0, 2.0, 1.5, 1.0],
+        edge_protection,
+        ..Default::default()
+    };
+    let rim = |result: &Frame| {
+        result
+            .data
+            .indexed_iter()
+            .filter(|((r, c), _)| (*r as f32 - 32.0).hypot(*c as f32 - 32.0) < 20.0)
+            .fold(0.0f32, |m, (_, &v)| m.max(v))
+            - 0.5
+    };
+
+    let plain = wavelet::sharpen(&frame, &params(0.0));
+    let protected = wavelet::sharpen(&frame, &params(1.0));
+    assert!(
+        rim(&protected) < 0.5 * rim(&plain),
+        "rim overshoot {} vs {}",
+        rim(&protected),
+        rim(&plain)
+    );
+}
+
+#[test]
+fn test_bilateral_denoise_keeps_edges() {
+    let step = Array2::from_shape_fn((48, 48), |(_, c)| if c < 24 { 0.3 } else { 0.7 });
+    let frame = Frame::new(&step + &noise(48, 48, 5, 0.02), 16);
+    let params = |denoise_mode| WaveletParams {
+        num_layers: 3,
+        coefficients: vec![1.0; 3],
+        denoise: vec![0.03, 0.02, 0.0],
+        denoise_mode,
+        ..Default::default()
+    };
+    let bilateral = wavelet::sharpen(&frame, &params(LayerDenoise::Bilateral));
+
+    // Noise in the flat half is reduced...
+    let flat = ndarray::s![4..44, 4..18];
+    assert!(std_dev(bilateral.data.slice(flat)) < 0.6 * std_dev(frame.data.slice(flat)));
+    // ...while the step stays as steep as it was.
+    let jump = |f: &Frame| f.data[[24, 24]] - f.data[[24, 23]];
+    assert!(
+        jump(&bilateral) > 0.9 * jump(&frame),
+        "jump {}",
+        jump(&bilateral)
+    );
+
+    let soft = wavelet::sharpen(&frame, &params(LayerDenoise::SoftThreshold));
+    assert!(jump(&bilateral) > jump(&soft));
+}
+
+#[test]
+fn test_linked_luminance_adds_same_detail_to_channels() {
+    let base = Array2::from_shape_fn((32, 32), |(r, c)| {
+        0.4 + 0.2 * ((r as f32 * 0.7).sin() * (c as f32 * 0.5).cos())
+    });
+    let color = ColorFrame {
+        red: Frame::new(&base + &noise(32, 32, 1, 0.05), 16),
+        green: Frame::new(base.clone(), 16),
+        blue: Frame::new(&base * 0.8, 16),
+    };
+    let params = WaveletParams {
+        linked_luminance: true,
+        ..Default::default()
+    };
+    let linked = wavelet::sharpen_color(&color, &params);
+    let red_detail = &linked.red.data - &color.red.data;
+    let blue_detail = &linked.blue.data - &color.blue.data;
+    let diff = (&red_detail - &blue_detail)
+        .iter()
+        .fold(0.0f32, |m, v| m.max(v.abs()));
+    assert!(diff < 1e-5, "channels got different detail: {diff}");
+
+    // Separate sharpening amplifies the red channel's own noise instead.
+    let separate = wavelet::sharpen_color(&color, &WaveletParams::default());
+    assert!(
+        std_dev((&separate.red.data - &base).view()) > std_dev((&linked.red.data - &base).view())
+    );
+}
+
+#[test]
+fn test_wavelet_params_serde_defaults() {
+    let parsed: WaveletParams =
+        serde_json::from_str(r#"{"num_layers":6,"coefficients":[1.5,1.3]}"#).unwrap();
+    assert_eq!(parsed.kernel, WaveletKernel::B3Spline);
+    assert_eq!(parsed.scaling, WaveletScaling::Dyadic);
+    assert_eq!(parsed.denoise_mode, LayerDenoise::SoftThreshold);
+    assert_eq!(parsed.edge_protection, 0.0);
+    assert!(!parsed.linked_luminance);
+
+    let parsed: WaveletParams = serde_json::from_str(
+        r#"{"num_layers":4,"coefficients":[],"kernel":"Gaussian","scaling":{"Linear":{"step":2}}}"#,
+    )
+    .unwrap();
+    assert_eq!(parsed.kernel, WaveletKernel::Gaussian);
+    assert_eq!(parsed.scaling, WaveletScaling::Linear { step: 2 });
+}
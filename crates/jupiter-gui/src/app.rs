@@ -1,5 +1,6 @@
 use std::sync::mpsc;
 
+use jupiter_core::pipeline::config::PipelineConfig;
 use jupiter_core::pipeline::{PipelineOutput, PipelineStage};
 
 use crate::convert::output_to_display_image;
@@ -60,8 +61,16 @@ impl JupiterApp {
                         path: path.clone(),
                         frame_index: 0,
                     });
+                    self.send_command(WorkerCommand::RestoreSession {
+                        config: self.pipeline_config(&path),
+                    });
                     self.ui_state.file_path = Some(path);
                 }
+                WorkerResult::SessionRestored { config } => {
+                    self.config = ConfigState::from_pipeline_config(&config);
+                    self.ui_state
+                        .add_log("Restored settings from session".into());
+                }
                 WorkerResult::FramePreview { output, index } => {
                     self.update_viewport_from_output(ctx, &output, &format!("Raw Frame #{index}"));
                 }
@@ -79,6 +88,7 @@ impl JupiterApp {
                     self.ui_state.running_stage = None;
                     self.ui_state
                         .add_log(format!("{frame_count} frames scored"));
+                    self.remember_settings();
                 }
                 WorkerResult::AlignComplete {
                     frame_count,
@@ -95,6 +105,7 @@ impl JupiterApp {
                         "Aligned {frame_count} frames in {}",
                         format_duration(elapsed)
                     ));
+                    self.remember_settings();
                 }
                 WorkerResult::StackComplete { result, elapsed } => {
                     self.ui_state
@@ -105,6 +116,17 @@ impl JupiterApp {
                     self.ui_state.clear_progress();
                     self.ui_state.viewing_raw = false;
                     self.update_processed_output(ctx, &result, "Stacked");
+                    self.remember_settings();
+                }
+                WorkerResult::ApDiagnostics { diagnostics } => {
+                    if let Some(ref d) = diagnostics {
+                        self.ui_state.add_log(format!(
+                            "{} APs, {} rejected AP/frame pairs",
+                            d.aps.len(),
+                            d.rejected.len()
+                        ));
+                    }
+                    self.ui_state.ap_diagnostics = diagnostics;
                 }
                 WorkerResult::SharpenComplete { result, elapsed } => {
                     self.ui_state.stages.sharpen.set_complete("Done".into());
@@ -221,6 +243,26 @@ impl JupiterApp {
                         .mark_dirty_from(PipelineStage::QualityAssessment);
                     self.ui_state.add_log("Config imported".into());
                 }
+                WorkerResult::FrameListImported { path, selected } => {
+                    self.ui_state.add_log(format!(
+                        "Frame list imported: {selected} frames from {}",
+                        path.display()
+                    ));
+                    self.config.frame_list = Some(path);
+                    self.ui_state
+                        .stages
+                        .mark_dirty_from(PipelineStage::Alignment);
+                }
+                WorkerResult::PsfImageChosen { path } => {
+                    self.ui_state
+                        .add_log(format!("PSF image: {}", path.display()));
+                    self.config.psf_file = Some(path);
+                    self.ui_state.mark_dirty_from_sharpen();
+                    self.ui_state.request_sharpen();
+                }
+                WorkerResult::ComposeInputChosen { role, path } => {
+                    self.ui_state.compose.set_input(role, path);
+                }
                 WorkerResult::Log { message } => {
                     self.ui_state.add_log(message);
                 }
@@ -292,6 +334,27 @@ impl JupiterApp {
         let _ = self.cmd_tx.send(cmd);
     }
 
+    /// Pipeline config for `input` from the current settings and output path.
+    pub fn pipeline_config(&self, input: &std::path::Path) -> PipelineConfig {
+        let output = if self.ui_state.output_path.is_empty() {
+            input.with_extension("tiff")
+        } else {
+            std::path::PathBuf::from(&self.ui_state.output_path)
+        };
+        self.config.to_pipeline_config(input, &output)
+    }
+
+    /// Store the current settings in the open file's session.
+    fn remember_settings(&self) {
+        if self.ui_state.is_video {
+            if let Some(ref path) = self.ui_state.file_path {
+                self.send_command(WorkerCommand::RememberSettings {
+                    config: self.pipeline_config(path),
+                });
+            }
+        }
+    }
+
     /// Auto-trigger sharpening when requested (on mouse-up or discrete control change).
     fn check_auto_sharpen(&mut self) {
         if !self.ui_state.sharpen_requested {
@@ -317,18 +380,41 @@ impl JupiterApp {
             }
         }
     }
+
+    /// Re-apply the filters for a live preview once an edit is finished
+    /// (mouse released); waits while the worker is busy.
+    fn check_auto_filter(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.filter_requested
+            || self.ui_state.is_busy()
+            || ctx.input(|i| i.pointer.any_down())
+        {
+            return;
+        }
+        self.ui_state.filter_requested = false;
+
+        let has_base =
+            self.ui_state.stages.stack.is_complete() || self.ui_state.stages.sharpen.is_complete();
+        if has_base && !self.config.filters.is_empty() {
+            self.ui_state.running_stage = Some(PipelineStage::Filtering);
+            self.send_command(WorkerCommand::ApplyFilters {
+                filters: self.config.filters.clone(),
+            });
+        }
+    }
 }
 
 impl eframe::App for JupiterApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.poll_results(ctx);
         self.check_auto_sharpen();
+        self.check_auto_filter(ctx);
 
         panels::menu_bar::show(ctx, self);
         panels::status::show(ctx, self);
         panels::controls::show(ctx, self);
         panels::general_controls_bar::show(ctx, self);
         panels::viewport::show(ctx, self);
+        panels::compose::show(ctx, self);
 
         // About dialog
         if self.show_about {
@@ -5,10 +5,11 @@ use jupiter_core::compute::DevicePreference;
 use jupiter_core::frame::SourceInfo;
 use jupiter_core::io::crop::CropRect;
 use jupiter_core::pipeline::config::{
-    AlignmentConfig, DebayerConfig, FilterStep, PipelineConfig, QualityMetric, SharpeningConfig,
-    StackMethod,
+    AlignmentConfig, ChannelRole, CompositionConfig, DebayerConfig, FilterStep,
+    FrameSelectionConfig, PipelineConfig, QualityMetric, SharpeningConfig, StackingConfig,
 };
 use jupiter_core::pipeline::{PipelineOutput, PipelineStage};
+use jupiter_core::stack::ap_diagnostics::ApDiagnostics;
 
 /// Commands sent from UI thread to worker thread.
 pub enum WorkerCommand {
@@ -27,13 +28,20 @@ pub enum WorkerCommand {
 
     /// Stage 2: Select best frames and compute alignment offsets.
     Align {
-        select_percentage: f32,
+        selection: FrameSelectionConfig,
         alignment: AlignmentConfig,
         device: DevicePreference,
     },
 
-    /// Stage 3: Stack using cached aligned frames.
-    Stack { method: StackMethod },
+    /// Stage 3: Stack using cached aligned frames. Multi-point, surface
+    /// warp and optical flow redo the global alignment with `alignment` on
+    /// `device`, over the frames of `frame_list` when one is set.
+    Stack {
+        stacking: StackingConfig,
+        alignment: AlignmentConfig,
+        frame_list: Option<PathBuf>,
+        device: DevicePreference,
+    },
 
     /// Stage 4: Apply deconvolution + wavelet sharpening to cached stacked frame.
     Sharpen {
@@ -47,9 +55,21 @@ pub enum WorkerCommand {
     /// Run all stages in sequence.
     RunAll { config: PipelineConfig },
 
+    /// Restore the cached stages of a freshly opened file from its session.
+    RestoreSession { config: PipelineConfig },
+
+    /// Store the current settings in the file's session.
+    RememberSettings { config: PipelineConfig },
+
     /// Save the currently displayed frame to disk.
     SaveImage { path: PathBuf },
 
+    /// Write per-frame scores, offsets and selection flags as CSV/JSON.
+    ExportFrameReport {
+        path: PathBuf,
+        metric: QualityMetric,
+    },
+
     /// Crop a SER file and save to a new file.
     CropAndSave {
         source_path: PathBuf,
@@ -68,6 +88,12 @@ pub enum WorkerCommand {
 
     /// Auto-detect planet and crop a SER file, then save and reopen.
     AutoCropAndSave { source_path: PathBuf },
+
+    /// Combine stacked mono filter images into a color image and open it.
+    Compose {
+        inputs: Vec<(ChannelRole, PathBuf)>,
+        config: CompositionConfig,
+    },
 }
 
 /// Results sent from worker thread back to UI thread.
@@ -89,6 +115,12 @@ pub enum WorkerResult {
         detected_planet_diameter: Option<usize>,
     },
 
+    /// Settings stored in a reopened file's session, applied before its
+    /// cached stages are reported.
+    SessionRestored {
+        config: PipelineConfig,
+    },
+
     /// Stage 2 complete: frames selected and alignment offsets computed.
     AlignComplete {
         frame_count: usize,
@@ -101,6 +133,11 @@ pub enum WorkerResult {
         elapsed: Duration,
     },
 
+    /// AP diagnostics of the latest stack (None for methods without APs).
+    ApDiagnostics {
+        diagnostics: Option<ApDiagnostics>,
+    },
+
     /// Stage 3 complete: sharpened result ready for preview.
     SharpenComplete {
         result: PipelineOutput,
@@ -147,6 +184,20 @@ pub enum WorkerResult {
     ConfigImported {
         config: jupiter_core::pipeline::config::PipelineConfig,
     },
+    /// An edited frame report was validated and should drive frame selection.
+    FrameListImported {
+        path: PathBuf,
+        selected: usize,
+    },
+    /// An image of a point source was chosen as the deconvolution PSF.
+    PsfImageChosen {
+        path: PathBuf,
+    },
+    /// A stacked image was chosen for one filter of a composition.
+    ComposeInputChosen {
+        role: ChannelRole,
+        path: PathBuf,
+    },
     Log {
         message: String,
     },
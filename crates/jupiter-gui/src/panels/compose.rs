@@ -0,0 +1,114 @@
+use jupiter_core::pipeline::config::{ChannelRole, RegistrationMode};
+
+use crate::app::JupiterApp;
+use crate::messages::{WorkerCommand, WorkerResult};
+use crate::states::ComposeModeChoice;
+
+/// Window for combining stacked mono filter images into a color image.
+pub fn show(ctx: &egui::Context, app: &mut JupiterApp) {
+    let mut open = app.ui_state.compose.open;
+    egui::Window::new("Compose Channels")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            inputs(ui, app);
+            ui.separator();
+            options(ui, app);
+            ui.separator();
+            compose_button(ui, app);
+        });
+    app.ui_state.compose.open &= open;
+}
+
+/// One file picker per filter; inputs the current mode does not use are
+/// greyed out.
+fn inputs(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    let required = app.ui_state.compose.to_config().required_roles();
+    egui::Grid::new("compose_inputs")
+        .num_columns(3)
+        .show(ui, |ui| {
+            for &role in ChannelRole::ALL {
+                let used = required.contains(&role);
+                ui.add_enabled(used, egui::Label::new(role.to_string()));
+                if ui
+                    .add_enabled(used, egui::Button::new("Choose..."))
+                    .clicked()
+                {
+                    let result_tx = app.result_tx.clone();
+                    std::thread::spawn(move || {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("Image files", &["tiff", "tif", "png", "jpg", "jpeg"])
+                            .pick_file()
+                        {
+                            let _ = result_tx.send(WorkerResult::ComposeInputChosen { role, path });
+                        }
+                    });
+                }
+                match app.ui_state.compose.input(role) {
+                    Some(path) => {
+                        let name = path
+                            .file_name()
+                            .map(|n| n.to_string_lossy().into_owned())
+                            .unwrap_or_default();
+                        ui.add_enabled(used, egui::Label::new(name))
+                            .on_hover_text(path.display().to_string());
+                    }
+                    None => {
+                        ui.weak("None");
+                    }
+                }
+                ui.end_row();
+            }
+        });
+}
+
+fn options(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    let state = &mut app.ui_state.compose;
+    crate::panels::enum_combo(ui, "Mode", &mut state.mode, ComposeModeChoice::ALL);
+    if state.mode == ComposeModeChoice::FalseColor {
+        crate::panels::enum_combo(
+            ui,
+            "CH4 replaces",
+            &mut state.methane_channel,
+            &[ChannelRole::Red, ChannelRole::Green, ChannelRole::Blue],
+        );
+    }
+    if matches!(
+        state.mode,
+        ComposeModeChoice::Lrgb | ComposeModeChoice::IrRgb
+    ) {
+        ui.add(egui::Slider::new(&mut state.luminance_weight, 0.0..=1.0).text("Luminance Weight"));
+    }
+    crate::panels::enum_combo(
+        ui,
+        "Registration",
+        &mut state.registration,
+        &[
+            RegistrationMode::None,
+            RegistrationMode::Translation,
+            RegistrationMode::Similarity,
+        ],
+    );
+    ui.checkbox(&mut state.normalize, "Normalize levels");
+}
+
+fn compose_button(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    let selected = app.ui_state.compose.selected_inputs();
+    let busy = app.ui_state.is_busy();
+    ui.horizontal(|ui| {
+        let button = ui.add_enabled(selected.is_ok() && !busy, egui::Button::new("Compose"));
+        if let Err(missing) = &selected {
+            ui.weak(format!("Needs the {missing} image"));
+        }
+        if button.clicked() {
+            if let Ok(inputs) = selected {
+                let config = app.ui_state.compose.to_config();
+                app.ui_state.add_log(format!(
+                    "Composing {} (registration: {})",
+                    app.ui_state.compose.mode, config.registration
+                ));
+                app.send_command(WorkerCommand::Compose { inputs, config });
+            }
+        }
+    });
+}
@@ -1,6 +1,7 @@
 use crate::app::JupiterApp;
 use crate::messages::WorkerCommand;
 use crate::states::AlignMethodChoice;
+use jupiter_core::pipeline::config::LimbModel;
 use jupiter_core::pipeline::PipelineStage;
 
 pub(super) fn alignment_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
@@ -14,20 +15,38 @@ pub(super) fn alignment_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
 
     let enabled = app.ui_state.stages.score.is_complete();
     ui.add_enabled_ui(enabled, |ui| {
-        // Keep percentage (frame selection) — display as percent, store as fraction
-        let mut keep_pct = app.config.select_percentage * 100.0;
-        if ui
-            .add(
-                egui::Slider::new(&mut keep_pct, 1.0..=100.0)
-                    .text("Keep %")
-                    .fixed_decimals(0),
-            )
-            .changed()
-        {
-            app.config.select_percentage = keep_pct / 100.0;
-            app.ui_state
-                .stages
-                .mark_dirty_from(PipelineStage::Alignment);
+        // Frame selection: an imported frame list replaces the keep percentage
+        if let Some(list) = app.config.frame_list.clone() {
+            ui.horizontal(|ui| {
+                let name = list
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                ui.label(format!("Frame list: {name}"))
+                    .on_hover_text(list.display().to_string());
+                if ui.small_button("Clear").clicked() {
+                    app.config.frame_list = None;
+                    app.ui_state
+                        .stages
+                        .mark_dirty_from(PipelineStage::Alignment);
+                }
+            });
+        } else {
+            // Keep percentage — display as percent, store as fraction
+            let mut keep_pct = app.config.select_percentage * 100.0;
+            if ui
+                .add(
+                    egui::Slider::new(&mut keep_pct, 1.0..=100.0)
+                        .text("Keep %")
+                        .fixed_decimals(0),
+                )
+                .changed()
+            {
+                app.config.select_percentage = keep_pct / 100.0;
+                app.ui_state
+                    .stages
+                    .mark_dirty_from(PipelineStage::Alignment);
+            }
         }
 
         // Method combo
@@ -57,13 +76,13 @@ pub(super) fn alignment_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                 }
             }
             AlignMethodChoice::Centroid => {
-                if ui
+                let changed = ui
                     .add(
                         egui::Slider::new(&mut app.config.centroid_threshold, 0.0..=0.5)
                             .text("Threshold"),
                     )
-                    .changed()
-                {
+                    .changed();
+                if changed {
                     app.ui_state
                         .stages
                         .mark_dirty_from(PipelineStage::Alignment);
@@ -81,9 +100,97 @@ pub(super) fn alignment_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                         .mark_dirty_from(PipelineStage::Alignment);
                 }
             }
+            AlignMethodChoice::LimbFit => {
+                if crate::panels::enum_combo(
+                    ui,
+                    "Limb model",
+                    &mut app.config.limb_model,
+                    &[LimbModel::Circle, LimbModel::Ellipse],
+                ) {
+                    app.ui_state
+                        .stages
+                        .mark_dirty_from(PipelineStage::Alignment);
+                }
+                if ui
+                    .add(
+                        egui::Slider::new(&mut app.config.limb_edge_threshold, 0.05..=0.9)
+                            .text("Edge threshold"),
+                    )
+                    .on_hover_text("Minimum limb edge strength, relative to the strongest edge")
+                    .changed()
+                {
+                    app.ui_state
+                        .stages
+                        .mark_dirty_from(PipelineStage::Alignment);
+                }
+            }
             _ => {}
         }
 
+        // Stacked-reference refinement
+        if ui
+            .checkbox(&mut app.config.refine_enabled, "Refine reference")
+            .on_hover_text("Re-align against a stack of the best aligned frames")
+            .changed()
+        {
+            app.ui_state
+                .stages
+                .mark_dirty_from(PipelineStage::Alignment);
+        }
+        if app.config.refine_enabled {
+            let mut top_pct = app.config.refine_top_fraction * 100.0;
+            let mut passes = app.config.refine_max_passes as i32;
+            let changed = ui
+                .add(
+                    egui::Slider::new(&mut top_pct, 5.0..=100.0)
+                        .text("Reference %")
+                        .fixed_decimals(0),
+                )
+                .changed()
+                | ui.add(egui::Slider::new(&mut passes, 1..=5).text("Passes"))
+                    .changed();
+            if changed {
+                app.config.refine_top_fraction = top_pct / 100.0;
+                app.config.refine_max_passes = passes as usize;
+                app.ui_state
+                    .stages
+                    .mark_dirty_from(PipelineStage::Alignment);
+            }
+        }
+
+        // Outlier rejection
+        if ui
+            .checkbox(&mut app.config.reject_enabled, "Reject outliers")
+            .on_hover_text(
+                "Drop frames with a weak correlation peak or an offset far from the drift track",
+            )
+            .changed()
+        {
+            app.ui_state
+                .stages
+                .mark_dirty_from(PipelineStage::Alignment);
+        }
+        if app.config.reject_enabled {
+            let changed = ui
+                .add(
+                    egui::Slider::new(&mut app.config.reject_min_confidence, 1.0..=20.0)
+                        .text("Min confidence")
+                        .fixed_decimals(1),
+                )
+                .changed()
+                | ui.add(
+                    egui::Slider::new(&mut app.config.reject_max_drift, 0.5..=20.0)
+                        .text("Max drift (px)")
+                        .fixed_decimals(1),
+                )
+                .changed();
+            if changed {
+                app.ui_state
+                    .stages
+                    .mark_dirty_from(PipelineStage::Alignment);
+            }
+        }
+
         // Align button
         let can_align = app.ui_state.stages.score.is_complete() && !app.ui_state.is_busy();
         if ui
@@ -95,7 +202,7 @@ pub(super) fn alignment_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                 .clear_downstream(PipelineStage::Alignment);
             app.ui_state.running_stage = Some(PipelineStage::Alignment);
             app.send_command(WorkerCommand::Align {
-                select_percentage: app.config.select_percentage,
+                selection: app.config.frame_selection_config(),
                 alignment: app.config.alignment_config(),
                 device: app.config.device_preference(),
             });
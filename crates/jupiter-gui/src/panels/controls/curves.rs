@@ -0,0 +1,92 @@
+use jupiter_core::filters::curves::ToneCurve;
+
+const EDITOR_SIZE: f32 = 160.0;
+const HANDLE_RADIUS: f32 = 4.0;
+const GRAB_DISTANCE: f32 = 8.0;
+const CURVE_SAMPLES: usize = 64;
+
+/// Tone curve editor: drag a point to move it, click to add one,
+/// right-click a point to remove it. Returns true when the points changed.
+pub(super) fn curve_editor(ui: &mut egui::Ui, id: egui::Id, points: &mut Vec<[f32; 2]>) -> bool {
+    let (response, painter) = ui.allocate_painter(
+        egui::Vec2::splat(EDITOR_SIZE),
+        egui::Sense::click_and_drag(),
+    );
+    let rect = response.rect;
+    let to_screen = |[x, y]: [f32; 2]| {
+        egui::pos2(
+            rect.left() + x * rect.width(),
+            rect.bottom() - y * rect.height(),
+        )
+    };
+    let from_screen = |pos: egui::Pos2| {
+        [
+            ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0),
+            ((rect.bottom() - pos.y) / rect.height()).clamp(0.0, 1.0),
+        ]
+    };
+    let nearest = |points: &[[f32; 2]], pos: egui::Pos2| {
+        points
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| (i, to_screen(p).distance(pos)))
+            .filter(|&(_, d)| d <= GRAB_DISTANCE)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    };
+
+    let mut changed = false;
+    if response.drag_started() {
+        let grabbed = response
+            .interact_pointer_pos()
+            .and_then(|pos| nearest(points, pos));
+        ui.data_mut(|d| d.insert_temp(id, grabbed));
+    }
+    let dragging = ui.data(|d| d.get_temp::<Option<usize>>(id)).flatten();
+    if let (Some(i), Some(pos)) = (dragging, response.interact_pointer_pos()) {
+        if response.dragged() && i < points.len() {
+            points[i] = from_screen(pos);
+            changed = true;
+        }
+    }
+    if response.drag_stopped() {
+        ui.data_mut(|d| d.remove::<Option<usize>>(id));
+    }
+    if let Some(pos) = response.interact_pointer_pos() {
+        if response.clicked() && nearest(points, pos).is_none() {
+            points.push(from_screen(pos));
+            changed = true;
+        }
+        if response.secondary_clicked() && points.len() > 2 {
+            if let Some(i) = nearest(points, pos) {
+                points.remove(i);
+                changed = true;
+            }
+        }
+    }
+
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    let grid = egui::Stroke::new(1.0, visuals.widgets.noninteractive.bg_stroke.color);
+    for i in 1..4 {
+        let t = i as f32 / 4.0;
+        painter.line_segment([to_screen([t, 0.0]), to_screen([t, 1.0])], grid);
+        painter.line_segment([to_screen([0.0, t]), to_screen([1.0, t])], grid);
+    }
+    painter.line_segment([to_screen([0.0, 0.0]), to_screen([1.0, 1.0])], grid);
+
+    let curve = ToneCurve::new(points);
+    let line: Vec<egui::Pos2> = (0..=CURVE_SAMPLES)
+        .map(|i| {
+            let x = i as f32 / CURVE_SAMPLES as f32;
+            to_screen([x, curve.eval(x).clamp(0.0, 1.0)])
+        })
+        .collect();
+    let stroke = egui::Stroke::new(1.5, visuals.selection.bg_fill);
+    painter.add(egui::Shape::line(line, stroke));
+    for &p in points.iter() {
+        painter.circle_filled(to_screen(p), HANDLE_RADIUS, visuals.strong_text_color());
+    }
+
+    changed
+}
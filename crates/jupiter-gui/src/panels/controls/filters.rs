@@ -1,7 +1,9 @@
 use crate::app::JupiterApp;
 use crate::messages::WorkerCommand;
 use crate::states::FilterType;
-use jupiter_core::pipeline::config::FilterStep;
+use jupiter_core::pipeline::config::{ColorChannel, FilterStep, ResampleMethod, WhiteBalance};
+
+use super::curves::curve_editor;
 use jupiter_core::pipeline::PipelineStage;
 
 pub(super) fn filter_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
@@ -126,20 +128,231 @@ pub(super) fn filter_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                             any_changed = true;
                         }
                     }
+                    FilterStep::NonLocalMeans {
+                        strength,
+                        patch_radius,
+                        search_radius,
+                        chroma_only,
+                    } => {
+                        ui.label("NLM");
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(strength)
+                                    .speed(0.05)
+                                    .range(0.0..=5.0)
+                                    .prefix("h: "),
+                            )
+                            .changed();
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(patch_radius)
+                                    .range(1..=5)
+                                    .prefix("P: "),
+                            )
+                            .changed();
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(search_radius)
+                                    .range(1..=15)
+                                    .prefix("S: "),
+                            )
+                            .changed();
+                        any_changed |= ui.checkbox(chroma_only, "chroma").changed();
+                    }
+                    FilterStep::Bilateral {
+                        spatial_sigma,
+                        range_sigma,
+                        chroma_only,
+                    } => {
+                        ui.label("Bilateral");
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(spatial_sigma)
+                                    .speed(0.1)
+                                    .range(0.5..=10.0)
+                                    .prefix("S: "),
+                            )
+                            .changed();
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(range_sigma)
+                                    .speed(0.005)
+                                    .range(0.001..=1.0)
+                                    .prefix("R: "),
+                            )
+                            .changed();
+                        any_changed |= ui.checkbox(chroma_only, "chroma").changed();
+                    }
+                    FilterStep::GuidedFilter {
+                        radius,
+                        epsilon,
+                        chroma_only,
+                    } => {
+                        ui.label("Guided");
+                        any_changed |= ui
+                            .add(egui::DragValue::new(radius).range(1..=20).prefix("R: "))
+                            .changed();
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(epsilon)
+                                    .speed(0.0001)
+                                    .range(0.00001..=0.1)
+                                    .prefix("\u{03b5}: "),
+                            )
+                            .changed();
+                        any_changed |= ui.checkbox(chroma_only, "chroma").changed();
+                    }
+                    FilterStep::WaveletDenoise {
+                        layers,
+                        strength,
+                        chroma_only,
+                    } => {
+                        ui.label("Wavelet NR");
+                        any_changed |= ui
+                            .add(egui::DragValue::new(layers).range(1..=8).prefix("L: "))
+                            .changed();
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(strength)
+                                    .speed(0.05)
+                                    .range(0.0..=5.0)
+                                    .prefix("A: "),
+                            )
+                            .changed();
+                        any_changed |= ui.checkbox(chroma_only, "chroma").changed();
+                    }
+                    FilterStep::Curves { channel, .. } => {
+                        ui.label("Curves");
+                        any_changed |= channel_combo(ui, i, channel);
+                    }
+                    FilterStep::Levels {
+                        channel,
+                        black_point,
+                        white_point,
+                        gamma,
+                    } => {
+                        ui.label("Levels");
+                        any_changed |= channel_combo(ui, i, channel);
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(black_point)
+                                    .speed(0.005)
+                                    .range(0.0..=1.0)
+                                    .prefix("B: "),
+                            )
+                            .changed();
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(white_point)
+                                    .speed(0.005)
+                                    .range(0.0..=1.0)
+                                    .prefix("W: "),
+                            )
+                            .changed();
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(gamma)
+                                    .speed(0.02)
+                                    .range(0.1..=5.0)
+                                    .prefix("\u{03b3}: "),
+                            )
+                            .changed();
+                    }
+                    FilterStep::Saturation {
+                        saturation,
+                        vibrance,
+                    } => {
+                        ui.label("Saturation");
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(saturation)
+                                    .speed(0.02)
+                                    .range(0.0..=3.0)
+                                    .prefix("S: "),
+                            )
+                            .changed();
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(vibrance)
+                                    .speed(0.02)
+                                    .range(-1.0..=2.0)
+                                    .prefix("V: "),
+                            )
+                            .changed();
+                    }
+                    FilterStep::WhiteBalance(balance) => {
+                        ui.label("WB");
+                        any_changed |= white_balance_controls(ui, i, balance);
+                    }
+                    FilterStep::Rotate { degrees, expand } => {
+                        ui.label("Rotate");
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(degrees)
+                                    .speed(0.5)
+                                    .range(-180.0..=180.0)
+                                    .suffix("\u{00b0}"),
+                            )
+                            .changed();
+                        any_changed |= ui.checkbox(expand, "expand").changed();
+                    }
+                    FilterStep::Flip {
+                        horizontal,
+                        vertical,
+                    } => {
+                        ui.label("Flip");
+                        any_changed |= ui.checkbox(horizontal, "H").changed();
+                        any_changed |= ui.checkbox(vertical, "V").changed();
+                    }
+                    FilterStep::Resample { scale, method } => {
+                        ui.label("Resample");
+                        any_changed |= ui
+                            .add(
+                                egui::DragValue::new(scale)
+                                    .speed(0.01)
+                                    .range(0.1..=4.0)
+                                    .suffix("x"),
+                            )
+                            .changed();
+                        egui::ComboBox::from_id_salt(("filter_resample", i))
+                            .selected_text(method.to_string())
+                            .width(80.0)
+                            .show_ui(ui, |ui| {
+                                for choice in [ResampleMethod::Lanczos3, ResampleMethod::Bicubic] {
+                                    any_changed |= ui
+                                        .selectable_value(method, choice, choice.to_string())
+                                        .changed();
+                                }
+                            });
+                    }
+                    FilterStep::Canvas { width, height } => {
+                        ui.label("Canvas");
+                        any_changed |= ui
+                            .add(egui::DragValue::new(width).range(16..=8192).prefix("W: "))
+                            .changed();
+                        any_changed |= ui
+                            .add(egui::DragValue::new(height).range(16..=8192).prefix("H: "))
+                            .changed();
+                    }
                 }
                 if ui.small_button("x").clicked() {
                     to_remove = Some(i);
                 }
             });
+            if let FilterStep::Curves { points, .. } = filter {
+                any_changed |= curve_editor(ui, ui.id().with(("filter_curve", i)), points);
+            }
         }
 
         if any_changed {
             app.ui_state.mark_dirty_from_filter();
+            app.ui_state.request_filter();
         }
 
         if let Some(i) = to_remove {
             app.config.filters.remove(i);
             app.ui_state.mark_dirty_from_filter();
+            app.ui_state.request_filter();
         }
 
         // Add filter menu
@@ -148,6 +361,7 @@ pub(super) fn filter_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                 if ui.button(filter_type.to_string()).clicked() {
                     app.config.filters.push(filter_type.default_step());
                     app.ui_state.mark_dirty_from_filter();
+                    app.ui_state.request_filter();
                     ui.close();
                 }
             }
@@ -168,3 +382,61 @@ pub(super) fn filter_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
         }
     });
 }
+
+/// Channel selector of the curves or levels step at `index`.
+fn channel_combo(ui: &mut egui::Ui, index: usize, channel: &mut ColorChannel) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_salt(("filter_channel", index))
+        .selected_text(channel.to_string())
+        .width(80.0)
+        .show_ui(ui, |ui| {
+            for &choice in ColorChannel::ALL {
+                changed |= ui
+                    .selectable_value(channel, choice, choice.to_string())
+                    .changed();
+            }
+        });
+    changed
+}
+
+/// Mode selector and manual gains of the white balance step at `index`.
+fn white_balance_controls(ui: &mut egui::Ui, index: usize, balance: &mut WhiteBalance) -> bool {
+    let mut changed = false;
+    let manual = WhiteBalance::Manual {
+        red: 1.0,
+        green: 1.0,
+        blue: 1.0,
+    };
+    egui::ComboBox::from_id_salt(("filter_white_balance", index))
+        .selected_text(match balance {
+            WhiteBalance::Manual { .. } => "Manual",
+            WhiteBalance::GreyWorld => "Grey World",
+            WhiteBalance::PlanetDisk => "Planet Disk",
+        })
+        .width(90.0)
+        .show_ui(ui, |ui| {
+            let is_manual = matches!(balance, WhiteBalance::Manual { .. });
+            if ui.selectable_label(is_manual, "Manual").clicked() && !is_manual {
+                *balance = manual;
+                changed = true;
+            }
+            for choice in [WhiteBalance::GreyWorld, WhiteBalance::PlanetDisk] {
+                changed |= ui
+                    .selectable_value(balance, choice, choice.to_string())
+                    .changed();
+            }
+        });
+    if let WhiteBalance::Manual { red, green, blue } = balance {
+        for (gain, prefix) in [(red, "R: "), (green, "G: "), (blue, "B: ")] {
+            changed |= ui
+                .add(
+                    egui::DragValue::new(gain)
+                        .speed(0.01)
+                        .range(0.0..=4.0)
+                        .prefix(prefix),
+                )
+                .changed();
+        }
+    }
+    changed
+}
@@ -1,4 +1,5 @@
 mod alignment;
+mod curves;
 mod filters;
 mod layout;
 mod score;
@@ -1,6 +1,9 @@
 use crate::app::JupiterApp;
+use crate::messages::WorkerResult;
 use crate::states::{DeconvMethodChoice, PsfModelChoice};
+use jupiter_core::pipeline::config::{PsfRegion, TilePsf};
 use jupiter_core::pipeline::PipelineStage;
+use jupiter_core::sharpen::wavelet::{LayerDenoise, WaveletKernel, WaveletScaling};
 
 pub(super) fn sharpen_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
     let status = if matches!(app.ui_state.running_stage, Some(PipelineStage::Sharpening)) {
@@ -55,6 +58,7 @@ pub(super) fn sharpen_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                     app.ui_state.request_sharpen();
                 }
             }
+            wavelet_options_controls(ui, app);
 
             ui.add_space(4.0);
 
@@ -89,6 +93,7 @@ pub(super) fn sharpen_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                         if resp.drag_stopped() || resp.lost_focus() {
                             app.ui_state.request_sharpen();
                         }
+                        rl_options_controls(ui, app);
                     }
                     DeconvMethodChoice::Wiener => {
                         let resp = ui.add(
@@ -103,6 +108,31 @@ pub(super) fn sharpen_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                             app.ui_state.request_sharpen();
                         }
                     }
+                    DeconvMethodChoice::BlindRichardsonLucy => {
+                        let mut iter = app.config.rl_iterations as i32;
+                        let resp = ui.add(egui::Slider::new(&mut iter, 1..=50).text("Rounds"));
+                        if resp.changed() {
+                            app.config.rl_iterations = iter as usize;
+                            app.ui_state.mark_dirty_from_sharpen();
+                        }
+                        if resp.drag_stopped() || resp.lost_focus() {
+                            app.ui_state.request_sharpen();
+                        }
+                        let mut size = app.config.blind_psf_size as i32;
+                        let resp = ui.add(
+                            egui::Slider::new(&mut size, 3..=41)
+                                .step_by(2.0)
+                                .text("PSF Size"),
+                        );
+                        if resp.changed() {
+                            app.config.blind_psf_size = size as usize;
+                            app.ui_state.mark_dirty_from_sharpen();
+                        }
+                        if resp.drag_stopped() || resp.lost_focus() {
+                            app.ui_state.request_sharpen();
+                        }
+                        ui.label("PSF below is the starting guess");
+                    }
                 }
 
                 // PSF model
@@ -153,8 +183,209 @@ pub(super) fn sharpen_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                             app.ui_state.request_sharpen();
                         }
                     }
+                    PsfModelChoice::Measured => measured_psf_controls(ui, app),
+                    PsfModelChoice::Limb => {
+                        ui.label("Width estimated from the planet's limb");
+                    }
                 }
+
+                tiling_controls(ui, app);
             }
         }
     });
 }
+
+/// Kernel, scales, layer denoise, edge protection and colour linking of the
+/// wavelet decomposition.
+fn wavelet_options_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    let mut changed = crate::panels::enum_combo(
+        ui,
+        "Kernel",
+        &mut app.config.wavelet_kernel,
+        &[WaveletKernel::B3Spline, WaveletKernel::Gaussian],
+    );
+
+    let mut linear = matches!(app.config.wavelet_scaling, WaveletScaling::Linear { .. });
+    if ui.checkbox(&mut linear, "Linear scales").changed() {
+        app.config.wavelet_scaling = if linear {
+            WaveletScaling::Linear { step: 1 }
+        } else {
+            WaveletScaling::Dyadic
+        };
+        changed = true;
+    }
+    let mut step_resp = None;
+    if let WaveletScaling::Linear { step } = &mut app.config.wavelet_scaling {
+        let mut value = *step as i32;
+        let resp = ui.add(egui::Slider::new(&mut value, 1..=8).text("Scale Step"));
+        *step = value as usize;
+        step_resp = Some(resp);
+    }
+
+    let edge = ui.add(
+        egui::Slider::new(&mut app.config.wavelet_edge_protection, 0.0..=1.0)
+            .text("Edge Protection"),
+    );
+    if !app.config.wavelet_denoise.is_empty() {
+        changed |= crate::panels::enum_combo(
+            ui,
+            "Denoise",
+            &mut app.config.wavelet_denoise_mode,
+            &[LayerDenoise::SoftThreshold, LayerDenoise::Bilateral],
+        );
+    }
+    changed |= ui
+        .checkbox(
+            &mut app.config.wavelet_linked_luminance,
+            "Sharpen luminance only (colour)",
+        )
+        .changed();
+
+    let sliders = [Some(&edge), step_resp.as_ref()];
+    if changed || sliders.iter().flatten().any(|r| r.changed()) {
+        app.ui_state.mark_dirty_from_sharpen();
+    }
+    if changed
+        || sliders
+            .iter()
+            .flatten()
+            .any(|r| r.drag_stopped() || r.lost_focus())
+    {
+        app.ui_state.request_sharpen();
+    }
+}
+
+/// Regularization, damping, stopping and boundary options of Richardson-Lucy.
+fn rl_options_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    let options = &mut app.config.rl_options;
+    let tv = ui.add(
+        egui::Slider::new(&mut options.tv_weight, 0.0..=0.02)
+            .text("TV Weight")
+            .custom_formatter(|v, _| format!("{v:.4}")),
+    );
+    let damping = ui.add(egui::Slider::new(&mut options.damping, 0.0..=5.0).text("Damping (σ)"));
+    let mut toggled = false;
+    toggled |= ui
+        .checkbox(&mut options.auto_stop, "Stop at noise level")
+        .changed();
+    toggled |= ui.checkbox(&mut options.pad_edges, "Pad edges").changed();
+    toggled |= ui
+        .checkbox(&mut options.planet_mask, "Planet disk only")
+        .changed();
+
+    if tv.changed() || damping.changed() || toggled {
+        app.ui_state.mark_dirty_from_sharpen();
+    }
+    if tv.drag_stopped()
+        || tv.lost_focus()
+        || damping.drag_stopped()
+        || damping.lost_focus()
+        || toggled
+    {
+        app.ui_state.request_sharpen();
+    }
+}
+
+/// Tiled, spatially varying deconvolution.
+fn tiling_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    if ui
+        .checkbox(&mut app.config.deconv_tiled, "Tiled (varying seeing)")
+        .changed()
+    {
+        app.ui_state.mark_dirty_from_sharpen();
+        app.ui_state.request_sharpen();
+    }
+    if !app.config.deconv_tiled {
+        return;
+    }
+    let tiling = &mut app.config.deconv_tiling;
+    let mut size = tiling.tile_size as i32;
+    let resp = ui.add(
+        egui::Slider::new(&mut size, 32..=512)
+            .step_by(16.0)
+            .text("Tile Size"),
+    );
+    if resp.changed() {
+        tiling.tile_size = size as usize;
+        app.ui_state.mark_dirty_from_sharpen();
+    }
+    if resp.drag_stopped() || resp.lost_focus() {
+        app.ui_state.request_sharpen();
+    }
+    if crate::panels::enum_combo(
+        ui,
+        "Tile PSF",
+        &mut app.config.deconv_tiling.psf,
+        &[TilePsf::Uniform, TilePsf::LocalQuality],
+    ) {
+        app.ui_state.mark_dirty_from_sharpen();
+        app.ui_state.request_sharpen();
+    }
+}
+
+/// PSF image picker and optional point-source region.
+fn measured_psf_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    ui.horizontal(|ui| {
+        if ui.button("PSF image...").clicked() {
+            let result_tx = app.result_tx.clone();
+            std::thread::spawn(move || {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Image files", &["tiff", "tif", "png", "jpg", "jpeg"])
+                    .pick_file()
+                {
+                    let _ = result_tx.send(WorkerResult::PsfImageChosen { path });
+                }
+            });
+        }
+        match app.config.psf_file {
+            Some(ref path) => {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                ui.label(name).on_hover_text(path.display().to_string());
+            }
+            None => {
+                ui.label("none");
+            }
+        }
+    });
+
+    let mut use_region = app.config.psf_region.is_some();
+    if ui
+        .checkbox(&mut use_region, "Region")
+        .on_hover_text("Pixel rectangle holding the star or moon; whole image if off")
+        .changed()
+    {
+        app.config.psf_region = use_region.then_some(PsfRegion {
+            x: 0,
+            y: 0,
+            width: 64,
+            height: 64,
+        });
+        app.ui_state.mark_dirty_from_sharpen();
+        app.ui_state.request_sharpen();
+    }
+    if let Some(ref mut region) = app.config.psf_region {
+        let (mut changed, mut committed) = (false, false);
+        ui.horizontal(|ui| {
+            for (value, label) in [
+                (&mut region.x, "x"),
+                (&mut region.y, "y"),
+                (&mut region.width, "w"),
+                (&mut region.height, "h"),
+            ] {
+                ui.label(label);
+                let resp = ui.add(egui::DragValue::new(value));
+                changed |= resp.changed();
+                committed |= resp.drag_stopped() || resp.lost_focus();
+            }
+        });
+        if changed {
+            app.ui_state.mark_dirty_from_sharpen();
+        }
+        if committed {
+            app.ui_state.request_sharpen();
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::app::JupiterApp;
 use crate::messages::WorkerCommand;
-use crate::states::StackMethodChoice;
+use crate::states::{ApPlacementChoice, LocalMethodChoice, StackMethodChoice};
 use jupiter_core::pipeline::PipelineStage;
 use jupiter_core::stack::multi_point::{auto_ap_size, auto_ap_size_from_frame};
 
@@ -15,7 +15,8 @@ pub(super) fn stack_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
 
     let is_multi_point = app.config.stack_method_choice == StackMethodChoice::MultiPoint;
     let is_surface_warp = app.config.stack_method_choice == StackMethodChoice::SurfaceWarp;
-    let enabled = if is_multi_point || is_surface_warp {
+    let is_optical_flow = app.config.stack_method_choice == StackMethodChoice::OpticalFlow;
+    let enabled = if is_multi_point || is_surface_warp || is_optical_flow {
         app.ui_state.stages.score.is_complete()
     } else {
         app.ui_state.stages.align.is_complete()
@@ -33,27 +34,47 @@ pub(super) fn stack_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
 
         // Method-specific params
         match app.config.stack_method_choice {
-            StackMethodChoice::SigmaClip => {
+            StackMethodChoice::Mean | StackMethodChoice::Median => {
+                quality_weighted_control(ui, app);
+            }
+            StackMethodChoice::SigmaClip
+            | StackMethodChoice::WinsorizedSigmaClip
+            | StackMethodChoice::LinearFitClip => {
+                sigma_clip_controls(ui, app);
+                quality_weighted_control(ui, app);
+            }
+            StackMethodChoice::Gesd => {
+                gesd_controls(ui, app);
+                quality_weighted_control(ui, app);
+            }
+            StackMethodChoice::MultiPoint => {
+                ap_controls(ui, app);
+                ap_placement_controls(ui, app);
+                local_method_controls(ui, app);
+            }
+            StackMethodChoice::SurfaceWarp => {
+                ap_controls(ui, app);
                 if ui
-                    .add(
-                        egui::Slider::new(&mut app.config.sigma_clip_sigma, 0.5..=5.0)
-                            .text("Sigma"),
-                    )
+                    .checkbox(&mut app.config.sw_spline_field, "Spline field")
+                    .on_hover_text("Thin-plate spline through the AP shifts instead of bilinear")
                     .changed()
                 {
                     app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
                 }
-                let mut iter = app.config.sigma_clip_iterations as i32;
-                if ui
-                    .add(egui::Slider::new(&mut iter, 1..=10).text("Iterations"))
-                    .changed()
+                if app.config.sw_spline_field
+                    && ui
+                        .add(
+                            egui::Slider::new(&mut app.config.sw_spline_smoothing, 0.0..=1.0)
+                                .logarithmic(true)
+                                .text("Smoothing"),
+                        )
+                        .changed()
                 {
-                    app.config.sigma_clip_iterations = iter as usize;
                     app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
                 }
             }
-            StackMethodChoice::MultiPoint | StackMethodChoice::SurfaceWarp => {
-                ap_controls(ui, app);
+            StackMethodChoice::OpticalFlow => {
+                flow_controls(ui, app);
             }
             StackMethodChoice::Drizzle => {
                 if ui
@@ -78,7 +99,9 @@ pub(super) fn stack_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                     app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
                 }
             }
-            _ => {}
+            StackMethodChoice::SuperResolution => {
+                super_resolution_controls(ui, app);
+            }
         }
 
         // Stack button
@@ -92,10 +115,127 @@ pub(super) fn stack_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
                 .clear_downstream(PipelineStage::Stacking);
             app.ui_state.running_stage = Some(PipelineStage::Stacking);
             app.send_command(WorkerCommand::Stack {
-                method: app.config.stack_method(),
+                stacking: app.config.stacking_config(),
+                alignment: app.config.alignment_config(),
+                frame_list: app.config.frame_list.clone(),
+                device: app.config.device_preference(),
             });
         }
     });
+
+    if app.ui_state.ap_diagnostics.is_some() {
+        ui.checkbox(&mut app.ui_state.show_ap_overlay, "Show AP grid");
+    }
+}
+
+/// Weight frames by quality score (standard and rejection methods).
+fn quality_weighted_control(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    if ui
+        .checkbox(&mut app.config.stack_quality_weighted, "Quality weighted")
+        .changed()
+    {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+}
+
+/// Threshold and passes shared by sigma, winsorized and linear-fit clipping.
+fn sigma_clip_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    if ui
+        .add(egui::Slider::new(&mut app.config.sigma_clip_sigma, 0.5..=5.0).text("Sigma"))
+        .changed()
+    {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    let mut iter = app.config.sigma_clip_iterations as i32;
+    if ui
+        .add(egui::Slider::new(&mut iter, 1..=10).text("Iterations"))
+        .changed()
+    {
+        app.config.sigma_clip_iterations = iter as usize;
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+}
+
+/// GESD rejection limits.
+fn gesd_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    if ui
+        .add(egui::Slider::new(&mut app.config.gesd_max_outliers, 0.05..=0.5).text("Max Outliers"))
+        .on_hover_text("Largest fraction of each pixel's values that may be rejected")
+        .changed()
+    {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    if ui
+        .add(
+            egui::Slider::new(&mut app.config.gesd_alpha, 0.001..=0.2)
+                .logarithmic(true)
+                .text("Alpha"),
+        )
+        .changed()
+    {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+}
+
+/// Per-AP stacking method (multi-point).
+fn local_method_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    if crate::panels::enum_combo(
+        ui,
+        "Local Stack",
+        &mut app.config.mp_local_method,
+        LocalMethodChoice::ALL,
+    ) {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    match app.config.mp_local_method {
+        LocalMethodChoice::SigmaClip
+        | LocalMethodChoice::WinsorizedSigmaClip
+        | LocalMethodChoice::LinearFitClip => sigma_clip_controls(ui, app),
+        LocalMethodChoice::Gesd => gesd_controls(ui, app),
+        LocalMethodChoice::Mean | LocalMethodChoice::Median => {}
+    }
+}
+
+/// Super-resolution reconstruction parameters.
+fn super_resolution_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    if ui
+        .add(egui::Slider::new(&mut app.config.sr_scale, 1.0..=4.0).text("Scale"))
+        .changed()
+    {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    let mut iter = app.config.sr_iterations as i32;
+    if ui
+        .add(egui::Slider::new(&mut iter, 1..=100).text("Iterations"))
+        .changed()
+    {
+        app.config.sr_iterations = iter as usize;
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    if ui
+        .add(egui::Slider::new(&mut app.config.sr_psf_sigma, 0.3..=4.0).text("PSF Sigma"))
+        .on_hover_text("Gaussian blur of the forward model, in output pixels")
+        .changed()
+    {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    if ui
+        .add(
+            egui::Slider::new(&mut app.config.sr_regularization, 0.0..=0.2)
+                .logarithmic(true)
+                .text("Smoothness"),
+        )
+        .on_hover_text("Higher values suppress noise and ringing at the cost of detail")
+        .changed()
+    {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    if ui
+        .checkbox(&mut app.config.sr_quality_weighted, "Quality weighted")
+        .changed()
+    {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
 }
 
 /// Shared AP controls for MultiPoint and SurfaceWarp stacking methods.
@@ -148,3 +288,80 @@ fn ap_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
         app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
     }
 }
+
+/// Pyramid and window controls for optical-flow stacking.
+fn flow_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    let mut levels = app.config.flow_levels as i32;
+    if ui
+        .add(egui::Slider::new(&mut levels, 0..=5).text("Levels"))
+        .changed()
+    {
+        app.config.flow_levels = levels as usize;
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    let mut window = app.config.flow_window_radius as i32;
+    if ui
+        .add(egui::Slider::new(&mut window, 2..=16).text("Window Radius"))
+        .changed()
+    {
+        app.config.flow_window_radius = window as usize;
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    let mut iter = app.config.flow_iterations as i32;
+    if ui
+        .add(egui::Slider::new(&mut iter, 1..=10).text("Iterations"))
+        .changed()
+    {
+        app.config.flow_iterations = iter as usize;
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    if ui
+        .add(egui::Slider::new(&mut app.config.flow_smoothing, 0.0..=5.0).text("Smoothing"))
+        .changed()
+    {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+}
+
+/// AP placement controls (multi-point only; surface warp needs a regular grid).
+fn ap_placement_controls(ui: &mut egui::Ui, app: &mut JupiterApp) {
+    if let Some(count) = app.config.mp_custom_points.as_ref().map(Vec::len) {
+        ui.horizontal(|ui| {
+            ui.label(format!("Custom APs: {count}"));
+            if ui.small_button("Clear").clicked() {
+                app.config.mp_custom_points = None;
+                app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+            }
+        });
+        return;
+    }
+
+    if crate::panels::enum_combo(
+        ui,
+        "AP Placement",
+        &mut app.config.mp_placement,
+        ApPlacementChoice::ALL,
+    ) {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+
+    if matches!(
+        app.config.mp_placement,
+        ApPlacementChoice::Structure | ApPlacementChoice::MultiScale
+    ) && ui
+        .add(egui::Slider::new(&mut app.config.mp_min_energy, 0.0..=1.0).text("Min Energy"))
+        .changed()
+    {
+        app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+    }
+    if app.config.mp_placement == ApPlacementChoice::MultiScale {
+        let mut small = app.config.mp_small_ap_size as i32;
+        if ui
+            .add(egui::Slider::new(&mut small, 8..=128).text("Small AP Size"))
+            .changed()
+        {
+            app.config.mp_small_ap_size = small as usize;
+            app.ui_state.stages.mark_dirty_from(PipelineStage::Stacking);
+        }
+    }
+}
@@ -43,12 +43,7 @@ pub(super) fn actions_section(ui: &mut egui::Ui, app: &mut JupiterApp) {
         .clicked()
     {
         if let Some(ref path) = app.ui_state.file_path {
-            let output = if app.ui_state.output_path.is_empty() {
-                path.with_extension("tiff")
-            } else {
-                std::path::PathBuf::from(&app.ui_state.output_path)
-            };
-            let config = app.config.to_pipeline_config(path, &output);
+            let config = app.pipeline_config(path);
             app.ui_state.running_stage = Some(PipelineStage::Reading);
             app.send_command(WorkerCommand::RunAll { config });
         }
@@ -1,6 +1,7 @@
 use crate::app::JupiterApp;
 use crate::messages::{WorkerCommand, WorkerResult};
 use crate::states::ConfigState;
+use jupiter_core::io::frame_report::FrameReport;
 use jupiter_core::pipeline::PipelineStage;
 
 pub fn show(ctx: &egui::Context, app: &mut JupiterApp) {
@@ -33,6 +34,11 @@ pub fn show(ctx: &egui::Context, app: &mut JupiterApp) {
                     save_file(app);
                 }
 
+                if ui.button("Compose Channels...").clicked() {
+                    ui.close();
+                    app.ui_state.compose.open = true;
+                }
+
                 ui.separator();
 
                 if ui.button("Import Config...").clicked() {
@@ -47,6 +53,18 @@ pub fn show(ctx: &egui::Context, app: &mut JupiterApp) {
 
                 ui.separator();
 
+                if ui.button("Export Frame Report...").clicked() {
+                    ui.close();
+                    export_frame_report(app);
+                }
+
+                if ui.button("Import Frame Selection...").clicked() {
+                    ui.close();
+                    import_frame_list(app);
+                }
+
+                ui.separator();
+
                 let quit_shortcut =
                     egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Q);
                 if ui
@@ -178,3 +196,43 @@ fn export_config(app: &mut JupiterApp) {
         }
     });
 }
+
+fn export_frame_report(app: &mut JupiterApp) {
+    let cmd_tx = app.cmd_tx.clone();
+    let metric = app.config.quality_metric;
+    std::thread::spawn(move || {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("JSON", &["json"])
+            .set_file_name("frames.csv")
+            .save_file()
+        {
+            let _ = cmd_tx.send(WorkerCommand::ExportFrameReport { path, metric });
+        }
+    });
+}
+
+fn import_frame_list(app: &mut JupiterApp) {
+    let result_tx = app.result_tx.clone();
+    std::thread::spawn(move || {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Frame report", &["csv", "json"])
+            .pick_file()
+        else {
+            return;
+        };
+        let result = match FrameReport::load(&path) {
+            Ok(report) if report.selected_indices().is_empty() => WorkerResult::Error {
+                message: format!("No frames selected in {}", path.display()),
+            },
+            Ok(report) => WorkerResult::FrameListImported {
+                selected: report.selected_indices().len(),
+                path,
+            },
+            Err(e) => WorkerResult::Error {
+                message: format!("Failed to import frame list: {e}"),
+            },
+        };
+        let _ = result_tx.send(result);
+    });
+}
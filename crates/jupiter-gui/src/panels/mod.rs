@@ -1,3 +1,4 @@
+pub mod compose;
 pub mod controls;
 pub mod crop_interaction;
 pub mod general_controls_bar;
@@ -1,3 +1,5 @@
+use jupiter_core::stack::ap_diagnostics::ApDiagnostics;
+
 use crate::app::JupiterApp;
 use crate::panels::crop_interaction;
 
@@ -38,6 +40,12 @@ pub fn show(ctx: &egui::Context, app: &mut JupiterApp) {
             let img_rect = compute_img_rect(rect, image_size, app);
             draw_image(ui, texture_id, img_rect);
 
+            if app.ui_state.show_ap_overlay {
+                if let Some(ref diagnostics) = app.ui_state.ap_diagnostics {
+                    draw_ap_overlay(ui, diagnostics, img_rect, image_size);
+                }
+            }
+
             if let Some(ref crop_rect) = app.ui_state.crop_state.rect {
                 crop_interaction::draw_crop_overlay(ui, crop_rect, img_rect, image_size);
             }
@@ -105,6 +113,61 @@ fn draw_image(ui: &egui::Ui, texture_id: egui::TextureId, img_rect: egui::Rect)
     );
 }
 
+/// AP squares coloured by frame usage (green = all selected frames used,
+/// red = none), with arrows for the mean local shifts scaled so the longest
+/// spans half an AP. Skipped when the image is not the stack's size.
+fn draw_ap_overlay(
+    ui: &egui::Ui,
+    diagnostics: &ApDiagnostics,
+    img_rect: egui::Rect,
+    image_size: egui::Vec2,
+) {
+    if image_size != egui::vec2(diagnostics.width as f32, diagnostics.height as f32) {
+        return;
+    }
+    let scale = img_rect.width() / image_size.x;
+    let to_screen = |x: f32, y: f32| img_rect.min + egui::vec2(x, y) * scale;
+    let max_shift = diagnostics.max_shift() as f32;
+    let arrow_scale = if max_shift > 0.0 {
+        diagnostics.grid.ap_size as f32 / 2.0 / max_shift
+    } else {
+        0.0
+    };
+
+    let painter = ui.painter_at(img_rect);
+    for ap in &diagnostics.grid.points {
+        let stats = &diagnostics.aps[ap.index];
+        let usage = stats.usage() as f32;
+        let color = egui::Color32::from_rgb(
+            (255.0 * (2.0 * (1.0 - usage)).min(1.0)) as u8,
+            (255.0 * (2.0 * usage).min(1.0)) as u8,
+            0,
+        );
+        let (cx, cy) = (ap.cx as f32, ap.cy as f32);
+        let half = ap.size as f32 / 2.0;
+        painter.rect_stroke(
+            egui::Rect::from_min_max(
+                to_screen(cx - half, cy - half),
+                to_screen(cx + half, cy + half),
+            ),
+            0.0,
+            egui::Stroke::new(1.0, color.gamma_multiply(0.6)),
+            egui::StrokeKind::Inside,
+        );
+        let shift =
+            egui::vec2(stats.mean_shift.dx as f32, stats.mean_shift.dy as f32) * arrow_scale;
+        if shift.length() * scale >= 2.0 {
+            painter.arrow(
+                to_screen(cx, cy),
+                shift * scale,
+                egui::Stroke::new(1.5, color),
+            );
+        } else {
+            painter.circle_filled(to_screen(cx, cy), 1.5, color);
+        }
+    }
+}
+
 fn draw_viewing_label(ui: &egui::Ui, rect: egui::Rect, label: &str) {
     if label.is_empty() {
         return;
@@ -1,6 +1,6 @@
 use std::fmt;
 
-use jupiter_core::pipeline::config::FilterStep;
+use jupiter_core::pipeline::config::{ColorChannel, FilterStep, ResampleMethod, WhiteBalance};
 
 /// Alignment method selector (no associated data — just the discriminant).
 #[derive(Clone, Copy, PartialEq, Default)]
@@ -11,6 +11,7 @@ pub enum AlignMethodChoice {
     Centroid,
     GradientCorrelation,
     Pyramid,
+    LimbFit,
 }
 
 impl AlignMethodChoice {
@@ -20,6 +21,7 @@ impl AlignMethodChoice {
         Self::Centroid,
         Self::GradientCorrelation,
         Self::Pyramid,
+        Self::LimbFit,
     ];
 }
 
@@ -31,6 +33,7 @@ impl fmt::Display for AlignMethodChoice {
             Self::Centroid => write!(f, "Centroid"),
             Self::GradientCorrelation => write!(f, "Gradient Correlation"),
             Self::Pyramid => write!(f, "Pyramid"),
+            Self::LimbFit => write!(f, "Limb Fit"),
         }
     }
 }
@@ -42,9 +45,14 @@ pub enum StackMethodChoice {
     Mean,
     Median,
     SigmaClip,
+    WinsorizedSigmaClip,
+    LinearFitClip,
+    Gesd,
     MultiPoint,
     Drizzle,
     SurfaceWarp,
+    OpticalFlow,
+    SuperResolution,
 }
 
 impl StackMethodChoice {
@@ -52,9 +60,14 @@ impl StackMethodChoice {
         Self::Mean,
         Self::Median,
         Self::SigmaClip,
+        Self::WinsorizedSigmaClip,
+        Self::LinearFitClip,
+        Self::Gesd,
         Self::MultiPoint,
         Self::Drizzle,
         Self::SurfaceWarp,
+        Self::OpticalFlow,
+        Self::SuperResolution,
     ];
 }
 
@@ -64,9 +77,75 @@ impl fmt::Display for StackMethodChoice {
             Self::Mean => write!(f, "Mean"),
             Self::Median => write!(f, "Median"),
             Self::SigmaClip => write!(f, "Sigma Clip"),
+            Self::WinsorizedSigmaClip => write!(f, "Winsorized Sigma Clip"),
+            Self::LinearFitClip => write!(f, "Linear Fit Clip"),
+            Self::Gesd => write!(f, "GESD"),
             Self::MultiPoint => write!(f, "Multi-Point"),
             Self::Drizzle => write!(f, "Drizzle"),
             Self::SurfaceWarp => write!(f, "Surface Warp"),
+            Self::OpticalFlow => write!(f, "Optical Flow"),
+            Self::SuperResolution => write!(f, "Super-Resolution"),
+        }
+    }
+}
+
+/// Alignment point placement selector (multi-point stacking).
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum ApPlacementChoice {
+    #[default]
+    Grid,
+    Structure,
+    Limb,
+    MultiScale,
+}
+
+impl ApPlacementChoice {
+    pub const ALL: &[Self] = &[Self::Grid, Self::Structure, Self::Limb, Self::MultiScale];
+}
+
+impl fmt::Display for ApPlacementChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Grid => write!(f, "Grid"),
+            Self::Structure => write!(f, "Structure"),
+            Self::Limb => write!(f, "Limb"),
+            Self::MultiScale => write!(f, "Multi-scale"),
+        }
+    }
+}
+
+/// Per-AP stacking method selector (multi-point).
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum LocalMethodChoice {
+    #[default]
+    Mean,
+    Median,
+    SigmaClip,
+    WinsorizedSigmaClip,
+    LinearFitClip,
+    Gesd,
+}
+
+impl LocalMethodChoice {
+    pub const ALL: &[Self] = &[
+        Self::Mean,
+        Self::Median,
+        Self::SigmaClip,
+        Self::WinsorizedSigmaClip,
+        Self::LinearFitClip,
+        Self::Gesd,
+    ];
+}
+
+impl fmt::Display for LocalMethodChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mean => write!(f, "Mean"),
+            Self::Median => write!(f, "Median"),
+            Self::SigmaClip => write!(f, "Sigma Clip"),
+            Self::WinsorizedSigmaClip => write!(f, "Winsorized"),
+            Self::LinearFitClip => write!(f, "Linear Fit"),
+            Self::Gesd => write!(f, "GESD"),
         }
     }
 }
@@ -77,10 +156,15 @@ pub enum DeconvMethodChoice {
     #[default]
     RichardsonLucy,
     Wiener,
+    BlindRichardsonLucy,
 }
 
 impl DeconvMethodChoice {
-    pub const ALL: &[Self] = &[Self::RichardsonLucy, Self::Wiener];
+    pub const ALL: &[Self] = &[
+        Self::RichardsonLucy,
+        Self::Wiener,
+        Self::BlindRichardsonLucy,
+    ];
 }
 
 impl fmt::Display for DeconvMethodChoice {
@@ -88,6 +172,7 @@ impl fmt::Display for DeconvMethodChoice {
         match self {
             Self::RichardsonLucy => write!(f, "Richardson-Lucy"),
             Self::Wiener => write!(f, "Wiener"),
+            Self::BlindRichardsonLucy => write!(f, "Blind Richardson-Lucy"),
         }
     }
 }
@@ -99,10 +184,18 @@ pub enum PsfModelChoice {
     Gaussian,
     Kolmogorov,
     Airy,
+    Measured,
+    Limb,
 }
 
 impl PsfModelChoice {
-    pub const ALL: &[Self] = &[Self::Gaussian, Self::Kolmogorov, Self::Airy];
+    pub const ALL: &[Self] = &[
+        Self::Gaussian,
+        Self::Kolmogorov,
+        Self::Airy,
+        Self::Measured,
+        Self::Limb,
+    ];
 }
 
 impl fmt::Display for PsfModelChoice {
@@ -111,6 +204,8 @@ impl fmt::Display for PsfModelChoice {
             Self::Gaussian => write!(f, "Gaussian"),
             Self::Kolmogorov => write!(f, "Kolmogorov"),
             Self::Airy => write!(f, "Airy"),
+            Self::Measured => write!(f, "Measured"),
+            Self::Limb => write!(f, "From limb"),
         }
     }
 }
@@ -124,6 +219,18 @@ pub enum FilterType {
     BrightnessContrast,
     UnsharpMask,
     GaussianBlur,
+    NonLocalMeans,
+    Bilateral,
+    GuidedFilter,
+    WaveletDenoise,
+    Curves,
+    Levels,
+    Saturation,
+    WhiteBalance,
+    Rotate,
+    Flip,
+    Resample,
+    Canvas,
 }
 
 impl FilterType {
@@ -134,6 +241,18 @@ impl FilterType {
         Self::BrightnessContrast,
         Self::UnsharpMask,
         Self::GaussianBlur,
+        Self::NonLocalMeans,
+        Self::Bilateral,
+        Self::GuidedFilter,
+        Self::WaveletDenoise,
+        Self::Curves,
+        Self::Levels,
+        Self::Saturation,
+        Self::WhiteBalance,
+        Self::Rotate,
+        Self::Flip,
+        Self::Resample,
+        Self::Canvas,
     ];
 
     /// Create a `FilterStep` with default parameters for this filter type.
@@ -158,6 +277,58 @@ impl FilterType {
                 threshold: 0.0,
             },
             Self::GaussianBlur => FilterStep::GaussianBlur { sigma: 1.0 },
+            Self::NonLocalMeans => FilterStep::NonLocalMeans {
+                strength: 1.0,
+                patch_radius: 2,
+                search_radius: 5,
+                chroma_only: false,
+            },
+            Self::Bilateral => FilterStep::Bilateral {
+                spatial_sigma: 2.0,
+                range_sigma: 0.05,
+                chroma_only: false,
+            },
+            Self::GuidedFilter => FilterStep::GuidedFilter {
+                radius: 4,
+                epsilon: 0.001,
+                chroma_only: false,
+            },
+            Self::WaveletDenoise => FilterStep::WaveletDenoise {
+                layers: 4,
+                strength: 1.0,
+                chroma_only: false,
+            },
+            Self::Curves => FilterStep::Curves {
+                channel: ColorChannel::All,
+                points: vec![[0.0, 0.0], [1.0, 1.0]],
+            },
+            Self::Levels => FilterStep::Levels {
+                channel: ColorChannel::All,
+                black_point: 0.0,
+                white_point: 1.0,
+                gamma: 1.0,
+            },
+            Self::Saturation => FilterStep::Saturation {
+                saturation: 1.0,
+                vibrance: 0.0,
+            },
+            Self::WhiteBalance => FilterStep::WhiteBalance(WhiteBalance::GreyWorld),
+            Self::Rotate => FilterStep::Rotate {
+                degrees: 0.0,
+                expand: false,
+            },
+            Self::Flip => FilterStep::Flip {
+                horizontal: true,
+                vertical: false,
+            },
+            Self::Resample => FilterStep::Resample {
+                scale: 1.0,
+                method: ResampleMethod::default(),
+            },
+            Self::Canvas => FilterStep::Canvas {
+                width: 512,
+                height: 512,
+            },
         }
     }
 }
@@ -171,6 +342,18 @@ impl fmt::Display for FilterType {
             Self::BrightnessContrast => write!(f, "Brightness/Contrast"),
             Self::UnsharpMask => write!(f, "Unsharp Mask"),
             Self::GaussianBlur => write!(f, "Gaussian Blur"),
+            Self::NonLocalMeans => write!(f, "Non-Local Means"),
+            Self::Bilateral => write!(f, "Bilateral"),
+            Self::GuidedFilter => write!(f, "Guided Filter"),
+            Self::WaveletDenoise => write!(f, "Wavelet Denoise"),
+            Self::Curves => write!(f, "Curves"),
+            Self::Levels => write!(f, "Levels"),
+            Self::Saturation => write!(f, "Saturation/Vibrance"),
+            Self::WhiteBalance => write!(f, "White Balance"),
+            Self::Rotate => write!(f, "Rotate"),
+            Self::Flip => write!(f, "Flip"),
+            Self::Resample => write!(f, "Resample"),
+            Self::Canvas => write!(f, "Canvas Crop/Pad"),
         }
     }
 }
@@ -0,0 +1,97 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use jupiter_core::consts::DEFAULT_LUMINANCE_WEIGHT;
+use jupiter_core::pipeline::config::{ChannelRole, CompositionConfig, RegistrationMode};
+
+/// Composition preset selector.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum ComposeModeChoice {
+    #[default]
+    Rgb,
+    Lrgb,
+    IrRgb,
+    FalseColor,
+}
+
+impl ComposeModeChoice {
+    pub const ALL: &[Self] = &[Self::Rgb, Self::Lrgb, Self::IrRgb, Self::FalseColor];
+}
+
+impl fmt::Display for ComposeModeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rgb => write!(f, "RGB"),
+            Self::Lrgb => write!(f, "LRGB"),
+            Self::IrRgb => write!(f, "IR-RGB"),
+            Self::FalseColor => write!(f, "False Color (CH4)"),
+        }
+    }
+}
+
+/// State of the channel composition window.
+pub struct ComposeState {
+    /// Whether the window is shown.
+    pub open: bool,
+    /// Chosen image per filter, in `ChannelRole::ALL` order.
+    pub inputs: Vec<(ChannelRole, Option<PathBuf>)>,
+    pub mode: ComposeModeChoice,
+    /// Channel the methane image replaces in false-color mode.
+    pub methane_channel: ChannelRole,
+    pub registration: RegistrationMode,
+    pub luminance_weight: f32,
+    pub normalize: bool,
+}
+
+impl Default for ComposeState {
+    fn default() -> Self {
+        Self {
+            open: false,
+            inputs: ChannelRole::ALL.iter().map(|&role| (role, None)).collect(),
+            mode: ComposeModeChoice::default(),
+            methane_channel: ChannelRole::Red,
+            registration: RegistrationMode::default(),
+            luminance_weight: DEFAULT_LUMINANCE_WEIGHT,
+            normalize: true,
+        }
+    }
+}
+
+impl ComposeState {
+    pub fn to_config(&self) -> CompositionConfig {
+        let preset = match self.mode {
+            ComposeModeChoice::Rgb => CompositionConfig::rgb(),
+            ComposeModeChoice::Lrgb => CompositionConfig::lrgb(),
+            ComposeModeChoice::IrRgb => CompositionConfig::ir_rgb(),
+            ComposeModeChoice::FalseColor => CompositionConfig::false_color(self.methane_channel),
+        };
+        CompositionConfig {
+            registration: self.registration,
+            luminance_weight: self.luminance_weight,
+            normalize: self.normalize,
+            ..preset
+        }
+    }
+
+    pub fn input(&self, role: ChannelRole) -> Option<&PathBuf> {
+        self.inputs
+            .iter()
+            .find(|(r, _)| *r == role)
+            .and_then(|(_, path)| path.as_ref())
+    }
+
+    pub fn set_input(&mut self, role: ChannelRole, path: PathBuf) {
+        if let Some((_, slot)) = self.inputs.iter_mut().find(|(r, _)| *r == role) {
+            *slot = Some(path);
+        }
+    }
+
+    /// The images the current mode needs, or the first role still missing.
+    pub fn selected_inputs(&self) -> Result<Vec<(ChannelRole, PathBuf)>, ChannelRole> {
+        self.to_config()
+            .required_roles()
+            .into_iter()
+            .map(|role| self.input(role).map(|p| (role, p.clone())).ok_or(role))
+            .collect()
+    }
+}
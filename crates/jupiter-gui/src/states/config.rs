@@ -1,17 +1,35 @@
+use std::path::PathBuf;
+
 use jupiter_core::color::debayer::DebayerMethod;
 use jupiter_core::compute::DevicePreference;
+use jupiter_core::consts::{
+    DEFAULT_AP_MIN_ENERGY, DEFAULT_AP_SMALL_SIZE, DEFAULT_BLIND_PSF_SIZE, DEFAULT_FLOW_ITERATIONS,
+    DEFAULT_FLOW_LEVELS, DEFAULT_FLOW_SMOOTHING, DEFAULT_FLOW_WINDOW_RADIUS, DEFAULT_GESD_ALPHA,
+    DEFAULT_GESD_MAX_OUTLIERS, DEFAULT_LIMB_EDGE_THRESHOLD, DEFAULT_REFINE_MAX_PASSES,
+    DEFAULT_REFINE_TOP_FRACTION, DEFAULT_REJECT_MAX_DRIFT, DEFAULT_REJECT_MIN_CONFIDENCE,
+    DEFAULT_SR_ITERATIONS, DEFAULT_SR_PSF_SIGMA, DEFAULT_SR_REGULARIZATION, DEFAULT_SR_SCALE,
+    DEFAULT_WARP_SPLINE_SMOOTHING,
+};
 use jupiter_core::pipeline::config::{
     AlignmentConfig, AlignmentMethod, CentroidConfig, DebayerConfig, DeconvolutionConfig,
-    DeconvolutionMethod, EnhancedPhaseConfig, FilterStep, FrameSelectionConfig, PipelineConfig,
-    PsfModel, PyramidConfig, QualityMetric, SharpeningConfig, StackMethod, StackingConfig,
+    DeconvolutionMethod, EnhancedPhaseConfig, FilterStep, FrameSelectionConfig, LimbFitConfig,
+    LimbModel, OutlierRejection, PipelineConfig, PsfModel, PsfRegion, PyramidConfig, QualityMetric,
+    ReferenceRefinement, SharpeningConfig, StackMethod, StackingConfig, TiledDeconvolution,
 };
-use jupiter_core::sharpen::wavelet::WaveletParams;
+use jupiter_core::sharpen::regularized_rl::RlOptions;
+use jupiter_core::sharpen::wavelet::{LayerDenoise, WaveletKernel, WaveletParams, WaveletScaling};
 use jupiter_core::stack::drizzle::DrizzleConfig;
-use jupiter_core::stack::multi_point::MultiPointConfig;
+use jupiter_core::stack::multi_point::{ApPlacement, ApSpec, LocalStackMethod, MultiPointConfig};
+use jupiter_core::stack::optical_flow::OpticalFlowConfig;
+use jupiter_core::stack::rejection::GesdParams;
 use jupiter_core::stack::sigma_clip::SigmaClipParams;
-use jupiter_core::stack::surface_warp::SurfaceWarpConfig;
+use jupiter_core::stack::super_resolution::SuperResolutionConfig;
+use jupiter_core::stack::surface_warp::{ShiftFieldModel, SurfaceWarpConfig};
 
-use super::choices::{AlignMethodChoice, DeconvMethodChoice, PsfModelChoice, StackMethodChoice};
+use super::choices::{
+    AlignMethodChoice, ApPlacementChoice, DeconvMethodChoice, LocalMethodChoice, PsfModelChoice,
+    StackMethodChoice,
+};
 
 /// All pipeline configuration parameters as editable UI fields.
 pub struct ConfigState {
@@ -22,42 +40,89 @@ pub struct ConfigState {
     // Frame selection
     pub quality_metric: QualityMetric,
     pub select_percentage: f32,
+    /// Imported frame report overriding `select_percentage`.
+    pub frame_list: Option<PathBuf>,
 
     // Alignment
     pub align_method: AlignMethodChoice,
     pub enhanced_phase_upsample: usize,
     pub centroid_threshold: f32,
     pub pyramid_levels: usize,
+    pub limb_edge_threshold: f32,
+    pub limb_model: LimbModel,
+    // Stacked-reference refinement
+    pub refine_enabled: bool,
+    pub refine_top_fraction: f32,
+    pub refine_max_passes: usize,
+    // Outlier rejection
+    pub reject_enabled: bool,
+    pub reject_min_confidence: f64,
+    pub reject_max_drift: f64,
 
     // Stacking
     pub stack_method_choice: StackMethodChoice,
-    // Sigma clip params
+    pub stack_quality_weighted: bool,
+    // Sigma clip params (also winsorized and linear-fit clipping)
     pub sigma_clip_sigma: f32,
     pub sigma_clip_iterations: usize,
+    // GESD params
+    pub gesd_max_outliers: f32,
+    pub gesd_alpha: f32,
     // Multi-point params
     pub mp_auto_ap_size: bool,
     pub mp_ap_size: usize,
     pub mp_search_radius: usize,
     pub mp_min_brightness: f32,
+    pub mp_placement: ApPlacementChoice,
+    pub mp_local_method: LocalMethodChoice,
+    pub mp_min_energy: f32,
+    pub mp_small_ap_size: usize,
+    /// AP list from a loaded config, overriding `mp_placement`.
+    pub mp_custom_points: Option<Vec<ApSpec>>,
+    // Surface warp params
+    pub sw_spline_field: bool,
+    pub sw_spline_smoothing: f32,
     // Drizzle params
     pub drizzle_scale: f32,
     pub drizzle_pixfrac: f32,
     pub drizzle_quality_weighted: bool,
+    // Optical flow params
+    pub flow_levels: usize,
+    pub flow_window_radius: usize,
+    pub flow_iterations: usize,
+    pub flow_smoothing: f32,
+    // Super-resolution params
+    pub sr_scale: f32,
+    pub sr_iterations: usize,
+    pub sr_psf_sigma: f32,
+    pub sr_regularization: f32,
+    pub sr_quality_weighted: bool,
 
     // Sharpening
     pub sharpen_enabled: bool,
     pub wavelet_num_layers: usize,
     pub wavelet_coefficients: Vec<f32>,
     pub wavelet_denoise: Vec<f32>,
+    pub wavelet_denoise_mode: LayerDenoise,
+    pub wavelet_kernel: WaveletKernel,
+    pub wavelet_scaling: WaveletScaling,
+    pub wavelet_edge_protection: f32,
+    pub wavelet_linked_luminance: bool,
     // Deconvolution
     pub deconv_enabled: bool,
     pub deconv_method: DeconvMethodChoice,
     pub rl_iterations: usize,
+    pub rl_options: RlOptions,
     pub wiener_noise_ratio: f32,
+    pub blind_psf_size: usize,
     pub psf_model: PsfModelChoice,
     pub psf_gaussian_sigma: f32,
     pub psf_kolmogorov_seeing: f32,
     pub psf_airy_radius: f32,
+    pub psf_file: Option<PathBuf>,
+    pub psf_region: Option<PsfRegion>,
+    pub deconv_tiled: bool,
+    pub deconv_tiling: TiledDeconvolution,
 
     // Filters
     pub filters: Vec<FilterStep>,
@@ -74,35 +139,74 @@ impl Default for ConfigState {
 
             quality_metric: QualityMetric::default(),
             select_percentage: 0.25,
+            frame_list: None,
 
             align_method: AlignMethodChoice::default(),
             enhanced_phase_upsample: 20,
             centroid_threshold: 0.1,
             pyramid_levels: 3,
+            limb_edge_threshold: DEFAULT_LIMB_EDGE_THRESHOLD,
+            limb_model: LimbModel::default(),
+            refine_enabled: false,
+            refine_top_fraction: DEFAULT_REFINE_TOP_FRACTION,
+            refine_max_passes: DEFAULT_REFINE_MAX_PASSES,
+            reject_enabled: false,
+            reject_min_confidence: DEFAULT_REJECT_MIN_CONFIDENCE,
+            reject_max_drift: DEFAULT_REJECT_MAX_DRIFT,
 
             stack_method_choice: StackMethodChoice::default(),
+            stack_quality_weighted: false,
             sigma_clip_sigma: 2.5,
             sigma_clip_iterations: 2,
+            gesd_max_outliers: DEFAULT_GESD_MAX_OUTLIERS,
+            gesd_alpha: DEFAULT_GESD_ALPHA,
             mp_auto_ap_size: true,
             mp_ap_size: 64,
             mp_search_radius: 16,
             mp_min_brightness: 0.05,
+            mp_placement: ApPlacementChoice::default(),
+            mp_local_method: LocalMethodChoice::default(),
+            mp_min_energy: DEFAULT_AP_MIN_ENERGY,
+            mp_small_ap_size: DEFAULT_AP_SMALL_SIZE,
+            mp_custom_points: None,
+            sw_spline_field: false,
+            sw_spline_smoothing: DEFAULT_WARP_SPLINE_SMOOTHING,
             drizzle_scale: 2.0,
             drizzle_pixfrac: 0.7,
             drizzle_quality_weighted: true,
+            flow_levels: DEFAULT_FLOW_LEVELS,
+            flow_window_radius: DEFAULT_FLOW_WINDOW_RADIUS,
+            flow_iterations: DEFAULT_FLOW_ITERATIONS,
+            flow_smoothing: DEFAULT_FLOW_SMOOTHING,
+            sr_scale: DEFAULT_SR_SCALE,
+            sr_iterations: DEFAULT_SR_ITERATIONS,
+            sr_psf_sigma: DEFAULT_SR_PSF_SIGMA,
+            sr_regularization: DEFAULT_SR_REGULARIZATION,
+            sr_quality_weighted: true,
 
             sharpen_enabled: true,
             wavelet_num_layers: 6,
             wavelet_coefficients: vec![1.5, 1.3, 1.2, 1.1, 1.0, 1.0],
             wavelet_denoise: vec![],
+            wavelet_denoise_mode: LayerDenoise::default(),
+            wavelet_kernel: WaveletKernel::default(),
+            wavelet_scaling: WaveletScaling::default(),
+            wavelet_edge_protection: 0.0,
+            wavelet_linked_luminance: false,
             deconv_enabled: false,
             deconv_method: DeconvMethodChoice::default(),
             rl_iterations: 20,
+            rl_options: RlOptions::default(),
             wiener_noise_ratio: 0.01,
+            blind_psf_size: DEFAULT_BLIND_PSF_SIZE,
             psf_model: PsfModelChoice::default(),
             psf_gaussian_sigma: 1.5,
             psf_kolmogorov_seeing: 2.0,
             psf_airy_radius: 3.0,
+            psf_file: None,
+            psf_region: None,
+            deconv_tiled: false,
+            deconv_tiling: TiledDeconvolution::default(),
 
             filters: Vec::new(),
 
@@ -127,8 +231,83 @@ impl ConfigState {
                 AlignMethodChoice::Pyramid => AlignmentMethod::Pyramid(PyramidConfig {
                     levels: self.pyramid_levels,
                 }),
+                AlignMethodChoice::LimbFit => AlignmentMethod::LimbFit(LimbFitConfig {
+                    model: self.limb_model,
+                    edge_threshold: self.limb_edge_threshold,
+                    ..Default::default()
+                }),
                 AlignMethodChoice::PhaseCorrelation => AlignmentMethod::PhaseCorrelation,
             },
+            refinement: self.refine_enabled.then(|| ReferenceRefinement {
+                top_fraction: self.refine_top_fraction,
+                max_passes: self.refine_max_passes,
+                ..Default::default()
+            }),
+            rejection: self.reject_enabled.then(|| OutlierRejection {
+                min_confidence: self.reject_min_confidence,
+                max_drift: self.reject_max_drift,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// AP placement for multi-point stacking.
+    pub fn ap_placement(&self) -> ApPlacement {
+        if let Some(ref points) = self.mp_custom_points {
+            return ApPlacement::Custom {
+                points: points.clone(),
+            };
+        }
+        match self.mp_placement {
+            ApPlacementChoice::Grid => ApPlacement::Grid,
+            ApPlacementChoice::Structure => ApPlacement::Structure {
+                min_energy: self.mp_min_energy,
+            },
+            ApPlacementChoice::Limb => ApPlacement::Limb,
+            ApPlacementChoice::MultiScale => ApPlacement::MultiScale {
+                small_size: self.mp_small_ap_size,
+                min_energy: self.mp_min_energy,
+            },
+        }
+    }
+
+    fn sigma_clip_params(&self) -> SigmaClipParams {
+        SigmaClipParams {
+            sigma: self.sigma_clip_sigma,
+            iterations: self.sigma_clip_iterations,
+        }
+    }
+
+    fn gesd_params(&self) -> GesdParams {
+        GesdParams {
+            max_outliers: self.gesd_max_outliers,
+            alpha: self.gesd_alpha,
+        }
+    }
+
+    pub fn local_stack_method(&self) -> LocalStackMethod {
+        let (sigma, iterations) = (self.sigma_clip_sigma, self.sigma_clip_iterations);
+        match self.mp_local_method {
+            LocalMethodChoice::Mean => LocalStackMethod::Mean,
+            LocalMethodChoice::Median => LocalStackMethod::Median,
+            LocalMethodChoice::SigmaClip => LocalStackMethod::SigmaClip { sigma, iterations },
+            LocalMethodChoice::WinsorizedSigmaClip => {
+                LocalStackMethod::WinsorizedSigmaClip { sigma, iterations }
+            }
+            LocalMethodChoice::LinearFitClip => {
+                LocalStackMethod::LinearFitClip { sigma, iterations }
+            }
+            LocalMethodChoice::Gesd => LocalStackMethod::Gesd {
+                max_outliers: self.gesd_max_outliers,
+                alpha: self.gesd_alpha,
+            },
+        }
+    }
+
+    pub fn stacking_config(&self) -> StackingConfig {
+        StackingConfig {
+            method: self.stack_method(),
+            quality_weighted: self.stack_quality_weighted,
         }
     }
 
@@ -136,17 +315,23 @@ impl ConfigState {
         match self.stack_method_choice {
             StackMethodChoice::Mean => StackMethod::Mean,
             StackMethodChoice::Median => StackMethod::Median,
-            StackMethodChoice::SigmaClip => StackMethod::SigmaClip(SigmaClipParams {
-                sigma: self.sigma_clip_sigma,
-                iterations: self.sigma_clip_iterations,
-            }),
+            StackMethodChoice::SigmaClip => StackMethod::SigmaClip(self.sigma_clip_params()),
+            StackMethodChoice::WinsorizedSigmaClip => {
+                StackMethod::WinsorizedSigmaClip(self.sigma_clip_params())
+            }
+            StackMethodChoice::LinearFitClip => {
+                StackMethod::LinearFitClip(self.sigma_clip_params())
+            }
+            StackMethodChoice::Gesd => StackMethod::Gesd(self.gesd_params()),
             StackMethodChoice::MultiPoint => StackMethod::MultiPoint(MultiPointConfig {
                 ap_size: self.mp_ap_size,
                 search_radius: self.mp_search_radius,
                 select_percentage: self.select_percentage,
                 min_brightness: self.mp_min_brightness,
                 quality_metric: self.quality_metric,
-                local_stack_method: Default::default(),
+                local_stack_method: self.local_stack_method(),
+                placement: self.ap_placement(),
+                frames: None,
             }),
             StackMethodChoice::Drizzle => StackMethod::Drizzle(DrizzleConfig {
                 scale: self.drizzle_scale,
@@ -160,7 +345,35 @@ impl ConfigState {
                 select_percentage: self.select_percentage,
                 min_brightness: self.mp_min_brightness,
                 quality_metric: self.quality_metric,
+                field_model: if self.sw_spline_field {
+                    ShiftFieldModel::ThinPlateSpline {
+                        smoothing: self.sw_spline_smoothing,
+                    }
+                } else {
+                    ShiftFieldModel::Bilinear
+                },
+                frames: None,
             }),
+            StackMethodChoice::OpticalFlow => StackMethod::OpticalFlow(OpticalFlowConfig {
+                select_percentage: self.select_percentage,
+                quality_metric: self.quality_metric,
+                levels: self.flow_levels,
+                window_radius: self.flow_window_radius,
+                iterations: self.flow_iterations,
+                smoothing: self.flow_smoothing,
+                ..Default::default()
+            }),
+            StackMethodChoice::SuperResolution => {
+                StackMethod::SuperResolution(SuperResolutionConfig {
+                    scale: self.sr_scale,
+                    iterations: self.sr_iterations,
+                    psf: PsfModel::Gaussian {
+                        sigma: self.sr_psf_sigma,
+                    },
+                    regularization: self.sr_regularization,
+                    quality_weighted: self.sr_quality_weighted,
+                })
+            }
         }
     }
 
@@ -176,10 +389,17 @@ impl ConfigState {
             let method = match self.deconv_method {
                 DeconvMethodChoice::RichardsonLucy => DeconvolutionMethod::RichardsonLucy {
                     iterations: self.rl_iterations,
+                    options: self.rl_options,
                 },
                 DeconvMethodChoice::Wiener => DeconvolutionMethod::Wiener {
                     noise_ratio: self.wiener_noise_ratio,
                 },
+                DeconvMethodChoice::BlindRichardsonLucy => {
+                    DeconvolutionMethod::BlindRichardsonLucy {
+                        iterations: self.rl_iterations,
+                        psf_size: self.blind_psf_size,
+                    }
+                }
             };
             let psf = match self.psf_model {
                 PsfModelChoice::Gaussian => PsfModel::Gaussian {
@@ -191,8 +411,17 @@ impl ConfigState {
                 PsfModelChoice::Airy => PsfModel::Airy {
                     radius: self.psf_airy_radius,
                 },
+                PsfModelChoice::Measured => PsfModel::Measured {
+                    path: self.psf_file.clone().unwrap_or_default(),
+                    region: self.psf_region,
+                },
+                PsfModelChoice::Limb => PsfModel::Limb,
             };
-            Some(DeconvolutionConfig { method, psf })
+            Some(DeconvolutionConfig {
+                method,
+                psf,
+                tiling: self.deconv_tiled.then_some(self.deconv_tiling),
+            })
         } else {
             None
         };
@@ -202,11 +431,24 @@ impl ConfigState {
                 num_layers: self.wavelet_num_layers,
                 coefficients: self.wavelet_coefficients.clone(),
                 denoise: self.wavelet_denoise.clone(),
+                denoise_mode: self.wavelet_denoise_mode,
+                kernel: self.wavelet_kernel,
+                scaling: self.wavelet_scaling,
+                edge_protection: self.wavelet_edge_protection,
+                linked_luminance: self.wavelet_linked_luminance,
             },
             deconvolution,
         })
     }
 
+    pub fn frame_selection_config(&self) -> FrameSelectionConfig {
+        FrameSelectionConfig {
+            select_percentage: self.select_percentage,
+            metric: self.quality_metric,
+            frame_list: self.frame_list.clone(),
+        }
+    }
+
     pub fn debayer_config(&self) -> Option<DebayerConfig> {
         if self.debayer_enabled {
             Some(DebayerConfig {
@@ -224,18 +466,14 @@ impl ConfigState {
     ) -> PipelineConfig {
         PipelineConfig {
             input: input.to_path_buf(),
+            extra_inputs: vec![],
             output: output.to_path_buf(),
             device: self.device_preference(),
             debayer: self.debayer_config(),
             force_mono: !self.debayer_enabled,
-            frame_selection: FrameSelectionConfig {
-                select_percentage: self.select_percentage,
-                metric: self.quality_metric,
-            },
+            frame_selection: self.frame_selection_config(),
             alignment: self.alignment_config(),
-            stacking: StackingConfig {
-                method: self.stack_method(),
-            },
+            stacking: self.stacking_config(),
             sharpening: self.sharpening_config(),
             filters: self.filters.clone(),
             memory: Default::default(),
@@ -255,6 +493,7 @@ impl ConfigState {
 
         state.quality_metric = config.frame_selection.metric;
         state.select_percentage = config.frame_selection.select_percentage;
+        state.frame_list = config.frame_selection.frame_list.clone();
 
         // Alignment
         match &config.alignment.method {
@@ -276,9 +515,25 @@ impl ConfigState {
                 state.align_method = AlignMethodChoice::Pyramid;
                 state.pyramid_levels = p.levels;
             }
+            AlignmentMethod::LimbFit(p) => {
+                state.align_method = AlignMethodChoice::LimbFit;
+                state.limb_edge_threshold = p.edge_threshold;
+                state.limb_model = p.model;
+            }
+        }
+        if let Some(ref refine) = config.alignment.refinement {
+            state.refine_enabled = true;
+            state.refine_top_fraction = refine.top_fraction;
+            state.refine_max_passes = refine.max_passes;
+        }
+        if let Some(ref reject) = config.alignment.rejection {
+            state.reject_enabled = true;
+            state.reject_min_confidence = reject.min_confidence;
+            state.reject_max_drift = reject.max_drift;
         }
 
         // Stacking
+        state.stack_quality_weighted = config.stacking.quality_weighted;
         match &config.stacking.method {
             StackMethod::Mean => state.stack_method_choice = StackMethodChoice::Mean,
             StackMethod::Median => state.stack_method_choice = StackMethodChoice::Median,
@@ -287,11 +542,72 @@ impl ConfigState {
                 state.sigma_clip_sigma = p.sigma;
                 state.sigma_clip_iterations = p.iterations;
             }
+            StackMethod::WinsorizedSigmaClip(p) => {
+                state.stack_method_choice = StackMethodChoice::WinsorizedSigmaClip;
+                state.sigma_clip_sigma = p.sigma;
+                state.sigma_clip_iterations = p.iterations;
+            }
+            StackMethod::LinearFitClip(p) => {
+                state.stack_method_choice = StackMethodChoice::LinearFitClip;
+                state.sigma_clip_sigma = p.sigma;
+                state.sigma_clip_iterations = p.iterations;
+            }
+            StackMethod::Gesd(p) => {
+                state.stack_method_choice = StackMethodChoice::Gesd;
+                state.gesd_max_outliers = p.max_outliers;
+                state.gesd_alpha = p.alpha;
+            }
             StackMethod::MultiPoint(p) => {
                 state.stack_method_choice = StackMethodChoice::MultiPoint;
                 state.mp_ap_size = p.ap_size;
                 state.mp_search_radius = p.search_radius;
                 state.mp_min_brightness = p.min_brightness;
+                state.mp_local_method = match p.local_stack_method {
+                    LocalStackMethod::Mean => LocalMethodChoice::Mean,
+                    LocalStackMethod::Median => LocalMethodChoice::Median,
+                    LocalStackMethod::SigmaClip { sigma, iterations } => {
+                        state.sigma_clip_sigma = sigma;
+                        state.sigma_clip_iterations = iterations;
+                        LocalMethodChoice::SigmaClip
+                    }
+                    LocalStackMethod::WinsorizedSigmaClip { sigma, iterations } => {
+                        state.sigma_clip_sigma = sigma;
+                        state.sigma_clip_iterations = iterations;
+                        LocalMethodChoice::WinsorizedSigmaClip
+                    }
+                    LocalStackMethod::LinearFitClip { sigma, iterations } => {
+                        state.sigma_clip_sigma = sigma;
+                        state.sigma_clip_iterations = iterations;
+                        LocalMethodChoice::LinearFitClip
+                    }
+                    LocalStackMethod::Gesd {
+                        max_outliers,
+                        alpha,
+                    } => {
+                        state.gesd_max_outliers = max_outliers;
+                        state.gesd_alpha = alpha;
+                        LocalMethodChoice::Gesd
+                    }
+                };
+                match &p.placement {
+                    ApPlacement::Grid => state.mp_placement = ApPlacementChoice::Grid,
+                    ApPlacement::Structure { min_energy } => {
+                        state.mp_placement = ApPlacementChoice::Structure;
+                        state.mp_min_energy = *min_energy;
+                    }
+                    ApPlacement::Limb => state.mp_placement = ApPlacementChoice::Limb,
+                    ApPlacement::Custom { points } => {
+                        state.mp_custom_points = Some(points.clone());
+                    }
+                    ApPlacement::MultiScale {
+                        small_size,
+                        min_energy,
+                    } => {
+                        state.mp_placement = ApPlacementChoice::MultiScale;
+                        state.mp_small_ap_size = *small_size;
+                        state.mp_min_energy = *min_energy;
+                    }
+                }
             }
             StackMethod::Drizzle(p) => {
                 state.stack_method_choice = StackMethodChoice::Drizzle;
@@ -304,6 +620,30 @@ impl ConfigState {
                 state.mp_ap_size = p.ap_size;
                 state.mp_search_radius = p.search_radius;
                 state.mp_min_brightness = p.min_brightness;
+                match p.field_model {
+                    ShiftFieldModel::Bilinear => state.sw_spline_field = false,
+                    ShiftFieldModel::ThinPlateSpline { smoothing } => {
+                        state.sw_spline_field = true;
+                        state.sw_spline_smoothing = smoothing;
+                    }
+                }
+            }
+            StackMethod::OpticalFlow(p) => {
+                state.stack_method_choice = StackMethodChoice::OpticalFlow;
+                state.flow_levels = p.levels;
+                state.flow_window_radius = p.window_radius;
+                state.flow_iterations = p.iterations;
+                state.flow_smoothing = p.smoothing;
+            }
+            StackMethod::SuperResolution(p) => {
+                state.stack_method_choice = StackMethodChoice::SuperResolution;
+                state.sr_scale = p.scale;
+                state.sr_iterations = p.iterations;
+                if let PsfModel::Gaussian { sigma } = p.psf {
+                    state.sr_psf_sigma = sigma;
+                }
+                state.sr_regularization = p.regularization;
+                state.sr_quality_weighted = p.quality_weighted;
             }
         }
 
@@ -316,17 +656,34 @@ impl ConfigState {
             state.wavelet_num_layers = s.wavelet.num_layers;
             state.wavelet_coefficients = s.wavelet.coefficients.clone();
             state.wavelet_denoise = s.wavelet.denoise.clone();
+            state.wavelet_denoise_mode = s.wavelet.denoise_mode;
+            state.wavelet_kernel = s.wavelet.kernel;
+            state.wavelet_scaling = s.wavelet.scaling;
+            state.wavelet_edge_protection = s.wavelet.edge_protection;
+            state.wavelet_linked_luminance = s.wavelet.linked_luminance;
             if let Some(ref d) = s.deconvolution {
                 state.deconv_enabled = true;
                 match &d.method {
-                    DeconvolutionMethod::RichardsonLucy { iterations } => {
+                    DeconvolutionMethod::RichardsonLucy {
+                        iterations,
+                        options,
+                    } => {
                         state.deconv_method = DeconvMethodChoice::RichardsonLucy;
                         state.rl_iterations = *iterations;
+                        state.rl_options = *options;
                     }
                     DeconvolutionMethod::Wiener { noise_ratio } => {
                         state.deconv_method = DeconvMethodChoice::Wiener;
                         state.wiener_noise_ratio = *noise_ratio;
                     }
+                    DeconvolutionMethod::BlindRichardsonLucy {
+                        iterations,
+                        psf_size,
+                    } => {
+                        state.deconv_method = DeconvMethodChoice::BlindRichardsonLucy;
+                        state.rl_iterations = *iterations;
+                        state.blind_psf_size = *psf_size;
+                    }
                 }
                 match &d.psf {
                     PsfModel::Gaussian { sigma } => {
@@ -341,6 +698,16 @@ impl ConfigState {
                         state.psf_model = PsfModelChoice::Airy;
                         state.psf_airy_radius = *radius;
                     }
+                    PsfModel::Measured { path, region } => {
+                        state.psf_model = PsfModelChoice::Measured;
+                        state.psf_file = Some(path.clone());
+                        state.psf_region = *region;
+                    }
+                    PsfModel::Limb => state.psf_model = PsfModelChoice::Limb,
+                }
+                state.deconv_tiled = d.tiling.is_some();
+                if let Some(tiling) = d.tiling {
+                    state.deconv_tiling = tiling;
                 }
             }
         } else {
@@ -1,4 +1,5 @@
 mod choices;
+mod compose;
 mod config;
 mod crop;
 mod stage_status;
@@ -6,8 +7,10 @@ mod ui;
 mod viewport;
 
 pub use choices::{
-    AlignMethodChoice, DeconvMethodChoice, FilterType, PsfModelChoice, StackMethodChoice,
+    AlignMethodChoice, ApPlacementChoice, DeconvMethodChoice, FilterType, LocalMethodChoice,
+    PsfModelChoice, StackMethodChoice,
 };
+pub use compose::ComposeModeChoice;
 pub use config::ConfigState;
 pub use crop::{CropAspect, CropRectPixels};
 pub use ui::UIState;
@@ -2,7 +2,9 @@ use std::path::PathBuf;
 
 use jupiter_core::frame::SourceInfo;
 use jupiter_core::pipeline::PipelineStage;
+use jupiter_core::stack::ap_diagnostics::ApDiagnostics;
 
+use super::compose::ComposeState;
 use super::crop::CropState;
 use super::stage_status::Stages;
 
@@ -35,14 +37,27 @@ pub struct UIState {
     /// Crop state.
     pub crop_state: CropState,
 
+    /// Channel composition window.
+    pub compose: ComposeState,
+
     /// Detected planet diameter in pixels (from scoring step).
     pub detected_planet_diameter: Option<usize>,
 
     /// Set to true on mouse-up to trigger auto-sharpening.
     pub sharpen_requested: bool,
 
+    /// Set when a filter was edited; the filters are re-applied once the
+    /// mouse is released.
+    pub filter_requested: bool,
+
     /// Whether the viewport is showing a raw frame (true) or processed result (false).
     pub viewing_raw: bool,
+
+    /// AP diagnostics of the latest multi-point / surface warp stack.
+    pub ap_diagnostics: Option<ApDiagnostics>,
+
+    /// Draw the AP grid and local shifts over the viewport.
+    pub show_ap_overlay: bool,
 }
 
 impl Default for UIState {
@@ -60,9 +75,13 @@ impl Default for UIState {
             progress_items_done: None,
             progress_items_total: None,
             crop_state: CropState::default(),
+            compose: ComposeState::default(),
             detected_planet_diameter: None,
             sharpen_requested: false,
+            filter_requested: false,
             viewing_raw: true,
+            ap_diagnostics: None,
+            show_ap_overlay: false,
         }
     }
 }
@@ -86,6 +105,11 @@ impl UIState {
         self.sharpen_requested = true;
     }
 
+    /// Request a live preview of the filters.
+    pub fn request_filter(&mut self) {
+        self.filter_requested = true;
+    }
+
     /// Mark filter stage as stale.
     pub fn mark_dirty_from_filter(&mut self) {
         self.stages.mark_dirty_from(PipelineStage::Filtering);
@@ -99,7 +123,9 @@ impl UIState {
         self.crop_state = Default::default();
         self.clear_progress();
         self.sharpen_requested = false;
+        self.filter_requested = false;
         self.viewing_raw = true;
+        self.ap_diagnostics = None;
     }
 
     /// Clear progress counters.
@@ -1,22 +1,26 @@
 use std::sync::mpsc;
 use std::time::Instant;
 
-use jupiter_core::align::compute_offset_configured;
+use jupiter_core::align::compute_offsets_configured_with_progress;
 use jupiter_core::color::debayer::luminance;
-use jupiter_core::compute::create_backend;
-use jupiter_core::frame::{AlignmentOffset, ColorFrame, Frame};
+use jupiter_core::color::debayer::DebayerMethod;
+use jupiter_core::compute::{create_backend, DevicePreference};
+use jupiter_core::error::Result;
+use jupiter_core::frame::{ColorFrame, Frame};
+use jupiter_core::io::frame_report::{select_listed, FrameReport};
 use jupiter_core::io::ser::SerReader;
-use jupiter_core::pipeline::config::AlignmentConfig;
+use jupiter_core::pipeline::config::{AlignmentConfig, FrameSelectionConfig};
+use jupiter_core::pipeline::session::{CachedAlignment, SessionCache};
 use jupiter_core::pipeline::PipelineStage;
 
 use crate::messages::WorkerResult;
 
-use super::{send, send_error, send_log, PipelineCache};
+use super::{make_progress_callback, send, send_error, send_log, session, PipelineCache};
 
 pub(super) fn handle_align(
-    select_percentage: f32,
+    selection: &FrameSelectionConfig,
     alignment_config: &AlignmentConfig,
-    device: &jupiter_core::compute::DevicePreference,
+    device: &DevicePreference,
     cache: &mut PipelineCache,
     tx: &mpsc::Sender<WorkerResult>,
     ctx: &egui::Context,
@@ -32,28 +36,33 @@ pub(super) fn handle_align(
     let start = Instant::now();
     let is_streaming = cache.is_streaming;
 
-    let (total, selected_indices) = if is_streaming {
-        let total = ranked.len();
-        let keep = (total as f32 * select_percentage).ceil() as usize;
-        let keep = keep.max(1).min(total);
-        let indices: Vec<usize> = ranked.iter().take(keep).map(|(i, _)| *i).collect();
-        (total, indices)
+    let total = if is_streaming {
+        ranked.len()
     } else {
-        let total = cache.all_frames.as_ref().unwrap().len();
-        let keep = (total as f32 * select_percentage).ceil() as usize;
+        cache.all_frames.as_ref().unwrap().len()
+    };
+
+    let selected: Vec<_> = if let Some(path) = &selection.frame_list {
+        let listed = FrameReport::load(path)
+            .and_then(|report| select_listed(ranked, &report.selected_indices(), total));
+        match listed {
+            Ok(l) => l,
+            Err(e) => {
+                send_error(tx, ctx, format!("Invalid frame list: {e}"));
+                return;
+            }
+        }
+    } else {
+        let keep = (total as f32 * selection.select_percentage).ceil() as usize;
         let keep = keep.max(1).min(total);
-        let indices: Vec<usize> = ranked.iter().take(keep).map(|(i, _)| *i).collect();
-        (total, indices)
+        ranked.iter().take(keep).cloned().collect()
     };
+    let selected_indices: Vec<usize> = selected.iter().map(|(i, _)| *i).collect();
 
     let frame_count = selected_indices.len();
 
     // Extract quality scores for the selected frames (for drizzle weighting)
-    let quality_scores: Vec<f64> = ranked
-        .iter()
-        .take(frame_count)
-        .map(|(_, score)| score.composite)
-        .collect();
+    let quality_scores: Vec<f64> = selected.iter().map(|(_, score)| score.composite).collect();
 
     // Load selected frames and color frames
     let (selected_frames, selected_color): (Vec<Frame>, Option<Vec<ColorFrame>>) = if is_streaming {
@@ -77,40 +86,17 @@ pub(super) fn handle_align(
             format!("Loading {frame_count} selected frames from disk..."),
         );
 
-        if cache.is_color {
-            let debayer_method = cache.debayer_method.as_ref().unwrap();
-
-            let color_frames: Vec<ColorFrame> = match selected_indices
-                .iter()
-                .map(|&i| reader.read_frame_as_color(i, debayer_method))
-                .collect::<jupiter_core::error::Result<_>>()
-            {
-                Ok(cf) => cf,
-                Err(e) => {
-                    send_error(
-                        tx,
-                        ctx,
-                        format!("Failed to read selected color frames: {e}"),
-                    );
-                    return;
-                }
-            };
-
-            let lum_frames: Vec<Frame> = color_frames.iter().map(luminance).collect();
-            (lum_frames, Some(color_frames))
+        let debayer_method = if cache.is_color {
+            cache.debayer_method.as_ref()
         } else {
-            let mono_frames: Vec<Frame> = match selected_indices
-                .iter()
-                .map(|&i| reader.read_frame(i))
-                .collect::<jupiter_core::error::Result<_>>()
-            {
-                Ok(frames) => frames,
-                Err(e) => {
-                    send_error(tx, ctx, format!("Failed to read selected frames: {e}"));
-                    return;
-                }
-            };
-            (mono_frames, None)
+            None
+        };
+        match read_selected_frames(&reader, &selected_indices, debayer_method) {
+            Ok(frames) => frames,
+            Err(e) => {
+                send_error(tx, ctx, format!("Failed to read selected frames: {e}"));
+                return;
+            }
         }
     } else {
         let frames = cache.all_frames.as_ref().unwrap();
@@ -146,38 +132,77 @@ pub(super) fn handle_align(
         },
     );
 
-    let backend = create_backend(device);
-    let reference = &selected_frames[0];
-
-    // Compute offsets with per-frame progress
-    let mut offsets = Vec::with_capacity(selected_frames.len());
-    offsets.push(AlignmentOffset::default()); // reference frame
-    for (i, frame) in selected_frames[1..].iter().enumerate() {
-        let offset = compute_offset_configured(
-            &reference.data,
-            &frame.data,
+    let alignment_key = cache
+        .quality_key
+        .as_ref()
+        .and_then(|quality| SessionCache::alignment_key(quality, selection, alignment_config).ok());
+    let cached = alignment_key
+        .as_ref()
+        .and_then(|key| cache.session.as_ref()?.alignment(key))
+        .filter(|cached| cached.selected == selected_indices)
+        .cloned();
+
+    let alignment = match cached {
+        Some(alignment) => {
+            send_log(tx, ctx, "Reusing cached alignment offsets");
+            alignment
+        }
+        None => match compute_alignment(
+            &selected_indices,
+            &selected_frames,
             alignment_config,
-            backend.as_ref(),
-        )
-        .unwrap_or_default();
-        offsets.push(offset);
-        send(
+            device,
+            tx,
+            ctx,
+        ) {
+            Ok(alignment) => alignment,
+            Err(e) => {
+                send_error(tx, ctx, format!("Alignment failed: {e}"));
+                return;
+            }
+        },
+    };
+    if !alignment.rejected.is_empty() {
+        send_log(
             tx,
             ctx,
-            WorkerResult::Progress {
-                stage: PipelineStage::Alignment,
-                items_done: Some(i + 1),
-                items_total: Some(frame_count),
-            },
+            format!(
+                "Rejected {} misaligned frame(s): {:?}",
+                alignment.rejected.len(),
+                alignment.rejected
+            ),
         );
     }
 
-    // Cache alignment results
+    if let Some((min, median)) = alignment.confidence_summary() {
+        send_log(
+            tx,
+            ctx,
+            format!("Alignment confidence: min {min:.1}, median {median:.1}"),
+        );
+    }
+
+    // Cache alignment results (rejected frames are dropped here)
+    let (selected_indices, offsets) = alignment.kept();
+    let mut selected_frames = selected_frames;
+    let mut selected_color = selected_color;
+    let mut quality_scores = quality_scores;
+    alignment.retain_kept(&mut selected_frames);
+    if let Some(ref mut color) = selected_color {
+        alignment.retain_kept(color);
+    }
+    alignment.retain_kept(&mut quality_scores);
+    let mut confidences = alignment.confidence_values();
+    alignment.retain_kept(&mut confidences);
+    let frame_count = selected_frames.len();
+    cache.selected_indices = Some(selected_indices);
     cache.selected_frames = Some(selected_frames);
     cache.selected_color_frames = selected_color;
     cache.alignment_offsets = Some(offsets);
+    cache.alignment_confidences = Some(confidences);
     cache.selected_quality_scores = Some(quality_scores);
     cache.invalidate_from_stack();
+    session::remember_alignment(cache, alignment_key, alignment, tx, ctx);
 
     let elapsed = start.elapsed();
     send_log(
@@ -197,3 +222,59 @@ pub(super) fn handle_align(
         },
     );
 }
+
+/// Offsets of every frame relative to the first, with per-frame progress,
+/// checked for outliers when the config asks for it.
+fn compute_alignment(
+    selected_indices: &[usize],
+    selected_frames: &[Frame],
+    alignment_config: &AlignmentConfig,
+    device: &DevicePreference,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) -> Result<CachedAlignment> {
+    let backend = create_backend(device);
+    let progress = make_progress_callback(tx, ctx, PipelineStage::Alignment, selected_frames.len());
+    let offsets = compute_offsets_configured_with_progress(
+        selected_frames,
+        0,
+        alignment_config,
+        backend.clone(),
+        progress,
+    )?;
+    // Confidences are measured even without rejection: the frame report shows them.
+    CachedAlignment::checked(
+        selected_indices,
+        offsets,
+        |k| Ok(selected_frames[k].data.clone()),
+        alignment_config,
+        backend.as_ref(),
+        true,
+    )
+}
+
+/// Read `indices` from disk; `debayer_method` is `Some` for colour sources,
+/// in which case luminance frames are returned alongside the colour frames.
+pub(super) fn read_selected_frames(
+    reader: &SerReader,
+    indices: &[usize],
+    debayer_method: Option<&DebayerMethod>,
+) -> Result<(Vec<Frame>, Option<Vec<ColorFrame>>)> {
+    match debayer_method {
+        Some(method) => {
+            let color_frames: Vec<ColorFrame> = indices
+                .iter()
+                .map(|&i| reader.read_frame_as_color(i, method))
+                .collect::<Result<_>>()?;
+            let lum_frames = color_frames.iter().map(luminance).collect();
+            Ok((lum_frames, Some(color_frames)))
+        }
+        None => {
+            let frames = indices
+                .iter()
+                .map(|&i| reader.read_frame(i))
+                .collect::<Result<_>>()?;
+            Ok((frames, None))
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use std::path::PathBuf;
 
 use jupiter_core::frame::{AlignmentOffset, ColorFrame, ColorMode, Frame, QualityScore};
+use jupiter_core::pipeline::session::{SessionCache, StageKey};
 use jupiter_core::pipeline::PipelineOutput;
+use jupiter_core::stack::ap_diagnostics::ApDiagnostics;
 
 use jupiter_core::color::debayer::DebayerMethod;
 
@@ -17,18 +19,29 @@ pub(crate) struct PipelineCache {
     pub(crate) all_frames: Option<Vec<Frame>>,
     pub(crate) all_color_frames: Option<Vec<ColorFrame>>,
     pub(crate) ranked: Option<Vec<(usize, QualityScore)>>,
+    /// Frame indices chosen by the Align stage, best first.
+    pub(crate) selected_indices: Option<Vec<usize>>,
     /// Selected + aligned data (from Align stage).
     pub(crate) selected_frames: Option<Vec<Frame>>,
     pub(crate) selected_color_frames: Option<Vec<ColorFrame>>,
     pub(crate) alignment_offsets: Option<Vec<AlignmentOffset>>,
+    /// Registration confidence of each selected frame (NaN for the reference).
+    pub(crate) alignment_confidences: Option<Vec<f64>>,
     /// Quality scores for the selected frames (same order as selected_frames).
     pub(crate) selected_quality_scores: Option<Vec<f64>>,
     /// Result after stacking (mono or color).
     pub(crate) stacked: Option<PipelineOutput>,
+    /// AP diagnostics of the stacked result (multi-point and surface warp).
+    pub(crate) ap_diagnostics: Option<ApDiagnostics>,
     /// Result after sharpening.
     pub(crate) sharpened: Option<PipelineOutput>,
     /// Result after filtering (final output).
     pub(crate) filtered: Option<PipelineOutput>,
+    /// On-disk session of the current file, if it could be opened.
+    pub(crate) session: Option<SessionCache>,
+    /// Session keys of the cached ranking and alignment.
+    pub(crate) quality_key: Option<StageKey>,
+    pub(crate) alignment_key: Option<StageKey>,
 }
 
 impl PipelineCache {
@@ -42,13 +55,19 @@ impl PipelineCache {
             all_frames: None,
             all_color_frames: None,
             ranked: None,
+            selected_indices: None,
             selected_frames: None,
             selected_color_frames: None,
             alignment_offsets: None,
+            alignment_confidences: None,
             selected_quality_scores: None,
             stacked: None,
+            ap_diagnostics: None,
             sharpened: None,
             filtered: None,
+            session: None,
+            quality_key: None,
+            alignment_key: None,
         }
     }
 
@@ -68,15 +87,19 @@ impl PipelineCache {
     }
 
     pub(crate) fn invalidate_downstream(&mut self) {
+        self.selected_indices = None;
         self.selected_frames = None;
         self.selected_color_frames = None;
         self.alignment_offsets = None;
+        self.alignment_confidences = None;
         self.selected_quality_scores = None;
+        self.alignment_key = None;
         self.invalidate_from_stack();
     }
 
     pub(crate) fn invalidate_from_stack(&mut self) {
         self.stacked = None;
+        self.ap_diagnostics = None;
         self.sharpened = None;
         self.filtered = None;
     }
@@ -5,7 +5,7 @@ use jupiter_core::pipeline::PipelineStage;
 use crate::messages::{WorkerCommand, WorkerResult};
 
 use super::cache::PipelineCache;
-use super::{align, io, pipeline, postprocess, scoring, stacking};
+use super::{align, io, pipeline, postprocess, scoring, session, stacking};
 
 /// Spawn the worker thread. Returns the command sender.
 pub fn spawn_worker(
@@ -99,22 +99,28 @@ fn worker_loop(
                 scoring::handle_load_and_score(&path, &metric, &debayer, &mut cache, &tx, &ctx);
             }
             WorkerCommand::Align {
-                select_percentage,
+                selection,
                 alignment,
                 device,
             } => {
-                align::handle_align(
-                    select_percentage,
+                align::handle_align(&selection, &alignment, &device, &mut cache, &tx, &ctx);
+            }
+            WorkerCommand::Stack {
+                stacking,
+                alignment,
+                frame_list,
+                device,
+            } => {
+                stacking::handle_stack(
+                    &stacking,
                     &alignment,
+                    frame_list.as_deref(),
                     &device,
                     &mut cache,
                     &tx,
                     &ctx,
                 );
             }
-            WorkerCommand::Stack { method } => {
-                stacking::handle_stack(&method, &mut cache, &tx, &ctx);
-            }
             WorkerCommand::Sharpen { config, device } => {
                 postprocess::handle_sharpen(&config, &device, &mut cache, &tx, &ctx);
             }
@@ -124,9 +130,18 @@ fn worker_loop(
             WorkerCommand::RunAll { config } => {
                 pipeline::handle_run_all(&config, &mut cache, &tx, &ctx);
             }
+            WorkerCommand::RestoreSession { config } => {
+                session::handle_restore_session(&config, &mut cache, &tx, &ctx);
+            }
+            WorkerCommand::RememberSettings { config } => {
+                session::handle_remember_settings(&config, &mut cache, &tx, &ctx);
+            }
             WorkerCommand::SaveImage { path } => {
                 io::handle_save_image(&path, &cache, &tx, &ctx);
             }
+            WorkerCommand::ExportFrameReport { path, metric } => {
+                io::handle_export_frame_report(&path, &metric, &cache, &tx, &ctx);
+            }
             WorkerCommand::CropAndSave {
                 source_path,
                 output_path,
@@ -143,6 +158,9 @@ fn worker_loop(
             WorkerCommand::AutoCropAndSave { source_path } => {
                 io::handle_auto_crop_and_save(&source_path, &tx, &ctx);
             }
+            WorkerCommand::Compose { inputs, config } => {
+                io::handle_compose(&inputs, &config, &mut cache, &tx, &ctx);
+            }
         }
     }
 }
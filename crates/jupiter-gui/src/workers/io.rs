@@ -4,15 +4,18 @@ use std::time::Instant;
 
 use std::path::PathBuf;
 
+use jupiter_core::color::compose::compose;
 use jupiter_core::color::debayer::{is_bayer, DebayerMethod};
 use jupiter_core::frame::ColorMode;
 use jupiter_core::io::autocrop::{auto_detect_crop, AutoCropConfig};
 use jupiter_core::io::crop::{crop_ser, CropRect};
+use jupiter_core::io::frame_report::FrameReport;
 use jupiter_core::io::image_io::{
     crop_color_frame, crop_frame, is_color_image, load_color_image, load_image, save_color_image,
     save_image,
 };
 use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::{ChannelRole, CompositionConfig, QualityMetric};
 use jupiter_core::pipeline::{PipelineOutput, PipelineStage};
 
 use crate::messages::WorkerResult;
@@ -126,6 +129,44 @@ pub(super) fn handle_save_image(
     }
 }
 
+pub(super) fn handle_export_frame_report(
+    path: &Path,
+    metric: &QualityMetric,
+    cache: &PipelineCache,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) {
+    let ranked = match &cache.ranked {
+        Some(r) => r,
+        None => {
+            send_error(tx, ctx, "Frames not scored. Run Score Frames first.");
+            return;
+        }
+    };
+
+    let mut report = FrameReport::new(ranked.len());
+    if let Some(reader) = cache
+        .file_path
+        .as_ref()
+        .and_then(|p| SerReader::open(p).ok())
+    {
+        report.record_timestamps(&reader);
+    }
+    report.record_scores(metric, ranked);
+    if let Some(indices) = &cache.selected_indices {
+        report.mark_selected(indices);
+        if let Some(offsets) = &cache.alignment_offsets {
+            let confidences = cache.alignment_confidences.as_deref();
+            report.record_offsets(indices, offsets, confidences);
+        }
+    }
+
+    match report.save(path) {
+        Ok(()) => send_log(tx, ctx, format!("Frame report saved to {}", path.display())),
+        Err(e) => send_error(tx, ctx, format!("Failed to export frame report: {e}")),
+    }
+}
+
 pub(super) fn handle_load_image_file(
     path: &Path,
     cache: &mut PipelineCache,
@@ -158,15 +199,78 @@ pub(super) fn handle_load_image_file(
         }
     };
 
+    open_single_image(path, output, cache, tx, ctx);
+}
+
+/// Load the images of a composition, combine them and open the result as
+/// if it were an image file next to the first input.
+pub(super) fn handle_compose(
+    inputs: &[(ChannelRole, PathBuf)],
+    config: &CompositionConfig,
+    cache: &mut PipelineCache,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) {
+    let start = Instant::now();
+    let mut images = Vec::with_capacity(inputs.len());
+    for (role, path) in inputs {
+        match load_image(path) {
+            Ok(frame) => images.push((*role, frame)),
+            Err(e) => {
+                send_error(tx, ctx, format!("Failed to load {}: {e}", path.display()));
+                return;
+            }
+        }
+    }
+
+    let color = match compose(&images, config) {
+        Ok(color) => color,
+        Err(e) => {
+            send_error(tx, ctx, format!("Composition failed: {e}"));
+            return;
+        }
+    };
+    let roles: Vec<String> = inputs.iter().map(|(role, _)| role.to_string()).collect();
+    send_log(
+        tx,
+        ctx,
+        format!(
+            "Composed {} in {:.1}s",
+            roles.join("+"),
+            start.elapsed().as_secs_f32()
+        ),
+    );
+
+    let dir = inputs
+        .first()
+        .and_then(|(_, path)| path.parent())
+        .unwrap_or(Path::new(""));
+    open_single_image(
+        &dir.join("composite.tiff"),
+        PipelineOutput::Color(color),
+        cache,
+        tx,
+        ctx,
+    );
+}
+
+/// Store a single image as the stacked result, so the sharpen and filter
+/// stages can work on it, and show it.
+fn open_single_image(
+    path: &Path,
+    output: PipelineOutput,
+    cache: &mut PipelineCache,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) {
     let (w, h) = match &output {
         PipelineOutput::Mono(f) => (f.width() as u32, f.height() as u32),
         PipelineOutput::Color(cf) => (cf.red.width() as u32, cf.red.height() as u32),
     };
 
-    // Store the loaded image as "stacked" so sharpen/filter stages can work on it.
     *cache = PipelineCache::new();
     cache.file_path = Some(path.to_path_buf());
-    cache.is_color = is_color;
+    cache.is_color = matches!(output, PipelineOutput::Color(_));
     cache.set_stacked(output.clone());
 
     send(
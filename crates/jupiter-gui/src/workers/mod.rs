@@ -5,6 +5,7 @@ mod io;
 mod pipeline;
 mod postprocess;
 mod scoring;
+mod session;
 mod stacking;
 
 pub(crate) use cache::PipelineCache;
@@ -3,12 +3,12 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use jupiter_core::compute::create_backend;
-use jupiter_core::pipeline::{run_pipeline_reported, PipelineOutput};
+use jupiter_core::pipeline::{run_pipeline_cached, run_pipeline_reported, PipelineOutput};
 
 use crate::messages::WorkerResult;
 use crate::progress::ChannelProgressReporter;
 
-use super::{send, send_error, send_log, PipelineCache};
+use super::{send, send_error, send_log, session, PipelineCache};
 
 pub(super) fn handle_run_all(
     config: &jupiter_core::pipeline::config::PipelineConfig,
@@ -22,12 +22,20 @@ pub(super) fn handle_run_all(
     let backend = create_backend(&config.device);
     let reporter = Arc::new(ChannelProgressReporter::new(tx.clone(), ctx.clone()));
 
-    match run_pipeline_reported(config, backend, reporter) {
+    let result = match session::session_for(cache, &config.input, tx, ctx) {
+        Some(session) => run_pipeline_cached(config, backend, reporter, session),
+        None => run_pipeline_reported(config, backend, reporter),
+    };
+    match result {
         Ok(output) => {
             let elapsed = start.elapsed();
             cache.file_path = Some(config.input.clone());
             cache.is_color = matches!(&output, PipelineOutput::Color(_));
             cache.stacked = Some(output.clone());
+            cache.ap_diagnostics = cache
+                .session
+                .as_ref()
+                .and_then(|s| s.last_ap_diagnostics().cloned());
             cache.sharpened = if config.sharpening.is_some() {
                 Some(output.clone())
             } else {
@@ -43,6 +51,7 @@ pub(super) fn handle_run_all(
                 ctx,
                 format!("Pipeline complete in {:.1}s", elapsed.as_secs_f32()),
             );
+            super::stacking::send_ap_diagnostics(cache, tx, ctx);
             send(
                 tx,
                 ctx,
@@ -1,11 +1,13 @@
 use std::sync::mpsc;
 use std::time::Instant;
 
-use jupiter_core::color::process::process_color_parallel;
+use jupiter_core::color::process::try_process_color_parallel;
 use jupiter_core::compute::create_backend;
 use jupiter_core::pipeline::config::{FilterStep, SharpeningConfig};
-use jupiter_core::pipeline::{apply_filter_step, PipelineOutput, PipelineStage};
-use jupiter_core::sharpen::deconvolution::{deconvolve, deconvolve_gpu};
+use jupiter_core::pipeline::{
+    apply_filter_step, apply_filter_step_color, PipelineOutput, PipelineStage,
+};
+use jupiter_core::sharpen::deconvolution::{deconvolve_gpu, deconvolve_loaded, Psf};
 use jupiter_core::sharpen::wavelet;
 
 use crate::messages::WorkerResult;
@@ -33,16 +35,30 @@ pub(super) fn handle_sharpen(
 
     let backend = create_backend(device);
 
-    let sharpen_mono = |frame: &jupiter_core::frame::Frame| -> jupiter_core::frame::Frame {
-        let mut result = frame.clone();
-        if let Some(ref deconv_config) = config.deconvolution {
-            if backend.is_gpu() {
-                result = deconvolve_gpu(&result, deconv_config, &*backend);
-            } else {
-                result = deconvolve(&result, deconv_config);
+    let psf = match config
+        .deconvolution
+        .as_ref()
+        .map(|d| Psf::load(&d.psf))
+        .transpose()
+    {
+        Ok(psf) => psf,
+        Err(e) => {
+            send_error(tx, ctx, format!("Sharpening failed: {e}"));
+            return;
+        }
+    };
+    let deconvolve_mono = |frame: &jupiter_core::frame::Frame| -> jupiter_core::error::Result<
+        jupiter_core::frame::Frame,
+    > {
+        match (&config.deconvolution, &psf) {
+            (Some(deconv_config), Some(psf)) if backend.is_gpu() => {
+                deconvolve_gpu(frame, deconv_config, psf, &*backend)
+            }
+            (Some(deconv_config), Some(psf)) => {
+                deconvolve_loaded(frame, deconv_config, psf).map(|(restored, _)| restored)
             }
+            _ => Ok(frame.clone()),
         }
-        wavelet::sharpen(&result, &config.wavelet)
     };
 
     let stacked = match &cache.stacked {
@@ -54,10 +70,18 @@ pub(super) fn handle_sharpen(
     };
 
     let output = match stacked {
-        PipelineOutput::Color(cf) => {
-            PipelineOutput::Color(process_color_parallel(&cf, |frame| sharpen_mono(frame)))
+        PipelineOutput::Color(cf) => try_process_color_parallel(&cf, deconvolve_mono)
+            .map(|cf| PipelineOutput::Color(wavelet::sharpen_color(&cf, &config.wavelet))),
+        PipelineOutput::Mono(f) => {
+            deconvolve_mono(&f).map(|f| PipelineOutput::Mono(wavelet::sharpen(&f, &config.wavelet)))
+        }
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            send_error(tx, ctx, format!("Sharpening failed: {e}"));
+            return;
         }
-        PipelineOutput::Mono(f) => PipelineOutput::Mono(sharpen_mono(&f)),
     };
 
     let elapsed = start.elapsed();
@@ -113,11 +137,7 @@ pub(super) fn handle_apply_filters(
     let mut output = base;
     for (i, step) in filters.iter().enumerate() {
         output = match output {
-            PipelineOutput::Color(cf) => {
-                PipelineOutput::Color(process_color_parallel(&cf, |frame| {
-                    apply_filter_step(frame, step)
-                }))
-            }
+            PipelineOutput::Color(cf) => PipelineOutput::Color(apply_filter_step_color(&cf, step)),
             PipelineOutput::Mono(f) => PipelineOutput::Mono(apply_filter_step(&f, step)),
         };
         send(
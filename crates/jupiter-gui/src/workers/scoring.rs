@@ -19,7 +19,7 @@ use jupiter_core::quality::laplacian::{
 
 use crate::messages::WorkerResult;
 
-use super::{make_progress_callback, send, send_error, send_log, PipelineCache};
+use super::{make_progress_callback, send, send_error, send_log, session, PipelineCache};
 
 pub(super) fn handle_load_and_score(
     path: &Path,
@@ -63,6 +63,10 @@ pub(super) fn handle_load_and_score(
         * total;
     let use_streaming = decoded_bytes > LOW_MEMORY_THRESHOLD_BYTES;
 
+    let quality_key =
+        session::quality_key(cache, path, metric, debayer_config, &color_mode, tx, ctx);
+    let cached_ranking = session::cached_ranking(cache, quality_key.as_ref());
+
     if use_streaming {
         let debayer_method = debayer_config
             .as_ref()
@@ -88,7 +92,10 @@ pub(super) fn handle_load_and_score(
         let streaming_progress =
             make_progress_callback(tx, ctx, PipelineStage::QualityAssessment, total);
 
-        let ranked = if use_color {
+        let ranked = if let Some(ranked) = cached_ranking {
+            send_log(tx, ctx, "Reusing cached quality scores");
+            ranked
+        } else if use_color {
             match metric {
                 QualityMetric::Laplacian => {
                     match rank_frames_color_streaming_with_progress(
@@ -169,6 +176,7 @@ pub(super) fn handle_load_and_score(
         cache.all_color_frames = None;
         cache.ranked = Some(ranked);
         cache.invalidate_downstream();
+        session::remember_ranking(cache, quality_key, tx, ctx);
 
         send_log(
             tx,
@@ -259,9 +267,15 @@ pub(super) fn handle_load_and_score(
 
     let eager_progress = make_progress_callback(tx, ctx, PipelineStage::QualityAssessment, total);
 
-    let ranked = match metric {
-        QualityMetric::Laplacian => rank_frames_with_progress(&scoring_frames, &eager_progress),
-        QualityMetric::Gradient => {
+    let ranked = match (cached_ranking, metric) {
+        (Some(ranked), _) => {
+            send_log(tx, ctx, "Reusing cached quality scores");
+            ranked
+        }
+        (None, QualityMetric::Laplacian) => {
+            rank_frames_with_progress(&scoring_frames, &eager_progress)
+        }
+        (None, QualityMetric::Gradient) => {
             rank_frames_gradient_with_progress(&scoring_frames, &eager_progress)
         }
     };
@@ -281,6 +295,7 @@ pub(super) fn handle_load_and_score(
     cache.all_color_frames = color_frames;
     cache.ranked = Some(ranked);
     cache.invalidate_downstream();
+    session::remember_ranking(cache, quality_key, tx, ctx);
 
     send_log(tx, ctx, format!("Scored {total} frames"));
     send(
@@ -295,7 +310,7 @@ pub(super) fn handle_load_and_score(
 }
 
 /// Run planet detection on a single frame and return the diameter (max of bbox width/height).
-fn detect_planet_diameter(frame: &Frame) -> Option<usize> {
+pub(super) fn detect_planet_diameter(frame: &Frame) -> Option<usize> {
     let config = DetectionConfig::default();
     let detection = detect_planet_in_frame(&frame.data, 0, &config)?;
     Some(detection.bbox_width.max(detection.bbox_height))
@@ -0,0 +1,270 @@
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Instant;
+
+use jupiter_core::frame::{ColorMode, QualityScore};
+use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::{DebayerConfig, PipelineConfig, QualityMetric};
+use jupiter_core::pipeline::resolve_debayer;
+use jupiter_core::pipeline::session::{CachedAlignment, SessionCache, StageKey};
+
+use crate::messages::WorkerResult;
+
+use super::align::read_selected_frames;
+use super::scoring::detect_planet_diameter;
+use super::{send, send_log, PipelineCache};
+
+/// Session for `path`, opening it if the cache holds none or another file's.
+/// Failures are logged and disable caching for this call.
+pub(super) fn session_for<'a>(
+    cache: &'a mut PipelineCache,
+    path: &Path,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) -> Option<&'a mut SessionCache> {
+    let stale = cache
+        .session
+        .as_ref()
+        .is_none_or(|s| s.dir() != SessionCache::sidecar_dir(path));
+    if stale {
+        match SessionCache::open(path) {
+            Ok(s) => cache.session = Some(s),
+            Err(e) => {
+                send_log(tx, ctx, format!("Session cache unavailable: {e}"));
+                cache.session = None;
+            }
+        }
+    }
+    cache.session.as_mut()
+}
+
+/// Write the session manifest, logging (not failing) on error.
+pub(super) fn persist(cache: &PipelineCache, tx: &mpsc::Sender<WorkerResult>, ctx: &egui::Context) {
+    if let Some(Err(e)) = cache.session.as_ref().map(SessionCache::save) {
+        send_log(tx, ctx, format!("Failed to save session: {e}"));
+    }
+}
+
+/// Quality key for scoring `path` with the given metric and colour handling
+/// (`debayer` is `None` when colour processing is off).
+pub(super) fn quality_key(
+    cache: &mut PipelineCache,
+    path: &Path,
+    metric: &QualityMetric,
+    debayer: &Option<DebayerConfig>,
+    color_mode: &ColorMode,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) -> Option<StageKey> {
+    let method = resolve_debayer(debayer.as_ref(), debayer.is_none(), color_mode);
+    session_for(cache, path, tx, ctx).map(|s| s.quality_key(metric, method.as_ref()))
+}
+
+/// Cached ranking stored under `key`, if any.
+pub(super) fn cached_ranking(
+    cache: &PipelineCache,
+    key: Option<&StageKey>,
+) -> Option<Vec<(usize, QualityScore)>> {
+    let key = key?;
+    cache.session.as_ref()?.ranking(key).map(<[_]>::to_vec)
+}
+
+/// Record the current ranking under `key` and save the session if it changed.
+pub(super) fn remember_ranking(
+    cache: &mut PipelineCache,
+    key: Option<StageKey>,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) {
+    cache.quality_key = key.clone();
+    let (Some(key), Some(ranked), Some(session)) =
+        (key, cache.ranked.clone(), cache.session.as_mut())
+    else {
+        return;
+    };
+    if session.ranking(&key).is_none() {
+        session.store_ranking(key, ranked);
+        persist(cache, tx, ctx);
+    }
+}
+
+/// Record `alignment` under `key` and save the session.
+pub(super) fn remember_alignment(
+    cache: &mut PipelineCache,
+    key: Option<StageKey>,
+    alignment: CachedAlignment,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) {
+    cache.alignment_key = key.clone();
+    let (Some(key), Some(session)) = (key, cache.session.as_mut()) else {
+        return;
+    };
+    session.store_alignment(key, alignment);
+    persist(cache, tx, ctx);
+}
+
+/// Remember the UI settings so the next open of this file can restore them.
+pub(super) fn handle_remember_settings(
+    config: &PipelineConfig,
+    cache: &mut PipelineCache,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) {
+    if let Some(session) = session_for(cache, &config.input, tx, ctx) {
+        session.store_settings(config);
+        persist(cache, tx, ctx);
+    }
+}
+
+/// Restore cached stages for a freshly opened file.
+///
+/// The settings stored in the session take precedence over `config` (the
+/// current UI state); each stage is restored only if every stage before it
+/// is cached too.
+pub(super) fn handle_restore_session(
+    config: &PipelineConfig,
+    cache: &mut PipelineCache,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) {
+    let path = config.input.clone();
+    let Ok(reader) = SerReader::open(&path) else {
+        return;
+    };
+    let Some(session) = session_for(cache, &path, tx, ctx) else {
+        return;
+    };
+    let config = match session.settings() {
+        Some(stored) => {
+            let mut stored = stored.clone();
+            stored.input = path.clone();
+            stored.output = config.output.clone();
+            stored
+        }
+        None => config.clone(),
+    };
+    let color_mode = reader.header.color_mode();
+    let debayer = resolve_debayer(config.debayer.as_ref(), config.force_mono, &color_mode);
+    let Ok(keys) = session.keys(&config, debayer.as_ref(), false) else {
+        return;
+    };
+    let Some(ranked) = session.ranking(&keys.quality).map(<[_]>::to_vec) else {
+        return;
+    };
+    let alignment = session.alignment(&keys.alignment).cloned();
+    let stacked = alignment
+        .as_ref()
+        .and_then(|_| session.stacked(&keys.stack));
+    let ap_diagnostics = session.ap_diagnostics(&keys.stack).cloned();
+    let restored_settings = session.settings().is_some();
+
+    if restored_settings {
+        send(
+            tx,
+            ctx,
+            WorkerResult::SessionRestored {
+                config: config.clone(),
+            },
+        );
+    }
+
+    // Scores: restored in streaming mode, frames are re-read on demand.
+    let total = reader.frame_count();
+    let ranked_preview: Vec<(usize, f64)> = ranked.iter().map(|(i, s)| (*i, s.composite)).collect();
+    let detected_planet_diameter = reader
+        .read_frame(0)
+        .ok()
+        .and_then(|f| detect_planet_diameter(&f));
+
+    cache.file_path = Some(path.clone());
+    cache.is_color = debayer.is_some();
+    cache.is_streaming = true;
+    cache.color_mode = debayer.as_ref().map(|_| color_mode.clone());
+    cache.debayer_method = debayer;
+    cache.all_frames = None;
+    cache.all_color_frames = None;
+    cache.ranked = Some(ranked.clone());
+    cache.invalidate_downstream();
+    cache.quality_key = Some(keys.quality);
+
+    send_log(
+        tx,
+        ctx,
+        format!("Restored session from {}", cache_dir(cache)),
+    );
+    send(
+        tx,
+        ctx,
+        WorkerResult::LoadAndScoreComplete {
+            frame_count: total,
+            ranked_preview,
+            detected_planet_diameter,
+        },
+    );
+
+    // Alignment: reload the selected frames and reuse the offsets.
+    let Some(alignment) = alignment else {
+        return;
+    };
+    let start = Instant::now();
+    let (selected, offsets) = alignment.kept();
+    let mut confidences = alignment.confidence_values();
+    alignment.retain_kept(&mut confidences);
+    let (frames, color_frames) =
+        match read_selected_frames(&reader, &selected, cache.debayer_method.as_ref()) {
+            Ok(f) => f,
+            Err(e) => {
+                send_log(tx, ctx, format!("Could not restore aligned frames: {e}"));
+                return;
+            }
+        };
+    let quality_scores: Vec<f64> = selected
+        .iter()
+        .map(|i| {
+            ranked
+                .iter()
+                .find(|(j, _)| j == i)
+                .map_or(0.0, |(_, s)| s.composite)
+        })
+        .collect();
+    let frame_count = frames.len();
+    cache.selected_indices = Some(selected);
+    cache.selected_frames = Some(frames);
+    cache.selected_color_frames = color_frames;
+    cache.alignment_offsets = Some(offsets);
+    cache.alignment_confidences = Some(confidences);
+    cache.selected_quality_scores = Some(quality_scores);
+    cache.alignment_key = Some(keys.alignment);
+    send(
+        tx,
+        ctx,
+        WorkerResult::AlignComplete {
+            frame_count,
+            elapsed: start.elapsed(),
+        },
+    );
+
+    // Stack
+    if let Some(stacked) = stacked {
+        cache.set_stacked(stacked.clone());
+        cache.ap_diagnostics = ap_diagnostics;
+        super::stacking::send_ap_diagnostics(cache, tx, ctx);
+        send(
+            tx,
+            ctx,
+            WorkerResult::StackComplete {
+                result: stacked,
+                elapsed: start.elapsed(),
+            },
+        );
+    }
+}
+
+fn cache_dir(cache: &PipelineCache) -> String {
+    cache
+        .session
+        .as_ref()
+        .map(|s| s.dir().display().to_string())
+        .unwrap_or_default()
+}
@@ -1,34 +1,123 @@
 mod drizzle;
 mod multi_point;
+mod optical_flow;
 mod standard;
+mod super_resolution;
 mod surface_warp;
 
+use std::path::Path;
 use std::sync::mpsc;
+use std::time::Instant;
 
-use jupiter_core::pipeline::config::StackMethod;
+use jupiter_core::compute::DevicePreference;
+use jupiter_core::io::frame_report::FrameReport;
+use jupiter_core::pipeline::config::{AlignmentConfig, StackMethod, StackingConfig};
+use jupiter_core::pipeline::session::SessionCache;
 
 use crate::messages::WorkerResult;
 
-use super::PipelineCache;
+use super::{send, send_error, send_log, session, PipelineCache};
 
 pub(crate) fn handle_stack(
-    method: &StackMethod,
+    stacking: &StackingConfig,
+    alignment: &AlignmentConfig,
+    frame_list: Option<&Path>,
+    device: &DevicePreference,
     cache: &mut PipelineCache,
     tx: &mpsc::Sender<WorkerResult>,
     ctx: &egui::Context,
 ) {
-    match method {
+    let start = Instant::now();
+    // The GUI always stacks in memory, so its stacks are exact.
+    let stack_key = cache
+        .alignment_key
+        .as_ref()
+        .map(|alignment| SessionCache::stack_key(alignment, stacking, false));
+    let cached = stack_key
+        .as_ref()
+        .and_then(|key| cache.session.as_ref()?.stacked(key));
+    if let Some(stacked) = cached {
+        send_log(tx, ctx, "Reusing cached stack");
+        cache.set_stacked(stacked.clone());
+        cache.ap_diagnostics = stack_key
+            .as_ref()
+            .and_then(|key| cache.session.as_ref()?.ap_diagnostics(key).cloned());
+        send_ap_diagnostics(cache, tx, ctx);
+        send(
+            tx,
+            ctx,
+            WorkerResult::StackComplete {
+                result: stacked,
+                elapsed: start.elapsed(),
+            },
+        );
+        return;
+    }
+
+    // Drop the previous result so a failed run is not cached under this key.
+    cache.invalidate_from_stack();
+    // The Align stage applied the frame list to the other methods already.
+    let method = match frame_list {
+        Some(path) if stacking.method.selects_own_frames() => match FrameReport::load(path) {
+            Ok(report) => stacking.method.with_frames(report.selected_indices()),
+            Err(e) => {
+                send_error(tx, ctx, format!("Invalid frame list: {e}"));
+                return;
+            }
+        },
+        _ => stacking.method.clone(),
+    };
+    match &method {
         StackMethod::MultiPoint(ref mp_config) => {
-            multi_point::handle_multi_point(mp_config, cache, tx, ctx);
+            multi_point::handle_multi_point(mp_config, alignment, device, cache, tx, ctx);
         }
         StackMethod::SurfaceWarp(ref sw_config) => {
-            surface_warp::handle_surface_warp(sw_config, cache, tx, ctx);
+            surface_warp::handle_surface_warp(sw_config, alignment, device, cache, tx, ctx);
+        }
+        StackMethod::OpticalFlow(ref flow_config) => {
+            optical_flow::handle_optical_flow(flow_config, alignment, device, cache, tx, ctx);
         }
         StackMethod::Drizzle(ref drizzle_config) => {
             drizzle::handle_drizzle(drizzle_config, cache, tx, ctx);
         }
-        method @ (StackMethod::Mean | StackMethod::Median | StackMethod::SigmaClip(_)) => {
-            standard::handle_standard(method, cache, tx, ctx);
+        StackMethod::SuperResolution(ref sr_config) => {
+            super_resolution::handle_super_resolution(sr_config, device, cache, tx, ctx);
+        }
+        method @ (StackMethod::Mean
+        | StackMethod::Median
+        | StackMethod::SigmaClip(_)
+        | StackMethod::WinsorizedSigmaClip(_)
+        | StackMethod::LinearFitClip(_)
+        | StackMethod::Gesd(_)) => {
+            let weights = cache
+                .selected_quality_scores
+                .clone()
+                .filter(|_| stacking.quality_weighted);
+            standard::handle_standard(method, weights.as_deref(), cache, tx, ctx);
+        }
+    }
+
+    if let (Some(key), Some(stacked), Some(session)) =
+        (stack_key, cache.stacked.as_ref(), cache.session.as_mut())
+    {
+        if let Err(e) = session.store_stack(key, stacked, cache.ap_diagnostics.clone()) {
+            send_log(tx, ctx, format!("Failed to cache stack: {e}"));
         }
+        session::persist(cache, tx, ctx);
     }
+    send_ap_diagnostics(cache, tx, ctx);
+}
+
+pub(super) fn send_ap_diagnostics(
+    cache: &PipelineCache,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) {
+    send(
+        tx,
+        ctx,
+        WorkerResult::ApDiagnostics {
+            diagnostics: cache.ap_diagnostics.clone(),
+        },
+    );
 }
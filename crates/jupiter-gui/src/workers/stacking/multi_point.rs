@@ -1,11 +1,13 @@
 use std::sync::mpsc;
 use std::time::Instant;
 
+use jupiter_core::compute::{create_backend, DevicePreference};
 use jupiter_core::frame::ColorMode;
 use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::AlignmentConfig;
 use jupiter_core::pipeline::{PipelineOutput, PipelineStage};
 use jupiter_core::stack::multi_point::{
-    multi_point_stack, multi_point_stack_color, MultiPointConfig,
+    multi_point_stack_color_with_diagnostics, multi_point_stack_with_diagnostics, MultiPointConfig,
 };
 
 use crate::messages::WorkerResult;
@@ -14,6 +16,8 @@ use super::super::{send, send_error, send_log, PipelineCache};
 
 pub(crate) fn handle_multi_point(
     mp_config: &MultiPointConfig,
+    alignment: &AlignmentConfig,
+    device: &DevicePreference,
     cache: &mut PipelineCache,
     tx: &mpsc::Sender<WorkerResult>,
     ctx: &egui::Context,
@@ -44,6 +48,8 @@ pub(crate) fn handle_multi_point(
         }
     };
 
+    let backend = create_backend(device);
+
     if cache.is_color {
         let color_mode = match reader.header.color_mode() {
             ColorMode::Mono => {
@@ -53,11 +59,20 @@ pub(crate) fn handle_multi_point(
             mode => mode,
         };
         let debayer_method = cache.debayer_method.unwrap_or_default();
-        match multi_point_stack_color(&reader, mp_config, &color_mode, &debayer_method, |_| {}) {
-            Ok(result) => {
+        match multi_point_stack_color_with_diagnostics(
+            &reader,
+            mp_config,
+            alignment,
+            backend.as_ref(),
+            &color_mode,
+            &debayer_method,
+            |_| {},
+        ) {
+            Ok((result, diagnostics)) => {
                 let elapsed = start.elapsed();
                 let output = PipelineOutput::Color(result);
                 cache.set_stacked(output.clone());
+                cache.ap_diagnostics = Some(diagnostics);
                 send_log(
                     tx,
                     ctx,
@@ -78,11 +93,18 @@ pub(crate) fn handle_multi_point(
             Err(e) => send_error(tx, ctx, format!("Multi-point color stacking failed: {e}")),
         }
     } else {
-        match multi_point_stack(&reader, mp_config, |_| {}) {
-            Ok(result) => {
+        match multi_point_stack_with_diagnostics(
+            &reader,
+            mp_config,
+            alignment,
+            backend.as_ref(),
+            |_| {},
+        ) {
+            Ok((result, diagnostics)) => {
                 let elapsed = start.elapsed();
                 let output = PipelineOutput::Mono(result);
                 cache.set_stacked(output.clone());
+                cache.ap_diagnostics = Some(diagnostics);
                 send_log(
                     tx,
                     ctx,
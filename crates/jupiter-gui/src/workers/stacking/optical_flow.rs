@@ -0,0 +1,120 @@
+use std::sync::mpsc;
+use std::time::Instant;
+
+use jupiter_core::compute::{create_backend, DevicePreference};
+use jupiter_core::frame::ColorMode;
+use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::AlignmentConfig;
+use jupiter_core::pipeline::{PipelineOutput, PipelineStage};
+use jupiter_core::stack::optical_flow::{
+    optical_flow_stack, optical_flow_stack_color, OpticalFlowConfig,
+};
+
+use crate::messages::WorkerResult;
+
+use super::super::{send, send_error, send_log, PipelineCache};
+
+pub(crate) fn handle_optical_flow(
+    flow_config: &OpticalFlowConfig,
+    alignment: &AlignmentConfig,
+    device: &DevicePreference,
+    cache: &mut PipelineCache,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) {
+    let file_path = match &cache.file_path {
+        Some(p) => p.clone(),
+        None => {
+            send_error(tx, ctx, "No file loaded. Run Score Frames first.");
+            return;
+        }
+    };
+    send_log(tx, ctx, "Optical flow stacking...");
+    send(
+        tx,
+        ctx,
+        WorkerResult::Progress {
+            stage: PipelineStage::Stacking,
+            items_done: None,
+            items_total: None,
+        },
+    );
+    let start = Instant::now();
+    let reader = match SerReader::open(&file_path) {
+        Ok(r) => r,
+        Err(e) => {
+            send_error(tx, ctx, format!("Failed to open file: {e}"));
+            return;
+        }
+    };
+
+    let backend = create_backend(device);
+
+    if cache.is_color {
+        let color_mode = match reader.header.color_mode() {
+            ColorMode::Mono => {
+                send_error(tx, ctx, "Expected color source but got mono");
+                return;
+            }
+            mode => mode,
+        };
+        let debayer_method = cache.debayer_method.unwrap_or_default();
+        match optical_flow_stack_color(
+            &reader,
+            flow_config,
+            alignment,
+            backend.as_ref(),
+            &color_mode,
+            &debayer_method,
+            |_| {},
+        ) {
+            Ok(result) => {
+                let elapsed = start.elapsed();
+                let output = PipelineOutput::Color(result);
+                cache.set_stacked(output.clone());
+                send_log(
+                    tx,
+                    ctx,
+                    format!(
+                        "Optical flow color stacking complete in {:.1}s",
+                        elapsed.as_secs_f32()
+                    ),
+                );
+                send(
+                    tx,
+                    ctx,
+                    WorkerResult::StackComplete {
+                        result: output,
+                        elapsed,
+                    },
+                );
+            }
+            Err(e) => send_error(tx, ctx, format!("Optical flow color stacking failed: {e}")),
+        }
+    } else {
+        match optical_flow_stack(&reader, flow_config, alignment, backend.as_ref(), |_| {}) {
+            Ok(result) => {
+                let elapsed = start.elapsed();
+                let output = PipelineOutput::Mono(result);
+                cache.set_stacked(output.clone());
+                send_log(
+                    tx,
+                    ctx,
+                    format!(
+                        "Optical flow stacking complete in {:.1}s",
+                        elapsed.as_secs_f32()
+                    ),
+                );
+                send(
+                    tx,
+                    ctx,
+                    WorkerResult::StackComplete {
+                        result: output,
+                        elapsed,
+                    },
+                );
+            }
+            Err(e) => send_error(tx, ctx, format!("Optical flow stacking failed: {e}")),
+        }
+    }
+}
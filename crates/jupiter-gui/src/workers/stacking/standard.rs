@@ -5,9 +5,9 @@ use jupiter_core::align::shift_frame;
 use jupiter_core::frame::{ColorFrame, Frame};
 use jupiter_core::pipeline::config::StackMethod;
 use jupiter_core::pipeline::{PipelineOutput, PipelineStage};
-use jupiter_core::stack::mean::mean_stack_with_progress;
-use jupiter_core::stack::median::median_stack;
-use jupiter_core::stack::sigma_clip::sigma_clip_stack;
+use jupiter_core::stack::mean::{mean_stack_with_progress, weighted_mean_stack_with_progress};
+use jupiter_core::stack::median::{median_stack, weighted_median_stack};
+use jupiter_core::stack::rejection::rejection_stack;
 
 use crate::messages::WorkerResult;
 
@@ -15,6 +15,7 @@ use super::super::{make_progress_callback, send, send_error, send_log, PipelineC
 
 pub(crate) fn handle_standard(
     method: &StackMethod,
+    weights: Option<&[f64]>,
     cache: &mut PipelineCache,
     tx: &mpsc::Sender<WorkerResult>,
     ctx: &egui::Context,
@@ -67,23 +68,9 @@ pub(crate) fn handle_standard(
         let color_stack_progress =
             make_progress_callback(tx, ctx, PipelineStage::Stacking, color_frame_count);
 
-        let stack_fn =
-            |frames: &[Frame], on_progress: &dyn Fn(usize)| -> jupiter_core::error::Result<Frame> {
-                match method {
-                    StackMethod::Mean => mean_stack_with_progress(frames, on_progress),
-                    StackMethod::Median => {
-                        let r = median_stack(frames);
-                        on_progress(frames.len());
-                        r
-                    }
-                    StackMethod::SigmaClip(params) => {
-                        let r = sigma_clip_stack(frames, params);
-                        on_progress(frames.len());
-                        r
-                    }
-                    _ => unreachable!(),
-                }
-            };
+        let stack_fn = |frames: &[Frame], on_progress: &dyn Fn(usize)| {
+            stack_frames(frames, method, weights, on_progress)
+        };
 
         let (sr, (sg, sb)) = rayon::join(
             || stack_fn(&red_frames, &color_stack_progress),
@@ -142,20 +129,7 @@ pub(crate) fn handle_standard(
         let stacking_progress =
             make_progress_callback(tx, ctx, PipelineStage::Stacking, frame_count);
 
-        let result = match method {
-            StackMethod::Mean => mean_stack_with_progress(&aligned, stacking_progress),
-            StackMethod::Median => {
-                let r = median_stack(&aligned);
-                stacking_progress(frame_count);
-                r
-            }
-            StackMethod::SigmaClip(params) => {
-                let r = sigma_clip_stack(&aligned, params);
-                stacking_progress(frame_count);
-                r
-            }
-            _ => unreachable!(),
-        };
+        let result = stack_frames(&aligned, method, weights, &stacking_progress);
 
         match result {
             Ok(result) => {
@@ -180,3 +154,27 @@ pub(crate) fn handle_standard(
         }
     }
 }
+
+/// Stack aligned frames with a standard or rejection method, weighting by
+/// quality score when `weights` is given.
+fn stack_frames(
+    frames: &[Frame],
+    method: &StackMethod,
+    weights: Option<&[f64]>,
+    on_progress: &dyn Fn(usize),
+) -> jupiter_core::error::Result<Frame> {
+    let result = match (method, weights) {
+        (StackMethod::Mean, None) => return mean_stack_with_progress(frames, on_progress),
+        (StackMethod::Mean, Some(weights)) => {
+            return weighted_mean_stack_with_progress(frames, weights, on_progress)
+        }
+        (StackMethod::Median, None) => median_stack(frames),
+        (StackMethod::Median, Some(weights)) => weighted_median_stack(frames, weights),
+        _ => {
+            let rejection = method.rejection().expect("standard stacking method");
+            rejection_stack(frames, &rejection, weights).map(|(frame, _)| frame)
+        }
+    };
+    on_progress(frames.len());
+    result
+}
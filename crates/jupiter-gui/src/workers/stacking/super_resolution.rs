@@ -0,0 +1,139 @@
+use std::sync::mpsc;
+use std::time::Instant;
+
+use jupiter_core::compute::{create_backend, DevicePreference};
+use jupiter_core::frame::{ColorFrame, Frame};
+use jupiter_core::pipeline::{PipelineOutput, PipelineStage};
+use jupiter_core::stack::super_resolution::{super_resolve_with_progress, SuperResolutionConfig};
+
+use crate::messages::WorkerResult;
+
+use super::super::{make_progress_callback, send, send_error, send_log, PipelineCache};
+
+pub(crate) fn handle_super_resolution(
+    sr_config: &SuperResolutionConfig,
+    device: &DevicePreference,
+    cache: &mut PipelineCache,
+    tx: &mpsc::Sender<WorkerResult>,
+    ctx: &egui::Context,
+) {
+    let selected_frames = match &cache.selected_frames {
+        Some(f) => f,
+        None => {
+            send_error(tx, ctx, "Frames not aligned. Run Align Frames first.");
+            return;
+        }
+    };
+    let offsets = match &cache.alignment_offsets {
+        Some(o) => o,
+        None => {
+            send_error(tx, ctx, "No alignment offsets. Run Align Frames first.");
+            return;
+        }
+    };
+
+    let start = Instant::now();
+    let backend = create_backend(device);
+    let iterations = sr_config.iterations;
+
+    send_log(
+        tx,
+        ctx,
+        format!("Super-resolution stacking ({} backend)...", backend.name()),
+    );
+    send(
+        tx,
+        ctx,
+        WorkerResult::Progress {
+            stage: PipelineStage::Stacking,
+            items_done: Some(0),
+            items_total: Some(iterations),
+        },
+    );
+
+    let scores = cache.selected_quality_scores.as_deref();
+    let sr_progress = make_progress_callback(tx, ctx, PipelineStage::Stacking, iterations);
+
+    if let Some(ref color_frames) = cache.selected_color_frames {
+        let red_frames: Vec<Frame> = color_frames.iter().map(|cf| cf.red.clone()).collect();
+        let green_frames: Vec<Frame> = color_frames.iter().map(|cf| cf.green.clone()).collect();
+        let blue_frames: Vec<Frame> = color_frames.iter().map(|cf| cf.blue.clone()).collect();
+
+        let resolve = |frames: &[Frame], progress: &(dyn Fn(usize) + Sync)| {
+            super_resolve_with_progress(
+                frames,
+                offsets,
+                sr_config,
+                scores,
+                backend.as_ref(),
+                progress,
+            )
+        };
+        let (sr, (sg, sb)) = rayon::join(
+            || resolve(&red_frames, &sr_progress),
+            || {
+                rayon::join(
+                    || resolve(&green_frames, &|_| {}),
+                    || resolve(&blue_frames, &|_| {}),
+                )
+            },
+        );
+        match (sr, sg, sb) {
+            (Ok(r), Ok(g), Ok(b)) => {
+                let elapsed = start.elapsed();
+                let output = PipelineOutput::Color(ColorFrame {
+                    red: r,
+                    green: g,
+                    blue: b,
+                });
+                cache.set_stacked(output.clone());
+                send_log(
+                    tx,
+                    ctx,
+                    format!(
+                        "Color super-resolution complete in {:.1}s",
+                        elapsed.as_secs_f32()
+                    ),
+                );
+                send(
+                    tx,
+                    ctx,
+                    WorkerResult::StackComplete {
+                        result: output,
+                        elapsed,
+                    },
+                );
+            }
+            _ => send_error(tx, ctx, "Color super-resolution stacking failed"),
+        }
+    } else {
+        match super_resolve_with_progress(
+            selected_frames,
+            offsets,
+            sr_config,
+            scores,
+            backend.as_ref(),
+            sr_progress,
+        ) {
+            Ok(result) => {
+                let elapsed = start.elapsed();
+                let output = PipelineOutput::Mono(result);
+                cache.set_stacked(output.clone());
+                send_log(
+                    tx,
+                    ctx,
+                    format!("Super-resolution complete in {:.1}s", elapsed.as_secs_f32()),
+                );
+                send(
+                    tx,
+                    ctx,
+                    WorkerResult::StackComplete {
+                        result: output,
+                        elapsed,
+                    },
+                );
+            }
+            Err(e) => send_error(tx, ctx, format!("Super-resolution stacking failed: {e}")),
+        }
+    }
+}
@@ -1,11 +1,14 @@
 use std::sync::mpsc;
 use std::time::Instant;
 
+use jupiter_core::compute::{create_backend, DevicePreference};
 use jupiter_core::frame::ColorMode;
 use jupiter_core::io::ser::SerReader;
+use jupiter_core::pipeline::config::AlignmentConfig;
 use jupiter_core::pipeline::{PipelineOutput, PipelineStage};
 use jupiter_core::stack::surface_warp::{
-    surface_warp_stack, surface_warp_stack_color, SurfaceWarpConfig,
+    surface_warp_stack_color_with_diagnostics, surface_warp_stack_with_diagnostics,
+    SurfaceWarpConfig,
 };
 
 use crate::messages::WorkerResult;
@@ -14,6 +17,8 @@ use super::super::{send, send_error, send_log, PipelineCache};
 
 pub(crate) fn handle_surface_warp(
     sw_config: &SurfaceWarpConfig,
+    alignment: &AlignmentConfig,
+    device: &DevicePreference,
     cache: &mut PipelineCache,
     tx: &mpsc::Sender<WorkerResult>,
     ctx: &egui::Context,
@@ -44,6 +49,8 @@ pub(crate) fn handle_surface_warp(
         }
     };
 
+    let backend = create_backend(device);
+
     if cache.is_color {
         let color_mode = match reader.header.color_mode() {
             ColorMode::Mono => {
@@ -53,11 +60,20 @@ pub(crate) fn handle_surface_warp(
             mode => mode,
         };
         let debayer_method = cache.debayer_method.unwrap_or_default();
-        match surface_warp_stack_color(&reader, sw_config, &color_mode, &debayer_method, |_| {}) {
-            Ok(result) => {
+        match surface_warp_stack_color_with_diagnostics(
+            &reader,
+            sw_config,
+            alignment,
+            backend.as_ref(),
+            &color_mode,
+            &debayer_method,
+            |_| {},
+        ) {
+            Ok((result, diagnostics)) => {
                 let elapsed = start.elapsed();
                 let output = PipelineOutput::Color(result);
                 cache.set_stacked(output.clone());
+                cache.ap_diagnostics = Some(diagnostics);
                 send_log(
                     tx,
                     ctx,
@@ -78,11 +94,18 @@ pub(crate) fn handle_surface_warp(
             Err(e) => send_error(tx, ctx, format!("Surface warp color stacking failed: {e}")),
         }
     } else {
-        match surface_warp_stack(&reader, sw_config, |_| {}) {
-            Ok(result) => {
+        match surface_warp_stack_with_diagnostics(
+            &reader,
+            sw_config,
+            alignment,
+            backend.as_ref(),
+            |_| {},
+        ) {
+            Ok((result, diagnostics)) => {
                 let elapsed = start.elapsed();
                 let output = PipelineOutput::Mono(result);
                 cache.set_stacked(output.clone());
+                cache.ap_diagnostics = Some(diagnostics);
                 send_log(
                     tx,
                     ctx,